@@ -0,0 +1,21 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use lwk_wollet::elements::AddressParams;
+use lwk_wollet::WolletDescriptor;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(s) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(desc) = WolletDescriptor::from_str(s) else {
+        return;
+    };
+    // accepted descriptors are printed in a canonical form
+    let canonical = desc.to_string();
+    let roundtrip = WolletDescriptor::from_str(&canonical).expect("canonical descriptor");
+    assert_eq!(roundtrip.to_string(), canonical);
+    let _ = desc.address(0, &AddressParams::LIQUID);
+});
@@ -0,0 +1,30 @@
+#![no_main]
+
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use libfuzzer_sys::fuzz_target;
+use lwk_wollet::elements::pset::PartiallySignedTransaction;
+use lwk_wollet::{ElementsNetwork, Wollet, WolletDescriptor};
+
+const DESCRIPTOR: &str = include_str!("../../lwk_common/test_data/pset_details/descriptor");
+
+fn wollet() -> &'static Wollet {
+    static WOLLET: OnceLock<Wollet> = OnceLock::new();
+    WOLLET.get_or_init(|| {
+        let desc = WolletDescriptor::from_str(DESCRIPTOR.trim()).expect("static");
+        Wollet::without_persist(ElementsNetwork::LiquidTestnet, desc).expect("static")
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    // PSETs are received base64 encoded
+    let Ok(s) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(pset) = PartiallySignedTransaction::from_str(s.trim()) else {
+        return;
+    };
+    let _ = wollet().get_details(&pset);
+    let _ = wollet().signature_status(&pset);
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use lwk_wollet::elements::encode::deserialize;
+use lwk_wollet::elements::Transaction;
+use lwk_wollet::{unblind_transaction, WolletDescriptor};
+
+const DESCRIPTOR: &str = include_str!("../../lwk_common/test_data/pset_details/descriptor");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(tx) = deserialize::<Transaction>(data) else {
+        return;
+    };
+    let desc = WolletDescriptor::from_str(DESCRIPTOR.trim()).expect("static");
+    let _ = unblind_transaction(&tx, &desc.as_ref().key);
+});
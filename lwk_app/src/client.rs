@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 
 use lwk_jade::TIMEOUT;
@@ -11,7 +12,7 @@ use serde_json::Value;
 use crate::error::Error;
 use crate::method::Method;
 use crate::reqwest_transport::ReqwestHttpTransport;
-use crate::{request, response};
+use crate::{request, response, AuthToken};
 
 pub struct Client {
     client: jsonrpc::Client,
@@ -19,8 +20,14 @@ pub struct Client {
 
 impl Client {
     pub fn new(addr: SocketAddr) -> Result<Self, Error> {
+        Self::with_auth_token(addr, None)
+    }
+
+    /// Like [`Client::new()`], sending `auth_token` to servers requiring one of their
+    /// `auth_tokens`
+    pub fn with_auth_token(addr: SocketAddr, auth_token: Option<AuthToken>) -> Result<Self, Error> {
         let url = format!("http://{addr}");
-        let transport = ReqwestHttpTransport::new(url, TIMEOUT);
+        let transport = ReqwestHttpTransport::new(url, TIMEOUT, auth_token);
         let client = jsonrpc::Client::with_transport(transport);
         Ok(Self { client })
     }
@@ -37,16 +44,36 @@ impl Client {
         let params = req.map(|req| to_raw_value(&req)).transpose()?;
         let method = method.to_string();
         let request = self.client.build_request(&method, params.as_deref());
-        log::trace!("---> {}", serde_json::to_string(&request)?);
+        tracing::trace!("---> {}", serde_json::to_string(&request)?);
         let response = self.client.send_request(request)?;
-        log::trace!("<--- {}", serde_json::to_string(&response)?);
-        match response.result.as_ref() {
-            Some(result) => Ok(serde_json::from_str(result.get())?),
-            None => match response.error {
-                Some(rpc_err) => Err(Error::RpcError(rpc_err)),
-                None => Err(Error::NeitherResultNorErrorSet),
-            },
-        }
+        tracing::trace!("<--- {}", serde_json::to_string(&response)?);
+        response_result(response)
+    }
+
+    /// Send many requests in a single round trip
+    ///
+    /// Returns the outcome of each request, in the same order of `requests`.
+    pub fn batch(
+        &self,
+        requests: Vec<(Method, Option<Value>)>,
+    ) -> Result<Vec<Result<Value, Error>>, Error> {
+        let methods: Vec<_> = requests.iter().map(|(m, _)| m.to_string()).collect();
+        let params = requests
+            .iter()
+            .map(|(_, p)| p.as_ref().map(to_raw_value).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        let requests: Vec<_> = methods
+            .iter()
+            .zip(params.iter())
+            .map(|(m, p)| self.client.build_request(m, p.as_deref()))
+            .collect();
+        tracing::trace!("---> {}", serde_json::to_string(&requests)?);
+        let responses = self.client.send_batch(&requests)?;
+        tracing::trace!("<--- {}", serde_json::to_string(&responses)?);
+        Ok(responses
+            .into_iter()
+            .map(|r| r.map_or(Err(Error::NeitherResultNorErrorSet), response_result))
+            .collect())
     }
 
     pub fn version(&self) -> Result<response::Version, Error> {
@@ -95,7 +122,11 @@ impl Client {
     }
 
     pub fn wallet_load(&self, descriptor: String, name: String) -> Result<response::Wallet, Error> {
-        let req = request::WalletLoad { descriptor, name };
+        let req = request::WalletLoad {
+            descriptor,
+            name,
+            birthday: None,
+        };
         self.make_request(Method::WalletLoad, Some(req))
     }
 
@@ -104,6 +135,15 @@ impl Client {
         self.make_request(Method::WalletUnload, Some(req))
     }
 
+    pub fn wallet_forget(
+        &self,
+        name: String,
+        delete_cache: bool,
+    ) -> Result<response::WalletForget, Error> {
+        let req = request::WalletForget { name, delete_cache };
+        self.make_request(Method::WalletForget, Some(req))
+    }
+
     pub fn signer_unload(&self, name: String) -> Result<response::SignerUnload, Error> {
         let req = request::SignerUnload { name };
         self.make_request(Method::SignerUnload, Some(req))
@@ -140,6 +180,56 @@ impl Client {
         self.make_request(Method::WalletAddress, Some(req))
     }
 
+    pub fn wallet_addresses(
+        &self,
+        name: String,
+        start: u32,
+        count: u32,
+        chain: String,
+    ) -> Result<response::WalletAddresses, Error> {
+        let req = request::WalletAddresses {
+            name,
+            start,
+            count,
+            chain,
+        };
+        self.make_request(Method::WalletAddresses, Some(req))
+    }
+
+    pub fn wallet_address_info(
+        &self,
+        name: String,
+        address: String,
+    ) -> Result<response::WalletAddressInfo, Error> {
+        let req = request::WalletAddressInfo { name, address };
+        self.make_request(Method::WalletAddressInfo, Some(req))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn wallet_verify_payment(
+        &self,
+        name: String,
+        txid: String,
+        vout: u32,
+        address: String,
+        asset: String,
+        satoshi: u64,
+        min_confirmations: Option<u32>,
+        signer: Option<String>,
+    ) -> Result<response::WalletVerifyPayment, Error> {
+        let req = request::WalletVerifyPayment {
+            name,
+            txid,
+            vout,
+            address,
+            asset,
+            satoshi,
+            min_confirmations,
+            signer,
+        };
+        self.make_request(Method::WalletVerifyPayment, Some(req))
+    }
+
     pub fn wallet_send_many(
         &self,
         name: String,
@@ -168,6 +258,20 @@ impl Client {
         self.make_request(Method::WalletDrain, Some(req))
     }
 
+    pub fn wallet_bump_fee(
+        &self,
+        name: String,
+        txid: String,
+        fee_rate: f32,
+    ) -> Result<response::Pset, Error> {
+        let req = request::WalletBumpFee {
+            name,
+            txid,
+            fee_rate,
+        };
+        self.make_request(Method::WalletBumpFee, Some(req))
+    }
+
     pub fn signer_singlesig_descriptor(
         &self,
         name: String,
@@ -216,8 +320,29 @@ impl Client {
         self.make_request(Method::SignerRegisterMultisig, Some(req))
     }
 
-    pub fn signer_sign(&self, name: String, pset: String) -> Result<response::Pset, Error> {
-        let req = request::SignerSign { name, pset };
+    pub fn signer_enroll_totp(
+        &self,
+        name: String,
+        threshold: u64,
+        secret: Option<String>,
+        totp: Option<String>,
+    ) -> Result<response::SignerTotp, Error> {
+        let req = request::SignerEnrollTotp {
+            name,
+            threshold,
+            secret,
+            totp,
+        };
+        self.make_request(Method::SignerEnrollTotp, Some(req))
+    }
+
+    pub fn signer_sign(
+        &self,
+        name: String,
+        pset: String,
+        totp: Option<String>,
+    ) -> Result<response::Pset, Error> {
+        let req = request::SignerSign { name, pset, totp };
         self.make_request(Method::SignerSign, Some(req))
     }
 
@@ -235,11 +360,37 @@ impl Client {
         self.make_request(Method::WalletBroadcast, Some(req))
     }
 
+    pub fn wallet_outbox(&self, name: String) -> Result<response::WalletOutbox, Error> {
+        let req = request::WalletOutbox { name };
+        self.make_request(Method::WalletOutbox, Some(req))
+    }
+
+    pub fn wallet_outbox_insert(
+        &self,
+        name: String,
+        tx: String,
+    ) -> Result<response::WalletBroadcast, Error> {
+        let req = request::WalletOutboxInsert { name, tx };
+        self.make_request(Method::WalletOutboxInsert, Some(req))
+    }
+
     pub fn wallet_details(&self, name: String) -> Result<response::WalletDetails, Error> {
         let req = request::WalletDetails { name };
         self.make_request(Method::WalletDetails, Some(req))
     }
 
+    pub fn wallet_retry_unblind(
+        &self,
+        name: String,
+        blinding_keys: Vec<String>,
+    ) -> Result<response::WalletRetryUnblind, Error> {
+        let req = request::WalletRetryUnblind {
+            name,
+            blinding_keys,
+        };
+        self.make_request(Method::WalletRetryUnblind, Some(req))
+    }
+
     pub fn signer_details(&self, name: String) -> Result<response::SignerDetails, Error> {
         let req = request::SignerDetails { name };
         self.make_request(Method::SignerDetails, Some(req))
@@ -282,6 +433,15 @@ impl Client {
         self.make_request(Method::WalletTxs, Some(req))
     }
 
+    pub fn wallet_export_history(
+        &self,
+        name: String,
+        format: String,
+    ) -> Result<response::WalletExportHistory, Error> {
+        let req = request::WalletExportHistory { name, format };
+        self.make_request(Method::WalletExportHistory, Some(req))
+    }
+
     pub fn wallet_tx(
         &self,
         name: String,
@@ -306,6 +466,32 @@ impl Client {
         self.make_request(Method::WalletSetTxMemo, Some(req))
     }
 
+    pub fn wallet_set_policy(
+        &self,
+        name: String,
+        daily_limit: Option<u64>,
+        asset_daily_limits: BTreeMap<String, u64>,
+        allowlist: Vec<String>,
+        required_cosigners: Vec<String>,
+    ) -> Result<response::WalletPolicyStatus, Error> {
+        let req = request::WalletSetPolicy {
+            name,
+            daily_limit,
+            asset_daily_limits,
+            allowlist,
+            required_cosigners,
+        };
+        self.make_request(Method::WalletSetPolicy, Some(req))
+    }
+
+    pub fn wallet_policy_status(
+        &self,
+        name: String,
+    ) -> Result<response::WalletPolicyStatus, Error> {
+        let req = request::WalletPolicyStatus { name };
+        self.make_request(Method::WalletPolicyStatus, Some(req))
+    }
+
     pub fn wallet_set_addr_memo(
         &self,
         name: String,
@@ -320,6 +506,16 @@ impl Client {
         self.make_request(Method::WalletSetAddrMemo, Some(req))
     }
 
+    pub fn wallet_backup(&self, name: String) -> Result<response::WalletBackup, Error> {
+        let req = request::WalletBackup { name };
+        self.make_request(Method::WalletBackup, Some(req))
+    }
+
+    pub fn wallet_restore(&self, name: String, backup: String) -> Result<response::Wallet, Error> {
+        let req = request::WalletRestore { name, backup };
+        self.make_request(Method::WalletRestore, Some(req))
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn wallet_issue(
         &self,
@@ -450,6 +646,95 @@ impl Client {
         self.make_request(Method::Amp2Cosign, Some(req))
     }
 
+    pub fn account_new(
+        &self,
+        signer: String,
+        account_kind: String,
+        descriptor_blinding_key: String,
+        name: Option<String>,
+        threshold: Option<u32>,
+        cosigners: Vec<String>,
+    ) -> Result<response::Account, Error> {
+        let req = request::AccountNew {
+            signer,
+            account_kind,
+            descriptor_blinding_key,
+            name,
+            threshold,
+            cosigners,
+        };
+        self.make_request(Method::AccountNew, Some(req))
+    }
+
+    pub fn account_list(
+        &self,
+        signer: String,
+        with_tickers: bool,
+    ) -> Result<response::AccountList, Error> {
+        let req = request::AccountList {
+            signer,
+            with_tickers,
+        };
+        self.make_request(Method::AccountList, Some(req))
+    }
+
+    pub fn tx_decode(
+        &self,
+        tx: String,
+        name: Option<String>,
+    ) -> Result<response::DecodedTx, Error> {
+        let req = request::TxDecode { tx, name };
+        self.make_request(Method::TxDecode, Some(req))
+    }
+
+    pub fn tx_broadcast(
+        &self,
+        tx: Option<String>,
+        pset: Option<String>,
+        dry_run: bool,
+    ) -> Result<response::TxBroadcast, Error> {
+        let req = request::TxBroadcast { tx, pset, dry_run };
+        self.make_request(Method::TxBroadcast, Some(req))
+    }
+
+    pub fn pset_decode(
+        &self,
+        pset: String,
+        name: Option<String>,
+    ) -> Result<response::DecodedTx, Error> {
+        let req = request::PsetDecode { pset, name };
+        self.make_request(Method::PsetDecode, Some(req))
+    }
+
+    pub fn pset_session_save(
+        &self,
+        session: Option<String>,
+        pset: String,
+        participants: Vec<String>,
+        threshold: Option<u32>,
+    ) -> Result<response::PsetSession, Error> {
+        let req = request::PsetSessionSave {
+            session,
+            pset,
+            participants,
+            threshold,
+        };
+        self.make_request(Method::PsetSessionSave, Some(req))
+    }
+
+    pub fn pset_session_load(&self, session: String) -> Result<response::PsetSession, Error> {
+        let req = request::PsetSessionLoad { session };
+        self.make_request(Method::PsetSessionLoad, Some(req))
+    }
+
+    pub fn pset_session_merge(
+        &self,
+        sessions: Vec<String>,
+    ) -> Result<response::PsetSession, Error> {
+        let req = request::PsetSessionMerge { sessions };
+        self.make_request(Method::PsetSessionMerge, Some(req))
+    }
+
     pub fn schema(&self, arg: Method, direction: request::Direction) -> Result<Value, Error> {
         let req = request::Schema {
             method: arg.to_string(),
@@ -458,19 +743,23 @@ impl Client {
         self.make_request(Method::Schema, Some(req))
     }
 
-    pub fn signer_jade_id(&self, emulator: Option<SocketAddr>) -> Result<Value, Error> {
+    pub fn signer_jade_id(&self, emulator: Option<SocketAddr>) -> Result<response::JadeId, Error> {
         let req = request::SignerJadeId { emulator };
         self.make_request(Method::SignerJadeId, Some(req))
     }
 
-    pub fn scan(&self) -> Result<Value, Error> {
+    pub fn scan(&self) -> Result<response::Empty, Error> {
         self.make_request(Method::Scan, None::<Box<RawValue>>)
     }
 
-    pub fn stop(&self) -> Result<Value, Error> {
+    pub fn server_status(&self) -> Result<response::ServerStatus, Error> {
+        self.make_request(Method::ServerStatus, None::<Box<RawValue>>)
+    }
+
+    pub fn stop(&self) -> Result<(), Error> {
         // TODO discriminate only stop error
         let _: Result<Value, Error> = self.make_request(Method::Stop, None::<Box<RawValue>>);
-        Ok(Value::Null)
+        Ok(())
     }
 }
 
@@ -481,3 +770,13 @@ fn unvalidate_addressee(a: lwk_wollet::UnvalidatedRecipient) -> request::Unvalid
         asset: a.asset,
     }
 }
+
+fn response_result<Res: DeserializeOwned>(response: jsonrpc::Response) -> Result<Res, Error> {
+    match response.result.as_ref() {
+        Some(result) => Ok(serde_json::from_str(result.get())?),
+        None => match response.error {
+            Some(rpc_err) => Err(Error::RpcError(rpc_err)),
+            None => Err(Error::NeitherResultNorErrorSet),
+        },
+    }
+}
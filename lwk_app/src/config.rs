@@ -2,15 +2,22 @@ use lwk_common::electrum_ssl::LIQUID_SOCKET;
 use lwk_common::electrum_ssl::LIQUID_TESTNET_SOCKET;
 use lwk_common::Network as JadeNetwork;
 use lwk_jade::TIMEOUT;
+use lwk_wollet::clients::CancellationToken;
+use lwk_wollet::elements::hex::ToHex;
 use lwk_wollet::elements::AssetId;
 use lwk_wollet::ElementsNetwork;
+use lwk_wollet::RetryPolicy;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 
-use crate::{consts, Error};
+use crate::{consts, Error, RateLimit, Webhook};
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -30,6 +37,344 @@ pub struct Config {
     pub registry_url: String,
     pub timeout: Duration,
     pub scanning_interval: Duration,
+
+    /// Use ELIP200 discounted fees for Confidential Transactions
+    ///
+    /// Nodes running Elements 23.2.2 or later relay transactions paying the discounted fee,
+    /// disable it if the backend or its peers still use the legacy relay policy.
+    pub ct_discount: bool,
+
+    /// Maximum rate of requests accepted from each client, unlimited if `None`
+    pub rate_limit: Option<RateLimit>,
+
+    /// Time after which clients get an error instead of the response of a method, by method name
+    ///
+    /// The method keeps running in background, so that for instance a scan is not left halfway.
+    pub method_timeouts: HashMap<String, Duration>,
+
+    /// Limits of the requests accepted by the server
+    pub limits: Limits,
+
+    /// Bearer tokens required to the clients, any client is accepted if empty
+    pub auth_tokens: Vec<AuthToken>,
+
+    /// Fee rate in sat/kvB of the transactions created without an explicit fee rate
+    pub default_fee_rate: Option<f32>,
+
+    /// HTTP endpoints notified of deposits and withdrawals of the wallets
+    pub webhooks: Vec<Webhook>,
+
+    /// Applications sharing the server, each with its own wallets, signers and data directory
+    pub tenants: Vec<Tenant>,
+
+    /// Elements node checking the transactions of dry run broadcasts against its mempool
+    pub elements_rpc: Option<ElementsRpc>,
+}
+
+/// The RPC interface of an Elements node
+///
+/// The password is not shown by [`fmt::Debug`], so that it does not end up in the logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ElementsRpc {
+    pub url: String,
+    pub user: String,
+    pub password: String,
+}
+
+impl fmt::Debug for ElementsRpc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElementsRpc")
+            .field("url", &self.url)
+            .field("user", &self.user)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// An application sharing the server with others
+///
+/// Clients authenticated with one of its `auth_tokens` only see the wallets, signers and assets of
+/// the tenant, which are persisted under `tenants/<id>` in the data directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tenant {
+    /// Identifier of the tenant, made of ASCII letters, digits, `-` and `_`
+    pub id: String,
+
+    /// Bearer tokens of the clients of this tenant
+    pub auth_tokens: Vec<AuthToken>,
+
+    /// HTTP endpoints notified of deposits and withdrawals of the wallets of this tenant
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+}
+
+/// A bearer token authenticating the clients of the server
+///
+/// Not shown by [`fmt::Debug`], so that it does not end up in the logs.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AuthToken(String);
+
+impl AuthToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for AuthToken {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(Error::Generic("Invalid auth token".into()));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AuthToken(***)")
+    }
+}
+
+/// The content of the config file, `~/.lwk/config.toml` by default
+///
+/// Every value is optional, the ones given on the command line or with environment variables take
+/// precedence.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// One of `mainnet`, `testnet` or `regtest`
+    pub network: Option<String>,
+
+    /// The address where the RPC server is listening or the client is connecting to
+    pub addr: Option<SocketAddr>,
+
+    pub datadir: Option<PathBuf>,
+    pub electrum_url: Option<String>,
+    pub esplora_api_url: Option<String>,
+    pub registry_url: Option<String>,
+
+    /// Timeout for RPC and HWW requests (seconds)
+    pub timeout: Option<u64>,
+
+    /// Interval between blockchain scans (seconds)
+    pub scanning_interval: Option<u64>,
+
+    /// Bearer tokens required by the server, clients send the first one
+    pub auth_tokens: Option<Vec<AuthToken>>,
+
+    /// Fee rate in sat/kvB of the transactions created without an explicit fee rate
+    pub fee_rate: Option<f32>,
+
+    /// HTTP endpoints notified of deposits and withdrawals of the wallets
+    pub webhooks: Option<Vec<Webhook>>,
+
+    /// Applications sharing the server, each with its own wallets, signers and data directory
+    pub tenants: Option<Vec<Tenant>>,
+
+    /// Elements node checking the transactions of dry run broadcasts against its mempool
+    pub elements_rpc_url: Option<String>,
+    pub elements_rpc_user: Option<String>,
+    pub elements_rpc_password: Option<String>,
+}
+
+impl ConfigFile {
+    /// The default location of the config file, `config.toml` in [`Config::default_home()`]
+    pub fn default_path() -> Result<PathBuf, Error> {
+        Ok(Config::default_home()?.join("config.toml"))
+    }
+
+    /// Read the config file at `path`, an empty config if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::from_toml(&content)
+                .map_err(|e| Error::InvalidConfigFile(path.display().to_string(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn from_toml(content: &str) -> Result<Self, String> {
+        let file: Self = toml::from_str(content).map_err(|e| e.to_string())?;
+        let tenants = file.tenants.iter().flatten();
+        let tenant_webhooks = tenants.clone().flat_map(|t| t.webhooks.iter());
+        for webhook in file.webhooks.iter().flatten().chain(tenant_webhooks) {
+            reqwest::Url::parse(&webhook.url)
+                .map_err(|e| format!("invalid webhook url '{}': {e}", webhook.url))?;
+            if webhook.confirmations == 0 {
+                return Err(format!(
+                    "webhook '{}' requires 0 confirmations",
+                    webhook.url
+                ));
+            }
+        }
+        if let Some(url) = file.elements_rpc_url.as_ref() {
+            reqwest::Url::parse(url)
+                .map_err(|e| format!("invalid elements_rpc_url '{url}': {e}"))?;
+        } else if file.elements_rpc_user.is_some() || file.elements_rpc_password.is_some() {
+            return Err("elements_rpc credentials require elements_rpc_url".into());
+        }
+        if file.tenants.is_some() && file.auth_tokens.is_none() {
+            return Err("tenants require auth_tokens for the other clients".into());
+        }
+        let mut ids = HashSet::new();
+        let mut tokens: HashSet<_> = file
+            .auth_tokens
+            .iter()
+            .flatten()
+            .map(AuthToken::as_str)
+            .collect();
+        for tenant in tenants {
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            if tenant.id.is_empty() || !tenant.id.chars().all(valid) {
+                return Err(format!("invalid tenant id '{}'", tenant.id));
+            }
+            if !ids.insert(tenant.id.as_str()) {
+                return Err(format!("duplicate tenant id '{}'", tenant.id));
+            }
+            if tenant.auth_tokens.is_empty() {
+                return Err(format!("tenant '{}' has no auth tokens", tenant.id));
+            }
+            for token in tenant.auth_tokens.iter() {
+                if !tokens.insert(token.as_str()) {
+                    return Err(format!("tenant '{}' reuses an auth token", tenant.id));
+                }
+            }
+        }
+        Ok(file)
+    }
+
+    /// A config file with every value of `config` commented out, except the network
+    ///
+    /// A random auth token is suggested, the server accepts any client until it is uncommented.
+    pub fn template(config: &Config) -> String {
+        let network = match config.network {
+            ElementsNetwork::Liquid => "mainnet",
+            ElementsNetwork::LiquidTestnet => "testnet",
+            ElementsNetwork::ElementsRegtest { .. } => "regtest",
+        };
+        let mut token = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut token);
+        let token = token.to_hex();
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let secret = secret.to_hex();
+        let mut tenant_token = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut tenant_token);
+        let tenant_token = tenant_token.to_hex();
+        format!(
+            r#"# Liquid Wallet Kit configuration
+#
+# Values given on the command line or with environment variables (LWK_ELECTRUM_URL, LWK_FEE_RATE,
+# ...) take precedence over the ones in this file.
+
+# One of mainnet, testnet or regtest
+network = "{network}"
+
+# Address where the server listens and the client connects to
+# addr = "{addr}"
+
+# Location for logs, server state, and other LWK data
+# datadir = "{datadir}"
+
+# Blockchain backends, defaults according to the network
+# electrum_url = "{electrum_url}"
+# esplora_api_url = "{esplora_api_url}"
+# registry_url = "{registry_url}"
+
+# Elements node checking the transactions of dry run broadcasts with testmempoolaccept
+# elements_rpc_url = "http://127.0.0.1:7041"
+# elements_rpc_user = "rpcuser"
+# elements_rpc_password = "rpcpassword"
+
+# Timeout for RPC and HWW requests (seconds)
+# timeout = {timeout}
+
+# Interval between blockchain scans (seconds)
+# scanning_interval = {scanning_interval}
+
+# Bearer tokens required by the server, clients send the first one
+# auth_tokens = ["{token}"]
+
+# Fee rate of the transactions created without an explicit fee rate (sat/kvB)
+# fee_rate = 100.0
+
+# Endpoints notified with a POST of deposits, deposits reaching the given confirmations and
+# confirmed withdrawals. The X-Lwk-Signature header has the HMAC-SHA256 of the body with the secret.
+# [[webhooks]]
+# url = "https://example.com/lwk"
+# secret = "{secret}"
+# confirmations = 2
+
+# Applications sharing the server, clients authenticated with the tenant tokens only access the
+# wallets and signers of the tenant, persisted in their own directory. Tenants can have [[webhooks]].
+# [[tenants]]
+# id = "shop"
+# auth_tokens = ["{tenant_token}"]
+"#,
+            addr = config.addr,
+            datadir = config.datadir.display(),
+            electrum_url = config.electrum_url,
+            esplora_api_url = config.esplora_api_url,
+            registry_url = config.registry_url,
+            timeout = config.timeout.as_secs(),
+            scanning_interval = config.scanning_interval.as_secs(),
+        )
+    }
+}
+
+/// Limits of the requests accepted by the server, unlimited if `None`
+///
+/// They protect shared servers from the memory used by hostile or mistaken requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum size of PSETs and PSET sessions, in characters of their string encoding
+    pub max_pset_size: Option<usize>,
+
+    /// Maximum number of addressees of a send
+    pub max_addressees: Option<usize>,
+
+    /// Maximum number of loaded wallets
+    pub max_wallets: Option<usize>,
+}
+
+impl Limits {
+    /// Check the size of a PSET or of a PSET session
+    pub fn check_pset_size(&self, pset: &str) -> Result<(), Error> {
+        match self.max_pset_size {
+            Some(max) if pset.len() > max => Err(Error::PsetTooLarge(pset.len(), max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Maximum size of the body of a request, derived from `max_pset_size`
+    ///
+    /// Bodies are rejected before being parsed, so the memory they use is bounded too.
+    pub fn max_body_size(&self) -> Option<usize> {
+        self.max_pset_size.map(|max| {
+            max.saturating_mul(consts::MAX_BODY_PSETS)
+                .saturating_add(consts::MAX_BODY_OVERHEAD)
+        })
+    }
+
+    /// Check the number of addressees of a send
+    pub fn check_addressees(&self, addressees: usize) -> Result<(), Error> {
+        match self.max_addressees {
+            Some(max) if addressees > max => Err(Error::TooManyAddressees(addressees, max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that another wallet can be loaded when `loaded` wallets are already loaded
+    pub fn check_wallets(&self, loaded: usize) -> Result<(), Error> {
+        match self.max_wallets {
+            Some(max) if loaded >= max => Err(Error::TooManyWallets(max)),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Config {
@@ -46,6 +391,15 @@ impl Config {
             registry_url: "https://assets-testnet.blockstream.info/".into(),
             timeout: TIMEOUT,
             scanning_interval: consts::SCANNING_INTERVAL,
+            ct_discount: true,
+            rate_limit: None,
+            method_timeouts: HashMap::new(),
+            limits: Limits::default(),
+            auth_tokens: vec![],
+            default_fee_rate: None,
+            webhooks: vec![],
+            tenants: vec![],
+            elements_rpc: None,
         }
     }
 
@@ -62,6 +416,15 @@ impl Config {
             registry_url: "https://assets.blockstream.info/".into(),
             timeout: TIMEOUT,
             scanning_interval: consts::SCANNING_INTERVAL,
+            ct_discount: true,
+            rate_limit: None,
+            method_timeouts: HashMap::new(),
+            limits: Limits::default(),
+            auth_tokens: vec![],
+            default_fee_rate: None,
+            webhooks: vec![],
+            tenants: vec![],
+            elements_rpc: None,
         }
     }
 
@@ -83,9 +446,72 @@ impl Config {
             timeout: TIMEOUT,
             // Scan more frequently while testing
             scanning_interval: Duration::from_secs(1),
+            ct_discount: true,
+            rate_limit: None,
+            method_timeouts: HashMap::new(),
+            limits: Limits::default(),
+            auth_tokens: vec![],
+            default_fee_rate: None,
+            webhooks: vec![],
+            tenants: vec![],
+            elements_rpc: None,
         }
     }
 
+    /// Set the values given in `file`, the network and the address are left unchanged
+    pub fn apply_file(&mut self, file: &ConfigFile) {
+        if let Some(datadir) = file.datadir.as_ref() {
+            self.datadir = datadir.clone();
+        }
+        if let Some(url) = file.electrum_url.as_ref() {
+            self.electrum_url = url.clone();
+        }
+        if let Some(url) = file.esplora_api_url.as_ref() {
+            self.esplora_api_url = url.clone();
+        }
+        if let Some(url) = file.registry_url.as_ref() {
+            self.registry_url = url.clone();
+        }
+        if let Some(timeout) = file.timeout {
+            self.timeout = Duration::from_secs(timeout);
+        }
+        if let Some(scanning_interval) = file.scanning_interval {
+            self.scanning_interval = Duration::from_secs(scanning_interval);
+        }
+        if let Some(auth_tokens) = file.auth_tokens.as_ref() {
+            self.auth_tokens = auth_tokens.clone();
+        }
+        if let Some(fee_rate) = file.fee_rate {
+            self.default_fee_rate = Some(fee_rate);
+        }
+        if let Some(webhooks) = file.webhooks.as_ref() {
+            self.webhooks = webhooks.clone();
+        }
+        if let Some(tenants) = file.tenants.as_ref() {
+            self.tenants = tenants.clone();
+        }
+        if let Some(url) = file.elements_rpc_url.as_ref() {
+            self.elements_rpc = Some(ElementsRpc {
+                url: url.clone(),
+                user: file.elements_rpc_user.clone().unwrap_or_default(),
+                password: file.elements_rpc_password.clone().unwrap_or_default(),
+            });
+        }
+    }
+
+    /// The config of the server state of `tenant`
+    ///
+    /// The data directory is `tenants/<id>` in the data directory of the server, while the
+    /// blockchain backends and the limits are shared.
+    pub fn tenant(&self, tenant: &Tenant) -> Config {
+        let mut config = self.clone();
+        config.datadir = self.datadir.join("tenants").join(&tenant.id);
+        config.auth_tokens = tenant.auth_tokens.clone();
+        config.webhooks = tenant.webhooks.clone();
+        config.tenants = vec![];
+        config
+    }
+
     pub fn jade_network(&self) -> JadeNetwork {
         match self.network {
             ElementsNetwork::Liquid => JadeNetwork::Liquid,
@@ -116,11 +542,51 @@ impl Config {
         Ok(path)
     }
 
+    /// Returns the path of the file with the health of the Electrum servers under datadir
+    pub fn server_health_path(&self) -> Result<PathBuf, Error> {
+        let mut path = self.datadir()?;
+        path.push("server_health.json");
+        Ok(path)
+    }
+
+    /// Returns the path of the file with the amounts spent by the wallets with a policy
+    pub fn policy_counters_path(&self) -> Result<PathBuf, Error> {
+        let mut path = self.datadir()?;
+        path.push("policy_counters.json");
+        Ok(path)
+    }
+
+    /// Returns the path of the file with the L-BTC signed without TOTP codes by the signers
+    pub fn totp_counters_path(&self) -> Result<PathBuf, Error> {
+        let mut path = self.datadir()?;
+        path.push("totp_counters.json");
+        Ok(path)
+    }
+
+    /// Returns the path of the file with the webhook deliveries not done yet
+    pub fn webhooks_queue_path(&self) -> Result<PathBuf, Error> {
+        let mut path = self.datadir()?;
+        path.push("webhooks_queue.json");
+        Ok(path)
+    }
+
+    /// Returns the path of the log of the webhook deliveries failed too many times
+    pub fn webhooks_dead_letter_path(&self) -> Result<PathBuf, Error> {
+        let mut path = self.datadir()?;
+        path.push("webhooks_dead_letter.jsonl");
+        Ok(path)
+    }
+
     /// True if Liquid mainnet
     pub fn is_mainnet(&self) -> bool {
         matches!(self.network, ElementsNetwork::Liquid)
     }
 
+    /// The block explorer to link to, `None` if `explorer_url` is not set
+    pub fn explorer(&self) -> Option<lwk_wollet::Explorer> {
+        (!self.explorer_url.is_empty()).then(|| lwk_wollet::Explorer::custom(&self.explorer_url))
+    }
+
     fn electrum_url(&self) -> Result<lwk_wollet::ElectrumUrl, Error> {
         Ok(
             lwk_wollet::ElectrumUrl::new(&self.electrum_url, self.tls, self.validate_domain)
@@ -128,9 +594,52 @@ impl Config {
         )
     }
 
+    /// The client used while handling RPC calls, failing at the first network error since the
+    /// caller is waiting and the state may be locked
     pub fn electrum_client(&self) -> Result<lwk_wollet::ElectrumClient, Error> {
         // TODO cache it instead of recreating every time
-        Ok(lwk_wollet::ElectrumClient::new(&self.electrum_url()?)?)
+        let options = lwk_wollet::ElectrumOptions {
+            network: Some(self.network),
+            retry: RetryPolicy::no_retry(),
+            ..Default::default()
+        };
+        Ok(lwk_wollet::ElectrumClient::with_options(
+            &self.electrum_url()?,
+            options,
+        )?)
+    }
+
+    /// The client used by the background scan, once connected retrying requests failing because
+    /// of network errors until `cancel` is triggered
+    ///
+    /// Connecting is retried with the default policy, the scan loop connects again at every
+    /// interval and records the failures in the server health.
+    pub fn scan_electrum_client(
+        &self,
+        cancel: &CancellationToken,
+    ) -> Result<lwk_wollet::ElectrumClient, Error> {
+        let options = lwk_wollet::ElectrumOptions {
+            network: Some(self.network),
+            cancel: cancel.clone(),
+            retry: RetryPolicy::default(),
+            ..Default::default()
+        };
+        let mut client = lwk_wollet::ElectrumClient::with_options(&self.electrum_url()?, options)?;
+        client.set_retry_policy(RetryPolicy::until_cancelled());
+        Ok(client)
+    }
+
+    /// The client of the Elements node, `None` if not configured
+    pub fn elements_rpc_client(&self) -> Result<Option<lwk_wollet::ElementsRpcClient>, Error> {
+        let Some(rpc) = self.elements_rpc.as_ref() else {
+            return Ok(None);
+        };
+        Ok(Some(lwk_wollet::ElementsRpcClient::new_from_credentials(
+            self.network,
+            &rpc.url,
+            &rpc.user,
+            &rpc.password,
+        )?))
     }
 
     pub fn esplora_client(&self) -> lwk_wollet::asyncr::EsploraClient {
@@ -146,3 +655,97 @@ impl Config {
         )?)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config_file() {
+        let config = Config::default_mainnet(PathBuf::from("/tmp/lwk"));
+        let template = ConfigFile::template(&config);
+        let file = ConfigFile::from_toml(&template).unwrap();
+        assert_eq!(
+            file,
+            ConfigFile {
+                network: Some("mainnet".into()),
+                ..Default::default()
+            }
+        );
+
+        // every value in the template is valid once uncommented
+        let uncommented = template.replace("\n# ", "\n");
+        let uncommented: Vec<_> = uncommented
+            .lines()
+            .filter(|l| l.contains(" = ") || l.starts_with("[["))
+            .collect();
+        let uncommented = uncommented.join("\n");
+        let file = ConfigFile::from_toml(&uncommented).unwrap();
+        assert_eq!(file.addr, Some(config.addr));
+        assert_eq!(file.electrum_url.as_deref(), Some(LIQUID_SOCKET));
+        let token = &file.auth_tokens.as_ref().unwrap()[0];
+        assert_eq!(token.as_str().len(), 32);
+        assert_eq!(format!("{token:?}"), "AuthToken(***)");
+        assert!(AuthToken::from_str("with space").is_err());
+
+        let mut applied = Config::default_mainnet(PathBuf::from("/tmp/lwk"));
+        applied.apply_file(&file);
+        assert_eq!(applied.default_fee_rate, Some(100.0));
+        assert_eq!(applied.auth_tokens, file.auth_tokens.unwrap());
+        assert_eq!(applied.electrum_url, config.electrum_url);
+        assert_eq!(applied.webhooks.len(), 1);
+        assert_eq!(applied.webhooks[0].confirmations, 2);
+        assert!(!format!("{applied:?}").contains(&applied.webhooks[0].secret));
+        let rpc = applied.elements_rpc.as_ref().unwrap();
+        assert_eq!(rpc.url, "http://127.0.0.1:7041");
+        assert_eq!(rpc.user, "rpcuser");
+        assert!(!format!("{applied:?}").contains(&rpc.password));
+        let err = ConfigFile::from_toml("elements_rpc_user = \"user\"").unwrap_err();
+        assert!(err.contains("require elements_rpc_url"), "{err}");
+
+        assert_eq!(applied.tenants.len(), 1);
+        let tenant = applied.tenant(&applied.tenants[0]);
+        assert_eq!(tenant.datadir, PathBuf::from("/tmp/lwk/tenants/shop"));
+        assert_eq!(tenant.auth_tokens, applied.tenants[0].auth_tokens);
+        assert!(tenant.webhooks.is_empty());
+        assert!(tenant.tenants.is_empty());
+
+        let tenants = |id: &str, token: &str| {
+            let toml = format!(
+                "auth_tokens = [\"a\"]\n[[tenants]]\nid = \"{id}\"\nauth_tokens = [\"{token}\"]"
+            );
+            ConfigFile::from_toml(&toml)
+        };
+        assert!(tenants("shop-1_a", "b").is_ok());
+        let err = tenants("../shop", "b").unwrap_err();
+        assert!(err.contains("invalid tenant id"), "{err}");
+        let err = tenants("shop", "a").unwrap_err();
+        assert!(err.contains("reuses an auth token"), "{err}");
+        let toml = "auth_tokens = [\"a\"]\n[[tenants]]\nid = \"shop\"\nauth_tokens = []";
+        let err = ConfigFile::from_toml(toml).unwrap_err();
+        assert!(err.contains("has no auth tokens"), "{err}");
+        let tenant = "[[tenants]]\nid = \"shop\"\nauth_tokens = [\"b\"]\n";
+        let err =
+            ConfigFile::from_toml(&format!("auth_tokens = [\"a\"]\n{tenant}{tenant}")).unwrap_err();
+        assert!(err.contains("duplicate tenant id"), "{err}");
+        let err =
+            ConfigFile::from_toml("[[tenants]]\nid = \"a\"\nauth_tokens = [\"x\"]").unwrap_err();
+        assert!(err.contains("tenants require auth_tokens"), "{err}");
+
+        let err =
+            ConfigFile::from_toml("[[webhooks]]\nurl = \"nope\"\nsecret = \"s\"").unwrap_err();
+        assert!(err.contains("invalid webhook url"), "{err}");
+
+        let err = ConfigFile::from_toml("electrum = \"typo\"").unwrap_err();
+        assert!(err.contains("unknown field"), "{err}");
+    }
+
+    #[test]
+    fn test_explorer() {
+        let mut config = Config::default_regtest(PathBuf::from("/tmp/lwk"));
+        assert!(config.explorer().is_none());
+        config.explorer_url = "http://localhost:5000".into();
+        let explorer = config.explorer().unwrap();
+        assert_eq!(explorer.base_url(), "http://localhost:5000/");
+    }
+}
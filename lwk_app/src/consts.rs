@@ -6,3 +6,33 @@ pub const DEFAULT_ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub const SCANNING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Confirmations required by `wallet_verify_payment` if not specified
+pub const DEFAULT_MIN_CONFIRMATIONS: u32 = 1;
+
+/// Derivation path of the key signing payment verifications, not used by any wallet
+pub const ATTESTATION_PATH: &str = "m/5413'/0'";
+
+/// PSETs of the maximum size fitting in a request body, when `max_pset_size` is set
+///
+/// Requests like `wallet_combine` and batches carry more than one PSET.
+pub const MAX_BODY_PSETS: usize = 16;
+
+/// Bytes of a request body in addition to its PSETs, when `max_pset_size` is set
+pub const MAX_BODY_OVERHEAD: usize = 64 * 1024;
+
+/// Maximum number of addresses returned by a single `wallet_addresses` call
+pub const MAX_ADDRESSES: u32 = 10_000;
+
+/// Deliveries to a webhook failing this many times are moved to the dead-letter log
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 6;
+
+/// Interval before retrying a failed delivery to a webhook, doubled at every attempt
+pub const WEBHOOK_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+pub const METRIC_WALLETS_LOADED: &str = "lwk_wallets_loaded";
+pub const METRIC_SYNC_DURATION: &str = "lwk_sync_duration_seconds";
+pub const METRIC_ELECTRUM_FAILURES: &str = "lwk_electrum_failures_total";
+pub const METRIC_TIP_HEIGHT: &str = "lwk_tip_height";
+pub const METRIC_BROADCASTS: &str = "lwk_broadcasts_total";
+pub const METRIC_WEBHOOK_FAILURES: &str = "lwk_webhook_failures_total";
@@ -0,0 +1,260 @@
+//! Inspection and maintenance of the wallet caches in the data directory
+//!
+//! The cache of a wallet is a directory named with a one-way hash of its descriptor, here they are
+//! mapped back to the wallets of the server state. These functions must not be used while the
+//! server is running on the same data directory.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lwk_rpc_model::request;
+use lwk_tiny_jrpc::Request;
+use lwk_wollet::{FsPersister, Wollet, WolletDescriptor};
+use serde::Serialize;
+
+use crate::method::Method;
+use crate::{Config, Error};
+
+/// A wallet cache in the data directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WalletCache {
+    /// Name of the cache directory, derived from the wallet descriptor
+    pub id: String,
+
+    pub path: PathBuf,
+
+    /// The tenant of the wallets using the cache, `None` for the ones not of a tenant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+
+    /// Names of the wallets of the server state using the cache, empty if not used anymore
+    pub wallets: Vec<String>,
+
+    /// Descriptor wallet id of the wallets using the cache
+    pub dwid: Option<String>,
+
+    /// Size of the cache on disk, in bytes
+    pub size: u64,
+
+    /// Number of updates in the cache
+    pub updates: usize,
+
+    /// Time of the last update written in the cache, in seconds since the Unix epoch
+    pub last_sync: Option<u64>,
+}
+
+/// The wallets persisted in the server state, with their cache
+struct Wallets {
+    tenant: Option<String>,
+    config: Config,
+    by_cache: HashMap<String, (Vec<String>, WolletDescriptor)>,
+}
+
+/// The wallet caches of the server and of its tenants, with the wallets using them
+pub fn wallet_caches(config: &Config) -> Result<Vec<WalletCache>, Error> {
+    let mut caches = vec![];
+    for wallets in server_wallets(config)? {
+        let dir = wallets.config.datadir.join(config.network.as_str());
+        let dir = dir.join("enc_cache");
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let id = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let (names, desc) = match wallets.by_cache.get(&id) {
+                Some((names, desc)) => (names.clone(), Some(desc)),
+                None => (vec![], None),
+            };
+            let (size, updates, last_sync) = cache_usage(&path)?;
+            caches.push(WalletCache {
+                id,
+                path,
+                tenant: wallets.tenant.clone(),
+                wallets: names,
+                dwid: desc.and_then(|d| d.dwid(config.jade_network()).ok()),
+                size,
+                updates,
+                last_sync,
+            });
+        }
+    }
+    caches.sort_by(|a, b| (&a.tenant, &a.id).cmp(&(&b.tenant, &b.id)));
+    Ok(caches)
+}
+
+/// Delete the caches not used by any wallet and not written since `older_than`
+pub fn prune_caches(config: &Config, older_than: Duration) -> Result<Vec<WalletCache>, Error> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut pruned = vec![];
+    for cache in wallet_caches(config)? {
+        let age = now.saturating_sub(Duration::from_secs(cache.last_sync.unwrap_or_default()));
+        if cache.wallets.is_empty() && age >= older_than {
+            fs::remove_dir_all(&cache.path)?;
+            pruned.push(cache);
+        }
+    }
+    Ok(pruned)
+}
+
+/// Discard the interrupted writes and the corrupted updates of the caches used by the wallets
+///
+/// Returns the caches after the cleanup.
+pub fn vacuum_caches(config: &Config) -> Result<Vec<WalletCache>, Error> {
+    for wallets in server_wallets(config)? {
+        for (_, desc) in wallets.by_cache.into_values() {
+            // Opening the wallet removes interrupted writes and truncates corrupted updates, it
+            // fails without modifying the cache on updates it cannot decode
+            let network = wallets.config.network;
+            Wollet::with_fs_persist(network, desc, &wallets.config.datadir)?;
+        }
+    }
+    wallet_caches(config)
+}
+
+/// The wallets of the server state and of the state of every tenant in the data directory
+fn server_wallets(config: &Config) -> Result<Vec<Wallets>, Error> {
+    let mut configs = vec![(None, config.clone())];
+    let tenants_dir = config.datadir.join("tenants");
+    if tenants_dir.is_dir() {
+        for entry in fs::read_dir(&tenants_dir)? {
+            let path = entry?.path();
+            if let (true, Some(id)) = (path.is_dir(), path.file_name()) {
+                let mut config = config.clone();
+                config.datadir = path.clone();
+                configs.push((Some(id.to_string_lossy().to_string()), config));
+            }
+        }
+    }
+
+    let mut result = vec![];
+    for (tenant, config) in configs {
+        let state_path = config
+            .datadir
+            .join(config.network.as_str())
+            .join("state.json");
+        let mut by_cache: HashMap<String, (Vec<String>, WolletDescriptor)> = HashMap::new();
+        let state = match fs::read_to_string(&state_path) {
+            Ok(state) => state,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        for line in state.lines() {
+            let r: Request = serde_json::from_str(line)?;
+            if r.method != Method::WalletLoad.to_string() {
+                continue;
+            }
+            let params = r.params.unwrap_or_default();
+            let r: request::WalletLoad = serde_json::from_value(params)?;
+            let desc: WolletDescriptor = r.descriptor.parse()?;
+            let id = cache_id(&config, &desc);
+            let entry = by_cache.entry(id).or_insert_with(|| (vec![], desc));
+            entry.0.push(r.name);
+        }
+        result.push(Wallets {
+            tenant,
+            config,
+            by_cache,
+        });
+    }
+    Ok(result)
+}
+
+/// The name of the cache directory of the wallet with descriptor `desc`
+fn cache_id(config: &Config, desc: &WolletDescriptor) -> String {
+    let path = FsPersister::cache_dir(&config.datadir, config.network, desc);
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The size, the number of updates and the time of the last update of the cache at `path`
+fn cache_usage(path: &Path) -> Result<(u64, usize, Option<u64>), Error> {
+    let mut size = 0;
+    let mut updates = 0;
+    let mut last = None;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        size += metadata.len();
+        if entry.path().extension().is_none() {
+            updates += 1;
+        }
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH);
+        let modified = modified.map(|d| d.as_secs()).unwrap_or_default();
+        last = last.max(Some(modified));
+    }
+    Ok((size, updates, last))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wallet_caches() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let config = Config::default_testnet(tempdir.path().to_path_buf());
+        let desc: WolletDescriptor = lwk_test_util::TEST_DESCRIPTOR.parse().unwrap();
+        let network = config.network;
+        let datadir = &config.datadir;
+        assert!(wallet_caches(&config).unwrap().is_empty());
+
+        // A cache used by a wallet of the state and one not used anymore
+        Wollet::with_fs_persist(network, desc.clone(), datadir).unwrap();
+        let used = FsPersister::cache_dir(datadir, network, &desc);
+        let load = request::WalletLoad {
+            descriptor: desc.to_string(),
+            name: "w".into(),
+            birthday: None,
+        };
+        let line = serde_json::json!({"jsonrpc": "2.0", "method": "wallet_load", "params": load});
+        fs::write(config.state_path().unwrap(), format!("{line}\n")).unwrap();
+        let unused = used.with_file_name("0000");
+        fs::create_dir_all(&unused).unwrap();
+        fs::write(unused.join("000000000000"), b"data").unwrap();
+
+        let caches = wallet_caches(&config).unwrap();
+        assert_eq!(caches.len(), 2);
+        let unused_cache = &caches[0];
+        assert_eq!(unused_cache.id, "0000");
+        assert!(unused_cache.wallets.is_empty());
+        assert_eq!(unused_cache.dwid, None);
+        assert_eq!((unused_cache.size, unused_cache.updates), (4, 1));
+        assert!(unused_cache.last_sync.is_some());
+        let used_cache = &caches[1];
+        assert_eq!(used_cache.path, used);
+        assert_eq!(used_cache.wallets, vec!["w".to_string()]);
+        let dwid = desc.dwid(config.jade_network()).unwrap();
+        assert_eq!(used_cache.dwid, Some(dwid));
+
+        // Interrupted writes are removed
+        fs::write(used.join("000000000005.tmp"), b"partial").unwrap();
+        let size = wallet_caches(&config).unwrap()[1].size;
+        let vacuumed = vacuum_caches(&config).unwrap();
+        assert_eq!(vacuumed[1].size, size - 7);
+
+        // Recent unused caches are kept if requested
+        let day = Duration::from_secs(86_400);
+        assert!(prune_caches(&config, day).unwrap().is_empty());
+        let pruned = prune_caches(&config, Duration::ZERO).unwrap();
+        assert_eq!(pruned, vec![caches[0].clone()]);
+        assert!(!unused.exists());
+        assert!(used.exists());
+    }
+}
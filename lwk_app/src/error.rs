@@ -104,9 +104,38 @@ pub enum Error {
     #[error("Error re-applying start request\nError {0}\nAt line {1} from file {2}\nConsider: (a) correct the line (b) remove the line (c) remove the file")]
     StartStateLoad(String, usize, String),
 
+    #[error("Invalid config file '{0}': {1}")]
+    InvalidConfigFile(String, String),
+
     #[error("Received stop command")]
     Stop,
 
+    #[error("PSET of {0} characters exceeds the maximum size of {1}")]
+    PsetTooLarge(usize, usize),
+
+    #[error("{0} addressees exceed the maximum of {1}")]
+    TooManyAddressees(usize, usize),
+
+    #[error("Cannot load more than {0} wallets")]
+    TooManyWallets(usize),
+
+    #[error("Policy of wallet '{0}' forbids signing: {1}")]
+    PolicyViolation(String, String),
+
+    #[error(
+        "Policy of wallet '{0}' cannot be removed or weakened while its signers are loaded: {1}"
+    )]
+    PolicyLocked(String, String),
+
+    #[error("Signer '{0}' requires a TOTP code")]
+    TotpRequired(String),
+
+    #[error("Invalid TOTP code for signer '{0}'")]
+    TotpInvalid(String),
+
+    #[error("Method not allowed for tenant '{0}'")]
+    MethodNotAllowedForTenant(String),
+
     // TODO remove into specific errors
     #[error("Generic error {0}")]
     Generic(String),
@@ -128,6 +157,18 @@ impl Error {
             Error::SignerAlreadyLoaded(_) => {
                 ImplementationDefinedCode::new(-32_011).expect("static")
             }
+            Error::PsetTooLarge(..) => ImplementationDefinedCode::new(-32_014).expect("static"),
+            Error::TooManyAddressees(..) => {
+                ImplementationDefinedCode::new(-32_015).expect("static")
+            }
+            Error::TooManyWallets(_) => ImplementationDefinedCode::new(-32_016).expect("static"),
+            Error::PolicyViolation(..) => ImplementationDefinedCode::new(-32_017).expect("static"),
+            Error::TotpRequired(_) => ImplementationDefinedCode::new(-32_018).expect("static"),
+            Error::TotpInvalid(_) => ImplementationDefinedCode::new(-32_019).expect("static"),
+            Error::MethodNotAllowedForTenant(_) => {
+                ImplementationDefinedCode::new(-32_020).expect("static")
+            }
+            Error::PolicyLocked(..) => ImplementationDefinedCode::new(-32_021).expect("static"),
 
             _ => lwk_tiny_jrpc::error::GENERIC,
         }
@@ -138,6 +179,14 @@ impl Error {
         match self {
             Error::WalletNotExist(n) => Some(json!({"name": n.to_string()})),
             Error::SignerNotExist(n) => Some(json!({"name": n.to_string()})),
+            Error::PsetTooLarge(size, max) => Some(json!({"size": size, "max": max})),
+            Error::TooManyAddressees(n, max) => Some(json!({"addressees": n, "max": max})),
+            Error::TooManyWallets(max) => Some(json!({"max": max})),
+            Error::PolicyViolation(n, reason) => Some(json!({"name": n, "reason": reason})),
+            Error::TotpRequired(n) => Some(json!({"name": n})),
+            Error::TotpInvalid(n) => Some(json!({"name": n})),
+            Error::MethodNotAllowedForTenant(t) => Some(json!({"tenant": t})),
+            Error::PolicyLocked(n, signers) => Some(json!({"name": n, "signers": signers})),
             _ => None,
         }
     }
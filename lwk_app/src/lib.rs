@@ -11,57 +11,73 @@
 //!
 //! All the requests and responses data model are in the [`lwk_rpc_model`] crate.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::net::SocketAddr;
 use std::num::NonZeroU8;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread::{sleep, JoinHandle};
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use lwk_common::{
     address_to_text_qr, address_to_uri_qr, keyorigin_xpub_from_str, multisig_desc, singlesig_desc,
-    InvalidBipVariant, InvalidBlindingKeyVariant, InvalidMultisigVariant, InvalidSinglesigVariant,
-    Signer,
+    singlesig_desc_account, Bip, InvalidBipVariant, InvalidBlindingKeyVariant,
+    InvalidMultisigVariant, InvalidSinglesigVariant, Multisig, Signer,
 };
 use lwk_jade::derivation_path_to_vec;
 use lwk_jade::get_receive_address::Variant;
 use lwk_jade::register_multisig::{JadeDescriptor, RegisterMultisigParams};
 use lwk_jade::Jade;
 use lwk_signer::{AnySigner, SwSigner};
-use lwk_tiny_jrpc::{tiny_http, JsonRpcServer, Request, Response};
+use lwk_tiny_jrpc::{tiny_http, Events, JsonRpcServer, Metrics, Request, Response};
 use lwk_wollet::amp2::Amp2;
-use lwk_wollet::bitcoin::bip32::Fingerprint;
+use lwk_wollet::bitcoin::bip32::{ChildNumber, Fingerprint};
 use lwk_wollet::bitcoin::XKeyIdentifier;
-use lwk_wollet::clients::blocking::BlockchainBackend;
-use lwk_wollet::elements::encode::serialize;
+use lwk_wollet::clients::blocking::{BlockchainBackend, ServerHealthCache};
+use lwk_wollet::clients::try_unblind;
+use lwk_wollet::clients::CancellationToken;
+use lwk_wollet::elements::encode::{deserialize, serialize};
 use lwk_wollet::elements::hex::{FromHex, ToHex};
 use lwk_wollet::elements::pset::PartiallySignedTransaction;
-use lwk_wollet::elements::{Address, AssetId, Txid};
+use lwk_wollet::elements::{Address, AssetId, Transaction, TxOut, Txid};
 use lwk_wollet::elements_miniscript::descriptor::{Descriptor, DescriptorType, WshInner};
 use lwk_wollet::elements_miniscript::miniscript::decode::Terminal;
 use lwk_wollet::elements_miniscript::{DescriptorPublicKey, ForEachKey};
+use lwk_wollet::registry::RegistryPost;
+use lwk_wollet::ElementsNetwork;
+use lwk_wollet::FsPersister;
+use lwk_wollet::PsetSession;
 use lwk_wollet::Wollet;
+use lwk_wollet::WolletBackup;
 use lwk_wollet::WolletDescriptor;
+use lwk_wollet::WolletTxBuilder;
+use lwk_wollet::{export_history, history_entries, HistoryFormat};
 use serde_json::Value;
 
 use crate::method::Method;
-use crate::state::{AppAsset, AppSigner, State};
+use crate::policy::{Policy, SpendCounters};
+use crate::state::{totp_request, AppAsset, AppSigner, State};
+use crate::totp::Totp;
 use lwk_rpc_model::{request, response};
 
 pub use client::Client;
-pub use config::Config;
+pub use config::{AuthToken, Config, ConfigFile, ElementsRpc, Limits, Tenant};
 pub use error::Error;
-pub use lwk_tiny_jrpc::RpcError;
+pub use lwk_tiny_jrpc::{RateLimit, RpcError};
+pub use webhook::Webhook;
 
 mod client;
 mod config;
 pub mod consts;
+pub mod datadir;
 mod error;
 pub mod method;
+mod policy;
 mod reqwest_transport;
 mod state;
+mod totp;
+mod webhook;
 
 pub struct App {
     rpc: Option<JsonRpcServer>,
@@ -70,22 +86,60 @@ pub struct App {
     /// Set to false to stop the background scanning thread
     is_scanning: Arc<AtomicBool>,
 
+    /// Aborts the scan in progress when stopping
+    cancel_scan: CancellationToken,
+
+    /// Wallet events discovered while scanning, streamed at the `/events` endpoint
+    events: Events,
+
     /// Handle of the scanning thread
     scanning_handle: Option<JoinHandle<()>>,
+
+    /// Handles of the threads delivering the events to the webhooks, one for each tenant with webhooks
+    webhooks_handles: Vec<JoinHandle<()>>,
 }
 
 impl App {
     pub fn new(config: Config) -> Result<App, Error> {
-        log::info!("Creating new app with config: {:?}", config);
+        tracing::info!("Creating new app with config: {:?}", config);
 
         Ok(App {
             rpc: None,
             config,
             scanning_handle: None,
+            webhooks_handles: vec![],
             is_scanning: Arc::new(AtomicBool::new(false)),
+            cancel_scan: CancellationToken::new(),
+            events: Events::new(),
         })
     }
 
+    /// Load the wallets, signers and assets of the previous run, persisted in the datadir of `config`
+    fn load_state(&self, config: &Config) -> Result<(), Error> {
+        let path = config.state_path()?;
+        match std::fs::read_to_string(&path) {
+            Ok(string) => {
+                tracing::info!(
+                    "Loading previous state, {} elements",
+                    string.lines().count()
+                );
+
+                let client =
+                    Client::with_auth_token(self.config.addr, config.auth_tokens.first().cloned())?;
+
+                for (n, line) in string.lines().enumerate() {
+                    self.apply_request(&client, line).map_err(|err| {
+                        Error::StartStateLoad(err.to_string(), n + 1, path.display().to_string())
+                    })?
+                }
+            }
+            Err(_) => {
+                tracing::info!("There is no previous state at {path:?}");
+            }
+        }
+        Ok(())
+    }
+
     fn apply_request(&self, client: &Client, line: &str) -> Result<(), Error> {
         let r: Request = serde_json::from_str(line)?;
         let method: Method = r.method.parse()?;
@@ -97,20 +151,16 @@ impl App {
         if self.rpc.is_some() {
             return Err(error::Error::AlreadyStarted);
         }
-        let mut state = State {
-            config: self.config.clone(),
-            wollets: Default::default(),
-            signers: Default::default(),
-            assets: Default::default(),
-            tx_memos: Default::default(),
-            addr_memos: Default::default(),
-            do_persist: false,
-            scan_loops_started: 0,
-            scan_loops_completed: 0,
-            interrupt_wait: false,
-        };
-        state.insert_policy_asset();
-        let state = Arc::new(Mutex::new(state));
+        let metrics = new_metrics();
+        let mut tenants = Tenants::new(new_state(&self.config, &metrics)?);
+        for tenant in self.config.tenants.iter() {
+            let config = self.config.tenant(tenant);
+            tenants.insert(&tenant.id, new_state(&config, &metrics)?);
+        }
+        let states: Vec<_> = tenants
+            .iter()
+            .map(|(id, s)| (id.is_some(), s.clone()))
+            .collect();
         let server = tiny_http::Server::http(self.config.addr)
             .map_err(|_| Error::ServerStart(self.config.addr.to_string()))?;
 
@@ -118,38 +168,60 @@ impl App {
         // replied after 15 seconds, using 1 instead seems to not have that issue.
         let config = lwk_tiny_jrpc::Config::builder()
             .with_num_threads(NonZeroU8::new(1).expect("static"))
+            .with_events(Some(self.events.clone()))
+            .with_metrics(Some(metrics.clone()))
+            .with_schema(Some(method::openrpc()?.to_string()))
+            .with_rate_limit(self.config.rate_limit)
+            .with_max_body_size(self.config.limits.max_body_size())
+            .with_method_timeouts(self.config.method_timeouts.clone())
+            .with_auth_tokens(auth_tokens(&self.config))
+            .with_tenant_tokens(tenant_tokens(&self.config))
             .build();
 
-        let rpc = lwk_tiny_jrpc::JsonRpcServer::new(server, config, state.clone(), method_handler);
-        let path = self.config.state_path()?;
-        match std::fs::read_to_string(&path) {
-            Ok(string) => {
-                log::info!(
-                    "Loading previous state, {} elements",
-                    string.lines().count()
-                );
-
-                let client = self.client()?;
-
-                for (n, line) in string.lines().enumerate() {
-                    self.apply_request(&client, line).map_err(|err| {
-                        Error::StartStateLoad(err.to_string(), n + 1, path.display().to_string())
-                    })?
+        let tenants = Arc::new(tenants);
+        let rpc = lwk_tiny_jrpc::JsonRpcServer::new(server, config, tenants, tenant_handler);
+        let mut targets = vec![];
+        for (is_tenant, state) in states {
+            let config = state.lock().map_err(|e| e.to_string())?.config.clone();
+            self.load_state(&config)?;
+            state.lock().map_err(|e| e.to_string())?.do_persist = true;
+
+            let webhooks = match config.webhooks.is_empty() {
+                true => None,
+                false => {
+                    let dispatcher = webhook::Dispatcher::new(
+                        config.webhooks.clone(),
+                        config.webhooks_queue_path()?,
+                        config.webhooks_dead_letter_path()?,
+                        config.timeout,
+                        consts::WEBHOOK_RETRY_INTERVAL,
+                        Some(metrics.clone()),
+                    );
+                    let (sender, handle) = dispatcher.spawn();
+                    self.webhooks_handles.push(handle);
+                    Some(sender)
                 }
-            }
-            Err(_) => {
-                log::info!("There is no previous state at {path:?}");
-            }
+            };
+            targets.push(ScanTarget {
+                state,
+                publish: !is_tenant,
+                webhooks,
+                webhook_confirmations: config.webhooks.iter().map(|w| w.confirmations).collect(),
+                webhook_states: HashMap::new(),
+            });
         }
-        state.lock().map_err(|e| e.to_string())?.do_persist = true;
 
         self.rpc = Some(rpc);
 
         // Wallets scanning thread
         self.is_scanning.store(true, Ordering::Relaxed);
         let is_scanning = self.is_scanning.clone();
-        let state_scanning = state.clone();
+        self.cancel_scan = CancellationToken::new();
+        let cancel_scan = self.cancel_scan.clone();
         let scanning_interval = self.config.scanning_interval;
+        let events = self.events.clone();
+        let mut last_tip = None;
+        let scanning_metrics = metrics;
         let stop_interval = Duration::from_millis(100);
         let mut interval = Duration::ZERO; // Do not wait in the first scan loop
         let scanning_handle = std::thread::spawn(move || 'scan: loop {
@@ -159,10 +231,9 @@ impl App {
                     break 'scan;
                 }
                 if interval == Duration::ZERO
-                    || state_scanning
-                        .lock()
-                        .map(|s| s.interrupt_wait)
-                        .unwrap_or(false)
+                    || targets
+                        .iter()
+                        .any(|t| t.state.lock().map(|s| s.interrupt_wait).unwrap_or(false))
                 {
                     interval = scanning_interval; // Reset wait interval
                     break 'stop;
@@ -171,51 +242,193 @@ impl App {
                 interval = interval.saturating_sub(stop_interval);
             }
 
-            let (wollets_names, config) = {
-                let mut s = state_scanning.lock().expect("state lock poison");
-                s.interrupt_wait = false;
-                s.scan_loops_started += 1;
-                let wollets_names: Vec<_> = s.wollets.iter().map(|e| e.0.to_owned()).collect();
-                let config = s.config.clone();
-                (wollets_names, config)
-            };
-
-            match config.electrum_client() {
-                Ok(mut electrum_client) => {
-                    for name in wollets_names {
-                        let state = match state_scanning
-                            .lock()
-                            .expect("state lock poison")
-                            .wollets
-                            .get(&name)
+            let mut wallets_loaded = 0;
+            for target in targets.iter_mut() {
+                let state_scanning = &target.state;
+                let (wollets_names, config) = {
+                    let mut s = state_scanning.lock().expect("state lock poison");
+                    s.interrupt_wait = false;
+                    s.scan_loops_started += 1;
+                    let wollets_names: Vec<_> = s.wollets.iter().map(|e| e.0.to_owned()).collect();
+                    wallets_loaded += wollets_names.len();
+                    let config = s.config.clone();
+                    (wollets_names, config)
+                };
+                target
+                    .webhook_states
+                    .retain(|(name, _), _| wollets_names.contains(name));
+
+                let url = config.electrum_url.as_str();
+                match config.scan_electrum_client(&cancel_scan) {
+                    Ok(mut electrum_client) => {
+                        let start = Instant::now();
+                        let features = electrum_client.server_features();
+                        let latency = start.elapsed();
                         {
-                            Ok(w) => w.state(),
-                            Err(_) => continue,
-                        };
-
-                        match electrum_client.full_scan(&state) {
-                            Ok(Some(update)) => {
-                                let mut s = state_scanning.lock().expect("state lock poison");
-                                let _ = match s.wollets.get_mut(&name) {
-                                    Ok(wollet) => wollet.apply_update(update),
-                                    Err(_) => continue,
-                                };
+                            let mut s = state_scanning.lock().expect("state lock poison");
+                            match features {
+                                Ok(features) => {
+                                    s.server_health.record_success(url, latency);
+                                    s.server_health.record_features(
+                                        url,
+                                        &features.protocol_max,
+                                        features.pruning,
+                                    );
+                                }
+                                Err(e) => {
+                                    tracing::debug!("Cannot get the server features: {e}");
+                                    s.server_health.record_failure(url);
+                                }
+                            }
+                        }
+                        for name in wollets_names {
+                            let _span =
+                                tracing::info_span!("wallet_sync", wallet = %name).entered();
+                            let state = match state_scanning
+                                .lock()
+                                .expect("state lock poison")
+                                .wollets
+                                .get(&name)
+                            {
+                                Ok(w) => w.state(),
+                                Err(_) => continue,
+                            };
+
+                            let start = Instant::now();
+                            let result = electrum_client.full_scan_cancellable(
+                                &state,
+                                0,
+                                |_| {},
+                                &cancel_scan,
+                            );
+                            scanning_metrics.observe(
+                                consts::METRIC_SYNC_DURATION,
+                                start.elapsed().as_secs_f64(),
+                            );
+                            match result {
+                                Ok(Some(update)) => {
+                                    let mut s = state_scanning.lock().expect("state lock poison");
+                                    let wollet = match s.wollets.get_mut(&name) {
+                                        Ok(wollet) => wollet,
+                                        Err(_) => continue,
+                                    };
+                                    // Computing the events is not free, skip it if nobody listens
+                                    let listening = target.publish && events.subscribers() > 0;
+                                    let before = (listening && !update.only_tip())
+                                        .then(|| tx_heights(wollet));
+                                    // The whole history is found in the first scan, it is not notified
+                                    let webhook_key =
+                                        (name.clone(), wollet.descriptor().to_string());
+                                    let before_webhooks = (target.webhooks.is_some()
+                                        && wollet.tip().height() > 0)
+                                        .then(|| {
+                                            target
+                                                .webhook_states
+                                                .remove(&webhook_key)
+                                                .unwrap_or_else(|| webhook::tx_states(wollet))
+                                        });
+                                    let reorg_depth = update.reorg_depth(wollet);
+                                    if let Some(depth) = reorg_depth {
+                                        tracing::info!("Reorg of {depth} blocks for wallet {name}");
+                                    }
+                                    if wollet.apply_update(update).is_err() {
+                                        continue;
+                                    }
+                                    let tip = wollet.tip();
+                                    scanning_metrics
+                                        .set(consts::METRIC_TIP_HEIGHT, tip.height() as f64);
+                                    let mut webhook_events = vec![];
+                                    if target.webhooks.is_some() {
+                                        let after = webhook::tx_states(wollet);
+                                        if let Some(before) = before_webhooks {
+                                            webhook_events = webhook::webhook_events(
+                                                &name,
+                                                &before,
+                                                &after,
+                                                &target.webhook_confirmations,
+                                            );
+                                        }
+                                        target.webhook_states.insert(webhook_key, after);
+                                    }
+                                    if let Some(webhooks) = target.webhooks.as_ref() {
+                                        for event in webhook_events.iter() {
+                                            let _ = webhooks.send(event.clone());
+                                        }
+                                    }
+                                    if listening {
+                                        let mut wallet_events = match before {
+                                            Some(before) => tx_events(
+                                                &name,
+                                                &before,
+                                                &tx_heights(wollet),
+                                                |txid| wollet.conflicted_by(txid),
+                                            ),
+                                            None => vec![],
+                                        };
+                                        if let Some(depth) = reorg_depth {
+                                            wallet_events.insert(
+                                                0,
+                                                response::Event::Reorg {
+                                                    name: name.clone(),
+                                                    depth,
+                                                },
+                                            );
+                                        }
+                                        if last_tip != Some(tip.height()) {
+                                            last_tip = Some(tip.height());
+                                            wallet_events.push(response::Event::Tip {
+                                                height: tip.height(),
+                                                hash: tip.hash().to_string(),
+                                            });
+                                        }
+                                        for event in wallet_events {
+                                            match serde_json::to_value(&event) {
+                                                Ok(data) => events.publish(event.kind(), &data),
+                                                Err(e) => {
+                                                    tracing::warn!("Cannot serialize event: {e}")
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                                Ok(None) => (),
+                                Err(lwk_wollet::Error::Cancelled) => break 'scan,
+                                Err(e) => {
+                                    scanning_metrics.inc(consts::METRIC_ELECTRUM_FAILURES);
+                                    tracing::warn!("Scan of wallet {name} failed: {e}");
+                                    let mut s = state_scanning.lock().expect("state lock poison");
+                                    s.server_health.record_failure(url);
+                                    continue;
+                                }
+                            }
+
+                            for tx in outbox_pending(state_scanning, &name) {
+                                match electrum_client.broadcast(&tx) {
+                                    Ok(txid) => tracing::debug!("Rebroadcast {txid}"),
+                                    // Also fails if the transaction is already in the mempool
+                                    Err(e) => {
+                                        tracing::debug!("Rebroadcast {} failed: {e}", tx.txid())
+                                    }
+                                }
                             }
-                            Ok(None) => (),
-                            Err(_) => continue,
                         }
                     }
-                }
-                Err(_) => {
-                    log::info!(
-                        "Cannot create an electrum client, are we conected? Retrying in one sec"
-                    );
-                    sleep(Duration::from_secs(1))
-                }
-            };
+                    Err(e) => {
+                        scanning_metrics.inc(consts::METRIC_ELECTRUM_FAILURES);
+                        // Connecting has already been retried according to the retry policy
+                        tracing::warn!("Cannot create an electrum client, skipping this scan: {e}");
+                        let mut s = state_scanning.lock().expect("state lock poison");
+                        s.server_health.record_failure(url);
+                    }
+                };
 
-            let mut s = state_scanning.lock().expect("state lock poison");
-            s.scan_loops_completed += 1;
+                let mut s = state_scanning.lock().expect("state lock poison");
+                s.scan_loops_completed += 1;
+                if let Err(e) = s.server_health.save() {
+                    tracing::warn!("Cannot persist the server health: {e}");
+                }
+            }
+            scanning_metrics.set(consts::METRIC_WALLETS_LOADED, wallets_loaded as f64);
         });
         self.scanning_handle = Some(scanning_handle);
 
@@ -224,6 +437,7 @@ impl App {
 
     pub fn stop(&self) -> Result<(), Error> {
         self.is_scanning.store(false, Ordering::Relaxed);
+        self.cancel_scan.cancel();
         match self.rpc.as_ref() {
             Some(rpc) => {
                 rpc.stop();
@@ -252,23 +466,230 @@ impl App {
         if let Some(scanning_handle) = self.scanning_handle.take() {
             let _ = scanning_handle.join();
         }
+        // Exit once the scanning thread, sending the events, is terminated
+        for webhooks_handle in self.webhooks_handles.drain(..) {
+            let _ = webhooks_handle.join();
+        }
         Ok(())
     }
+}
+
+fn auth_tokens(config: &Config) -> Vec<String> {
+    let tokens = config.auth_tokens.iter();
+    tokens.map(|t| t.as_str().to_string()).collect()
+}
+
+/// The id of the tenant of every tenant token
+fn tenant_tokens(config: &Config) -> HashMap<String, String> {
+    let mut tokens = HashMap::new();
+    for tenant in config.tenants.iter() {
+        for token in tenant.auth_tokens.iter() {
+            tokens.insert(token.as_str().to_string(), tenant.id.clone());
+        }
+    }
+    tokens
+}
+
+/// The server state of the clients not belonging to a tenant and the ones of every tenant
+///
+/// Created when the server starts and not modified afterwards, thus shared without a lock.
+struct Tenants {
+    default: Arc<Mutex<State>>,
+    tenants: HashMap<String, Arc<Mutex<State>>>,
+}
+
+impl Tenants {
+    fn new(default: Arc<Mutex<State>>) -> Self {
+        Self {
+            default,
+            tenants: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, id: &str, state: Arc<Mutex<State>>) {
+        self.tenants.insert(id.to_string(), state);
+    }
+
+    fn get(&self, tenant: Option<&str>) -> Result<Arc<Mutex<State>>, Error> {
+        match tenant {
+            None => Ok(self.default.clone()),
+            Some(id) => self
+                .tenants
+                .get(id)
+                .cloned()
+                .ok_or_else(|| Error::Generic(format!("Unknown tenant {id}"))),
+        }
+    }
+
+    /// The states, with their tenant id, the first one is the default state
+    fn iter(&self) -> impl Iterator<Item = (Option<&str>, &Arc<Mutex<State>>)> {
+        let tenants = self.tenants.iter().map(|(id, s)| (Some(id.as_str()), s));
+        std::iter::once((None, &self.default)).chain(tenants)
+    }
+}
+
+/// A server state scanned by the scanning thread
+struct ScanTarget {
+    state: Arc<Mutex<State>>,
+
+    /// Stream the wallet events at the `/events` endpoint, only for the default state
+    publish: bool,
+
+    /// Sends the events to the webhook dispatcher of the state, if any webhook is configured
+    webhooks: Option<std::sync::mpsc::Sender<lwk_rpc_model::response::Event>>,
+
+    /// The confirmations notified to the webhooks
+    webhook_confirmations: BTreeSet<u32>,
+
+    /// The transactions of the wallets after their last scan, by name and descriptor, to compute
+    /// the webhook events of the next one
+    webhook_states: HashMap<(String, String), HashMap<Txid, webhook::TxState>>,
+}
+
+/// Create the state of the server, or of one of its tenants, according to `config`
+fn new_state(config: &Config, metrics: &Metrics) -> Result<Arc<Mutex<State>>, Error> {
+    let server_health = ServerHealthCache::load(config.server_health_path()?).unwrap_or_else(|e| {
+        tracing::warn!("Cannot load the server health, starting from scratch: {e}");
+        ServerHealthCache::new()
+    });
+    let spend_counters = SpendCounters::load(config.policy_counters_path()?)?;
+    let totp_counters = SpendCounters::load(config.totp_counters_path()?)?;
+    let mut state = State {
+        config: config.clone(),
+        wollets: Default::default(),
+        signers: Default::default(),
+        assets: Default::default(),
+        tx_memos: Default::default(),
+        addr_memos: Default::default(),
+        outbox: Default::default(),
+        do_persist: false,
+        server_health,
+        policies: Default::default(),
+        spend_counters,
+        totps: Default::default(),
+        totp_counters,
+        scan_loops_started: 0,
+        scan_loops_completed: 0,
+        interrupt_wait: false,
+        metrics: metrics.clone(),
+    };
+    state.insert_policy_asset();
+    state.insert_known_assets();
+    Ok(Arc::new(Mutex::new(state)))
+}
+
+/// Create the metrics exposed by the server
+fn new_metrics() -> Metrics {
+    let metrics = Metrics::new();
+    metrics.register_gauge(consts::METRIC_WALLETS_LOADED, "Number of wallets loaded");
+    metrics.register_histogram(
+        consts::METRIC_SYNC_DURATION,
+        "Duration of the scan of a wallet",
+        &[0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0],
+    );
+    metrics.register_counter(
+        consts::METRIC_ELECTRUM_FAILURES,
+        "Number of failed requests to the Electrum server",
+    );
+    metrics.register_gauge(consts::METRIC_TIP_HEIGHT, "Height of the blockchain tip");
+    metrics.register_counter(
+        consts::METRIC_BROADCASTS,
+        "Number of transactions broadcast",
+    );
+    metrics.register_counter(
+        consts::METRIC_WEBHOOK_FAILURES,
+        "Number of webhook deliveries moved to the dead-letter log",
+    );
+    metrics
+}
+
+/// The outbox transactions of wallet `name` to rebroadcast
+///
+/// Transactions confirmed or conflicted are removed from the outbox.
+fn outbox_pending(state: &Mutex<State>, name: &str) -> Vec<Transaction> {
+    let mut s = state.lock().expect("state lock poison");
+    let State {
+        wollets, outbox, ..
+    } = &mut *s;
+    let settled = match wollets.get(name) {
+        Ok(wollet) => outbox.settle(name, wollet),
+        Err(_) => return vec![],
+    };
+    match settled {
+        Ok(true) => {
+            if let Err(e) = s.persist_all() {
+                tracing::warn!("Cannot persist the outbox: {e}");
+            }
+        }
+        Ok(false) => (),
+        Err(e) => tracing::warn!("Cannot settle the outbox of wallet {name}: {e}"),
+    }
+    s.outbox.for_wollet(name)
+}
+
+/// The height of every transaction of the wallet, none if unconfirmed
+fn tx_heights(wollet: &Wollet) -> HashMap<Txid, Option<u32>> {
+    wollet
+        .transactions()
+        .map(|txs| txs.iter().map(|tx| (tx.txid, tx.height)).collect())
+        .unwrap_or_default()
+}
 
-    fn client(&self) -> Result<Client, Error> {
-        Client::new(self.config.addr)
+/// The new, newly confirmed and conflicted transactions of wallet `name`
+fn tx_events(
+    name: &str,
+    before: &HashMap<Txid, Option<u32>>,
+    after: &HashMap<Txid, Option<u32>>,
+    conflicted_by: impl Fn(&Txid) -> Option<Txid>,
+) -> Vec<response::Event> {
+    let mut events = vec![];
+    for txid in before.keys().filter(|txid| !after.contains_key(*txid)) {
+        if let Some(conflicting_txid) = conflicted_by(txid) {
+            events.push(response::Event::TxConflicted {
+                name: name.to_string(),
+                txid: txid.to_string(),
+                conflicting_txid: conflicting_txid.to_string(),
+            });
+        }
+    }
+    for (txid, height) in after {
+        match (before.get(txid), height) {
+            (None, _) => events.push(response::Event::NewTx {
+                name: name.to_string(),
+                txid: txid.to_string(),
+                height: *height,
+            }),
+            (Some(None), Some(height)) => events.push(response::Event::Confirmation {
+                name: name.to_string(),
+                txid: txid.to_string(),
+                height: *height,
+            }),
+            _ => (),
+        }
     }
+    events
+}
+
+/// Handle the request with the state of its tenant
+fn tenant_handler(
+    request: Request,
+    tenants: Arc<Tenants>,
+) -> Result<Response, lwk_tiny_jrpc::Error> {
+    let state = tenants.get(request.tenant.as_deref())?;
+    method_handler(request, state)
 }
 
 fn method_handler(
     request: Request,
     state: Arc<Mutex<State>>,
 ) -> Result<Response, lwk_tiny_jrpc::Error> {
+    let id = serde_json::to_string(&request.id).unwrap_or_default();
+    let _span = tracing::info_span!("rpc", method = %request.method, id = %id).entered();
     Ok(inner_method_handler(request, state)?)
 }
 
 fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Response, Error> {
-    log::debug!(
+    tracing::debug!(
         "method: {} params: {:?} ",
         request.method.as_str(),
         request.params
@@ -308,17 +729,53 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 })?,
             )
         }
+        Method::ServerStatus => {
+            let s = state.lock()?;
+            let servers = s
+                .server_health
+                .iter()
+                .map(|(url, h)| response::ServerHealth {
+                    url: url.to_string(),
+                    healthy: h.is_healthy(),
+                    latency_ms: h.latency_ms,
+                    protocol_version: h.protocol_version.clone(),
+                    pruning: h.pruning,
+                    successes: h.successes,
+                    failures: h.failures,
+                    consecutive_failures: h.consecutive_failures,
+                    last_failure: h.last_failure,
+                })
+                .collect();
+            Response::result(
+                request.id,
+                serde_json::to_value(response::ServerStatus { servers })?,
+            )
+        }
         Method::WalletLoad => {
             let r: request::WalletLoad = serde_json::from_value(params)?;
             let mut s = state.lock()?;
             // TODO recognize different name same descriptor?
 
             let desc: WolletDescriptor = r.descriptor.parse()?;
-            if desc.is_mainnet() != s.config.is_mainnet() {
-                return Err(Error::Generic("Descriptor is for the wrong network".into()));
+            desc.check_network(s.config.network)?;
+            s.config.limits.check_wallets(s.wollets.iter().count())?;
+            let mut wollet = Wollet::with_fs_persist(s.config.network, desc, &s.config.datadir)?;
+            // A wallet can be loaded while the server is down, but not on a server of another
+            // network. Regtest servers are not verified, there is no need to connect.
+            let replaying = !s.do_persist;
+            if !replaying && s.config.network.genesis_hash().is_some() {
+                match s.config.electrum_client() {
+                    Ok(client) => wollet.verify_backend(&client)?,
+                    Err(e @ Error::Wollet(lwk_wollet::Error::WrongGenesisHash { .. })) => {
+                        return Err(e)
+                    }
+                    Err(e) => tracing::warn!("Cannot verify the network of the server: {e}"),
+                }
             }
-            let wollet = Wollet::with_fs_persist(s.config.network, desc, &s.config.datadir)?;
+            wollet.set_birthday(r.birthday);
             s.wollets.insert(&r.name, wollet)?;
+            let loaded = s.wollets.iter().count() as f64;
+            s.metrics.set(consts::METRIC_WALLETS_LOADED, loaded);
 
             s.persist(&request)?;
 
@@ -332,11 +789,7 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         }
         Method::WalletUnload => {
             let r: request::WalletUnload = serde_json::from_value(params)?;
-            let mut s = state.lock()?;
-            let removed = s.wollets.remove(&r.name)?;
-            s.tx_memos.remove(&r.name);
-            s.addr_memos.remove(&r.name);
-            s.persist_all()?;
+            let removed = state.lock()?.unload_wollet(&r.name)?;
 
             Response::result(
                 request.id,
@@ -348,6 +801,34 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 })?,
             )
         }
+        Method::WalletForget => {
+            let r: request::WalletForget = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let removed = s.unload_wollet(&r.name)?;
+            let descriptor = removed.wollet_descriptor();
+            let forgotten = response::Wallet {
+                name: r.name,
+                descriptor: removed.descriptor().to_string(),
+            };
+            drop(removed); // Nothing is written in the cache anymore
+
+            let mut deleted_cache = None;
+            if r.delete_cache {
+                let path = FsPersister::cache_dir(&s.config.datadir, s.config.network, &descriptor);
+                if path.exists() {
+                    std::fs::remove_dir_all(&path)?;
+                    deleted_cache = Some(path.display().to_string());
+                }
+            }
+
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletForget {
+                    forgotten,
+                    deleted_cache,
+                })?,
+            )
+        }
         Method::WalletList => {
             let s = state.lock()?;
             let wallets = s
@@ -398,17 +879,57 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             let mut s = state.lock()?;
             let removed = s.signers.remove(&r.name)?;
             let signer: response::Signer = signer_response_from(&r.name, &removed)?;
+            s.totps.remove(&r.name);
             s.persist_all()?;
             Response::result(
                 request.id,
                 serde_json::to_value(response::SignerUnload { unloaded: signer })?,
             )
         }
+        Method::SignerEnrollTotp => {
+            let r: request::SignerEnrollTotp = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let signer_persisted = s.signers.get(&r.name)?.is_persisted();
+            // Replacing an enrolled secret needs one of its codes, otherwise the API token would
+            // be enough to remove the protection. The state is replayed without codes.
+            let replaying = !s.do_persist;
+            if let Some(current) = s.totps.get_mut(&r.name) {
+                if !replaying {
+                    let code = r
+                        .totp
+                        .as_deref()
+                        .ok_or_else(|| Error::TotpRequired(r.name.clone()))?;
+                    if !current.verify(code) {
+                        return Err(Error::TotpInvalid(r.name));
+                    }
+                }
+            }
+            let totp = match r.secret.as_deref() {
+                Some(secret) => Totp::from_base32(secret, r.threshold)?,
+                None => Totp::random(r.threshold),
+            };
+            let response = response::SignerTotp {
+                name: r.name.clone(),
+                secret: totp.secret(),
+                uri: totp.uri(&r.name),
+                threshold: totp.threshold,
+            };
+            let persisted = totp_request(&r.name, &totp)?;
+            s.totps.insert(&r.name, totp);
+            if signer_persisted {
+                s.persist(persisted)?;
+            }
+            Response::result(request.id, serde_json::to_value(response)?)
+        }
         Method::SignerDetails => {
             let r: request::SignerDetails = serde_json::from_value(params)?;
             let s = state.lock()?;
             let signer = s.signers.get(&r.name)?;
-            let details = signer_details(&r.name, signer)?;
+            let mut details = signer_details(&r.name, signer)?;
+            if s.totps.get(&r.name).is_some() {
+                // Otherwise the API token would be enough to get the mnemonic and sign elsewhere
+                details.mnemonic = None;
+            }
             Response::result(request.id, serde_json::to_value(details)?)
         }
         Method::SignerList => {
@@ -511,6 +1032,7 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 request.id,
                 serde_json::to_value(response::WalletAddress {
                     address: address.to_string(),
+                    unconfidential_address: addr.unconfidential().to_string(),
                     index: addr.index(),
                     memo,
                     text_qr,
@@ -518,26 +1040,136 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 })?,
             )
         }
+        Method::WalletAddresses => {
+            let r: request::WalletAddresses = serde_json::from_value(params)?;
+            if r.count > consts::MAX_ADDRESSES {
+                return Err(Error::Generic(format!(
+                    "At most {} addresses can be requested",
+                    consts::MAX_ADDRESSES
+                )));
+            }
+            let end = r
+                .start
+                .checked_add(r.count)
+                .filter(|end| *end <= 1 << 31)
+                .ok_or_else(|| Error::Generic("Derivation index out of range".into()))?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            let addresses = (r.start..end)
+                .map(|index| {
+                    let addr = match r.chain.as_str() {
+                        "external" => wollet.address(Some(index))?,
+                        "internal" => wollet.change(Some(index))?,
+                        _ => {
+                            return Err(Error::Generic(
+                                "Chain must be \"external\" or \"internal\"".into(),
+                            ))
+                        }
+                    };
+                    Ok(response::DerivedAddress {
+                        address: addr.address().to_string(),
+                        unconfidential_address: addr.unconfidential().to_string(),
+                        index,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletAddresses { addresses })?,
+            )
+        }
+        Method::WalletAddressInfo => {
+            let r: request::WalletAddressInfo = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            let address = lwk_wollet::elements::Address::from_str(&r.address)?;
+            let info = wollet.is_mine(&address.script_pubkey())?.filter(|i| {
+                address.blinding_pubkey.is_none()
+                    || address.blinding_pubkey == i.address().blinding_pubkey
+            });
+            let chain = |c| match c {
+                lwk_wollet::Chain::External => "external".to_string(),
+                lwk_wollet::Chain::Internal => "internal".to_string(),
+            };
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletAddressInfo {
+                    is_mine: info.is_some(),
+                    chain: info.as_ref().map(|i| chain(i.chain())),
+                    index: info.as_ref().map(|i| i.index()),
+                    confidential_address: info.as_ref().map(|i| i.address().to_string()),
+                    unconfidential_address: info.as_ref().map(|i| i.unconfidential().to_string()),
+                })?,
+            )
+        }
+        Method::WalletVerifyPayment => {
+            let r: request::WalletVerifyPayment = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            let txid = Txid::from_str(&r.txid)?;
+            let address = Address::from_str(&r.address)?;
+            let asset = AssetId::from_str(&r.asset)?;
+            let min_confirmations = r
+                .min_confirmations
+                .unwrap_or(consts::DEFAULT_MIN_CONFIRMATIONS);
+            let v = wollet.verify_payment(
+                &txid,
+                r.vout,
+                &address,
+                asset,
+                r.satoshi,
+                min_confirmations,
+            )?;
+            let message = v.message();
+            let (signature, signature_pubkey) = match r.signer.as_ref() {
+                Some(name) => {
+                    let (signature, pubkey) = s.signers.get(name)?.sign_attestation(&message)?;
+                    (Some(signature.to_base64()), Some(pubkey.to_string()))
+                }
+                None => (None, None),
+            };
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletVerifyPayment {
+                    verified: v.is_verified(),
+                    mismatch: v.mismatch.map(|m| m.to_string()),
+                    wallet_id: v.wallet_id,
+                    min_confirmations: v.min_confirmations,
+                    height: v.height,
+                    confirmations: v.confirmations,
+                    tip_height: v.tip_height,
+                    tip_hash: v.tip_hash.to_string(),
+                    message,
+                    signature,
+                    signature_pubkey,
+                })?,
+            )
+        }
         Method::WalletBalance => {
             let r: request::WalletBalance = serde_json::from_value(params)?;
             let mut s = state.lock()?;
             let wollet = s.wollets.get_mut(&r.name)?;
-            let mut balance = wollet
-                .balance()?
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v as i64))
+            let sats = wollet.balance()?;
+            let mut balance = sats
+                .iter()
+                .map(|(k, v)| (k.to_string(), *v as i64))
                 .collect();
+            let mut display = None;
             if r.with_tickers {
                 balance = s.replace_id_with_ticker(balance);
+                display = Some(s.display_balance(sats.into_iter().map(|(k, v)| (k, v as i64))));
             }
             Response::result(
                 request.id,
-                serde_json::to_value(response::WalletBalance { balance })?,
+                serde_json::to_value(response::WalletBalance { balance, display })?,
             )
         }
         Method::WalletSendMany => {
             let r: request::WalletSendMany = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            s.config.limits.check_addressees(r.addressees.len())?;
+            let ct_discount = s.config.ct_discount;
+            let default_fee_rate = s.config.default_fee_rate;
             let wollet: &mut Wollet = s.wollets.get_mut(&r.name)?;
 
             let recipients: Vec<_> = r
@@ -545,10 +1177,9 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 .into_iter()
                 .map(unvalidated_addressee)
                 .collect();
-            let builder = wollet
-                .tx_builder()
+            let builder = tx_builder(wollet, ct_discount)
                 .set_unvalidated_recipients(&recipients)?
-                .fee_rate(r.fee_rate);
+                .fee_rate(r.fee_rate.or(default_fee_rate));
             let mut tx = builder.finish()?;
 
             add_contracts(&mut tx, s.assets.iter());
@@ -562,14 +1193,15 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::WalletDrain => {
             let r: request::WalletDrain = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let ct_discount = s.config.ct_discount;
+            let default_fee_rate = s.config.default_fee_rate;
             let wollet: &mut Wollet = s.wollets.get_mut(&r.name)?;
 
             let address = Address::from_str(&r.address)?;
-            let mut tx = wollet
-                .tx_builder()
+            let mut tx = tx_builder(wollet, ct_discount)
                 .drain_lbtc_wallet()
                 .drain_lbtc_to(address)
-                .fee_rate(r.fee_rate)
+                .fee_rate(r.fee_rate.or(default_fee_rate))
                 .finish()?;
 
             add_contracts(&mut tx, s.assets.iter());
@@ -580,6 +1212,22 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 })?,
             )
         }
+        Method::WalletBumpFee => {
+            let r: request::WalletBumpFee = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let wollet: &mut Wollet = s.wollets.get_mut(&r.name)?;
+
+            let txid = Txid::from_str(&r.txid)?;
+            let mut tx = wollet.bump_fee(&txid, r.fee_rate)?;
+
+            add_contracts(&mut tx, s.assets.iter());
+            Response::result(
+                request.id,
+                serde_json::to_value(response::Pset {
+                    pset: tx.to_string(),
+                })?,
+            )
+        }
         Method::SignerSinglesigDescriptor => {
             let r: request::SignerSinglesigDescriptor = serde_json::from_value(params)?;
             let mut s = state.lock()?;
@@ -673,13 +1321,44 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::SignerSign => {
             let r: request::SignerSign = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let limits = s.config.limits;
+
+            let fingerprint = s.get_available_signer(&r.name)?.fingerprint()?;
+
+            let mut pset = parse_pset(&limits, &r.pset)?;
+
+            let day = policy::today();
+            let spends = s.check_policies(&pset, fingerprint, day)?;
+
+            let mut totp_spend = None;
+            if s.totps.get(&r.name).is_some() {
+                totp_spend = s.totp_free_spend(&r.name, &pset, fingerprint, day)?;
+                if totp_spend.is_none() {
+                    let code = r
+                        .totp
+                        .as_deref()
+                        .ok_or_else(|| Error::TotpRequired(r.name.clone()))?;
+                    let totp = s.totps.get_mut(&r.name).expect("checked above");
+                    if !totp.verify(code) {
+                        return Err(Error::TotpInvalid(r.name));
+                    }
+                }
+            }
 
             let signer = s.get_available_signer(&r.name)?;
+            signer.sign(&mut pset)?;
 
-            let mut pset =
-                PartiallySignedTransaction::from_str(&r.pset).map_err(|e| e.to_string())?;
+            if let Some((txid, spent)) = totp_spend {
+                s.totp_counters.record(&r.name, day, txid, &spent);
+                s.totp_counters.save()?;
+            }
 
-            signer.sign(&mut pset)?;
+            if !spends.is_empty() {
+                for (name, txid, spent) in spends {
+                    s.spend_counters.record(&name, day, txid, &spent);
+                }
+                s.spend_counters.save()?;
+            }
 
             // TODO we may want to return other details such as if signatures have been added
 
@@ -693,15 +1372,33 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::WalletBroadcast => {
             let r: request::WalletBroadcast = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let limits = s.config.limits;
 
             let wollet = s.wollets.get_mut(&r.name)?;
-            let mut pset =
-                PartiallySignedTransaction::from_str(&r.pset).map_err(|e| e.to_string())?;
+            let mut pset = parse_pset(&limits, &r.pset)?;
             let tx = wollet.finalize(&mut pset)?;
             let electrum_client = s.config.electrum_client()?;
 
             if !r.dry_run {
-                electrum_client.broadcast(&tx)?;
+                if let Err(e) = electrum_client.broadcast(&tx) {
+                    s.metrics.inc(consts::METRIC_ELECTRUM_FAILURES);
+                    return Err(e.into());
+                }
+                s.metrics.inc(consts::METRIC_BROADCASTS);
+
+                // Rebroadcast in case the transaction is dropped from the mempools
+                let params = request::WalletOutboxInsert {
+                    name: r.name.clone(),
+                    tx: serialize(&tx).to_hex(),
+                };
+                s.outbox.insert(&r.name, tx.clone());
+                s.persist(Request {
+                    jsonrpc: "2.0".into(),
+                    id: None,
+                    method: Method::WalletOutboxInsert.to_string(),
+                    params: Some(serde_json::to_value(params)?),
+                    tenant: None,
+                })?;
             }
 
             Response::result(
@@ -711,6 +1408,35 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 })?,
             )
         }
+        Method::WalletOutbox => {
+            let r: request::WalletOutbox = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            s.wollets.get(&r.name)?;
+            let txids = s
+                .outbox
+                .for_wollet(&r.name)
+                .iter()
+                .map(|tx| tx.txid().to_string())
+                .collect();
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletOutbox { txids })?,
+            )
+        }
+        Method::WalletOutboxInsert => {
+            let r: request::WalletOutboxInsert = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            s.wollets.get(&r.name)?;
+            let bytes = Vec::<u8>::from_hex(&r.tx).map_err(|e| e.to_string())?;
+            let tx: Transaction = deserialize(&bytes).map_err(|e| e.to_string())?;
+            let txid = tx.txid().to_string();
+            s.outbox.insert(&r.name, tx);
+            s.persist(&request)?;
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletBroadcast { txid })?,
+            )
+        }
         Method::WalletDetails => {
             let r: request::WalletDetails = serde_json::from_value(params)?;
             let mut s = state.lock()?;
@@ -728,7 +1454,9 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                             }
                             _ => response::WalletType::Unknown,
                         },
-                        _ => response::WalletType::Unknown,
+                        WshInner::SortedMulti(smv) => {
+                            response::WalletType::WshSortedMulti(smv.k, smv.pks.len())
+                        }
                     },
                     _ => response::WalletType::Unknown,
                 },
@@ -743,6 +1471,24 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             if !has_unique_fingerprints {
                 warnings.push("wallet has multiple signers with the same fingerprint".into());
             }
+            let descriptor_warnings: Vec<_> = wollet
+                .wollet_descriptor()
+                .validate_strict()
+                .iter()
+                .map(|w| response::DescriptorWarning {
+                    kind: w.kind().to_string(),
+                    key: w.key().map(ToString::to_string),
+                    message: w.to_string(),
+                })
+                .collect();
+            warnings.extend(descriptor_warnings.iter().map(|w| w.message.clone()));
+            let unblindable_outputs = unblindable_outputs(wollet)?;
+            if !unblindable_outputs.is_empty() {
+                warnings.push(format!(
+                    "wallet has {} outputs that cannot be unblinded, their funds are not in the balance",
+                    unblindable_outputs.len()
+                ));
+            }
 
             let signers: Vec<_> = wollet
                 .signers()
@@ -763,17 +1509,50 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                     type_: type_.to_string(),
                     signers,
                     warnings: warnings.join(", "),
+                    descriptor_warnings,
+                    unblindable_outputs,
+                })?,
+            )
+        }
+        Method::WalletRetryUnblind => {
+            let r: request::WalletRetryUnblind = serde_json::from_value(params)?;
+            let blinding_keys = r
+                .blinding_keys
+                .iter()
+                .map(|key| {
+                    lwk_wollet::elements::secp256k1_zkp::SecretKey::from_str(key)
+                        .map_err(|e| Error::Generic(format!("Invalid blinding key: {e}")))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut s = state.lock()?;
+            let wollet = s.wollets.get_mut(&r.name)?;
+            // The wallet cache persists the unblinded outputs, the request with the keys is not
+            // persisted
+            let unblinded = wollet
+                .retry_unblind(&blinding_keys)?
+                .into_iter()
+                .map(|outpoint| response::UnblindedOutput {
+                    txid: outpoint.txid.to_string(),
+                    vout: outpoint.vout,
+                })
+                .collect();
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletRetryUnblind {
+                    unblinded,
+                    unblindable_outputs: unblindable_outputs(wollet)?,
                 })?,
             )
         }
         Method::WalletCombine => {
             let r: request::WalletCombine = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let limits = s.config.limits;
             let wollet = s.wollets.get_mut(&r.name)?;
 
             let mut psets = vec![];
             for pset in r.pset {
-                psets.push(PartiallySignedTransaction::from_str(&pset).map_err(|e| e.to_string())?);
+                psets.push(parse_pset(&limits, &pset)?);
             }
             let pset = wollet.combine(&psets)?;
             Response::result(
@@ -786,9 +1565,10 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::WalletPsetDetails => {
             let r: request::WalletPsetDetails = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let limits = s.config.limits;
             let wollet = s.wollets.get_mut(&r.name)?;
 
-            let pset = PartiallySignedTransaction::from_str(&r.pset).map_err(|e| e.to_string())?;
+            let pset = parse_pset(&limits, &r.pset)?;
             let details = wollet.get_details(&pset)?;
             let mut warnings = vec![];
             let has_signatures_from = details
@@ -872,13 +1652,13 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::WalletTxs => {
             let r: request::WalletTxs = serde_json::from_value(params)?;
             let mut s = state.lock()?;
-            let explorer_url = s.config.explorer_url.clone();
+            let explorer = s.config.explorer();
             let memos = s.tx_memos.for_wollet(&r.name);
             let wollet = s.wollets.get_mut(&r.name)?;
             let mut txs: Vec<response::Tx> = wollet
                 .transactions()?
                 .iter()
-                .map(|tx| convert_tx(tx, &explorer_url, &memos))
+                .map(|tx| convert_tx(tx, explorer.as_ref(), &memos))
                 .collect();
             if r.with_tickers {
                 for tx in &mut txs {
@@ -890,6 +1670,26 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 serde_json::to_value(response::WalletTxs { txs })?,
             )
         }
+        Method::WalletExportHistory => {
+            let r: request::WalletExportHistory = serde_json::from_value(params)?;
+            let format: HistoryFormat = r.format.parse()?;
+            let mut s = state.lock()?;
+            let memos = s.tx_memos.for_wollet(&r.name);
+            let wollet = s.wollets.get_mut(&r.name)?;
+            let txs = wollet.transactions()?;
+            let policy_asset = wollet.policy_asset();
+            let asset_info = |asset_id: &AssetId| {
+                s.get_asset(asset_id)
+                    .ok()
+                    .map(|a| (a.ticker(), a.precision()))
+            };
+            let entries = history_entries(&txs, policy_asset, asset_info, &memos)?;
+            let content = export_history(&entries, format)?;
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletExportHistory { content })?,
+            )
+        }
         Method::WalletTx => {
             let r: request::WalletTx = serde_json::from_value(params)?;
             let mut s = state.lock()?;
@@ -929,22 +1729,120 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             s.persist(&request)?;
             Response::result(request.id, serde_json::to_value(response::Empty {})?)
         }
-        Method::WalletIssue => {
-            let r: request::WalletIssue = serde_json::from_value(params)?;
+        Method::WalletSetPolicy => {
+            let r: request::WalletSetPolicy = serde_json::from_value(params)?;
             let mut s = state.lock()?;
-            let wollet = s.wollets.get_mut(&r.name)?;
-            let tx = wollet
-                .tx_builder()
-                .issue_asset(
-                    r.satoshi_asset,
-                    r.address_asset.map(|a| Address::from_str(&a)).transpose()?,
-                    r.satoshi_token,
-                    r.address_token.map(|a| Address::from_str(&a)).transpose()?,
-                    r.contract
-                        .map(|c| lwk_wollet::Contract::from_str(&c))
+            // Make sure the wallet exists
+            let _wollet = s.wollets.get(&r.name)?;
+            let policy_asset = s.config.network.policy_asset();
+            let mut daily_limits = r
+                .asset_daily_limits
+                .iter()
+                .map(|(asset, limit)| Ok((AssetId::from_str(asset)?, *limit)))
+                .collect::<Result<BTreeMap<_, _>, Error>>()?;
+            if let Some(limit) = r.daily_limit {
+                daily_limits.insert(policy_asset, limit);
+            }
+            let policy = Policy {
+                daily_limits,
+                allowlist: r
+                    .allowlist
+                    .iter()
+                    .map(|a| Address::from_str(a))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| Error::Generic(e.to_string()))?,
+                required_cosigners: r
+                    .required_cosigners
+                    .iter()
+                    .map(|f| Fingerprint::from_str(f))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| Error::Generic(e.to_string()))?,
+            };
+            s.check_policy_change(&r.name, &policy)?;
+            s.policies.set(&r.name, policy);
+            s.persist(&request)?;
+            Response::result(
+                request.id,
+                serde_json::to_value(policy_status(&s, &r.name))?,
+            )
+        }
+        Method::WalletPolicyStatus => {
+            let r: request::WalletPolicyStatus = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let _wollet = s.wollets.get(&r.name)?;
+            Response::result(
+                request.id,
+                serde_json::to_value(policy_status(&s, &r.name))?,
+            )
+        }
+        Method::WalletBackup => {
+            let r: request::WalletBackup = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            let mut backup = wollet.export_backup();
+            backup.tx_memos = s.tx_memos.for_wollet(&r.name).into_iter().collect();
+            backup.addr_memos = s
+                .addr_memos
+                .for_wollet(&r.name)
+                .into_iter()
+                .map(|(a, m)| (a.to_string(), m))
+                .collect();
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletBackup {
+                    backup: backup.to_string(),
+                })?,
+            )
+        }
+        Method::WalletRestore => {
+            let r: request::WalletRestore = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+
+            let backup: WolletBackup = r.backup.parse()?;
+            if backup.network != s.config.network {
+                return Err(Error::Generic("Backup is for the wrong network".into()));
+            }
+            let desc = backup.descriptor()?;
+            let descriptor = desc.to_string();
+            s.config.limits.check_wallets(s.wollets.iter().count())?;
+            let mut wollet = Wollet::with_fs_persist(s.config.network, desc, &s.config.datadir)?;
+            wollet.set_birthday(backup.birthday);
+            s.wollets.insert(&r.name, wollet)?;
+            for (txid, memo) in backup.tx_memos.iter() {
+                s.tx_memos.set(&r.name, txid, memo)?;
+            }
+            for (address, memo) in backup.addr_memos.iter() {
+                let address =
+                    Address::from_str(address).map_err(|e| Error::Generic(e.to_string()))?;
+                s.addr_memos.set(&r.name, &address, memo)?;
+            }
+            s.persist_all()?;
+
+            Response::result(
+                request.id,
+                serde_json::to_value(response::Wallet {
+                    descriptor,
+                    name: r.name,
+                })?,
+            )
+        }
+        Method::WalletIssue => {
+            let r: request::WalletIssue = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let ct_discount = s.config.ct_discount;
+            let default_fee_rate = s.config.default_fee_rate;
+            let wollet = s.wollets.get_mut(&r.name)?;
+            let tx = tx_builder(wollet, ct_discount)
+                .issue_asset(
+                    r.satoshi_asset,
+                    r.address_asset.map(|a| Address::from_str(&a)).transpose()?,
+                    r.satoshi_token,
+                    r.address_token.map(|a| Address::from_str(&a)).transpose()?,
+                    r.contract
+                        .map(|c| lwk_wollet::Contract::from_str(&c))
                         .transpose()?,
                 )?
-                .fee_rate(r.fee_rate)
+                .fee_rate(r.fee_rate.or(default_fee_rate))
                 .finish()?;
             Response::result(
                 request.id,
@@ -956,19 +1854,20 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::WalletReissue => {
             let r: request::WalletReissue = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let ct_discount = s.config.ct_discount;
+            let default_fee_rate = s.config.default_fee_rate;
             let asset_id = AssetId::from_str(&r.asset)?;
             let issuance_tx = s.get_issuance_tx(&asset_id);
             let wollet = s.wollets.get_mut(&r.name)?;
 
-            let mut pset = wollet
-                .tx_builder()
+            let mut pset = tx_builder(wollet, ct_discount)
                 .reissue_asset(
                     asset_id,
                     r.satoshi_asset,
                     r.address_asset.map(|a| Address::from_str(&a)).transpose()?,
                     issuance_tx,
                 )?
-                .fee_rate(r.fee_rate)
+                .fee_rate(r.fee_rate.or(default_fee_rate))
                 .finish()?;
 
             add_contracts(&mut pset, s.assets.iter());
@@ -982,13 +1881,14 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::WalletBurn => {
             let r: request::WalletBurn = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let ct_discount = s.config.ct_discount;
+            let default_fee_rate = s.config.default_fee_rate;
             let asset_id = AssetId::from_str(&r.asset)?;
             let wollet = s.wollets.get_mut(&r.name)?;
 
-            let mut pset = wollet
-                .tx_builder()
+            let mut pset = tx_builder(wollet, ct_discount)
                 .add_burn(r.satoshi_asset, asset_id)?
-                .fee_rate(r.fee_rate)
+                .fee_rate(r.fee_rate.or(default_fee_rate))
                 .finish()?;
 
             add_contracts(&mut pset, s.assets.iter());
@@ -1088,7 +1988,7 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 let s = state.lock()?;
                 (s.config.jade_network(), Some(s.config.timeout))
             };
-            log::debug!("jade network: {}", network);
+            tracing::debug!("jade network: {}", network);
 
             let jade = match r.emulator {
                 Some(emulator) => Jade::from_socket(emulator, network)?,
@@ -1120,6 +2020,9 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             Response::result(request.id, serde_json::to_value(response::Empty {})?)
         }
         Method::Stop => {
+            if let Some(tenant) = request.tenant {
+                return Err(Error::MethodNotAllowedForTenant(tenant));
+            }
             return Err(Error::Stop);
         }
         Method::AssetPublish => {
@@ -1131,14 +2034,17 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             if let AppAsset::RegistryAsset(asset) = asset {
                 let client = reqwest::blocking::Client::new();
                 let url = &s.config.registry_url;
-                let contract = asset.contract();
-                let data = serde_json::json!({"asset_id": asset_id, "contract": contract});
-                log::debug!("posting {data:?} as json to {url} ");
+                let data = RegistryPost::new(asset.contract().clone(), asset_id);
+                tracing::debug!("posting {data} as json to {url} ");
                 let response = client.post(url).json(&data).send()?;
                 let mut result = response.text()?;
                 if result.contains("failed verifying linked entity") {
-                    let domain = contract.entity.domain();
-                    result = format!("https://{domain}/.well-known/liquid-asset-proof-{asset_id} must contain the following 'Authorize linking the domain name {domain} to the Liquid asset {asset_id}'");
+                    let proof = data.domain_proof();
+                    result = format!(
+                        "{} must contain the following '{}'",
+                        proof.url(),
+                        proof.content()
+                    );
                 }
                 Response::result(
                     request.id,
@@ -1196,17 +2102,318 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 ));
             }
             let amp2 = Amp2::new_testnet();
-            let pset = PartiallySignedTransaction::from_str(&r.pset).map_err(|e| e.to_string())?;
+            let pset = parse_pset(&s.config.limits, &r.pset)?;
             let pset = amp2.blocking_cosign(&pset)?.pset.to_string();
             Response::result(
                 request.id,
                 serde_json::to_value(response::Amp2Cosign { pset })?,
             )
         }
+        Method::AccountNew => {
+            let r: request::AccountNew = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let is_mainnet = s.config.is_mainnet();
+
+            let blinding_variant = r
+                .descriptor_blinding_key
+                .parse()
+                .map_err(|e: InvalidBlindingKeyVariant| e.to_string())?;
+
+            let fingerprint = s.get_available_signer(&r.signer)?.fingerprint()?;
+            let index = s
+                .wollets
+                .iter()
+                .filter_map(|(_, w)| account_of(w, &fingerprint))
+                .filter(|(kind, _)| *kind == r.account_kind)
+                .map(|(_, index)| index + 1)
+                .max()
+                .unwrap_or(0);
+
+            let signer = s.get_available_signer(&r.signer)?;
+            let descriptor = match r.account_kind.as_str() {
+                "wpkh" | "shwpkh" => {
+                    let script_variant = r
+                        .account_kind
+                        .parse()
+                        .map_err(|e: InvalidSinglesigVariant| e.to_string())?;
+                    singlesig_desc_account(signer, script_variant, blinding_variant, index)?
+                }
+                "wsh" => {
+                    let threshold = r.threshold.ok_or_else(|| {
+                        Error::Generic("Multisig accounts require a threshold".into())
+                    })?;
+                    let mut keyorigin_xpubs = vec![];
+                    let signer_xpub =
+                        signer.keyorigin_xpub_account(Bip::Bip87, is_mainnet, index)?;
+                    for keyorigin_xpub in std::iter::once(&signer_xpub).chain(r.cosigners.iter()) {
+                        keyorigin_xpubs.push(
+                            keyorigin_xpub_from_str(keyorigin_xpub)
+                                .map_err(|e| Error::Generic(e.to_string()))?,
+                        );
+                    }
+                    multisig_desc(threshold, keyorigin_xpubs, Multisig::Wsh, blinding_variant)?
+                }
+                kind => return Err(Error::Generic(format!("Invalid account kind: {kind}"))),
+            };
+
+            let name = r
+                .name
+                .unwrap_or_else(|| format!("{}-{}-{}", r.signer, r.account_kind, index));
+            let desc: WolletDescriptor = descriptor.parse()?;
+            s.config.limits.check_wallets(s.wollets.iter().count())?;
+            let wollet = Wollet::with_fs_persist(s.config.network, desc, &s.config.datadir)?;
+            let balance = wollet
+                .balance()?
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v as i64))
+                .collect();
+            s.wollets.insert(&name, wollet)?;
+
+            // Persist as a wallet load, so that restoring does not depend on the other accounts
+            // and on random blinding keys
+            let params = request::WalletLoad {
+                descriptor: descriptor.clone(),
+                name: name.clone(),
+                birthday: None,
+            };
+            s.persist(Request {
+                jsonrpc: "2.0".into(),
+                id: None,
+                method: Method::WalletLoad.to_string(),
+                params: Some(serde_json::to_value(params)?),
+                tenant: None,
+            })?;
+
+            Response::result(
+                request.id,
+                serde_json::to_value(response::Account {
+                    name,
+                    descriptor,
+                    kind: r.account_kind,
+                    index,
+                    balance,
+                })?,
+            )
+        }
+        Method::AccountList => {
+            let r: request::AccountList = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let fingerprint = s.get_available_signer(&r.signer)?.fingerprint()?;
+
+            let mut accounts = vec![];
+            let mut total: HashMap<AssetId, u64> = HashMap::new();
+            for (name, wollet) in s.wollets.iter() {
+                let (kind, index) = match account_of(wollet, &fingerprint) {
+                    Some(account) => account,
+                    None => continue,
+                };
+                let balance = wollet.balance()?;
+                for (asset, satoshi) in balance.iter() {
+                    *total.entry(*asset).or_default() += satoshi;
+                }
+                accounts.push(response::Account {
+                    name: name.to_string(),
+                    descriptor: wollet.descriptor().to_string(),
+                    kind: kind.to_string(),
+                    index,
+                    balance: balance
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v as i64))
+                        .collect(),
+                });
+            }
+            accounts.sort_by(|a, b| (&a.kind, a.index, &a.name).cmp(&(&b.kind, b.index, &b.name)));
+
+            let mut balance = total
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v as i64))
+                .collect();
+            if r.with_tickers {
+                balance = s.replace_id_with_ticker(balance);
+                for account in accounts.iter_mut() {
+                    account.balance =
+                        s.replace_id_with_ticker(std::mem::take(&mut account.balance));
+                }
+            }
+            Response::result(
+                request.id,
+                serde_json::to_value(response::AccountList { accounts, balance })?,
+            )
+        }
+        Method::TxDecode => {
+            let r: request::TxDecode = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = r.name.as_ref().map(|n| s.wollets.get(n)).transpose()?;
+            let bytes = Vec::<u8>::from_hex(&r.tx).map_err(|e| e.to_string())?;
+            let tx: Transaction = deserialize(&bytes).map_err(|e| e.to_string())?;
+            let decoded = decode_tx(&tx, wollet, s.config.network)?;
+            Response::result(request.id, serde_json::to_value(decoded)?)
+        }
+        Method::TxBroadcast => {
+            let r: request::TxBroadcast = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let tx = match (r.tx, r.pset) {
+                (Some(tx), None) => {
+                    let bytes = Vec::<u8>::from_hex(&tx).map_err(|e| e.to_string())?;
+                    deserialize::<Transaction>(&bytes).map_err(|e| e.to_string())?
+                }
+                (None, Some(pset)) => {
+                    let mut pset = parse_pset(&s.config.limits, &pset)?;
+                    lwk_wollet::finalize_pset(&mut pset)?
+                }
+                _ => {
+                    return Err(Error::Generic(
+                        "Exactly one of tx and pset is required".into(),
+                    ))
+                }
+            };
+
+            let mut mempool_accepted = None;
+            if r.dry_run {
+                if let Some(client) = s.config.elements_rpc_client()? {
+                    client.test_mempool_accept(&tx)?;
+                    mempool_accepted = Some(true);
+                }
+            } else {
+                let electrum_client = s.config.electrum_client()?;
+                if let Err(e) = electrum_client.broadcast(&tx) {
+                    s.metrics.inc(consts::METRIC_ELECTRUM_FAILURES);
+                    return Err(e.into());
+                }
+                s.metrics.inc(consts::METRIC_BROADCASTS);
+            }
+
+            Response::result(
+                request.id,
+                serde_json::to_value(response::TxBroadcast {
+                    txid: tx.txid().to_string(),
+                    broadcast: !r.dry_run,
+                    mempool_accepted,
+                })?,
+            )
+        }
+        Method::PsetDecode => {
+            let r: request::PsetDecode = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = r.name.as_ref().map(|n| s.wollets.get(n)).transpose()?;
+            let pset = parse_pset(&s.config.limits, &r.pset)?;
+            let decoded = decode_pset(&pset, wollet, s.config.network)?;
+            Response::result(request.id, serde_json::to_value(decoded)?)
+        }
+        Method::PsetSessionSave => {
+            let r: request::PsetSessionSave = serde_json::from_value(params)?;
+            let limits = state.lock()?.config.limits;
+            let pset = parse_pset(&limits, &r.pset)?;
+            let session = match r.session {
+                Some(session) => {
+                    limits.check_pset_size(&session)?;
+                    let mut session = PsetSession::from_str(&session)?;
+                    session.add_pset(pset)?;
+                    session
+                }
+                None => {
+                    let threshold = r
+                        .threshold
+                        .ok_or_else(|| "Threshold is required to create a session".to_string())?;
+                    let participants = if r.participants.is_empty() {
+                        pset.inputs()
+                            .iter()
+                            .flat_map(|i| i.bip32_derivation.values().map(|(f, _)| *f))
+                            .collect()
+                    } else {
+                        r.participants
+                            .iter()
+                            .map(|f| Fingerprint::from_str(f))
+                            .collect::<Result<_, _>>()
+                            .map_err(|e| e.to_string())?
+                    };
+                    PsetSession::new(pset, participants, threshold as usize)?
+                }
+            };
+            Response::result(request.id, serde_json::to_value(convert_session(&session))?)
+        }
+        Method::PsetSessionLoad => {
+            let r: request::PsetSessionLoad = serde_json::from_value(params)?;
+            state.lock()?.config.limits.check_pset_size(&r.session)?;
+            let session = PsetSession::from_str(&r.session)?;
+            Response::result(request.id, serde_json::to_value(convert_session(&session))?)
+        }
+        Method::PsetSessionMerge => {
+            let r: request::PsetSessionMerge = serde_json::from_value(params)?;
+            let limits = state.lock()?.config.limits;
+            for session in r.sessions.iter() {
+                limits.check_pset_size(session)?;
+            }
+            let mut sessions = r.sessions.iter().map(|s| PsetSession::from_str(s));
+            let mut session = sessions
+                .next()
+                .ok_or_else(|| "At least a session is required".to_string())??;
+            for other in sessions {
+                session.merge(other?)?;
+            }
+            Response::result(request.id, serde_json::to_value(convert_session(&session))?)
+        }
     };
     Ok(response)
 }
 
+/// The spending policy of the wallet `name` and its usage in the current day
+fn policy_status(s: &State, name: &str) -> response::WalletPolicyStatus {
+    let policy_asset = s.config.network.policy_asset();
+    let policy = s.policies.get(name).cloned().unwrap_or_default();
+    let mut asset_spent_today = s.spend_counters.spent(name, policy::today());
+    let spent_today = asset_spent_today.remove(&policy_asset).unwrap_or(0);
+    let daily_limit = policy.daily_limit(&policy_asset);
+    response::WalletPolicyStatus {
+        name: name.to_string(),
+        daily_limit,
+        spent_today,
+        remaining_today: daily_limit.map(|limit| limit.saturating_sub(spent_today)),
+        asset_daily_limits: policy
+            .daily_limits
+            .iter()
+            .filter(|(asset, _)| **asset != policy_asset)
+            .map(|(asset, limit)| (asset.to_string(), *limit))
+            .collect(),
+        asset_spent_today: asset_spent_today
+            .iter()
+            .map(|(asset, spent)| (asset.to_string(), *spent))
+            .collect(),
+        allowlist: policy.allowlist.iter().map(|a| a.to_string()).collect(),
+        required_cosigners: policy
+            .required_cosigners
+            .iter()
+            .map(|f| f.to_string())
+            .collect(),
+    }
+}
+
+/// Parse a PSET checking its size first
+fn parse_pset(limits: &Limits, pset: &str) -> Result<PartiallySignedTransaction, Error> {
+    limits.check_pset_size(pset)?;
+    Ok(PartiallySignedTransaction::from_str(pset).map_err(|e| e.to_string())?)
+}
+
+fn convert_session(session: &PsetSession) -> response::PsetSession {
+    response::PsetSession {
+        session: session.to_string(),
+        pset: session.pset().to_string(),
+        state: session.state().to_string(),
+        participants: session
+            .participants()
+            .iter()
+            .map(|f| f.to_string())
+            .collect(),
+        threshold: session.threshold() as u32,
+        signatures: session
+            .signatures()
+            .iter()
+            .map(|(f, idxs)| (f.to_string(), idxs.iter().cloned().collect()))
+            .collect(),
+        missing: session.missing().iter().map(|f| f.to_string()).collect(),
+    }
+}
+
 fn scan(state: &Arc<Mutex<State>>) -> Result<(), Error> {
     let required_scan_loops = {
         let mut s = state.lock()?;
@@ -1227,6 +2434,17 @@ fn scan(state: &Arc<Mutex<State>>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Returns a transaction builder for `wollet`, using discounted fees for Confidential
+/// Transactions only if `ct_discount` is true
+fn tx_builder(wollet: &Wollet, ct_discount: bool) -> WolletTxBuilder {
+    let builder = wollet.tx_builder();
+    if ct_discount {
+        builder
+    } else {
+        builder.disable_ct_discount()
+    }
+}
+
 fn unvalidated_addressee(a: request::UnvalidatedAddressee) -> lwk_wollet::UnvalidatedRecipient {
     lwk_wollet::UnvalidatedRecipient {
         satoshi: a.satoshi,
@@ -1271,6 +2489,19 @@ fn add_contracts<'a>(
     }
 }
 
+fn unblindable_outputs(wollet: &Wollet) -> Result<Vec<response::UnblindableOutput>, Error> {
+    Ok(wollet
+        .unblindable_outputs()?
+        .into_iter()
+        .map(|o| response::UnblindableOutput {
+            txid: o.outpoint.txid.to_string(),
+            vout: o.outpoint.vout,
+            height: o.height,
+            script_pubkey: o.txout.script_pubkey.to_hex(),
+        })
+        .collect())
+}
+
 fn convert_utxo(u: &lwk_wollet::WalletTxOut) -> response::Utxo {
     response::Utxo {
         txid: u.outpoint.txid.to_string(),
@@ -1285,14 +2516,20 @@ fn convert_utxo(u: &lwk_wollet::WalletTxOut) -> response::Utxo {
 
 fn convert_tx(
     tx: &lwk_wollet::WalletTx,
-    explorer_url: &str,
+    explorer: Option<&lwk_wollet::Explorer>,
     memos: &HashMap<Txid, String>,
 ) -> response::Tx {
-    let unblinded_url = tx.unblinded_url(explorer_url);
+    // Without an explorer only the relative path with the blinding data is returned
+    let unblinded_url = match explorer {
+        Some(explorer) => explorer.unblinded_tx_url(tx),
+        None => tx.unblinded_url(""),
+    };
     let memo = memos.get(&tx.txid).cloned().unwrap_or_default();
     response::Tx {
         txid: tx.txid.to_string(),
         height: tx.height,
+        confirmations: tx.confirmations,
+        status: tx.status.to_string(),
         balance: tx
             .balance
             .iter()
@@ -1306,6 +2543,147 @@ fn convert_tx(
     }
 }
 
+/// Returns the account kind and index if `wollet` is an account of the signer with `fingerprint`
+///
+/// That is if the signer key origin is `purpose'/coin_type'/account'` and the purpose matches the
+/// descriptor type.
+fn account_of(wollet: &Wollet, fingerprint: &Fingerprint) -> Option<(&'static str, u32)> {
+    let (kind, purpose) = match wollet.descriptor().descriptor.desc_type() {
+        DescriptorType::Wpkh => ("wpkh", 84),
+        DescriptorType::ShWpkh => ("shwpkh", 49),
+        DescriptorType::Wsh | DescriptorType::WshSortedMulti => ("wsh", 87),
+        _ => return None,
+    };
+    let mut index = None;
+    wollet.descriptor().descriptor.for_each_key(|k| {
+        let origin = match k {
+            DescriptorPublicKey::XPub(x) => x.origin.as_ref(),
+            DescriptorPublicKey::MultiXPub(x) => x.origin.as_ref(),
+            DescriptorPublicKey::Single(_) => None,
+        };
+        if let Some((f, path)) = origin {
+            if f == fingerprint {
+                if let [ChildNumber::Hardened { index: p }, ChildNumber::Hardened { .. }, ChildNumber::Hardened { index: account }] =
+                    path.as_ref()
+                {
+                    if *p == purpose {
+                        index = Some(*account);
+                    }
+                }
+            }
+        }
+        true
+    });
+    index.map(|i| (kind, i))
+}
+
+/// Returns the asset and the amount of `txout`, if explicit or if it can be unblinded by `wollet`
+fn decode_txout(txout: &TxOut, wollet: Option<&Wollet>) -> (Option<AssetId>, Option<u64>) {
+    if let (Some(asset), Some(value)) = (txout.asset.explicit(), txout.value.explicit()) {
+        return (Some(asset), Some(value));
+    }
+    let descriptor = wollet.map(|w| w.wollet_descriptor());
+    match descriptor.and_then(|d| try_unblind(txout.clone(), &d).ok()) {
+        Some(secrets) => (Some(secrets.asset), Some(secrets.value)),
+        None => (txout.asset.explicit(), txout.value.explicit()),
+    }
+}
+
+fn decode_tx(
+    tx: &Transaction,
+    wollet: Option<&Wollet>,
+    network: ElementsNetwork,
+) -> Result<response::DecodedTx, Error> {
+    let mut inputs = vec![];
+    for txin in tx.input.iter() {
+        let outpoint = txin.previous_output;
+        let prevout = match wollet {
+            Some(wollet) => wollet
+                .transaction(&outpoint.txid)?
+                .and_then(|t| t.tx.output.get(outpoint.vout as usize).cloned()),
+            None => None,
+        };
+        let (asset, satoshi) = match prevout {
+            Some(txout) => decode_txout(&txout, wollet),
+            None => (None, None),
+        };
+        inputs.push(response::DecodedInput {
+            txid: outpoint.txid.to_string(),
+            vout: outpoint.vout,
+            sequence: txin.sequence.to_consensus_u32(),
+            is_pegin: txin.is_pegin,
+            has_issuance: txin.has_issuance(),
+            asset: asset.map(|a| a.to_string()),
+            satoshi,
+        });
+    }
+
+    let outputs = tx
+        .output
+        .iter()
+        .map(|txout| {
+            let (asset, satoshi) = decode_txout(txout, wollet);
+            response::DecodedOutput {
+                script_pubkey: txout.script_pubkey.to_hex(),
+                address: Address::from_script(&txout.script_pubkey, None, network.address_params())
+                    .map(|a| a.to_string()),
+                is_confidential: txout.value.is_confidential(),
+                is_fee: txout.is_fee(),
+                asset: asset.map(|a| a.to_string()),
+                satoshi,
+            }
+        })
+        .collect();
+
+    Ok(response::DecodedTx {
+        txid: tx.txid().to_string(),
+        version: tx.version,
+        lock_time: tx.lock_time.to_consensus_u32(),
+        inputs,
+        outputs,
+        fee: tx.fee_in(network.policy_asset()),
+    })
+}
+
+fn decode_pset(
+    pset: &PartiallySignedTransaction,
+    wollet: Option<&Wollet>,
+    network: ElementsNetwork,
+) -> Result<response::DecodedTx, Error> {
+    let tx = pset.extract_tx().map_err(|e| e.to_string())?;
+    let mut decoded = decode_tx(&tx, wollet, network)?;
+
+    for (input, decoded) in pset.inputs().iter().zip(decoded.inputs.iter_mut()) {
+        let (asset, satoshi) = match (input.asset, input.amount, input.witness_utxo.as_ref()) {
+            (Some(asset), Some(satoshi), _) => (Some(asset), Some(satoshi)),
+            (_, _, Some(txout)) => decode_txout(txout, wollet),
+            _ => continue,
+        };
+        decoded.asset = asset.map(|a| a.to_string()).or(decoded.asset.take());
+        decoded.satoshi = satoshi.or(decoded.satoshi);
+    }
+
+    for (output, decoded) in pset.outputs().iter().zip(decoded.outputs.iter_mut()) {
+        let blinding_pubkey = output.blinding_key.map(|k| k.inner);
+        decoded.address = Address::from_script(
+            &output.script_pubkey,
+            blinding_pubkey,
+            network.address_params(),
+        )
+        .map(|a| a.to_string());
+        decoded.is_confidential |= blinding_pubkey.is_some();
+        decoded.is_fee = output.script_pubkey.is_empty();
+        if let Some(asset) = output.asset {
+            decoded.asset = Some(asset.to_string());
+        }
+        if let Some(satoshi) = output.amount {
+            decoded.satoshi = Some(satoshi);
+        }
+    }
+
+    Ok(decoded)
+}
+
 fn amp2userkey(signer: &AnySigner) -> Result<String, Error> {
     let bip = lwk_common::Bip::Bip87;
     let is_mainnet = false;
@@ -1318,19 +2696,40 @@ mod tests {
 
     use super::*;
 
-    fn app_random_port() -> App {
-        let addr = TcpListener::bind("127.0.0.1:0")
+    fn random_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
             .unwrap()
             .local_addr()
-            .unwrap();
-        let tempdir = tempfile::tempdir().unwrap();
-        let mut config = Config::default_testnet(tempdir.path().to_path_buf());
-        config.addr = addr;
+            .unwrap()
+    }
+
+    /// A regtest config with the datadir in `datadir`, listening on a random port and without a
+    /// reachable electrum server
+    fn regtest_config(datadir: &std::path::Path) -> Config {
+        let mut config = Config::default_regtest(datadir.to_path_buf());
+        config.addr = random_addr();
+        config.electrum_url = "tcp://invalid".into();
+        config
+    }
+
+    fn start_app(config: Config) -> App {
         let mut app = App::new(config).unwrap();
         app.run().unwrap();
         app
     }
 
+    fn stop_app(mut app: App) {
+        app.stop().unwrap();
+        app.join_threads().unwrap();
+    }
+
+    fn app_random_port() -> App {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_testnet(tempdir.path().to_path_buf());
+        config.addr = random_addr();
+        start_app(config)
+    }
+
     #[test]
     fn version() {
         let mut app = app_random_port();
@@ -1345,7 +2744,642 @@ mod tests {
         let actual: response::Version = serde_json::from_str(&result).unwrap();
         assert_eq!(actual.version, consts::APP_VERSION);
 
+        let metrics = reqwest::blocking::get(format!("http://{url}/metrics"))
+            .unwrap()
+            .text()
+            .unwrap();
+        assert!(metrics.contains("lwk_wallets_loaded 0\n"));
+        assert!(metrics.contains("# TYPE lwk_sync_duration_seconds histogram\n"));
+
+        app.stop().unwrap();
+        app.join_threads().unwrap();
+    }
+    #[test]
+    fn batch() {
+        let mut app = app_random_port();
+        let client = Client::new(app.addr()).unwrap();
+
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let params = serde_json::json!({"name": "s", "mnemonic": mnemonic, "persist": false});
+        let results = client
+            .batch(vec![
+                (Method::SignerLoadSoftware, Some(params)),
+                (
+                    Method::SignerDetails,
+                    Some(serde_json::json!({"name": "s"})),
+                ),
+                (
+                    Method::SignerDetails,
+                    Some(serde_json::json!({"name": "x"})),
+                ),
+                (Method::Version, None),
+            ])
+            .unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap()["name"], "s");
+        assert_eq!(results[1].as_ref().unwrap()["name"], "s");
+        assert!(matches!(results[2], Err(Error::RpcError(_))));
+        assert_eq!(results[3].as_ref().unwrap()["version"], consts::APP_VERSION);
+
+        app.stop().unwrap();
+        app.join_threads().unwrap();
+    }
+    #[test]
+    fn server_status() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let status = || {
+            let app = start_app(regtest_config(tempdir.path()));
+            let client = Client::new(app.addr()).unwrap();
+            client.scan().unwrap();
+            let status = client.server_status().unwrap();
+            stop_app(app);
+            status
+        };
+
+        let servers = status().servers;
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].url, "tcp://invalid");
+        assert!(!servers[0].healthy);
+        assert!(servers[0].last_failure.is_some());
+        let failures = servers[0].consecutive_failures;
+        assert!(failures > 0);
+
+        // Persisted across restarts
+        let servers = status().servers;
+        assert!(servers[0].consecutive_failures > failures);
+    }
+
+    #[test]
+    fn wallet_policy() {
+        use lwk_common::{DescriptorBlindingKey, Singlesig};
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let start = || start_app(regtest_config(tempdir.path()));
+
+        let app = start();
+        let client = Client::new(app.addr()).unwrap();
+        let desc = lwk_test_util::TEST_DESCRIPTOR.to_string();
+        client.wallet_load(desc.clone(), "w".into()).unwrap();
+        let status = client.wallet_policy_status("w".into()).unwrap();
+        assert_eq!(status.daily_limit, None);
+        assert_eq!(status.spent_today, 0);
+        assert!(client.wallet_policy_status("x".into()).is_err());
+
+        let address = "el1qq2xvpcvfup5j8zscjq05u2wxxjcyewk7979f3mmz5l7uw5pqmx6xf5xy50hsn6vhkm5euwt72x878eq6zxx2z0z676mna6kdq";
+        let err = client
+            .wallet_set_policy(
+                "w".into(),
+                None,
+                BTreeMap::new(),
+                vec!["invalid".into()],
+                vec![],
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("base58"), "{err}");
+        let status = client
+            .wallet_set_policy(
+                "w".into(),
+                Some(100_000),
+                BTreeMap::new(),
+                vec![address.into()],
+                vec!["11111111".into()],
+            )
+            .unwrap();
+        assert_eq!(status.daily_limit, Some(100_000));
+        assert_eq!(status.remaining_today, Some(100_000));
+        assert_eq!(status.allowlist, vec![address.to_string()]);
+        assert_eq!(status.required_cosigners, vec!["11111111".to_string()]);
+        stop_app(app);
+
+        // Persisted across restarts
+        let app = start();
+        let client = Client::new(app.addr()).unwrap();
+        let restored = client.wallet_policy_status("w".into()).unwrap();
+        assert_eq!(restored, status);
+
+        // Removed with the wallet
+        client.wallet_unload("w".into()).unwrap();
+        client.wallet_load(desc, "w".into()).unwrap();
+        let status = client.wallet_policy_status("w".into()).unwrap();
+        assert_eq!(status.daily_limit, None);
+        assert!(status.allowlist.is_empty());
+
+        // Once set, the policy of a wallet with loaded signers can only be made stricter
+        let mnemonic = lwk_test_util::TEST_MNEMONIC;
+        client
+            .signer_load_software("s".into(), mnemonic.into(), false)
+            .unwrap();
+        let signer = lwk_signer::SwSigner::new(mnemonic, false).unwrap();
+        let desc = singlesig_desc(&signer, Singlesig::Wpkh, DescriptorBlindingKey::Slip77).unwrap();
+        client.wallet_load(desc, "s".into()).unwrap();
+        let asset = "77".repeat(32);
+        let set = |daily_limit, asset_limit| {
+            let asset_daily_limits = BTreeMap::from([(asset.clone(), asset_limit)]);
+            client.wallet_set_policy("s".into(), daily_limit, asset_daily_limits, vec![], vec![])
+        };
+        let status = set(Some(1_000), 10).unwrap();
+        assert_eq!(status.daily_limit, Some(1_000));
+        assert_eq!(status.asset_daily_limits.get(&asset), Some(&10));
+        set(Some(500), 5).unwrap();
+        let err = set(Some(600), 5).unwrap_err();
+        assert!(err.to_string().contains("cannot be removed"), "{err}");
+        let err = set(Some(500), 6).unwrap_err();
+        assert!(err.to_string().contains("cannot be removed"), "{err}");
+        let err = client
+            .wallet_set_policy("s".into(), None, BTreeMap::new(), vec![], vec![])
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot be removed"), "{err}");
+        let err = client.wallet_unload("s".into()).unwrap_err();
+        assert!(err.to_string().contains("cannot be removed"), "{err}");
+        client.signer_unload("s".into()).unwrap();
+        set(None, 100).unwrap();
+        client.wallet_unload("s".into()).unwrap();
+        stop_app(app);
+    }
+
+    #[test]
+    fn wallet_forget() {
+        let mut app = app_random_port();
+        let client = Client::new(app.addr()).unwrap();
+        let datadir = app.config.datadir.clone();
+        let network = app.config.network;
+        let desc = lwk_test_util::TEST_DESCRIPTOR.to_string();
+        let cache_dir = FsPersister::cache_dir(&datadir, network, &desc.parse().unwrap());
+
+        client.wallet_load(desc.clone(), "w".into()).unwrap();
+        assert!(cache_dir.exists());
+        let r = client.wallet_forget("w".into(), false).unwrap();
+        assert_eq!(r.forgotten.name, "w");
+        assert_eq!(r.deleted_cache, None);
+        assert!(cache_dir.exists());
+        assert!(client.wallet_list().unwrap().wallets.is_empty());
+
+        client.wallet_load(desc.clone(), "w".into()).unwrap();
+        let r = client.wallet_forget("w".into(), true).unwrap();
+        assert_eq!(r.forgotten.descriptor, desc);
+        assert_eq!(r.deleted_cache, Some(cache_dir.display().to_string()));
+        assert!(!cache_dir.exists());
+        assert!(client.wallet_forget("w".into(), true).is_err());
+
         app.stop().unwrap();
         app.join_threads().unwrap();
     }
+
+    #[test]
+    fn wallet_verify_payment() {
+        use lwk_wollet::bitcoin::sign_message::{signed_msg_hash, MessageSignature};
+
+        let mut app = app_random_port();
+        let client = Client::new(app.addr()).unwrap();
+        let network = app.config.network;
+        let mnemonic = lwk_test_util::TEST_MNEMONIC;
+        client
+            .signer_load_software("s".into(), mnemonic.into(), false)
+            .unwrap();
+        let desc = lwk_test_util::TEST_DESCRIPTOR;
+        client.wallet_load(desc.into(), "w".into()).unwrap();
+        let wollet = Wollet::without_persist(network, desc.parse().unwrap()).unwrap();
+        let address = wollet.address(Some(0)).unwrap().address().to_string();
+        let txid = "00".repeat(32);
+        let asset = network.policy_asset().to_string();
+        let verify = |signer| {
+            let (txid, address, asset) = (txid.clone(), address.clone(), asset.clone());
+            client.wallet_verify_payment("w".into(), txid, 0, address, asset, 1_000, None, signer)
+        };
+
+        // The transaction was not synced
+        let r = verify(None).unwrap();
+        assert!(!r.verified);
+        assert_eq!(r.mismatch.as_deref(), Some("unknown transaction"));
+        assert_eq!((r.height, r.confirmations), (None, 0));
+        assert_eq!(r.min_confirmations, 1);
+        assert!(r.message.contains(&format!("address: {address}\n")));
+        assert!(r.message.contains(&format!("wallet: {}\n", r.wallet_id)));
+        assert!(r.message.contains(&format!("tip hash: {}\n", r.tip_hash)));
+        assert_eq!(r.signature, None);
+
+        // Signed with the attestation key of the signer
+        let signed = verify(Some("s".into())).unwrap();
+        assert_eq!(signed.message, r.message);
+        let signature = MessageSignature::from_base64(&signed.signature.unwrap()).unwrap();
+        let secp = lwk_wollet::secp256k1::Secp256k1::new();
+        let pubkey = signature
+            .recover_pubkey(&secp, signed_msg_hash(&signed.message))
+            .unwrap();
+        assert_eq!(Some(pubkey.to_string()), signed.signature_pubkey);
+        let path =
+            lwk_wollet::bitcoin::bip32::DerivationPath::from_str(consts::ATTESTATION_PATH).unwrap();
+        let signer = SwSigner::new(mnemonic, false).unwrap();
+        let xpub = signer.derive_xpub(&path).unwrap();
+        assert_eq!(pubkey.inner, xpub.public_key);
+        assert_ne!(pubkey.inner, signer.xpub().public_key);
+
+        let err = verify(Some("x".into())).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{err}");
+
+        app.stop().unwrap();
+        app.join_threads().unwrap();
+    }
+
+    #[test]
+    fn wallet_unblindable_outputs() {
+        use lwk_wollet::blocking::MockBackend;
+        use lwk_wollet::elements::{secp256k1_zkp, Address};
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let config = regtest_config(tempdir.path());
+        let network = config.network;
+
+        // The wallet cache has an output blinded by the sender with another key
+        let desc = lwk_test_util::TEST_DESCRIPTOR;
+        let mut wollet =
+            Wollet::with_fs_persist(network, desc.parse().unwrap(), tempdir.path()).unwrap();
+        let script_pubkey = wollet.address(Some(0)).unwrap().address().script_pubkey();
+        let key = secp256k1_zkp::SecretKey::from_slice(&[7; 32]).unwrap();
+        let secp = secp256k1_zkp::Secp256k1::new();
+        let blinder = secp256k1_zkp::PublicKey::from_secret_key(&secp, &key);
+        let address = Address::from_script(&script_pubkey, Some(blinder), network.address_params());
+        let mut mock = MockBackend::new(network);
+        let txid = mock.fund(&address.unwrap(), 5_000, None).unwrap();
+        mock.sync(&mut wollet).unwrap();
+
+        let app = start_app(config);
+        let client = Client::new(app.addr()).unwrap();
+        client.wallet_load(desc.into(), "w".into()).unwrap();
+        let r = client.wallet_details("w".into()).unwrap();
+        assert!(
+            r.warnings.contains("1 outputs that cannot be unblinded"),
+            "{}",
+            r.warnings
+        );
+        assert_eq!(r.unblindable_outputs.len(), 1);
+        assert_eq!(r.unblindable_outputs[0].txid, txid.to_string());
+        assert_eq!(
+            r.unblindable_outputs[0].script_pubkey,
+            script_pubkey.to_hex()
+        );
+
+        // Retried with the key used by the sender
+        let other = secp256k1_zkp::SecretKey::from_slice(&[8; 32]).unwrap();
+        let retry = |keys: Vec<String>| client.wallet_retry_unblind("w".into(), keys);
+        let r = retry(vec![other.display_secret().to_string()]).unwrap();
+        assert!(r.unblinded.is_empty());
+        assert_eq!(r.unblindable_outputs.len(), 1);
+        let err = retry(vec!["xx".into()]).unwrap_err();
+        assert!(err.to_string().contains("Invalid blinding key"), "{err}");
+        let r = retry(vec![key.display_secret().to_string()]).unwrap();
+        assert_eq!(r.unblinded.len(), 1);
+        assert_eq!(r.unblinded[0].txid, txid.to_string());
+        assert!(r.unblindable_outputs.is_empty());
+        let balance = client.wallet_balance("w".into(), false).unwrap().balance;
+        assert_eq!(balance[&network.policy_asset().to_string()], 5_000);
+
+        stop_app(app);
+    }
+
+    #[test]
+    fn wallet_restore_birthday() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let config = regtest_config(tempdir.path());
+        let app = start_app(config.clone());
+        let client = Client::new(app.addr()).unwrap();
+
+        let backup = WolletBackup {
+            version: WolletBackup::VERSION,
+            network: config.network,
+            descriptor: lwk_test_util::TEST_DESCRIPTOR.to_string(),
+            birthday: Some(42),
+            tx_memos: Default::default(),
+            addr_memos: Default::default(),
+        };
+        client
+            .wallet_restore("w".into(), backup.to_string())
+            .unwrap();
+        let r = client.wallet_backup("w".into()).unwrap();
+        let restored: WolletBackup = r.backup.parse().unwrap();
+        assert_eq!(restored.birthday, Some(42));
+        stop_app(app);
+
+        // The birthday is kept after a restart
+        let app = start_app(config);
+        let client = Client::new(app.addr()).unwrap();
+        let r = client.wallet_backup("w".into()).unwrap();
+        let restored: WolletBackup = r.backup.parse().unwrap();
+        assert_eq!(restored.birthday, Some(42));
+        stop_app(app);
+    }
+
+    #[test]
+    fn tx_broadcast() {
+        use lwk_common::{singlesig_desc, DescriptorBlindingKey, Signer, Singlesig};
+        use lwk_wollet::blocking::MockBackend;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let config = regtest_config(tempdir.path());
+        let app = start_app(config);
+        let client = Client::new(app.addr()).unwrap();
+
+        // A signed PSET spending from a wallet not loaded in the server
+        let mnemonic = lwk_test_util::TEST_MNEMONIC;
+        let signer = SwSigner::new(mnemonic, false).unwrap();
+        let desc = singlesig_desc(&signer, Singlesig::Wpkh, DescriptorBlindingKey::Slip77).unwrap();
+        let network = ElementsNetwork::default_regtest();
+        let mut wollet = Wollet::without_persist(network, desc.parse().unwrap()).unwrap();
+        let mut mock = MockBackend::new(network);
+        let address = wollet.address(None).unwrap().address().clone();
+        mock.fund(&address, 100_000, None).unwrap();
+        mock.sync(&mut wollet).unwrap();
+        let mut pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 10_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let unsigned = pset.to_string();
+        signer.sign(&mut pset).unwrap();
+        let tx = wollet.finalize(&mut pset.clone()).unwrap();
+
+        // Without an Elements node the dry run only checks the finalization
+        let r = client
+            .tx_broadcast(None, Some(pset.to_string()), true)
+            .unwrap();
+        assert_eq!(r.txid, tx.txid().to_string());
+        assert!(!r.broadcast);
+        assert_eq!(r.mempool_accepted, None);
+        let hex = serialize(&tx).to_hex();
+        let r = client.tx_broadcast(Some(hex.clone()), None, true).unwrap();
+        assert_eq!(r.txid, tx.txid().to_string());
+
+        let err = client.tx_broadcast(None, Some(unsigned), true).unwrap_err();
+        assert!(err.to_string().contains("MissingPubkey"), "{err}");
+        let err = client
+            .tx_broadcast(Some(hex.clone()), Some(pset.to_string()), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("Exactly one"), "{err}");
+        let err = client.tx_broadcast(None, None, true).unwrap_err();
+        assert!(err.to_string().contains("Exactly one"), "{err}");
+
+        // The Electrum server is not reachable
+        assert!(client.tx_broadcast(Some(hex), None, false).is_err());
+
+        stop_app(app);
+    }
+
+    #[test]
+    fn tenants() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let token = |t: &str| AuthToken::from_str(t).unwrap();
+        let start = || {
+            let mut config = regtest_config(tempdir.path());
+            config.auth_tokens = vec![token("admin")];
+            config.tenants = ["shop", "games"]
+                .iter()
+                .map(|id| Tenant {
+                    id: id.to_string(),
+                    auth_tokens: vec![token(&format!("{id}-token"))],
+                    webhooks: vec![],
+                })
+                .collect();
+            start_app(config)
+        };
+        let client =
+            |app: &App, t: &str| Client::with_auth_token(app.addr(), Some(token(t))).unwrap();
+        let names = |client: &Client| -> Vec<String> {
+            let wallets = client.wallet_list().unwrap().wallets;
+            wallets.into_iter().map(|w| w.name).collect()
+        };
+
+        let app = start();
+        let admin = client(&app, "admin");
+        let shop = client(&app, "shop-token");
+        let games = client(&app, "games-token");
+        assert!(Client::new(app.addr()).unwrap().wallet_list().is_err());
+
+        let desc = lwk_test_util::TEST_DESCRIPTOR.to_string();
+        shop.wallet_load(desc.clone(), "w".into()).unwrap();
+        assert_eq!(names(&shop), vec!["w"]);
+        assert!(names(&games).is_empty());
+        assert!(names(&admin).is_empty());
+
+        // The same name in another tenant does not collide
+        games.wallet_load(desc, "w".into()).unwrap();
+        games.wallet_unload("w".into()).unwrap();
+        assert_eq!(names(&shop), vec!["w"]);
+
+        // Tenants cannot stop the server
+        shop.stop().unwrap();
+        assert!(app.is_running().unwrap());
+        let path = "tenants/shop/liquid-regtest/state.json";
+        assert!(tempdir.path().join(path).exists());
+        stop_app(app);
+
+        // Persisted across restarts
+        let app = start();
+        assert_eq!(names(&client(&app, "shop-token")), vec!["w"]);
+        assert!(names(&client(&app, "games-token")).is_empty());
+        assert!(names(&client(&app, "admin")).is_empty());
+        stop_app(app);
+    }
+
+    #[test]
+    fn signer_totp() {
+        use lwk_common::{DescriptorBlindingKey, Singlesig};
+        use lwk_wollet::blocking::MockBackend;
+
+        let tempdir = tempfile::tempdir().unwrap();
+        let start = || start_app(regtest_config(tempdir.path()));
+        let code = |secret: &str, delta: u64| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let totp = Totp::from_base32(secret, 0).unwrap();
+            format!("{:06}", totp.code_at(now / 30 + delta))
+        };
+
+        // PSETs spending some sats and the fee, or an asset, from the wallet of the signer
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let signer = SwSigner::new(mnemonic, false).unwrap();
+        let desc = singlesig_desc(&signer, Singlesig::Wpkh, DescriptorBlindingKey::Slip77).unwrap();
+        let network = ElementsNetwork::default_regtest();
+        let mut wollet = Wollet::without_persist(network, desc.parse().unwrap()).unwrap();
+        let mut mock = MockBackend::new(network);
+        let asset = AssetId::from_slice(&[7; 32]).unwrap();
+        mock.fund_wollet(&mut wollet, 100_000, None).unwrap();
+        mock.fund_wollet(&mut wollet, 1_000, Some(asset)).unwrap();
+        mock.sync(&mut wollet).unwrap();
+        let other: WolletDescriptor = lwk_test_util::TEST_DESCRIPTOR.parse().unwrap();
+        let other = Wollet::without_persist(network, other).unwrap();
+        let address = other.address(None).unwrap().address().clone();
+        let pset = |satoshi, asset| {
+            wollet
+                .tx_builder()
+                .add_recipient(&address, satoshi, asset)
+                .unwrap()
+                .finish()
+                .unwrap()
+                .to_string()
+        };
+        let policy_asset = network.policy_asset();
+        let (pset_10k, pset_20k) = (pset(10_000, policy_asset), pset(20_000, policy_asset));
+        let pset_30k = pset(30_000, policy_asset);
+        let pset_asset = pset(1, asset);
+
+        let app = start();
+        let client = Client::new(app.addr()).unwrap();
+        client
+            .signer_load_software("s".into(), mnemonic.into(), true)
+            .unwrap();
+        client.wallet_load(desc, "w".into()).unwrap();
+        let details = client.signer_details("s".into()).unwrap();
+        assert_eq!(details.mnemonic.as_deref(), Some(mnemonic));
+
+        let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        let r = client
+            .signer_enroll_totp("s".into(), 50_000, Some(secret.into()), None)
+            .unwrap();
+        assert_eq!(r.secret, secret);
+        assert!(r.uri.starts_with("otpauth://totp/LWK:s?secret="));
+
+        // Once enrolled the mnemonic is not returned anymore
+        let details = client.signer_details("s".into()).unwrap();
+        assert_eq!(details.mnemonic, None);
+
+        // Spending other assets always needs a code
+        let err = client
+            .signer_sign("s".into(), pset_asset.clone(), None)
+            .unwrap_err();
+        assert!(err.to_string().contains("requires a TOTP code"), "{err}");
+
+        // Below the threshold in the day no code is needed, signing again counts once
+        client
+            .signer_sign("s".into(), pset_10k.clone(), None)
+            .unwrap();
+        client
+            .signer_sign("s".into(), pset_10k.clone(), None)
+            .unwrap();
+        client
+            .signer_sign("s".into(), pset_20k.clone(), None)
+            .unwrap();
+        let err = client
+            .signer_sign("s".into(), pset_30k.clone(), None)
+            .unwrap_err();
+        assert!(err.to_string().contains("requires a TOTP code"), "{err}");
+
+        // The threshold cannot be changed without a code
+        let err = client
+            .signer_enroll_totp("s".into(), 1_000, Some(secret.into()), None)
+            .unwrap_err();
+        assert!(err.to_string().contains("requires a TOTP code"), "{err}");
+        client
+            .signer_enroll_totp(
+                "s".into(),
+                1_000,
+                Some(secret.into()),
+                Some(code(secret, 0)),
+            )
+            .unwrap();
+
+        // Above the threshold a code is needed, and it can be used only once
+        let err = client
+            .signer_sign("s".into(), pset_30k.clone(), Some("000000".into()))
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid TOTP code"), "{err}");
+        let totp = code(secret, 1);
+        client
+            .signer_sign("s".into(), pset_30k.clone(), Some(totp.clone()))
+            .unwrap();
+        let err = client
+            .signer_sign("s".into(), pset_30k.clone(), Some(totp))
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid TOTP code"), "{err}");
+        stop_app(app);
+
+        // Persisted across restarts, with the amounts signed without codes
+        let app = start();
+        let client = Client::new(app.addr()).unwrap();
+        client.signer_sign("s".into(), pset_10k, None).unwrap();
+        let err = client.signer_sign("s".into(), pset_30k, None).unwrap_err();
+        assert!(err.to_string().contains("requires a TOTP code"), "{err}");
+        client
+            .signer_sign("s".into(), pset_asset, Some(code(secret, 1)))
+            .unwrap();
+        stop_app(app);
+    }
+
+    #[test]
+    fn limits() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_testnet(tempdir.path().to_path_buf());
+        config.addr = random_addr();
+        config.limits.max_pset_size = Some(10);
+        let app = start_app(config);
+        let client = Client::new(app.addr()).unwrap();
+
+        let params = serde_json::json!({"pset": "cHNldP8BAgQCAAAA"});
+        let err = client
+            .batch(vec![(Method::PsetDecode, Some(params))])
+            .unwrap()
+            .remove(0)
+            .unwrap_err();
+        match err {
+            Error::RpcError(e) => {
+                assert_eq!(e.code, -32_014);
+                let data: serde_json::Value = serde_json::from_str(e.data.unwrap().get()).unwrap();
+                assert_eq!(data, serde_json::json!({"size": 16, "max": 10}));
+            }
+            e => panic!("unexpected error {e:?}"),
+        }
+
+        // bodies too large are rejected before being parsed
+        let max_body_size = 10 * consts::MAX_BODY_PSETS + consts::MAX_BODY_OVERHEAD;
+        let err = client
+            .pset_decode("a".repeat(max_body_size), None)
+            .unwrap_err();
+        match err {
+            Error::RpcError(e) => assert_eq!(e.code, -32_093),
+            e => panic!("unexpected error {e:?}"),
+        }
+
+        stop_app(app);
+    }
+    #[test]
+    fn events() {
+        let txid = |n: u8| Txid::from_str(&format!("{n:064x}")).unwrap();
+        let before: HashMap<_, _> = [
+            (txid(1), None),
+            (txid(2), Some(10)),
+            (txid(4), None),
+            (txid(5), None),
+        ]
+        .into();
+        let after: HashMap<_, _> =
+            [(txid(1), Some(11)), (txid(2), Some(10)), (txid(3), None)].into();
+        // txid(4) is replaced by txid(3), txid(5) is just dropped
+        let conflicted_by = |t: &Txid| (*t == txid(4)).then(|| txid(3));
+        let mut events = tx_events("w", &before, &after, conflicted_by);
+        events.sort_by_key(|e| e.kind());
+        assert_eq!(
+            events,
+            vec![
+                response::Event::Confirmation {
+                    name: "w".into(),
+                    txid: txid(1).to_string(),
+                    height: 11,
+                },
+                response::Event::NewTx {
+                    name: "w".into(),
+                    txid: txid(3).to_string(),
+                    height: None,
+                },
+                response::Event::TxConflicted {
+                    name: "w".into(),
+                    txid: txid(4).to_string(),
+                    conflicting_txid: txid(3).to_string(),
+                },
+            ]
+        );
+        let value = serde_json::to_value(&events[1]).unwrap();
+        assert_eq!(value["type"], events[1].kind());
+    }
 }
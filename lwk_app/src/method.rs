@@ -5,7 +5,7 @@ use lwk_rpc_model::{
     response,
 };
 use schemars::schema_for;
-use serde_json::Value;
+use serde_json::{json, Value};
 
 #[derive(Debug, thiserror::Error)]
 #[error("The rpc method '{name}' does not exist")]
@@ -13,33 +13,46 @@ pub struct MethodNotExist {
     name: String,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-#[cfg_attr(test, derive(enum_iterator::Sequence))]
+#[derive(Clone, Debug, PartialEq, Eq, enum_iterator::Sequence)]
 pub enum Method {
     Schema,
     Version,
     Scan,
     Stop,
+    ServerStatus,
     WalletLoad,
     WalletUnload,
+    WalletForget,
     WalletList,
     WalletDetails,
+    WalletRetryUnblind,
     WalletAddress,
+    WalletAddresses,
+    WalletAddressInfo,
+    WalletVerifyPayment,
     WalletBalance,
     WalletUtxos,
     WalletTxs,
+    WalletExportHistory,
     WalletTx,
     WalletSendMany,
     WalletDrain,
+    WalletBumpFee,
     WalletIssue,
     WalletReissue,
     WalletBurn,
     WalletCombine,
     WalletBroadcast,
+    WalletOutbox,
+    WalletOutboxInsert,
     WalletPsetDetails,
     WalletMultisigDescriptor,
     WalletSetTxMemo,
     WalletSetAddrMemo,
+    WalletSetPolicy,
+    WalletPolicyStatus,
+    WalletBackup,
+    WalletRestore,
     SignerGenerate,
     SignerJadeId,
     SignerLoadSoftware,
@@ -52,6 +65,7 @@ pub enum Method {
     SignerSign,
     SignerSinglesigDescriptor,
     SignerRegisterMultisig,
+    SignerEnrollTotp,
     AssetContract,
     AssetInsert,
     AssetRemove,
@@ -62,6 +76,14 @@ pub enum Method {
     Amp2Descriptor,
     Amp2Register,
     Amp2Cosign,
+    AccountNew,
+    AccountList,
+    TxDecode,
+    TxBroadcast,
+    PsetDecode,
+    PsetSessionSave,
+    PsetSessionLoad,
+    PsetSessionMerge,
 }
 impl Method {
     pub(crate) fn schema(&self, direction: request::Direction) -> Result<Value, serde_json::Error> {
@@ -71,28 +93,42 @@ impl Method {
                 Method::Version => schema_for!(request::Empty),
                 Method::Scan => schema_for!(request::Empty),
                 Method::Stop => schema_for!(request::Empty),
+                Method::ServerStatus => schema_for!(request::Empty),
                 Method::WalletLoad => schema_for!(request::WalletLoad),
                 Method::WalletUnload => schema_for!(request::WalletUnload),
+                Method::WalletForget => schema_for!(request::WalletForget),
                 Method::WalletList => schema_for!(request::Empty),
                 Method::WalletDetails => schema_for!(request::WalletDetails),
+                Method::WalletRetryUnblind => schema_for!(request::WalletRetryUnblind),
                 Method::WalletAddress => schema_for!(request::WalletAddress),
+                Method::WalletAddresses => schema_for!(request::WalletAddresses),
+                Method::WalletAddressInfo => schema_for!(request::WalletAddressInfo),
+                Method::WalletVerifyPayment => schema_for!(request::WalletVerifyPayment),
                 Method::WalletBalance => schema_for!(request::WalletBalance),
                 Method::WalletUtxos => schema_for!(request::WalletUtxos),
                 Method::WalletTxs => schema_for!(request::WalletTxs),
+                Method::WalletExportHistory => schema_for!(request::WalletExportHistory),
                 Method::WalletTx => schema_for!(request::WalletTx),
                 Method::WalletSendMany => schema_for!(request::WalletSendMany),
                 Method::WalletDrain => schema_for!(request::WalletDrain),
+                Method::WalletBumpFee => schema_for!(request::WalletBumpFee),
                 Method::WalletIssue => schema_for!(request::WalletIssue),
                 Method::WalletReissue => schema_for!(request::WalletReissue),
                 Method::WalletBurn => schema_for!(request::WalletBurn),
                 Method::WalletCombine => schema_for!(request::WalletCombine),
                 Method::WalletBroadcast => schema_for!(request::WalletBroadcast),
+                Method::WalletOutbox => schema_for!(request::WalletOutbox),
+                Method::WalletOutboxInsert => schema_for!(request::WalletOutboxInsert),
                 Method::WalletPsetDetails => schema_for!(request::WalletPsetDetails),
                 Method::WalletMultisigDescriptor => schema_for!(request::WalletMultisigDescriptor),
                 Method::WalletSetTxMemo => schema_for!(request::WalletSetTxMemo),
                 Method::WalletSetAddrMemo => schema_for!(request::WalletSetAddrMemo),
+                Method::WalletSetPolicy => schema_for!(request::WalletSetPolicy),
+                Method::WalletPolicyStatus => schema_for!(request::WalletPolicyStatus),
+                Method::WalletBackup => schema_for!(request::WalletBackup),
+                Method::WalletRestore => schema_for!(request::WalletRestore),
                 Method::SignerGenerate => schema_for!(request::Empty),
-                Method::SignerJadeId => schema_for!(request::Empty),
+                Method::SignerJadeId => schema_for!(request::SignerJadeId),
                 Method::SignerLoadSoftware => schema_for!(request::SignerLoadSoftware),
                 Method::SignerLoadJade => schema_for!(request::SignerLoadJade),
                 Method::SignerLoadExternal => schema_for!(request::SignerLoadExternal),
@@ -105,6 +141,7 @@ impl Method {
                     schema_for!(request::SignerSinglesigDescriptor)
                 }
                 Method::SignerRegisterMultisig => schema_for!(request::SignerRegisterMultisig),
+                Method::SignerEnrollTotp => schema_for!(request::SignerEnrollTotp),
                 Method::AssetContract => schema_for!(request::AssetContract),
                 Method::AssetInsert => schema_for!(request::AssetInsert),
                 Method::AssetRemove => schema_for!(request::AssetRemove),
@@ -115,32 +152,54 @@ impl Method {
                 Method::Amp2Descriptor => schema_for!(request::Amp2Descriptor),
                 Method::Amp2Register => schema_for!(request::Amp2Register),
                 Method::Amp2Cosign => schema_for!(request::Amp2Cosign),
+                Method::AccountNew => schema_for!(request::AccountNew),
+                Method::AccountList => schema_for!(request::AccountList),
+                Method::TxDecode => schema_for!(request::TxDecode),
+                Method::TxBroadcast => schema_for!(request::TxBroadcast),
+                Method::PsetDecode => schema_for!(request::PsetDecode),
+                Method::PsetSessionSave => schema_for!(request::PsetSessionSave),
+                Method::PsetSessionLoad => schema_for!(request::PsetSessionLoad),
+                Method::PsetSessionMerge => schema_for!(request::PsetSessionMerge),
             },
             Direction::Response => match self {
                 Method::Schema => return serde_json::from_str(include_str!("../schema.json")),
                 Method::Version => schema_for!(response::Version),
                 Method::Scan => schema_for!(response::Empty),
                 Method::Stop => schema_for!(request::Empty),
+                Method::ServerStatus => schema_for!(response::ServerStatus),
                 Method::WalletLoad => schema_for!(response::Wallet),
                 Method::WalletUnload => schema_for!(response::WalletUnload),
+                Method::WalletForget => schema_for!(response::WalletForget),
                 Method::WalletList => schema_for!(response::WalletList),
                 Method::WalletDetails => schema_for!(response::WalletDetails),
+                Method::WalletRetryUnblind => schema_for!(response::WalletRetryUnblind),
                 Method::WalletAddress => schema_for!(response::WalletAddress),
+                Method::WalletAddresses => schema_for!(response::WalletAddresses),
+                Method::WalletAddressInfo => schema_for!(response::WalletAddressInfo),
+                Method::WalletVerifyPayment => schema_for!(response::WalletVerifyPayment),
                 Method::WalletBalance => schema_for!(response::WalletBalance),
                 Method::WalletUtxos => schema_for!(response::WalletUtxos),
                 Method::WalletTxs => schema_for!(response::WalletTxs),
+                Method::WalletExportHistory => schema_for!(response::WalletExportHistory),
                 Method::WalletTx => schema_for!(response::WalletTx),
                 Method::WalletSendMany => schema_for!(response::Pset),
                 Method::WalletDrain => schema_for!(response::Pset),
+                Method::WalletBumpFee => schema_for!(response::Pset),
                 Method::WalletIssue => schema_for!(response::Pset),
                 Method::WalletReissue => schema_for!(response::Pset),
                 Method::WalletBurn => schema_for!(response::Pset),
                 Method::WalletCombine => schema_for!(response::WalletCombine),
                 Method::WalletBroadcast => schema_for!(response::WalletBroadcast),
+                Method::WalletOutbox => schema_for!(response::WalletOutbox),
+                Method::WalletOutboxInsert => schema_for!(response::WalletBroadcast),
                 Method::WalletPsetDetails => schema_for!(response::WalletPsetDetails),
                 Method::WalletMultisigDescriptor => schema_for!(response::WalletMultisigDescriptor),
                 Method::WalletSetTxMemo => schema_for!(response::Empty),
                 Method::WalletSetAddrMemo => schema_for!(response::Empty),
+                Method::WalletSetPolicy => schema_for!(response::WalletPolicyStatus),
+                Method::WalletPolicyStatus => schema_for!(response::WalletPolicyStatus),
+                Method::WalletBackup => schema_for!(response::WalletBackup),
+                Method::WalletRestore => schema_for!(response::Wallet),
                 Method::SignerGenerate => schema_for!(response::SignerGenerate),
                 Method::SignerJadeId => schema_for!(response::JadeId),
                 Method::SignerLoadSoftware => schema_for!(response::Signer),
@@ -155,6 +214,7 @@ impl Method {
                     schema_for!(response::SignerSinglesigDescriptor)
                 }
                 Method::SignerRegisterMultisig => schema_for!(response::Empty),
+                Method::SignerEnrollTotp => schema_for!(response::SignerTotp),
                 Method::AssetContract => schema_for!(response::AssetContract),
                 Method::AssetInsert => schema_for!(response::Empty),
                 Method::AssetRemove => schema_for!(request::Empty),
@@ -165,11 +225,98 @@ impl Method {
                 Method::Amp2Descriptor => schema_for!(response::Amp2Descriptor),
                 Method::Amp2Register => schema_for!(response::Amp2Register),
                 Method::Amp2Cosign => schema_for!(response::Amp2Cosign),
+                Method::AccountNew => schema_for!(response::Account),
+                Method::AccountList => schema_for!(response::AccountList),
+                Method::TxDecode => schema_for!(response::DecodedTx),
+                Method::TxBroadcast => schema_for!(response::TxBroadcast),
+                Method::PsetDecode => schema_for!(response::DecodedTx),
+                Method::PsetSessionSave => schema_for!(response::PsetSession),
+                Method::PsetSessionLoad => schema_for!(response::PsetSession),
+                Method::PsetSessionMerge => schema_for!(response::PsetSession),
             },
         })
     }
 }
 
+/// The version of the OpenRPC specification followed by [`openrpc()`]
+pub const OPENRPC_VERSION: &str = "1.2.6";
+
+/// Return an [OpenRPC](https://spec.open-rpc.org) document describing every method of the server
+///
+/// Parameters are passed by name, they are the properties of the request of the method. Types
+/// referenced by more methods are in the document components.
+pub fn openrpc() -> Result<Value, serde_json::Error> {
+    let mut components = serde_json::Map::new();
+    let mut methods = vec![];
+    for method in enum_iterator::all::<Method>() {
+        let request = openrpc_schema(method.schema(Direction::Request)?, &mut components)?;
+        let result = match method {
+            // the response is the JSON schema meta-schema, which cannot be moved in the components
+            Method::Schema => json!({"description": "A JSON schema", "type": "object"}),
+            _ => openrpc_schema(method.schema(Direction::Response)?, &mut components)?,
+        };
+        let required: Vec<&str> = request["required"]
+            .as_array()
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let params: Vec<Value> = request["properties"]
+            .as_object()
+            .map(|p| {
+                p.iter()
+                    .map(|(name, schema)| {
+                        json!({
+                            "name": name,
+                            "required": required.contains(&name.as_str()),
+                            "schema": schema,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut m = json!({
+            "name": method.to_string(),
+            "paramStructure": "by-name",
+            "params": params,
+            "result": {
+                "name": "result",
+                "schema": result,
+            },
+        });
+        if let Some(description) = request.get("description") {
+            m["description"] = description.clone();
+        }
+        methods.push(m);
+    }
+    Ok(json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": {
+            "title": "Liquid Wallet Kit RPC",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": methods,
+        "components": {
+            "schemas": components,
+        },
+    }))
+}
+
+/// Move the definitions of a schema generated by schemars to the OpenRPC components
+fn openrpc_schema(
+    schema: Value,
+    components: &mut serde_json::Map<String, Value>,
+) -> Result<Value, serde_json::Error> {
+    let schema = serde_json::to_string(&schema)?.replace("#/definitions/", "#/components/schemas/");
+    let mut schema: Value = serde_json::from_str(&schema)?;
+    if let Some(schema) = schema.as_object_mut() {
+        schema.remove("$schema");
+        schema.remove("title");
+        if let Some(Value::Object(definitions)) = schema.remove("definitions") {
+            components.extend(definitions);
+        }
+    }
+    Ok(schema)
+}
+
 impl FromStr for Method {
     type Err = MethodNotExist;
 
@@ -179,26 +326,40 @@ impl FromStr for Method {
             "version" => Method::Version,
             "scan" => Method::Scan,
             "stop" => Method::Stop,
+            "server_status" => Method::ServerStatus,
             "wallet_load" => Method::WalletLoad,
             "wallet_unload" => Method::WalletUnload,
+            "wallet_forget" => Method::WalletForget,
             "wallet_list" => Method::WalletList,
             "wallet_details" => Method::WalletDetails,
+            "wallet_retry_unblind" => Method::WalletRetryUnblind,
             "wallet_address" => Method::WalletAddress,
+            "wallet_addresses" => Method::WalletAddresses,
+            "wallet_address_info" => Method::WalletAddressInfo,
+            "wallet_verify_payment" => Method::WalletVerifyPayment,
             "wallet_balance" => Method::WalletBalance,
             "wallet_utxos" => Method::WalletUtxos,
             "wallet_txs" => Method::WalletTxs,
+            "wallet_export_history" => Method::WalletExportHistory,
             "wallet_tx" => Method::WalletTx,
             "wallet_send_many" => Method::WalletSendMany,
             "wallet_drain" => Method::WalletDrain,
+            "wallet_bump_fee" => Method::WalletBumpFee,
             "wallet_issue" => Method::WalletIssue,
             "wallet_reissue" => Method::WalletReissue,
             "wallet_burn" => Method::WalletBurn,
             "wallet_combine" => Method::WalletCombine,
             "wallet_broadcast" => Method::WalletBroadcast,
+            "wallet_outbox" => Method::WalletOutbox,
+            "wallet_outbox_insert" => Method::WalletOutboxInsert,
             "wallet_pset_details" => Method::WalletPsetDetails,
             "wallet_multisig_descriptor" => Method::WalletMultisigDescriptor,
             "wallet_set_tx_memo" => Method::WalletSetTxMemo,
             "wallet_set_addr_memo" => Method::WalletSetAddrMemo,
+            "wallet_set_policy" => Method::WalletSetPolicy,
+            "wallet_policy_status" => Method::WalletPolicyStatus,
+            "wallet_backup" => Method::WalletBackup,
+            "wallet_restore" => Method::WalletRestore,
             "signer_generate" => Method::SignerGenerate,
             "signer_jade_id" => Method::SignerJadeId,
             "signer_load_software" => Method::SignerLoadSoftware,
@@ -211,6 +372,7 @@ impl FromStr for Method {
             "signer_sign" => Method::SignerSign,
             "signer_singlesig_descriptor" => Method::SignerSinglesigDescriptor,
             "signer_register_multisig" => Method::SignerRegisterMultisig,
+            "signer_enroll_totp" => Method::SignerEnrollTotp,
             "asset_contract" => Method::AssetContract,
             "asset_insert" => Method::AssetInsert,
             "asset_remove" => Method::AssetRemove,
@@ -221,6 +383,14 @@ impl FromStr for Method {
             "amp2_descriptor" => Method::Amp2Descriptor,
             "amp2_register" => Method::Amp2Register,
             "amp2_cosign" => Method::Amp2Cosign,
+            "account_new" => Method::AccountNew,
+            "account_list" => Method::AccountList,
+            "tx_decode" => Method::TxDecode,
+            "tx_broadcast" => Method::TxBroadcast,
+            "pset_decode" => Method::PsetDecode,
+            "pset_session_save" => Method::PsetSessionSave,
+            "pset_session_load" => Method::PsetSessionLoad,
+            "pset_session_merge" => Method::PsetSessionMerge,
             _ => {
                 return Err(MethodNotExist {
                     name: s.to_string(),
@@ -237,26 +407,40 @@ impl std::fmt::Display for Method {
             Method::Version => "version",
             Method::Scan => "scan",
             Method::Stop => "stop",
+            Method::ServerStatus => "server_status",
             Method::WalletLoad => "wallet_load",
             Method::WalletUnload => "wallet_unload",
+            Method::WalletForget => "wallet_forget",
             Method::WalletList => "wallet_list",
             Method::WalletDetails => "wallet_details",
+            Method::WalletRetryUnblind => "wallet_retry_unblind",
             Method::WalletAddress => "wallet_address",
+            Method::WalletAddresses => "wallet_addresses",
+            Method::WalletAddressInfo => "wallet_address_info",
+            Method::WalletVerifyPayment => "wallet_verify_payment",
             Method::WalletBalance => "wallet_balance",
             Method::WalletUtxos => "wallet_utxos",
             Method::WalletTxs => "wallet_txs",
+            Method::WalletExportHistory => "wallet_export_history",
             Method::WalletTx => "wallet_tx",
             Method::WalletSendMany => "wallet_send_many",
             Method::WalletDrain => "wallet_drain",
+            Method::WalletBumpFee => "wallet_bump_fee",
             Method::WalletIssue => "wallet_issue",
             Method::WalletReissue => "wallet_reissue",
             Method::WalletBurn => "wallet_burn",
             Method::WalletCombine => "wallet_combine",
             Method::WalletBroadcast => "wallet_broadcast",
+            Method::WalletOutbox => "wallet_outbox",
+            Method::WalletOutboxInsert => "wallet_outbox_insert",
             Method::WalletPsetDetails => "wallet_pset_details",
             Method::WalletMultisigDescriptor => "wallet_multisig_descriptor",
             Method::WalletSetTxMemo => "wallet_set_tx_memo",
             Method::WalletSetAddrMemo => "wallet_set_addr_memo",
+            Method::WalletSetPolicy => "wallet_set_policy",
+            Method::WalletPolicyStatus => "wallet_policy_status",
+            Method::WalletBackup => "wallet_backup",
+            Method::WalletRestore => "wallet_restore",
             Method::SignerGenerate => "signer_generate",
             Method::SignerJadeId => "signer_jade_id",
             Method::SignerLoadSoftware => "signer_load_software",
@@ -269,6 +453,7 @@ impl std::fmt::Display for Method {
             Method::SignerSign => "signer_sign",
             Method::SignerSinglesigDescriptor => "signer_singlesig_descriptor",
             Method::SignerRegisterMultisig => "signer_register_multisig",
+            Method::SignerEnrollTotp => "signer_enroll_totp",
             Method::AssetContract => "asset_contract",
             Method::AssetInsert => "asset_insert",
             Method::AssetRemove => "asset_remove",
@@ -279,6 +464,14 @@ impl std::fmt::Display for Method {
             Method::Amp2Descriptor => "amp2_descriptor",
             Method::Amp2Register => "amp2_register",
             Method::Amp2Cosign => "amp2_cosign",
+            Method::AccountNew => "account_new",
+            Method::AccountList => "account_list",
+            Method::TxDecode => "tx_decode",
+            Method::TxBroadcast => "tx_broadcast",
+            Method::PsetDecode => "pset_decode",
+            Method::PsetSessionSave => "pset_session_save",
+            Method::PsetSessionLoad => "pset_session_load",
+            Method::PsetSessionMerge => "pset_session_merge",
         };
         write!(f, "{}", s)
     }
@@ -288,7 +481,27 @@ impl std::fmt::Display for Method {
 mod test {
     use enum_iterator::all;
 
-    use super::Method;
+    use super::{openrpc, Method};
+
+    #[test]
+    fn openrpc_contract() {
+        // Fails on changes of the RPC interface, if intended update the document with
+        // `UPDATE_OPENRPC=1 cargo test -p lwk_app openrpc_contract`
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openrpc.json");
+        let mut doc = openrpc().unwrap();
+        let methods = doc["methods"].as_array().unwrap();
+        assert_eq!(methods.len(), all::<Method>().count());
+        if std::env::var("UPDATE_OPENRPC").is_ok() {
+            let pretty = serde_json::to_string_pretty(&doc).unwrap();
+            std::fs::write(path, pretty + "\n").unwrap();
+        }
+        let mut expected: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        // the version changes on every release
+        doc["info"]["version"] = serde_json::Value::Null;
+        expected["info"]["version"] = serde_json::Value::Null;
+        assert_eq!(doc, expected);
+    }
 
     #[test]
     fn method_roundtrip() {
@@ -0,0 +1,488 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lwk_wollet::bitcoin::bip32::Fingerprint;
+use lwk_wollet::elements::pset::{Input, PartiallySignedTransaction};
+use lwk_wollet::elements::{Address, AssetId, Txid};
+use lwk_wollet::elements_miniscript::ForEachKey;
+use lwk_wollet::{AddressInfo, Wollet, EC};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Amounts by asset
+pub type Amounts = BTreeMap<AssetId, u64>;
+
+/// Spending rules of a wallet, checked before a loaded signer signs a PSET spending from it
+///
+/// Once a PSET is signed the rules are not enforced anymore, they protect the signers loaded in
+/// the server, not the wallet itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Maximum amount of each asset the wallet can spend per UTC day, fees included
+    ///
+    /// If not empty, the assets without a limit cannot be spent.
+    pub daily_limits: BTreeMap<AssetId, u64>,
+
+    /// If not empty, the only addresses the wallet can send to
+    pub allowlist: Vec<Address>,
+
+    /// Cosigners that must sign the wallet inputs before a loaded signer adds its signature
+    pub required_cosigners: Vec<Fingerprint>,
+}
+
+impl Policy {
+    /// The amount of `asset` the wallet can spend per day, `None` if unlimited
+    pub fn daily_limit(&self, asset: &AssetId) -> Option<u64> {
+        if self.daily_limits.is_empty() {
+            None
+        } else {
+            Some(self.daily_limits.get(asset).copied().unwrap_or(0))
+        }
+    }
+
+    /// Whether `self` allows some spending that `current` forbids
+    pub fn is_weaker_than(&self, current: &Policy) -> bool {
+        let weaker_limits = !current.daily_limits.is_empty()
+            && (self.daily_limits.is_empty()
+                || self
+                    .daily_limits
+                    .iter()
+                    .any(|(asset, limit)| Some(*limit) > current.daily_limit(asset)));
+        let weaker_allowlist = !current.allowlist.is_empty()
+            && (self.allowlist.is_empty()
+                || self
+                    .allowlist
+                    .iter()
+                    .any(|a| !current.allowlist.contains(a)));
+        let weaker_cosigners = current
+            .required_cosigners
+            .iter()
+            .any(|f| !self.required_cosigners.contains(f));
+        weaker_limits || weaker_allowlist || weaker_cosigners
+    }
+
+    /// Check that `signer` can sign `pset` spending `spent` from `wollet`, when the wallet already
+    /// spent `spent_today`, returning the reason of the violation if any
+    pub fn check(
+        &self,
+        wollet: &Wollet,
+        pset: &PartiallySignedTransaction,
+        signer: Fingerprint,
+        spent: &Amounts,
+        spent_today: &Amounts,
+    ) -> Result<Result<(), String>, Error> {
+        if !self.allowlist.is_empty() {
+            let details = wollet.get_details(pset)?;
+            for recipient in details.balance.recipients.iter() {
+                let script = &pset.outputs()[recipient.vout as usize].script_pubkey;
+                if !self.allowlist.iter().any(|a| &a.script_pubkey() == script) {
+                    let to = match recipient.address.as_ref() {
+                        Some(address) => address.to_string(),
+                        None => format!("output {}", recipient.vout),
+                    };
+                    return Ok(Err(format!("{to} is not in the allowlist")));
+                }
+            }
+        }
+
+        let cosigners: Vec<_> = self
+            .required_cosigners
+            .iter()
+            .filter(|f| **f != signer)
+            .collect();
+        if !cosigners.is_empty() {
+            // The fingerprints in the PSET are set by its creator, the signatures are counted only
+            // if made by the cosigner keys in the wallet descriptor and valid for the sighash
+            if let Err(e) = wollet.verify_signatures(pset) {
+                return Ok(Err(e.to_string()));
+            }
+            for input in pset.inputs() {
+                let Some(info) = wallet_input(wollet, input) else {
+                    continue;
+                };
+                let desc = wollet
+                    .wollet_descriptor()
+                    .definite_descriptor(info.chain(), info.index())?;
+                for cosigner in cosigners.iter() {
+                    let mut signed = false;
+                    desc.for_each_key(|k| {
+                        if k.master_fingerprint() == **cosigner {
+                            signed |= k
+                                .derive_public_key(&EC)
+                                .is_ok_and(|pk| input.partial_sigs.contains_key(&pk));
+                        }
+                        true
+                    });
+                    if !signed {
+                        return Ok(Err(format!("cosigner {cosigner} did not sign yet")));
+                    }
+                }
+            }
+        }
+
+        for (asset, spent) in spent.iter() {
+            if let Some(limit) = self.daily_limit(asset) {
+                let spent_today = spent_today.get(asset).copied().unwrap_or(0);
+                if spent_today + spent > limit {
+                    return Ok(Err(format!(
+                        "spending {spent} of {asset} exceeds the daily limit of {limit}, {spent_today} already spent today"
+                    )));
+                }
+            }
+        }
+        Ok(Ok(()))
+    }
+}
+
+/// The wallet address spent by the input, if any
+fn wallet_input(wollet: &Wollet, input: &Input) -> Option<AddressInfo> {
+    input
+        .witness_utxo
+        .as_ref()
+        .and_then(|txout| wollet.is_mine(&txout.script_pubkey).ok().flatten())
+}
+
+/// The amounts of the assets spent by `wollet` in `pset`, if `signer` signs for some of its inputs
+///
+/// `None` if the signer cannot sign for the wallet, so that the policy does not apply.
+pub fn spent_by(
+    wollet: &Wollet,
+    pset: &PartiallySignedTransaction,
+    signer: Fingerprint,
+) -> Result<Option<Amounts>, Error> {
+    let involved = pset.inputs().iter().any(|input| {
+        wallet_input(wollet, input).is_some()
+            && input.bip32_derivation.values().any(|(f, _)| *f == signer)
+    });
+    if !involved {
+        return Ok(None);
+    }
+    let details = wollet.get_details(pset)?;
+    Ok(Some(
+        details
+            .balance
+            .balances
+            .iter()
+            .filter(|(_, v)| **v < 0)
+            .map(|(asset, v)| (*asset, v.unsigned_abs()))
+            .collect(),
+    ))
+}
+
+/// The current UTC day, as days since the unix epoch
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0)
+}
+
+#[derive(Default)]
+pub struct Policies(HashMap<String, Policy>);
+
+impl Policies {
+    pub fn get(&self, name: &str) -> Option<&Policy> {
+        self.0.get(name)
+    }
+
+    /// Set the policy of the wallet `name`, an empty policy removes it
+    pub fn set(&mut self, name: &str, policy: Policy) {
+        if policy == Policy::default() {
+            self.0.remove(name);
+        } else {
+            self.0.insert(name.to_string(), policy);
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Policy)> {
+        self.0.iter()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+struct DailySpend {
+    day: u64,
+    #[serde(default)]
+    assets: Amounts,
+    txids: BTreeSet<Txid>,
+}
+
+/// The assets spent by each wallet, or signer, in the current day, persisted in a file
+///
+/// Transactions are counted once, even if signed several times or by several signers.
+#[derive(Debug, Clone, Default)]
+pub struct SpendCounters {
+    path: Option<PathBuf>,
+    wallets: BTreeMap<String, DailySpend>,
+}
+
+impl SpendCounters {
+    /// Load the counters from `path`, empty if the file does not exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let wallets = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            wallets,
+        })
+    }
+
+    /// Write the counters to the file they were loaded from, if any
+    pub fn save(&self) -> Result<(), Error> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(&self.wallets)?)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    fn current(&self, name: &str, day: u64) -> Option<&DailySpend> {
+        self.wallets.get(name).filter(|d| d.day == day)
+    }
+
+    /// The amounts of the assets spent by the wallet `name` in `day`
+    pub fn spent(&self, name: &str, day: u64) -> Amounts {
+        self.current(name, day)
+            .map(|d| d.assets.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether the transaction `txid` was already counted in `day`
+    pub fn is_counted(&self, name: &str, day: u64, txid: &Txid) -> bool {
+        self.current(name, day)
+            .is_some_and(|d| d.txids.contains(txid))
+    }
+
+    /// Count the amounts `spent` by the wallet `name` in `day` with the transaction `txid`
+    pub fn record(&mut self, name: &str, day: u64, txid: Txid, spent: &Amounts) {
+        let entry = self.wallets.entry(name.to_string()).or_default();
+        if entry.day != day {
+            *entry = DailySpend {
+                day,
+                ..Default::default()
+            };
+        }
+        if entry.txids.insert(txid) {
+            for (asset, amount) in spent.iter() {
+                *entry.assets.entry(*asset).or_default() += amount;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use lwk_common::{singlesig_desc, DescriptorBlindingKey, Signer, Singlesig};
+    use lwk_signer::SwSigner;
+    use lwk_wollet::blocking::MockBackend;
+    use lwk_wollet::{ElementsNetwork, WolletDescriptor};
+
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        let network = ElementsNetwork::default_regtest();
+        let policy_asset = network.policy_asset();
+        let signer = SwSigner::new(lwk_test_util::TEST_MNEMONIC, false).unwrap();
+        let desc = singlesig_desc(&signer, Singlesig::Wpkh, DescriptorBlindingKey::Slip77).unwrap();
+        let desc = WolletDescriptor::from_str(&desc).unwrap();
+        let mut wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap();
+        client.fund(address.address(), 100_000, None).unwrap();
+        let asset = AssetId::from_slice(&[7; 32]).unwrap();
+        client.fund_wollet(&mut wollet, 1_000, Some(asset)).unwrap();
+        client.sync(&mut wollet).unwrap();
+
+        let other_desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let other_wollet = Wollet::without_persist(network, other_desc).unwrap();
+        let external = other_wollet.address(None).unwrap().address().clone();
+        let pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&external, 10_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let fingerprint = signer.fingerprint();
+        let other = Fingerprint::from_str("11111111").unwrap();
+
+        let spent = spent_by(&wollet, &pset, fingerprint).unwrap().unwrap();
+        assert!(spent[&policy_asset] > 10_000);
+        assert_eq!(spent.len(), 1);
+        assert!(spent_by(&wollet, &pset, other).unwrap().is_none());
+
+        let check = |policy: &Policy, pset: &PartiallySignedTransaction, signer, spent_today| {
+            let spent_today = BTreeMap::from([(policy_asset, spent_today)]);
+            policy
+                .check(&wollet, pset, signer, &spent, &spent_today)
+                .unwrap()
+        };
+        assert!(check(&Policy::default(), &pset, fingerprint, 0).is_ok());
+
+        let limit = Policy {
+            daily_limits: BTreeMap::from([(policy_asset, 20_000)]),
+            ..Default::default()
+        };
+        assert!(check(&limit, &pset, fingerprint, 0).is_ok());
+        let err = check(&limit, &pset, fingerprint, 10_000).unwrap_err();
+        assert!(err.contains("daily limit"), "{err}");
+
+        // Assets without a limit cannot be spent
+        let asset_pset = wollet
+            .tx_builder()
+            .add_recipient(&external, 100, asset)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let asset_spent = spent_by(&wollet, &asset_pset, fingerprint)
+            .unwrap()
+            .unwrap();
+        assert_eq!(asset_spent[&asset], 100);
+        let no_spent = BTreeMap::new();
+        let err = limit
+            .check(&wollet, &asset_pset, fingerprint, &asset_spent, &no_spent)
+            .unwrap()
+            .unwrap_err();
+        assert!(err.contains(&asset.to_string()), "{err}");
+        let mut asset_limit = limit.clone();
+        asset_limit.daily_limits.insert(asset, 100);
+        assert!(asset_limit
+            .check(&wollet, &asset_pset, fingerprint, &asset_spent, &no_spent)
+            .unwrap()
+            .is_ok());
+
+        let allowlist = Policy {
+            allowlist: vec![address.address().clone()],
+            ..Default::default()
+        };
+        let err = check(&allowlist, &pset, fingerprint, 0).unwrap_err();
+        assert!(err.contains("allowlist"), "{err}");
+        let allowlist = Policy {
+            allowlist: vec![external.clone()],
+            ..Default::default()
+        };
+        assert!(check(&allowlist, &pset, fingerprint, 0).is_ok());
+
+        // A required cosigner does not need its own signature
+        let cosigners = Policy {
+            required_cosigners: vec![fingerprint],
+            ..Default::default()
+        };
+        assert!(check(&cosigners, &pset, fingerprint, 0).is_ok());
+        let err = check(&cosigners, &pset, other, 0).unwrap_err();
+        assert!(err.contains("did not sign"), "{err}");
+
+        let mut signed = pset.clone();
+        signer.sign(&mut signed).unwrap();
+        assert!(check(&cosigners, &signed, other, 0).is_ok());
+
+        // Signatures not valid for the sighash are not counted
+        let mut forged = signed.clone();
+        let (pk, sig) = forged.inputs()[0].partial_sigs.iter().next().unwrap();
+        let (pk, mut sig) = (*pk, sig.clone());
+        let last = sig.len() - 2;
+        sig[last] ^= 1;
+        forged.inputs_mut()[0].partial_sigs.insert(pk, sig);
+        let err = check(&cosigners, &forged, other, 0).unwrap_err();
+        assert!(err.contains("signature"), "{err}");
+
+        // Neither the fingerprints set by the PSET creator
+        let mut relabeled = signed.clone();
+        for (_, (f, _)) in relabeled.inputs_mut()[0].bip32_derivation.iter_mut() {
+            *f = other;
+        }
+        let cosigners = Policy {
+            required_cosigners: vec![other],
+            ..Default::default()
+        };
+        let err = check(&cosigners, &relabeled, fingerprint, 0).unwrap_err();
+        assert!(err.contains("did not sign"), "{err}");
+    }
+
+    #[test]
+    fn test_policy_is_weaker() {
+        let network = ElementsNetwork::default_regtest();
+        let policy_asset = network.policy_asset();
+        let asset = AssetId::from_slice(&[7; 32]).unwrap();
+        let address = Address::from_str(
+            "el1qq2xvpcvfup5j8zscjq05u2wxxjcyewk7979f3mmz5l7uw5pqmx6xf5xy50hsn6vhkm5euwt72x878eq6zxx2z0z676mna6kdq",
+        )
+        .unwrap();
+        let current = Policy {
+            daily_limits: BTreeMap::from([(policy_asset, 1_000)]),
+            allowlist: vec![address.clone()],
+            required_cosigners: vec![Fingerprint::from_str("11111111").unwrap()],
+        };
+        assert!(!current.is_weaker_than(&current));
+        assert!(Policy::default().is_weaker_than(&current));
+        assert!(!current.is_weaker_than(&Policy::default()));
+
+        let mut stricter = current.clone();
+        stricter.daily_limits.insert(policy_asset, 500);
+        stricter
+            .required_cosigners
+            .push(Fingerprint::from_str("22222222").unwrap());
+        assert!(!stricter.is_weaker_than(&current));
+        assert!(current.is_weaker_than(&stricter));
+
+        let mut weaker = current.clone();
+        weaker.daily_limits.insert(asset, 1);
+        assert!(weaker.is_weaker_than(&current));
+        let mut weaker = current.clone();
+        weaker.allowlist.clear();
+        assert!(weaker.is_weaker_than(&current));
+        let mut weaker = current.clone();
+        weaker.required_cosigners.clear();
+        assert!(weaker.is_weaker_than(&current));
+    }
+
+    #[test]
+    fn test_spend_counters() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy_counters.json");
+        let mut counters = SpendCounters::load(&path).unwrap();
+        let (a, b) = (
+            Txid::from_str(&"aa".repeat(32)).unwrap(),
+            Txid::from_str(&"bb".repeat(32)).unwrap(),
+        );
+        let (x, y) = (
+            AssetId::from_slice(&[1; 32]).unwrap(),
+            AssetId::from_slice(&[2; 32]).unwrap(),
+        );
+        let spent = |amounts: &[(AssetId, u64)]| amounts.iter().copied().collect();
+
+        counters.record("w", 1, a, &spent(&[(x, 100), (y, 1)]));
+        counters.record("w", 1, a, &spent(&[(x, 100)]));
+        counters.record("w", 1, b, &spent(&[(x, 50)]));
+        assert_eq!(counters.spent("w", 1), spent(&[(x, 150), (y, 1)]));
+        assert!(counters.is_counted("w", 1, &a));
+        assert!(counters.spent("w", 2).is_empty());
+        assert!(!counters.is_counted("w", 2, &a));
+
+        counters.save().unwrap();
+        let mut counters = SpendCounters::load(&path).unwrap();
+        assert_eq!(counters.spent("w", 1), spent(&[(x, 150), (y, 1)]));
+
+        counters.record("w", 2, a, &spent(&[(x, 10)]));
+        assert_eq!(counters.spent("w", 2), spent(&[(x, 10)]));
+        assert!(counters.spent("w", 1).is_empty());
+    }
+}
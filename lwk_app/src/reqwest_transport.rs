@@ -2,17 +2,25 @@ use std::{fmt, time::Duration};
 
 use jsonrpc::{Request, Response};
 
+use crate::AuthToken;
+
 #[derive(Clone, Debug)]
 pub struct ReqwestHttpTransport {
     /// URL of the RPC server.
     url: String,
     /// timeout only supports second granularity.
     timeout: Duration,
+    /// Bearer token sent in the `Authorization` header
+    auth_token: Option<AuthToken>,
 }
 
 impl ReqwestHttpTransport {
-    pub fn new(url: String, timeout: Duration) -> Self {
-        ReqwestHttpTransport { url, timeout }
+    pub fn new(url: String, timeout: Duration, auth_token: Option<AuthToken>) -> Self {
+        ReqwestHttpTransport {
+            url,
+            timeout,
+            auth_token,
+        }
     }
     fn request<R>(&self, req: impl serde::Serialize) -> Result<R, crate::Error>
     where
@@ -21,7 +29,11 @@ impl ReqwestHttpTransport {
         let client = reqwest::blocking::ClientBuilder::new()
             .timeout(self.timeout)
             .build()?;
-        let response = client.post(&self.url).json(&req).send()?;
+        let mut request = client.post(&self.url).json(&req);
+        if let Some(token) = self.auth_token.as_ref() {
+            request = request.bearer_auth(token.as_str());
+        }
+        let response = request.send()?;
         Ok(response.json()?)
     }
 }
@@ -1,29 +1,36 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use lwk_common::Signer;
+use lwk_common::{PrecisionAmount, Signer};
 use lwk_jade::Jade;
 use lwk_rpc_model::request;
 use lwk_signer::AnySigner;
 use lwk_signer::SwSigner;
-use lwk_tiny_jrpc::Request;
+use lwk_tiny_jrpc::{Metrics, Request};
 use lwk_wollet::asset_ids;
-use lwk_wollet::bitcoin::bip32::{Fingerprint, Xpub};
+use lwk_wollet::bitcoin::bip32::{DerivationPath, Fingerprint, Xpub};
+use lwk_wollet::bitcoin::sign_message::MessageSignature;
 use lwk_wollet::bitcoin::XKeyIdentifier;
+use lwk_wollet::clients::blocking::ServerHealthCache;
 use lwk_wollet::elements::encode::serialize;
 use lwk_wollet::elements::hex::ToHex;
 use lwk_wollet::elements::pset::elip100::AssetMetadata;
+use lwk_wollet::elements::pset::PartiallySignedTransaction;
 use lwk_wollet::elements::{Address, AssetId, OutPoint, Transaction, Txid};
-use lwk_wollet::Contract;
+use lwk_wollet::elements_miniscript::ForEachKey;
 use lwk_wollet::Wollet;
+use lwk_wollet::{Contract, KnownAsset, KnownAssets};
 use serde::Serialize;
 
 use crate::config::Config;
+use crate::consts;
 use crate::method::Method;
+use crate::policy::{self, Policies, Policy, SpendCounters};
+use crate::totp::{Totp, Totps};
 use crate::Error;
 
 #[derive(Debug)]
@@ -74,6 +81,11 @@ impl AppSigner {
         }
     }
 
+    /// Whether the signer can sign in the server, external signers cannot
+    pub fn can_sign(&self) -> bool {
+        !matches!(self.inner, AppSignerInner::ExternalSigner(_))
+    }
+
     pub fn fingerprint(&self) -> Result<Fingerprint, Error> {
         Ok(match &self.inner {
             AppSignerInner::AvailableSigner(s) => s.fingerprint()?,
@@ -106,6 +118,44 @@ impl AppSigner {
         }
     }
 
+    /// Sign `message` with the key at [`consts::ATTESTATION_PATH`], in the Bitcoin signed message
+    /// format, returning the signature and the public key
+    ///
+    /// Only software signers can sign messages.
+    pub fn sign_attestation(
+        &self,
+        message: &str,
+    ) -> Result<(MessageSignature, lwk_wollet::bitcoin::PublicKey), Error> {
+        match &self.inner {
+            AppSignerInner::AvailableSigner(AnySigner::Software(s)) => {
+                let path = DerivationPath::from_str(consts::ATTESTATION_PATH)
+                    .map_err(lwk_signer::SignerError::from)?;
+                let xpub = s
+                    .derive_xpub(&path)
+                    .map_err(lwk_signer::SignerError::from)?;
+                let signature = s
+                    .sign_message(message, &path)
+                    .map_err(lwk_signer::SignerError::from)?;
+                Ok((
+                    signature,
+                    lwk_wollet::bitcoin::PublicKey::new(xpub.public_key),
+                ))
+            }
+            _ => Err(Error::Generic(format!(
+                "A {} signer cannot sign messages",
+                self.type_()
+            ))),
+        }
+    }
+
+    /// Whether loading the signer was persisted, software signers can be loaded without persisting
+    pub fn is_persisted(&self) -> bool {
+        match &self.inner {
+            AppSignerInner::AvailableSigner(AnySigner::Software(_)) => self.persist,
+            _ => true,
+        }
+    }
+
     pub fn type_(&self) -> String {
         match &self.inner {
             AppSignerInner::ExternalSigner(_) => "external".into(),
@@ -179,6 +229,9 @@ pub enum AppAsset {
 
     /// A reissuance token for an asset
     ReissuanceToken(RegistryAssetData),
+
+    /// An asset from the bundled snapshot of well-known assets, without contract
+    KnownAsset(KnownAsset),
 }
 
 impl AppAsset {
@@ -189,6 +242,7 @@ impl AppAsset {
             AppAsset::ReissuanceToken(d) => {
                 format!("reissuance token for {}", d.contract.name)
             }
+            AppAsset::KnownAsset(a) => a.name.clone(),
         }
     }
 
@@ -199,12 +253,28 @@ impl AppAsset {
             AppAsset::ReissuanceToken(d) => {
                 format!("reissuance token for {}", d.contract.ticker)
             }
+            AppAsset::KnownAsset(a) => a.ticker.clone(),
         }
     }
 
+    /// The number of decimal digits used to display amounts of this asset
+    pub fn precision(&self) -> u8 {
+        match self {
+            AppAsset::PolicyAsset(_) => 8,
+            AppAsset::RegistryAsset(d) => d.contract.precision,
+            AppAsset::ReissuanceToken(_) => 0,
+            AppAsset::KnownAsset(a) => a.precision,
+        }
+    }
+
+    /// The given `sats` of this asset, displayed with its precision and ticker
+    pub fn amount(&self, sats: i64) -> Option<PrecisionAmount> {
+        PrecisionAmount::new(sats, self.precision(), &self.ticker()).ok()
+    }
+
     pub fn asset_metadata(&self) -> Option<AssetMetadata> {
         match self {
-            AppAsset::PolicyAsset(_) => None,
+            AppAsset::PolicyAsset(_) | AppAsset::KnownAsset(_) => None,
             AppAsset::RegistryAsset(d) => {
                 Some(AssetMetadata::new(d.contract_str(), d.issuance_prevout()))
             }
@@ -219,6 +289,7 @@ impl AppAsset {
             AppAsset::PolicyAsset(asset) => *asset,
             AppAsset::RegistryAsset(d) => d.asset_id,
             AppAsset::ReissuanceToken(d) => d.token_id,
+            AppAsset::KnownAsset(a) => a.asset_id,
         }
     }
 
@@ -242,6 +313,7 @@ impl AppAsset {
                     id: None,
                     method: Method::AssetInsert.to_string(),
                     params: Some(serde_json::to_value(params).expect("derived")),
+                    tenant: None,
                 })
             }
             _ => None,
@@ -264,6 +336,10 @@ pub struct TxMemos(HashMap<String, HashMap<Txid, String>>);
 #[derive(Default)]
 pub struct AddrMemos(HashMap<String, HashMap<Address, String>>);
 
+/// Transactions broadcast by each wallet and not yet confirmed
+#[derive(Default)]
+pub struct Outbox(HashMap<String, HashMap<Txid, Transaction>>);
+
 pub struct State {
     // TODO: config is read-only, so it's not useful to wrap it in a mutex.
     // Ideally it should be in _another_ struct accessible by method_handler.
@@ -273,8 +349,25 @@ pub struct State {
     pub assets: Assets,
     pub tx_memos: TxMemos,
     pub addr_memos: AddrMemos,
+    pub outbox: Outbox,
     pub do_persist: bool,
 
+    /// Measured health of the Electrum servers, persisted in its own file in the datadir
+    pub server_health: ServerHealthCache,
+
+    /// Spending policies of the wallets, checked before signing
+    pub policies: Policies,
+
+    /// Amounts spent today by the wallets with a policy, persisted in their own file in the datadir
+    pub spend_counters: SpendCounters,
+
+    /// TOTP required by the signers for large spends
+    pub totps: Totps,
+
+    /// Amounts signed today without TOTP codes by the signers, persisted in their own file in the
+    /// datadir
+    pub totp_counters: SpendCounters,
+
     /// Number of scan loops started
     pub scan_loops_started: u32,
 
@@ -283,6 +376,9 @@ pub struct State {
 
     /// Signal the scanning thread that we don't want to wait anymore
     pub interrupt_wait: bool,
+
+    /// Metrics exposed at the `/metrics` endpoint
+    pub metrics: Metrics,
 }
 
 impl Wollets {
@@ -357,7 +453,7 @@ impl Signers {
         timeout: Option<Duration>,
     ) -> Result<&AnySigner, Error> {
         let app_signer = self.get(name)?;
-        log::debug!("get_available({}) return {:?}", name, app_signer);
+        tracing::debug!("get_available({}) return {:?}", name, app_signer);
         let jade = match &app_signer.inner {
             #[cfg(not(feature = "serial"))]
             AppSignerInner::JadeId(_, _) => {
@@ -525,6 +621,54 @@ impl AddrMemos {
     }
 }
 
+impl Outbox {
+    pub fn for_wollet(&self, wollet: &str) -> Vec<Transaction> {
+        self.0
+            .get(wollet)
+            .map(|txs| txs.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn insert(&mut self, wollet: &str, tx: Transaction) {
+        self.0
+            .entry(wollet.to_string())
+            .or_default()
+            .insert(tx.txid(), tx);
+    }
+
+    /// Remove the transactions of `wollet` that are confirmed or conflict with a wallet
+    /// transaction, returning whether some have been removed
+    pub fn settle(&mut self, name: &str, wollet: &Wollet) -> Result<bool, Error> {
+        let Some(txs) = self.0.get_mut(name) else {
+            return Ok(false);
+        };
+        let wallet_txs = wollet.transactions()?;
+        let before = txs.len();
+        txs.retain(|txid, tx| {
+            let settled = wallet_txs.iter().any(|w| {
+                let confirmed = &w.txid == txid && w.height.is_some();
+                let conflicting = &w.txid != txid
+                    && w.tx.input.iter().any(|i| {
+                        tx.input
+                            .iter()
+                            .any(|o| o.previous_output == i.previous_output)
+                    });
+                confirmed || conflicting
+            });
+            !settled
+        });
+        Ok(txs.len() != before)
+    }
+
+    pub fn remove(&mut self, wollet: &str) {
+        self.0.remove(wollet);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &HashMap<Txid, Transaction>)> {
+        self.0.iter()
+    }
+}
+
 impl State {
     pub fn insert_policy_asset(&mut self) {
         let asset_id = self.config.network.policy_asset();
@@ -533,6 +677,35 @@ impl State {
             .insert(asset_id, AppAsset::PolicyAsset(asset_id));
     }
 
+    /// Insert the well-known assets of the network, so their name and ticker are available
+    /// without inserting their contract
+    pub fn insert_known_assets(&mut self) {
+        let known = KnownAssets::bundled(self.config.network);
+        for asset in known.iter() {
+            self.assets
+                .0
+                .entry(asset.asset_id)
+                .or_insert_with(|| AppAsset::KnownAsset(asset.clone()));
+        }
+    }
+
+    /// Remove the wallet `name` with its memos, outbox and policy
+    ///
+    /// Fails if the wallet has a policy and its signers are loaded, see
+    /// [`State::check_policy_change()`].
+    pub fn unload_wollet(&mut self, name: &str) -> Result<Wollet, Error> {
+        self.check_policy_change(name, &Policy::default())?;
+        let removed = self.wollets.remove(name)?;
+        let loaded = self.wollets.iter().count() as f64;
+        self.metrics.set(consts::METRIC_WALLETS_LOADED, loaded);
+        self.tx_memos.remove(name);
+        self.addr_memos.remove(name);
+        self.outbox.remove(name);
+        self.policies.remove(name);
+        self.persist_all()?;
+        Ok(removed)
+    }
+
     pub fn get_asset(&self, asset: &AssetId) -> Result<&AppAsset, Error> {
         self.assets
             .0
@@ -586,6 +759,20 @@ impl State {
             .collect()
     }
 
+    /// Format the balance with the precision and ticker of the known assets
+    pub fn display_balance(
+        &self,
+        balance: impl IntoIterator<Item = (AssetId, i64)>,
+    ) -> HashMap<String, String> {
+        balance
+            .into_iter()
+            .filter_map(|(asset_id, sats)| {
+                let asset = self.get_asset(&asset_id).ok()?;
+                Some((asset.ticker(), asset.amount(sats)?.to_string()))
+            })
+            .collect()
+    }
+
     pub fn persist<T: Serialize>(&mut self, data: T) -> Result<(), Error> {
         if self.do_persist {
             let data = serde_json::to_string(&data)?;
@@ -627,12 +814,14 @@ impl State {
             let params = request::WalletLoad {
                 descriptor: w.descriptor().to_string(),
                 name: n.to_string(),
+                birthday: w.birthday(),
             };
             let r = Request {
                 jsonrpc: "2.0".into(),
                 id: None,
                 method: Method::WalletLoad.to_string(),
                 params: Some(serde_json::to_value(params)?),
+                tenant: None,
             };
             requests.push(r);
         }
@@ -650,6 +839,7 @@ impl State {
                     id: None,
                     method: Method::WalletSetTxMemo.to_string(),
                     params: Some(serde_json::to_value(params)?),
+                    tenant: None,
                 };
                 requests.push(r);
             }
@@ -668,11 +858,59 @@ impl State {
                     id: None,
                     method: Method::WalletSetAddrMemo.to_string(),
                     params: Some(serde_json::to_value(params)?),
+                    tenant: None,
                 };
                 requests.push(r);
             }
         }
 
+        // Outbox
+        for (name, txs) in self.outbox.iter() {
+            for tx in txs.values() {
+                let params = request::WalletOutboxInsert {
+                    name: name.to_string(),
+                    tx: serialize(tx).to_hex(),
+                };
+                let r = Request {
+                    jsonrpc: "2.0".into(),
+                    id: None,
+                    method: Method::WalletOutboxInsert.to_string(),
+                    params: Some(serde_json::to_value(params)?),
+                    tenant: None,
+                };
+                requests.push(r);
+            }
+        }
+
+        // Policies
+        let policy_asset = self.config.network.policy_asset();
+        for (name, policy) in self.policies.iter() {
+            let params = request::WalletSetPolicy {
+                name: name.to_string(),
+                daily_limit: policy.daily_limits.get(&policy_asset).copied(),
+                asset_daily_limits: policy
+                    .daily_limits
+                    .iter()
+                    .filter(|(asset, _)| **asset != policy_asset)
+                    .map(|(asset, limit)| (asset.to_string(), *limit))
+                    .collect(),
+                allowlist: policy.allowlist.iter().map(|a| a.to_string()).collect(),
+                required_cosigners: policy
+                    .required_cosigners
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect(),
+            };
+            let r = Request {
+                jsonrpc: "2.0".into(),
+                id: None,
+                method: Method::WalletSetPolicy.to_string(),
+                params: Some(serde_json::to_value(params)?),
+                tenant: None,
+            };
+            requests.push(r);
+        }
+
         // Signers
         for (n, s) in self.signers.iter() {
             let (params, method) = match &s.inner {
@@ -721,10 +959,16 @@ impl State {
                 id: None,
                 method: method.to_string(),
                 params: Some(params),
+                tenant: None,
             };
             requests.push(r);
         }
 
+        // TOTP, after the signers they refer to
+        for (name, totp) in self.totps.iter() {
+            requests.push(totp_request(name, totp)?);
+        }
+
         // Assets
         for (_, a) in self.assets.iter() {
             if let Some(r) = a.request() {
@@ -735,6 +979,122 @@ impl State {
         Ok(requests)
     }
 
+    /// Check the policies of the wallets `pset` spends from, before `signer` signs it
+    ///
+    /// Returns the spends to count once the PSET is signed, as wallet name, txid and amounts.
+    pub fn check_policies(
+        &self,
+        pset: &PartiallySignedTransaction,
+        signer: Fingerprint,
+        day: u64,
+    ) -> Result<Vec<(String, Txid, policy::Amounts)>, Error> {
+        let txid = pset.extract_tx().map_err(|e| e.to_string())?.txid();
+        let mut spends = vec![];
+        for (name, policy) in self.policies.iter() {
+            let wollet = self.wollets.get(name)?;
+            let Some(spent) = policy::spent_by(wollet, pset, signer)? else {
+                continue;
+            };
+            // Signing again a transaction already counted does not spend more
+            let (spent, spent_today) = if self.spend_counters.is_counted(name, day, &txid) {
+                (BTreeMap::new(), BTreeMap::new())
+            } else {
+                (spent, self.spend_counters.spent(name, day))
+            };
+            if let Err(reason) = policy.check(wollet, pset, signer, &spent, &spent_today)? {
+                return Err(Error::PolicyViolation(name.to_string(), reason));
+            }
+            spends.push((name.to_string(), txid, spent));
+        }
+        Ok(spends)
+    }
+
+    /// The assets spent by the loaded wallets for which `signer` signs `pset`
+    ///
+    /// `None` if the PSET does not spend from any loaded wallet of the signer, so that the amounts
+    /// are unknown.
+    pub fn spent_by_signer(
+        &self,
+        pset: &PartiallySignedTransaction,
+        signer: Fingerprint,
+    ) -> Result<Option<policy::Amounts>, Error> {
+        let mut total: Option<policy::Amounts> = None;
+        for (_, wollet) in self.wollets.iter() {
+            if let Some(spent) = policy::spent_by(wollet, pset, signer)? {
+                let total = total.get_or_insert_with(Default::default);
+                for (asset, amount) in spent {
+                    *total.entry(asset).or_default() += amount;
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// The spend the signer `name` can sign in `day` without a TOTP code, as txid and amounts to
+    /// count once the PSET is signed
+    ///
+    /// `None` if a code is needed: the L-BTC signed without codes in the day would exceed the
+    /// threshold, the PSET spends other assets or the amounts are unknown.
+    pub fn totp_free_spend(
+        &self,
+        name: &str,
+        pset: &PartiallySignedTransaction,
+        signer: Fingerprint,
+        day: u64,
+    ) -> Result<Option<(Txid, policy::Amounts)>, Error> {
+        let threshold = self.totps.get(name).map_or(0, |t| t.threshold);
+        let Some(spent) = self.spent_by_signer(pset, signer)? else {
+            return Ok(None);
+        };
+        let policy_asset = self.config.network.policy_asset();
+        if spent.keys().any(|asset| *asset != policy_asset) {
+            return Ok(None);
+        }
+        let txid = pset.extract_tx().map_err(|e| e.to_string())?.txid();
+        if self.totp_counters.is_counted(name, day, &txid) {
+            // Signing again a transaction already counted does not spend more
+            return Ok(Some((txid, policy::Amounts::new())));
+        }
+        let spent_today = self.totp_counters.spent(name, day);
+        let lbtc = |amounts: &policy::Amounts| amounts.get(&policy_asset).copied().unwrap_or(0);
+        if lbtc(&spent_today) + lbtc(&spent) > threshold {
+            return Ok(None);
+        }
+        Ok(Some((txid, spent)))
+    }
+
+    /// Fail if the policy of the wallet `name` would be removed or replaced by the weaker `new`
+    /// while signers of the wallet are loaded
+    ///
+    /// Otherwise calling the server would be enough to lift the policy before signing. The
+    /// signers must be unloaded first, and loading them again requires their secret or device.
+    pub fn check_policy_change(&self, name: &str, new: &Policy) -> Result<(), Error> {
+        let Some(current) = self.policies.get(name) else {
+            return Ok(());
+        };
+        if !new.is_weaker_than(current) {
+            return Ok(());
+        }
+        let wollet = self.wollets.get(name)?;
+        let mut fingerprints = BTreeSet::new();
+        wollet.descriptor().descriptor.for_each_key(|k| {
+            fingerprints.insert(k.master_fingerprint());
+            true
+        });
+        let signers: Vec<_> = self
+            .signers
+            .iter()
+            .filter(|(_, s)| s.can_sign())
+            .filter(|(_, s)| s.fingerprint().is_ok_and(|f| fingerprints.contains(&f)))
+            .map(|(n, _)| n.to_string())
+            .collect();
+        if signers.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::PolicyLocked(name.to_string(), signers.join(", ")))
+        }
+    }
+
     /// Get an available signer identified by name.
     ///
     /// In some cases, like with a jade not currently linked, it may try to connect to it first
@@ -743,3 +1103,20 @@ impl State {
         self.signers.get_available(name, timeout)
     }
 }
+
+/// The request enrolling `totp` for the signer `name`, used to persist it
+pub fn totp_request(name: &str, totp: &Totp) -> Result<Request, Error> {
+    let params = request::SignerEnrollTotp {
+        name: name.to_string(),
+        threshold: totp.threshold,
+        secret: Some(totp.secret()),
+        totp: None,
+    };
+    Ok(Request {
+        jsonrpc: "2.0".into(),
+        id: None,
+        method: Method::SignerEnrollTotp.to_string(),
+        params: Some(serde_json::to_value(params)?),
+        tenant: None,
+    })
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lwk_wollet::hashes::hmac::{Hmac, HmacEngine};
+use lwk_wollet::hashes::{sha1, Hash, HashEngine};
+use rand::RngCore;
+
+use crate::Error;
+
+/// Seconds each code is valid for
+const STEP: u64 = 30;
+
+/// Number of digits of the codes
+const DIGITS: u32 = 6;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Time-based one-time password (RFC 6238) confirming the signatures of a signer
+///
+/// Signing PSETs requires a code from the authenticator app where the secret was enrolled if the
+/// L-BTC signed without codes in the current UTC day would exceed `threshold` satoshi, or if they
+/// spend other assets, so that the API token alone is not enough.
+#[derive(Debug, Clone)]
+pub struct Totp {
+    secret: Vec<u8>,
+
+    /// Maximum amount of L-BTC in satoshi signed without codes per UTC day
+    pub threshold: u64,
+
+    /// The time step of the last accepted code, which cannot be used again
+    last_step: Option<u64>,
+}
+
+impl Totp {
+    /// A TOTP with a random secret
+    pub fn random(threshold: u64) -> Self {
+        let mut secret = vec![0u8; 20];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self::new(secret, threshold)
+    }
+
+    pub fn new(secret: Vec<u8>, threshold: u64) -> Self {
+        Self {
+            secret,
+            threshold,
+            last_step: None,
+        }
+    }
+
+    /// A TOTP with the secret encoded in base32, as shown by authenticator apps
+    pub fn from_base32(secret: &str, threshold: u64) -> Result<Self, Error> {
+        let secret = base32_decode(secret)
+            .ok_or_else(|| Error::Generic(format!("Invalid base32 TOTP secret '{secret}'")))?;
+        Ok(Self::new(secret, threshold))
+    }
+
+    /// The secret encoded in base32
+    pub fn secret(&self) -> String {
+        base32_encode(&self.secret)
+    }
+
+    /// The `otpauth://` URI to enroll the secret in an authenticator app, usually as QR code
+    pub fn uri(&self, name: &str) -> String {
+        format!(
+            "otpauth://totp/LWK:{name}?secret={}&issuer=LWK&algorithm=SHA1&digits={DIGITS}&period={STEP}",
+            self.secret()
+        )
+    }
+
+    pub(crate) fn code_at(&self, step: u64) -> u32 {
+        let mut engine = HmacEngine::<sha1::Hash>::new(&self.secret);
+        engine.input(&step.to_be_bytes());
+        let hmac = Hmac::<sha1::Hash>::from_engine(engine).to_byte_array();
+        let offset = (hmac[19] & 0x0f) as usize;
+        let truncated = u32::from_be_bytes([
+            hmac[offset] & 0x7f,
+            hmac[offset + 1],
+            hmac[offset + 2],
+            hmac[offset + 3],
+        ]);
+        truncated % 10u32.pow(DIGITS)
+    }
+
+    /// Verify `code` at the unix time `now`, accepting also the previous and the next one to
+    /// tolerate clock drifts
+    ///
+    /// Codes are accepted only once.
+    pub fn verify_at(&mut self, code: &str, now: u64) -> bool {
+        let Ok(code) = code.trim().parse::<u32>() else {
+            return false;
+        };
+        let current = now / STEP;
+        for step in [current.saturating_sub(1), current, current + 1] {
+            if self.last_step.is_some_and(|last| step <= last) {
+                continue;
+            }
+            if self.code_at(step) == code {
+                self.last_step = Some(step);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Verify `code` at the current time
+    pub fn verify(&mut self, code: &str) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.verify_at(code, now)
+    }
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut result = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    result
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut result = vec![];
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in s.chars().filter(|c| *c != '=' && !c.is_whitespace()) {
+        let c = c.to_ascii_uppercase() as u8;
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+        }
+    }
+    (!result.is_empty()).then_some(result)
+}
+
+#[derive(Default)]
+pub struct Totps(HashMap<String, Totp>);
+
+impl Totps {
+    pub fn get(&self, name: &str) -> Option<&Totp> {
+        self.0.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Totp> {
+        self.0.get_mut(name)
+    }
+
+    pub fn insert(&mut self, name: &str, totp: Totp) {
+        self.0.insert(name.to_string(), totp);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Totp)> {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp() {
+        // RFC 6238 test vectors for SHA1, truncated to 6 digits
+        let secret = b"12345678901234567890".to_vec();
+        let totp = Totp::new(secret.clone(), 0);
+        assert_eq!(totp.secret(), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        for (time, code) in [
+            (59, 287082),
+            (1111111109, 81804),
+            (1111111111, 50471),
+            (1234567890, 5924),
+            (2000000000, 279037),
+        ] {
+            assert_eq!(totp.code_at(time / STEP), code);
+        }
+
+        let mut totp = Totp::from_base32("gezd gnbv gy3t qojq gezd gnbv gy3t qojq", 0).unwrap();
+        assert!(!totp.verify_at("123456", 59));
+        assert!(!totp.verify_at("not a code", 59));
+        assert!(totp.verify_at("287082", 59));
+        // codes cannot be reused
+        assert!(!totp.verify_at("287082", 59));
+        // previous step still accepted for clock drifts, but not once a later code was used
+        let mut totp = Totp::new(secret, 0);
+        assert!(totp.verify_at("287082", 60));
+        assert!(!totp.verify_at("287082", 89));
+
+        assert!(Totp::from_base32("1", 0).is_err());
+        let random = Totp::random(1_000);
+        let decoded = Totp::from_base32(&random.secret(), 1_000).unwrap();
+        assert_eq!(random.secret, decoded.secret);
+        assert!(random.uri("s").starts_with("otpauth://totp/LWK:s?secret="));
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use lwk_rpc_model::response::Event;
+use lwk_tiny_jrpc::Metrics;
+use lwk_wollet::elements::Txid;
+use lwk_wollet::hashes::hmac::{Hmac, HmacEngine};
+use lwk_wollet::hashes::{sha256, Hash, HashEngine};
+use lwk_wollet::Wollet;
+use serde::{Deserialize, Serialize};
+
+use crate::consts;
+
+/// Header with the HMAC-SHA256 of the body, as `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-Lwk-Signature";
+
+/// Header with the type of the event
+pub const EVENT_HEADER: &str = "X-Lwk-Event";
+
+/// An HTTP endpoint notified of deposits and withdrawals of the wallets
+///
+/// Events are sent as JSON POST requests, signed with the `secret` so that the receiver can verify
+/// they come from this server. Deliveries waiting for a retry are kept in a queue file in the
+/// datadir and deliveries failing [`consts::WEBHOOK_MAX_ATTEMPTS`] times are appended to the
+/// dead-letter log.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    /// The URL receiving the events
+    pub url: String,
+
+    /// The key of the HMAC-SHA256 of the body, sent in the `X-Lwk-Signature` header
+    pub secret: String,
+
+    /// The confirmations after which a deposit is notified again with a `deposit_confirmed`
+    #[serde(default = "default_confirmations")]
+    pub confirmations: u32,
+}
+
+fn default_confirmations() -> u32 {
+    1
+}
+
+impl fmt::Debug for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Webhook")
+            .field("url", &self.url)
+            .field("secret", &"***")
+            .field("confirmations", &self.confirmations)
+            .finish()
+    }
+}
+
+impl Webhook {
+    /// Whether the webhook is notified of `event`
+    fn wants(&self, event: &Event) -> bool {
+        match event {
+            Event::Deposit { .. } | Event::WithdrawalConfirmed { .. } => true,
+            Event::DepositConfirmed { confirmations, .. } => *confirmations == self.confirmations,
+            _ => false,
+        }
+    }
+
+    /// The value of the `X-Lwk-Signature` header for `body`
+    pub fn signature(&self, body: &[u8]) -> String {
+        let mut engine = HmacEngine::<sha256::Hash>::new(self.secret.as_bytes());
+        engine.input(body);
+        format!("sha256={}", Hmac::<sha256::Hash>::from_engine(engine))
+    }
+}
+
+/// A wallet transaction as needed to compute the webhook events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TxState {
+    pub height: Option<u32>,
+    pub confirmations: u32,
+    pub balance: HashMap<String, i64>,
+}
+
+impl TxState {
+    /// Incoming transactions only increase the balance of the wallet
+    fn is_deposit(&self) -> bool {
+        self.balance.values().all(|v| *v >= 0) && self.balance.values().any(|v| *v > 0)
+    }
+}
+
+/// The transactions of the wallet, by txid
+pub(crate) fn tx_states(wollet: &Wollet) -> HashMap<Txid, TxState> {
+    let txs = wollet.transactions().unwrap_or_default();
+    txs.into_iter()
+        .map(|tx| {
+            let balance = tx.balance.iter().map(|(a, v)| (a.to_string(), *v));
+            let state = TxState {
+                height: tx.height,
+                confirmations: tx.confirmations,
+                balance: balance.collect(),
+            };
+            (tx.txid, state)
+        })
+        .collect()
+}
+
+/// The deposits, deposits reaching one of the `confirmations` and confirmed withdrawals of wallet
+/// `name` between the `before` and `after` states
+pub(crate) fn webhook_events(
+    name: &str,
+    before: &HashMap<Txid, TxState>,
+    after: &HashMap<Txid, TxState>,
+    confirmations: &BTreeSet<u32>,
+) -> Vec<Event> {
+    let mut events = vec![];
+    for (txid, tx) in after {
+        let previous = before.get(txid);
+        let reached = |n: u32| previous.map_or(0, |p| p.confirmations) < n && n <= tx.confirmations;
+        if tx.is_deposit() {
+            if previous.is_none() {
+                events.push(Event::Deposit {
+                    name: name.to_string(),
+                    txid: txid.to_string(),
+                    height: tx.height,
+                    balance: tx.balance.clone(),
+                });
+            }
+            if let Some(height) = tx.height {
+                for n in confirmations.iter().filter(|n| reached(**n)) {
+                    events.push(Event::DepositConfirmed {
+                        name: name.to_string(),
+                        txid: txid.to_string(),
+                        height,
+                        confirmations: *n,
+                        balance: tx.balance.clone(),
+                    });
+                }
+            }
+        } else if let (Some(height), true) = (tx.height, reached(1)) {
+            events.push(Event::WithdrawalConfirmed {
+                name: name.to_string(),
+                txid: txid.to_string(),
+                height,
+                balance: tx.balance.clone(),
+            });
+        }
+    }
+    events
+}
+
+/// An event waiting to be delivered to a webhook
+struct Delivery {
+    webhook: usize,
+    kind: &'static str,
+    body: String,
+    attempts: u32,
+    next_attempt: Instant,
+    last_error: String,
+}
+
+/// A delivery of the queue file, attempted again as soon as the server restarts
+#[derive(Serialize, Deserialize)]
+struct QueuedDelivery {
+    url: String,
+    body: String,
+    attempts: u32,
+    last_error: String,
+}
+
+/// A line of the dead-letter log
+#[derive(Serialize)]
+struct DeadLetter<'a> {
+    timestamp: u64,
+    url: &'a str,
+    attempts: u32,
+    error: &'a str,
+    event: serde_json::Value,
+}
+
+/// Delivers the events sent to it to the webhooks, until the sender is dropped
+///
+/// Failed deliveries are retried after `retry_interval`, doubled at every attempt. The deliveries
+/// not done yet are persisted in the queue file, so they are not lost when the server stops.
+pub(crate) struct Dispatcher {
+    webhooks: Vec<Webhook>,
+    queue_path: PathBuf,
+    dead_letter_path: PathBuf,
+    retry_interval: Duration,
+    client: reqwest::blocking::Client,
+    metrics: Option<Metrics>,
+    pending: Vec<Delivery>,
+}
+
+impl Dispatcher {
+    pub fn new(
+        webhooks: Vec<Webhook>,
+        queue_path: PathBuf,
+        dead_letter_path: PathBuf,
+        timeout: Duration,
+        retry_interval: Duration,
+        metrics: Option<Metrics>,
+    ) -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        let pending = load_queue(&queue_path, &webhooks);
+        Self {
+            webhooks,
+            queue_path,
+            dead_letter_path,
+            retry_interval,
+            client,
+            metrics,
+            pending,
+        }
+    }
+
+    /// Start delivering in a new thread
+    pub fn spawn(self) -> (Sender<Event>, JoinHandle<()>) {
+        let (sender, receiver) = mpsc::channel();
+        let handle = std::thread::spawn(move || self.run(receiver));
+        (sender, handle)
+    }
+
+    fn run(mut self, receiver: Receiver<Event>) {
+        loop {
+            let now = Instant::now();
+            let wait = self
+                .pending
+                .iter()
+                .map(|d| d.next_attempt.saturating_duration_since(now))
+                .min()
+                .unwrap_or(Duration::from_secs(3_600));
+            let enqueued = match receiver.recv_timeout(wait) {
+                Ok(event) => self.enqueue(&event),
+                Err(RecvTimeoutError::Timeout) => false,
+                // the deliveries waiting for a retry are in the queue file
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if self.deliver_due() || enqueued {
+                self.save_queue();
+            }
+        }
+    }
+
+    /// Add the deliveries of `event`, returns whether there are any
+    fn enqueue(&mut self, event: &Event) -> bool {
+        let body = match serde_json::to_string(event) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Cannot serialize event: {e}");
+                return false;
+            }
+        };
+        let len = self.pending.len();
+        for (webhook, _) in self
+            .webhooks
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.wants(event))
+        {
+            self.pending.push(Delivery {
+                webhook,
+                kind: event.kind(),
+                body: body.clone(),
+                attempts: 0,
+                next_attempt: Instant::now(),
+                last_error: String::new(),
+            });
+        }
+        self.pending.len() > len
+    }
+
+    /// Attempt the deliveries due, returns whether there were any
+    fn deliver_due(&mut self) -> bool {
+        let now = Instant::now();
+        let (due, waiting): (Vec<_>, _) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|d| d.next_attempt <= now);
+        self.pending = waiting;
+        let attempted = !due.is_empty();
+        for mut delivery in due {
+            let webhook = &self.webhooks[delivery.webhook];
+            let result = self
+                .client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, delivery.kind)
+                .header(
+                    SIGNATURE_HEADER,
+                    webhook.signature(delivery.body.as_bytes()),
+                )
+                .body(delivery.body.clone())
+                .send()
+                .and_then(|r| r.error_for_status());
+            delivery.attempts += 1;
+            match result {
+                Ok(_) => {
+                    tracing::debug!("Delivered {} to webhook {}", delivery.kind, webhook.url)
+                }
+                Err(e) if delivery.attempts >= consts::WEBHOOK_MAX_ATTEMPTS => {
+                    delivery.last_error = e.to_string();
+                    self.dead_letter(&delivery);
+                }
+                Err(e) => {
+                    tracing::debug!("Delivery to webhook {} failed: {e}", webhook.url);
+                    delivery.last_error = e.to_string();
+                    delivery.next_attempt =
+                        Instant::now() + self.retry_interval * 2u32.pow(delivery.attempts - 1);
+                    self.pending.push(delivery);
+                }
+            }
+        }
+        attempted
+    }
+
+    /// Write the pending deliveries to the queue file
+    fn save_queue(&self) {
+        let queue: Vec<_> = self
+            .pending
+            .iter()
+            .map(|d| QueuedDelivery {
+                url: self.webhooks[d.webhook].url.clone(),
+                body: d.body.clone(),
+                attempts: d.attempts,
+                last_error: d.last_error.clone(),
+            })
+            .collect();
+        let tmp_path = self.queue_path.with_extension("tmp");
+        let result = serde_json::to_vec(&queue)
+            .map_err(std::io::Error::from)
+            .and_then(|bytes| {
+                let mut file = File::create(&tmp_path)?;
+                file.write_all(&bytes)?;
+                file.sync_all()?;
+                std::fs::rename(&tmp_path, &self.queue_path)
+            });
+        if let Err(e) = result {
+            tracing::warn!("Cannot write the webhooks queue: {e}");
+        }
+    }
+
+    fn dead_letter(&self, delivery: &Delivery) {
+        let url = &self.webhooks[delivery.webhook].url;
+        tracing::warn!(
+            "Delivery of {} to webhook {url} failed {} times: {}",
+            delivery.kind,
+            delivery.attempts,
+            delivery.last_error
+        );
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.inc(consts::METRIC_WEBHOOK_FAILURES);
+        }
+        let line = DeadLetter {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            url,
+            attempts: delivery.attempts,
+            error: &delivery.last_error,
+            event: serde_json::from_str(&delivery.body).unwrap_or_default(),
+        };
+        let result = serde_json::to_string(&line)
+            .map_err(std::io::Error::from)
+            .and_then(|line| {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.dead_letter_path)?;
+                writeln!(file, "{line}")
+            });
+        if let Err(e) = result {
+            tracing::warn!("Cannot write the webhooks dead-letter log: {e}");
+        }
+    }
+}
+
+/// The deliveries of the queue file at `path`, due immediately
+///
+/// The deliveries to webhooks not in the config anymore are dropped.
+fn load_queue(path: &Path, webhooks: &[Webhook]) -> Vec<Delivery> {
+    let queue: Vec<QueuedDelivery> = match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            tracing::warn!("Cannot read the webhooks queue: {e}");
+            vec![]
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => {
+            tracing::warn!("Cannot read the webhooks queue: {e}");
+            vec![]
+        }
+    };
+    let mut pending = vec![];
+    for queued in queue {
+        let Some(webhook) = webhooks.iter().position(|w| w.url == queued.url) else {
+            tracing::warn!("Dropping a delivery to removed webhook {}", queued.url);
+            continue;
+        };
+        let Ok(event) = serde_json::from_str::<Event>(&queued.body) else {
+            continue;
+        };
+        pending.push(Delivery {
+            webhook,
+            kind: event.kind(),
+            body: queued.body,
+            attempts: queued.attempts,
+            next_attempt: Instant::now(),
+            last_error: queued.last_error,
+        });
+    }
+    pending
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use lwk_tiny_jrpc::tiny_http;
+
+    use super::*;
+
+    #[test]
+    fn test_webhook_events() {
+        let txid = |n: u8| Txid::from_str(&format!("{n:064x}")).unwrap();
+        let tx = |height: Option<u32>, confirmations: u32, amount: i64| TxState {
+            height,
+            confirmations,
+            balance: [("aa".repeat(32), amount)].into(),
+        };
+        let before: HashMap<_, _> = [
+            (txid(1), tx(None, 0, 1_000)),
+            (txid(2), tx(Some(10), 1, 2_000)),
+            (txid(3), tx(None, 0, -500)),
+        ]
+        .into();
+        let after: HashMap<_, _> = [
+            (txid(1), tx(Some(12), 1, 1_000)),
+            (txid(2), tx(Some(10), 3, 2_000)),
+            (txid(3), tx(Some(12), 1, -500)),
+            (txid(4), tx(None, 0, 3_000)),
+        ]
+        .into();
+        let confirmations = [1, 2, 6].into();
+        let mut events = webhook_events("w", &before, &after, &confirmations);
+        events.sort_by_key(|e| match e {
+            Event::Deposit { txid, .. } => (txid.clone(), 0),
+            Event::DepositConfirmed {
+                txid,
+                confirmations,
+                ..
+            } => (txid.clone(), *confirmations),
+            Event::WithdrawalConfirmed { txid, .. } => (txid.clone(), 0),
+            _ => unreachable!(),
+        });
+        let balance = |amount: i64| HashMap::from([("aa".repeat(32), amount)]);
+        assert_eq!(
+            events,
+            vec![
+                Event::DepositConfirmed {
+                    name: "w".into(),
+                    txid: txid(1).to_string(),
+                    height: 12,
+                    confirmations: 1,
+                    balance: balance(1_000),
+                },
+                Event::DepositConfirmed {
+                    name: "w".into(),
+                    txid: txid(2).to_string(),
+                    height: 10,
+                    confirmations: 2,
+                    balance: balance(2_000),
+                },
+                Event::WithdrawalConfirmed {
+                    name: "w".into(),
+                    txid: txid(3).to_string(),
+                    height: 12,
+                    balance: balance(-500),
+                },
+                Event::Deposit {
+                    name: "w".into(),
+                    txid: txid(4).to_string(),
+                    height: None,
+                    balance: balance(3_000),
+                },
+            ]
+        );
+        assert!(webhook_events("w", &after, &after, &confirmations).is_empty());
+    }
+
+    #[test]
+    fn test_webhook_dispatcher() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let webhook = Webhook {
+            url: format!("http://127.0.0.1:{port}/hook"),
+            secret: "secret".into(),
+            confirmations: 2,
+        };
+        let unreachable = Webhook {
+            url: "http://127.0.0.1:1/hook".into(),
+            secret: "secret".into(),
+            confirmations: 1,
+        };
+        assert!(!format!("{webhook:?}").contains("secret\""));
+        let tempdir = tempfile::tempdir().unwrap();
+        let queue_path = tempdir.path().join("queue.json");
+        let dead_letter_path = tempdir.path().join("dead_letter.jsonl");
+        let webhooks = vec![webhook.clone(), unreachable];
+        let dispatcher = Dispatcher::new(
+            webhooks.clone(),
+            queue_path.clone(),
+            dead_letter_path.clone(),
+            Duration::from_secs(5),
+            Duration::from_millis(10),
+            None,
+        );
+        let (sender, handle) = dispatcher.spawn();
+        let event = Event::DepositConfirmed {
+            name: "w".into(),
+            txid: "bb".repeat(32),
+            height: 10,
+            confirmations: 2,
+            balance: HashMap::from([("aa".repeat(32), 1_000)]),
+        };
+        sender.send(event.clone()).unwrap();
+
+        // The first attempt fails and it is retried
+        for status in [500, 200] {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let header = |name: &'static str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv(name))
+                    .map(|h| h.value.to_string())
+            };
+            assert_eq!(header(EVENT_HEADER).unwrap(), "deposit_confirmed");
+            assert_eq!(
+                header(SIGNATURE_HEADER).unwrap(),
+                webhook.signature(body.as_bytes())
+            );
+            assert_eq!(serde_json::from_str::<Event>(&body).unwrap(), event);
+            request.respond(tiny_http::Response::empty(status)).unwrap();
+        }
+
+        // Only the unreachable webhook wants this event, it's still queued when the server stops
+        let mut event = event;
+        if let Event::DepositConfirmed { confirmations, .. } = &mut event {
+            *confirmations = 1;
+        }
+        sender.send(event.clone()).unwrap();
+        drop(sender);
+        handle.join().unwrap();
+        assert!(!dead_letter_path.exists());
+        let queue: Vec<serde_json::Value> =
+            serde_json::from_slice(&std::fs::read(&queue_path).unwrap()).unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0]["url"], "http://127.0.0.1:1/hook");
+
+        // After a restart it's attempted again, until it ends in the dead-letter log
+        let dispatcher = Dispatcher::new(
+            webhooks,
+            queue_path.clone(),
+            dead_letter_path.clone(),
+            Duration::from_secs(5),
+            Duration::from_millis(1),
+            None,
+        );
+        assert_eq!(dispatcher.pending.len(), 1);
+        let (sender, handle) = dispatcher.spawn();
+        while !dead_letter_path.exists() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        drop(sender);
+        handle.join().unwrap();
+        let queue: Vec<serde_json::Value> =
+            serde_json::from_slice(&std::fs::read(&queue_path).unwrap()).unwrap();
+        assert!(queue.is_empty());
+        let dead_letter = std::fs::read_to_string(&dead_letter_path).unwrap();
+        let lines: Vec<serde_json::Value> = dead_letter
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["url"], "http://127.0.0.1:1/hook");
+        assert_eq!(lines[0]["event"], serde_json::to_value(&event).unwrap());
+        assert!(server.try_recv().unwrap().is_none());
+    }
+}
@@ -18,6 +18,11 @@ impl AddressResult {
         Arc::new(self.inner.address().clone().into())
     }
 
+    /// The address without the blinding key, see [`Address::to_unconfidential()`]
+    pub fn unconfidential(&self) -> Arc<Address> {
+        Arc::new(self.inner.unconfidential().into())
+    }
+
     pub fn index(&self) -> u32 {
         self.inner.index()
     }
@@ -44,5 +49,10 @@ mod tests {
         assert_eq!(address_result.address().to_string(), address_str);
 
         assert_eq!(address_result.index(), index);
+
+        assert_eq!(
+            address_result.unconfidential().to_string(),
+            "tex1q6rz28mcfaxtmd6v789l9rrlrusdprr9p634wu8"
+        );
     }
 }
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use crate::{types::AssetId, Address, Network, Txid, WalletTx};
+
+/// Wrapper over [`lwk_wollet::Explorer`]
+#[derive(uniffi::Object, PartialEq, Eq, Debug)]
+pub struct Explorer {
+    inner: lwk_wollet::Explorer,
+}
+
+impl From<lwk_wollet::Explorer> for Explorer {
+    fn from(inner: lwk_wollet::Explorer) -> Self {
+        Self { inner }
+    }
+}
+
+#[uniffi::export]
+impl Explorer {
+    /// See [`lwk_wollet::Explorer::custom`]
+    #[uniffi::constructor]
+    pub fn custom(base_url: &str) -> Arc<Self> {
+        Arc::new(lwk_wollet::Explorer::custom(base_url).into())
+    }
+
+    /// See [`lwk_wollet::Explorer::blockstream`]
+    #[uniffi::constructor]
+    pub fn blockstream(network: &Network) -> Option<Arc<Self>> {
+        lwk_wollet::Explorer::blockstream(network.into()).map(|e| Arc::new(e.into()))
+    }
+
+    /// See [`lwk_wollet::Explorer::liquid_network`]
+    #[uniffi::constructor]
+    pub fn liquid_network(network: &Network) -> Option<Arc<Self>> {
+        lwk_wollet::Explorer::liquid_network(network.into()).map(|e| Arc::new(e.into()))
+    }
+
+    pub fn base_url(&self) -> String {
+        self.inner.base_url().to_string()
+    }
+
+    pub fn tx_url(&self, txid: &Txid) -> String {
+        self.inner.tx_url(&txid.into())
+    }
+
+    /// See [`lwk_wollet::Explorer::unblinded_tx_url`]
+    pub fn unblinded_tx_url(&self, tx: &WalletTx) -> String {
+        tx.unblinded_url(self.inner.base_url())
+    }
+
+    pub fn address_url(&self, address: &Address) -> String {
+        self.inner.address_url(address.as_ref())
+    }
+
+    pub fn asset_url(&self, asset_id: AssetId) -> String {
+        self.inner.asset_url(&asset_id.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Explorer, Network};
+
+    #[test]
+    fn test_explorer() {
+        let explorer = Network::mainnet().default_explorer().unwrap();
+        assert_eq!(explorer.base_url(), "https://blockstream.info/liquid/");
+        let txid = "0000000000000000000000000000000000000000000000000000000000000001";
+        assert_eq!(
+            explorer.tx_url(&txid.parse().unwrap()),
+            format!("https://blockstream.info/liquid/tx/{txid}")
+        );
+
+        let explorer = Explorer::liquid_network(&Network::testnet()).unwrap();
+        assert_eq!(explorer.base_url(), "https://liquid.network/testnet/");
+        assert!(Explorer::blockstream(&Network::regtest_default()).is_none());
+        assert_eq!(
+            Explorer::custom("http://127.0.0.1:3000").base_url(),
+            "http://127.0.0.1:3000/"
+        );
+    }
+}
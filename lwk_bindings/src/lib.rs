@@ -10,6 +10,7 @@ mod desc;
 mod electrum_client;
 mod error;
 mod esplora_client;
+mod explorer;
 mod mnemonic;
 mod network;
 mod persister;
@@ -43,6 +44,7 @@ pub use desc::WolletDescriptor;
 pub use electrum_client::ElectrumClient;
 pub use error::LwkError;
 pub use esplora_client::EsploraClient;
+pub use explorer::Explorer;
 pub use mnemonic::Mnemonic;
 pub use network::Network;
 pub use persister::{ForeignPersister, ForeignPersisterLink};
@@ -2,7 +2,7 @@ use std::{fmt::Display, sync::Arc};
 
 use lwk_common::electrum_ssl::{LIQUID_SOCKET, LIQUID_TESTNET_SOCKET};
 
-use crate::{types::AssetId, ElectrumClient, EsploraClient, LwkError, TxBuilder};
+use crate::{types::AssetId, ElectrumClient, EsploraClient, Explorer, LwkError, TxBuilder};
 
 /// Wrapper over [`lwk_wollet::ElementsNetwork`]
 #[derive(uniffi::Object, PartialEq, Eq, Debug, Clone, Copy)]
@@ -87,6 +87,11 @@ impl Network {
         EsploraClient::new(url, &self.inner.into())
     }
 
+    /// See [`lwk_wollet::ElementsNetwork::explorer`]
+    pub fn default_explorer(&self) -> Option<Arc<Explorer>> {
+        self.inner.explorer().map(|e| Arc::new(e.into()))
+    }
+
     pub fn is_mainnet(&self) -> bool {
         matches!(&self.inner, &lwk_wollet::ElementsNetwork::Liquid)
     }
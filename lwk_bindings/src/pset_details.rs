@@ -203,6 +203,10 @@ impl Recipient {
     pub fn vout(&self) -> u32 {
         self.inner.vout
     }
+
+    pub fn is_confidential(&self) -> bool {
+        self.inner.confidential
+    }
 }
 
 #[cfg(test)]
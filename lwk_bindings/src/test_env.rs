@@ -42,6 +42,6 @@ impl TestEnv {
     }
 
     pub fn electrum_url(&self) -> String {
-        self.inner.electrs.electrum_url.clone()
+        self.inner.electrum_url()
     }
 }
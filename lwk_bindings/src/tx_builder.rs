@@ -17,9 +17,6 @@ pub struct TxBuilder {
     /// So, inside the Mutex we have an option that allow to consume the inner builder and also
     /// to emulate the consumption of this builder after the call to finish.
     inner: Mutex<Option<lwk_wollet::TxBuilder>>,
-
-    /// We are keeping a copy of the network here so that we can read it without lockig the mutex
-    network: lwk_wollet::ElementsNetwork,
 }
 
 impl Display for TxBuilder {
@@ -42,7 +39,6 @@ impl TxBuilder {
     pub fn new(network: &Network) -> Self {
         TxBuilder {
             inner: Mutex::new(Some(lwk_wollet::TxBuilder::new(network.into()))),
-            network: network.into(),
         }
     }
 
@@ -70,6 +66,14 @@ impl TxBuilder {
         Ok(())
     }
 
+    /// Allow sending to unconfidential addresses
+    pub fn allow_unconfidential_addresses(&self) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.allow_unconfidential_addresses());
+        Ok(())
+    }
+
     /// Sets the address to drain excess L-BTC to
     pub fn drain_lbtc_to(&self, address: &Address) -> Result<(), LwkError> {
         let mut lock = self.inner.lock()?;
@@ -81,8 +85,7 @@ impl TxBuilder {
     /// Add a recipient receiving L-BTC
     pub fn add_lbtc_recipient(&self, address: &Address, satoshi: u64) -> Result<(), LwkError> {
         let unvalidated_recipient = UnvalidatedRecipient::lbtc(address.to_string(), satoshi);
-        let recipient = unvalidated_recipient.validate(self.network)?;
-        self.add_validated_recipient(recipient)
+        self.add_unvalidated_recipient(&unvalidated_recipient)
     }
 
     /// Add a recipient receiving the given asset
@@ -97,15 +100,13 @@ impl TxBuilder {
             address: address.to_string(),
             asset: asset.to_string(),
         };
-        let recipient = unvalidated_recipient.validate(self.network)?;
-        self.add_validated_recipient(recipient)
+        self.add_unvalidated_recipient(&unvalidated_recipient)
     }
 
     /// Burn satoshi units of the given asset
     pub fn add_burn(&self, satoshi: u64, asset: &AssetId) -> Result<(), LwkError> {
         let unvalidated_recipient = UnvalidatedRecipient::burn(asset.to_string(), satoshi);
-        let recipient = unvalidated_recipient.validate(self.network)?;
-        self.add_validated_recipient(recipient)
+        self.add_unvalidated_recipient(&unvalidated_recipient)
     }
 
     /// Issue an asset, wrapper of [`lwk_wollet::TxBuilder::issue_asset()`]
@@ -150,6 +151,19 @@ impl TxBuilder {
         Ok(())
     }
 
+    /// Split a reissuance token, wrapper of [`lwk_wollet::TxBuilder::split_reissuance_token()`]
+    pub fn split_reissuance_token(
+        &self,
+        token: AssetId,
+        receivers: Vec<Arc<Address>>,
+    ) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        let receivers = receivers.iter().map(|e| e.as_ref().into()).collect();
+        *lock = Some(inner.split_reissuance_token(token.into(), receivers)?);
+        Ok(())
+    }
+
     /// Manual coin selection, wrapper of [`lwk_wollet::TxBuilder::set_wallet_utxos()`]
     pub fn set_wallet_utxos(&self, utxos: Vec<Arc<OutPoint>>) -> Result<(), LwkError> {
         let mut lock = self.inner.lock()?;
@@ -161,13 +175,41 @@ impl TxBuilder {
         *lock = Some(inner.set_wallet_utxos(utxos));
         Ok(())
     }
+
+    /// Set the locktime, wrapper of [`lwk_wollet::TxBuilder::set_locktime()`]
+    pub fn set_locktime(&self, height: u32) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.set_locktime(height));
+        Ok(())
+    }
+
+    /// Set the sequence of an input, wrapper of [`lwk_wollet::TxBuilder::set_sequence()`]
+    pub fn set_sequence(&self, outpoint: &OutPoint, sequence: u32) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.set_sequence(outpoint.into(), elements::Sequence(sequence)));
+        Ok(())
+    }
+
+    /// Add the ELIP-100 metadata of an asset, wrapper of [`lwk_wollet::TxBuilder::add_asset_metadata()`]
+    pub fn add_asset_metadata(
+        &self,
+        contract: &Contract,
+        issuance_prevout: &OutPoint,
+    ) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.add_asset_metadata(contract.into(), issuance_prevout.into())?);
+        Ok(())
+    }
 }
 
 impl TxBuilder {
-    fn add_validated_recipient(&self, recipient: lwk_wollet::Recipient) -> Result<(), LwkError> {
+    fn add_unvalidated_recipient(&self, recipient: &UnvalidatedRecipient) -> Result<(), LwkError> {
         let mut lock = self.inner.lock()?;
         let inner = lock.take().ok_or_else(builder_finished)?;
-        *lock = Some(inner.add_validated_recipient(recipient));
+        *lock = Some(inner.add_unvalidated_recipient(recipient)?);
         Ok(())
     }
 }
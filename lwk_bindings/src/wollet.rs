@@ -101,6 +101,11 @@ impl Wollet {
             .collect())
     }
 
+    /// Get the fee of a wallet transaction, `None` if the transaction is not in the wallet
+    pub fn tx_fee(&self, txid: &Txid) -> Result<Option<u64>, LwkError> {
+        Ok(self.inner.lock()?.tx_fee(&txid.into()))
+    }
+
     /// Get the unspent transaction outputs of the wallet
     pub fn utxos(&self) -> Result<Vec<Arc<WalletTxOut>>, LwkError> {
         Ok(self
@@ -1,4 +1,4 @@
-use std::{fmt::Display, net::SocketAddr, path::PathBuf};
+use std::{fmt::Display, net::SocketAddr, path::PathBuf, str::FromStr};
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
@@ -10,22 +10,62 @@ pub enum Network {
     Regtest,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+
+    /// A JSON object per line, for log aggregation
+    Json,
+}
+
 /// A liquid wallet with watch-only confidential descriptors and hardware signers.
 /// WARNING: not yet for production use, expect bugs, breaking changes and loss of funds.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    arg_required_else_help = true
+)]
 pub struct Cli {
-    /// Network
-    #[structopt(short, long, default_value = "testnet", env)]
-    pub network: Network,
+    /// Network, if not specified the one in the config file or testnet
+    #[structopt(short, long, env)]
+    pub network: Option<Network>,
 
     /// Server socket address
     #[arg(long, env)]
     pub addr: Option<SocketAddr>,
 
+    /// Config file, default is `$HOME/.lwk/config.toml`
+    ///
+    /// Values given on the command line or with environment variables take precedence over the
+    /// ones in the config file.
+    #[arg(long, env = "LWK_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Token sent to servers requiring authentication, if not specified the first of the
+    /// `auth_tokens` in the config file
+    ///
+    /// The server started with this option accepts it in addition to the config file ones.
+    #[arg(long, env = "LWK_AUTH_TOKEN", hide_env_values = true)]
+    pub auth_token: Option<lwk_app::AuthToken>,
+
+    /// Format of the logs written to stderr, the verbosity is set with `RUST_LOG`
+    #[arg(long, env = "LWK_LOG_FORMAT", default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Send the requests in the given JSON file to the server in a single round trip
+    ///
+    /// The file contains an array of objects with the `method` and optional `params` of the
+    /// requests, the result is the array of their results or errors in the same order.
+    #[arg(long)]
+    pub batch: Option<PathBuf>,
+
     /// The sub command
     #[command(subcommand)]
-    pub command: CliCommand,
+    pub command: Option<CliCommand>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -45,12 +85,34 @@ pub enum CliCommand {
     /// AMP2 commands (expect breaking changes)
     Amp2(Amp2Args),
 
+    /// Account commands, wallets derived from different accounts of the same signer
+    Account(AccountArgs),
+
+    /// Decode transactions and PSETs
+    Decode(DecodeArgs),
+
+    /// Transaction commands, not related to the loaded wallets
+    Tx(TxArgs),
+
+    /// PSET commands
+    #[command(alias = "psbt")]
+    Pset(PsetArgs),
+
     /// Print JSON schema of RPC requests and responses
     ///
     /// E.g. `lwk_cli schema response wallet details` returns the response parameters for
     /// `lwk_cli wallet details`
     Schema(SchemaArgs),
 
+    /// Interactive shell with completion, history and a current wallet and signer
+    Shell,
+
+    /// Config file commands
+    Config(ConfigArgs),
+
+    /// Inspect and clean up the wallet caches in the data directory
+    Datadir(DatadirArgs),
+
     #[clap(hide = true)]
     GenerateCompletion { shell: Shell },
 
@@ -79,6 +141,14 @@ impl CliCommand {
             CliCommand::Server(crate::args::ServerArgs {
                 command: ServerCommand::Start { .. },
             }) | CliCommand::GenerateCompletion { .. }
+                | CliCommand::Config(_)
+                | CliCommand::Datadir(_)
+                | CliCommand::Pset(PsetArgs {
+                    command: PsetCommand::Export { .. } | PsetCommand::Import { .. },
+                })
+                | CliCommand::Signer(SignerArgs {
+                    command: SignerCommand::JadeQrUnlock { .. },
+                })
         )
     }
 
@@ -89,7 +159,15 @@ impl CliCommand {
             CliCommand::Server(crate::args::ServerArgs {
                 command: ServerCommand::Start { .. },
             }) | CliCommand::GenerateCompletion { .. }
+                | CliCommand::Config(_)
+                | CliCommand::Datadir(_)
                 | CliCommand::Generate { .. }
+                | CliCommand::Pset(PsetArgs {
+                    command: PsetCommand::Export { .. } | PsetCommand::Import { .. },
+                })
+                | CliCommand::Signer(SignerArgs {
+                    command: SignerCommand::JadeQrUnlock { .. },
+                })
         )
     }
 }
@@ -129,6 +207,10 @@ pub enum MainCommand {
     Signer(SignerSubCommands),
     Asset(AssetSubCommands),
     Amp2(Amp2SubCommands),
+    Account(AccountSubCommands),
+    Decode(DecodeSubCommands),
+    Tx(TxSubCommands),
+    Pset(PsetSubCommands),
     Schema,
 }
 
@@ -142,6 +224,7 @@ pub struct ServerSubCommands {
 pub enum ServerSubCommandsEnum {
     // Start is a special command
     Scan,
+    Status,
     Stop,
 }
 
@@ -155,21 +238,33 @@ pub struct WalletSubCommands {
 pub enum WalletSubCommandsEnum {
     Load,
     Unload,
+    Forget,
     List,
     Address,
+    Addresses,
+    AddressInfo,
+    VerifyPayment,
     Balance,
     Send,
+    BumpFee,
     Issue,
     Reissue,
     MultisigDesc,
     Broadcast,
+    Outbox,
     Details,
+    RetryUnblind,
     Combine,
     PsetDetails,
     Utxos,
     Txs,
+    ExportHistory,
     SetTxMemo,
     SetAddrMemo,
+    SetPolicy,
+    PolicyStatus,
+    Backup,
+    Restore,
 }
 
 #[derive(Debug, Args)]
@@ -189,6 +284,7 @@ pub enum SignerSubCommandsEnum {
     Details,
     List,
     Sign,
+    EnrollTotp,
     SinglesigDesc,
     Xpub,
 }
@@ -222,6 +318,55 @@ pub enum Amp2SubCommandsEnum {
     Cosign,
 }
 
+#[derive(Debug, Args)]
+pub struct AccountSubCommands {
+    #[command(subcommand)]
+    pub command: AccountSubCommandsEnum,
+}
+
+#[derive(Debug, Subcommand, ValueEnum, Clone)]
+pub enum AccountSubCommandsEnum {
+    New,
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeSubCommands {
+    #[command(subcommand)]
+    pub command: DecodeSubCommandsEnum,
+}
+
+#[derive(Debug, Subcommand, ValueEnum, Clone)]
+pub enum DecodeSubCommandsEnum {
+    Tx,
+    Pset,
+}
+
+#[derive(Debug, Args)]
+pub struct TxSubCommands {
+    #[command(subcommand)]
+    pub command: TxSubCommandsEnum,
+}
+
+#[derive(Debug, Subcommand, ValueEnum, Clone)]
+pub enum TxSubCommandsEnum {
+    Broadcast,
+}
+
+#[derive(Debug, Args)]
+pub struct PsetSubCommands {
+    #[command(subcommand)]
+    pub command: PsetSubCommandsEnum,
+}
+
+#[derive(Debug, Subcommand, ValueEnum, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum PsetSubCommandsEnum {
+    SessionSave,
+    SessionLoad,
+    SessionMerge,
+}
+
 #[derive(ValueEnum, Clone, Debug)]
 pub enum SignerKind {
     Software,
@@ -246,6 +391,39 @@ pub enum XpubKind {
     Bip87,
 }
 
+#[derive(ValueEnum, Clone, Debug)]
+pub enum AddressChain {
+    /// Receiving addresses
+    External,
+
+    /// Change addresses
+    Internal,
+}
+
+impl std::fmt::Display for AddressChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AddressChain::External => write!(f, "external"),
+            AddressChain::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum HistoryFormat {
+    Csv,
+    Json,
+}
+
+impl std::fmt::Display for HistoryFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HistoryFormat::Csv => write!(f, "csv"),
+            HistoryFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
 impl std::fmt::Display for XpubKind {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -268,6 +446,29 @@ pub enum SignerCommand {
         emulator: Option<SocketAddr>,
     },
 
+    /// Unlock a Jade in QR mode, without connecting it by cable
+    ///
+    /// Scan the `jade-pin` qr codes shown by the Jade and pass their UR parts, the request is
+    /// forwarded to the PIN server and the UR parts of the reply are returned for the Jade to
+    /// scan. Repeat for every request shown by the Jade, until it is unlocked.
+    JadeQrUnlock {
+        /// The scanned `jade-pin` UR parts, in any order
+        #[arg(long, required = true, num_args = 1..)]
+        ur: Vec<String>,
+
+        /// Show the qr codes of the reply UR parts in the terminal, on the standard error
+        #[arg(long)]
+        qr: bool,
+
+        /// The maximum number of bytes in each UR part
+        #[arg(long, default_value_t = 200)]
+        max_fragment_len: usize,
+
+        /// Milliseconds between the qr codes of an animated sequence
+        #[arg(long, default_value_t = 300)]
+        frame_interval: u64,
+    },
+
     /// Load a software signer giving it a name
     LoadSoftware {
         #[arg(short, long, env)]
@@ -331,6 +532,32 @@ pub enum SignerCommand {
 
         #[arg(long)]
         pset: String,
+
+        /// The TOTP code, needed if the signer enrolled TOTP and the PSET exceeds its daily threshold
+        #[arg(long)]
+        totp: Option<String>,
+    },
+
+    /// Require TOTP codes to sign PSETs spending more than a daily threshold
+    ///
+    /// Returns the secret to enroll in an authenticator app.
+    EnrollTotp {
+        #[arg(short, long, env)]
+        signer: String,
+
+        /// Maximum amount of L-BTC in satoshi signed without TOTP codes per UTC day
+        ///
+        /// Signing PSETs spending other assets always requires a code.
+        #[arg(long)]
+        threshold: u64,
+
+        /// The TOTP secret in base32, if not given a random one is generated
+        #[arg(long)]
+        secret: Option<String>,
+
+        /// A code of the currently enrolled secret, needed to change it or its threshold
+        #[arg(long)]
+        totp: Option<String>,
     },
 
     ///  Prints a singlesig descriptor using this signer key
@@ -404,12 +631,14 @@ impl Display for SinglesigKind {
 #[derive(ValueEnum, Clone, Debug)]
 pub enum MultisigKind {
     Wsh,
+    WshSorted,
 }
 
 impl Display for MultisigKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             MultisigKind::Wsh => write!(f, "wsh"),
+            MultisigKind::WshSorted => write!(f, "wsh-sorted"),
         }
     }
 }
@@ -439,6 +668,17 @@ pub enum WalletCommand {
         wallet: String,
     },
 
+    /// Unload a wallet and optionally delete its cache in the data directory
+    Forget {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// Delete the cached blockchain data of the wallet, it must be scanned again if reloaded
+        #[arg(long)]
+        delete_cache: bool,
+    },
+
     /// List existing loaded wallets
     List,
 
@@ -469,6 +709,82 @@ pub enum WalletCommand {
         /// the given number is the number of pixel per qr code module
         #[arg(long)]
         with_uri_qr: Option<u8>,
+
+        /// Show the qr code of the address in the terminal, on the standard error
+        #[arg(long)]
+        qr: bool,
+    },
+
+    /// Get a range of addresses of the given wallet name, with their derivation index
+    ///
+    /// E.g. to pre-generate deposit addresses and load them in another system
+    Addresses {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// The derivation index of the first address
+        #[arg(long, default_value_t = 0)]
+        start: u32,
+
+        /// The number of addresses, at most 10000 for each call
+        #[arg(long, default_value_t = 100)]
+        count: u32,
+
+        /// The chain of the addresses
+        #[arg(long, value_enum, default_value_t = AddressChain::External)]
+        chain: AddressChain,
+    },
+
+    /// Get whether an address belongs to the given wallet name, and its derivation
+    ///
+    /// Addresses are looked up to the gap limit after the last used one.
+    AddressInfo {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// The address, confidential or not
+        #[arg(long)]
+        address: String,
+    },
+
+    /// Verify that a transaction output pays an amount of an asset to an address of the wallet
+    ///
+    /// The transaction must be synced in the wallet and confirmed. With a software signer, the
+    /// result is signed with its key at m/5413'/0'.
+    VerifyPayment {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// The transaction claimed to pay the wallet
+        #[arg(long)]
+        txid: String,
+
+        /// The output of the transaction claimed to pay the wallet
+        #[arg(long)]
+        vout: u32,
+
+        /// The address claimed to be paid, confidential or not
+        #[arg(long)]
+        address: String,
+
+        /// The asset claimed to be paid
+        #[arg(long)]
+        asset: String,
+
+        /// The amount claimed to be paid, in satoshi
+        #[arg(long)]
+        satoshi: u64,
+
+        /// Confirmations required for the payment to be verified, 1 if not specified
+        #[arg(long)]
+        min_confirmations: Option<u32>,
+
+        /// Software signer signing the result
+        #[arg(short, long)]
+        signer: Option<String>,
     },
 
     /// Get the balance of the given wallet name
@@ -496,9 +812,18 @@ pub enum WalletCommand {
         ///
         /// Address can either be a valid address or "burn" if you want to burn the asset, i.e.
         /// create a provably unspendable output.
-        #[arg(long, required = true)]
+        #[arg(long, required_unless_present = "from_csv")]
         recipient: Vec<String>,
 
+        /// Read the recipients from a CSV file with rows "address,asset_id,satoshi"
+        ///
+        /// An empty asset_id is the network policy asset, a first line starting with "address" is
+        /// a header and it's skipped. Every row is validated before creating the PSET: addresses
+        /// must be of the network, amounts of the policy asset not below dust, and the same
+        /// address and asset must not be repeated.
+        #[arg(long, conflicts_with = "recipient")]
+        from_csv: Option<PathBuf>,
+
         /// Fee rate to use
         #[arg(long)]
         fee_rate: Option<f32>,
@@ -519,6 +844,24 @@ pub enum WalletCommand {
         fee_rate: Option<f32>,
     },
 
+    /// Bump the fee of an unconfirmed wallet transaction
+    ///
+    /// The returned PSET spends the same inputs of the original transaction and it has a lower
+    /// change, it must be signed and broadcast to replace the original transaction.
+    BumpFee {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// The transaction id of the transaction to replace
+        #[arg(long)]
+        txid: String,
+
+        /// The new fee rate to use
+        #[arg(long)]
+        fee_rate: f32,
+    },
+
     /// Issue an asset
     Issue {
         /// Wallet name
@@ -627,6 +970,16 @@ pub enum WalletCommand {
         pset: String,
     },
 
+    /// List the broadcast transactions not yet confirmed
+    ///
+    /// They are broadcast again at every scan, until confirmed or replaced by a conflicting
+    /// transaction.
+    Outbox {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+    },
+
     /// Get detailed information about the wallet
     Details {
         /// Wallet name
@@ -634,6 +987,19 @@ pub enum WalletCommand {
         wallet: String,
     },
 
+    /// Try again to unblind the wallet outputs that cannot be unblinded
+    RetryUnblind {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// A blinding private key in hex, for outputs blinded by the sender with a different key
+        ///
+        /// Can be specified multiple times.
+        #[arg(long)]
+        blinding_key: Vec<String>,
+    },
+
     /// Combine PSETs
     Combine {
         /// Wallet name
@@ -680,6 +1046,20 @@ pub enum WalletCommand {
         with_tickers: bool,
     },
 
+    /// Export the wallet transaction history for accounting, one row per transaction and asset
+    ExportHistory {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        #[arg(long, default_value_t = HistoryFormat::Csv)]
+        format: HistoryFormat,
+
+        /// Write the history to this file instead of returning it
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
     /// Get a transaction
     Tx {
         /// Wallet name
@@ -724,6 +1104,67 @@ pub enum WalletCommand {
         #[arg(long)]
         memo: String,
     },
+
+    /// Set the spending policy of the wallet
+    ///
+    /// The policy is checked before a signer loaded in the server signs a PSET spending from the
+    /// wallet. Calling it without rules removes the policy. While signers of the wallet are
+    /// loaded, the policy can only be made stricter and the wallet cannot be unloaded.
+    SetPolicy {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// Maximum amount of L-BTC in satoshi the wallet can spend per UTC day, fees included
+        #[arg(long)]
+        daily_limit: Option<u64>,
+
+        /// Maximum amount of another asset the wallet can spend per UTC day, as ASSET_ID=AMOUNT
+        ///
+        /// Can be specified multiple times. If any daily limit is set, the assets without a limit
+        /// cannot be spent.
+        #[arg(long, value_parser = parse_asset_daily_limit)]
+        asset_daily_limit: Vec<(String, u64)>,
+
+        /// An address the wallet can send to, if given the wallet can send only to these addresses
+        ///
+        /// Can be specified multiple times.
+        #[arg(long)]
+        allow: Vec<String>,
+
+        /// Fingerprint of a cosigner that must sign before the signers loaded in the server
+        ///
+        /// Can be specified multiple times.
+        #[arg(long)]
+        required_cosigner: Vec<String>,
+    },
+
+    /// Get the spending policy of the wallet and how much it spent today
+    PolicyStatus {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+    },
+
+    /// Export a portable backup of the wallet
+    ///
+    /// The backup contains the descriptor, the birthday and the memos of the wallet, but no key.
+    Backup {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+    },
+
+    /// Restore a wallet from a backup created with `wallet backup`
+    Restore {
+        /// The name given to the restored wallet
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// The backup, in JSON
+        #[arg(long)]
+        backup: String,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -853,6 +1294,267 @@ pub enum Amp2Command {
     },
 }
 
+#[derive(ValueEnum, Clone, Debug)]
+pub enum AccountKind {
+    Wpkh,
+    Shwpkh,
+    Wsh,
+}
+
+impl Display for AccountKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountKind::Wpkh => write!(f, "wpkh"),
+            AccountKind::Shwpkh => write!(f, "shwpkh"),
+            AccountKind::Wsh => write!(f, "wsh"),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct AccountArgs {
+    #[command(subcommand)]
+    pub command: AccountCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AccountCommand {
+    /// Create a wallet for the next unused account of a signer and load it
+    ///
+    /// Singlesig accounts use the signer key at `purpose'/coin_type'/account'`, multisig accounts
+    /// use the signer key at `87'/coin_type'/account'` and the cosigners keys.
+    New {
+        /// Signer name
+        #[arg(short, long, env)]
+        signer: String,
+
+        #[arg(long)]
+        kind: AccountKind,
+
+        #[arg(long)]
+        descriptor_blinding_key: BlindingKeyKind,
+
+        /// Wallet name, if not specified "<signer>-<kind>-<index>" is used
+        #[arg(short, long)]
+        wallet: Option<String>,
+
+        /// Number of signatures required to spend, only for multisig accounts
+        #[arg(long)]
+        threshold: Option<u32>,
+
+        /// Cosigners xpubs with key origin, only for multisig accounts
+        #[arg(long)]
+        cosigner: Vec<String>,
+    },
+
+    /// List the accounts of a signer with their balances and the total balance
+    List {
+        /// Signer name
+        #[arg(short, long, env)]
+        signer: String,
+
+        /// Replace asset ids with tickers when possible
+        #[arg(long)]
+        with_tickers: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeArgs {
+    #[command(subcommand)]
+    pub command: DecodeCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DecodeCommand {
+    /// Decode a transaction
+    ///
+    /// Amounts and assets are returned if explicit or if they can be unblinded by the given
+    /// wallet.
+    Tx {
+        /// Transaction in hex format
+        #[arg(long)]
+        tx: String,
+
+        /// Wallet name, used to unblind inputs and outputs belonging to the wallet
+        #[arg(short, long)]
+        wallet: Option<String>,
+    },
+
+    /// Decode a PSET
+    ///
+    /// Amounts and assets are returned if present in the PSET, explicit or if they can be
+    /// unblinded by the given wallet.
+    Pset {
+        /// PSET in base64 format
+        #[arg(short, long)]
+        pset: String,
+
+        /// Wallet name, used to unblind inputs and outputs belonging to the wallet
+        #[arg(short, long)]
+        wallet: Option<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct TxArgs {
+    #[command(subcommand)]
+    pub command: TxCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TxCommand {
+    /// Broadcast a transaction or finalize a PSET and broadcast its transaction
+    ///
+    /// The transaction doesn't need to be related to the loaded wallets. With `--dry-run` the
+    /// transaction is not broadcast, but checked by the Elements node of the server, if any.
+    Broadcast {
+        /// Transaction in hex format
+        #[arg(long, required_unless_present = "pset", conflicts_with = "pset")]
+        tx: Option<String>,
+
+        /// PSET in base64 format
+        #[arg(short, long)]
+        pset: Option<String>,
+
+        /// Check the transaction without the broadcast
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct PsetArgs {
+    #[command(subcommand)]
+    pub command: PsetCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PsetCommand {
+    /// Multisig signing sessions, a single file exchanged between the cosigners
+    Session(PsetSessionArgs),
+
+    /// Export a PSET as `crypto-psbt` UR parts, for mobile wallets and signers scanning QR codes
+    Export {
+        /// PSET in base64 format
+        #[arg(short, long)]
+        pset: String,
+
+        /// Show the qr codes of the UR parts in the terminal, on the standard error
+        ///
+        /// Large PSETs are split in multiple parts, shown as an animated sequence until enter is
+        /// pressed.
+        #[arg(long)]
+        qr: bool,
+
+        /// The maximum number of bytes in each UR part
+        #[arg(long, default_value_t = 200)]
+        max_fragment_len: usize,
+
+        /// Milliseconds between the qr codes of an animated sequence
+        #[arg(long, default_value_t = 300)]
+        frame_interval: u64,
+    },
+
+    /// Import a PSET from the scanned `crypto-psbt` UR parts, such as the ones of a PSET signed
+    /// by an airgapped signer
+    Import {
+        /// The scanned UR parts, in any order
+        #[arg(long, required = true, num_args = 1..)]
+        ur: Vec<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct PsetSessionArgs {
+    #[command(subcommand)]
+    pub command: PsetSessionCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PsetSessionCommand {
+    /// Add the signatures of a PSET to the session file, creating it if it does not exist
+    Save {
+        /// The session file
+        #[arg(short, long)]
+        file: String,
+
+        /// PSET in base64 format
+        #[arg(short, long)]
+        pset: String,
+
+        /// Fingerprint of a cosigner, used only when creating the session
+        ///
+        /// If not given, the fingerprints in the key origins of the PSET inputs are used.
+        #[arg(long)]
+        participant: Vec<String>,
+
+        /// The number of signatures needed for each input, required when creating the session
+        #[arg(long)]
+        threshold: Option<u32>,
+    },
+
+    /// Show the state of the session file
+    Load {
+        /// The session file
+        #[arg(short, long)]
+        file: String,
+    },
+
+    /// Merge other session files of the same PSET in the session file
+    Merge {
+        /// The session file
+        #[arg(short, long)]
+        file: String,
+
+        /// Other session files
+        #[arg(long, required(true))]
+        other: Vec<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Write a config file with the defaults of the network, to be edited
+    Init {
+        /// Overwrite the config file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct DatadirArgs {
+    /// Location of the LWK data, the one of the config file or `$HOME/.lwk` if not specified
+    #[arg(long, env = "LWK_DATADIR")]
+    pub datadir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: DatadirCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DatadirCommand {
+    /// List the wallet caches, with the wallets using them, their size and their last sync
+    List,
+
+    /// Delete the caches not used by any wallet, the server must be stopped
+    Prune {
+        /// Keep the caches written in the last given days
+        #[arg(long, default_value_t = 0)]
+        older_than_days: u64,
+    },
+
+    /// Remove interrupted writes and unreadable updates from the caches, the server must be stopped
+    Vacuum,
+}
+
 #[derive(Debug, Args)]
 pub struct ServerArgs {
     #[command(subcommand)]
@@ -864,38 +1566,98 @@ pub enum ServerCommand {
     /// Start the server
     Start {
         /// Electrum URL, if not specified a reasonable default is used according to the network
-        #[arg(short, long)]
+        #[arg(short, long, env = "LWK_ELECTRUM_URL")]
         electrum_url: Option<String>,
 
-        #[arg(long)]
+        #[arg(long, env = "LWK_REGISTRY_URL")]
         #[cfg(feature = "registry")]
         /// Needed only in regtest because public network have their official defaults
         registry_url: Option<String>,
 
-        #[arg(long)]
+        #[arg(long, env = "LWK_ESPLORA_API_URL")]
         /// Esplora API URL, if not specified a reasonable default is used according to the network
         esplora_api_url: Option<String>,
 
         /// Location for logs, server state, and other LWK data
         ///
         /// Default is `$HOME/.lwk`, or `./.lwk` if unable to determine the home dir
-        #[arg(long)]
+        #[arg(long, env = "LWK_DATADIR")]
         datadir: Option<PathBuf>,
 
         /// Timeout for RPC and HWW requests (seconds)
-        #[arg(long)]
+        #[arg(long, env = "LWK_TIMEOUT")]
         timeout: Option<u64>,
 
         /// Interval between blockchain scans (seconds)
-        #[arg(long)]
+        #[arg(long, env = "LWK_SCANNING_INTERVAL")]
         scanning_interval: Option<u64>,
+
+        /// Fee rate of the transactions created without an explicit fee rate (sat/kvB)
+        #[arg(long, env = "LWK_FEE_RATE")]
+        fee_rate: Option<f32>,
+
+        /// Do not use ELIP200 discounted fees for Confidential Transactions
+        ///
+        /// Use this if the backend node or its peers do not relay transactions paying discounted
+        /// fees (Elements before 23.2.2).
+        #[arg(long)]
+        disable_ct_discount: bool,
+
+        /// Maximum number of requests per second accepted from each client address
+        ///
+        /// Requests of a batch count individually, if not specified requests are not limited.
+        #[arg(long)]
+        rate_limit: Option<u32>,
+
+        /// Timeout for the response of a method, as METHOD=SECONDS, can be repeated
+        ///
+        /// Clients get an error after the timeout, while the method keeps running in background.
+        /// For instance `--method-timeout scan=30`.
+        #[arg(long, value_parser = parse_method_timeout)]
+        method_timeout: Vec<(String, u64)>,
+
+        /// Maximum size of PSETs and PSET sessions in requests (characters)
+        #[arg(long)]
+        max_pset_size: Option<usize>,
+
+        /// Maximum number of addressees of a send
+        #[arg(long)]
+        max_addressees: Option<usize>,
+
+        /// Maximum number of loaded wallets
+        #[arg(long)]
+        max_wallets: Option<usize>,
     },
 
     /// Wait until an entire blockchain scan has been completed
     Scan,
 
+    /// Show the measured health of the Electrum servers, also of the previous runs
+    Status,
+
     /// Stop the server
     ///
     /// Alternatively the server can be stopped also with SIGINT (ctrl-c)
     Stop,
 }
+
+fn parse_asset_daily_limit(s: &str) -> Result<(String, u64), String> {
+    let (asset, amount) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid asset daily limit '{s}', expected ASSET_ID=AMOUNT"))?;
+    let amount = amount
+        .parse()
+        .map_err(|e| format!("invalid amount '{amount}': {e}"))?;
+    Ok((asset.to_string(), amount))
+}
+
+fn parse_method_timeout(s: &str) -> Result<(String, u64), String> {
+    let (method, secs) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid method timeout '{s}', expected METHOD=SECONDS"))?;
+    lwk_app::method::Method::from_str(method).map_err(|e| e.to_string())?;
+    let secs = secs
+        .parse()
+        .map_err(|e| format!("invalid seconds '{secs}': {e}"))?;
+    Ok((method.to_string(), secs))
+}
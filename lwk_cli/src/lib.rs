@@ -4,47 +4,70 @@
 
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
     time::Duration,
 };
 
 use anyhow::{anyhow, Context};
-use clap::CommandFactory;
-use env_logger::Env;
-use lwk_app::Config;
+use clap::{CommandFactory, ValueEnum};
+use elements::pset::PartiallySignedTransaction;
+use lwk_app::{Config, ConfigFile};
+use lwk_wollet::ElementsNetwork;
 use serde_json::Value;
 
 use crate::args::{
-    Amp2Command, AssetCommand, CliCommand, Network, ServerCommand, SignerCommand, WalletCommand,
+    AccountCommand, Amp2Command, AssetCommand, CliCommand, ConfigCommand, DatadirCommand,
+    DecodeCommand, Network, PsetCommand, PsetSessionCommand, ServerCommand, SignerCommand,
+    TxCommand, WalletCommand,
 };
 pub use args::Cli;
 
 pub use args::{
-    AssetSubCommandsEnum, ServerSubCommandsEnum, SignerSubCommandsEnum, WalletSubCommandsEnum,
+    AccountSubCommandsEnum, AssetSubCommandsEnum, DecodeSubCommandsEnum, ServerSubCommandsEnum,
+    SignerSubCommandsEnum, TxSubCommandsEnum, WalletSubCommandsEnum,
 };
 
 mod args;
+mod logging;
+mod qr;
+mod recipients;
 mod schema;
+mod shell;
 
 pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
-    let directive = if let CliCommand::Server(args::ServerArgs {
+    let directive = if let Some(CliCommand::Server(args::ServerArgs {
         command: ServerCommand::Start { .. },
-    }) = args.command
+    })) = args.command
     {
         "info"
     } else {
         "warn"
     };
-    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or(directive));
-
-    match builder.try_init() {
-        Ok(_) => log::info!("logging initialized"),
-        Err(_) => log::debug!("logging already initialized"),
+    if logging::init(directive, args.log_format) {
+        tracing::info!("logging initialized");
+    } else {
+        tracing::debug!("logging already initialized");
     }
 
-    log::info!("CLI initialized with args: {:?}", args);
+    tracing::info!("CLI initialized with args: {:?}", args);
+
+    let config_path = match args.config.clone() {
+        Some(path) => path,
+        None => ConfigFile::default_path().unwrap_or(PathBuf::from("config.toml")),
+    };
+    let config_file = match args.command {
+        Some(CliCommand::Config(_)) => ConfigFile::default(),
+        _ => ConfigFile::load(&config_path)?,
+    };
+    let cli_network = match (args.network.clone(), config_file.network.as_deref()) {
+        (Some(network), _) => network,
+        (None, Some(network)) => Network::from_str(network, true)
+            .map_err(|e| anyhow!("Invalid network in {}: {e}", config_path.display()))?,
+        (None, None) => Network::Testnet,
+    };
 
     // TODO: improve network types conversion or comparison
-    let (network, default_port) = match args.network {
+    let (network, default_port) = match cli_network {
         Network::Mainnet => ("liquid", 32110),
         Network::Testnet => ("liquid-testnet", 32111),
         Network::Regtest => ("liquid-regtest", 32112),
@@ -52,24 +75,30 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
 
     let addr = args
         .addr
+        .or(config_file.addr)
         .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), default_port));
-    let client = lwk_app::Client::new(addr)?;
-
-    // verify the server is up if needed
-    if args.command.requires_server_running() {
-        let version = client
-            .version()
-            .with_context(|| format!("Is the server at {:?} running?", addr))?;
-        let server_network = version.network;
+    let auth_token = args.auth_token.clone().or_else(|| {
+        let tokens = config_file.auth_tokens.as_ref()?;
+        tokens.first().cloned()
+    });
+    let client = lwk_app::Client::with_auth_token(addr, auth_token)?;
 
-        if server_network != network {
-            return Err(anyhow!(
-                "Inconsistent networks (cli: {network}, server: {server_network})",
-            ));
+    let command = match (args.command, args.batch) {
+        (Some(command), None) => command,
+        (None, Some(path)) => {
+            check_server(&client, addr, network)?;
+            return batch(&client, &path);
         }
+        (Some(_), Some(_)) => return Err(anyhow!("--batch cannot be used with a command")),
+        (None, None) => return Err(anyhow!("A command or --batch is required")),
+    };
+
+    // verify the server is up if needed
+    if command.requires_server_running() {
+        check_server(&client, addr, network)?;
     }
 
-    Ok(match args.command {
+    Ok(match command {
         CliCommand::Server(a) => {
             match a.command {
                 ServerCommand::Start {
@@ -80,6 +109,13 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                     datadir,
                     timeout,
                     scanning_interval,
+                    fee_rate,
+                    disable_ct_discount,
+                    rate_limit,
+                    method_timeout,
+                    max_pset_size,
+                    max_addressees,
+                    max_wallets,
                 } => {
                     let (tx, rx) = std::sync::mpsc::channel();
                     let _ = ctrlc::try_set_handler(move || {
@@ -87,23 +123,39 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                     });
 
                     // start the app with default host/port
-                    let datadir = datadir.unwrap_or_else(|| {
-                        Config::default_home().unwrap_or(std::path::PathBuf::from("."))
-                    });
-                    let mut config = match args.network {
-                        Network::Mainnet => Config::default_mainnet(datadir),
-                        Network::Testnet => Config::default_testnet(datadir),
-                        Network::Regtest => Config::default_regtest(datadir),
-                    };
+                    let mut config = default_config(&cli_network);
+                    config.apply_file(&config_file);
+                    if let Some(datadir) = datadir {
+                        config.datadir = datadir;
+                    }
                     if let Some(timeout) = timeout {
                         config.timeout = Duration::from_secs(timeout);
                     };
                     if let Some(scanning_interval) = scanning_interval {
                         config.scanning_interval = Duration::from_secs(scanning_interval);
                     };
+                    if let Some(fee_rate) = fee_rate {
+                        config.default_fee_rate = Some(fee_rate);
+                    }
+                    if let Some(token) = args.auth_token {
+                        if !config.auth_tokens.contains(&token) {
+                            config.auth_tokens.push(token);
+                        }
+                    }
+                    config.ct_discount = !disable_ct_discount;
+                    config.rate_limit = rate_limit.map(lwk_app::RateLimit::per_second);
+                    config.method_timeouts = method_timeout
+                        .into_iter()
+                        .map(|(method, secs)| (method, Duration::from_secs(secs)))
+                        .collect();
+                    config.limits = lwk_app::Limits {
+                        max_pset_size,
+                        max_addressees,
+                        max_wallets,
+                    };
                     if let Some(url) = electrum_url {
                         config.electrum_url = url;
-                    } else if let Network::Regtest = args.network {
+                    } else if config.electrum_url.is_empty() {
                         anyhow::bail!("on regtest you have to specify --electrum-url");
                     };
                     if let Some(url) = esplora_api_url {
@@ -122,19 +174,19 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
 
                     // get the app version
                     let version = client.version()?.version;
-                    log::info!("App running version {}", version);
+                    tracing::info!("App running version {}", version);
 
                     loop {
                         match rx.recv_timeout(Duration::from_millis(100)) {
                             Ok(_) => {
-                                log::debug!("Received ctrl-c signal");
+                                tracing::debug!("Received ctrl-c signal");
                                 break;
                             }
                             Err(_) => {
                                 if app.is_running().unwrap_or(false) {
                                     continue;
                                 } else {
-                                    log::debug!("Received stop signal");
+                                    tracing::debug!("Received stop signal");
                                     break;
                                 }
                             }
@@ -142,11 +194,14 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                     }
                     app.stop()?;
                     app.join_threads()?;
-                    log::info!("Threads ended");
+                    tracing::info!("Threads ended");
                 }
                 ServerCommand::Scan => {
                     client.scan()?;
                 }
+                ServerCommand::Status => {
+                    return Ok(serde_json::to_value(client.server_status()?)?);
+                }
                 ServerCommand::Stop => {
                     client.stop()?;
                 }
@@ -163,8 +218,30 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 let j = client.signer_jade_id(emulator)?;
                 serde_json::to_value(j)?
             }
-            SignerCommand::Sign { signer, pset } => {
-                let r = client.signer_sign(signer, pset)?;
+            SignerCommand::JadeQrUnlock {
+                ur,
+                qr,
+                max_fragment_len,
+                frame_interval,
+            } => {
+                let request = lwk_jade::qr::QrPinRequest::from_ur(&ur)?;
+                let ur = request.forward_blocking(max_fragment_len)?;
+                if qr {
+                    qr::animate(&ur, Duration::from_millis(frame_interval))?;
+                }
+                serde_json::json!({ "ur": ur })
+            }
+            SignerCommand::Sign { signer, pset, totp } => {
+                let r = client.signer_sign(signer, pset, totp)?;
+                serde_json::to_value(r)?
+            }
+            SignerCommand::EnrollTotp {
+                signer,
+                threshold,
+                secret,
+                totp,
+            } => {
+                let r = client.signer_enroll_totp(signer, threshold, secret, totp)?;
                 serde_json::to_value(r)?
             }
             SignerCommand::LoadSoftware {
@@ -230,6 +307,13 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 let r = client.wallet_unload(wallet)?;
                 serde_json::to_value(r)?
             }
+            WalletCommand::Forget {
+                wallet,
+                delete_cache,
+            } => {
+                let r = client.wallet_forget(wallet, delete_cache)?;
+                serde_json::to_value(r)?
+            }
             WalletCommand::Balance {
                 wallet,
                 with_tickers,
@@ -240,6 +324,7 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
             WalletCommand::Send {
                 wallet,
                 recipient,
+                from_csv,
                 fee_rate,
             } => {
                 let mut addressees = vec![];
@@ -249,6 +334,14 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                             .with_context(|| "error parsing recipient argument")?,
                     );
                 }
+                if let Some(path) = from_csv {
+                    let network = match cli_network {
+                        Network::Mainnet => ElementsNetwork::Liquid,
+                        Network::Testnet => ElementsNetwork::LiquidTestnet,
+                        Network::Regtest => ElementsNetwork::default_regtest(),
+                    };
+                    addressees = recipients::from_csv(&path, network)?;
+                }
 
                 let r = client.wallet_send_many(wallet, addressees, fee_rate)?;
                 serde_json::to_value(r)?
@@ -261,14 +354,61 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 let r = client.wallet_drain(wallet, address, fee_rate)?;
                 serde_json::to_value(r)?
             }
+            WalletCommand::BumpFee {
+                wallet,
+                txid,
+                fee_rate,
+            } => {
+                let r = client.wallet_bump_fee(wallet, txid, fee_rate)?;
+                serde_json::to_value(r)?
+            }
             WalletCommand::Address {
                 index,
                 wallet,
                 signer,
                 with_text_qr,
                 with_uri_qr,
+                qr,
             } => {
                 let r = client.wallet_address(wallet, index, signer, with_text_qr, with_uri_qr)?;
+                if qr {
+                    qr::show_address(&r.address)?;
+                }
+                serde_json::to_value(r)?
+            }
+            WalletCommand::Addresses {
+                wallet,
+                start,
+                count,
+                chain,
+            } => {
+                let r = client.wallet_addresses(wallet, start, count, chain.to_string())?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::AddressInfo { wallet, address } => {
+                let r = client.wallet_address_info(wallet, address)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::VerifyPayment {
+                wallet,
+                txid,
+                vout,
+                address,
+                asset,
+                satoshi,
+                min_confirmations,
+                signer,
+            } => {
+                let r = client.wallet_verify_payment(
+                    wallet,
+                    txid,
+                    vout,
+                    address,
+                    asset,
+                    satoshi,
+                    min_confirmations,
+                    signer,
+                )?;
                 serde_json::to_value(r)?
             }
             WalletCommand::List => serde_json::to_value(client.wallet_list()?)?,
@@ -334,10 +474,21 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 let r = client.wallet_broadcast(wallet, dry_run, pset)?;
                 serde_json::to_value(r)?
             }
+            WalletCommand::Outbox { wallet } => {
+                let r = client.wallet_outbox(wallet)?;
+                serde_json::to_value(r)?
+            }
             WalletCommand::Details { wallet } => {
                 let r = client.wallet_details(wallet)?;
                 serde_json::to_value(r)?
             }
+            WalletCommand::RetryUnblind {
+                wallet,
+                blinding_key,
+            } => {
+                let r = client.wallet_retry_unblind(wallet, blinding_key)?;
+                serde_json::to_value(r)?
+            }
             WalletCommand::Combine { wallet, pset } => {
                 let r = client.wallet_combine(wallet, pset)?;
                 serde_json::to_value(r)?
@@ -361,6 +512,20 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 let r = client.wallet_txs(wallet, with_tickers)?;
                 serde_json::to_value(r)?
             }
+            WalletCommand::ExportHistory {
+                wallet,
+                format,
+                output,
+            } => {
+                let r = client.wallet_export_history(wallet, format.to_string())?;
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, &r.content)?;
+                        serde_json::to_value(lwk_rpc_model::response::Empty {})?
+                    }
+                    None => serde_json::to_value(r)?,
+                }
+            }
             WalletCommand::Tx {
                 wallet,
                 txid,
@@ -381,6 +546,34 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 let r = client.wallet_set_addr_memo(wallet, address, memo)?;
                 serde_json::to_value(r)?
             }
+            WalletCommand::SetPolicy {
+                wallet,
+                daily_limit,
+                asset_daily_limit,
+                allow,
+                required_cosigner,
+            } => {
+                let r = client.wallet_set_policy(
+                    wallet,
+                    daily_limit,
+                    asset_daily_limit.into_iter().collect(),
+                    allow,
+                    required_cosigner,
+                )?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::PolicyStatus { wallet } => {
+                let r = client.wallet_policy_status(wallet)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::Backup { wallet } => {
+                let r = client.wallet_backup(wallet)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::Restore { wallet, backup } => {
+                let r = client.wallet_restore(wallet, backup)?;
+                serde_json::to_value(r)?
+            }
         },
         CliCommand::Asset(a) => match a.command {
             AssetCommand::Contract {
@@ -441,7 +634,144 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 serde_json::to_value(r)?
             }
         },
+        CliCommand::Account(a) => match a.command {
+            AccountCommand::New {
+                signer,
+                kind,
+                descriptor_blinding_key,
+                wallet,
+                threshold,
+                cosigner,
+            } => {
+                let r = client.account_new(
+                    signer,
+                    kind.to_string(),
+                    descriptor_blinding_key.to_string(),
+                    wallet,
+                    threshold,
+                    cosigner,
+                )?;
+                serde_json::to_value(r)?
+            }
+            AccountCommand::List {
+                signer,
+                with_tickers,
+            } => {
+                let r = client.account_list(signer, with_tickers)?;
+                serde_json::to_value(r)?
+            }
+        },
+        CliCommand::Decode(a) => match a.command {
+            DecodeCommand::Tx { tx, wallet } => {
+                let r = client.tx_decode(tx, wallet)?;
+                serde_json::to_value(r)?
+            }
+            DecodeCommand::Pset { pset, wallet } => {
+                let r = client.pset_decode(pset, wallet)?;
+                serde_json::to_value(r)?
+            }
+        },
+        CliCommand::Tx(a) => match a.command {
+            TxCommand::Broadcast { tx, pset, dry_run } => {
+                let r = client.tx_broadcast(tx, pset, dry_run)?;
+                serde_json::to_value(r)?
+            }
+        },
+        CliCommand::Pset(a) => match a.command {
+            PsetCommand::Session(a) => match a.command {
+                PsetSessionCommand::Save {
+                    file,
+                    pset,
+                    participant,
+                    threshold,
+                } => {
+                    let session = match std::fs::read_to_string(&file) {
+                        Ok(session) => Some(session),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                        Err(e) => return Err(e.into()),
+                    };
+                    let r = client.pset_session_save(session, pset, participant, threshold)?;
+                    std::fs::write(&file, &r.session)?;
+                    serde_json::to_value(r)?
+                }
+                PsetSessionCommand::Load { file } => {
+                    let session = std::fs::read_to_string(&file)?;
+                    let r = client.pset_session_load(session)?;
+                    serde_json::to_value(r)?
+                }
+                PsetSessionCommand::Merge { file, other } => {
+                    let mut sessions = vec![std::fs::read_to_string(&file)?];
+                    for other in other {
+                        sessions.push(std::fs::read_to_string(&other)?);
+                    }
+                    let r = client.pset_session_merge(sessions)?;
+                    std::fs::write(&file, &r.session)?;
+                    serde_json::to_value(r)?
+                }
+            },
+            PsetCommand::Export {
+                pset,
+                qr,
+                max_fragment_len,
+                frame_interval,
+            } => {
+                let pset: PartiallySignedTransaction = pset.parse()?;
+                let ur = lwk_common::ur::pset_to_ur(&pset, max_fragment_len);
+                if qr {
+                    qr::animate(&ur, Duration::from_millis(frame_interval))?;
+                }
+                serde_json::json!({ "ur": ur })
+            }
+            PsetCommand::Import { ur } => {
+                let pset = lwk_common::ur::pset_from_ur(&ur)?;
+                serde_json::json!({ "pset": pset.to_string() })
+            }
+        },
         CliCommand::Schema(a) => schema::schema(a, client)?,
+        CliCommand::Shell => {
+            if let Some(token) = args.auth_token {
+                // used by the commands run in the shell
+                std::env::set_var("LWK_AUTH_TOKEN", token.as_str());
+            }
+            shell::shell(&client, &cli_network, addr, &config_path, args.log_format)?
+        }
+        CliCommand::Config(a) => match a.command {
+            ConfigCommand::Init { force } => {
+                if config_path.exists() && !force {
+                    anyhow::bail!(
+                        "{} already exists, use --force to overwrite it",
+                        config_path.display()
+                    );
+                }
+                let mut config = default_config(&cli_network);
+                config.addr = addr;
+                if let Some(parent) = config_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&config_path, ConfigFile::template(&config))
+                    .with_context(|| format!("Cannot write {}", config_path.display()))?;
+                serde_json::json!({ "path": config_path })
+            }
+        },
+        CliCommand::Datadir(a) => {
+            let mut config = default_config(&cli_network);
+            config.apply_file(&config_file);
+            if let Some(datadir) = a.datadir {
+                config.datadir = datadir;
+            }
+            if !matches!(a.command, DatadirCommand::List) && !server_stopped(addr) {
+                anyhow::bail!("Stop the server at {addr} before modifying its data directory");
+            }
+            let caches = match a.command {
+                DatadirCommand::List => lwk_app::datadir::wallet_caches(&config)?,
+                DatadirCommand::Prune { older_than_days } => {
+                    let older_than = Duration::from_secs(older_than_days * 24 * 60 * 60);
+                    lwk_app::datadir::prune_caches(&config, older_than)?
+                }
+                DatadirCommand::Vacuum => lwk_app::datadir::vacuum_caches(&config)?,
+            };
+            serde_json::json!({ "caches": caches })
+        }
         CliCommand::GenerateCompletion { shell } => {
             let mut result = vec![];
             clap_complete::generate(shell, &mut Cli::command(), "lwk_cli", &mut result);
@@ -456,3 +786,65 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
         }
     })
 }
+
+/// The defaults of `network`, with the default datadir
+fn default_config(network: &Network) -> Config {
+    let datadir = Config::default_home().unwrap_or(PathBuf::from("."));
+    match network {
+        Network::Mainnet => Config::default_mainnet(datadir),
+        Network::Testnet => Config::default_testnet(datadir),
+        Network::Regtest => Config::default_regtest(datadir),
+    }
+}
+
+fn check_server(client: &lwk_app::Client, addr: SocketAddr, network: &str) -> anyhow::Result<()> {
+    let version = client
+        .version()
+        .with_context(|| format!("Is the server at {:?} running?", addr))?;
+    let server_network = version.network;
+
+    if server_network != network {
+        return Err(anyhow!(
+            "Inconsistent networks (cli: {network}, server: {server_network})",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether nothing is listening at `addr`
+///
+/// A server that is busy, slow or refusing the request is still using its data directory, so
+/// only a refused connection means it is stopped.
+fn server_stopped(addr: SocketAddr) -> bool {
+    match std::net::TcpStream::connect_timeout(&addr, Duration::from_secs(5)) {
+        Ok(_) => false,
+        Err(e) => e.kind() == std::io::ErrorKind::ConnectionRefused,
+    }
+}
+
+fn batch(client: &lwk_app::Client, path: &Path) -> anyhow::Result<Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read batch file {}", path.display()))?;
+    let requests: Vec<Value> = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid batch file {}", path.display()))?;
+    let requests = requests
+        .into_iter()
+        .map(|r| {
+            let method = r
+                .get("method")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("Missing method in batch request {r}"))?;
+            Ok((method.parse()?, r.get("params").cloned()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let results = client
+        .batch(requests)?
+        .into_iter()
+        .map(|r| match r {
+            Ok(value) => Ok(value),
+            Err(lwk_app::Error::RpcError(e)) => Ok(serde_json::to_value(e)?),
+            Err(e) => Err(e.into()),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Value::Array(results))
+}
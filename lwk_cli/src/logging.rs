@@ -0,0 +1,26 @@
+//! Logging of the CLI and of the server it runs
+//!
+//! `log` records and `tracing` events are written to stderr, as text or JSON lines, together with
+//! the fields of the `tracing` spans they are emitted in (for instance the RPC method and request
+//! id). The verbosity is configured with `RUST_LOG`, using the [`EnvFilter`] syntax.
+
+use std::io::IsTerminal;
+
+use tracing_subscriber::EnvFilter;
+
+use crate::args::LogFormat;
+
+/// Install the logger, `directive` is used if `RUST_LOG` is not set
+///
+/// Returns false if a logger was already installed.
+pub(crate) fn init(directive: &str, format: LogFormat) -> bool {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(directive));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal());
+    match format {
+        LogFormat::Text => builder.try_init().is_ok(),
+        LogFormat::Json => builder.json().try_init().is_ok(),
+    }
+}
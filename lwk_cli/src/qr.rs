@@ -0,0 +1,59 @@
+//! Render QR codes in the terminal, on the standard error so that the output remains JSON
+
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Print the QR code of the address, the same returned with `--with-text-qr`
+pub(crate) fn show_address(address: &str) -> anyhow::Result<()> {
+    let address: elements::Address = address.parse()?;
+    eprintln!("{}", lwk_common::address_to_text_qr(&address)?);
+    Ok(())
+}
+
+/// Print the QR codes of the UR `parts`, one after the other every `interval`
+///
+/// When in a terminal the parts are shown in a loop, replacing the previous one, until enter is
+/// pressed, so that they can be scanned by mobile wallets and signers such as Jade.
+pub(crate) fn animate(parts: &[String], interval: Duration) -> anyhow::Result<()> {
+    // uppercase URs use the more compact alphanumeric QR encoding
+    let frames = parts
+        .iter()
+        .map(|p| lwk_common::string_to_text_qr(&p.to_uppercase()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if frames.len() == 1 || !interactive {
+        frames.iter().for_each(|f| eprintln!("{f}"));
+        return Ok(());
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_reader = stop.clone();
+    std::thread::spawn(move || {
+        let _ = std::io::stdin().read_line(&mut String::new());
+        stop_reader.store(true, Ordering::Relaxed);
+    });
+
+    let mut stderr = std::io::stderr();
+    let mut previous_lines = 0;
+    for (i, frame) in frames.iter().enumerate().cycle() {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        if previous_lines > 0 {
+            // move the cursor up to the previous frame and clear it
+            write!(stderr, "\x1b[{previous_lines}A\x1b[J")?;
+        }
+        let text = format!(
+            "{frame}\npart {}/{}, press enter to stop\n",
+            i + 1,
+            frames.len()
+        );
+        write!(stderr, "{text}")?;
+        stderr.flush()?;
+        previous_lines = text.matches('\n').count();
+        std::thread::sleep(interval);
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Recipients of `wallet send` read from a CSV file
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use lwk_wollet::{ElementsNetwork, UnvalidatedRecipient};
+
+/// Smallest amount of the policy asset accepted for an output
+pub(crate) const DUST_SATOSHI: u64 = 546;
+
+/// Read the recipients from a CSV file with rows `address,asset,satoshi`
+///
+/// The asset can be empty for the policy asset, and the address can be "burn". Empty lines, lines
+/// starting with `#` and a first line starting with `address` as header are skipped.
+///
+/// All the rows are validated before returning, the error contains every invalid row: the address
+/// must be confidential and of the network, the amount must not be dust and the same address
+/// and asset must not appear twice.
+pub(crate) fn from_csv(
+    path: &Path,
+    network: ElementsNetwork,
+) -> anyhow::Result<Vec<UnvalidatedRecipient>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    parse_csv(&content, network)
+}
+
+fn parse_csv(content: &str, network: ElementsNetwork) -> anyhow::Result<Vec<UnvalidatedRecipient>> {
+    let mut recipients = vec![];
+    let mut errors = vec![];
+    let mut seen = HashSet::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (i == 0 && line.starts_with("address")) {
+            continue;
+        }
+        match parse_row(line, network) {
+            Ok((recipient, asset)) => {
+                if !seen.insert((recipient.address.clone(), asset)) {
+                    errors.push(format!("line {}: duplicated address and asset", i + 1));
+                } else {
+                    recipients.push(recipient);
+                }
+            }
+            Err(e) => errors.push(format!("line {}: {e}", i + 1)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(anyhow!("invalid recipients:\n{}", errors.join("\n")));
+    }
+    if recipients.is_empty() {
+        return Err(anyhow!("no recipients"));
+    }
+    Ok(recipients)
+}
+
+fn parse_row(
+    line: &str,
+    network: ElementsNetwork,
+) -> anyhow::Result<(UnvalidatedRecipient, elements::AssetId)> {
+    let fields: Vec<_> = line.split(',').map(str::trim).collect();
+    let [address, asset, satoshi] = fields[..] else {
+        return Err(anyhow!("expected 3 fields, found {}", fields.len()));
+    };
+    let recipient = UnvalidatedRecipient {
+        address: address.to_string(),
+        asset: asset.to_string(),
+        satoshi: satoshi
+            .parse()
+            .with_context(|| format!("invalid amount {satoshi}"))?,
+    };
+    let validated = recipient.validate(network)?;
+    let burn = recipient.address == "burn";
+    if !burn && validated.asset == network.policy_asset() && validated.satoshi < DUST_SATOSHI {
+        return Err(anyhow!(
+            "amount {satoshi} is below the dust threshold of {DUST_SATOSHI}"
+        ));
+    }
+    Ok((recipient, validated.asset))
+}
@@ -3,8 +3,9 @@ use lwk_rpc_model::request::Direction;
 use serde_json::Value;
 
 use crate::args::{
-    Amp2SubCommandsEnum, AssetSubCommandsEnum, DirectionCommand, MainCommand, SchemaArgs,
-    ServerSubCommandsEnum, SignerSubCommandsEnum, WalletSubCommandsEnum,
+    AccountSubCommandsEnum, Amp2SubCommandsEnum, AssetSubCommandsEnum, DecodeSubCommandsEnum,
+    DirectionCommand, MainCommand, PsetSubCommandsEnum, SchemaArgs, ServerSubCommandsEnum,
+    SignerSubCommandsEnum, TxSubCommandsEnum, WalletSubCommandsEnum,
 };
 
 pub(crate) fn schema(a: SchemaArgs, client: Client) -> Result<Value, anyhow::Error> {
@@ -15,6 +16,10 @@ pub(crate) fn schema(a: SchemaArgs, client: Client) -> Result<Value, anyhow::Err
             MainCommand::Signer(s) => client.schema(s.command.into(), Direction::Request)?,
             MainCommand::Asset(s) => client.schema(s.command.into(), Direction::Request)?,
             MainCommand::Amp2(s) => client.schema(s.command.into(), Direction::Request)?,
+            MainCommand::Account(s) => client.schema(s.command.into(), Direction::Request)?,
+            MainCommand::Decode(s) => client.schema(s.command.into(), Direction::Request)?,
+            MainCommand::Tx(s) => client.schema(s.command.into(), Direction::Request)?,
+            MainCommand::Pset(s) => client.schema(s.command.into(), Direction::Request)?,
             MainCommand::Schema => client.schema(Method::Schema, Direction::Request)?,
         },
         DirectionCommand::Response(res) => match res.command {
@@ -23,6 +28,10 @@ pub(crate) fn schema(a: SchemaArgs, client: Client) -> Result<Value, anyhow::Err
             MainCommand::Signer(s) => client.schema(s.command.into(), Direction::Response)?,
             MainCommand::Asset(s) => client.schema(s.command.into(), Direction::Response)?,
             MainCommand::Amp2(s) => client.schema(s.command.into(), Direction::Response)?,
+            MainCommand::Account(s) => client.schema(s.command.into(), Direction::Response)?,
+            MainCommand::Decode(s) => client.schema(s.command.into(), Direction::Response)?,
+            MainCommand::Tx(s) => client.schema(s.command.into(), Direction::Response)?,
+            MainCommand::Pset(s) => client.schema(s.command.into(), Direction::Response)?,
             MainCommand::Schema => client.schema(Method::Schema, Direction::Response)?,
         },
     })
@@ -32,6 +41,7 @@ impl From<ServerSubCommandsEnum> for Method {
     fn from(value: ServerSubCommandsEnum) -> Self {
         match value {
             ServerSubCommandsEnum::Scan => Method::Scan,
+            ServerSubCommandsEnum::Status => Method::ServerStatus,
             ServerSubCommandsEnum::Stop => Method::Stop,
         }
     }
@@ -42,21 +52,33 @@ impl From<WalletSubCommandsEnum> for Method {
         match value {
             WalletSubCommandsEnum::Load => Method::WalletLoad,
             WalletSubCommandsEnum::Unload => Method::WalletUnload,
+            WalletSubCommandsEnum::Forget => Method::WalletForget,
             WalletSubCommandsEnum::List => Method::WalletList,
             WalletSubCommandsEnum::Address => Method::WalletAddress,
+            WalletSubCommandsEnum::Addresses => Method::WalletAddresses,
+            WalletSubCommandsEnum::AddressInfo => Method::WalletAddressInfo,
+            WalletSubCommandsEnum::VerifyPayment => Method::WalletVerifyPayment,
             WalletSubCommandsEnum::Balance => Method::WalletBalance,
             WalletSubCommandsEnum::Send => Method::WalletSendMany,
+            WalletSubCommandsEnum::BumpFee => Method::WalletBumpFee,
             WalletSubCommandsEnum::Issue => Method::WalletIssue,
             WalletSubCommandsEnum::Reissue => Method::WalletReissue,
             WalletSubCommandsEnum::MultisigDesc => Method::WalletMultisigDescriptor,
             WalletSubCommandsEnum::Broadcast => Method::WalletBroadcast,
+            WalletSubCommandsEnum::Outbox => Method::WalletOutbox,
             WalletSubCommandsEnum::Details => Method::WalletDetails,
+            WalletSubCommandsEnum::RetryUnblind => Method::WalletRetryUnblind,
             WalletSubCommandsEnum::Combine => Method::WalletCombine,
             WalletSubCommandsEnum::PsetDetails => Method::WalletPsetDetails,
             WalletSubCommandsEnum::Utxos => Method::WalletUtxos,
             WalletSubCommandsEnum::Txs => Method::WalletTxs,
+            WalletSubCommandsEnum::ExportHistory => Method::WalletExportHistory,
             WalletSubCommandsEnum::SetTxMemo => Method::WalletSetTxMemo,
             WalletSubCommandsEnum::SetAddrMemo => Method::WalletSetAddrMemo,
+            WalletSubCommandsEnum::SetPolicy => Method::WalletSetPolicy,
+            WalletSubCommandsEnum::PolicyStatus => Method::WalletPolicyStatus,
+            WalletSubCommandsEnum::Backup => Method::WalletBackup,
+            WalletSubCommandsEnum::Restore => Method::WalletRestore,
         }
     }
 }
@@ -73,6 +95,7 @@ impl From<SignerSubCommandsEnum> for Method {
             SignerSubCommandsEnum::Details => Method::SignerDetails,
             SignerSubCommandsEnum::List => Method::SignerList,
             SignerSubCommandsEnum::Sign => Method::SignerSign,
+            SignerSubCommandsEnum::EnrollTotp => Method::SignerEnrollTotp,
             SignerSubCommandsEnum::SinglesigDesc => Method::SignerSinglesigDescriptor,
             SignerSubCommandsEnum::Xpub => Method::SignerXpub,
         }
@@ -101,3 +124,39 @@ impl From<Amp2SubCommandsEnum> for Method {
         }
     }
 }
+
+impl From<AccountSubCommandsEnum> for Method {
+    fn from(value: AccountSubCommandsEnum) -> Self {
+        match value {
+            AccountSubCommandsEnum::New => Method::AccountNew,
+            AccountSubCommandsEnum::List => Method::AccountList,
+        }
+    }
+}
+
+impl From<DecodeSubCommandsEnum> for Method {
+    fn from(value: DecodeSubCommandsEnum) -> Self {
+        match value {
+            DecodeSubCommandsEnum::Tx => Method::TxDecode,
+            DecodeSubCommandsEnum::Pset => Method::PsetDecode,
+        }
+    }
+}
+
+impl From<TxSubCommandsEnum> for Method {
+    fn from(value: TxSubCommandsEnum) -> Self {
+        match value {
+            TxSubCommandsEnum::Broadcast => Method::TxBroadcast,
+        }
+    }
+}
+
+impl From<PsetSubCommandsEnum> for Method {
+    fn from(value: PsetSubCommandsEnum) -> Self {
+        match value {
+            PsetSubCommandsEnum::SessionSave => Method::PsetSessionSave,
+            PsetSubCommandsEnum::SessionLoad => Method::PsetSessionLoad,
+            PsetSubCommandsEnum::SessionMerge => Method::PsetSessionMerge,
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Interactive shell running the CLI commands against the server
+
+use std::net::SocketAddr;
+use std::path::Path;
+
+use clap::{CommandFactory, Parser, ValueEnum};
+use lwk_app::Config;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Context, Editor, Helper};
+use serde_json::Value;
+
+use crate::args::{CliCommand, LogFormat, Network, ServerCommand};
+use crate::Cli;
+
+const BUILTINS: [&str; 5] = ["use", "history", "help", "exit", "quit"];
+
+const HELP: &str = "Type the commands without `lwk_cli`, e.g. `wallet list`, append `--help` for \
+their help. Tab completes commands, options, wallet and signer names.
+
+Shell commands:
+  use wallet <name>   use the wallet when `--wallet` is not given
+  use signer <name>   use the signer when `--signer` is not given
+  use                 show the wallet and the signer in use
+  history             show the commands history
+  help                show this help
+  exit, quit          exit the shell
+
+`$pset` is replaced with the last PSET returned by a command.";
+
+/// Run the shell until the user exits
+pub(crate) fn shell(
+    client: &lwk_app::Client,
+    network: &Network,
+    addr: SocketAddr,
+    config: &Path,
+    log_format: LogFormat,
+) -> anyhow::Result<Value> {
+    let global_args = [
+        "lwk_cli".to_string(),
+        "--network".to_string(),
+        value_name(network),
+        "--addr".to_string(),
+        addr.to_string(),
+        "--config".to_string(),
+        config.display().to_string(),
+        "--log-format".to_string(),
+        value_name(&log_format),
+    ];
+    let history_path = Config::default_home().ok().map(|p| p.join("cli_history"));
+    let editor_config = rustyline::Config::builder()
+        .auto_add_history(false)
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor: Editor<ShellHelper, FileHistory> = Editor::with_config(editor_config)?;
+    editor.set_helper(Some(ShellHelper { client }));
+    if let Some(path) = history_path.as_ref() {
+        // the history file doesn't exist at the first run
+        let _ = editor.load_history(path);
+    }
+    let mut last_pset: Option<String> = None;
+
+    loop {
+        let prompt = match std::env::var("WALLET") {
+            Ok(wallet) => format!("lwk [{wallet}]> "),
+            Err(_) => "lwk> ".to_string(),
+        };
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line.trim().to_string(),
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        // the lines with secrets are not kept, the history is saved in plaintext
+        if !line.is_empty() && !is_secret(&line) && editor.add_history_entry(line.as_str())? {
+            if let Some(path) = history_path.as_ref() {
+                editor.append_history(path)?;
+            }
+        }
+        let mut words = match shellwords::split(&line) {
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                continue;
+            }
+        };
+        if words.first().map(String::as_str) == Some("lwk_cli") {
+            words.remove(0);
+        }
+        match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            [] => continue,
+            ["exit"] | ["quit"] => break,
+            ["help"] => println!("{HELP}"),
+            ["history"] => editor.history().iter().for_each(|l| println!("{l}")),
+            ["use"] => {
+                for var in ["WALLET", "SIGNER"] {
+                    let name = std::env::var(var).unwrap_or_default();
+                    println!("{}: {name}", var.to_lowercase());
+                }
+            }
+            ["use", kind @ ("wallet" | "signer"), name] => {
+                // the commands read the wallet and the signer from the environment when not given
+                std::env::set_var(kind.to_uppercase(), name);
+            }
+            ["use", ..] => eprintln!("Usage: use wallet <name> | use signer <name>"),
+            _ => {
+                let args = words.into_iter().map(|w| match (w.as_str(), &last_pset) {
+                    ("$pset", Some(pset)) => pset.clone(),
+                    _ => w,
+                });
+                let cli = match Cli::try_parse_from(global_args.iter().cloned().chain(args)) {
+                    Ok(cli) => cli,
+                    Err(e) => {
+                        let _ = e.print();
+                        continue;
+                    }
+                };
+                if matches!(
+                    cli.command,
+                    Some(CliCommand::Shell)
+                        | Some(CliCommand::Server(crate::args::ServerArgs {
+                            command: ServerCommand::Start { .. },
+                        }))
+                ) {
+                    eprintln!("Error: the command cannot be used in the shell");
+                    continue;
+                }
+                match crate::inner_main(cli) {
+                    Ok(value) => {
+                        if let Some(pset) = value.get("pset").and_then(Value::as_str) {
+                            last_pset = Some(pset.to_string());
+                        }
+                        println!("{:#}", value);
+                    }
+                    Err(e) => match e.downcast_ref::<lwk_app::Error>() {
+                        Some(lwk_app::Error::RpcError(e)) => {
+                            println!("{:#}", serde_json::to_value(e)?)
+                        }
+                        _ => eprintln!("Error: {e:#}"),
+                    },
+                }
+            }
+        }
+    }
+    Ok(Value::Null)
+}
+
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// The completions of the last word of `line`
+fn complete(client: &lwk_app::Client, line: &str) -> Vec<String> {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    let prefix = match line.ends_with(char::is_whitespace) {
+        true => "",
+        false => words.pop().unwrap_or(""),
+    };
+    let wallets = || match client.wallet_list() {
+        Ok(r) => r.wallets.into_iter().map(|w| w.name).collect(),
+        Err(_) => vec![],
+    };
+    let signers = || match client.signer_list() {
+        Ok(r) => r.signers.into_iter().map(|s| s.name).collect(),
+        Err(_) => vec![],
+    };
+
+    let candidates: Vec<String> = match (&words[..], words.last()) {
+        (["use"], _) => vec!["wallet".into(), "signer".into()],
+        (["use", "wallet"], _) | (_, Some(&"--wallet")) | (_, Some(&"-w")) => wallets(),
+        (["use", "signer"], _) | (_, Some(&"--signer")) | (_, Some(&"-s")) => signers(),
+        _ => {
+            let mut command = Cli::command();
+            for word in words.iter().filter(|w| !w.starts_with('-')) {
+                match command.find_subcommand(word) {
+                    Some(sub) => command = sub.clone(),
+                    None => break,
+                }
+            }
+            if prefix.starts_with('-') {
+                command
+                    .get_arguments()
+                    .filter(|a| !a.is_hide_set())
+                    .filter_map(|a| a.get_long().map(|l| format!("--{l}")))
+                    .collect()
+            } else {
+                let mut subcommands: Vec<String> = command
+                    .get_subcommands()
+                    .filter(|s| !s.is_hide_set())
+                    .map(|s| s.get_name().to_string())
+                    .collect();
+                if words.is_empty() {
+                    subcommands.extend(BUILTINS.iter().map(|b| b.to_string()));
+                }
+                subcommands
+            }
+        }
+    };
+    let mut candidates: Vec<String> = candidates
+        .into_iter()
+        .filter(|c| c.starts_with(prefix))
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Completes the commands with [`complete()`]
+struct ShellHelper<'a> {
+    client: &'a lwk_app::Client,
+}
+
+impl Completer for ShellHelper<'_> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        Ok((start, complete(self.client, line)))
+    }
+}
+
+impl Hinter for ShellHelper<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper<'_> {}
+
+impl Validator for ShellHelper<'_> {}
+
+impl Helper for ShellHelper<'_> {}
+
+/// Options whose values must not be saved in the history
+const SECRET_OPTIONS: [&str; 3] = ["--mnemonic", "--secret", "--totp"];
+
+/// Whether the line contains a secret, such as a mnemonic or a TOTP code
+fn is_secret(line: &str) -> bool {
+    let words = shellwords::split(line)
+        .unwrap_or_else(|_| line.split_whitespace().map(str::to_string).collect());
+    words.iter().any(|word| {
+        let option = word.split('=').next().unwrap_or(word);
+        SECRET_OPTIONS.contains(&option)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_secret;
+
+    #[test]
+    fn test_is_secret() {
+        assert!(!is_secret("wallet list"));
+        assert!(!is_secret("signer sign --pset $pset"));
+        assert!(is_secret(
+            "signer load-software --signer s --mnemonic \"abandon abandon about\" --persist false"
+        ));
+        assert!(is_secret(
+            "signer load-software --mnemonic=\"abandon about\""
+        ));
+        assert!(is_secret("signer sign --pset $pset --totp 123456"));
+        assert!(is_secret("signer enroll-totp --threshold 1 --secret ABCD"));
+        assert!(is_secret("signer sign --totp \"123456"));
+        assert!(!is_secret("wallet details --wallet totp"));
+    }
+}
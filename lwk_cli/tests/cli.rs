@@ -15,8 +15,8 @@ use lwk_containers::{testcontainers::clients, JadeEmulator, EMULATOR_PORT};
 use serde_json::Value;
 
 use lwk_cli::{
-    inner_main, AssetSubCommandsEnum, Cli, ServerSubCommandsEnum, SignerSubCommandsEnum,
-    WalletSubCommandsEnum,
+    inner_main, AccountSubCommandsEnum, AssetSubCommandsEnum, Cli, DecodeSubCommandsEnum,
+    ServerSubCommandsEnum, SignerSubCommandsEnum, TxSubCommandsEnum, WalletSubCommandsEnum,
 };
 use lwk_test_util::TestElectrumServer;
 use tempfile::TempDir;
@@ -403,6 +403,12 @@ fn test_start_stop_persist() {
     let r = sh(&format!("{cli} signer list"));
     assert_eq!(get_len(&r, "signers"), 4);
 
+    sh(&format!("{cli} server scan"));
+    let r = sh(&format!("{cli} server status"));
+    assert_eq!(get_len(&r, "servers"), 1);
+    assert!(r["servers"][0]["healthy"].as_bool().unwrap());
+    let expected_successes = r["servers"][0]["successes"].as_u64().unwrap();
+
     sh(&format!("{cli} server stop"));
     t.join().unwrap();
 
@@ -424,6 +430,13 @@ fn test_start_stop_persist() {
     let result = sh(&format!("{cli} asset list"));
     assert_eq!(expected_assets, result, "persist not working");
 
+    let r = sh(&format!("{cli} server status"));
+    let successes = r["servers"][0]["successes"].as_u64().unwrap();
+    assert!(
+        successes >= expected_successes,
+        "server health not persisted"
+    );
+
     sh(&format!("{cli} server stop"));
     t.join().unwrap();
 
@@ -488,6 +501,32 @@ fn test_signer_load_unload_list() {
     t.join().unwrap();
 }
 
+#[test]
+fn test_batch() {
+    let (t, tmp, cli, _params, _server, _) = setup_cli(false);
+
+    let m = lwk_test_util::TEST_MNEMONIC;
+    let path = tmp.path().join("batch.json");
+    let requests = serde_json::json!([
+        {"method": "signer_load_software", "params": {"name": "s1", "mnemonic": m, "persist": false}},
+        {"method": "signer_details", "params": {"name": "s1"}},
+        {"method": "signer_details", "params": {"name": "s2"}},
+        {"method": "signer_list"},
+    ]);
+    std::fs::write(&path, requests.to_string()).unwrap();
+
+    let r = sh(&format!("{cli} --batch {}", path.display()));
+    let results = r.as_array().unwrap();
+    assert_eq!(results.len(), 4);
+    assert_eq!(get_str(&results[0], "name"), "s1");
+    assert_eq!(get_str(&results[1], "mnemonic"), m);
+    assert!(results[2].get("code").is_some());
+    assert_eq!(get_len(&results[3], "signers"), 1);
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
 #[test]
 fn test_signer_external() {
     let (t, _tmp, cli, _params, _server, _) = setup_cli(false);
@@ -665,6 +704,80 @@ fn test_wallet_memos() {
     t.join().unwrap();
 }
 
+#[test]
+fn test_wallet_backup() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+    let (txid, _) = fund(&server, &cli, "w1", 1_000_000);
+    let txid = txid.to_string();
+
+    let r = sh(&format!("{cli} wallet address --wallet w1"));
+    let w1_addr = get_str(&r, "address").to_string();
+    let index = r.get("index").unwrap().as_u64().unwrap() as u32;
+    sh(&format!(
+        "{cli} wallet set-tx-memo -w w1 --txid {txid} --memo MEMO1"
+    ));
+    sh(&format!(
+        "{cli} wallet set-addr-memo -w w1 --address {w1_addr} --memo MEMO2"
+    ));
+
+    let r = sh(&format!("{cli} wallet backup -w w1"));
+    let backup = get_str(&r, "backup").to_string();
+    assert!(!backup.contains("prv"));
+    let desc = get_desc(&sh(&format!("{cli} wallet details -w w1")));
+
+    // Restore on a different name
+    sh(&format!("{cli} wallet unload --wallet w1"));
+    let r = sh(&format!("{cli} wallet restore -w w2 --backup '{backup}'"));
+    assert_eq!(get_desc(&r), desc);
+    sh(&format!("{cli} server scan"));
+    wait_tx(&cli, "w2", &txid);
+    assert_eq!(tx_memo(&cli, "w2", &txid), "MEMO1");
+    assert_eq!(addr_memo(&cli, "w2", index), "MEMO2");
+
+    let err = sh_err(&format!("{cli} wallet restore -w w3 --backup '{{}}'"));
+    assert!(err.contains("missing field"));
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_datadir() {
+    let (t, tmp, cli, _params, server, _) = setup_cli(false);
+    let datadir = format!("--datadir {}", tmp.path().display());
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+    fund(&server, &cli, "w1", 1_000_000);
+    let r = sh(&format!("{cli} datadir {datadir} list"));
+    let caches = r.get("caches").unwrap().as_array().unwrap();
+    assert_eq!(caches.len(), 1);
+    assert_eq!(caches[0]["wallets"][0], "w1");
+    assert!(caches[0]["updates"].as_u64().unwrap() > 0);
+    assert!(caches[0]["size"].as_u64().unwrap() > 0);
+
+    let err = sh_err(&format!("{cli} datadir {datadir} prune"));
+    assert!(err.contains("Stop the server"), "{err}");
+
+    sh(&format!("{cli} wallet unload --wallet w1"));
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+
+    let r = sh(&format!("{cli} datadir {datadir} list"));
+    assert_eq!(r["caches"][0]["wallets"].as_array().unwrap().len(), 0);
+    let r = sh(&format!(
+        "{cli} datadir {datadir} prune --older-than-days 1"
+    ));
+    assert_eq!(r["caches"].as_array().unwrap().len(), 0);
+    let r = sh(&format!("{cli} datadir {datadir} prune"));
+    assert_eq!(r["caches"].as_array().unwrap().len(), 1);
+    let r = sh(&format!("{cli} datadir {datadir} list"));
+    assert_eq!(r["caches"].as_array().unwrap().len(), 0);
+}
+
 #[test]
 fn test_wallet_details() {
     let (t, _tmp, cli, _params, _server, _) = setup_cli(false);
@@ -717,6 +830,7 @@ fn test_wallet_details() {
     let r = sh(&format!("{cli} wallet details --wallet ss"));
     assert_eq!(get_desc(&r), remove_checksum(desc_ss));
     assert!(get_str(&r, "warnings").is_empty());
+    assert_eq!(get_len(&r, "descriptor_warnings"), 0);
     assert_eq!(get_str(&r, "type"), "wpkh");
     let signers = r.get("signers").unwrap().as_array().unwrap();
     assert_eq!(signers.len(), 1);
@@ -747,16 +861,31 @@ fn test_wallet_details() {
     assert!(signers[1].get("name").is_none());
 
     let r = sh(&format!("{cli} wallet details --wallet ms_same_signers"));
+    let xpub = xpub1.rsplit(']').next().unwrap();
     assert_eq!(
         get_str(&r, "warnings"),
-        "wallet has multiple signers with the same fingerprint"
+        format!("wallet has multiple signers with the same fingerprint, key {xpub} is used more than once")
     );
+    let descriptor_warnings = r.get("descriptor_warnings").unwrap().as_array().unwrap();
+    assert_eq!(descriptor_warnings.len(), 1);
+    assert_eq!(get_str(&descriptor_warnings[0], "kind"), "duplicate_key");
+    assert_eq!(get_str(&descriptor_warnings[0], "key"), xpub);
     assert_eq!(r.get("type").unwrap().as_str().unwrap(), "wsh_multi_2of2");
     let signers = r.get("signers").unwrap().as_array().unwrap();
     assert_eq!(signers.len(), 2);
     assert_eq!(get_str(&signers[0], "name"), "s1");
     assert_eq!(get_str(&signers[1], "name"), "s1");
 
+    // Multi sig wallet, sorted keys
+    let r = sh(&format!("{cli} wallet multisig-desc --descriptor-blinding-key elip151 --kind wsh-sorted --threshold 1 --keyorigin-xpub {xpub1} --keyorigin-xpub {xpub2}"));
+    let desc_ms_sorted = get_str(&r, "descriptor");
+    assert!(desc_ms_sorted.contains("sortedmulti"));
+    sh(&format!(
+        "{cli} wallet load --wallet ms_sorted -d {desc_ms_sorted}"
+    ));
+    let r = sh(&format!("{cli} wallet details --wallet ms_sorted"));
+    assert_eq!(get_str(&r, "type"), "wsh_sortedmulti_1of2");
+
     sh(&format!("{cli} server stop"));
     t.join().unwrap();
 }
@@ -772,9 +901,17 @@ fn test_broadcast() {
     let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
     assert_eq!(1_000_000, get_balance(&cli, "w1", policy_asset));
     let addr = server.elementsd_getnewaddress().to_string();
-    send(&cli, "w1", &addr, policy_asset, 1000, &["s1"]);
+    let txid = send(&cli, "w1", &addr, policy_asset, 1000, &["s1"]);
     assert!(1_000_000 > get_balance(&cli, "w1", policy_asset));
 
+    // The transaction is in the outbox until confirmed
+    let r = sh(&format!("{cli} wallet outbox --wallet w1"));
+    assert_eq!(r.get("txids").unwrap(), &serde_json::json!([txid]));
+    server.elementsd_generate(1);
+    sh(&format!("{cli} server scan"));
+    let r = sh(&format!("{cli} wallet outbox --wallet w1"));
+    assert_eq!(get_len(&r, "txids"), 0);
+
     sh(&format!("{cli} server stop"));
     t.join().unwrap();
 }
@@ -839,7 +976,11 @@ fn test_issue() {
 
     let r = sh(&format!("{cli} wallet balance --wallet w1 --with-tickers"));
     let balance = r.get("balance").unwrap().as_object().unwrap();
-    assert!(balance.get("L-BTC").unwrap().as_u64().unwrap() > 0);
+    let lbtc = balance.get("L-BTC").unwrap().as_u64().unwrap();
+    assert!(lbtc > 0);
+    let display = r.get("display").unwrap().as_object().unwrap();
+    let expected = format!("{}.{:08} L-BTC", lbtc / 100_000_000, lbtc % 100_000_000);
+    assert_eq!(display.get("L-BTC").unwrap().as_str().unwrap(), expected);
 
     let r = sh(&format!("{cli} asset details --asset {policy_asset}"));
     assert_eq!(get_str(&r, "name"), "liquid bitcoin");
@@ -933,6 +1074,17 @@ fn test_issue() {
     let balance = txs[0].get("balance").unwrap().as_object().unwrap();
     assert!(balance.contains_key("L-BTC"));
 
+    let r = sh(&format!("{cli} wallet export-history --wallet w1"));
+    let csv = get_str(&r, "content");
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("date,txid,asset,amount,fee,type,memo"));
+    assert!(lines.any(|l| l.contains(",L-BTC,")));
+    let r = sh(&format!(
+        "{cli} wallet export-history --wallet w1 --format json"
+    ));
+    let rows: Value = serde_json::from_str(get_str(&r, "content")).unwrap();
+    assert!(rows.as_array().unwrap().len() >= txs.len());
+
     // Move the reissuance token to another wallet and perform an "external" reissuance
     sw_signer(&cli, "s2");
     singlesig_wallet(&cli, "w2", "s2", "slip77", "wpkh");
@@ -1024,7 +1176,7 @@ fn test_jade_emulator() {
 
 #[test]
 fn test_commands() {
-    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+    let (t, tmp, cli, _params, server, _) = setup_cli(false);
 
     let result = sh(&format!("{cli} signer generate"));
     assert!(result.get("mnemonic").is_some());
@@ -1055,6 +1207,42 @@ fn test_commands() {
     assert_eq!(get_str(&r, "address"), "el1qqg0nthgrrl4jxeapsa40us5d2wv4ps2y63pxwqpf3zk6y69jderdtzfyr95skyuu3t03sh0fvj09f9xut8erjly3ndquhu0ry");
     assert_eq!(r.get("index").unwrap().as_u64().unwrap(), 0);
 
+    let r = sh(&format!(
+        "{cli} wallet addresses --wallet custody --count 3"
+    ));
+    let addresses = r.get("addresses").unwrap().as_array().unwrap();
+    assert_eq!(addresses.len(), 3);
+    assert_eq!(get_str(&addresses[0], "address"), "el1qqg0nthgrrl4jxeapsa40us5d2wv4ps2y63pxwqpf3zk6y69jderdtzfyr95skyuu3t03sh0fvj09f9xut8erjly3ndquhu0ry");
+    assert_eq!(get_str(&addresses[1], "address"), "el1qqdtwgfchn6rtl8peyw6afhrkpphqlyxls04vlwycez2fz6l7chlhxr8wtvy9s2v34f9sk0e2g058p0dwdp9kj38296xw5ur70");
+    assert_eq!(addresses[2].get("index").unwrap().as_u64().unwrap(), 2);
+
+    let r = sh(&format!(
+        "{cli} wallet addresses --wallet custody --start 1 --count 1 --chain internal"
+    ));
+    let addresses = r.get("addresses").unwrap().as_array().unwrap();
+    assert_eq!(addresses[0].get("index").unwrap().as_u64().unwrap(), 1);
+
+    let err = sh_err(&format!(
+        "{cli} wallet addresses --wallet custody --count 10001"
+    ));
+    assert!(err.contains("At most 10000 addresses can be requested"));
+
+    let addr1 = "el1qqdtwgfchn6rtl8peyw6afhrkpphqlyxls04vlwycez2fz6l7chlhxr8wtvy9s2v34f9sk0e2g058p0dwdp9kj38296xw5ur70";
+    let r = sh(&format!(
+        "{cli} wallet address-info --wallet custody --address {addr1}"
+    ));
+    assert!(r.get("is_mine").unwrap().as_bool().unwrap());
+    assert_eq!(get_str(&r, "chain"), "external");
+    assert_eq!(r.get("index").unwrap().as_u64().unwrap(), 1);
+    assert_eq!(get_str(&r, "confidential_address"), addr1);
+
+    let node_address = server.elementsd_getnewaddress();
+    let r = sh(&format!(
+        "{cli} wallet address-info --wallet custody --address {node_address}"
+    ));
+    assert!(!r.get("is_mine").unwrap().as_bool().unwrap());
+    assert!(r.get("index").is_none());
+
     let cli_addr = format!("{cli} wallet address --wallet custody");
     let r = sh(&format!("{cli_addr} --with-text-qr"));
     assert!(get_str(&r, "text_qr").contains('█'));
@@ -1068,10 +1256,49 @@ fn test_commands() {
     assert!(get_str(&r, "text_qr").contains('█'));
     assert!(get_str(&r, "uri_qr").contains("data:image/bmp;base64"));
 
+    let r = sh(&format!("{cli_addr} --qr"));
+    assert!(r.get("text_qr").is_none());
+
     let result = sh(&format!("{cli} wallet send --wallet custody --recipient el1qqdtwgfchn6rtl8peyw6afhrkpphqlyxls04vlwycez2fz6l7chlhxr8wtvy9s2v34f9sk0e2g058p0dwdp9kj38296xw5ur70:2:5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225"));
     let pset = result.get("pset").unwrap().as_str().unwrap();
     let _: PartiallySignedTransaction = pset.parse().unwrap();
 
+    let r = sh(&format!("{cli} pset export --pset {pset}"));
+    let ur = r.get("ur").unwrap().as_array().unwrap();
+    assert!(ur.len() > 1);
+    assert!(ur[0].as_str().unwrap().starts_with("ur:crypto-psbt/1-"));
+    let parts: Vec<&str> = ur.iter().rev().map(|p| p.as_str().unwrap()).collect();
+    let r = sh(&format!("{cli} pset import --ur {}", parts.join(" ")));
+    assert_eq!(get_str(&r, "pset"), pset);
+
+    let addr = "el1qqdtwgfchn6rtl8peyw6afhrkpphqlyxls04vlwycez2fz6l7chlhxr8wtvy9s2v34f9sk0e2g058p0dwdp9kj38296xw5ur70";
+    let addr2 = "el1qqg0nthgrrl4jxeapsa40us5d2wv4ps2y63pxwqpf3zk6y69jderdtzfyr95skyuu3t03sh0fvj09f9xut8erjly3ndquhu0ry";
+    let csv = tmp.path().join("payouts.csv");
+    std::fs::write(
+        &csv,
+        format!("address,asset,satoshi\n{addr},,1000\n{addr2},{asset},2000\n"),
+    )
+    .unwrap();
+    let csv = csv.display();
+    let r = sh(&format!(
+        "{cli} wallet send --wallet custody --from-csv {csv}"
+    ));
+    let csv_pset: PartiallySignedTransaction = get_str(&r, "pset").parse().unwrap();
+    assert_eq!(csv_pset.outputs().len(), 4); // 2 recipients, change and fee
+
+    let bad_csv = tmp.path().join("bad.csv");
+    std::fs::write(
+        &bad_csv,
+        format!("{addr},,1000\n{addr},{asset},2000\n{addr2},,1\n"),
+    )
+    .unwrap();
+    let err = sh_err(&format!(
+        "{cli} wallet send --wallet custody --from-csv {}",
+        bad_csv.display()
+    ));
+    assert!(err.contains("line 2: duplicated address and asset"));
+    assert!(err.contains("line 3: amount 1 is below the dust threshold"));
+
     let result = sh(&format!("{cli}  wallet unload --wallet custody"));
     let unloaded = result.get("unloaded").unwrap();
     assert_eq!(unloaded.get("descriptor").unwrap().as_str().unwrap(), desc);
@@ -1228,6 +1455,171 @@ fn test_multisig() {
     t.join().unwrap();
 }
 
+#[test]
+fn test_wallet_policy() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    sw_signer(&cli, "s2");
+
+    let r = sh(&format!("{cli} signer xpub --signer s1 --kind bip87"));
+    let keyorigin_xpub1 = r.get("keyorigin_xpub").unwrap().as_str().unwrap();
+    let r = sh(&format!("{cli} signer xpub --signer s2 --kind bip87"));
+    let keyorigin_xpub2 = r.get("keyorigin_xpub").unwrap().as_str().unwrap();
+    let r = sh(&format!("{cli} signer details --signer s1"));
+    let fingerprint1 = r.get("fingerprint").unwrap().as_str().unwrap();
+
+    let r = sh(&format!("{cli} wallet multisig-desc --descriptor-blinding-key slip77-rand --kind wsh --threshold 2 --keyorigin-xpub {keyorigin_xpub1} --keyorigin-xpub {keyorigin_xpub2}"));
+    let desc = r.get("descriptor").unwrap().as_str().unwrap();
+    sh(&format!("{cli} wallet load --wallet multi -d {desc}"));
+
+    let _ = fund(&server, &cli, "multi", 1_000_000);
+
+    let node_address = server.elementsd_getnewaddress();
+    let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let recipient = format!("{node_address}:1000:{policy_asset}");
+
+    // s2 signs only after s1, at most 100_000 sats a day and only to the node address
+    let r = sh(&format!("{cli} wallet set-policy --wallet multi --daily-limit 100000 --allow {node_address} --required-cosigner {fingerprint1}"));
+    assert_eq!(r.get("remaining_today").unwrap().as_u64().unwrap(), 100_000);
+
+    let r = sh(&format!(
+        "{cli} wallet send --wallet multi --recipient {recipient}"
+    ));
+    let pset_u = r.get("pset").unwrap().as_str().unwrap();
+
+    let err = sh_err(&format!("{cli} signer sign --signer s2 --pset {pset_u}"));
+    assert!(err.contains("did not sign yet"));
+    let r = sh(&format!("{cli} signer sign --signer s1 --pset {pset_u}"));
+    let pset_s1 = r.get("pset").unwrap().as_str().unwrap();
+    let r = sh(&format!("{cli} signer sign --signer s2 --pset {pset_s1}"));
+    let pset_s = r.get("pset").unwrap().as_str().unwrap();
+    sh(&format!(
+        "{cli} wallet broadcast --wallet multi --pset {pset_s}"
+    ));
+
+    let r = sh(&format!("{cli} wallet policy-status --wallet multi"));
+    let spent_today = r.get("spent_today").unwrap().as_u64().unwrap();
+    assert!(spent_today > 1000);
+
+    // Other recipients and amounts over the limit are rejected
+    let other = format!("{}:1000:{policy_asset}", server.elementsd_getnewaddress());
+    let r = sh(&format!(
+        "{cli} wallet send --wallet multi --recipient {other}"
+    ));
+    let pset_u = r.get("pset").unwrap().as_str().unwrap();
+    let err = sh_err(&format!("{cli} signer sign --signer s1 --pset {pset_u}"));
+    assert!(err.contains("allowlist"));
+
+    let big = format!("{node_address}:100000:{policy_asset}");
+    let r = sh(&format!(
+        "{cli} wallet send --wallet multi --recipient {big}"
+    ));
+    let pset_u = r.get("pset").unwrap().as_str().unwrap();
+    let err = sh_err(&format!("{cli} signer sign --signer s1 --pset {pset_u}"));
+    assert!(err.contains("daily limit"));
+
+    // The policy cannot be removed while the signers of the wallet are loaded
+    let err = sh_err(&format!("{cli} wallet set-policy --wallet multi"));
+    assert!(err.contains("cannot be removed"));
+    let r = sh(&format!("{cli} signer details --signer s1"));
+    let mnemonic1 = get_str(&r, "mnemonic").to_string();
+    sh(&format!("{cli} signer unload --signer s1"));
+    sh(&format!("{cli} signer unload --signer s2"));
+    sh(&format!("{cli} wallet set-policy --wallet multi"));
+    sh(&format!(
+        "{cli} signer load-software --persist true --mnemonic \"{mnemonic1}\" --signer s1"
+    ));
+
+    // Without policy any PSET can be signed
+    sh(&format!("{cli} signer sign --signer s1 --pset {pset_u}"));
+
+    // With TOTP enrolled, signing over the threshold needs a code
+    let r = sh(&format!(
+        "{cli} signer enroll-totp --signer s1 --threshold 1000"
+    ));
+    assert!(r
+        .get("uri")
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .starts_with("otpauth://"));
+    let err = sh_err(&format!("{cli} signer sign --signer s1 --pset {pset_u}"));
+    assert!(err.contains("requires a TOTP code"));
+    let err = sh_err(&format!(
+        "{cli} signer sign --signer s1 --pset {pset_u} --totp 000000"
+    ));
+    assert!(err.contains("Invalid TOTP code"));
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_pset_session() {
+    let (t, tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    sw_signer(&cli, "s2");
+
+    let r = sh(&format!("{cli} signer xpub --signer s1 --kind bip87"));
+    let keyorigin_xpub1 = get_str(&r, "keyorigin_xpub").to_string();
+    let r = sh(&format!("{cli} signer xpub --signer s2 --kind bip87"));
+    let keyorigin_xpub2 = get_str(&r, "keyorigin_xpub").to_string();
+    let r = sh(&format!("{cli} wallet multisig-desc --descriptor-blinding-key slip77-rand --kind wsh --threshold 2 --keyorigin-xpub {keyorigin_xpub1} --keyorigin-xpub {keyorigin_xpub2}"));
+    let desc = get_str(&r, "descriptor").to_string();
+    sh(&format!("{cli} wallet load --wallet multi -d {desc}"));
+    let _ = fund(&server, &cli, "multi", 1_000_000);
+
+    let node_address = server.elementsd_getnewaddress();
+    let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let recipient = format!("{node_address}:1000:{policy_asset}");
+    let r = sh(&format!(
+        "{cli} wallet send --wallet multi --recipient {recipient}"
+    ));
+    let pset_u = get_str(&r, "pset").to_string();
+
+    let file = tmp.path().join("session.json").display().to_string();
+    let file2 = tmp.path().join("session2.json").display().to_string();
+    let err = sh_err(&format!("{cli} pset session save -f {file} -p {pset_u}"));
+    assert!(err.contains("Threshold is required"));
+    let r = sh(&format!(
+        "{cli} pset session save -f {file} -p {pset_u} --threshold 2"
+    ));
+    assert_eq!(get_str(&r, "state"), "waiting_signatures");
+    assert_eq!(r.get("participants").unwrap().as_array().unwrap().len(), 2);
+    assert_eq!(r.get("missing").unwrap().as_array().unwrap().len(), 2);
+    std::fs::copy(&file, &file2).unwrap();
+
+    // Cosigners sign in parallel on their copy of the session
+    let r = sh(&format!("{cli} signer sign --signer s1 --pset {pset_u}"));
+    let pset_s1 = get_str(&r, "pset").to_string();
+    let r = sh(&format!("{cli} pset session save -f {file} -p {pset_s1}"));
+    assert_eq!(get_str(&r, "state"), "waiting_signatures");
+    assert_eq!(r.get("missing").unwrap().as_array().unwrap().len(), 1);
+
+    let r = sh(&format!("{cli} signer sign --signer s2 --pset {pset_u}"));
+    let pset_s2 = get_str(&r, "pset").to_string();
+    sh(&format!("{cli} psbt session save -f {file2} -p {pset_s2}"));
+
+    let r = sh(&format!(
+        "{cli} pset session merge -f {file} --other {file2}"
+    ));
+    assert_eq!(get_str(&r, "state"), "ready_to_finalize");
+    let r = sh(&format!("{cli} pset session load -f {file}"));
+    assert_eq!(get_str(&r, "state"), "ready_to_finalize");
+    assert!(r.get("missing").unwrap().as_array().unwrap().is_empty());
+    let pset = get_str(&r, "pset").to_string();
+
+    let r = sh(&format!(
+        "{cli} wallet broadcast --wallet multi --pset {pset}"
+    ));
+    assert!(r.get("txid").unwrap().as_str().is_some());
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
 #[test]
 fn test_inconsistent_network() {
     let (_t, _tmp, cli, _params, _server, _) = setup_cli(false);
@@ -1236,6 +1628,135 @@ fn test_inconsistent_network() {
     assert!(err.contains("Inconsistent network"));
 }
 
+#[test]
+fn test_accounts() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    sw_signer(&cli, "s2");
+
+    let r = sh(&format!("{cli} account list --signer s1"));
+    assert!(r.get("accounts").unwrap().as_array().unwrap().is_empty());
+
+    let r = sh(&format!(
+        "{cli} account new --signer s1 --kind wpkh --descriptor-blinding-key slip77"
+    ));
+    assert_eq!(get_str(&r, "name"), "s1-wpkh-0");
+    assert_eq!(r.get("index").unwrap().as_u64().unwrap(), 0);
+    assert!(get_str(&r, "descriptor").contains("/84h/1h/0h]"));
+
+    let r = sh(&format!(
+        "{cli} account new --signer s1 --kind wpkh --descriptor-blinding-key slip77 -w savings"
+    ));
+    assert_eq!(get_str(&r, "name"), "savings");
+    assert_eq!(r.get("index").unwrap().as_u64().unwrap(), 1);
+    assert!(get_str(&r, "descriptor").contains("/84h/1h/1h]"));
+
+    let keyorigin_xpub2 = keyorigin(&cli, "s2", "bip87");
+    let r = sh(&format!("{cli} account new --signer s1 --kind wsh --descriptor-blinding-key slip77-rand --threshold 2 --cosigner {keyorigin_xpub2}"));
+    assert_eq!(get_str(&r, "name"), "s1-wsh-0");
+    assert!(get_str(&r, "descriptor").contains("/87h/1h/0h]"));
+
+    let err = sh_err(&format!(
+        "{cli} account new --signer s1 --kind wsh --descriptor-blinding-key slip77"
+    ));
+    assert!(err.contains("threshold"));
+
+    // wallets not created with account new are recognized as accounts
+    singlesig_wallet(&cli, "s2-wallet", "s2", "slip77", "shwpkh");
+
+    let _ = fund(&server, &cli, "s1-wpkh-0", 1_000_000);
+    let _ = fund(&server, &cli, "savings", 2_000_000);
+
+    let r = sh(&format!("{cli} account list --signer s1"));
+    let accounts = r.get("accounts").unwrap().as_array().unwrap();
+    assert_eq!(accounts.len(), 3);
+    let names: Vec<_> = accounts.iter().map(|a| get_str(a, "name")).collect();
+    assert_eq!(names, vec!["s1-wpkh-0", "savings", "s1-wsh-0"]);
+    let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let total = r.get("balance").unwrap().get(policy_asset).unwrap();
+    assert_eq!(total.as_u64().unwrap(), 3_000_000);
+
+    let r = sh(&format!("{cli} account list --signer s2"));
+    let accounts = r.get("accounts").unwrap().as_array().unwrap();
+    assert_eq!(accounts.len(), 2);
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_decode() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+    let _ = fund(&server, &cli, "w1", 1_000_000);
+
+    let node_address = server.elementsd_getnewaddress();
+    let r = sh(&format!(
+        "{cli} wallet send --wallet w1 --recipient {node_address}:1000"
+    ));
+    let pset = get_str(&r, "pset");
+
+    // Without the wallet only the PSET fields are available
+    let r = sh(&format!("{cli} decode pset --pset {pset}"));
+    let outputs = r.get("outputs").unwrap().as_array().unwrap();
+    assert_eq!(outputs.len(), 3);
+    let recipient = outputs
+        .iter()
+        .find(|o| o.get("address").unwrap().as_str() == Some(&node_address.to_string()))
+        .unwrap();
+    assert_eq!(recipient.get("satoshi").unwrap().as_u64().unwrap(), 1000);
+    let fee = r.get("fee").unwrap().as_u64().unwrap();
+    assert!(fee > 0);
+    let fee_output = outputs
+        .iter()
+        .find(|o| o.get("is_fee").unwrap().as_bool().unwrap())
+        .unwrap();
+    assert_eq!(fee_output.get("satoshi").unwrap().as_u64().unwrap(), fee);
+
+    let signed = get_str(
+        &sh(&format!("{cli} signer sign --signer s1 --pset {pset}")),
+        "pset",
+    )
+    .to_string();
+    let r = sh(&format!(
+        "{cli} wallet broadcast --wallet w1 --pset {signed}"
+    ));
+    let txid = get_str(&r, "txid").to_string();
+    wait_tx(&cli, "w1", &txid);
+    let r = sh(&format!("{cli} wallet tx --wallet w1 --txid {txid}"));
+    let tx = get_str(&r, "tx").to_string();
+
+    // The transaction outputs are blinded, amounts are known only to the wallet
+    let r = sh(&format!("{cli} decode tx --tx {tx}"));
+    assert_eq!(get_str(&r, "txid"), txid);
+    assert_eq!(r.get("fee").unwrap().as_u64().unwrap(), fee);
+    let inputs = r.get("inputs").unwrap().as_array().unwrap();
+    assert!(inputs.iter().all(|i| i.get("satoshi").unwrap().is_null()));
+    let outputs = r.get("outputs").unwrap().as_array().unwrap();
+    let n_unknown = outputs
+        .iter()
+        .filter(|o| o.get("satoshi").unwrap().is_null())
+        .count();
+    assert_eq!(n_unknown, 2);
+
+    let r = sh(&format!("{cli} decode tx --tx {tx} --wallet w1"));
+    let inputs = r.get("inputs").unwrap().as_array().unwrap();
+    assert!(inputs.iter().all(|i| !i.get("satoshi").unwrap().is_null()));
+    let outputs = r.get("outputs").unwrap().as_array().unwrap();
+    let n_unknown = outputs
+        .iter()
+        .filter(|o| o.get("satoshi").unwrap().is_null())
+        .count();
+    // the recipient output cannot be unblinded by the wallet
+    assert_eq!(n_unknown, 1);
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
 #[test]
 fn test_schema() {
     let (t, _tmp, cli, _params, _server, _) = setup_cli(false);
@@ -1280,6 +1801,36 @@ fn test_schema() {
         assert!(result.get("$schema").is_some(), "failed for {}", cmd);
     }
 
+    for a in AccountSubCommandsEnum::value_variants() {
+        let a = a.to_possible_value();
+        let cmd = a.map(|e| e.get_name().to_string()).unwrap();
+        let result = sh(&format!("{cli} schema request account {cmd}"));
+        assert!(result.get("$schema").is_some(), "failed for {}", cmd);
+
+        let result = sh(&format!("{cli} schema response account {cmd}"));
+        assert!(result.get("$schema").is_some(), "failed for {}", cmd);
+    }
+
+    for a in TxSubCommandsEnum::value_variants() {
+        let a = a.to_possible_value();
+        let cmd = a.map(|e| e.get_name().to_string()).unwrap();
+        let result = sh(&format!("{cli} schema request tx {cmd}"));
+        assert!(result.get("$schema").is_some(), "failed for {}", cmd);
+
+        let result = sh(&format!("{cli} schema response tx {cmd}"));
+        assert!(result.get("$schema").is_some(), "failed for {}", cmd);
+    }
+
+    for a in DecodeSubCommandsEnum::value_variants() {
+        let a = a.to_possible_value();
+        let cmd = a.map(|e| e.get_name().to_string()).unwrap();
+        let result = sh(&format!("{cli} schema request decode {cmd}"));
+        assert!(result.get("$schema").is_some(), "failed for {}", cmd);
+
+        let result = sh(&format!("{cli} schema response decode {cmd}"));
+        assert!(result.get("$schema").is_some(), "failed for {}", cmd);
+    }
+
     sh(&format!("{cli} server stop"));
     t.join().unwrap();
 }
@@ -1453,6 +2004,111 @@ fn test_start_errors() {
     t.join().unwrap();
 }
 
+#[test]
+fn test_config_file() {
+    let server = lwk_test_util::setup();
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("config.toml");
+    let addr = get_available_addr().unwrap();
+    let init = format!(
+        "cli -n regtest --addr {addr} --config {} config init",
+        path.display()
+    );
+
+    let r = sh(&init);
+    assert_eq!(get_str(&r, "path"), path.display().to_string());
+    let template = std::fs::read_to_string(&path).unwrap();
+    assert!(template.contains("network = \"regtest\""));
+    assert!(template.contains(&format!("# addr = \"{addr}\"")));
+    let err = sh_err(&init);
+    assert!(err.contains("already exists"));
+    sh(&format!("{init} --force"));
+
+    let config = format!(
+        "network = \"regtest\"\naddr = \"{addr}\"\ndatadir = \"{}\"\nelectrum_url = \"{}\"\nscanning_interval = 1\nauth_tokens = [\"secret\"]\n",
+        tmp.path().display(),
+        server.electrs.electrum_url,
+    );
+    std::fs::write(&path, config).unwrap();
+    let cli = format!("cli --config {}", path.display());
+    let t = {
+        let cli = cli.clone();
+        std::thread::spawn(move || {
+            sh(&format!("{cli} server start"));
+        })
+    };
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    // the network, the address and the token are taken from the config file
+    sh(&format!("{cli} signer list"));
+    let err = sh_err(&format!("{cli} --auth-token wrong signer list"));
+    assert!(err.contains("authorization token"), "{err}");
+    let err = sh_err(&format!("cli -n regtest --addr {addr} signer list"));
+    assert!(err.contains("authorization token"), "{err}");
+
+    std::fs::write(&path, "electrum = \"typo\"").unwrap();
+    let err = sh_err(&format!("{cli} signer list"));
+    assert!(err.contains("Invalid config file"), "{err}");
+
+    sh(&format!(
+        "cli -n regtest --addr {addr} --auth-token secret server stop"
+    ));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_tx_broadcast() {
+    let server = lwk_test_util::setup();
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("config.toml");
+    let addr = get_available_addr().unwrap();
+    let (user, password) = server.elements_rpc_credentials();
+    let config = format!(
+        "network = \"regtest\"\naddr = \"{addr}\"\ndatadir = \"{}\"\nelectrum_url = \"{}\"\nscanning_interval = 1\nelements_rpc_url = \"{}\"\nelements_rpc_user = \"{user}\"\nelements_rpc_password = \"{password}\"\n",
+        tmp.path().display(),
+        server.electrs.electrum_url,
+        server.elements_rpc_url(),
+    );
+    std::fs::write(&path, config).unwrap();
+    let cli = format!("cli --config {}", path.display());
+    let t = {
+        let cli = cli.clone();
+        std::thread::spawn(move || {
+            sh(&format!("{cli} server start"));
+        })
+    };
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    sw_signer(&cli, "sw");
+    singlesig_wallet(&cli, "w1", "sw", "slip77", "wpkh");
+    let _ = fund(&server, &cli, "w1", 1_000_000);
+    let node_address = server.elementsd_getnewaddress();
+    let r = sh(&format!(
+        "{cli} wallet send -w w1 --recipient {node_address}:1000"
+    ));
+    let pset = get_str(&r, "pset");
+    let r = sh(&format!("{cli} signer sign -s sw --pset {pset}"));
+    let pset = get_str(&r, "pset");
+
+    // The node checks the transaction without broadcasting it
+    let r = sh(&format!("{cli} tx broadcast --pset {pset} --dry-run"));
+    assert!(!r.get("broadcast").unwrap().as_bool().unwrap());
+    assert!(r.get("mempool_accepted").unwrap().as_bool().unwrap());
+    let txid = get_str(&r, "txid").to_string();
+
+    let r = sh(&format!("{cli} tx broadcast --pset {pset}"));
+    assert!(r.get("broadcast").unwrap().as_bool().unwrap());
+    assert_eq!(get_str(&r, "txid"), txid);
+    wait_tx(&cli, "w1", &txid);
+
+    // Already in the mempool
+    let err = sh_err(&format!("{cli} tx broadcast --pset {pset} --dry-run"));
+    assert!(err.contains("would not accept"), "{err}");
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
 #[test]
 fn test_send_all() {
     let (t, _tmp, cli, _params, server, _) = setup_cli(false);
@@ -1476,7 +2132,7 @@ fn test_send_all() {
 }
 
 #[test]
-fn test_ct_discount() {
+fn test_bump_fee() {
     let (t, _tmp, cli, _params, server, _) = setup_cli(false);
 
     sw_signer(&cli, "sw");
@@ -1485,6 +2141,44 @@ fn test_ct_discount() {
 
     let _ = fund(&server, &cli, "w1", 1_000_000);
 
+    // Send to ourselves, so that the replacement has only wallet outputs
+    let address = address(&cli, "w1");
+    let r = sh(&format!(
+        "{cli} wallet send -w w1 --recipient {address}:1000"
+    ));
+    let pset = get_str(&r, "pset");
+    let r = sh(&format!("{cli} wallet pset-details --wallet w1 -p {pset}"));
+    let fee = r.get("fee").unwrap().as_u64().unwrap();
+    let txid = complete(&cli, "w1", pset, signers);
+
+    let r = sh(&format!(
+        "{cli} wallet bump-fee -w w1 --txid {txid} --fee-rate 1000"
+    ));
+    let pset = get_str(&r, "pset");
+    let r = sh(&format!("{cli} wallet pset-details --wallet w1 -p {pset}"));
+    let bumped_fee = r.get("fee").unwrap().as_u64().unwrap();
+    assert!(bumped_fee > fee);
+    sh(&format!("{cli} signer sign -s sw --pset {pset}"));
+
+    let err = sh_err(&format!(
+        "{cli} wallet bump-fee -w w1 --txid {txid} --fee-rate 1"
+    ));
+    assert!(err.contains("must be higher than the current fee"));
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_ct_discount() {
+    let (t, _tmp, cli, params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "sw");
+    singlesig_wallet(&cli, "w1", "sw", "slip77", "wpkh");
+    let signers = &["sw"];
+
+    let _ = fund(&server, &cli, "w1", 1_000_000);
+
     let address = server.elementsd_getnewaddress();
     let sats = 1_000;
     let recipient = format!(" --recipient {address}:{sats}");
@@ -1498,6 +2192,27 @@ fn test_ct_discount() {
 
     assert_eq!(fee_default, 26);
 
+    // Restart the server without CT discount
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+    let t = {
+        let cli = cli.clone();
+        let params = params.clone();
+        std::thread::spawn(move || {
+            sh(&format!(
+                "{cli} server start --scanning-interval 1 --disable-ct-discount {params}"
+            ));
+        })
+    };
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+    sh(&format!("{cli} server scan"));
+
+    let r = sh(&format!("{cli} wallet send -w w1 {recipient}"));
+    let pset = get_str(&r, "pset");
+    let r = sh(&format!("{cli} wallet pset-details --wallet w1 -p {pset}"));
+    let fee_no_discount = r.get("fee").unwrap().as_u64().unwrap();
+    assert!(fee_no_discount > fee_default);
+
     sh(&format!("{cli} server stop"));
     t.join().unwrap();
 }
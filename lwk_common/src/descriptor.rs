@@ -8,17 +8,32 @@ use thiserror::Error;
 
 use crate::Signer;
 
+/// Create a singlesig CT descriptor for the first account of the `signer`
+///
+/// The returned descriptor has the checksum and it's the same returned by the
+/// `signer_singlesig_descriptor` RPC method, for instance
+/// `ct(slip77(...),elwpkh([fingerprint/84h/1h/0h]tpub.../<0;1>/*))#checksum`.
 // TODO impl error handling
 pub fn singlesig_desc<S: Signer>(
     signer: &S,
     script_variant: Singlesig,
     blinding_variant: DescriptorBlindingKey,
+) -> Result<String, String> {
+    singlesig_desc_account(signer, script_variant, blinding_variant, 0)
+}
+
+/// Like [`singlesig_desc`] but for the given BIP44 `account`
+pub fn singlesig_desc_account<S: Signer>(
+    signer: &S,
+    script_variant: Singlesig,
+    blinding_variant: DescriptorBlindingKey,
+    account: u32,
 ) -> Result<String, String> {
     let is_mainnet = signer.is_mainnet().map_err(|e| format!("{:?}", e))?;
     let coin_type = if is_mainnet { 1776 } else { 1 };
     let (prefix, path, suffix) = match script_variant {
-        Singlesig::Wpkh => ("elwpkh", format!("84h/{coin_type}h/0h"), ""),
-        Singlesig::ShWpkh => ("elsh(wpkh", format!("49h/{coin_type}h/0h"), ")"),
+        Singlesig::Wpkh => ("elwpkh", format!("84h/{coin_type}h/{account}h"), ""),
+        Singlesig::ShWpkh => ("elsh(wpkh", format!("49h/{coin_type}h/{account}h"), ")"),
     };
 
     let fingerprint = signer.fingerprint().map_err(|e| format!("{:?}", e))?;
@@ -52,6 +67,13 @@ fn fmt_path(path: &DerivationPath) -> String {
     path.to_string().replace("m/", "").replace('\'', "h")
 }
 
+/// Create a multisig CT descriptor with the given `threshold` of `xpubs`
+///
+/// The returned descriptor has the checksum and it's the same returned by the
+/// `wallet_multisig_descriptor` RPC method. The key origin of the xpubs is included if present.
+///
+/// The deterministic [`DescriptorBlindingKey::Slip77`] is not supported since there is no
+/// single seed the key can be derived from.
 // TODO impl error handling
 pub fn multisig_desc(
     threshold: u32,
@@ -67,32 +89,14 @@ pub fn multisig_desc(
 
     let (prefix, suffix) = match script_variant {
         Multisig::Wsh => ("elwsh(multi", ")"),
+        Multisig::WshSorted => ("elwsh(sortedmulti", ")"),
     };
 
-    let blinding_key = match blinding_variant {
-        DescriptorBlindingKey::Slip77 => {
-            return Err(
-                "Deterministic slip77 key not supported in multisig descriptor generation".into(),
-            )
-        }
-        DescriptorBlindingKey::Slip77Rand => {
-            let mut bytes = [0u8; 32];
-            thread_rng().fill(&mut bytes);
-            format!("slip77({})", bytes.to_hex())
-        }
-        DescriptorBlindingKey::Elip151 => "elip151".to_string(),
-    };
+    let blinding_key = multikey_blinding_key(blinding_variant, "multisig")?;
 
     let xpubs = xpubs
         .iter()
-        .map(|(keyorigin, xpub)| {
-            let prefix = if let Some((fingerprint, path)) = keyorigin {
-                format!("[{fingerprint}/{}]", fmt_path(path))
-            } else {
-                "".to_string()
-            };
-            format!("{prefix}{xpub}/<0;1>/*")
-        })
+        .map(|(keyorigin, xpub)| fmt_xpub(keyorigin, xpub))
         .collect::<Vec<_>>()
         .join(",");
     let desc = format!("ct({blinding_key},{prefix}({threshold},{xpubs}){suffix})");
@@ -100,6 +104,65 @@ pub fn multisig_desc(
     Ok(format!("{desc}#{checksum}"))
 }
 
+/// Create an inheritance CT descriptor: `owner` can spend at any time, `heir` can spend an
+/// output once it has `blocks` confirmations
+///
+/// The returned descriptor has the checksum, for instance
+/// `ct(elip151,elwsh(or_d(pk(owner/<0;1>/*),and_v(v:pk(heir/<0;1>/*),older(blocks)))))#checksum`.
+/// The key origin of the xpubs is included if present.
+///
+/// The owner should periodically move the funds to reset the timelock, otherwise the heir can
+/// spend them.
+///
+/// The deterministic [`DescriptorBlindingKey::Slip77`] is not supported since there is no
+/// single seed the key can be derived from.
+pub fn inheritance_desc(
+    owner: (Option<KeySource>, Xpub),
+    heir: (Option<KeySource>, Xpub),
+    blocks: u16,
+    blinding_variant: DescriptorBlindingKey,
+) -> Result<String, String> {
+    if blocks == 0 {
+        return Err("Timelock cannot be 0 blocks".into());
+    }
+    let blinding_key = multikey_blinding_key(blinding_variant, "inheritance")?;
+    let owner = fmt_xpub(&owner.0, &owner.1);
+    let heir = fmt_xpub(&heir.0, &heir.1);
+    let desc =
+        format!("ct({blinding_key},elwsh(or_d(pk({owner}),and_v(v:pk({heir}),older({blocks})))))");
+    let checksum = desc_checksum(&desc).map_err(|e| format!("{:?}", e))?;
+    Ok(format!("{desc}#{checksum}"))
+}
+
+fn fmt_xpub(keyorigin: &Option<KeySource>, xpub: &Xpub) -> String {
+    let prefix = if let Some((fingerprint, path)) = keyorigin {
+        format!("[{fingerprint}/{}]", fmt_path(path))
+    } else {
+        "".to_string()
+    };
+    format!("{prefix}{xpub}/<0;1>/*")
+}
+
+/// Blinding key for descriptors with keys from different signers
+fn multikey_blinding_key(
+    blinding_variant: DescriptorBlindingKey,
+    kind: &str,
+) -> Result<String, String> {
+    Ok(match blinding_variant {
+        DescriptorBlindingKey::Slip77 => {
+            return Err(format!(
+                "Deterministic slip77 key not supported in {kind} descriptor generation"
+            ))
+        }
+        DescriptorBlindingKey::Slip77Rand => {
+            let mut bytes = [0u8; 32];
+            thread_rng().fill(&mut bytes);
+            format!("slip77({})", bytes.to_hex())
+        }
+        DescriptorBlindingKey::Elip151 => "elip151".to_string(),
+    })
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Singlesig {
     /// as defined by bip84
@@ -206,12 +269,17 @@ impl FromStr for Bip {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub enum Multisig {
+    /// Witness script hash with `multi`, keys are in the descriptor order
     Wsh,
+
+    /// Witness script hash with `sortedmulti`, keys are sorted lexicographically at each derivation
+    WshSorted,
 }
 
 #[derive(Error, Debug)]
-#[error("Invalid multisig variant '{0}' supported variant are: 'wsh'")]
+#[error("Invalid multisig variant '{0}' supported variant are: 'wsh', 'wsh-sorted'")]
 pub struct InvalidMultisigVariant(String);
 
 impl FromStr for Multisig {
@@ -220,6 +288,7 @@ impl FromStr for Multisig {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
             "wsh" => Multisig::Wsh,
+            "wsh-sorted" => Multisig::WshSorted,
             v => return Err(InvalidMultisigVariant(v.to_string())),
         })
     }
@@ -229,7 +298,10 @@ impl FromStr for Multisig {
 mod test {
     use std::str::FromStr;
 
-    use super::Bip;
+    use elements::bitcoin::bip32::{DerivationPath, Fingerprint, Xpub};
+    use elements_miniscript::{ConfidentialDescriptor, DescriptorPublicKey};
+
+    use super::{inheritance_desc, multisig_desc, Bip, DescriptorBlindingKey, Multisig};
 
     #[test]
     fn roundtrip_bip() {
@@ -240,4 +312,80 @@ mod test {
         }
         Bip::from_str("vattelapesca").unwrap_err();
     }
+
+    #[test]
+    fn test_multisig_desc() {
+        let xpub = Xpub::from_str("tpubDCTUqRMrF2GHZ6pi5FcamdyGZ3oDJtQMH4y5Hyh8Uu7CQ3Zymbh1hpM84aXyJhgBhuh6WcUpKteMeYdyYfVUDRrsz8FUeRdoaaSRKkyMx6Y").unwrap();
+        let fingerprint = Fingerprint::from_str("11111111").unwrap();
+        let path = DerivationPath::from_str("m/87h/1h/0h").unwrap();
+        let xpubs = vec![(Some((fingerprint, path)), xpub), (None, xpub)];
+
+        let desc = multisig_desc(
+            2,
+            xpubs.clone(),
+            Multisig::Wsh,
+            DescriptorBlindingKey::Elip151,
+        )
+        .unwrap();
+        assert_eq!(
+            desc.split('#').next().unwrap(),
+            format!(
+                "ct(elip151,elwsh(multi(2,[11111111/87h/1h/0h]{xpub}/<0;1>/*,{xpub}/<0;1>/*)))"
+            )
+        );
+        ConfidentialDescriptor::<DescriptorPublicKey>::from_str(&desc).unwrap();
+
+        let desc = multisig_desc(
+            1,
+            xpubs.clone(),
+            Multisig::Wsh,
+            DescriptorBlindingKey::Slip77Rand,
+        )
+        .unwrap();
+        assert!(desc.starts_with("ct(slip77("));
+        ConfidentialDescriptor::<DescriptorPublicKey>::from_str(&desc).unwrap();
+
+        let multisig = Multisig::from_str("wsh-sorted").unwrap();
+        let desc =
+            multisig_desc(2, xpubs.clone(), multisig, DescriptorBlindingKey::Elip151).unwrap();
+        assert!(desc.starts_with("ct(elip151,elwsh(sortedmulti(2,"));
+        ConfidentialDescriptor::<DescriptorPublicKey>::from_str(&desc).unwrap();
+        Multisig::from_str("sh").unwrap_err();
+
+        for (threshold, blinding) in [
+            (0, DescriptorBlindingKey::Elip151),
+            (3, DescriptorBlindingKey::Elip151),
+            (1, DescriptorBlindingKey::Slip77),
+        ] {
+            multisig_desc(threshold, xpubs.clone(), Multisig::Wsh, blinding).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_inheritance_desc() {
+        let owner = Xpub::from_str("tpubDC2Q4xK4XH72GM7MowNuajyWVbigRLBWKswyP5T88hpPwu5nGqJWnda8zhJEFt71av73Hm8mUMMFSz9acNVzz8b1UbdSHCDXKTbSv5eEytu").unwrap();
+        let heir = Xpub::from_str("tpubDCTUqRMrF2GHZ6pi5FcamdyGZ3oDJtQMH4y5Hyh8Uu7CQ3Zymbh1hpM84aXyJhgBhuh6WcUpKteMeYdyYfVUDRrsz8FUeRdoaaSRKkyMx6Y").unwrap();
+        let fingerprint = Fingerprint::from_str("11111111").unwrap();
+        let path = DerivationPath::from_str("m/87h/1h/0h").unwrap();
+
+        let desc = inheritance_desc(
+            (Some((fingerprint, path)), owner),
+            (None, heir),
+            144,
+            DescriptorBlindingKey::Elip151,
+        )
+        .unwrap();
+        assert_eq!(
+            desc.split('#').next().unwrap(),
+            format!("ct(elip151,elwsh(or_d(pk([11111111/87h/1h/0h]{owner}/<0;1>/*),and_v(v:pk({heir}/<0;1>/*),older(144)))))")
+        );
+        ConfidentialDescriptor::<DescriptorPublicKey>::from_str(&desc).unwrap();
+
+        for (blocks, blinding) in [
+            (0, DescriptorBlindingKey::Elip151),
+            (144, DescriptorBlindingKey::Slip77),
+        ] {
+            inheritance_desc((None, owner), (None, heir), blocks, blinding).unwrap_err();
+        }
+    }
 }
@@ -63,6 +63,9 @@ pub enum Error {
     #[error("Private blinding key not available")]
     MissingPrivateBlindingKey,
 
+    #[error("Invalid UR: {0}")]
+    InvalidUr(String),
+
     #[error(transparent)]
     DescConversion(#[from] elements_miniscript::descriptor::ConversionError),
 
@@ -4,8 +4,9 @@
 //!
 //!   * Utils to inspect a PSET: get the net effect of a PSET on a given wallet [`pset_balance()`], or get how many
 //!     signatures are missing , and which signers should provide them [`pset_signatures()`].
+//!   * Check that a PSET is well formed before passing it to a role [`pset::sanity_check()`]
 //!  * [`Signer`] trait: contains the methods to be implemented by a signer such as signing a pset or
-//!     returning an xpub
+//!     returning an xpub, and a summary of what a signature authorizes [`Signer::preview()`]
 //!
 //!  To avoid circular dependencies this crate must not depend on other crate of the workspace
 
@@ -15,23 +16,25 @@ mod keyorigin_xpub;
 mod model;
 mod network;
 pub mod precision;
+pub mod pset;
 mod qr;
 mod segwit;
 mod signer;
+pub mod ur;
 
 pub use crate::descriptor::{
-    multisig_desc, singlesig_desc, Bip, DescriptorBlindingKey, InvalidBipVariant,
-    InvalidBlindingKeyVariant, InvalidMultisigVariant, InvalidSinglesigVariant, Multisig,
-    Singlesig,
+    inheritance_desc, multisig_desc, singlesig_desc, singlesig_desc_account, Bip,
+    DescriptorBlindingKey, InvalidBipVariant, InvalidBlindingKeyVariant, InvalidMultisigVariant,
+    InvalidSinglesigVariant, Multisig, Singlesig,
 };
 pub use crate::error::Error;
 pub use crate::keyorigin_xpub::{keyorigin_xpub_from_str, InvalidKeyOriginXpub};
 pub use crate::model::*;
 pub use crate::network::Network;
-pub use crate::precision::Precision;
+pub use crate::precision::{Precision, PrecisionAmount};
 pub use crate::qr::*;
 pub use crate::segwit::is_provably_segwit;
-pub use crate::signer::Signer;
+pub use crate::signer::{SignCapability, Signer};
 
 use elements::confidential::{Asset, Value};
 use elements_miniscript::confidential::bare::tweak_private_key;
@@ -39,13 +42,19 @@ use elements_miniscript::confidential::Key;
 use elements_miniscript::descriptor::DescriptorSecretKey;
 use elements_miniscript::elements::bitcoin::secp256k1::SecretKey;
 use elements_miniscript::elements::{
-    bitcoin::{bip32::KeySource, key::PublicKey},
+    bitcoin::{
+        bip32::{DerivationPath, Fingerprint, KeySource},
+        key::PublicKey,
+    },
     opcodes::all::OP_RETURN,
-    pset::PartiallySignedTransaction,
-    script::Builder,
+    pset::{Output, PartiallySignedTransaction},
+    script::{Builder, Instruction},
     secp256k1_zkp::{All, Generator, PedersenCommitment, Secp256k1},
-    AssetId, BlindAssetProofs, BlindValueProofs, OutPoint, Script, TxOutSecrets,
+    taproot::TapLeafHash,
+    Address, AddressParams, AssetId, BlindAssetProofs, BlindValueProofs, OutPoint, Script, TxOut,
+    TxOutSecrets,
 };
+use elements_miniscript::slip77::MasterBlindingKey;
 use elements_miniscript::{ConfidentialDescriptor, DescriptorPublicKey};
 use std::collections::btree_map::BTreeMap;
 
@@ -68,8 +77,15 @@ pub fn derive_blinding_key(
     descriptor: &ConfidentialDescriptor<DescriptorPublicKey>,
     script_pubkey: &Script,
 ) -> Option<SecretKey> {
+    derive_blinding_key_from_key(&descriptor.key, script_pubkey)
+}
+
+/// Derive the private blinding key for `script_pubkey` from a descriptor blinding key
+///
+/// Returns `None` if the descriptor blinding key is not private.
+pub fn derive_blinding_key_from_key(key: &Key, script_pubkey: &Script) -> Option<SecretKey> {
     let secp = Secp256k1::new();
-    match &descriptor.key {
+    match key {
         Key::Slip77(k) => Some(k.blinding_private_key(script_pubkey)),
         Key::View(DescriptorSecretKey::XPrv(dxk)) => {
             let k = dxk.xkey.to_priv();
@@ -223,6 +239,7 @@ pub fn pset_balance(
                 vout: idx as u32,
                 asset: output.asset,
                 value: output.amount,
+                confidential: output.blinding_key.is_some(),
             };
 
             recipients.push(recipient);
@@ -291,6 +308,183 @@ pub fn pset_balance(
     })
 }
 
+/// Compute what a signer authorizes by signing the PSET, see [`Signer::preview()`]
+///
+/// The inputs of the signer are the ones with key origins matching `fingerprint`. The outputs of
+/// the signer are the ones paying to a key with such origin, which is derived with
+/// `derive_pubkey`, since the key origins are set by the creator of the PSET. The inputs and
+/// outputs of the signer are unblinded with `master_blinding_key`. Outputs blinded for someone
+/// else are reported only if the PSET contains valid blind proofs of their asset and value.
+pub fn pset_preview<F>(
+    pset: &PartiallySignedTransaction,
+    fingerprint: Fingerprint,
+    derive_pubkey: F,
+    master_blinding_key: Option<&MasterBlindingKey>,
+) -> SignPreview
+where
+    F: Fn(&DerivationPath) -> Option<PublicKey>,
+{
+    let secp = Secp256k1::new();
+    let mut balances: BTreeMap<AssetId, i64> = BTreeMap::new();
+    let mut complete = true;
+
+    let mut inputs = vec![];
+    for (idx, input) in pset.inputs().iter().enumerate() {
+        if !has_fingerprint(&input.bip32_derivation, &input.tap_key_origins, fingerprint) {
+            continue;
+        }
+        inputs.push(idx);
+        let secrets = input.witness_utxo.as_ref().and_then(|txout| {
+            // The rangeproof is stored in another field since the output witness is not serialized
+            let mut txout = txout.clone();
+            txout
+                .witness
+                .rangeproof
+                .clone_from(&input.in_utxo_rangeproof);
+            txout_secrets(&secp, &txout, master_blinding_key)
+        });
+        match secrets {
+            Some((asset, value)) => *balances.entry(asset).or_default() -= value as i64,
+            None => complete = false,
+        }
+    }
+
+    let mut outputs = vec![];
+    let mut fee: Option<u64> = None;
+    for (vout, output) in pset.outputs().iter().enumerate() {
+        let is_mine = pays_to_signer(&secp, output, fingerprint, &derive_pubkey);
+        let is_fee = output.script_pubkey.is_empty();
+        let secrets = if is_mine {
+            // the blind proofs do not prove the signer can unblind the output
+            let secrets = txout_secrets(&secp, &output.to_txout(), master_blinding_key);
+            match secrets {
+                Some((asset, value)) => *balances.entry(asset).or_default() += value as i64,
+                None => complete = false,
+            }
+            secrets
+        } else {
+            txout_secrets(&secp, &output.to_txout(), None).or_else(|| proven_secrets(&secp, output))
+        };
+        if is_fee {
+            let value = output.amount.filter(|_| output.amount_comm.is_none());
+            fee = Some(fee.unwrap_or(0) + value.unwrap_or(0));
+        }
+        outputs.push(PreviewOutput {
+            vout: vout as u32,
+            script_pubkey: output.script_pubkey.clone(),
+            asset: secrets.map(|(asset, _)| asset),
+            value: secrets.map(|(_, value)| value),
+            is_mine,
+            is_fee,
+            confidential: output.blinding_key.is_some() || output.asset_comm.is_some(),
+        });
+    }
+
+    // Remove assets with 0 balance, for example sent to the signer itself
+    balances.retain(|_, v| *v != 0);
+
+    SignPreview {
+        inputs,
+        outputs,
+        fee,
+        balances,
+        complete,
+    }
+}
+
+/// Whether the key origins of an input or an output contain `fingerprint`
+pub(crate) fn has_fingerprint<T>(
+    bip32_derivation: &BTreeMap<PublicKey, KeySource>,
+    tap_key_origins: &BTreeMap<T, (Vec<TapLeafHash>, KeySource)>,
+    fingerprint: Fingerprint,
+) -> bool {
+    bip32_derivation.values().any(|(f, _)| *f == fingerprint)
+        || tap_key_origins
+            .values()
+            .any(|(_, (f, _))| *f == fingerprint)
+}
+
+/// Whether `output` pays to a key of the signer with `fingerprint`
+///
+/// The key must be the one derived by the signer at its path and the output script must pay to
+/// it, alone or in its witness script, so that key origins added by the creator of the PSET
+/// cannot make an output look like one of the signer.
+fn pays_to_signer<F>(
+    secp: &Secp256k1<All>,
+    output: &Output,
+    fingerprint: Fingerprint,
+    derive_pubkey: &F,
+) -> bool
+where
+    F: Fn(&DerivationPath) -> Option<PublicKey>,
+{
+    let params = &AddressParams::ELEMENTS;
+    let script_pubkey = &output.script_pubkey;
+    let single = |pk: &PublicKey| {
+        *script_pubkey == Address::p2wpkh(pk, None, params).script_pubkey()
+            || *script_pubkey == Address::p2shwpkh(pk, None, params).script_pubkey()
+    };
+    let in_witness_script = |pk: &PublicKey| {
+        output.witness_script.as_ref().is_some_and(|ws| {
+            let pushed = ws.instructions().any(|i| {
+                matches!(i, Ok(Instruction::PushBytes(bytes)) if bytes == pk.to_bytes().as_slice())
+            });
+            pushed
+                && (*script_pubkey == Address::p2wsh(ws, None, params).script_pubkey()
+                    || *script_pubkey == Address::p2shwsh(ws, None, params).script_pubkey())
+        })
+    };
+    let ecdsa = output
+        .bip32_derivation
+        .iter()
+        .filter(|(_, (f, _))| *f == fingerprint)
+        .any(|(pk, (_, path))| {
+            derive_pubkey(path) == Some(*pk) && (single(pk) || in_witness_script(pk))
+        });
+    // key path spends only, the tweak of a script tree is not verified
+    let taproot = output
+        .tap_key_origins
+        .iter()
+        .filter(|(_, (_, (f, _)))| *f == fingerprint)
+        .any(|(key, (_, (_, path)))| {
+            derive_pubkey(path).map(|pk| pk.inner.x_only_public_key().0) == Some(*key)
+                && output.tap_internal_key == Some(*key)
+                && *script_pubkey == Address::p2tr(secp, *key, None, None, params).script_pubkey()
+        });
+    ecdsa || taproot
+}
+
+/// The asset and value of an explicit output, or of a blinded one unblindable with the given key
+fn txout_secrets(
+    secp: &Secp256k1<All>,
+    txout: &TxOut,
+    master_blinding_key: Option<&MasterBlindingKey>,
+) -> Option<(AssetId, u64)> {
+    match (txout.asset, txout.value) {
+        (Asset::Explicit(asset), Value::Explicit(value)) => Some((asset, value)),
+        (Asset::Confidential(_), Value::Confidential(_)) => {
+            let key = master_blinding_key?.blinding_private_key(&txout.script_pubkey);
+            let secrets = txout.unblind(secp, key).ok()?;
+            (commitments(secp, &secrets) == (txout.asset.commitment()?, txout.value.commitment()?))
+                .then_some((secrets.asset, secrets.value))
+        }
+        _ => None,
+    }
+}
+
+/// The asset and value of a blinded output, if proven by its blind proofs
+fn proven_secrets(secp: &Secp256k1<All>, output: &Output) -> Option<(AssetId, u64)> {
+    let asset = output.asset?;
+    let value = output.amount?;
+    let asset_comm = output.asset_comm?;
+    let value_comm = output.amount_comm?;
+    let asset_proof = output.blind_asset_proof.as_ref()?;
+    let value_proof = output.blind_value_proof.as_ref()?;
+    (asset_proof.blind_asset_proof_verify(secp, asset, asset_comm)
+        && value_proof.blind_value_proof_verify(secp, value, asset_comm, value_comm))
+    .then_some((asset, value))
+}
+
 pub fn pset_signatures(pset: &PartiallySignedTransaction) -> Vec<PsetSignatures> {
     pset.inputs()
         .iter()
@@ -323,10 +517,14 @@ pub fn burn_script() -> Script {
 
 #[cfg(test)]
 mod test {
+    use elements::bitcoin::bip32::{DerivationPath, Fingerprint};
+    use elements::bitcoin::PublicKey;
+    use elements::secp256k1_zkp::Secp256k1;
     use elements::{pset::PartiallySignedTransaction, AssetId};
-    use elements_miniscript::{ConfidentialDescriptor, DescriptorPublicKey};
+    use elements_miniscript::slip77::MasterBlindingKey;
+    use elements_miniscript::{ConfidentialDescriptor, DescriptorPublicKey, ForEachKey};
 
-    use crate::pset_balance;
+    use crate::{pset_balance, pset_preview};
 
     fn setup_pset_details() -> (AssetId, ConfidentialDescriptor<DescriptorPublicKey>) {
         let asset_id_str = "38fca2d939696061a8f76d4e6b5eecd54e3b4221c846f24a6b279e79952850a5";
@@ -386,4 +584,97 @@ mod test {
         assert_eq!(recipient.value.unwrap(), expected_value);
         assert_eq!(recipient.vout, 0);
     }
+
+    #[test]
+    fn test_pset_preview() {
+        let pset_str = include_str!("../test_data/pset_outputs/pset.base64");
+        let pset: PartiallySignedTransaction = pset_str.parse().unwrap();
+        let desc_str = include_str!("../test_data/pset_outputs/descriptor");
+        let desc: ConfidentialDescriptor<DescriptorPublicKey> = desc_str.parse().unwrap();
+        let balance = pset_balance(&pset, &desc, &elements::AddressParams::LIQUID_TESTNET).unwrap();
+        let slip77 = match desc.key {
+            elements_miniscript::confidential::Key::Slip77(k) => k,
+            _ => unreachable!(),
+        };
+        let fingerprint = "43d9b504".parse().unwrap();
+        let mut account = None;
+        desc.descriptor.for_each_key(|k| {
+            if let DescriptorPublicKey::MultiXPub(x) = k {
+                account = Some((x.origin.clone().unwrap().1, x.xkey));
+            }
+            true
+        });
+        let (account_path, account_xpub) = account.unwrap();
+        let derive_pubkey = |path: &DerivationPath| {
+            let rest = path.as_ref().strip_prefix(account_path.as_ref())?;
+            let xpub = account_xpub.derive_pub(&Secp256k1::new(), &rest).ok()?;
+            Some(PublicKey::new(xpub.public_key))
+        };
+
+        let preview = pset_preview(&pset, fingerprint, derive_pubkey, Some(&slip77));
+        assert!(preview.complete);
+        assert_eq!(preview.inputs.len(), pset.inputs().len());
+        assert_eq!(preview.fee, Some(balance.fee));
+        assert_eq!(preview.balances, balance.balances);
+        assert_eq!(preview.outputs.len(), pset.outputs().len());
+        let recipient = &preview.outputs[0];
+        assert!(!recipient.is_mine && !recipient.is_fee && recipient.confidential);
+        assert_eq!(recipient.value, Some(120));
+        assert_eq!(
+            recipient.asset.unwrap().to_string(),
+            "144c654344aa716d6f3abcc1ca90e5641e4e2a7f633bc09fe3baf64585819a49"
+        );
+        assert_eq!(preview.outputs.iter().filter(|o| o.is_fee).count(), 1);
+
+        // Without the blinding key the amounts of the signer are unknown
+        let preview = pset_preview(&pset, fingerprint, derive_pubkey, None);
+        assert!(!preview.complete);
+        assert_eq!(preview.outputs[0].value, Some(120));
+
+        // Another signer is not authorizing anything
+        let preview = pset_preview(
+            &pset,
+            "deadbeef".parse().unwrap(),
+            derive_pubkey,
+            Some(&slip77),
+        );
+        assert!(preview.complete);
+        assert!(preview.inputs.is_empty());
+        assert!(preview.balances.is_empty());
+        assert!(preview.outputs.iter().all(|o| !o.is_mine));
+
+        // Key origins claiming an output of someone else for the signer are not trusted
+        let mut forged = pset.clone();
+        let change = preview_change(&pset, fingerprint, derive_pubkey, &slip77);
+        let claimed = forged.outputs()[change].bip32_derivation.clone();
+        forged.outputs_mut()[0].bip32_derivation = claimed;
+        let preview = pset_preview(&forged, fingerprint, derive_pubkey, Some(&slip77));
+        assert!(!preview.outputs[0].is_mine);
+        assert!(preview.outputs[change].is_mine);
+        assert_eq!(preview.balances, balance.balances);
+
+        // as the ones of keys not derived by the signer
+        let mut forged = pset.clone();
+        let (pk, source) = forged.outputs_mut()[change]
+            .bip32_derivation
+            .pop_first()
+            .unwrap();
+        let mut other = pk;
+        other.compressed = !other.compressed;
+        forged.outputs_mut()[change]
+            .bip32_derivation
+            .insert(other, source);
+        let preview = pset_preview(&forged, fingerprint, derive_pubkey, Some(&slip77));
+        assert!(!preview.outputs[change].is_mine);
+    }
+
+    fn preview_change(
+        pset: &PartiallySignedTransaction,
+        fingerprint: Fingerprint,
+        derive_pubkey: impl Fn(&DerivationPath) -> Option<PublicKey>,
+        slip77: &MasterBlindingKey,
+    ) -> usize {
+        let preview = pset_preview(pset, fingerprint, derive_pubkey, Some(slip77));
+        preview.outputs.iter().position(|o| o.is_mine).unwrap()
+    }
 }
@@ -4,7 +4,7 @@ use elements_miniscript::elements::bitcoin::{
 };
 use elements_miniscript::elements::pset::Input;
 use elements_miniscript::elements::secp256k1_zkp::ZERO_TWEAK;
-use elements_miniscript::elements::{AssetId, AssetIssuance, OutPoint, Txid};
+use elements_miniscript::elements::{AssetId, AssetIssuance, OutPoint, Script, Txid};
 use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Debug, Clone)]
@@ -33,6 +33,60 @@ pub struct Recipient {
 
     /// The index of the output in the transaction
     pub vout: u32,
+
+    /// Whether the output has a blinding key, and thus will be blinded.
+    ///
+    /// Sending to an unconfidential output reveals the asset and the value to everyone.
+    pub confidential: bool,
+}
+
+/// What a signer authorizes by signing a PSET, see [`crate::Signer::preview()`]
+///
+/// Unlike [`PsetBalance`] it is computed without the wallet descriptor: the inputs and outputs of
+/// the signer are the ones with key origins matching its fingerprint and they are unblinded with
+/// its slip77 master blinding key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignPreview {
+    /// The indexes of the inputs the signer has keys for, thus is going to sign
+    pub inputs: Vec<usize>,
+
+    /// All the outputs of the transaction, including the fee
+    pub outputs: Vec<PreviewOutput>,
+
+    /// The fee of the transaction, `None` if there is no explicit fee output
+    pub fee: Option<u64>,
+
+    /// The net effect of the transaction on the funds of the signer, per asset
+    pub balances: BTreeMap<AssetId, i64>,
+
+    /// Whether all the inputs and outputs of the signer could be unblinded.
+    ///
+    /// If false `balances` misses some amounts and must not be used to approve the signature.
+    pub complete: bool,
+}
+
+/// An output of a PSET as seen by the signer, see [`SignPreview`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewOutput {
+    /// The index of the output in the transaction
+    pub vout: u32,
+
+    pub script_pubkey: Script,
+
+    /// The asset of the output, `None` if blinded for someone else without a valid blind proof
+    pub asset: Option<AssetId>,
+
+    /// The value of the output, `None` if blinded for someone else without a valid blind proof
+    pub value: Option<u64>,
+
+    /// Whether the output goes to a key of the signer, such as the change
+    pub is_mine: bool,
+
+    /// Whether the output is the fee
+    pub is_fee: bool,
+
+    /// Whether the output is or will be blinded
+    pub confidential: bool,
 }
 
 #[derive(Debug, Clone)]
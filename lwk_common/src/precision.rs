@@ -16,6 +16,9 @@ pub enum Error {
 
     #[error("Our precision is {our}, given a string with {given}")]
     StringTooPrecise { our: u8, given: u8 },
+
+    #[error("Expected ticker {expected}, given {given}")]
+    TickerMismatch { expected: String, given: String },
 }
 
 /// Helper to convert satoshi values of an asset to the value with the given precision and viceversa.
@@ -91,6 +94,35 @@ impl Precision {
         }
     }
 
+    /// Convert the given string to satoshi units, rounding the digits beyond our precision.
+    ///
+    /// Halves are rounded away from zero.
+    ///
+    /// ```
+    /// # use lwk_common::precision::Precision;
+    /// let p = Precision::new(2).unwrap();
+    /// assert_eq!(p.string_to_sats_rounded("1.004").unwrap(), 100);
+    /// assert_eq!(p.string_to_sats_rounded("1.005").unwrap(), 101);
+    /// assert_eq!(p.string_to_sats_rounded("-1.005").unwrap(), -101);
+    /// ```
+    pub fn string_to_sats_rounded(&self, val: &str) -> Result<i64, Error> {
+        let idx = match val.find('.') {
+            Some(idx) if val.len() - idx - 1 > self.0 as usize => idx,
+            _ => return self.string_to_sats(val),
+        };
+        // reject invalid digits also in the part that is rounded away
+        val.replacen('.', "", 1).parse::<i128>()?;
+        let (kept, dropped) = val.split_at(idx + 1 + self.0 as usize);
+        let sats = self.string_to_sats(kept.trim_end_matches('.'))?;
+        if dropped.starts_with(['5', '6', '7', '8', '9']) {
+            let sign = if kept.starts_with('-') { -1 } else { 1 };
+            sats.checked_add(sign)
+                .ok_or_else(|| Error::Overflow(val.to_string()))
+        } else {
+            Ok(sats)
+        }
+    }
+
     fn inner_convert(&self, val: &str) -> Result<i128, Error> {
         let num: i128 = val.parse()?;
         let pow = 10i128.pow(self.0 as u32);
@@ -99,6 +131,77 @@ impl Precision {
     }
 }
 
+/// An amount of an asset, displayed with the asset precision and ticker, e.g. `12.34 USDt`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecisionAmount {
+    sats: i64,
+    precision: u8,
+    ticker: String,
+}
+
+impl PrecisionAmount {
+    /// Create a new amount of `sats` of an asset with the given precision and ticker
+    pub fn new(sats: i64, precision: u8, ticker: &str) -> Result<Self, Error> {
+        Precision::new(precision)?;
+        Ok(Self {
+            sats,
+            precision,
+            ticker: ticker.to_string(),
+        })
+    }
+
+    /// Parse a displayed amount like `12.34 USDt` or `12.34`, rounding the digits beyond the
+    /// precision of the asset
+    ///
+    /// ```
+    /// # use lwk_common::precision::PrecisionAmount;
+    /// let a = PrecisionAmount::parse("12.345 USDt", 2, "USDt").unwrap();
+    /// assert_eq!(a.sats(), 1235);
+    /// assert_eq!(a.to_string(), "12.35 USDt");
+    /// ```
+    pub fn parse(s: &str, precision: u8, ticker: &str) -> Result<Self, Error> {
+        let s = s.trim();
+        let value = match s.split_once(' ') {
+            Some((value, given)) => {
+                if given.trim() != ticker {
+                    return Err(Error::TickerMismatch {
+                        expected: ticker.to_string(),
+                        given: given.trim().to_string(),
+                    });
+                }
+                value
+            }
+            None => s,
+        };
+        let sats = Precision::new(precision)?.string_to_sats_rounded(value)?;
+        Self::new(sats, precision, ticker)
+    }
+
+    /// The amount in satoshi units
+    pub fn sats(&self) -> i64 {
+        self.sats
+    }
+
+    /// The amount with the asset precision, without the ticker
+    pub fn value(&self) -> String {
+        Precision(self.precision).sats_to_string(self.sats)
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn ticker(&self) -> &str {
+        &self.ticker
+    }
+}
+
+impl std::fmt::Display for PrecisionAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.value(), self.ticker)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use rand::{thread_rng, Rng};
@@ -160,6 +263,40 @@ mod test {
         assert_eq!(exp, p.string_to_sats("0.01").unwrap_err().to_string());
     }
 
+    #[test]
+    fn test_rounding() {
+        let p = Precision::new(2).unwrap();
+        assert_eq!(p.string_to_sats_rounded("12.34").unwrap(), 1234);
+        assert_eq!(p.string_to_sats_rounded("12.344").unwrap(), 1234);
+        assert_eq!(p.string_to_sats_rounded("12.345").unwrap(), 1235);
+        assert_eq!(p.string_to_sats_rounded("12.3499").unwrap(), 1235);
+        assert_eq!(p.string_to_sats_rounded("12.349").unwrap(), 1235);
+        assert_eq!(p.string_to_sats_rounded("12.999").unwrap(), 1300);
+        assert_eq!(p.string_to_sats_rounded("-12.345").unwrap(), -1235);
+        assert_eq!(p.string_to_sats_rounded("-12.344").unwrap(), -1234);
+        assert_eq!(p.string_to_sats_rounded("0.004").unwrap(), 0);
+        assert_eq!(p.string_to_sats_rounded(".005").unwrap(), 1);
+        assert!(p.string_to_sats_rounded("12.34a").is_err());
+
+        let p = Precision::new(0).unwrap();
+        assert_eq!(p.string_to_sats_rounded("12.5").unwrap(), 13);
+        assert_eq!(p.string_to_sats_rounded("12.4").unwrap(), 12);
+    }
+
+    #[test]
+    fn test_precision_amount() {
+        let a = PrecisionAmount::new(1234, 2, "USDt").unwrap();
+        assert_eq!(a.to_string(), "12.34 USDt");
+        assert_eq!(a.value(), "12.34");
+        assert_eq!(PrecisionAmount::parse("12.34 USDt", 2, "USDt").unwrap(), a);
+        assert_eq!(PrecisionAmount::parse("12.34", 2, "USDt").unwrap(), a);
+        assert_eq!(PrecisionAmount::parse("12.3449", 2, "USDt").unwrap(), a);
+
+        let err = PrecisionAmount::parse("12.34 LCAD", 2, "USDt").unwrap_err();
+        assert_eq!(err.to_string(), "Expected ticker USDt, given LCAD");
+        assert!(PrecisionAmount::new(1, 9, "USDt").is_err());
+    }
+
     #[test]
     fn test_precision_roundtrips() {
         let mut rng = thread_rng();
@@ -0,0 +1,547 @@
+//! Checks that a PSET is well formed before handing it to a PSET role
+//!
+//! PSETv2 (BIP370 plus the Elements extensions) splits the life of a transaction across several
+//! roles, each one expecting the previous ones to have done their part. [`sanity_check()`]
+//! verifies those expectations, so that for instance a PSET missing the previous outputs is
+//! rejected with a descriptive error instead of reaching a hardware signer.
+//! [`verify_signatures()`] checks the signatures added by the signers, for instance on a
+//! coordinator before combining the PSETs of the cosigners.
+//! [`trim_for_signer()`] removes what a signer doesn't need, for hardware signers with limited
+//! message sizes.
+
+use std::collections::HashSet;
+
+use elements::bitcoin::bip32::Fingerprint;
+use elements::bitcoin::PublicKey;
+use elements::hashes::Hash;
+use elements::pset::{Input, Output, PartiallySignedTransaction};
+use elements::secp256k1_zkp::Secp256k1;
+use elements::sighash::SighashCache;
+use elements::{BlockHash, OutPoint, TxOut};
+use elements_miniscript::psbt::PsbtExt;
+use elements_miniscript::{elementssig_from_rawsig, ElementsSig};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("PSET has no inputs")]
+    NoInputs,
+
+    #[error("PSET has no outputs")]
+    NoOutputs,
+
+    #[error("Input #{idx} spends {outpoint} which is already spent by another input")]
+    DuplicateInput { idx: usize, outpoint: OutPoint },
+
+    #[error("Input #{idx} is missing the previous output, add witness_utxo or non_witness_utxo")]
+    MissingPreviousOutput { idx: usize },
+
+    #[error("Input #{idx} has a non_witness_utxo which is not the transaction it spends")]
+    NonWitnessUtxoMismatch { idx: usize },
+
+    #[error("Input #{idx} has a witness_utxo not matching its non_witness_utxo")]
+    WitnessUtxoMismatch { idx: usize },
+
+    #[error("Input #{idx} has no signatures")]
+    MissingSignatures { idx: usize },
+
+    #[error("Input #{idx} is not finalized")]
+    NotFinalized { idx: usize },
+
+    #[error("Output #{idx} has neither an explicit amount nor an amount commitment")]
+    OutputMissingAmount { idx: usize },
+
+    #[error("Output #{idx} has neither an explicit asset nor an asset commitment")]
+    OutputMissingAsset { idx: usize },
+
+    #[error("Output #{idx} has a blinder index {blinder_index} but there are {inputs} inputs")]
+    InvalidBlinderIndex {
+        idx: usize,
+        blinder_index: u32,
+        inputs: usize,
+    },
+
+    #[error("Output #{idx} has a blinding key but it is not blinded")]
+    OutputNotBlinded { idx: usize },
+
+    #[error("Input #{idx} has an invalid signature for {pubkey}")]
+    InvalidSignature { idx: usize, pubkey: PublicKey },
+
+    #[error("Cannot compute the sighash of input #{idx}: {reason}")]
+    Sighash { idx: usize, reason: String },
+}
+
+/// The roles defined by BIP174, BIP370 and the Elements PSET specification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Adds inputs and outputs
+    Constructor,
+
+    /// Adds the previous outputs, derivation paths and scripts of the inputs
+    Updater,
+
+    /// Blinds the outputs
+    Blinder,
+
+    /// Adds signatures
+    Signer,
+
+    /// Builds the final witnesses from the signatures
+    Finalizer,
+
+    /// Extracts the network serialized transaction
+    Extractor,
+}
+
+/// Check that `pset` can be passed to `role`
+///
+/// Every role requires a structurally valid PSET. Blinders and later roles also require the
+/// previous outputs of every input, signers and later roles also require the outputs with a
+/// blinding key to be blinded, since blinding after signing invalidates the signatures. Outputs
+/// trimmed by [`trim_for_signer()`] are considered blinded, except by extractors.
+/// Finalizers require at least a signature for every input and extractors require every input to
+/// be finalized.
+pub fn sanity_check(pset: &PartiallySignedTransaction, role: Role) -> Result<(), Error> {
+    check_structure(pset)?;
+
+    if role == Role::Constructor || role == Role::Updater {
+        return Ok(());
+    }
+    for (idx, input) in pset.inputs().iter().enumerate() {
+        check_previous_output(idx, input)?;
+    }
+
+    if role == Role::Blinder {
+        return Ok(());
+    }
+    for (idx, output) in pset.outputs().iter().enumerate() {
+        let trimmed = role != Role::Extractor && is_trimmed(output);
+        if output.blinding_key.is_some() && !is_blinded(output) && !trimmed {
+            return Err(Error::OutputNotBlinded { idx });
+        }
+    }
+
+    for (idx, input) in pset.inputs().iter().enumerate() {
+        match role {
+            Role::Finalizer if !is_finalized(input) && !has_signatures(input) => {
+                return Err(Error::MissingSignatures { idx })
+            }
+            Role::Extractor if !is_finalized(input) => return Err(Error::NotFinalized { idx }),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// A copy of `pset` without the fields not needed by the signer with `fingerprint`
+///
+/// Removed fields are the input derivations of the other keys, the previous transactions of
+/// inputs having the previous output, the rangeproofs of the previous outputs and, if no input is
+/// taproot, the range and surjection proofs of the blinded outputs having the explicit proofs of
+/// their commitments. The output derivations are kept, multisig signers use them to recognize the
+/// change.
+///
+/// ECDSA signatures don't commit to the removed proofs, so they are valid for `pset` too. Taproot
+/// signatures commit to the output proofs, which are kept if any input is taproot.
+///
+/// For very large transactions, the trimmed PSET may fit within the message size limits of
+/// hardware signers. Once signed, merge it with [`PartiallySignedTransaction::merge()`] into
+/// `pset` to restore the removed fields. The trimmed PSET passes [`sanity_check()`] up to
+/// [`Role::Finalizer`], it can't be extracted.
+pub fn trim_for_signer(
+    pset: &PartiallySignedTransaction,
+    fingerprint: Fingerprint,
+) -> PartiallySignedTransaction {
+    let mut pset = pset.clone();
+    let has_taproot_input = pset.inputs().iter().any(is_taproot);
+    for input in pset.inputs_mut() {
+        input.bip32_derivation.retain(|_, (f, _)| *f == fingerprint);
+        if input.witness_utxo.is_some() {
+            input.non_witness_utxo = None;
+        }
+        input.in_utxo_rangeproof = None;
+    }
+    for output in pset.outputs_mut() {
+        if !has_taproot_input
+            && is_blinded(output)
+            && output.blind_value_proof.is_some()
+            && output.blind_asset_proof.is_some()
+        {
+            output.value_rangeproof = None;
+            output.asset_surjection_proof = None;
+        }
+    }
+    pset
+}
+
+/// Whether the input spends a taproot output, or will be signed as one
+fn is_taproot(input: &Input) -> bool {
+    let spends_taproot = input
+        .witness_utxo
+        .as_ref()
+        .is_some_and(|u| u.script_pubkey.is_v1_p2tr());
+    spends_taproot || input.tap_internal_key.is_some() || !input.tap_key_origins.is_empty()
+}
+
+/// Verify the ECDSA signatures of the inputs, returning how many they are
+///
+/// Requires the previous outputs of the inputs with signatures and the scripts needed to compute
+/// their sighash, the witness script for P2WSH inputs.
+pub fn verify_signatures(pset: &PartiallySignedTransaction) -> Result<usize, Error> {
+    let sighash_error = |idx, reason: String| Error::Sighash { idx, reason };
+    let tx = pset
+        .extract_tx()
+        .map_err(|e| sighash_error(0, e.to_string()))?;
+    let mut cache = SighashCache::new(&tx);
+    let secp = Secp256k1::verification_only();
+    // genesis hash is used only for taproot sighashes
+    let genesis_hash = BlockHash::all_zeros();
+
+    let mut verified = 0;
+    for (idx, input) in pset.inputs().iter().enumerate() {
+        for (pubkey, rawsig) in input.partial_sigs.iter() {
+            let invalid = || Error::InvalidSignature {
+                idx,
+                pubkey: *pubkey,
+            };
+            let (sig, hash_ty): ElementsSig =
+                elementssig_from_rawsig(rawsig).map_err(|_| invalid())?;
+
+            // the sighash depends on the sighash type of the signature
+            let mut pset = pset.clone();
+            pset.inputs_mut()[idx].sighash_type = Some(hash_ty.into());
+            let msg = pset
+                .sighash_msg(idx, &mut cache, None, genesis_hash)
+                .map_err(|e| sighash_error(idx, e.to_string()))?
+                .to_secp_msg();
+            secp.verify_ecdsa(&msg, &sig, &pubkey.inner)
+                .map_err(|_| invalid())?;
+            verified += 1;
+        }
+    }
+    Ok(verified)
+}
+
+fn check_structure(pset: &PartiallySignedTransaction) -> Result<(), Error> {
+    if pset.inputs().is_empty() {
+        return Err(Error::NoInputs);
+    }
+    if pset.outputs().is_empty() {
+        return Err(Error::NoOutputs);
+    }
+
+    let mut outpoints = HashSet::new();
+    for (idx, input) in pset.inputs().iter().enumerate() {
+        let outpoint = OutPoint::new(input.previous_txid, input.previous_output_index);
+        if !outpoints.insert(outpoint) {
+            return Err(Error::DuplicateInput { idx, outpoint });
+        }
+    }
+
+    let inputs = pset.inputs().len();
+    for (idx, output) in pset.outputs().iter().enumerate() {
+        if output.amount.is_none() && output.amount_comm.is_none() {
+            return Err(Error::OutputMissingAmount { idx });
+        }
+        if output.asset.is_none() && output.asset_comm.is_none() {
+            return Err(Error::OutputMissingAsset { idx });
+        }
+        if let Some(blinder_index) = output.blinder_index {
+            if blinder_index as usize >= inputs {
+                return Err(Error::InvalidBlinderIndex {
+                    idx,
+                    blinder_index,
+                    inputs,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_previous_output(idx: usize, input: &Input) -> Result<(), Error> {
+    let vout = input.previous_output_index as usize;
+    match (&input.witness_utxo, &input.non_witness_utxo) {
+        (None, None) => Err(Error::MissingPreviousOutput { idx }),
+        (_, Some(tx)) if tx.txid() != input.previous_txid || tx.output.len() <= vout => {
+            Err(Error::NonWitnessUtxoMismatch { idx })
+        }
+        (Some(txout), Some(tx)) if !same_output(&tx.output[vout], txout) => {
+            Err(Error::WitnessUtxoMismatch { idx })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Compare the outputs ignoring the proofs, which may have been pruned from the witness utxo
+fn same_output(a: &TxOut, b: &TxOut) -> bool {
+    a.asset == b.asset
+        && a.value == b.value
+        && a.nonce == b.nonce
+        && a.script_pubkey == b.script_pubkey
+}
+
+fn is_blinded(output: &Output) -> bool {
+    output.amount_comm.is_some()
+        && output.asset_comm.is_some()
+        && output.value_rangeproof.is_some()
+        && output.asset_surjection_proof.is_some()
+}
+
+/// The output proofs have been removed by [`trim_for_signer()`]
+fn is_trimmed(output: &Output) -> bool {
+    output.amount_comm.is_some()
+        && output.asset_comm.is_some()
+        && output.value_rangeproof.is_none()
+        && output.asset_surjection_proof.is_none()
+        && output.blind_value_proof.is_some()
+        && output.blind_asset_proof.is_some()
+}
+
+fn has_signatures(input: &Input) -> bool {
+    !input.partial_sigs.is_empty()
+        || input.tap_key_sig.is_some()
+        || !input.tap_script_sigs.is_empty()
+}
+
+fn is_finalized(input: &Input) -> bool {
+    input.final_script_sig.is_some() || input.final_script_witness.is_some()
+}
+
+#[cfg(test)]
+mod test {
+    use elements::confidential::{Asset, Nonce, Value};
+    use elements::secp256k1_zkp::SecretKey;
+    use elements::sighash::Prevouts;
+    use elements::{AssetId, EcdsaSighashType, SchnorrSighashType, Script, WPubkeyHash};
+    use elements_miniscript::elementssig_to_rawsig;
+
+    use super::*;
+
+    fn pset() -> PartiallySignedTransaction {
+        include_str!("../test_data/pset_details/pset.base64")
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sanity_check() {
+        let pset = pset();
+        for role in [
+            Role::Constructor,
+            Role::Updater,
+            Role::Blinder,
+            Role::Signer,
+        ] {
+            sanity_check(&pset, role).unwrap();
+        }
+        assert_eq!(
+            sanity_check(&pset, Role::Finalizer),
+            Err(Error::MissingSignatures { idx: 0 })
+        );
+        assert_eq!(
+            sanity_check(&pset, Role::Extractor),
+            Err(Error::NotFinalized { idx: 0 })
+        );
+
+        let mut missing_utxo = pset.clone();
+        missing_utxo.inputs_mut()[0].witness_utxo = None;
+        sanity_check(&missing_utxo, Role::Updater).unwrap();
+        assert_eq!(
+            sanity_check(&missing_utxo, Role::Signer),
+            Err(Error::MissingPreviousOutput { idx: 0 })
+        );
+
+        let mut duplicate = pset.clone();
+        let input = duplicate.inputs()[0].clone();
+        let outpoint = OutPoint::new(input.previous_txid, input.previous_output_index);
+        duplicate.add_input(input);
+        let idx = duplicate.inputs().len() - 1;
+        assert_eq!(
+            sanity_check(&duplicate, Role::Constructor),
+            Err(Error::DuplicateInput { idx, outpoint })
+        );
+
+        let mut not_blinded = pset.clone();
+        let idx = not_blinded
+            .outputs()
+            .iter()
+            .position(|o| o.blinding_key.is_some())
+            .unwrap();
+        let output = &mut not_blinded.outputs_mut()[idx];
+        output.value_rangeproof = None;
+        output.blinder_index = Some(0);
+        sanity_check(&not_blinded, Role::Blinder).unwrap();
+        assert_eq!(
+            sanity_check(&not_blinded, Role::Signer),
+            Err(Error::OutputNotBlinded { idx })
+        );
+
+        not_blinded.outputs_mut()[idx].blinder_index = Some(100);
+        assert!(matches!(
+            sanity_check(&not_blinded, Role::Constructor),
+            Err(Error::InvalidBlinderIndex { .. })
+        ));
+
+        let mut missing_amount = pset;
+        let output = &mut missing_amount.outputs_mut()[0];
+        output.amount = None;
+        output.amount_comm = None;
+        assert_eq!(
+            sanity_check(&missing_amount, Role::Constructor),
+            Err(Error::OutputMissingAmount { idx: 0 })
+        );
+    }
+
+    #[test]
+    fn test_trim_for_signer() {
+        let pset = pset();
+        let fingerprints: HashSet<Fingerprint> = pset
+            .inputs()
+            .iter()
+            .flat_map(|i| i.bip32_derivation.values().map(|(f, _)| *f))
+            .collect();
+        let fingerprint = *fingerprints.iter().next().unwrap();
+        let other = Fingerprint::from([0xff; 4]);
+        assert!(!fingerprints.contains(&other));
+
+        let trimmed = trim_for_signer(&pset, fingerprint);
+        let size = |pset| elements::encode::serialize(pset).len();
+        assert!(size(&trimmed) < size(&pset));
+        assert!(trimmed
+            .inputs()
+            .iter()
+            .flat_map(|i| i.bip32_derivation.values())
+            .all(|(f, _)| *f == fingerprint));
+        assert!(trimmed
+            .outputs()
+            .iter()
+            .all(|o| o.value_rangeproof.is_none() || o.blind_value_proof.is_none()));
+        sanity_check(&trimmed, Role::Signer).unwrap();
+        assert_eq!(
+            sanity_check(&trimmed, Role::Finalizer),
+            Err(Error::MissingSignatures { idx: 0 })
+        );
+        let idx = trimmed
+            .outputs()
+            .iter()
+            .position(|o| o.blinding_key.is_some())
+            .unwrap();
+        assert_eq!(
+            sanity_check(&trimmed, Role::Extractor),
+            Err(Error::OutputNotBlinded { idx })
+        );
+
+        // The sighash doesn't change
+        let sighash = |pset: &PartiallySignedTransaction| {
+            let tx = pset.extract_tx().unwrap();
+            pset.sighash_msg(0, &mut SighashCache::new(&tx), None, BlockHash::all_zeros())
+                .unwrap()
+                .to_secp_msg()
+        };
+        assert_eq!(sighash(&trimmed), sighash(&pset));
+
+        // Merging restores the removed fields
+        let mut restored = pset.clone();
+        restored.merge(trimmed).unwrap();
+        assert_eq!(restored.inputs(), pset.inputs());
+        assert_eq!(restored.outputs(), pset.outputs());
+
+        let trimmed = trim_for_signer(&pset, other);
+        assert!(trimmed
+            .inputs()
+            .iter()
+            .all(|i| i.bip32_derivation.is_empty()));
+
+        // The output derivations are kept to recognize the change
+        assert_eq!(
+            trimmed
+                .outputs()
+                .iter()
+                .map(|o| &o.bip32_derivation)
+                .collect::<Vec<_>>(),
+            pset.outputs()
+                .iter()
+                .map(|o| &o.bip32_derivation)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_trim_for_signer_taproot() {
+        let mut pset = pset();
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let (internal_key, _) = secret_key.x_only_public_key(&secp);
+        let utxo = pset.inputs_mut()[0].witness_utxo.as_mut().unwrap();
+        utxo.script_pubkey = Script::new_v1_p2tr(&secp, internal_key, None);
+        let fingerprint = Fingerprint::from([0xff; 4]);
+
+        // Taproot signatures commit to the output proofs
+        let sighash = |pset: &PartiallySignedTransaction| {
+            let tx = pset.extract_tx().unwrap();
+            let prevouts: Vec<_> = pset
+                .inputs()
+                .iter()
+                .map(|i| i.witness_utxo.clone().unwrap())
+                .collect();
+            SighashCache::new(&tx)
+                .taproot_key_spend_signature_hash(
+                    0,
+                    &Prevouts::All(&prevouts),
+                    SchnorrSighashType::Default,
+                    BlockHash::all_zeros(),
+                )
+                .unwrap()
+        };
+        let mut without_proofs = pset.clone();
+        for output in without_proofs.outputs_mut() {
+            output.value_rangeproof = None;
+            output.asset_surjection_proof = None;
+        }
+        assert_ne!(sighash(&without_proofs), sighash(&pset));
+
+        // Thus they are kept and the sighash doesn't change
+        let trimmed = trim_for_signer(&pset, fingerprint);
+        assert_eq!(trimmed.outputs(), pset.outputs());
+        assert_eq!(sighash(&trimmed), sighash(&pset));
+    }
+
+    #[test]
+    fn test_verify_signatures() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let pubkey = PublicKey::new(secret_key.public_key(&secp));
+        let asset = AssetId::from_slice(&[2; 32]).unwrap();
+        let script_pubkey = Script::new_v0_wpkh(&WPubkeyHash::hash(&pubkey.to_bytes()));
+
+        let mut pset = PartiallySignedTransaction::new_v2();
+        let mut input = Input::from_prevout(OutPoint::default());
+        input.witness_utxo = Some(TxOut {
+            asset: Asset::Explicit(asset),
+            value: Value::Explicit(10_000),
+            nonce: Nonce::Null,
+            script_pubkey: script_pubkey.clone(),
+            witness: Default::default(),
+        });
+        pset.add_input(input);
+        pset.add_output(Output::new_explicit(script_pubkey, 9_000, asset, None));
+        pset.add_output(Output::new_explicit(Script::new(), 1_000, asset, None));
+        assert_eq!(verify_signatures(&pset), Ok(0));
+
+        let tx = pset.extract_tx().unwrap();
+        let msg = pset
+            .sighash_msg(0, &mut SighashCache::new(&tx), None, BlockHash::all_zeros())
+            .unwrap()
+            .to_secp_msg();
+        let sig = secp.sign_ecdsa(&msg, &secret_key);
+        let rawsig = elementssig_to_rawsig(&(sig, EcdsaSighashType::All));
+        pset.inputs_mut()[0].partial_sigs.insert(pubkey, rawsig);
+        assert_eq!(verify_signatures(&pset), Ok(1));
+
+        pset.outputs_mut()[0].amount = Some(8_000);
+        assert_eq!(
+            verify_signatures(&pset),
+            Err(Error::InvalidSignature { idx: 0, pubkey })
+        );
+    }
+}
@@ -18,8 +18,12 @@ fn address_to_qr_text(address: &Address) -> String {
 
 /// Convert the given address in a string representing a QR code to be consumed from a terminal
 pub fn address_to_text_qr(address: &Address) -> Result<String, QrError> {
-    let address = address_to_qr_text(address);
-    let qr_code = qr_code::QrCode::new(address)?;
+    string_to_text_qr(&address_to_qr_text(address))
+}
+
+/// Convert the given string in a string representing a QR code to be consumed from a terminal
+pub fn string_to_text_qr(s: &str) -> Result<String, QrError> {
+    let qr_code = qr_code::QrCode::new(s)?;
     Ok(qr_code.to_string(true, 3))
 }
 
@@ -11,6 +11,20 @@ use elements::{
 use elements_miniscript::slip77::MasterBlindingKey;
 
 use crate::descriptor::Bip;
+use crate::{has_fingerprint, pset_preview, SignPreview};
+
+/// Which inputs of a PSET a signer has keys for, see [`Signer::can_sign()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignCapability {
+    /// The signer has keys for all the inputs
+    Full,
+
+    /// The signer has keys only for the inputs at the given indexes
+    Partial(Vec<usize>),
+
+    /// The signer has keys for none of the inputs, it is not part of the wallet spending them
+    NotInWallet,
+}
 
 /// A trait defining methods of signers, providing blanket implementations for some methods.
 pub trait Signer {
@@ -43,11 +57,21 @@ pub trait Signer {
 
     /// Return keyorigin and xpub, like "[73c5da0a/84h/1h/0h]tpub..."
     fn keyorigin_xpub(&self, bip: Bip, is_mainnet: bool) -> Result<String, Self::Error> {
+        self.keyorigin_xpub_account(bip, is_mainnet, 0)
+    }
+
+    /// Return keyorigin and xpub for the given account, like "[73c5da0a/84h/1h/2h]tpub..."
+    fn keyorigin_xpub_account(
+        &self,
+        bip: Bip,
+        is_mainnet: bool,
+        account: u32,
+    ) -> Result<String, Self::Error> {
         let coin_type = if is_mainnet { 1776 } else { 1 };
         let path = match bip {
-            Bip::Bip84 => format!("84h/{coin_type}h/0h"),
-            Bip::Bip49 => format!("49h/{coin_type}h/0h"),
-            Bip::Bip87 => format!("87h/{coin_type}h/0h"),
+            Bip::Bip84 => format!("84h/{coin_type}h/{account}h"),
+            Bip::Bip49 => format!("49h/{coin_type}h/{account}h"),
+            Bip::Bip87 => format!("87h/{coin_type}h/{account}h"),
         };
 
         let fingerprint = self.fingerprint()?;
@@ -57,6 +81,56 @@ pub trait Signer {
         Ok(keyorigin_xpub)
     }
 
+    /// Return which inputs of `pset` this signer has keys for
+    ///
+    /// The inputs are matched using the key origins (fingerprint) of their derivations, the
+    /// signer is not asked to derive keys, so this can be used to drive UIs without interacting
+    /// with hardware devices more than needed.
+    fn can_sign(&self, pset: &PartiallySignedTransaction) -> Result<SignCapability, Self::Error> {
+        let fingerprint = self.fingerprint()?;
+        let signable: Vec<usize> = pset
+            .inputs()
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| {
+                has_fingerprint(&input.bip32_derivation, &input.tap_key_origins, fingerprint)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        Ok(if signable.is_empty() {
+            SignCapability::NotInWallet
+        } else if signable.len() == pset.inputs().len() {
+            SignCapability::Full
+        } else {
+            SignCapability::Partial(signable)
+        })
+    }
+
+    /// Summarize what signing `pset` authorizes: the inputs signed, the outputs, the fee and the
+    /// net effect on the funds of the signer
+    ///
+    /// The summary is computed from the PSET and the keys of the signer only, independently from
+    /// the wallet that created the PSET, so that services signing without a user confirmation can
+    /// log or approve exactly what the key is authorizing. The keys of the outputs claimed by the
+    /// PSET as of the signer are derived to verify it. The amounts of the signer are unblinded
+    /// with its slip77 master blinding key, if the wallet uses another blinding key
+    /// [`SignPreview::complete`] is false.
+    fn preview(&self, pset: &PartiallySignedTransaction) -> Result<SignPreview, Self::Error> {
+        let fingerprint = self.fingerprint()?;
+        // Signers might not support slip77, in this case the amounts of the signer are unknown
+        let master_blinding_key = self.slip77_master_blinding_key().ok();
+        let derive_pubkey = |path: &DerivationPath| {
+            let xpub = self.derive_xpub(path).ok()?;
+            Some(bitcoin::PublicKey::new(xpub.public_key))
+        };
+        Ok(pset_preview(
+            pset,
+            fingerprint,
+            derive_pubkey,
+            master_blinding_key.as_ref(),
+        ))
+    }
+
     fn is_mainnet(&self) -> Result<bool, Self::Error> {
         let xpub = match self.xpub() {
             Ok(xpub) => xpub,
@@ -0,0 +1,395 @@
+//! Encode and decode data as [Uniform Resources](https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-005-ur.md),
+//! the format used by airgapped wallets and signers to exchange PSETs with animated QR codes.
+//!
+//! Multi-part URs contain only the fragments of the message in sequence, the rateless fountain
+//! parts following them are not returned, so the parts must be shown in a loop until the receiver
+//! decodes them.
+//! When decoding, the fountain parts are combined with the fragments, so the message is decoded
+//! even if some of the fragments shown by the sender are missed.
+
+use std::collections::BTreeSet;
+
+use elements::encode::{deserialize, serialize};
+use elements::pset::PartiallySignedTransaction;
+
+use crate::Error;
+
+/// UR type of PSETs, the same used for bitcoin PSBTs
+pub const CRYPTO_PSBT: &str = "crypto-psbt";
+
+/// Smallest length of the fragments of a multi-part UR
+const MIN_FRAGMENT_LEN: usize = 10;
+
+/// Encode the PSET as `crypto-psbt` UR parts, a single part if it fits in `max_fragment_len` bytes
+pub fn pset_to_ur(pset: &PartiallySignedTransaction, max_fragment_len: usize) -> Vec<String> {
+    let mut message = vec![];
+    cbor_bytes(&mut message, &serialize(pset));
+    encode(CRYPTO_PSBT, &message, max_fragment_len)
+}
+
+/// Encode the CBOR `message` as UR parts of type `ur_type`
+///
+/// A single part is returned if the message fits in `max_fragment_len` bytes, otherwise the
+/// message is split in fragments and multi-part URs like `ur:<type>/<n>-<count>/<data>` are
+/// returned.
+pub fn encode(ur_type: &str, message: &[u8], max_fragment_len: usize) -> Vec<String> {
+    if message.len() <= max_fragment_len {
+        return vec![ur::ur::encode(message, &ur::Type::Custom(ur_type))];
+    }
+    let max_fragment_len = max_fragment_len.max(MIN_FRAGMENT_LEN);
+    let mut encoder = ur::Encoder::new(message, max_fragment_len, ur_type)
+        .expect("message is not empty and fragment length is not zero");
+    (0..encoder.fragment_count())
+        .map(|_| encoder.next_part().expect("encoding in a vec can't fail"))
+        .collect()
+}
+
+/// Decode the PSET from the scanned `crypto-psbt` UR parts, in any order
+pub fn pset_from_ur<S: AsRef<str>>(parts: &[S]) -> Result<PartiallySignedTransaction, Error> {
+    let (ur_type, message) = decode(parts)?;
+    if ur_type != CRYPTO_PSBT {
+        return Err(Error::InvalidUr(format!(
+            "expected type {CRYPTO_PSBT}, found {ur_type}"
+        )));
+    }
+    let mut data = &message[..];
+    let bytes = cbor_read_bytes(&mut data)?;
+    deserialize(bytes).map_err(|e| Error::InvalidUr(e.to_string()))
+}
+
+/// Decode the UR parts, in any order, returning the UR type and the CBOR message
+pub fn decode<S: AsRef<str>>(parts: &[S]) -> Result<(String, Vec<u8>), Error> {
+    let mut decoder = Decoder::default();
+    for part in parts {
+        decoder.receive(part.as_ref())?;
+    }
+    decoder.message()
+}
+
+/// Collect the UR parts as they are scanned, until the message is complete
+///
+/// Multi-part URs are decoded with the fountain decoder of the [`ur`] crate, so that the message
+/// is complete even if some of the fragments are missed, when the fountain parts combining them
+/// are received.
+#[derive(Default)]
+pub struct Decoder {
+    ur_type: Option<String>,
+    /// The fragment count of multi-part URs
+    count: Option<u64>,
+    /// The sequence numbers of the multi-part URs received
+    received: BTreeSet<u64>,
+    fountain: ur::Decoder,
+    message: Option<Vec<u8>>,
+}
+
+impl Decoder {
+    /// Add a scanned part
+    ///
+    /// Parts already received and parts received once the message is complete are ignored,
+    /// parts of a different UR are refused.
+    pub fn receive(&mut self, part: &str) -> Result<(), Error> {
+        let part = part.trim().to_lowercase();
+        let rest = part
+            .strip_prefix("ur:")
+            .ok_or_else(|| Error::InvalidUr("missing ur: prefix".into()))?;
+        let elements: Vec<&str> = rest.split('/').collect();
+        let (ur_type, seq) = match elements[..] {
+            [ur_type, _] => (ur_type, None),
+            [ur_type, seq, _] => (ur_type, Some(seq)),
+            _ => return Err(Error::InvalidUr(format!("unexpected part {part}"))),
+        };
+        if !is_valid_type(ur_type) {
+            return Err(Error::InvalidUr(format!("invalid type {ur_type}")));
+        }
+        match &self.ur_type {
+            Some(t) if t != ur_type => {
+                return Err(Error::InvalidUr(format!(
+                    "part of type {ur_type} while decoding {t}"
+                )))
+            }
+            _ => self.ur_type = Some(ur_type.to_string()),
+        }
+
+        let seq = match seq {
+            None => {
+                if self.count.is_some() {
+                    return Err(Error::InvalidUr("single part while decoding parts".into()));
+                }
+                let (_, message) = ur::ur::decode(&part).map_err(invalid_ur)?;
+                self.message = Some(message);
+                return Ok(());
+            }
+            Some(seq) => seq,
+        };
+        let (seq_num, count) = seq
+            .split_once('-')
+            .and_then(|(n, c)| Some((n.parse::<u64>().ok()?, c.parse::<u64>().ok()?)))
+            .filter(|(n, c)| *n > 0 && *c > 0)
+            .ok_or_else(|| Error::InvalidUr(format!("invalid sequence {seq}")))?;
+        match self.count {
+            Some(c) if c != count => {
+                return Err(Error::InvalidUr("part of a different message".into()))
+            }
+            _ => self.count = Some(count),
+        }
+        if self.is_complete() {
+            return Ok(());
+        }
+        self.fountain.receive(&part).map_err(invalid_ur)?;
+        self.received.insert(seq_num);
+        if self.fountain.complete() {
+            self.message = self.fountain.message().map_err(invalid_ur)?;
+        }
+        Ok(())
+    }
+
+    /// Whether all the parts have been received
+    pub fn is_complete(&self) -> bool {
+        self.message.is_some()
+    }
+
+    /// The number of parts received and the number of fragments of the message, if known
+    ///
+    /// Since fountain parts combine fragments, the message may be complete before or after
+    /// receiving as many parts as the fragments.
+    pub fn progress(&self) -> (usize, Option<usize>) {
+        match (self.count, &self.message) {
+            (None, Some(_)) => (1, Some(1)),
+            (count, _) => (self.received.len(), count.map(|c| c as usize)),
+        }
+    }
+
+    /// The UR type and the CBOR message, once all the parts have been received
+    pub fn message(&self) -> Result<(String, Vec<u8>), Error> {
+        match (&self.ur_type, &self.message) {
+            (Some(ur_type), Some(message)) => Ok((ur_type.clone(), message.clone())),
+            _ => {
+                let (received, count) = self.progress();
+                let count = count.map(|c| c.to_string()).unwrap_or("?".into());
+                Err(Error::InvalidUr(format!(
+                    "incomplete, received {received} parts of {count} fragments"
+                )))
+            }
+        }
+    }
+}
+
+fn invalid_ur(e: impl std::fmt::Display) -> Error {
+    Error::InvalidUr(e.to_string())
+}
+
+fn is_valid_type(ur_type: &str) -> bool {
+    !ur_type.is_empty()
+        && ur_type
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend([major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend((value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend((value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend(value.to_be_bytes());
+        }
+    }
+}
+
+fn cbor_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    cbor_head(out, 2, bytes.len() as u64);
+    out.extend(bytes);
+}
+
+/// Read the major type and the value of a CBOR data item head
+fn cbor_read_head(data: &mut &[u8]) -> Result<(u8, u64), Error> {
+    let err = || Error::InvalidUr("invalid CBOR".into());
+    let (first, rest) = data.split_first().ok_or_else(err)?;
+    let len = match first & 0x1f {
+        0..=23 => 0,
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return Err(err()),
+    };
+    if rest.len() < len {
+        return Err(err());
+    }
+    let value = match len {
+        0 => (first & 0x1f) as u64,
+        _ => rest[..len]
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | *b as u64),
+    };
+    *data = &rest[len..];
+    Ok((first >> 5, value))
+}
+
+fn cbor_read_bytes<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    match cbor_read_head(data)? {
+        (2, len) if len as usize <= data.len() => {
+            let (bytes, rest) = data.split_at(len as usize);
+            *data = rest;
+            Ok(bytes)
+        }
+        _ => Err(Error::InvalidUr("expected CBOR byte string".into())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bytewords() {
+        // test vector from the bytewords specification
+        let data = vec![0, 1, 2, 128, 255];
+        let decoded = decode(&["ur:bytes/aeadaolazmjendeoti"]).unwrap();
+        assert_eq!(decoded, ("bytes".to_string(), data));
+        assert!(decode(&["ur:bytes/aeadaolazmjendeota"]).is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut message = vec![];
+        cbor_bytes(&mut message, &[0xaa; 20]);
+
+        let parts = encode("bytes", &message, 100);
+        assert_eq!(
+            decode(&parts).unwrap(),
+            ("bytes".to_string(), message.clone())
+        );
+
+        let mut parts = encode("bytes", &message, 10);
+        parts.reverse();
+        let mut decoder = Decoder::default();
+        decoder.receive(&parts[0]).unwrap();
+        decoder.receive(&parts[0]).unwrap();
+        assert_eq!(decoder.progress(), (1, Some(3)));
+        assert!(decoder.message().is_err());
+        decoder.receive(&parts[1].to_uppercase()).unwrap();
+        decoder.receive(&parts[2]).unwrap();
+        assert!(decoder.is_complete());
+        assert_eq!(decoder.message().unwrap().1, message);
+
+        let other = encode("other", &message, 10);
+        let mut decoder = Decoder::default();
+        decoder.receive(&parts[0]).unwrap();
+        assert!(decoder.receive(&other[1]).is_err());
+        assert!(decoder.receive("ur:bytes").is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut message = vec![];
+        cbor_bytes(&mut message, &[0xaa; 20]);
+
+        let parts = encode("bytes", &message, 100);
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].starts_with("ur:bytes/ghpkpkpkpkpk"));
+
+        let parts = encode("bytes", &message, 10);
+        assert_eq!(parts.len(), 3);
+        for (i, part) in parts.iter().enumerate() {
+            assert!(part.starts_with(&format!("ur:bytes/{}-3/", i + 1)));
+        }
+    }
+
+    #[test]
+    fn test_vectors() {
+        // test vectors from the BC-UR reference implementation, a 50 and a 256 bytes message
+        // generated with the "Wolf" seed, the latter split in fragments of at most 30 bytes
+        let single = "ur:bytes/hdeymejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtgwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsdwkbrkch";
+        let (ur_type, message) = decode(&[single]).unwrap();
+        assert_eq!(ur_type, "bytes");
+        assert_eq!(message.len(), 52);
+        assert_eq!(encode("bytes", &message, 100), vec![single]);
+
+        let parts = [
+            "ur:bytes/1-9/lpadascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtdkgslpgh",
+            "ur:bytes/2-9/lpaoascfadaxcywenbpljkhdcagwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsgmghhkhstlrdcxaefz",
+            "ur:bytes/3-9/lpaxascfadaxcywenbpljkhdcahelbknlkuejnbadmssfhfrdpsbiegecpasvssovlgeykssjykklronvsjksopdzmol",
+            "ur:bytes/4-9/lpaaascfadaxcywenbpljkhdcasotkhemthydawydtaxneurlkosgwcekonertkbrlwmplssjtammdplolsbrdzcrtas",
+            "ur:bytes/5-9/lpahascfadaxcywenbpljkhdcatbbdfmssrkzmcwnezelennjpfzbgmuktrhtejscktelgfpdlrkfyfwdajldejokbwf",
+            "ur:bytes/6-9/lpamascfadaxcywenbpljkhdcackjlhkhybssklbwefectpfnbbectrljectpavyrolkzczcpkmwidmwoxkilghdsowp",
+            "ur:bytes/7-9/lpatascfadaxcywenbpljkhdcavszmwnjkwtclrtvaynhpahrtoxmwvwatmedibkaegdosftvandiodagdhthtrlnnhy",
+            "ur:bytes/8-9/lpayascfadaxcywenbpljkhdcadmsponkkbbhgsoltjntegepmttmoonftnbuoiyrehfrtsabzsttorodklubbuyaetk",
+            "ur:bytes/9-9/lpasascfadaxcywenbpljkhdcajskecpmdckihdyhphfotjojtfmlnwmadspaxrkytbztpbauotbgtgtaeaevtgavtny",
+        ];
+        let (ur_type, message) = decode(&parts).unwrap();
+        assert_eq!(ur_type, "bytes");
+        assert_eq!(message.len(), 259);
+        let mut data = &message[..];
+        assert_eq!(cbor_read_bytes(&mut data).unwrap().len(), 256);
+        assert_eq!(encode("bytes", &message, 30), parts);
+
+        // the fountain parts following the fragments, the 11th is the 3rd fragment and the others
+        // combine more fragments
+        let fountain = [
+            "ur:bytes/10-9/lpbkascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtwdkiplzs",
+            "ur:bytes/11-9/lpbdascfadaxcywenbpljkhdcahelbknlkuejnbadmssfhfrdpsbiegecpasvssovlgeykssjykklronvsjkvetiiapk",
+            "ur:bytes/12-9/lpbnascfadaxcywenbpljkhdcarllaluzmdmgstospeyiefmwejlwtpedamktksrvlcygmzemovovllarodtmtbnptrs",
+            "ur:bytes/13-9/lpbtascfadaxcywenbpljkhdcamtkgtpknghchchyketwsvwgwfdhpgmgtylctotzopdrpayoschcmhplffziachrfgd",
+            "ur:bytes/14-9/lpbaascfadaxcywenbpljkhdcapazewnvonnvdnsbyleynwtnsjkjndeoldydkbkdslgjkbbkortbelomueekgvstegt",
+            "ur:bytes/15-9/lpbsascfadaxcywenbpljkhdcaynmhpddpzmversbdqdfyrehnqzlugmjzmnmtwmrouohtstgsbsahpawkditkckynwt",
+            "ur:bytes/16-9/lpbeascfadaxcywenbpljkhdcawygekobamwtlihsnpalnsghenskkiynthdzotsimtojetprsttmukirlrsbtamjtpd",
+            "ur:bytes/17-9/lpbyascfadaxcywenbpljkhdcamklgftaxykpewyrtqzhydntpnytyisincxmhtbceaykolduortotiaiaiafhiaoyce",
+            "ur:bytes/18-9/lpbgascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtntwkbkwy",
+            "ur:bytes/19-9/lpbwascfadaxcywenbpljkhdcadekicpaajootjzpsdrbalpeywllbdsnbinaerkurspbncxgslgftvtsrjtksplcpeo",
+            "ur:bytes/20-9/lpbbascfadaxcywenbpljkhdcayapmrleeleaxpasfrtrdkncffwjyjzgyetdmlewtkpktgllepfrltataztksmhkbot",
+        ];
+
+        // the 3rd fragment is missed, it's recovered from the fountain parts
+        let mut decoder = Decoder::default();
+        for part in parts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 2)
+            .map(|(_, p)| p)
+        {
+            decoder.receive(part).unwrap();
+        }
+        assert!(!decoder.is_complete());
+        assert_eq!(decoder.progress(), (8, Some(9)));
+        for part in fountain {
+            decoder.receive(part).unwrap();
+        }
+        assert!(decoder.is_complete());
+        assert_eq!(decoder.message().unwrap().1, message);
+
+        // none of the fragments are received, the message is decoded from the fountain parts
+        let mut encoder = ur::Encoder::bytes(&message, 30).unwrap();
+        let mut decoder = Decoder::default();
+        for _ in 0..parts.len() {
+            encoder.next_part().unwrap();
+        }
+        while !decoder.is_complete() {
+            assert!(encoder.current_index() < 100);
+            decoder.receive(&encoder.next_part().unwrap()).unwrap();
+        }
+        assert_eq!(decoder.message().unwrap().1, message);
+    }
+
+    #[test]
+    fn test_pset_to_ur() {
+        let pset: PartiallySignedTransaction =
+            include_str!("../test_data/pset_details/pset.base64")
+                .trim()
+                .parse()
+                .unwrap();
+        let parts = pset_to_ur(&pset, 200);
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|p| p.starts_with("ur:crypto-psbt/")));
+        assert_eq!(pset_from_ur(&parts).unwrap(), pset);
+    }
+}
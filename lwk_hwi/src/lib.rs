@@ -1,29 +1,290 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(test), deny(clippy::unwrap_used))]
 
-// from:
+// Commands and output format from:
+// https://github.com/bitcoin-core/HWI/blob/70ffb2be827e5b3d304203b4ded1f79c07b04a5f/hwilib/commands.py
 // https://github.com/bitcoin-core/HWI/blob/70ffb2be827e5b3d304203b4ded1f79c07b04a5f/hwilib/hwwclient.py
-// https://github.com/bitcoin-core/HWI/blob/70ffb2be827e5b3d304203b4ded1f79c07b04a5f/test/test_jade.py
-
-// enumerate
-
-// supported by jade
-
-// get_master_xpub(self, addrtype: AddressType = AddressType.WIT, account: int = 0) -> ExtendedKey:
-// get_master_fingerprint(self) -> bytes
-// get_pubkey_at_path(self, bip32_path: str) -> ExtendedKey
-// sign_tx(self, psbt: PSBT) -> PSBT
-// sign_message(self, message: Union[str, bytes], bip32_path: str) -> str
-// display_singlesig_address(self, bip32_path: str, addr_type: AddressType) -> str
-// display_multisig_address(self, addr_type: AddressType, multisig: MultisigDescriptor,) -> str
-// close(self) -> None
-// can_sign_taproot(self) -> bool (false)
-
-// unsupported by jade
-
-// wipe_device(self) -> bool
-// setup_device(self, label: str = "", passphrase: str = "") -> bool
-// restore_device(self, label: str = "", word_count: int = 24) -> bool
-// backup_device(self, label: str = "", passphrase: str = "") -> bool
-// prompt_pin(self) -> bool
-// send_pin(self, pin: str) -> bool
-// toggle_passphrase(self) -> bool
+
+use std::process::Command;
+use std::str::FromStr;
+
+use elements_miniscript::elements::bitcoin::bip32::{DerivationPath, Fingerprint, Xpub};
+use elements_miniscript::elements::pset::PartiallySignedTransaction;
+use elements_miniscript::slip77::MasterBlindingKey;
+use lwk_common::{Network, Signer};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// The default name of the HWI executable
+pub const HWI_BINARY: &str = "hwi";
+
+/// Possible errors when using [`Hwi`]
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    PsetParse(#[from] elements_miniscript::elements::pset::ParseError),
+
+    #[error("HWI error {code}: {message}")]
+    Hwi { code: i64, message: String },
+
+    #[error("HWI exited with {status}: {stderr}")]
+    Exit { status: String, stderr: String },
+
+    #[error("Operation not supported by HWI: {0}")]
+    Unsupported(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A device found by [`Hwi::enumerate()`]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeviceInfo {
+    /// The device type, for instance "trezor" or "jade"
+    #[serde(rename = "type")]
+    pub device_type: String,
+
+    pub model: String,
+
+    /// The path to reach the device
+    pub path: String,
+
+    /// The master fingerprint, missing if the device is locked
+    #[serde(default)]
+    pub fingerprint: Option<Fingerprint>,
+
+    #[serde(default)]
+    pub needs_pin_sent: bool,
+
+    #[serde(default)]
+    pub needs_passphrase_sent: bool,
+
+    /// Why the device cannot be used, if so
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HwiError {
+    error: String,
+    #[serde(default)]
+    code: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct XpubResult {
+    xpub: Xpub,
+}
+
+#[derive(Deserialize)]
+struct SignTxResult {
+    psbt: String,
+}
+
+/// A hardware wallet used through an HWI-compatible executable
+///
+/// Every operation runs the executable, selecting the device by its master fingerprint.
+#[derive(Debug, Clone)]
+pub struct Hwi {
+    binary: String,
+    fingerprint: Fingerprint,
+    network: Network,
+}
+
+impl Hwi {
+    /// Use the device with the given master `fingerprint` through the `hwi` executable in `PATH`
+    pub fn new(fingerprint: Fingerprint, network: Network) -> Self {
+        Self::with_binary(HWI_BINARY, fingerprint, network)
+    }
+
+    /// Use the device with the given master `fingerprint` through the executable `binary`
+    pub fn with_binary(binary: &str, fingerprint: Fingerprint, network: Network) -> Self {
+        Self {
+            binary: binary.to_string(),
+            fingerprint,
+            network,
+        }
+    }
+
+    /// List the devices visible to the executable `binary`
+    pub fn enumerate(binary: &str) -> Result<Vec<DeviceInfo>> {
+        run(binary, &["enumerate".to_string()])
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Get the xpub at the given path, path can contain hardened derivations
+    pub fn get_xpub(&self, path: &DerivationPath) -> Result<Xpub> {
+        let result: XpubResult = self.run(&["getxpub".to_string(), path_arg(path)])?;
+        Ok(result.xpub)
+    }
+
+    /// Let the device sign `pset`, returning the PSET including the signatures
+    pub fn sign_pset(
+        &self,
+        pset: &PartiallySignedTransaction,
+    ) -> Result<PartiallySignedTransaction> {
+        let result: SignTxResult = self.run(&["signtx".to_string(), pset.to_string()])?;
+        Ok(PartiallySignedTransaction::from_str(&result.psbt)?)
+    }
+
+    fn run<T: DeserializeOwned>(&self, command: &[String]) -> Result<T> {
+        let mut args = self.global_args();
+        args.extend(command.iter().cloned());
+        run(&self.binary, &args)
+    }
+
+    fn global_args(&self) -> Vec<String> {
+        let chain = match self.network {
+            Network::Liquid => "main",
+            Network::TestnetLiquid => "test",
+            Network::LocaltestLiquid => "regtest",
+        };
+        vec![
+            "--fingerprint".to_string(),
+            self.fingerprint.to_string(),
+            "--chain".to_string(),
+            chain.to_string(),
+        ]
+    }
+}
+
+/// HWI expects paths starting with "m/"
+fn path_arg(path: &DerivationPath) -> String {
+    if path.is_empty() {
+        "m".to_string()
+    } else {
+        format!("m/{path}")
+    }
+}
+
+fn run<T: DeserializeOwned>(binary: &str, args: &[String]) -> Result<T> {
+    log::debug!("running {binary} {}", args.join(" "));
+    let output = Command::new(binary).args(args).output()?;
+    if output.stdout.is_empty() && !output.status.success() {
+        return Err(Error::Exit {
+            status: output.status.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+    parse_output(&output.stdout)
+}
+
+/// HWI prints JSON also on failures, with an "error" and a "code" fields
+fn parse_output<T: DeserializeOwned>(stdout: &[u8]) -> Result<T> {
+    let value: serde_json::Value = serde_json::from_slice(stdout)?;
+    if let Ok(e) = serde_json::from_value::<HwiError>(value.clone()) {
+        return Err(Error::Hwi {
+            code: e.code,
+            message: e.error,
+        });
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+fn signatures(pset: &PartiallySignedTransaction) -> usize {
+    pset.inputs().iter().map(|i| i.partial_sigs.len()).sum()
+}
+
+impl Signer for &Hwi {
+    type Error = Error;
+
+    fn sign(&self, pset: &mut PartiallySignedTransaction) -> Result<u32> {
+        let before = signatures(pset);
+        let signed = self.sign_pset(pset)?;
+        let added = signatures(&signed).saturating_sub(before);
+        *pset = signed;
+        Ok(added as u32)
+    }
+
+    fn derive_xpub(&self, path: &DerivationPath) -> Result<Xpub> {
+        self.get_xpub(path)
+    }
+
+    fn slip77_master_blinding_key(&self) -> Result<MasterBlindingKey> {
+        Err(Error::Unsupported("slip77 master blinding key".to_string()))
+    }
+
+    fn fingerprint(&self) -> Result<Fingerprint> {
+        Ok(self.fingerprint)
+    }
+}
+
+impl Signer for Hwi {
+    type Error = Error;
+
+    fn sign(&self, pset: &mut PartiallySignedTransaction) -> Result<u32> {
+        Signer::sign(&self, pset)
+    }
+
+    fn derive_xpub(&self, path: &DerivationPath) -> Result<Xpub> {
+        Signer::derive_xpub(&self, path)
+    }
+
+    fn slip77_master_blinding_key(&self) -> Result<MasterBlindingKey> {
+        Signer::slip77_master_blinding_key(&self)
+    }
+
+    fn fingerprint(&self) -> Result<Fingerprint> {
+        Signer::fingerprint(&self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let enumerate = r#"[{"type": "trezor", "model": "trezor_t", "label": null, "path": "webusb:000:1:1", "needs_pin_sent": false, "needs_passphrase_sent": false, "fingerprint": "73c5da0a"}, {"type": "jade", "model": "jade", "path": "/dev/ttyACM0", "needs_pin_sent": false, "needs_passphrase_sent": false, "error": "Could not open client or get fingerprint information", "code": -13}]"#;
+        let devices: Vec<DeviceInfo> = parse_output(enumerate.as_bytes()).unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].device_type, "trezor");
+        assert_eq!(devices[0].fingerprint, Some("73c5da0a".parse().unwrap()));
+        assert!(devices[0].error.is_none());
+        assert!(devices[1].fingerprint.is_none());
+        assert!(devices[1].error.is_some());
+
+        let xpub = r#"{"xpub": "tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s"}"#;
+        let result: XpubResult = parse_output(xpub.as_bytes()).unwrap();
+        assert_eq!(result.xpub.fingerprint().to_string(), "73c5da0a");
+
+        let err = r#"{"error": "Could not find device with specified fingerprint", "code": -3}"#;
+        let err = parse_output::<XpubResult>(err.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "HWI error -3: Could not find device with specified fingerprint"
+        );
+
+        assert!(parse_output::<XpubResult>(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_args() {
+        let fingerprint: Fingerprint = "73c5da0a".parse().unwrap();
+        let hwi = Hwi::new(fingerprint, Network::TestnetLiquid);
+        assert_eq!(
+            hwi.global_args(),
+            vec!["--fingerprint", "73c5da0a", "--chain", "test"]
+        );
+        assert_eq!(Signer::fingerprint(&hwi).unwrap(), fingerprint);
+
+        assert_eq!(path_arg(&DerivationPath::master()), "m");
+        let path: DerivationPath = "m/84h/1h/0h".parse().unwrap();
+        assert_eq!(path_arg(&path), "m/84'/1'/0'");
+
+        let missing = Hwi::with_binary("lwk-hwi-missing-binary", fingerprint, Network::Liquid);
+        assert!(matches!(missing.get_xpub(&path).unwrap_err(), Error::Io(_)));
+        assert!(matches!(
+            Signer::slip77_master_blinding_key(&missing).unwrap_err(),
+            Error::Unsupported(_)
+        ));
+    }
+}
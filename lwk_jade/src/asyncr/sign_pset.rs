@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use elements::pset::PartiallySignedTransaction;
+use lwk_common::pset::{sanity_check, Role};
 
 use crate::{
     create_jade_sign_req, derivation_path_to_vec, protocol::GetSignatureParams, script_code_wpkh,
@@ -12,6 +13,7 @@ use super::{Jade, Stream};
 impl<S: Stream> Jade<S> {
     /// Sign a pset from a Jade
     pub async fn sign(&self, pset: &mut PartiallySignedTransaction) -> Result<u32, Error> {
+        sanity_check(pset, Role::Signer)?;
         let my_fingerprint = self.fingerprint().await?;
 
         // Singlesig signing don't need this, however, it is simpler to always ask for it and once cached is a
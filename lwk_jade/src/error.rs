@@ -60,6 +60,12 @@ pub enum Error {
     #[error(transparent)]
     Pset(#[from] elements::pset::Error),
 
+    #[error(transparent)]
+    PsetSanity(#[from] lwk_common::pset::Error),
+
+    #[error(transparent)]
+    Common(#[from] lwk_common::Error),
+
     #[error("Missing asset id in output {0}")]
     MissingAssetIdInOutput(usize),
 
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use elements::bitcoin::bip32::{DerivationPath, Xpub};
+use elements_miniscript::slip77::MasterBlindingKey;
+use lwk_common::Network;
+use serde::{Deserialize, Serialize};
+
+/// The public data of a Jade: xpubs and SLIP77 master blinding key
+///
+/// Once obtained from the device, it can be stored and loaded later, so that computing
+/// descriptors and addresses doesn't require to unlock the device again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JadeIdentity {
+    network: Network,
+
+    #[serde(default)]
+    xpubs: BTreeMap<DerivationPath, Xpub>,
+
+    #[serde(default, with = "slip77_hex")]
+    slip77: Option<MasterBlindingKey>,
+}
+
+impl JadeIdentity {
+    pub fn new(network: Network) -> Self {
+        Self {
+            network,
+            xpubs: BTreeMap::new(),
+            slip77: None,
+        }
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// The xpubs known, keyed by derivation path
+    pub fn xpubs(&self) -> &BTreeMap<DerivationPath, Xpub> {
+        &self.xpubs
+    }
+
+    pub fn insert_xpub(&mut self, path: DerivationPath, xpub: Xpub) {
+        self.xpubs.insert(path, xpub);
+    }
+
+    pub fn slip77(&self) -> Option<MasterBlindingKey> {
+        self.slip77
+    }
+
+    pub fn set_slip77(&mut self, slip77: MasterBlindingKey) {
+        self.slip77 = Some(slip77);
+    }
+
+    /// The name of the file storing the identity of the device with the given id
+    /// (the `efusemac` returned by [`crate::protocol::VersionInfoResult`])
+    pub fn file_name(device_id: &str, network: Network) -> String {
+        format!("jade_{device_id}_{network}.json")
+    }
+}
+
+mod slip77_hex {
+    use std::str::FromStr;
+
+    use elements_miniscript::slip77::MasterBlindingKey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        slip77: &Option<MasterBlindingKey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match slip77 {
+            Some(key) => serializer.collect_str(key),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<MasterBlindingKey>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| MasterBlindingKey::from_str(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_identity_roundtrip() {
+        let mut identity = JadeIdentity::new(Network::TestnetLiquid);
+        let empty: JadeIdentity =
+            serde_json::from_str(&serde_json::to_string(&identity).unwrap()).unwrap();
+        assert_eq!(empty, identity);
+
+        let xpub = Xpub::from_str("tpubD6NzVbkrYhZ4XYa9MoLt4BiMZ4gkt2faZ4BcmKu2a9te4LDpQmvEz2L2yDERivHxFPnxXXhqDRkUNnQCpZggCyEZLBktV7VaSmwayqMJy1s").unwrap();
+        identity.insert_xpub(DerivationPath::master(), xpub);
+        identity.insert_xpub("m/84h/1h/0h".parse().unwrap(), xpub);
+        identity.set_slip77(MasterBlindingKey::from([7u8; 32]));
+
+        let s = serde_json::to_string(&identity).unwrap();
+        let back: JadeIdentity = serde_json::from_str(&s).unwrap();
+        assert_eq!(back, identity);
+        assert_eq!(back.slip77(), Some(MasterBlindingKey::from([7u8; 32])));
+        assert_eq!(back.xpubs().len(), 2);
+
+        assert_eq!(
+            JadeIdentity::file_name("aabbcc", Network::Liquid),
+            "jade_aabbcc_liquid.json"
+        );
+    }
+}
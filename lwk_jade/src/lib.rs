@@ -8,7 +8,9 @@ pub mod asyncr;
 pub mod consts;
 pub mod error;
 pub mod get_receive_address;
+pub mod identity;
 pub mod protocol;
+pub mod qr;
 pub mod register_multisig;
 pub mod sign_liquid_tx;
 
@@ -32,7 +34,7 @@ use elements::{
         all::{OP_CHECKMULTISIG, OP_PUSHNUM_1, OP_PUSHNUM_16},
         All,
     },
-    pset::PartiallySignedTransaction,
+    pset::{Output, PartiallySignedTransaction},
     script::Instruction,
     Script,
 };
@@ -67,6 +69,7 @@ pub const JADE_DEVICE_IDS: [(u16, u16); 6] = [
     (0x303a, 0x1001),
 ];
 
+const RECEIVE_CHAIN: ChildNumber = ChildNumber::Normal { index: 0 };
 const CHANGE_CHAIN: ChildNumber = ChildNumber::Normal { index: 1 };
 
 fn try_parse_response<T>(reader: &[u8]) -> Option<Result<T>>
@@ -167,75 +170,7 @@ fn create_jade_sign_req(
         };
         trusted_commitments.push(trusted_commitment);
 
-        let mut change = None;
-        for (fingerprint, path) in output.bip32_derivation.values() {
-            if fingerprint == &my_fingerprint {
-                // This ensures that path has at least 2 elements
-                let is_change = path.clone().into_iter().nth_back(1) == Some(&CHANGE_CHAIN);
-                if is_change {
-                    if output.script_pubkey.is_v0_p2wpkh() {
-                        change = Some(Change {
-                            address: SingleOrMulti::Single {
-                                variant: Variant::Wpkh,
-                                path: derivation_path_to_vec(path),
-                            },
-                            is_change,
-                        });
-                    } else if output.script_pubkey.is_p2sh() {
-                        if let Some(redeem_script) = output.redeem_script.as_ref() {
-                            if redeem_script.is_v0_p2wpkh() {
-                                change = Some(Change {
-                                    address: SingleOrMulti::Single {
-                                        variant: Variant::ShWpkh,
-                                        path: derivation_path_to_vec(path),
-                                    },
-                                    is_change,
-                                });
-                            }
-                        }
-                    } else if output.script_pubkey.is_v0_p2wsh() {
-                        if let Some(witness_script) = output.witness_script.as_ref() {
-                            if is_multisig(witness_script) {
-                                for details in &multisigs_details {
-                                    // path has at least 2 elements
-                                    let index = path[path.len() - 1];
-                                    if let Ok(derived_witness_script) = details
-                                        .descriptor
-                                        .derive_witness_script(is_change, index.into())
-                                    {
-                                        if witness_script == &derived_witness_script {
-                                            let mut paths = vec![];
-                                            for _ in 0..details.descriptor.signers.len() {
-                                                // FIXME: here we should only pass the paths that were
-                                                // not passed when calling register_multisig. However
-                                                // deducing them now is not trivial, thus we only take
-                                                // the last 2 elements in the derivation path which we
-                                                // expect to be "0|1,*"
-                                                let v = derivation_path_to_vec(path);
-                                                // path has at least 2 elements
-                                                let v = v[(path.len() - 2)..].to_vec();
-                                                paths.push(v);
-                                            }
-                                            change = Some(Change {
-                                                address: SingleOrMulti::Multi {
-                                                    multisig_name: details
-                                                        .multisig_name
-                                                        .to_string(),
-                                                    paths,
-                                                },
-                                                is_change,
-                                            });
-                                            break; // No need to check for more multisigs
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        changes.push(change);
+        changes.push(wallet_output(output, my_fingerprint, &multisigs_details));
     }
     let mut assets_info = vec![];
     for asset_id in asset_ids_in_tx {
@@ -278,6 +213,86 @@ fn script_code_wpkh(script: &Script) -> Script {
     Script::from(script_code)
 }
 
+/// Returns the information needed by Jade to verify that `output` belongs to the wallet
+///
+/// Outputs on the change chain are not displayed by Jade, outputs on the receive chain are shown
+/// as verified addresses of the wallet instead of as spends.
+/// Multisig outputs are recognized only if the wallet is registered on Jade.
+fn wallet_output(
+    output: &Output,
+    my_fingerprint: Fingerprint,
+    multisigs_details: &[RegisteredMultisigDetails],
+) -> Option<Change> {
+    for (fingerprint, path) in output.bip32_derivation.values() {
+        if fingerprint != &my_fingerprint {
+            continue;
+        }
+        // This ensures that path has at least 2 elements
+        let is_change = match path.clone().into_iter().nth_back(1) {
+            Some(&CHANGE_CHAIN) => true,
+            Some(&RECEIVE_CHAIN) => false,
+            _ => continue,
+        };
+        if output.script_pubkey.is_v0_p2wpkh() {
+            return Some(Change {
+                address: SingleOrMulti::Single {
+                    variant: Variant::Wpkh,
+                    path: derivation_path_to_vec(path),
+                },
+                is_change,
+            });
+        } else if output.script_pubkey.is_p2sh() {
+            if let Some(redeem_script) = output.redeem_script.as_ref() {
+                if redeem_script.is_v0_p2wpkh() {
+                    return Some(Change {
+                        address: SingleOrMulti::Single {
+                            variant: Variant::ShWpkh,
+                            path: derivation_path_to_vec(path),
+                        },
+                        is_change,
+                    });
+                }
+            }
+        } else if output.script_pubkey.is_v0_p2wsh() {
+            if let Some(witness_script) = output.witness_script.as_ref() {
+                if is_multisig(witness_script) {
+                    for details in multisigs_details {
+                        // path has at least 2 elements
+                        let index = path[path.len() - 1];
+                        if let Ok(derived_witness_script) = details
+                            .descriptor
+                            .derive_witness_script(is_change, index.into())
+                        {
+                            if witness_script == &derived_witness_script {
+                                let mut paths = vec![];
+                                for _ in 0..details.descriptor.signers.len() {
+                                    // FIXME: here we should only pass the paths that were
+                                    // not passed when calling register_multisig. However
+                                    // deducing them now is not trivial, thus we only take
+                                    // the last 2 elements in the derivation path which we
+                                    // expect to be "0|1,*"
+                                    let v = derivation_path_to_vec(path);
+                                    // path has at least 2 elements
+                                    let v = v[(path.len() - 2)..].to_vec();
+                                    paths.push(v);
+                                }
+                                return Some(Change {
+                                    address: SingleOrMulti::Multi {
+                                        multisig_name: details.multisig_name.to_string(),
+                                        paths,
+                                    },
+                                    is_change,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
 // taken and adapted from:
 // https://github.com/rust-bitcoin/rust-bitcoin/blob/37daf4620c71dc9332c3e08885cf9de696204bca/bitcoin/src/blockdata/script/borrowed.rs#L266
 // TODO remove once it's released
@@ -341,9 +356,14 @@ fn is_multisig(script: &Script) -> bool {
 mod test {
     use std::str::FromStr;
 
+    use elements::bitcoin::bip32::{DerivationPath, Fingerprint};
+    use elements::bitcoin::PublicKey;
+    use elements::pset::Output;
     use elements::Script;
 
-    use crate::{is_multisig, json_to_cbor};
+    use crate::get_receive_address::{SingleOrMulti, Variant};
+    use crate::sign_liquid_tx::Change;
+    use crate::{is_multisig, json_to_cbor, wallet_output};
 
     fn cbor_to_json(value: serde_cbor::Value) -> Result<serde_json::Value, crate::Error> {
         Ok(serde_json::to_value(value)?)
@@ -371,4 +391,52 @@ mod test {
         );
         assert!(!is_multisig(&not_multisig));
     }
+
+    #[test]
+    fn test_wallet_output() {
+        let fingerprint = Fingerprint::from_str("73c5da0a").unwrap();
+        let other = Fingerprint::from_str("00000000").unwrap();
+        let pk = PublicKey::from_str(
+            "02ebc62c20f1e09e169a88745f60f6dac878c92db5c7ed78c6703d2d0426a01f94",
+        )
+        .unwrap();
+        let output = |f: Fingerprint, path: &str| {
+            let mut output = Output {
+                script_pubkey: Script::from_str("001414fe45f2c2a2b7c00d0940d694a3b6af6c9bf165")
+                    .unwrap(),
+                ..Default::default()
+            };
+            let path = DerivationPath::from_str(path).unwrap();
+            output.bip32_derivation.insert(pk, (f, path));
+            output
+        };
+        let single = |path: Vec<u32>, is_change| {
+            Some(Change {
+                address: SingleOrMulti::Single {
+                    variant: Variant::Wpkh,
+                    path,
+                },
+                is_change,
+            })
+        };
+        let h = 1 << 31;
+
+        let change = output(fingerprint, "m/84h/1h/0h/1/3");
+        assert_eq!(
+            wallet_output(&change, fingerprint, &[]),
+            single(vec![84 + h, 1 + h, h, 1, 3], true)
+        );
+
+        let receive = output(fingerprint, "m/84h/1h/0h/0/7");
+        assert_eq!(
+            wallet_output(&receive, fingerprint, &[]),
+            single(vec![84 + h, 1 + h, h, 0, 7], false)
+        );
+
+        assert_eq!(wallet_output(&receive, other, &[]), None);
+        let other_chain = output(fingerprint, "m/84h/1h/0h/2/7");
+        assert_eq!(wallet_output(&other_chain, fingerprint, &[]), None);
+        let no_derivation = Output::default();
+        assert_eq!(wallet_output(&no_derivation, fingerprint, &[]), None);
+    }
 }
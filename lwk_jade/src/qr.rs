@@ -0,0 +1,172 @@
+//! Unlock a Jade in QR mode, without connecting it by cable
+//!
+//! In QR mode the Jade shows its request for the PIN server as `jade-pin` UR parts. The companion
+//! scans them with [`QrPinRequest::from_ur()`], forwards the request to the PIN server and shows
+//! the reply, encoded with [`QrPinRequest::reply_to_ur()`], for the Jade to scan. The exchange is
+//! repeated for every step of the PIN server handshake, until the Jade is unlocked.
+//!
+//! PSETs are exchanged with the `crypto-psbt` UR parts of [`lwk_common::ur`].
+
+use lwk_common::ur;
+use serde::Serialize;
+
+use crate::protocol::{AuthResult, Response};
+use crate::{json_to_cbor, Error, Result};
+
+/// UR type of the messages exchanged with the Jade to unlock it in QR mode
+pub const JADE_PIN: &str = "jade-pin";
+
+/// A request for the PIN server scanned from the Jade
+#[derive(Debug)]
+pub struct QrPinRequest {
+    id: String,
+    auth: AuthResult,
+}
+
+#[derive(Serialize)]
+struct QrPinReply<'a> {
+    id: &'a str,
+    method: &'a str,
+    params: serde_cbor::Value,
+}
+
+impl QrPinRequest {
+    /// Decode the `jade-pin` UR parts shown by the Jade, in any order
+    pub fn from_ur<S: AsRef<str>>(parts: &[S]) -> Result<Self> {
+        let (ur_type, message) = ur::decode(parts)?;
+        if ur_type != JADE_PIN {
+            return Err(Error::Generic(format!(
+                "Expected UR of type {JADE_PIN}, found {ur_type}"
+            )));
+        }
+        let response: Response<AuthResult> = serde_cbor::from_slice(&message)?;
+        match (response.result, response.error) {
+            (Some(auth), _) => Ok(Self {
+                id: response.id,
+                auth,
+            }),
+            (None, Some(error)) => Err(Error::JadeError(error)),
+            (None, None) => Err(Error::JadeNeitherErrorNorResult),
+        }
+    }
+
+    /// The request to forward to the PIN server
+    pub fn auth(&self) -> &AuthResult {
+        &self.auth
+    }
+
+    /// Encode the reply of the PIN server as `jade-pin` UR parts for the Jade to scan
+    pub fn reply_to_ur(
+        &self,
+        pin_server_reply: &serde_json::Value,
+        max_fragment_len: usize,
+    ) -> Result<Vec<String>> {
+        let reply = QrPinReply {
+            id: &self.id,
+            method: self.auth.on_reply(),
+            params: json_to_cbor(pin_server_reply)?,
+        };
+        let message = serde_cbor::to_vec(&reply)?;
+        Ok(ur::encode(JADE_PIN, &message, max_fragment_len))
+    }
+
+    /// Forward the request to the PIN server and encode its reply as `jade-pin` UR parts
+    #[cfg(feature = "sync")]
+    pub fn forward_blocking(&self, max_fragment_len: usize) -> Result<Vec<String>> {
+        let client = reqwest::blocking::Client::new();
+        let pin_server_reply = crate::sync::pin_server_post(&client, &self.auth)?;
+        self.reply_to_ur(&pin_server_reply, max_fragment_len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_qr_pin_request() {
+        let request = serde_json::json!({
+            "id": "qrauth",
+            "result": {
+                "http_request": {
+                    "params": {
+                        "urls": ["https://j8d.io/start_handshake"],
+                        "method": "POST",
+                        "accept": "json",
+                        "data": {"cke": "aa", "encrypted_data": "bb"},
+                    },
+                    "on-reply": "handshake_init",
+                }
+            }
+        });
+        let message = serde_cbor::to_vec(&request).unwrap();
+        let parts = ur::encode(JADE_PIN, &message, 30);
+        assert!(parts.len() > 1);
+
+        let request = QrPinRequest::from_ur(&parts).unwrap();
+        assert_eq!(
+            request.auth().url(false),
+            Some("https://j8d.io/start_handshake")
+        );
+        assert_eq!(request.auth().on_reply(), "handshake_init");
+
+        let reply = serde_json::json!({"ske": "cc", "sig": "dd"});
+        let parts = request.reply_to_ur(&reply, 500).unwrap();
+        let (ur_type, message) = ur::decode(&parts).unwrap();
+        assert_eq!(ur_type, JADE_PIN);
+        let decoded: serde_json::Value = serde_cbor::from_slice(&message).unwrap();
+        assert_eq!(
+            decoded,
+            serde_json::json!({"id": "qrauth", "method": "handshake_init", "params": reply})
+        );
+
+        let psbt = ur::encode(ur::CRYPTO_PSBT, &message, 500);
+        assert!(QrPinRequest::from_ur(&psbt).is_err());
+    }
+
+    #[test]
+    fn test_qr_pin_request_fountain() {
+        // the Jade shows the fragments followed by the fountain parts, encoded here with the
+        // reference implementation, the first fragment is missed
+        let request = serde_json::json!({
+            "id": "qrauth",
+            "result": {
+                "http_request": {
+                    "params": {
+                        "urls": ["https://j8d.io/get_pin"],
+                        "method": "POST",
+                        "accept": "json",
+                        "data": {"cke": "aa", "encrypted_data": "bb", "hmac": "cc"},
+                    },
+                    "on-reply": "pin",
+                }
+            }
+        });
+        let message = serde_cbor::to_vec(&request).unwrap();
+        let mut encoder = ::ur::Encoder::new(&message, 20, JADE_PIN).unwrap();
+        let count = encoder.fragment_count();
+        let parts: Vec<String> = (0..3 * count)
+            .map(|_| encoder.next_part().unwrap())
+            .skip(1)
+            .collect();
+        assert!(QrPinRequest::from_ur(&parts[..count - 1]).is_err());
+        let request = QrPinRequest::from_ur(&parts).unwrap();
+        assert_eq!(request.auth().url(false), Some("https://j8d.io/get_pin"));
+        assert_eq!(request.auth().on_reply(), "pin");
+
+        // the reply is decoded by the reference implementation
+        let reply = serde_json::json!({"encrypted_key": "dd", "hmac": "ee"});
+        let parts = request.reply_to_ur(&reply, 20).unwrap();
+        assert!(parts.iter().all(|p| p.starts_with("ur:jade-pin/")));
+        let mut decoder = ::ur::Decoder::default();
+        for part in parts {
+            decoder.receive(&part).unwrap();
+        }
+        let message = decoder.message().unwrap().unwrap();
+        let decoded: serde_json::Value = serde_cbor::from_slice(&message).unwrap();
+        assert_eq!(
+            decoded,
+            serde_json::json!({"id": "qrauth", "method": "pin", "params": reply})
+        );
+    }
+}
@@ -1,11 +1,13 @@
 use std::collections::BTreeMap;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::{collections::HashMap, io::ErrorKind};
 
 use crate::get_receive_address::{GetReceiveAddressParams, SingleOrMulti, Variant};
+use crate::identity::JadeIdentity;
 use crate::protocol::{
-    AuthUserParams, DebugSetMnemonicParams, EntropyParams, EpochParams, GenericMethod,
+    AuthResult, AuthUserParams, DebugSetMnemonicParams, EntropyParams, EpochParams, GenericMethod,
     GetMasterBlindingKeyParams, GetSignatureParams, GetXpubParams, IsAuthResult, Request,
     SignMessageParams, UpdatePinserverParams, VersionInfoResult,
 };
@@ -31,6 +33,26 @@ mod sign_pset;
 #[cfg(feature = "serial")]
 use crate::consts::{BAUD_RATE, TIMEOUT};
 
+/// Forward the Jade authentication request to the PIN server, returning its reply
+pub(crate) fn pin_server_post(
+    client: &reqwest::blocking::Client,
+    auth: &AuthResult,
+) -> Result<serde_json::Value> {
+    let url = auth.url(false).ok_or(Error::NoUsableUrl)?;
+    let str = serde_json::to_string(auth.data())?;
+    let value: serde_json::Value = serde_json::from_str(&str)?;
+    log::debug!("POSTING to {url} data: {value}",);
+    let resp = client.post(url).json(&value).send()?;
+    let status_code = resp.status().as_u16();
+    if status_code != 200 {
+        return Err(Error::HttpStatus(url.to_string(), status_code));
+    }
+    let bytes = &resp.bytes()?;
+    let value: serde_json::Value = serde_json::from_slice(bytes.as_ref())?;
+    log::debug!("RECEIVED from {url} data: {:?}", value);
+    Ok(value)
+}
+
 #[derive(Debug)]
 pub struct Jade {
     /// Jade working via emulator(tcp), physical(serial/bluetooth)
@@ -44,6 +66,12 @@ pub struct Jade {
 
     /// Cached multisigs details
     multisigs_details: Mutex<Option<Vec<RegisteredMultisigDetails>>>,
+
+    /// Cached slip77 master blinding key
+    cached_slip77: Mutex<Option<MasterBlindingKey>>,
+
+    /// File where the cached xpubs and slip77 master blinding key are persisted
+    identity_file: Option<PathBuf>,
 }
 
 impl Jade {
@@ -53,9 +81,75 @@ impl Jade {
             network,
             cached_xpubs: Mutex::new(HashMap::new()),
             multisigs_details: Mutex::new(None),
+            cached_slip77: Mutex::new(None),
+            identity_file: None,
         }
     }
 
+    /// Persist the xpubs and the slip77 master blinding key obtained from the device in `dir`,
+    /// and load the ones previously stored for this device.
+    ///
+    /// Once cached, deriving xpubs and getting the slip77 master blinding key don't need to
+    /// unlock the device. The file is keyed by the device id and the network.
+    pub fn set_identity_cache(&mut self, dir: &Path) -> Result<()> {
+        let device_id = self.version_info()?.efusemac;
+        let path = dir.join(JadeIdentity::file_name(&device_id, self.network));
+        if path.exists() {
+            let identity: JadeIdentity = serde_json::from_reader(std::fs::File::open(&path)?)?;
+            if identity.network() == self.network {
+                self.cached_xpubs.lock()?.extend(identity.xpubs().clone());
+                if let Some(slip77) = identity.slip77() {
+                    *self.cached_slip77.lock()? = Some(slip77);
+                }
+            }
+        }
+        self.identity_file = Some(path);
+        Ok(())
+    }
+
+    /// The xpubs and slip77 master blinding key obtained from the device so far
+    pub fn identity(&self) -> Result<JadeIdentity> {
+        let mut identity = JadeIdentity::new(self.network);
+        for (path, xpub) in self.cached_xpubs.lock()?.iter() {
+            identity.insert_xpub(path.clone(), *xpub);
+        }
+        if let Some(slip77) = *self.cached_slip77.lock()? {
+            identity.set_slip77(slip77);
+        }
+        Ok(identity)
+    }
+
+    fn persist_identity(&self) -> Result<()> {
+        if let Some(path) = self.identity_file.as_ref() {
+            let identity = serde_json::to_vec(&self.identity()?)?;
+            std::fs::write(path, identity)?;
+        }
+        Ok(())
+    }
+
+    fn cached_xpub(&self, path: &DerivationPath) -> Result<Option<Xpub>> {
+        Ok(self.cached_xpubs.lock()?.get(path).cloned())
+    }
+
+    /// Get the slip77 master blinding key, asking the device only if not cached
+    pub fn get_cached_slip77(&self) -> Result<MasterBlindingKey> {
+        if let Some(slip77) = *self.cached_slip77.lock()? {
+            return Ok(slip77);
+        }
+        let params = GetMasterBlindingKeyParams {
+            only_if_silent: false,
+        };
+        let bytes = self.get_master_blinding_key(params)?;
+        let array: [u8; 32] = bytes
+            .to_vec()
+            .try_into()
+            .map_err(|_| Error::Slip77MasterBlindingKeyInvalidSize)?;
+        let slip77 = MasterBlindingKey::from(array);
+        *self.cached_slip77.lock()? = Some(slip77);
+        self.persist_identity()?;
+        Ok(slip77)
+    }
+
     pub fn generic(&self, method: String, params: serde_cbor::Value) -> Result<serde_cbor::Value> {
         self.send(Request::Generic(GenericMethod { method, params }))
     }
@@ -148,6 +242,8 @@ impl Jade {
         } else {
             let result = self.get_xpub(params)?;
             guard.insert(der_path, result);
+            drop(guard);
+            self.persist_identity()?;
             Ok(result)
         }
     }
@@ -220,19 +316,7 @@ impl Jade {
                 let client = reqwest::blocking::Client::new();
 
                 loop {
-                    let url = result.url(false).ok_or(Error::NoUsableUrl)?;
-                    let str = serde_json::to_string(result.data())?;
-                    let value: serde_json::Value = serde_json::from_str(&str)?;
-                    log::debug!("POSTING to {url} data: {value}",);
-                    let resp = client.post(url).json(&value).send()?;
-                    let status_code = resp.status().as_u16();
-                    if status_code != 200 {
-                        return Err(Error::HttpStatus(url.to_string(), status_code));
-                    }
-                    let bytes = &resp.bytes()?;
-                    let value: serde_json::Value = serde_json::from_slice(bytes.as_ref())?;
-                    log::debug!("RECEIVED from {url} data: {:?}", value);
-
+                    let value = pin_server_post(&client, &result)?;
                     let params: serde_cbor::Value = json_to_cbor(&value)?;
 
                     let method = result.on_reply().to_string();
@@ -395,6 +479,9 @@ impl Signer for &Jade {
         &self,
         path: &elements::bitcoin::bip32::DerivationPath,
     ) -> std::result::Result<Xpub, Self::Error> {
+        if let Some(xpub) = self.cached_xpub(path)? {
+            return Ok(xpub);
+        }
         let params = GetXpubParams {
             network: self.network,
             path: derivation_path_to_vec(path),
@@ -407,15 +494,7 @@ impl Signer for &Jade {
     fn slip77_master_blinding_key(
         &self,
     ) -> std::result::Result<slip77::MasterBlindingKey, Self::Error> {
-        let params = GetMasterBlindingKeyParams {
-            only_if_silent: false,
-        };
-        let bytes = self.get_master_blinding_key(params)?;
-        let array: [u8; 32] = bytes
-            .to_vec()
-            .try_into()
-            .map_err(|_| Self::Error::Slip77MasterBlindingKeyInvalidSize)?;
-        Ok(slip77::MasterBlindingKey::from(array))
+        self.get_cached_slip77()
     }
 }
 
@@ -1,4 +1,5 @@
 use elements::pset::PartiallySignedTransaction;
+use lwk_common::pset::{sanity_check, Role};
 use std::collections::HashMap;
 
 use crate::{
@@ -9,6 +10,7 @@ use crate::{
 impl Jade {
     /// Sign a pset from a Jade
     pub fn sign(&self, pset: &mut PartiallySignedTransaction) -> Result<u32, Error> {
+        sanity_check(pset, Role::Signer)?;
         let my_fingerprint = self.fingerprint()?;
 
         // Singlesig signing don't need this, however, it is simpler to always ask for it and once cached is a
@@ -15,6 +15,7 @@ use lwk_containers::{
 };
 use lwk_jade::{
     get_receive_address::{GetReceiveAddressParams, SingleOrMulti, Variant},
+    identity::JadeIdentity,
     protocol::{
         GetMasterBlindingKeyParams, GetSignatureParams, GetXpubParams, JadeState,
         SignMessageParams, UpdatePinserverParams, VersionInfoResult,
@@ -408,6 +409,39 @@ fn jade_get_master_blinding_key() {
     assert_eq!(hex::encode(result), lwk_test_util::TEST_MNEMONIC_SLIP77);
 }
 
+#[test]
+fn jade_identity_cache() {
+    let docker = clients::Cli::default();
+    let mut jade = TestJadeEmulator::new(&docker);
+    jade.set_debug_mnemonic(TEST_MNEMONIC);
+    let tempdir = tempfile::tempdir().unwrap();
+    jade.jade.set_identity_cache(tempdir.path()).unwrap();
+
+    let path = bitcoin::bip32::DerivationPath::from_str("m/84h/1h/0h").unwrap();
+    let xpub = lwk_common::Signer::derive_xpub(&jade.jade, &path).unwrap();
+    let slip77 = lwk_common::Signer::slip77_master_blinding_key(&jade.jade).unwrap();
+    assert_eq!(slip77.to_string(), lwk_test_util::TEST_MNEMONIC_SLIP77);
+
+    let identity = jade.jade.identity().unwrap();
+    assert_eq!(identity.xpubs().get(&path), Some(&xpub));
+    let device_id = jade.jade.version_info().unwrap().efusemac;
+    let file_name = JadeIdentity::file_name(&device_id, jade.jade.network());
+    let file = std::fs::read_to_string(tempdir.path().join(file_name)).unwrap();
+    let stored: JadeIdentity = serde_json::from_str(&file).unwrap();
+    assert_eq!(stored, identity);
+
+    // Once cached, the device is not asked again, even if locked
+    assert!(jade.jade.logout().unwrap());
+    assert_eq!(
+        lwk_common::Signer::derive_xpub(&jade.jade, &path).unwrap(),
+        xpub
+    );
+    assert_eq!(
+        lwk_common::Signer::slip77_master_blinding_key(&jade.jade).unwrap(),
+        slip77
+    );
+}
+
 #[cfg(feature = "asyncr")]
 #[tokio::test]
 async fn async_ping() {
@@ -81,6 +81,9 @@ impl<T: Transport> Signer for &Ledger<T> {
     type Error = crate::Error;
 
     fn sign(&self, pset: &mut PartiallySignedTransaction) -> std::result::Result<u32, Self::Error> {
+        lwk_common::pset::sanity_check(pset, lwk_common::pset::Role::Signer)
+            .map_err(|e| Error::ClientError(e.to_string()))?;
+
         // Set the default values some fields that Ledger requires
         if pset.global.tx_data.fallback_locktime.is_none() {
             pset.global.tx_data.fallback_locktime =
@@ -0,0 +1,41 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Possible errors of the client
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error of the HTTP client
+    #[error("Reqwest Error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+
+    /// Error converting requests or responses
+    #[error("Serde JSON Error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+
+    /// Error returned by the server
+    #[error("RPC Error: {0}")]
+    Rpc(RpcError),
+
+    /// The response of the server has neither a result nor an error
+    #[error("Neither result nor error are set in the response")]
+    NeitherResultNorErrorSet,
+}
+
+/// An error returned by the server
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RpcError {
+    /// The error code
+    pub code: i64,
+
+    /// The error message
+    pub message: String,
+
+    /// Additional data about the error
+    pub data: Option<Value>,
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
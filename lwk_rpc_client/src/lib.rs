@@ -0,0 +1,392 @@
+#![deny(missing_docs)]
+#![cfg_attr(not(test), deny(clippy::unwrap_used))]
+
+//! An async client of the RPC server, with a typed method for every method of the server
+//! using the data model defined in [`lwk_rpc_model`].
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), lwk_rpc_client::Error> {
+//! use lwk_rpc_client::{request, ClientBuilder};
+//!
+//! let client = ClientBuilder::new("http://127.0.0.1:32111").build()?;
+//! let balance = client
+//!     .wallet_balance(request::WalletBalance {
+//!         name: "w".to_string(),
+//!         with_tickers: false,
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use crate::error::{Error, RpcError};
+pub use lwk_rpc_model::{request, response};
+
+/// The default timeout of a request
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Authentication sent with every request, for instance to a reverse proxy in front of the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Auth {
+    /// HTTP basic authentication
+    Basic {
+        /// The user name
+        username: String,
+
+        /// The password
+        password: Option<String>,
+    },
+
+    /// Bearer token in the `Authorization` header
+    Bearer(String),
+}
+
+/// A builder for the [`Client`]
+pub struct ClientBuilder {
+    url: String,
+    auth: Option<Auth>,
+    timeout: Duration,
+    http_client: Option<reqwest::Client>,
+}
+
+impl ClientBuilder {
+    /// Create a builder for a client of the server at `url`, e.g. `http://127.0.0.1:32111`
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            auth: None,
+            timeout: DEFAULT_TIMEOUT,
+            http_client: None,
+        }
+    }
+
+    /// Authenticate every request
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Set the timeout of every request, [`DEFAULT_TIMEOUT`] if not set
+    ///
+    /// Ignored if an HTTP client is given with [`ClientBuilder::http_client()`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use the given HTTP client, sharing its connection pool
+    pub fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<Client, Error> {
+        let http_client = match self.http_client {
+            Some(http_client) => http_client,
+            None => reqwest::Client::builder().timeout(self.timeout).build()?,
+        };
+        Ok(Client {
+            url: self.url,
+            auth: self.auth,
+            http_client,
+            next_id: AtomicU64::new(0),
+        })
+    }
+}
+
+/// An async client of the RPC server
+///
+/// Connections are pooled and reused by the inner HTTP client, the client can be shared between
+/// tasks.
+pub struct Client {
+    url: String,
+    auth: Option<Auth>,
+    http_client: reqwest::Client,
+    next_id: AtomicU64,
+}
+
+#[derive(Serialize)]
+struct JsonRpcRequest<'a, Req> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params: Option<Req>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+impl Client {
+    /// Call the RPC `method` with `params`, for methods without a typed counterpart
+    pub async fn call<Req, Res>(&self, method: &str, params: Option<Req>) -> Result<Res, Error>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0",
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            method,
+            params,
+        };
+        let mut builder = self.http_client.post(&self.url).json(&request);
+        builder = match &self.auth {
+            Some(Auth::Basic { username, password }) => {
+                builder.basic_auth(username, password.as_ref())
+            }
+            Some(Auth::Bearer(token)) => builder.bearer_auth(token),
+            None => builder,
+        };
+        // errors have a JSON-RPC body even if the status is not successful, e.g. when rate limited
+        let response: JsonRpcResponse = builder.send().await?.json().await?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(Error::Rpc(error)),
+            (Some(result), None) => Ok(serde_json::from_value(result)?),
+            (None, None) => Err(Error::NeitherResultNorErrorSet),
+        }
+    }
+
+    /// Request the JSON schema of the request or the response of a method
+    pub async fn schema(&self, req: request::Schema) -> Result<Value, Error> {
+        self.call("schema", Some(req)).await
+    }
+
+    /// Stop the server
+    pub async fn stop(&self) -> Result<(), Error> {
+        // The server stops without answering
+        match self.call::<_, Value>("stop", None::<()>).await {
+            Ok(_) | Err(Error::Reqwest(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Define a typed method for every RPC method with a request, and without a request
+macro_rules! methods {
+    ($($(#[$doc:meta])* $name:ident($req:ty) -> $res:ty;)* -- $($(#[$doc_empty:meta])* $name_empty:ident() -> $res_empty:ty;)*) => {
+        impl Client {
+            $(
+                $(#[$doc])*
+                pub async fn $name(&self, req: $req) -> Result<$res, Error> {
+                    self.call(stringify!($name), Some(req)).await
+                }
+            )*
+            $(
+                $(#[$doc_empty])*
+                pub async fn $name_empty(&self) -> Result<$res_empty, Error> {
+                    self.call(stringify!($name_empty), None::<()>).await
+                }
+            )*
+        }
+    };
+}
+
+methods! {
+    /// Load a wallet
+    wallet_load(request::WalletLoad) -> response::Wallet;
+    /// Unload a wallet
+    wallet_unload(request::WalletUnload) -> response::WalletUnload;
+    /// Unload a wallet, optionally deleting its cache
+    wallet_forget(request::WalletForget) -> response::WalletForget;
+    /// Get the details of a wallet
+    wallet_details(request::WalletDetails) -> response::WalletDetails;
+    /// Try again to unblind the wallet outputs that cannot be unblinded
+    wallet_retry_unblind(request::WalletRetryUnblind) -> response::WalletRetryUnblind;
+    /// Get an address of a wallet
+    wallet_address(request::WalletAddress) -> response::WalletAddress;
+    /// Get a range of addresses of a wallet
+    wallet_addresses(request::WalletAddresses) -> response::WalletAddresses;
+    /// Get whether an address belongs to a wallet and its derivation
+    wallet_address_info(request::WalletAddressInfo) -> response::WalletAddressInfo;
+    /// Verify a payment received by a wallet, optionally signing the result
+    wallet_verify_payment(request::WalletVerifyPayment) -> response::WalletVerifyPayment;
+    /// Get the balance of a wallet
+    wallet_balance(request::WalletBalance) -> response::WalletBalance;
+    /// Get the unspent outputs of a wallet
+    wallet_utxos(request::WalletUtxos) -> response::WalletUtxos;
+    /// Get the transactions of a wallet
+    wallet_txs(request::WalletTxs) -> response::WalletTxs;
+    /// Export the transaction history of a wallet
+    wallet_export_history(request::WalletExportHistory) -> response::WalletExportHistory;
+    /// Get a transaction of a wallet
+    wallet_tx(request::WalletTx) -> response::WalletTx;
+    /// Create a PSET sending to many recipients
+    wallet_send_many(request::WalletSendMany) -> response::Pset;
+    /// Create a PSET sending all the L-BTC to an address
+    wallet_drain(request::WalletDrain) -> response::Pset;
+    /// Create a PSET replacing a transaction with a higher fee
+    wallet_bump_fee(request::WalletBumpFee) -> response::Pset;
+    /// Create a PSET issuing an asset
+    wallet_issue(request::WalletIssue) -> response::Pset;
+    /// Create a PSET reissuing an asset
+    wallet_reissue(request::WalletReissue) -> response::Pset;
+    /// Create a PSET burning an asset
+    wallet_burn(request::WalletBurn) -> response::Pset;
+    /// Combine PSETs
+    wallet_combine(request::WalletCombine) -> response::WalletCombine;
+    /// Finalize and broadcast a PSET
+    wallet_broadcast(request::WalletBroadcast) -> response::WalletBroadcast;
+    /// List the transactions broadcast and not yet confirmed
+    wallet_outbox(request::WalletOutbox) -> response::WalletOutbox;
+    /// Add a signed transaction to the outbox, it is broadcast at every scan until confirmed
+    wallet_outbox_insert(request::WalletOutboxInsert) -> response::WalletBroadcast;
+    /// Get the details of a PSET with respect to a wallet
+    wallet_pset_details(request::WalletPsetDetails) -> response::WalletPsetDetails;
+    /// Create a multisig descriptor
+    wallet_multisig_descriptor(request::WalletMultisigDescriptor) -> response::WalletMultisigDescriptor;
+    /// Set the memo of a transaction
+    wallet_set_tx_memo(request::WalletSetTxMemo) -> response::Empty;
+    /// Set the memo of an address
+    wallet_set_addr_memo(request::WalletSetAddrMemo) -> response::Empty;
+    /// Set the spending policy of a wallet
+    wallet_set_policy(request::WalletSetPolicy) -> response::WalletPolicyStatus;
+    /// Get the spending policy of a wallet and its usage today
+    wallet_policy_status(request::WalletPolicyStatus) -> response::WalletPolicyStatus;
+    /// Backup a wallet
+    wallet_backup(request::WalletBackup) -> response::WalletBackup;
+    /// Restore a wallet from a backup
+    wallet_restore(request::WalletRestore) -> response::Wallet;
+    /// Get the identifiers of a Jade
+    signer_jade_id(request::SignerJadeId) -> response::JadeId;
+    /// Load a software signer
+    signer_load_software(request::SignerLoadSoftware) -> response::Signer;
+    /// Load a Jade signer
+    signer_load_jade(request::SignerLoadJade) -> response::Signer;
+    /// Load an external signer
+    signer_load_external(request::SignerLoadExternal) -> response::Signer;
+    /// Unload a signer
+    signer_unload(request::SignerUnload) -> response::SignerUnload;
+    /// Get the details of a signer
+    signer_details(request::SignerDetails) -> response::SignerDetails;
+    /// Get an xpub of a signer
+    signer_xpub(request::SignerXpub) -> response::SignerXpub;
+    /// Sign a PSET
+    signer_sign(request::SignerSign) -> response::Pset;
+    /// Create a singlesig descriptor
+    signer_singlesig_descriptor(request::SignerSinglesigDescriptor) -> response::SignerSinglesigDescriptor;
+    /// Register a multisig wallet on a signer
+    signer_register_multisig(request::SignerRegisterMultisig) -> response::Empty;
+    /// Require TOTP codes for the signatures of a signer spending more than a threshold
+    signer_enroll_totp(request::SignerEnrollTotp) -> response::SignerTotp;
+    /// Create an asset contract
+    asset_contract(request::AssetContract) -> response::AssetContract;
+    /// Insert an asset
+    asset_insert(request::AssetInsert) -> response::Empty;
+    /// Remove an asset
+    asset_remove(request::AssetRemove) -> response::Empty;
+    /// Get the details of an asset
+    asset_details(request::AssetDetails) -> response::AssetDetails;
+    /// Insert an asset fetching its details from the explorer
+    asset_from_explorer(request::AssetFromExplorer) -> response::Empty;
+    /// Publish an asset contract
+    asset_publish(request::AssetPublish) -> response::AssetPublish;
+    /// Get an AMP2 descriptor
+    amp2_descriptor(request::Amp2Descriptor) -> response::Amp2Descriptor;
+    /// Register a wallet with AMP2
+    amp2_register(request::Amp2Register) -> response::Amp2Register;
+    /// Ask AMP2 to cosign a PSET
+    amp2_cosign(request::Amp2Cosign) -> response::Amp2Cosign;
+    /// Create a new account
+    account_new(request::AccountNew) -> response::Account;
+    /// List the accounts
+    account_list(request::AccountList) -> response::AccountList;
+    /// Decode a transaction
+    tx_decode(request::TxDecode) -> response::DecodedTx;
+    /// Broadcast a transaction not related to the loaded wallets
+    tx_broadcast(request::TxBroadcast) -> response::TxBroadcast;
+    /// Decode a PSET
+    pset_decode(request::PsetDecode) -> response::DecodedTx;
+    /// Save a PSET session
+    pset_session_save(request::PsetSessionSave) -> response::PsetSession;
+    /// Load a PSET session
+    pset_session_load(request::PsetSessionLoad) -> response::PsetSession;
+    /// Merge PSET sessions
+    pset_session_merge(request::PsetSessionMerge) -> response::PsetSession;
+    --
+    /// Get the version of the server
+    version() -> response::Version;
+    /// Scan the wallets
+    scan() -> response::Empty;
+    /// Get the health of the servers used
+    server_status() -> response::ServerStatus;
+    /// List the wallets
+    wallet_list() -> response::WalletList;
+    /// Generate a new mnemonic
+    signer_generate() -> response::SignerGenerate;
+    /// List the signers
+    signer_list() -> response::SignerList;
+    /// List the assets
+    asset_list() -> response::AssetList;
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use lwk_tiny_jrpc::tiny_http::Server;
+    use lwk_tiny_jrpc::{Config, JsonRpcServer, Request, Response};
+
+    use super::*;
+
+    fn process(request: Request, _state: Arc<Mutex<()>>) -> Result<Response, lwk_tiny_jrpc::Error> {
+        let result = match request.method.as_str() {
+            "version" => serde_json::to_value(response::Version {
+                version: "0.9.0".to_string(),
+                network: "liquid".to_string(),
+            }),
+            "wallet_balance" => {
+                let req: request::WalletBalance =
+                    serde_json::from_value(request.params.unwrap()).unwrap();
+                serde_json::to_value(response::WalletBalance {
+                    balance: HashMap::from([(req.name, 5)]),
+                    display: None,
+                })
+            }
+            _ => return Ok(Response::unimplemented(request.id, request.method)),
+        };
+        Ok(Response::result(request.id, result.unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_client() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let mut rpc = JsonRpcServer::new(server, Config::default(), state, process);
+        let url = format!("http://127.0.0.1:{}/", rpc.port().unwrap());
+        let client = ClientBuilder::new(&url)
+            .auth(Auth::Bearer("token".to_string()))
+            .build()
+            .unwrap();
+
+        let version = client.version().await.unwrap();
+        assert_eq!(version.network, "liquid");
+
+        let req = request::WalletBalance {
+            name: "w".to_string(),
+            with_tickers: false,
+        };
+        let balance = client.wallet_balance(req).await.unwrap();
+        assert_eq!(balance.balance, HashMap::from([("w".to_string(), 5)]));
+
+        let err = client.wallet_list().await.unwrap_err();
+        assert!(matches!(err, Error::Rpc(RpcError { code: -32601, .. })));
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+}
@@ -1,5 +1,6 @@
 //! Data models of every requests made via RPC
 
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 
 #[cfg(doc)]
@@ -9,11 +10,11 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// An empty request, doesn't require any param.
-#[derive(JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Empty {}
 
 /// Request a JSON schema of a method of the RPC
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Schema {
     /// Name of the method to request the schema for
     pub method: String,
@@ -23,7 +24,7 @@ pub struct Schema {
 }
 
 /// The direction, to the server (request) or from the server (response)
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Direction {
     /// Request, to the server
@@ -34,24 +35,41 @@ pub enum Direction {
 }
 
 /// Request to load a wallet in the server, returning [`response::Wallet`]
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletLoad {
     /// The read-only descriptor describing the wallet outputs
     pub descriptor: String,
 
     /// The name given to the wallet, will be needed for calls related to the wallet
     pub name: String,
+
+    /// The height of the first block containing a wallet transaction, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birthday: Option<u32>,
 }
 
 /// Unload the wallet identified by the given name
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletUnload {
     /// The name given to the wallet
     pub name: String,
 }
 
+/// Unload the wallet identified by the given name, optionally deleting its cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletForget {
+    /// The name given to the wallet
+    pub name: String,
+
+    /// Delete the blockchain data of the wallet cached in the data directory
+    ///
+    /// The wallet must be fully scanned again if loaded later.
+    #[serde(default)]
+    pub delete_cache: bool,
+}
+
 /// Load a signer in the server
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerLoadSoftware {
     /// The name of the signer, will be needed to reference it in other calls
     pub name: String,
@@ -64,7 +82,7 @@ pub struct SignerLoadSoftware {
 }
 
 /// Load a signer in the server
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerLoadJade {
     /// The name of the signer, will be needed to reference it in other calls
     pub name: String,
@@ -77,7 +95,7 @@ pub struct SignerLoadJade {
 }
 
 /// Load a signer in the server
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerLoadExternal {
     /// The name of the signer, will be needed to reference it in other calls
     pub name: String,
@@ -87,21 +105,21 @@ pub struct SignerLoadExternal {
 }
 
 /// Get the signer details
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerDetails {
     /// The name of the signer
     pub name: String,
 }
 
 /// Unload the signer identified by the given name
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerUnload {
     /// The name of the signer
     pub name: String,
 }
 
 /// Request a receiving address
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletAddress {
     /// The wallet name
     pub name: String,
@@ -119,8 +137,64 @@ pub struct WalletAddress {
     pub with_uri_qr: Option<u8>,
 }
 
+/// Request a range of addresses of a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletAddresses {
+    /// The wallet name
+    pub name: String,
+
+    /// The derivation index of the first address
+    pub start: u32,
+
+    /// The number of addresses
+    pub count: u32,
+
+    /// The chain of the addresses, "external" for receiving or "internal" for change
+    pub chain: String,
+}
+
+/// Request whether an address belongs to a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletAddressInfo {
+    /// The wallet name
+    pub name: String,
+
+    /// The address, confidential or not
+    pub address: String,
+}
+
+/// Request to verify a payment received by a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletVerifyPayment {
+    /// The wallet name
+    pub name: String,
+
+    /// The transaction claimed to pay the wallet
+    pub txid: String,
+
+    /// The output of the transaction claimed to pay the wallet
+    pub vout: u32,
+
+    /// The address of the wallet claimed to be paid, confidential or not
+    pub address: String,
+
+    /// The asset claimed to be paid
+    pub asset: String,
+
+    /// The amount claimed to be paid, in satoshi
+    pub satoshi: u64,
+
+    /// Confirmations required for the payment to be verified, 1 if not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confirmations: Option<u32>,
+
+    /// The name of a software signer signing the result of the verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+}
+
 /// The balance of a wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletBalance {
     /// The wallet name
     pub name: String,
@@ -130,7 +204,7 @@ pub struct WalletBalance {
 }
 
 /// Send a transaction from a wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletSendMany {
     /// The wallet name creating the transaction
     pub name: String,
@@ -143,7 +217,7 @@ pub struct WalletSendMany {
 }
 
 ///  An addressee which has yet to be validated
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct UnvalidatedAddressee {
     /// The amount to send in satoshi
     pub satoshi: u64,
@@ -160,7 +234,7 @@ pub struct UnvalidatedAddressee {
 }
 
 /// Drain a wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletDrain {
     /// The wallet name creating the transaction
     pub name: String,
@@ -172,8 +246,21 @@ pub struct WalletDrain {
     pub fee_rate: Option<f32>,
 }
 
+/// Bump the fee of an unconfirmed wallet transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletBumpFee {
+    /// The wallet name that created the transaction
+    pub name: String,
+
+    /// The transaction id of the transaction to replace
+    pub txid: String,
+
+    /// The new fee rate in sat/kvB
+    pub fee_rate: f32,
+}
+
 /// A request containing information to create a single signature descriptor wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerSinglesigDescriptor {
     /// The signer name
     pub name: String,
@@ -186,12 +273,12 @@ pub struct SignerSinglesigDescriptor {
 }
 
 /// A request containing information to create a multi signature descriptor wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletMultisigDescriptor {
     /// The descriptor blinding key
     pub descriptor_blinding_key: String,
 
-    /// The multisig kind // TODO enum
+    /// The multisig kind, "wsh" or "wsh-sorted" // TODO enum
     pub multisig_kind: String,
 
     /// The number of signatures required to spend
@@ -202,7 +289,7 @@ pub struct WalletMultisigDescriptor {
 }
 
 /// Request to register a multisig wallet on a signer
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerRegisterMultisig {
     /// The signer name
     pub name: String,
@@ -212,7 +299,7 @@ pub struct SignerRegisterMultisig {
 }
 
 /// Request to a signer for a derived xpub
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerXpub {
     /// The signer name
     pub name: String,
@@ -222,17 +309,40 @@ pub struct SignerXpub {
 }
 
 /// A request to sign a PSET
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerSign {
     /// The signer name
     pub name: String,
 
     /// The PSET in base64
     pub pset: String,
+
+    /// The TOTP code, needed if the signer enrolled TOTP and the PSET exceeds its daily threshold
+    #[serde(default)]
+    pub totp: Option<String>,
+}
+
+/// Request to require TOTP codes for the signatures of a signer spending more than a daily threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SignerEnrollTotp {
+    /// The signer name
+    pub name: String,
+
+    /// Maximum amount of L-BTC in satoshi signed without TOTP codes per UTC day
+    ///
+    /// Signing PSETs spending other assets always requires a code.
+    pub threshold: u64,
+
+    /// The TOTP secret in base32, if not given a random one is generated
+    pub secret: Option<String>,
+
+    /// A code of the currently enrolled secret, needed to change it or its threshold
+    #[serde(default)]
+    pub totp: Option<String>,
 }
 
 /// Request to broadcast a transaction
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletBroadcast {
     /// The wallet name
     pub name: String,
@@ -244,15 +354,43 @@ pub struct WalletBroadcast {
     pub pset: String,
 }
 
+/// Request the transactions broadcast by a wallet and not yet confirmed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletOutbox {
+    /// The wallet name
+    pub name: String,
+}
+
+/// Request to add a signed transaction to the outbox of a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletOutboxInsert {
+    /// The wallet name
+    pub name: String,
+
+    /// The transaction in hex
+    pub tx: String,
+}
+
 /// Request details for a wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletDetails {
     /// The wallet name
     pub name: String,
 }
 
+/// Request to try again to unblind the outputs of a wallet that cannot be unblinded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletRetryUnblind {
+    /// The wallet name
+    pub name: String,
+
+    /// Other blinding private keys, in hex, for outputs blinded by the sender with a different key
+    #[serde(default)]
+    pub blinding_keys: Vec<String>,
+}
+
 /// Request to do an issuance
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletIssue {
     /// The wallet name doing the issuance
     pub name: String,
@@ -263,7 +401,7 @@ pub struct WalletIssue {
     /// The address receiving the asset, if missing a receiving address from the wallet doing the issuance is used
     pub address_asset: Option<String>,
 
-    /// The number of reissuance token to be created
+    /// The number of reissuance token to be created, if 0 the asset cannot be reissued
     pub satoshi_token: u64,
 
     /// The address receiving the reissuance token, if missing a receiving address from the wallet doing the issuance is used
@@ -277,7 +415,7 @@ pub struct WalletIssue {
 }
 
 /// Request to do a reissuance
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletReissue {
     /// The wallet name doing the reissuance
     pub name: String,
@@ -296,7 +434,7 @@ pub struct WalletReissue {
 }
 
 /// Request to do burn an asset
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletBurn {
     /// The wallet name
     pub name: String,
@@ -312,7 +450,7 @@ pub struct WalletBurn {
 }
 
 /// A request creating a contract in the JSON format expected by the issue call
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetContract {
     /// Domain of the issuer
     pub domain: String,
@@ -334,7 +472,7 @@ pub struct AssetContract {
 }
 
 /// Request to combine PSETs
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletCombine {
     /// The wallet name
     pub name: String,
@@ -344,7 +482,7 @@ pub struct WalletCombine {
 }
 
 /// Request to see details of a PSET
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletPsetDetails {
     /// The wallet name
     pub name: String,
@@ -357,14 +495,14 @@ pub struct WalletPsetDetails {
 }
 
 /// Request to get the wallet unspet transaction Outputs
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletUtxos {
     /// The wallet name
     pub name: String,
 }
 
 /// Request to get the wallet transactions
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletTxs {
     /// The wallet name
     pub name: String,
@@ -373,8 +511,18 @@ pub struct WalletTxs {
     pub with_tickers: bool,
 }
 
+/// Request to export the wallet transaction history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletExportHistory {
+    /// The wallet name
+    pub name: String,
+
+    /// The output format, "csv" or "json"
+    pub format: String,
+}
+
 /// Request to get a transaction
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletTx {
     /// The wallet name
     pub name: String,
@@ -387,14 +535,14 @@ pub struct WalletTx {
 }
 
 /// Request to have details of an asset
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetDetails {
     /// The asset identifier
     pub asset_id: String,
 }
 
 /// Request to insert an asset
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetInsert {
     /// Asset ID in hex
     pub asset_id: String,
@@ -407,35 +555,35 @@ pub struct AssetInsert {
 }
 
 /// Request to remove an asset
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetRemove {
     /// The asset identifier
     pub asset_id: String,
 }
 
 /// Request to insert an asset retrieving information from the explorer
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetFromExplorer {
     /// The asset identifier
     pub asset_id: String,
 }
 
 /// Request to publish
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetPublish {
     /// The asset identifier
     pub asset_id: String,
 }
 
 /// Request to obtain jade identifiers
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerJadeId {
     /// If set, instead of looking for physical jade, try to connect to the emulator at the following port
     pub emulator: Option<SocketAddr>,
 }
 
 /// Request to set a transaction memo
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletSetTxMemo {
     /// The wallet name
     pub name: String,
@@ -447,8 +595,43 @@ pub struct WalletSetTxMemo {
     pub memo: String,
 }
 
+/// Request to set the spending policy of a wallet
+///
+/// The policy is checked before a signer loaded in the server signs a PSET spending from the
+/// wallet, setting an empty policy removes it. While signers of the wallet are loaded, the policy
+/// can only be made stricter and the wallet cannot be unloaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletSetPolicy {
+    /// The wallet name
+    pub name: String,
+
+    /// Maximum amount of L-BTC in satoshi the wallet can spend per UTC day, fees included
+    pub daily_limit: Option<u64>,
+
+    /// Maximum amount of other assets the wallet can spend per UTC day, by asset id
+    ///
+    /// If any daily limit is set, the assets without a limit cannot be spent.
+    #[serde(default)]
+    pub asset_daily_limits: BTreeMap<String, u64>,
+
+    /// If not empty, the only addresses the wallet can send to
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+
+    /// Fingerprints of the cosigners that must sign before the signers loaded in the server
+    #[serde(default)]
+    pub required_cosigners: Vec<String>,
+}
+
+/// Request the spending policy of a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletPolicyStatus {
+    /// The wallet name
+    pub name: String,
+}
+
 /// Request to set an address memo
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletSetAddrMemo {
     /// The wallet name
     pub name: String,
@@ -460,27 +643,147 @@ pub struct WalletSetAddrMemo {
     pub memo: String,
 }
 
+/// Request to export a portable backup of a wallet, returning [`response::WalletBackup`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletBackup {
+    /// The wallet name
+    pub name: String,
+}
+
+/// Request to restore a wallet from a backup, returning [`response::Wallet`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletRestore {
+    /// The name given to the restored wallet
+    pub name: String,
+
+    /// The backup in JSON, as returned by [`request::WalletBackup`]
+    pub backup: String,
+}
+
 /// Request to get an AMP2 wallet descriptor
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Amp2Descriptor {
     /// The signer name
     pub name: String,
 }
 
 /// Request to register an AMP2 wallet descriptor
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Amp2Register {
     /// The signer name
     pub name: String,
 }
 
 /// Request for AMP2 to cosign a PSET
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Amp2Cosign {
     /// The PSET to cosign
     pub pset: String,
 }
 
+/// Request to create a new account for a signer, returning [`response::Account`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AccountNew {
+    /// The signer name
+    pub signer: String,
+
+    /// The account kind, one of "wpkh", "shwpkh" or "wsh" (multisig) // TODO enum
+    pub account_kind: String,
+
+    /// The descriptor blinding key
+    pub descriptor_blinding_key: String,
+
+    /// The name given to the account wallet, if missing "<signer>-<account_kind>-<index>" is used
+    pub name: Option<String>,
+
+    /// The number of signatures required to spend, only for multisig accounts
+    pub threshold: Option<u32>,
+
+    /// The xpubs with key origin of the other partecipants, only for multisig accounts
+    #[serde(default)]
+    pub cosigners: Vec<String>,
+}
+
+/// Request to list the accounts of a signer, returning [`response::AccountList`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AccountList {
+    /// The signer name
+    pub signer: String,
+
+    /// Replace asset ids with tickers when possible
+    pub with_tickers: bool,
+}
+
+/// Request to decode a transaction, returning [`response::DecodedTx`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TxDecode {
+    /// The transaction in hex format
+    pub tx: String,
+
+    /// Optional wallet name, used to unblind inputs and outputs belonging to the wallet
+    pub name: Option<String>,
+}
+
+/// Request to broadcast a transaction not related to the loaded wallets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TxBroadcast {
+    /// The transaction in hex format, alternative to `pset`
+    pub tx: Option<String>,
+
+    /// The PSET in base64, finalized before the broadcast, alternative to `tx`
+    pub pset: Option<String>,
+
+    /// Check the transaction without broadcasting it
+    ///
+    /// If the server is configured with an Elements node, the node checks the transaction would be
+    /// accepted in its mempool.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Request to decode a PSET, returning [`response::DecodedTx`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PsetDecode {
+    /// The PSET in base64 format
+    pub pset: String,
+
+    /// Optional wallet name, used to unblind inputs and outputs belonging to the wallet
+    pub name: Option<String>,
+}
+
+/// Request to create or update a PSET session, returning [`response::PsetSession`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PsetSessionSave {
+    /// The session in JSON format, if missing a new session is created
+    pub session: Option<String>,
+
+    /// The PSET in base64 format, its signatures are added to the session
+    pub pset: String,
+
+    /// The fingerprints of the cosigners, used only when creating a session
+    ///
+    /// If empty, the fingerprints in the key origins of the PSET inputs are used.
+    #[serde(default)]
+    pub participants: Vec<String>,
+
+    /// The number of signatures needed for each input, required when creating a session
+    pub threshold: Option<u32>,
+}
+
+/// Request to load a PSET session, returning [`response::PsetSession`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PsetSessionLoad {
+    /// The session in JSON format
+    pub session: String,
+}
+
+/// Request to merge PSET sessions of the same PSET, returning [`response::PsetSession`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PsetSessionMerge {
+    /// The sessions in JSON format
+    pub sessions: Vec<String>,
+}
+
 #[cfg(test)]
 mod test {
     use schemars::schema_for;
@@ -491,7 +794,7 @@ mod test {
     fn test_json_schema() {
         let schema = schema_for!(WalletLoad);
         assert_eq!(
-            r#"{"$schema":"http://json-schema.org/draft-07/schema#","title":"WalletLoad","description":"Request to load a wallet in the server, returning [`response::Wallet`]","type":"object","required":["descriptor","name"],"properties":{"descriptor":{"description":"The read-only descriptor describing the wallet outputs","type":"string"},"name":{"description":"The name given to the wallet, will be needed for calls related to the wallet","type":"string"}}}"#,
+            r#"{"$schema":"http://json-schema.org/draft-07/schema#","title":"WalletLoad","description":"Request to load a wallet in the server, returning [`response::Wallet`]","type":"object","required":["descriptor","name"],"properties":{"birthday":{"description":"The height of the first block containing a wallet transaction, if known","type":["integer","null"],"format":"uint32","minimum":0.0},"descriptor":{"description":"The read-only descriptor describing the wallet outputs","type":"string"},"name":{"description":"The name given to the wallet, will be needed for calls related to the wallet","type":"string"}}}"#,
             serde_json::to_string(&schema).unwrap()
         );
     }
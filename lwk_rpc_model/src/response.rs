@@ -5,14 +5,14 @@ use crate::request;
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// An empty response.
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Empty {}
 
 /// Server version response
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Version {
     /// The server version
     pub version: String,
@@ -21,22 +21,108 @@ pub struct Version {
     pub network: String,
 }
 
+/// Measured health of an Electrum server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ServerHealth {
+    /// The server url
+    pub url: String,
+
+    /// True if the last request to the server succeeded
+    pub healthy: bool,
+
+    /// Response time in milliseconds, smoothed over the last measurements
+    pub latency_ms: Option<u64>,
+
+    /// The highest protocol version supported by the server
+    pub protocol_version: Option<String>,
+
+    /// The height below which the server pruned the blocks, if any
+    pub pruning: Option<i64>,
+
+    /// Number of successful requests
+    pub successes: u32,
+
+    /// Number of failed requests
+    pub failures: u32,
+
+    /// Number of failed requests since the last successful one
+    pub consecutive_failures: u32,
+
+    /// Unix timestamp in seconds of the last failure
+    pub last_failure: Option<u64>,
+}
+
+/// Response of the server status, also of the previous runs of the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ServerStatus {
+    /// The servers used
+    pub servers: Vec<ServerHealth>,
+}
+
+/// Response with the spending policy of a wallet and its usage in the current day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletPolicyStatus {
+    /// The wallet name
+    pub name: String,
+
+    /// Maximum amount of L-BTC in satoshi the wallet can spend per UTC day, fees included
+    pub daily_limit: Option<u64>,
+
+    /// L-BTC in satoshi spent in the current UTC day, fees included
+    pub spent_today: u64,
+
+    /// L-BTC in satoshi that can still be spent in the current UTC day, if there is a limit
+    pub remaining_today: Option<u64>,
+
+    /// Maximum amount of other assets the wallet can spend per UTC day, by asset id
+    ///
+    /// If any daily limit is set, the assets without a limit cannot be spent.
+    pub asset_daily_limits: BTreeMap<String, u64>,
+
+    /// Amounts of other assets spent in the current UTC day, by asset id
+    pub asset_spent_today: BTreeMap<String, u64>,
+
+    /// If not empty, the only addresses the wallet can send to
+    pub allowlist: Vec<String>,
+
+    /// Fingerprints of the cosigners that must sign before the signers loaded in the server
+    pub required_cosigners: Vec<String>,
+}
+
+/// Response with the TOTP secret enrolled for a signer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SignerTotp {
+    /// The signer name
+    pub name: String,
+
+    /// The TOTP secret in base32
+    pub secret: String,
+
+    /// The `otpauth://` URI to enroll the secret in an authenticator app
+    pub uri: String,
+
+    /// Maximum amount of L-BTC in satoshi signed without TOTP codes per UTC day
+    ///
+    /// Signing PSETs spending other assets always requires a code.
+    pub threshold: u64,
+}
+
 /// Response for generate signer
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerGenerate {
     /// Randomly generated mnemonic from the server
     pub mnemonic: String,
 }
 
 /// Response for list signers call
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerList {
     /// Returned signers currently loaded in the server
     pub signers: Vec<Signer>,
 }
 
 /// Wallet response, returned from various call such as [`request::WalletLoad`], [`request::WalletUnload`]
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Wallet {
     /// Public descriptor definining wallet outputs
     pub descriptor: String,
@@ -46,28 +132,39 @@ pub struct Wallet {
 }
 
 /// Response for list wallets call
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletList {
     /// Returned wallets currently loaded in the server
     pub wallets: Vec<Wallet>,
 }
 
 /// Response for unload wallet call
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletUnload {
     /// Details of the wallet unloaded from the server
     pub unloaded: Wallet,
 }
 
+/// Response for forget wallet call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletForget {
+    /// Details of the wallet unloaded from the server
+    pub forgotten: Wallet,
+
+    /// The cache directory deleted, if requested and existing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_cache: Option<String>,
+}
+
 /// Response for unload signer call
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerUnload {
     /// Details of the signer unloaded from the server
     pub unloaded: Signer,
 }
 
 /// Response of a signer
-#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Signer {
     /// The signer name
     pub name: String,
@@ -77,11 +174,14 @@ pub struct Signer {
 }
 
 /// Address response
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletAddress {
     /// The receiving address
     pub address: String,
 
+    /// The receiving address without the blinding key, funds sent to it are not blinded
+    pub unconfidential_address: String,
+
     /// The index of the derivation of the given address
     pub index: u32,
 
@@ -97,50 +197,169 @@ pub struct WalletAddress {
     pub uri_qr: Option<String>,
 }
 
+/// An address derived from a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DerivedAddress {
+    /// The address
+    pub address: String,
+
+    /// The address without the blinding key
+    pub unconfidential_address: String,
+
+    /// The derivation index of the address
+    pub index: u32,
+}
+
+/// Addresses response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletAddresses {
+    /// The addresses, ordered by derivation index
+    pub addresses: Vec<DerivedAddress>,
+}
+
+/// Result of the verification of a payment received by a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletVerifyPayment {
+    /// Whether the output pays the claimed amount of the asset to the address of the wallet,
+    /// with at least the required confirmations
+    pub verified: bool,
+
+    /// Why the payment is not verified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mismatch: Option<String>,
+
+    /// The descriptor wallet id of the wallet
+    pub wallet_id: String,
+
+    /// Confirmations required for the payment to be verified
+    pub min_confirmations: u32,
+
+    /// Height of the block including the transaction, absent if unconfirmed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+
+    /// Confirmations of the transaction, 0 if unconfirmed
+    pub confirmations: u32,
+
+    /// Height of the wallet tip when verified
+    pub tip_height: u32,
+
+    /// Hash of the wallet tip when verified
+    pub tip_hash: String,
+
+    /// The claimed payment and the result of the verification, as signed
+    pub message: String,
+
+    /// Signature of the message with the key of the signer at `m/5413'/0'`, in the Bitcoin signed
+    /// message format encoded in base64
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+
+    /// The public key of the signature, in hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_pubkey: Option<String>,
+}
+
+/// Address ownership response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletAddressInfo {
+    /// Whether the address belongs to the wallet
+    ///
+    /// A confidential address belongs to the wallet only if also the blinding key is the wallet
+    /// one, otherwise the wallet couldn't unblind the outputs sent to it.
+    pub is_mine: bool,
+
+    /// The chain of the address, "external" for receiving or "internal" for change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+
+    /// The derivation index of the address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+
+    /// The confidential address of the wallet with the same script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidential_address: Option<String>,
+
+    /// The address of the wallet with the same script without the blinding key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unconfidential_address: Option<String>,
+}
+
 /// Balance respone
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletBalance {
     /// A map of the balance of every asset in the wallet
     pub balance: HashMap<String, i64>,
+
+    /// The balances formatted with the asset precision and ticker, e.g. `12.34 USDt`
+    ///
+    /// Present when tickers are requested, assets without known precision are omitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<HashMap<String, String>>,
 }
 
 /// PSET response
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Pset {
     /// The PSET in base64 format
     pub pset: String,
 }
 
 /// Response containing a single signature descriptor
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerSinglesigDescriptor {
     /// The singlesig descriptor
     pub descriptor: String,
 }
 
 /// Response containing a multi signature descriptor
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletMultisigDescriptor {
     /// The multisig descriptor
     pub descriptor: String,
 }
 
 /// A response containing an xpub with keyorigin
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerXpub {
     /// The xpub with keyorigin prepended (fingerprint+derivation path)
     pub keyorigin_xpub: String,
 }
 
 /// The response of a broadcast
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletBroadcast {
     /// The txid of the transaction just broadacasted
     pub txid: String,
 }
 
+/// The response of a broadcast of a transaction not related to the loaded wallets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TxBroadcast {
+    /// The txid of the transaction
+    pub txid: String,
+
+    /// Whether the transaction was broadcast, false for dry runs
+    pub broadcast: bool,
+
+    /// Whether the Elements node of the server accepted the transaction in its mempool, absent if
+    /// not checked
+    ///
+    /// Checked only for dry runs, if the server has an Elements node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mempool_accepted: Option<bool>,
+}
+
+/// The transactions broadcast by a wallet and not yet confirmed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletOutbox {
+    /// The ids of the transactions, rebroadcast at every scan until confirmed or conflicted
+    pub txids: Vec<String>,
+}
+
 /// A response of a JSON contract containing asset metadata and validated according to the contract rules
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetContract {
     /// Entity emitting the asset
     pub entity: Entity,
@@ -162,17 +381,17 @@ pub struct AssetContract {
 }
 
 /// Entity issuing the asset
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Entity {
     /// Domain of the entity issuing the asset
-    domain: String,
+    pub domain: String,
 }
 
 /// Details of a signer (short version)
 ///
 /// Intended for use in complex responses,
 /// eg when showing PSET details
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerShortDetails {
     /// The name of the signer
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -183,7 +402,7 @@ pub struct SignerShortDetails {
 }
 
 /// Details of a loaded signer
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SignerDetails {
     /// Signer name
     pub name: String,
@@ -199,7 +418,7 @@ pub struct SignerDetails {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub xpub: Option<String>,
 
-    /// Mnemonic
+    /// Mnemonic, not returned for signers with TOTP enrolled
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mnemonic: Option<String>,
 
@@ -209,7 +428,7 @@ pub struct SignerDetails {
 }
 
 /// Details of a wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletDetails {
     /// Wallet descriptor
     pub descriptor: String,
@@ -223,17 +442,75 @@ pub struct WalletDetails {
 
     /// Warnings on this wallet
     pub warnings: String,
+
+    /// Potential problems of the wallet descriptor, also included in `warnings`
+    pub descriptor_warnings: Vec<DescriptorWarning>,
+
+    /// Outputs on wallet scripts that the wallet cannot unblind, not included in the balance
+    #[serde(default)]
+    pub unblindable_outputs: Vec<UnblindableOutput>,
+}
+
+/// The outputs unblinded by retrying
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletRetryUnblind {
+    /// The outputs unblinded, now part of the wallet balance
+    pub unblinded: Vec<UnblindedOutput>,
+
+    /// The outputs still not unblinded
+    pub unblindable_outputs: Vec<UnblindableOutput>,
+}
+
+/// An output unblinded by retrying
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UnblindedOutput {
+    /// Transaction ID
+    pub txid: String,
+
+    /// Output index
+    pub vout: u32,
+}
+
+/// An output on a wallet script that the wallet cannot unblind, for instance because the sender
+/// blinded it with a different key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UnblindableOutput {
+    /// Transaction ID
+    pub txid: String,
+
+    /// Output index
+    pub vout: u32,
+
+    /// Height
+    pub height: Option<u32>,
+
+    /// Output script pubkey
+    pub script_pubkey: String,
+}
+
+/// A potential problem of a wallet descriptor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DescriptorWarning {
+    /// Kind of the warning, for instance `missing_key_origin` or `duplicate_key`
+    pub kind: String,
+
+    /// The key the warning refers to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// Description of the warning
+    pub message: String,
 }
 
 /// Response to wallet combine
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletCombine {
     /// PSET in base64 format
     pub pset: String,
 }
 
 /// Response containing detail of a PSET
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Issuance {
     /// Asset id
     pub asset: String,
@@ -261,7 +538,7 @@ pub struct Issuance {
 }
 
 /// Details about a reissuance
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Reissuance {
     /// The asset id
     pub asset: String,
@@ -280,7 +557,7 @@ pub struct Reissuance {
 }
 
 /// Details of a PSET
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletPsetDetails {
     /// Signatures contained in the PSET
     pub has_signatures_from: Vec<SignerShortDetails>,
@@ -305,7 +582,7 @@ pub struct WalletPsetDetails {
 }
 
 /// Unspent Transaction Output
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Utxo {
     /// Transction ID
     pub txid: String,
@@ -330,14 +607,14 @@ pub struct Utxo {
 }
 
 /// Wallet unspent transaction outputs
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletUtxos {
     /// UTXOs
     pub utxos: Vec<Utxo>,
 }
 
 /// Transaction
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Tx {
     /// Transction ID
     pub txid: String,
@@ -348,6 +625,12 @@ pub struct Tx {
     /// Timestamp of the block containing the transaction, present only if the tx is confirmed.
     pub timestamp: Option<u32>,
 
+    /// Number of confirmations, 0 if the tx is unconfirmed
+    pub confirmations: u32,
+
+    /// Confirmation status: "unconfirmed", "confirming" or "final"
+    pub status: String,
+
     /// Net balance for the transaction
     pub balance: HashMap<String, i64>,
 
@@ -366,21 +649,29 @@ pub struct Tx {
 }
 
 /// Wallet transactions
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletTxs {
     /// Transactions
     pub txs: Vec<Tx>,
 }
 
+/// Wallet transaction history, for accounting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletExportHistory {
+    /// The history in the requested format, one row per transaction and asset with columns
+    /// date, txid, asset, amount, fee, type and memo
+    pub content: String,
+}
+
 /// Transaction
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WalletTx {
     /// Transaction in hex
     pub tx: String,
 }
 
 /// Details of an asset
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetDetails {
     /// Name of the asset
     pub name: String,
@@ -390,7 +681,7 @@ pub struct AssetDetails {
 }
 
 /// Asset details
-#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialOrd, Ord, PartialEq, Eq)]
 pub struct Asset {
     /// The asset identifier (32 bytes as 64 hex chars)
     pub asset_id: String,
@@ -400,7 +691,7 @@ pub struct Asset {
 }
 
 /// Publish asset response
-#[derive(Debug, Serialize, Deserialize, JsonSchema, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialOrd, Ord, PartialEq, Eq)]
 pub struct AssetPublish {
     /// The asset identifier (32 bytes as 64 hex chars)
     pub asset_id: String,
@@ -410,21 +701,21 @@ pub struct AssetPublish {
 }
 
 /// A list of assets
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct AssetList {
     /// The list of assets
     pub assets: Vec<Asset>,
 }
 
 /// Asset details
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct JadeId {
     /// The jade full identifier (20 bytes as 40 hex chars), the first 4 bytes are the fingerprint
     pub identifier: String,
 }
 
 /// The wallet type // TODO move to response
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum WalletType {
     /// Unknowm type
     Unknown,
@@ -437,29 +728,293 @@ pub enum WalletType {
 
     /// Witnes script hash, multisig N of M
     WshMulti(usize, usize),
+
+    /// Witnes script hash, multisig N of M with keys sorted
+    WshSortedMulti(usize, usize),
 }
 
 /// Descriptor of an AMP2 wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Amp2Descriptor {
     /// AMP2 wallet descriptor
     pub descriptor: String,
 }
 
 /// Registered AMP2 wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Amp2Register {
     /// AMP2 wallet id
     pub wid: String,
 }
 
 /// PSET cosigned by AMP2 wallet
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Amp2Cosign {
     /// The cosigned PSET
     pub pset: String,
 }
 
+/// An account of a signer, a wallet loaded in the server deriving its keys from a BIP44 account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Account {
+    /// The wallet name
+    pub name: String,
+
+    /// Public descriptor definining wallet outputs
+    pub descriptor: String,
+
+    /// The account kind, one of "wpkh", "shwpkh" or "wsh"
+    pub kind: String,
+
+    /// The account index in the signer derivation path
+    pub index: u32,
+
+    /// A map of the balance of every asset in the account
+    pub balance: HashMap<String, i64>,
+}
+
+/// Response for list accounts call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AccountList {
+    /// The accounts of the signer currently loaded in the server
+    pub accounts: Vec<Account>,
+
+    /// A map of the balance of every asset summed across all the accounts
+    pub balance: HashMap<String, i64>,
+}
+
+/// A decoded transaction input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DecodedInput {
+    /// The transaction id of the output spent
+    pub txid: String,
+
+    /// The output index of the output spent
+    pub vout: u32,
+
+    /// The input sequence number
+    pub sequence: u32,
+
+    /// Whether the input is a peg-in
+    pub is_pegin: bool,
+
+    /// Whether the input has an issuance or a reissuance
+    pub has_issuance: bool,
+
+    /// The asset of the output spent, if explicit or unblindable
+    pub asset: Option<String>,
+
+    /// The amount in satoshi of the output spent, if explicit or unblindable
+    pub satoshi: Option<u64>,
+}
+
+/// A decoded transaction output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DecodedOutput {
+    /// The output script pubkey in hex format
+    pub script_pubkey: String,
+
+    /// The output address, if the script pubkey has an address form
+    ///
+    /// For PSET outputs with a blinding key this is the confidential address.
+    pub address: Option<String>,
+
+    /// Whether the output is blinded or is going to be blinded
+    pub is_confidential: bool,
+
+    /// Whether the output is a fee output
+    pub is_fee: bool,
+
+    /// The output asset, if explicit or unblindable
+    pub asset: Option<String>,
+
+    /// The output amount in satoshi, if explicit or unblindable
+    pub satoshi: Option<u64>,
+}
+
+/// A decoded transaction or PSET
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DecodedTx {
+    /// The transaction id
+    ///
+    /// For PSETs this is the id of the unsigned transaction.
+    pub txid: String,
+
+    /// The transaction version
+    pub version: u32,
+
+    /// The transaction locktime
+    pub lock_time: u32,
+
+    /// The transaction inputs
+    pub inputs: Vec<DecodedInput>,
+
+    /// The transaction outputs
+    pub outputs: Vec<DecodedOutput>,
+
+    /// The fee in satoshi of the policy asset
+    pub fee: u64,
+}
+
+/// A portable backup of a wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WalletBackup {
+    /// The backup in JSON, containing the descriptor, the birthday and the memos, but no key
+    pub backup: String,
+}
+
+/// A PSET being signed by the cosigners of a multisig wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PsetSession {
+    /// The session in JSON format, to be shared with the cosigners
+    pub session: String,
+
+    /// The PSET with the signatures collected so far, in base64 format
+    pub pset: String,
+
+    /// The state of the session: "waiting_signatures", "ready_to_finalize" or "finalized"
+    pub state: String,
+
+    /// The fingerprints of the cosigners
+    pub participants: Vec<String>,
+
+    /// The number of signatures needed for each input
+    pub threshold: u32,
+
+    /// For each cosigner that signed, the indexes of the inputs signed
+    pub signatures: HashMap<String, Vec<usize>>,
+
+    /// The fingerprints of the cosigners that have not signed yet
+    pub missing: Vec<String>,
+}
+
+/// An event pushed by the server to the clients of the `/events` endpoint
+///
+/// Sent as a server-sent event whose type is the `type` field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A transaction of a wallet has been seen for the first time
+    NewTx {
+        /// The name of the wallet
+        name: String,
+
+        /// The transaction id
+        txid: String,
+
+        /// The height of the block including the transaction, none if unconfirmed
+        height: Option<u32>,
+    },
+
+    /// An unconfirmed transaction of a wallet has been included in a block
+    Confirmation {
+        /// The name of the wallet
+        name: String,
+
+        /// The transaction id
+        txid: String,
+
+        /// The height of the block including the transaction
+        height: u32,
+    },
+
+    /// An unconfirmed transaction of a wallet has been double spent by another transaction, it
+    /// is removed from the wallet transactions and balance
+    TxConflicted {
+        /// The name of the wallet
+        name: String,
+
+        /// The id of the conflicted transaction
+        txid: String,
+
+        /// The id of the transaction spending the same outputs
+        conflicting_txid: String,
+    },
+
+    /// Blocks including transactions of a wallet have been replaced, the confirmations of the
+    /// transactions in these blocks have been verified again
+    Reorg {
+        /// The name of the wallet
+        name: String,
+
+        /// The number of blocks replaced, as observed by the wallet
+        depth: u32,
+    },
+
+    /// An incoming transaction of a wallet has been seen for the first time, sent to the webhooks
+    Deposit {
+        /// The name of the wallet
+        name: String,
+
+        /// The transaction id
+        txid: String,
+
+        /// The height of the block including the transaction, none if unconfirmed
+        height: Option<u32>,
+
+        /// The amounts received, by asset id
+        balance: HashMap<String, i64>,
+    },
+
+    /// An incoming transaction of a wallet has reached the confirmations required by a webhook
+    DepositConfirmed {
+        /// The name of the wallet
+        name: String,
+
+        /// The transaction id
+        txid: String,
+
+        /// The height of the block including the transaction
+        height: u32,
+
+        /// The confirmations reached, as configured in the webhook
+        confirmations: u32,
+
+        /// The amounts received, by asset id
+        balance: HashMap<String, i64>,
+    },
+
+    /// An outgoing transaction of a wallet has been included in a block, sent to the webhooks
+    WithdrawalConfirmed {
+        /// The name of the wallet
+        name: String,
+
+        /// The transaction id
+        txid: String,
+
+        /// The height of the block including the transaction
+        height: u32,
+
+        /// The net amounts sent, negative, by asset id, including the fee
+        balance: HashMap<String, i64>,
+    },
+
+    /// The blockchain tip changed
+    Tip {
+        /// The height of the new tip
+        height: u32,
+
+        /// The hash of the new tip
+        hash: String,
+    },
+}
+
+impl Event {
+    /// The type of the event
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::NewTx { .. } => "new_tx",
+            Event::Confirmation { .. } => "confirmation",
+            Event::TxConflicted { .. } => "tx_conflicted",
+            Event::Reorg { .. } => "reorg",
+            Event::Deposit { .. } => "deposit",
+            Event::DepositConfirmed { .. } => "deposit_confirmed",
+            Event::WithdrawalConfirmed { .. } => "withdrawal_confirmed",
+            Event::Tip { .. } => "tip",
+        }
+    }
+}
+
 impl std::fmt::Display for WalletType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -469,6 +1024,64 @@ impl std::fmt::Display for WalletType {
             WalletType::WshMulti(threshold, num_pubkeys) => {
                 write!(f, "wsh_multi_{}of{}", threshold, num_pubkeys)
             }
+            WalletType::WshSortedMulti(threshold, num_pubkeys) => {
+                write!(f, "wsh_sortedmulti_{}of{}", threshold, num_pubkeys)
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use serde::de::DeserializeOwned;
+
+    use crate::response::*;
+
+    fn roundtrip<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(value: T) {
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<T>(&json).unwrap(), value);
+        let cbor = serde_cbor::to_vec(&value).unwrap();
+        assert_eq!(serde_cbor::from_slice::<T>(&cbor).unwrap(), value);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip(Empty {});
+        roundtrip(AssetContract {
+            entity: Entity {
+                domain: "example.com".to_string(),
+            },
+            issuer_pubkey: "02".repeat(33),
+            name: "Asset".to_string(),
+            precision: 2,
+            ticker: "ASS".to_string(),
+            version: 0,
+        });
+        let balance = WalletBalance {
+            balance: HashMap::from([("aa".repeat(32), -5)]),
+            display: None,
+        };
+        roundtrip(balance.clone());
+        roundtrip(WalletBalance {
+            display: Some(HashMap::from([("aa".repeat(32), "-0.05 ASS".to_string())])),
+            ..balance
+        });
+        roundtrip(WalletType::WshMulti(2, 3));
+        roundtrip(Event::NewTx {
+            name: "w".to_string(),
+            txid: "bb".repeat(32),
+            height: None,
+        });
+        roundtrip(Event::Tip {
+            height: 1,
+            hash: "cc".repeat(32),
+        });
+        roundtrip(Event::DepositConfirmed {
+            name: "w".to_string(),
+            txid: "bb".repeat(32),
+            height: 10,
+            confirmations: 2,
+            balance: HashMap::from([("aa".repeat(32), 1_000)]),
+        });
+    }
+}
@@ -13,7 +13,7 @@ pub use bip39;
 use elements_miniscript::bitcoin::bip32::{self, DerivationPath, Fingerprint};
 use elements_miniscript::elements::bitcoin::bip32::Xpub;
 use elements_miniscript::elements::pset::PartiallySignedTransaction;
-use lwk_common::Signer;
+use lwk_common::{SignCapability, Signer};
 
 /// Possible errors when signing with [`AnySigner`]
 #[derive(thiserror::Error, Debug)]
@@ -31,6 +31,9 @@ pub enum SignerError {
 
     #[error(transparent)]
     Bip32Error(#[from] bip32::Error),
+
+    #[error("The signer is not part of the wallet spending the PSET inputs")]
+    SignerNotInWallet,
 }
 
 /// A signer that can be a software signer [`SwSigner`] or a [`lwk_jade::Jade`]
@@ -74,6 +77,9 @@ impl Signer for &AnySigner {
     type Error = SignerError;
 
     fn sign(&self, pset: &mut PartiallySignedTransaction) -> Result<u32, Self::Error> {
+        if self.can_sign(pset)? == SignCapability::NotInWallet {
+            return Err(SignerError::SignerNotInWallet);
+        }
         Ok(match self {
             AnySigner::Software(signer) => signer.sign(pset)?,
 
@@ -115,11 +121,18 @@ impl Signer for &AnySigner {
         Ok(match self {
             AnySigner::Software(s) => s.fingerprint(),
 
+            // The fingerprint is the first 4 bytes of the identifier, no need to ask the device
             #[cfg(feature = "jade")]
-            AnySigner::Jade(s, _) => s.fingerprint()?,
+            AnySigner::Jade(_, id) => id_to_fingerprint(id),
 
             #[cfg(feature = "ledger")]
-            AnySigner::Ledger(s, _) => s.fingerprint()?,
+            AnySigner::Ledger(_, id) => id_to_fingerprint(id),
         })
     }
 }
+
+#[cfg(any(feature = "jade", feature = "ledger"))]
+fn id_to_fingerprint(id: &elements_miniscript::bitcoin::XKeyIdentifier) -> Fingerprint {
+    let bytes: [u8; 4] = id[0..4].try_into().expect("identifier is 20 bytes");
+    Fingerprint::from(bytes)
+}
@@ -22,7 +22,7 @@ use elements_miniscript::{
     psbt::PsbtExt,
     slip77::MasterBlindingKey,
 };
-use lwk_common::Signer;
+use lwk_common::{SignCapability, Signer};
 
 /// Possible errors when signing with the software signer [`SwSigner`]
 #[derive(thiserror::Error, Debug)]
@@ -44,6 +44,9 @@ pub enum SignError {
 
     #[error("Cannot derive slip77 key (mnemonic/seed not available)")]
     DeterministicSlip77NotAvailable,
+
+    #[error("The signer is not part of the wallet spending the PSET inputs")]
+    SignerNotInWallet,
 }
 
 /// Possible errors when creating a new software signer [`SwSigner`]
@@ -197,6 +200,9 @@ impl Signer for SwSigner {
     type Error = SignError;
 
     fn sign(&self, pset: &mut PartiallySignedTransaction) -> Result<u32, Self::Error> {
+        if self.can_sign(pset)? == SignCapability::NotInWallet {
+            return Err(SignError::SignerNotInWallet);
+        }
         let tx = pset.extract_tx()?;
         let mut sighash_cache = SighashCache::new(&tx);
         let mut signature_added = 0;
@@ -330,6 +336,32 @@ mod tests {
         assert!(sig_low_r.len() < sig_no_grind.len());
     }
 
+    #[test]
+    fn test_can_sign() {
+        let signer = SwSigner::new(lwk_test_util::TEST_MNEMONIC, false).unwrap();
+        let b64 = include_str!("../../lwk_jade/test_data/pset_to_be_signed.base64");
+        let pset: PartiallySignedTransaction = b64.parse().unwrap();
+        assert_eq!(signer.can_sign(&pset).unwrap(), SignCapability::Full);
+
+        // Another signer is not part of the wallet
+        let (other, _) = SwSigner::random(false).unwrap();
+        assert_eq!(other.can_sign(&pset).unwrap(), SignCapability::NotInWallet);
+        let mut unsigned = pset.clone();
+        let err = other.sign(&mut unsigned).unwrap_err();
+        assert!(matches!(err, SignError::SignerNotInWallet));
+        assert_eq!(unsigned, pset);
+
+        // An input without derivations from the signer
+        let mut partial = pset.clone();
+        let mut input = partial.inputs()[0].clone();
+        input.bip32_derivation.clear();
+        partial.add_input(input);
+        assert_eq!(
+            signer.can_sign(&partial).unwrap(),
+            SignCapability::Partial(vec![0])
+        );
+    }
+
     #[test]
     fn test_sign_verify() {
         let signer = SwSigner::new(lwk_test_util::TEST_MNEMONIC, true).unwrap();
@@ -346,4 +378,42 @@ mod tests {
         // result checked also with bitcoin-cli
         // bitcoin-cli verifymessage "1BZ9j3F7m4H1RPyeDp5iFwpR31SB6zrs19" "Hwlg40qLYZXEj9AoA3oZpfJMJPxaXzBL0+siHAJRhTIvSFiwSdtCsqxqB7TxgWfhqIr/YnGE4nagWzPchFJElTo=" 'Hello, world!'
     }
+
+    #[test]
+    fn test_preview() {
+        use elements_miniscript::elements::confidential;
+
+        let signer = SwSigner::new(lwk_test_util::TEST_MNEMONIC, false).unwrap();
+        let b64 = include_str!("../../lwk_jade/test_data/pset_to_be_signed.base64");
+        let mut pset: PartiallySignedTransaction = b64.parse().unwrap();
+
+        // The input is blinded with a key other than the slip77 one of the signer
+        let preview = signer.preview(&pset).unwrap();
+        assert!(!preview.complete);
+        assert_eq!(preview.inputs, vec![0]);
+        assert_eq!(preview.fee, Some(250));
+        let mine: Vec<_> = preview.outputs.iter().filter(|o| o.is_mine).collect();
+        assert_eq!(mine.len(), 2);
+        assert!(mine.iter().all(|o| o.confidential && o.value.is_some()));
+        assert!(preview.outputs[2].is_fee);
+        assert!(!preview.outputs[2].confidential);
+
+        // With the input amount known the preview is complete
+        let asset = preview.outputs[0].asset.unwrap();
+        let utxo = pset.inputs_mut()[0].witness_utxo.as_mut().unwrap();
+        utxo.asset = confidential::Asset::Explicit(asset);
+        utxo.value = confidential::Value::Explicit(1_000_000);
+        let preview = signer.preview(&pset).unwrap();
+        assert!(preview.complete);
+        assert_eq!(preview.balances[&asset], -250);
+
+        // Another signer has nothing to sign
+        let (other, _) = SwSigner::random(false).unwrap();
+        let preview = other.preview(&pset).unwrap();
+        assert!(preview.inputs.is_empty());
+        assert!(preview.balances.is_empty());
+        assert!(preview.outputs.iter().all(|o| !o.is_mine));
+
+        assert_eq!(signer.sign(&mut pset).unwrap(), 1);
+    }
 }
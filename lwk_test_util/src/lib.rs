@@ -1,3 +1,22 @@
+//! Test utilities for crates using the Liquid Wallet Kit
+//!
+//! [`TestElectrumServer`] runs a throwaway Liquid regtest network, made of an `elementsd` node
+//! and an `electrs` server, which can be used to write integration tests: fund addresses with
+//! L-BTC or newly issued assets and mine blocks.
+//!
+//! The executables are taken from the `ELEMENTSD_EXEC` and `ELECTRS_LIQUID_EXEC` environment
+//! variables (and `BITCOIND_EXEC` for pegins).
+//!
+//! ```no_run
+//! let server = lwk_test_util::setup();
+//! let address = server.elementsd_getnewaddress();
+//! server.fund(&address, 100_000, None);
+//! let asset = server.issue_and_fund(&address, 1_000);
+//! server.mine(10);
+//! let electrum_url = server.electrum_url();
+//! # let _ = (asset, electrum_url);
+//! ```
+
 use electrsd::bitcoind::BitcoinD;
 use elements_miniscript::elements::{self, BlockHeader};
 
@@ -235,24 +254,68 @@ impl TestElectrumServer {
         let electrs = electrsd::ElectrsD::with_conf(electrs_exec, &node, &conf).unwrap();
 
         elementsd_generate(&node.client, 100);
-        electrs.trigger().unwrap();
-
-        let mut i = 120;
-        loop {
-            assert!(i > 0, "1 minute without updates");
-            i -= 1;
-            let height = electrs.client.block_headers_subscribe_raw().unwrap().height;
-            if height == 101 {
-                break;
-            }
-            thread::sleep(Duration::from_millis(500));
-        }
 
-        Self {
+        let server = Self {
             elementsd: node,
             electrs,
             bitcoind,
+        };
+        server.wait_for_height(101);
+        server
+    }
+
+    // helpers for integration tests
+
+    /// The url of the electrum server
+    pub fn electrum_url(&self) -> String {
+        self.electrs.electrum_url.clone()
+    }
+
+    /// The url of the esplora server including the scheme, if the server was started with it
+    pub fn esplora_url(&self) -> Option<String> {
+        self.electrs
+            .esplora_url
+            .as_ref()
+            .map(|url| format!("http://{url}"))
+    }
+
+    /// Wait until the electrum server indexed the block at `height`
+    pub fn wait_for_height(&self, height: u64) {
+        self.electrs.trigger().unwrap();
+        for _ in 0..120 {
+            let tip = self
+                .electrs
+                .client
+                .block_headers_subscribe_raw()
+                .unwrap()
+                .height;
+            if tip as u64 >= height {
+                return;
+            }
+            thread::sleep(Duration::from_millis(500));
         }
+        panic!("1 minute without reaching height {height}");
+    }
+
+    /// Mine `blocks` and wait until the electrum server indexed them
+    pub fn mine(&self, blocks: u32) {
+        let height = self.elementsd_height();
+        self.elementsd_generate(blocks);
+        self.wait_for_height(height + blocks as u64);
+    }
+
+    /// Send `satoshi` of `asset` (L-BTC if `None`) to `address` and confirm the transaction
+    pub fn fund(&self, address: &Address, satoshi: u64, asset: Option<AssetId>) -> Txid {
+        let txid = self.elementsd_sendtoaddress(address, satoshi, asset);
+        self.mine(1);
+        txid
+    }
+
+    /// Issue `satoshi` units of a new asset, send them to `address` and confirm the transaction
+    pub fn issue_and_fund(&self, address: &Address, satoshi: u64) -> AssetId {
+        let asset = self.elementsd_issueasset(satoshi);
+        self.fund(address, satoshi, Some(asset));
+        asset
     }
 
     // methods on elementsd
@@ -1,7 +1,9 @@
-use std::{num::NonZeroU8, path::PathBuf};
+use std::{collections::HashMap, num::NonZeroU8, path::PathBuf, time::Duration};
 
 use tiny_http::Header;
 
+use crate::{Events, Metrics, RateLimit};
+
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Additional headers to add to GET and OPTIONS requests.
@@ -10,6 +12,24 @@ pub struct Config {
     pub num_threads: NonZeroU8,
     /// The path to serve HTTP GET requests from.
     pub serve_dir: Option<PathBuf>,
+    /// The events to stream to the clients of the `/events` endpoint.
+    pub events: Option<Events>,
+    /// The metrics to expose at the `/metrics` endpoint.
+    pub metrics: Option<Metrics>,
+    /// The JSON document describing the interface, served at the `/schema` endpoint.
+    pub schema: Option<String>,
+    /// The maximum rate of JSON-RPC requests of each client, unlimited if `None`.
+    pub rate_limit: Option<RateLimit>,
+    /// The maximum size in bytes of the body of JSON-RPC requests, unlimited if `None`.
+    pub max_body_size: Option<usize>,
+    /// How long clients wait for the response of the given methods, then they get an error.
+    /// Only one call at a time runs for these methods, the others are rejected until it ends.
+    pub method_timeouts: HashMap<String, Duration>,
+    /// The bearer tokens accepted for JSON-RPC requests and for the `/events`, `/metrics` and
+    /// `/schema` endpoints, any request is accepted if empty and there are no `tenant_tokens`.
+    pub auth_tokens: Vec<String>,
+    /// Bearer tokens identifying a tenant, by token, given to the handler in [`crate::Request::tenant`].
+    pub tenant_tokens: HashMap<String, String>,
 }
 
 impl Config {
@@ -24,6 +44,14 @@ impl Default for Config {
             headers: Vec::new(),
             num_threads: NonZeroU8::new(4).expect("non-zero"),
             serve_dir: None,
+            events: None,
+            metrics: None,
+            schema: None,
+            rate_limit: None,
+            max_body_size: None,
+            method_timeouts: HashMap::new(),
+            auth_tokens: Vec::new(),
+            tenant_tokens: HashMap::new(),
         }
     }
 }
@@ -32,6 +60,14 @@ pub struct ConfigBuilder {
     headers: Vec<Header>,
     num_threads: NonZeroU8,
     serve_dir: Option<PathBuf>,
+    events: Option<Events>,
+    metrics: Option<Metrics>,
+    schema: Option<String>,
+    rate_limit: Option<RateLimit>,
+    max_body_size: Option<usize>,
+    method_timeouts: HashMap<String, Duration>,
+    auth_tokens: Vec<String>,
+    tenant_tokens: HashMap<String, String>,
 }
 
 impl ConfigBuilder {
@@ -54,11 +90,59 @@ impl ConfigBuilder {
         self
     }
 
+    pub fn with_events(mut self, events: Option<Events>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Option<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn with_schema(mut self, schema: Option<String>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: Option<RateLimit>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    pub fn with_max_body_size(mut self, max_body_size: Option<usize>) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    pub fn with_method_timeouts(mut self, method_timeouts: HashMap<String, Duration>) -> Self {
+        self.method_timeouts = method_timeouts;
+        self
+    }
+
+    pub fn with_auth_tokens(mut self, auth_tokens: Vec<String>) -> Self {
+        self.auth_tokens = auth_tokens;
+        self
+    }
+
+    pub fn with_tenant_tokens(mut self, tenant_tokens: HashMap<String, String>) -> Self {
+        self.tenant_tokens = tenant_tokens;
+        self
+    }
+
     pub fn build(self) -> Config {
         Config {
             headers: self.headers,
             num_threads: self.num_threads,
             serve_dir: self.serve_dir,
+            events: self.events,
+            metrics: self.metrics,
+            schema: self.schema,
+            rate_limit: self.rate_limit,
+            max_body_size: self.max_body_size,
+            method_timeouts: self.method_timeouts,
+            auth_tokens: self.auth_tokens,
+            tenant_tokens: self.tenant_tokens,
         }
     }
 }
@@ -69,6 +153,14 @@ impl Default for ConfigBuilder {
             headers: Vec::new(),
             num_threads: NonZeroU8::new(4).expect("non-zero"),
             serve_dir: None,
+            events: None,
+            metrics: None,
+            schema: None,
+            rate_limit: None,
+            max_body_size: None,
+            method_timeouts: HashMap::new(),
+            auth_tokens: Vec::new(),
+            tenant_tokens: HashMap::new(),
         }
     }
 }
@@ -44,6 +44,27 @@ pub enum InnerError {
 
     #[error("'jsonrpc' version should be '2.0'")]
     InvalidVersion,
+
+    #[error("Batch request must contain at least one request")]
+    EmptyBatch,
+
+    #[error("Invalid request object: {0}")]
+    InvalidRequest(serde_json::Error),
+
+    #[error("Too many requests, retry later")]
+    RateLimited,
+
+    #[error("Method '{0}' timed out")]
+    Timeout(String),
+
+    #[error("Method '{0}' is still running a previous call, retry later")]
+    Busy(String),
+
+    #[error("Missing or invalid authorization token")]
+    Unauthorized,
+
+    #[error("Request body is larger than {0} bytes")]
+    BodyTooLarge(usize),
 }
 
 impl From<String> for Error {
@@ -83,6 +104,13 @@ impl AsRpcError for InnerError {
             InnerError::WrongContentType => (WRONG_CONTENT_TYPE, None),
             InnerError::ReservedMethodPrefix => (METHOD_RESERVED, None),
             InnerError::InvalidVersion => (INVALID_VERSION, None),
+            InnerError::EmptyBatch => (INVALID_REQUEST, None),
+            InnerError::InvalidRequest(_) => (INVALID_REQUEST, None),
+            InnerError::RateLimited => (RATE_LIMITED, None),
+            InnerError::Timeout(_) => (TIMEOUT, None),
+            InnerError::Busy(_) => (BUSY, None),
+            InnerError::Unauthorized => (UNAUTHORIZED, None),
+            InnerError::BodyTooLarge(_) => (BODY_TOO_LARGE, None),
         };
 
         RpcError {
@@ -123,7 +151,7 @@ impl AsRpcError for Error {
 const PARSE_ERROR: i64 = -32_700;
 
 // -32600 	Invalid Request 	The JSON sent is not a valid Request object.
-// const INVALID_REQUEST: i64 = -32_600; // TODO if failing to parse the request object, try to parse as Value and if succesfull return this instead of PARSE_ERROR
+const INVALID_REQUEST: i64 = -32_600;
 
 // -32601 	Method not found 	The method does not exist / is not available.
 pub(crate) const METHOD_NOT_FOUND: i64 = -32_601;
@@ -142,6 +170,11 @@ const METHOD_RESERVED: i64 = -32_003;
 const INVALID_VERSION: i64 = -32_004;
 
 // GENERIC = -32_098, // TODO remove
+const RATE_LIMITED: i64 = -32_097;
+const TIMEOUT: i64 = -32_096;
+const UNAUTHORIZED: i64 = -32_095;
+const BUSY: i64 = -32_094;
+const BODY_TOO_LARGE: i64 = -32_093;
 const STOP_ERROR: i64 = -32_099;
 
 #[derive(Debug)]
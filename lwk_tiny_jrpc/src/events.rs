@@ -0,0 +1,97 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use serde_json::Value;
+use tiny_http::Header;
+
+/// How often a comment is sent to idle clients, to keep the connection open and detect
+/// disconnected clients
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Broadcasts server-sent events to the clients connected to the `/events` endpoint
+///
+/// Clone it to publish events from other threads, all the clones share the same clients.
+#[derive(Debug, Clone, Default)]
+pub struct Events {
+    subscribers: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send an event of the given type to every connected client
+    pub fn publish(&self, event: &str, data: &Value) {
+        let message = format!("event: {event}\ndata: {data}\n\n");
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            // clients that disconnected have dropped their receiver
+            subscribers.retain(|s| s.send(message.clone()).is_ok());
+        }
+    }
+
+    /// The number of connected clients
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.lock().map(|s| s.len()).unwrap_or(0)
+    }
+
+    fn subscribe(&self) -> Option<Receiver<String>> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().ok()?.push(sender);
+        Some(receiver)
+    }
+
+    /// Stream the events to the client of `http_request` until it disconnects or the server stops
+    ///
+    /// Blocks the calling thread.
+    pub(crate) fn stream(
+        &self,
+        http_request: tiny_http::Request,
+        headers: &[Header],
+        running: Arc<AtomicBool>,
+    ) {
+        let Some(receiver) = self.subscribe() else {
+            return;
+        };
+        let mut writer = http_request.into_writer();
+        let mut head = "HTTP/1.1 200 OK\r\n\
+            Content-Type: text/event-stream\r\n\
+            Cache-Control: no-cache\r\n\
+            Connection: close\r\n"
+            .to_string();
+        for header in headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", header.field, header.value));
+        }
+        head.push_str("\r\n");
+        if let Err(e) = write_flush(&mut writer, &head) {
+            log::debug!("events client disconnected: {}", e);
+            return;
+        }
+
+        loop {
+            let message = match receiver.recv_timeout(KEEP_ALIVE) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) if running.load(Ordering::SeqCst) => {
+                    ": keep-alive\n\n".to_string()
+                }
+                Err(_) => break,
+            };
+            if let Err(e) = write_flush(&mut writer, &message) {
+                log::debug!("events client disconnected: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+fn write_flush(writer: &mut impl Write, message: &str) -> std::io::Result<()> {
+    writer.write_all(message.as_bytes())?;
+    writer.flush()
+}
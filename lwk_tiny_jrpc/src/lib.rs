@@ -2,12 +2,14 @@
 #![cfg_attr(not(test), deny(clippy::unwrap_used))]
 
 use std::{
+    collections::{HashMap, HashSet},
     fmt::Display,
     fs::File,
     io::{ErrorKind, Read},
     str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
         Arc, Mutex,
     },
     thread::{self, JoinHandle},
@@ -17,6 +19,10 @@ use std::{
 pub use config::Config;
 pub use error::Error;
 use error::{AsRpcError, InnerError, METHOD_NOT_FOUND};
+pub use events::Events;
+pub use metrics::Metrics;
+pub use rate_limit::RateLimit;
+use rate_limit::RateLimiter;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
 use tiny_http::Server;
@@ -24,6 +30,9 @@ use tiny_http::{Header, Response as HttpResponse};
 
 pub mod config;
 pub mod error;
+pub mod events;
+pub mod metrics;
+pub mod rate_limit;
 
 // re-export
 pub use tiny_http;
@@ -37,10 +46,13 @@ pub struct JsonRpcServer {
 
 impl JsonRpcServer {
     /// Creates and runs a new JSON RPC Server.
-    pub fn new<F, T>(server: Server, config: Config, state: Arc<Mutex<T>>, func: F) -> Self
+    ///
+    /// The `state` is shared by the threads handling the requests, state modified by `func`
+    /// needs interior mutability, for instance a [`Mutex`].
+    pub fn new<F, T>(server: Server, config: Config, state: Arc<T>, func: F) -> Self
     where
-        F: Fn(Request, Arc<Mutex<T>>) -> Result<Response, Error> + Clone + Send + Sync + 'static,
-        T: Send + 'static,
+        F: Fn(Request, Arc<T>) -> Result<Response, Error> + Clone + Send + Sync + 'static,
+        T: Send + Sync + 'static,
     {
         Self::run(Arc::new(server), config, state, func)
     }
@@ -60,13 +72,15 @@ impl JsonRpcServer {
         &self.config
     }
 
-    fn run<F, T>(server: Arc<Server>, config: Config, state: Arc<Mutex<T>>, func: F) -> Self
+    fn run<F, T>(server: Arc<Server>, config: Config, state: Arc<T>, func: F) -> Self
     where
-        F: Fn(Request, Arc<Mutex<T>>) -> Result<Response, Error> + Clone + Send + Sync + 'static,
-        T: Send + 'static,
+        F: Fn(Request, Arc<T>) -> Result<Response, Error> + Clone + Send + Sync + 'static,
+        T: Send + Sync + 'static,
     {
         let mut handles = Vec::with_capacity(4);
         let running = Arc::new(AtomicBool::new(true));
+        let limiter = config.rate_limit.map(RateLimiter::new);
+        let busy = Arc::new(Mutex::new(HashSet::new()));
 
         for _ in 0..config.num_threads.get() {
             let server = server.clone();
@@ -74,6 +88,8 @@ impl JsonRpcServer {
             let state = state.clone();
             let running = running.clone();
             let config = config.clone();
+            let limiter = limiter.clone();
+            let busy = busy.clone();
             let handle = thread::spawn(move || {
                 loop {
                     // receive http request
@@ -96,6 +112,57 @@ impl JsonRpcServer {
 
                     // check request method
                     match http_request.method() {
+                        tiny_http::Method::Get
+                            if AUTHORIZED_PATHS.contains(&http_request.url())
+                                && !authorize_get(&http_request, &config) =>
+                        {
+                            log::debug!("unauthorized {:?}", http_request.remote_addr());
+                            let message = "401: Unauthorized";
+                            let response = HttpResponse::from_string(message).with_status_code(401);
+                            send_http_response(http_request, response, message);
+                        }
+                        tiny_http::Method::Get if http_request.url() == "/events" => {
+                            let Some(events) = config.events.clone() else {
+                                let message = "404: No events defined in server config";
+                                let response =
+                                    HttpResponse::from_string(message).with_status_code(404);
+                                send_http_response(http_request, response, message);
+                                continue;
+                            };
+                            // the stream is long lived, don't block this server thread
+                            let headers = config.headers.clone();
+                            let running = running.clone();
+                            thread::spawn(move || events.stream(http_request, &headers, running));
+                        }
+                        tiny_http::Method::Get if http_request.url() == "/metrics" => {
+                            let Some(metrics) = config.metrics.as_ref() else {
+                                let message = "404: No metrics defined in server config";
+                                let response =
+                                    HttpResponse::from_string(message).with_status_code(404);
+                                send_http_response(http_request, response, message);
+                                continue;
+                            };
+                            let content_type =
+                                Header::from_str("Content-Type: text/plain; version=0.0.4")
+                                    .expect("valid header");
+                            let response = HttpResponse::from_string(metrics.render())
+                                .with_header(content_type);
+                            send_http_response(http_request, response, "Metrics");
+                        }
+                        tiny_http::Method::Get if http_request.url() == "/schema" => {
+                            let Some(schema) = config.schema.as_ref() else {
+                                let message = "404: No schema defined in server config";
+                                let response =
+                                    HttpResponse::from_string(message).with_status_code(404);
+                                send_http_response(http_request, response, message);
+                                continue;
+                            };
+                            let content_type = Header::from_str("Content-Type: application/json")
+                                .expect("valid header");
+                            let response =
+                                HttpResponse::from_string(schema).with_header(content_type);
+                            send_http_response(http_request, response, "Schema");
+                        }
                         tiny_http::Method::Get => {
                             // respond to the http GET request
                             let Some(mut path) = config.serve_dir.clone() else {
@@ -169,34 +236,64 @@ impl JsonRpcServer {
                             send_http_response(http_request, response, message);
                         }
                         tiny_http::Method::Post => {
-                            // validate/parse the jsonrpc POST request
-                            let response = match validate_jsonrpc_request(&mut http_request) {
-                                Ok(request) => {
-                                    // handle the request
-                                    let id = request.id.clone();
-                                    match handle_jsonrpc_request(
-                                        request,
-                                        state.clone(),
-                                        func.clone(),
-                                    ) {
-                                        Ok(response) => response,
-                                        Err(Error::Stop) => {
-                                            running.store(false, Ordering::SeqCst);
-                                            Response::from_error(id, Error::Stop)
-                                        }
-                                        Err(err) => Response::from_error(id, err),
-                                    }
+                            // validate/parse the jsonrpc POST request, an array is a batch
+                            let body =
+                                validate_jsonrpc_request(&mut http_request, config.max_body_size);
+                            let cost = match &body {
+                                Ok(Value::Array(requests)) => requests.len().max(1),
+                                _ => 1,
+                            };
+                            let limited = match (&limiter, http_request.remote_addr()) {
+                                (Some(limiter), Some(addr)) => !limiter.check(addr.ip(), cost),
+                                _ => false,
+                            };
+                            let tenant = authorize(&http_request, &config);
+                            let authorized = tenant.is_some();
+                            let process = |request| {
+                                to_value(process_request(
+                                    request,
+                                    tenant.clone().flatten(),
+                                    state.clone(),
+                                    func.clone(),
+                                    &running,
+                                    &config.method_timeouts,
+                                    &busy,
+                                ))
+                            };
+                            let response = match body {
+                                _ if !authorized => {
+                                    log::debug!("unauthorized {:?}", http_request.remote_addr());
+                                    to_value(Response::from_error(None, InnerError::Unauthorized))
+                                }
+                                _ if limited => {
+                                    log::debug!("rate limited {:?}", http_request.remote_addr());
+                                    to_value(Response::from_error(None, InnerError::RateLimited))
+                                }
+                                Ok(Value::Array(requests)) if requests.is_empty() => {
+                                    to_value(Response::from_error(None, InnerError::EmptyBatch))
+                                }
+                                Ok(Value::Array(requests)) => {
+                                    Value::Array(requests.into_iter().map(process).collect())
                                 }
+                                Ok(request) => process(request),
                                 Err(err) => {
                                     // no id since we couldn't validate the request...
-                                    Response::from_error(None, err)
+                                    to_value(Response::from_error(None, err))
                                 }
                             };
 
                             // send the response
-                            if let Err(err) =
-                                send_jsonrpc_response(http_request, response, &config.headers)
-                            {
+                            let status = match (authorized, limited) {
+                                (false, _) => 401,
+                                (true, true) => 429,
+                                (true, false) => 200,
+                            };
+                            if let Err(err) = send_jsonrpc_response(
+                                http_request,
+                                response,
+                                status,
+                                &config.headers,
+                            ) {
                                 log::error!("send_response error: {}", err);
                             }
                         }
@@ -240,6 +337,9 @@ impl JsonRpcServer {
     }
 }
 
+/// The GET endpoints requiring the same authorization as the JSON RPC requests
+const AUTHORIZED_PATHS: [&str; 3] = ["/events", "/metrics", "/schema"];
+
 // sends the response and debug logs the status code and message, or logs the error.
 fn send_http_response<R>(http_request: tiny_http::Request, response: HttpResponse<R>, message: &str)
 where
@@ -256,12 +356,70 @@ where
     }
 }
 
-fn validate_jsonrpc_request(http_request: &mut tiny_http::Request) -> Result<Request, InnerError> {
+/// The tenant of the request, `Some(None)` for requests not of a tenant, `None` if unauthorized
+///
+/// Requests are authorized if no tokens are required or if they have one of them as
+/// `Authorization: Bearer` token.
+fn authorize(http_request: &tiny_http::Request, config: &Config) -> Option<Option<String>> {
+    let tokens: Vec<&str> = http_request
+        .headers()
+        .iter()
+        .filter(|h| h.field.equiv("Authorization"))
+        .filter_map(|h| h.value.as_str().trim().strip_prefix("Bearer "))
+        .map(str::trim)
+        .collect();
+    let tenant = tokens.iter().find_map(|t| {
+        config
+            .tenant_tokens
+            .iter()
+            .find(|(token, _)| token_eq(token, t))
+            .map(|(_, tenant)| tenant)
+    });
+    if let Some(tenant) = tenant {
+        return Some(Some(tenant.clone()));
+    }
+    let open = config.auth_tokens.is_empty() && config.tenant_tokens.is_empty();
+    let authorized = tokens
+        .iter()
+        .any(|t| config.auth_tokens.iter().any(|a| token_eq(a, t)));
+    (open || authorized).then_some(None)
+}
+
+/// Whether the GET request of one of the [`AUTHORIZED_PATHS`] is authorized
+///
+/// The events and the metrics are of the whole server, they are not available to tenants.
+fn authorize_get(http_request: &tiny_http::Request, config: &Config) -> bool {
+    match authorize(http_request, config) {
+        None => false,
+        Some(Some(_)) => http_request.url() == "/schema",
+        Some(None) => true,
+    }
+}
+
+/// Compare the tokens in constant time, not to leak how many leading bytes of a guess are right
+fn token_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+fn validate_jsonrpc_request(
+    http_request: &mut tiny_http::Request,
+    max_body_size: Option<usize>,
+) -> Result<Value, InnerError> {
+    // do not log the authorization tokens
+    let headers: Vec<_> = http_request
+        .headers()
+        .iter()
+        .filter(|h| !h.field.equiv("Authorization"))
+        .collect();
     log::debug!(
         "received request - method: {:?}, url: {:?}, headers: {:?}",
         http_request.method(),
         http_request.url(),
-        http_request.headers()
+        headers
     );
 
     // check content-type header exists
@@ -282,23 +440,131 @@ fn validate_jsonrpc_request(http_request: &mut tiny_http::Request) -> Result<Req
         return Err(InnerError::WrongContentType);
     }
 
-    // parse json into request
+    // read at most one byte more than allowed, to tell a body of exactly the maximum size apart
     let mut s = String::new(); // todo: performance
-    http_request.as_reader().read_to_string(&mut s)?;
+    match max_body_size {
+        Some(max) => {
+            let limit = u64::try_from(max).unwrap_or(u64::MAX).saturating_add(1);
+            http_request
+                .as_reader()
+                .take(limit)
+                .read_to_string(&mut s)?;
+            if s.len() > max {
+                return Err(InnerError::BodyTooLarge(max));
+            }
+        }
+        None => {
+            http_request.as_reader().read_to_string(&mut s)?;
+        }
+    }
+
+    // parse json, the request objects are parsed later since this could be a batch
+
+    Ok(serde_json::from_str(&s)?)
+}
+
+// parses and handles a single request, of a batch or not, stopping the server on Error::Stop
+fn process_request<F, T>(
+    request: Value,
+    tenant: Option<String>,
+    state: Arc<T>,
+    func: F,
+    running: &AtomicBool,
+    timeouts: &HashMap<String, Duration>,
+    busy: &Arc<Mutex<HashSet<String>>>,
+) -> Response
+where
+    F: Fn(Request, Arc<T>) -> Result<Response, Error> + Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+{
+    // the JSON is valid, so failing here means it's not a request object
+    let id = request
+        .get("id")
+        .and_then(|id| serde_json::from_value(id.clone()).ok());
+    let mut request: Request = match serde_json::from_value(request) {
+        Ok(request) => request,
+        Err(err) => return Response::from_error(id, InnerError::InvalidRequest(err)),
+    };
+    request.tenant = tenant;
+    let id = request.id.clone();
+    let result = match timeouts.get(&request.method) {
+        None => handle_jsonrpc_request(request, state, func),
+        Some(timeout) => {
+            // the handler can't be interrupted, if it times out it keeps running in background
+            // and its result is discarded. Calls to the same method are rejected until it ends,
+            // so that timed out handlers don't pile up waiting on the state lock.
+            let method = request.method.clone();
+            let guard = match BusyGuard::new(busy.clone(), &method) {
+                Some(guard) => guard,
+                None => return Response::from_error(id, InnerError::Busy(method)),
+            };
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let result = handle_jsonrpc_request(request, state, func);
+                drop(guard);
+                sender.send(result)
+            });
+            match receiver.recv_timeout(*timeout) {
+                Ok(result) => result,
+                Err(RecvTimeoutError::Timeout) => {
+                    log::warn!("{} timed out after {:?}", method, timeout);
+                    Err(Error::Inner(InnerError::Timeout(method)))
+                }
+                Err(RecvTimeoutError::Disconnected) => Err(format!("{method} panicked").into()),
+            }
+        }
+    };
+    match result {
+        Ok(response) => response,
+        Err(Error::Stop) => {
+            running.store(false, Ordering::SeqCst);
+            Response::from_error(id, Error::Stop)
+        }
+        Err(err) => Response::from_error(id, err),
+    }
+}
 
-    let request: Request = serde_json::from_str(&s)?;
+// marks a method as running, until dropped
+struct BusyGuard {
+    busy: Arc<Mutex<HashSet<String>>>,
+    method: String,
+}
 
-    Ok(request)
+impl BusyGuard {
+    // returns None if the method is already running
+    fn new(busy: Arc<Mutex<HashSet<String>>>, method: &str) -> Option<Self> {
+        let inserted = busy
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(method.to_string());
+        inserted.then(|| Self {
+            busy,
+            method: method.to_string(),
+        })
+    }
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        self.busy
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.method);
+    }
+}
+
+fn to_value(response: Response) -> Value {
+    serde_json::to_value(response).expect("response is serializable")
 }
 
 fn handle_jsonrpc_request<F, T>(
     request: Request,
-    state: Arc<Mutex<T>>,
+    state: Arc<T>,
     process: F,
 ) -> Result<Response, Error>
 where
-    F: Fn(Request, Arc<Mutex<T>>) -> Result<Response, Error> + Clone + Send + Sync + 'static,
-    T: Send + 'static,
+    F: Fn(Request, Arc<T>) -> Result<Response, Error> + Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
 {
     // check jsonrpc version
     if request.jsonrpc.as_str() != "2.0" {
@@ -327,11 +593,12 @@ where
 
 fn send_jsonrpc_response(
     request: tiny_http::Request,
-    response: Response,
+    response: Value,
+    status: u16,
     headers: &[Header],
 ) -> Result<(), InnerError> {
     let data = serde_json::to_string(&response)?;
-    let mut response = HttpResponse::from_string(data);
+    let mut response = HttpResponse::from_string(data).with_status_code(status);
     for header in headers.iter() {
         response.add_header(header.clone());
     }
@@ -344,6 +611,10 @@ pub struct Request {
     pub id: Option<Id>,
     pub method: String,
     pub params: Option<Value>,
+
+    /// The tenant identified by the bearer token of the request, see [`Config::tenant_tokens`]
+    #[serde(skip)]
+    pub tenant: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -444,6 +715,12 @@ mod test {
                 result: request.params,
                 error: None,
             },
+            "tenant" => Response::result(request.id, json!(request.tenant)),
+            "sleep" => {
+                let millis = request.params.as_ref().and_then(Value::as_u64).unwrap();
+                thread::sleep(Duration::from_millis(millis));
+                Response::result(request.id, Value::Null)
+            }
             _ => unimplemented!(),
         };
         Ok(response)
@@ -459,6 +736,14 @@ mod test {
         response
     }
 
+    #[test]
+    fn token_comparison() {
+        assert!(token_eq("secret", "secret"));
+        assert!(!token_eq("secret", "secreT"));
+        assert!(!token_eq("secret", "secret2"));
+        assert!(!token_eq("", "secret"));
+    }
+
     fn assert_response_contains(response: &[u8], expected: &str) {
         assert!(String::from_utf8_lossy(response).contains(expected));
     }
@@ -506,6 +791,324 @@ mod test {
         assert!(response.error.is_some());
     }
 
+    fn post(port: u16, body: &str) -> Value {
+        let response = post_with_headers(port, "", body);
+        let body_start = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap();
+        serde_json::from_slice(&response[body_start + 4..]).unwrap()
+    }
+
+    fn post_with_headers(port: u16, headers: &str, body: &str) -> Vec<u8> {
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            headers,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn batch() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let mut rpc = JsonRpcServer::new(server, Config::default(), state, process);
+        let port = rpc.port().unwrap();
+
+        let body = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "echo", "params": "a"},
+            {"jsonrpc": "2.0", "id": "two", "method": "rpc.reserved"},
+            {"foo": "bar"},
+            {"jsonrpc": "2.0", "id": 3, "method": "echo", "params": "b"},
+        ]);
+        let response = post(port, &body.to_string());
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"], "a");
+        assert_eq!(responses[1]["id"], "two");
+        assert_eq!(responses[1]["error"]["code"], -32_003);
+        assert_eq!(responses[2]["id"], Value::Null);
+        assert_eq!(responses[2]["error"]["code"], -32_600);
+        assert_eq!(responses[3]["result"], "b");
+
+        let response = post(port, "[]");
+        assert_eq!(response["error"]["code"], -32_600);
+
+        let response = post(port, r#"[{"jsonrpc": "2.0", "id": 5}]"#);
+        assert_eq!(response[0]["id"], 5);
+        assert_eq!(response[0]["error"]["code"], -32_600);
+
+        let response = post(port, "[1, 2");
+        assert_eq!(response["error"]["code"], -32_700);
+
+        let response = post(port, r#"{"jsonrpc": "2.0", "id": 4, "method": "echo"}"#);
+        assert_eq!(response["id"], 4);
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
+    #[test]
+    fn events() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let events = Events::new();
+        let config = Config::builder().with_events(Some(events.clone())).build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        let request = "GET /events HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n";
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut received = String::new();
+        let mut buf = [0u8; 1024];
+        while !received.contains("\r\n\r\n") {
+            let n = stream.read(&mut buf).unwrap();
+            received.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+        assert!(received.starts_with("HTTP/1.1 200"));
+        assert!(received.contains("Content-Type: text/event-stream"));
+        assert_eq!(events.subscribers(), 1);
+
+        events.publish("tip", &json!({"height": 42}));
+        while !received.ends_with("\n\n") {
+            let n = stream.read(&mut buf).unwrap();
+            received.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+        assert!(received.ends_with("event: tip\ndata: {\"height\":42}\n\n"));
+
+        drop(stream);
+        rpc.stop();
+        rpc.join_threads();
+
+        // without events in the config the endpoint does not exist
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let rpc = JsonRpcServer::new(server, Config::default(), state, process);
+        let port = rpc.port().unwrap();
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        let request = "GET /events HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n";
+        let response = send_http_request(&mut stream, request);
+        assert_response_contains(&response, "HTTP/1.1 404");
+    }
+
+    #[test]
+    fn metrics_endpoint() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let metrics = Metrics::new();
+        metrics.register_counter("calls_total", "Calls");
+        let config = Config::builder()
+            .with_metrics(Some(metrics.clone()))
+            .build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+        metrics.inc("calls_total");
+
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        let request = "GET /metrics HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n";
+        let response = send_http_request(&mut stream, request);
+        assert_response_contains(&response, "HTTP/1.1 200");
+        assert_response_contains(&response, "text/plain; version=0.0.4");
+        assert_response_contains(&response, "calls_total 1\n");
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
+    #[test]
+    fn schema_endpoint() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let config = Config::builder()
+            .with_schema(Some(r#"{"openrpc":"1.2.6"}"#.to_string()))
+            .build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+        let request = "GET /schema HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n";
+        let response = send_http_request(&mut stream, request);
+        assert_response_contains(&response, "HTTP/1.1 200");
+        assert_response_contains(&response, "application/json");
+        assert_response_contains(&response, r#"{"openrpc":"1.2.6"}"#);
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
+    #[test]
+    fn max_body_size() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let small = r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":"a"}"#;
+        let config = Config::builder()
+            .with_max_body_size(Some(small.len()))
+            .build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+
+        let response = post(port, small);
+        assert_eq!(response["result"], "a");
+
+        let large = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"echo","params":"{}"}}"#,
+            "a".repeat(1000)
+        );
+        let response = post(port, &large);
+        assert_eq!(response["error"]["code"], -32_093);
+        assert_eq!(
+            response["error"]["message"],
+            format!("Request body is larger than {} bytes", small.len())
+        );
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
+    #[test]
+    fn rate_limit() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let limit = RateLimit {
+            requests: 3,
+            period: Duration::from_secs(60),
+        };
+        let config = Config::builder().with_rate_limit(Some(limit)).build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+
+        let echo = r#"{"jsonrpc": "2.0", "id": 1, "method": "echo", "params": "a"}"#;
+        assert_eq!(post(port, echo)["result"], "a");
+        let response = post(port, &format!("[{echo}, {echo}, {echo}]"));
+        assert_eq!(response["error"]["code"], -32_097);
+        assert_eq!(post(port, &format!("[{echo}, {echo}]"))[1]["result"], "a");
+        assert_eq!(post(port, echo)["error"]["code"], -32_097);
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
+    #[test]
+    fn auth_tokens() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let config = Config::builder()
+            .with_auth_tokens(vec!["secret".to_string()])
+            .build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+
+        let echo = r#"{"jsonrpc": "2.0", "id": 1, "method": "echo", "params": "a"}"#;
+        assert_eq!(post(port, echo)["error"]["code"], -32_095);
+
+        let request = |token: &str| {
+            let header = format!("Authorization: Bearer {token}\r\n");
+            post_with_headers(port, &header, echo)
+        };
+        assert_response_contains(&request("secret"), "HTTP/1.1 200");
+        assert_response_contains(&request("secret"), r#""result":"a""#);
+        assert_response_contains(&request("wrong"), "HTTP/1.1 401");
+        assert_response_contains(&request("secre"), "HTTP/1.1 401");
+
+        for path in AUTHORIZED_PATHS {
+            let get = |headers: &str| {
+                let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+                let request = format!("GET {path} HTTP/1.1\r\nHost: 127.0.0.1\r\n{headers}\r\n");
+                send_http_request(&mut stream, &request)
+            };
+            assert_response_contains(&get(""), "HTTP/1.1 401");
+            assert_response_contains(&get("Authorization: Bearer wrong\r\n"), "HTTP/1.1 401");
+            // not configured in this server
+            assert_response_contains(&get("Authorization: Bearer secret\r\n"), "HTTP/1.1 404");
+        }
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
+    #[test]
+    fn tenant_tokens() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let tenant_tokens = [("shop-token".to_string(), "shop".to_string())].into();
+        let config = Config::builder().with_tenant_tokens(tenant_tokens).build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+
+        let tenant = r#"{"jsonrpc": "2.0", "id": 1, "method": "tenant"}"#;
+        // tenant tokens alone require authorization
+        assert_eq!(post(port, tenant)["error"]["code"], -32_095);
+
+        let header = "Authorization: Bearer shop-token\r\n";
+        let response = post_with_headers(port, header, tenant);
+        assert_response_contains(&response, r#""result":"shop""#);
+
+        // the events and the metrics are not of the tenant
+        for (path, status) in [("/events", "401"), ("/metrics", "401"), ("/schema", "404")] {
+            let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+            let request = format!("GET {path} HTTP/1.1\r\nHost: 127.0.0.1\r\n{header}\r\n");
+            let response = send_http_request(&mut stream, &request);
+            assert_response_contains(&response, &format!("HTTP/1.1 {status}"));
+        }
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
+    #[test]
+    fn method_timeout() {
+        let addr = "127.0.0.1:0";
+        let server = Server::http(addr).unwrap();
+        let state = Arc::new(Mutex::new(()));
+        let timeouts = [("sleep".to_string(), Duration::from_millis(100))].into();
+        let config = Config::builder().with_method_timeouts(timeouts).build();
+        let mut rpc = JsonRpcServer::new(server, config, state, process);
+        let port = rpc.port().unwrap();
+
+        let sleep = |millis| {
+            format!(r#"{{"jsonrpc": "2.0", "id": 1, "method": "sleep", "params": {millis}}}"#)
+        };
+        let response = post(port, &sleep(10));
+        assert!(response.get("error").is_none());
+        let response = post(port, &sleep(1_000));
+        assert_eq!(response["error"]["code"], -32_096);
+        assert_eq!(response["id"], 1);
+
+        // the timed out call is still running, new calls are rejected
+        let response = post(port, &sleep(10));
+        assert_eq!(response["error"]["code"], -32_094);
+        assert_eq!(response["id"], 1);
+
+        // once it ends the method can be called again
+        std::thread::sleep(Duration::from_millis(1_000));
+        let response = post(port, &sleep(10));
+        assert!(response.get("error").is_none());
+
+        // other methods are not affected
+        let echo = r#"{"jsonrpc": "2.0", "id": 2, "method": "echo", "params": "a"}"#;
+        assert_eq!(post(port, echo)["result"], "a");
+
+        rpc.stop();
+        rpc.join_threads();
+    }
+
     #[test]
     fn response_serialization() {
         // result response must not include error key
@@ -0,0 +1,184 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+/// Metrics exposed in the Prometheus text format at the `/metrics` endpoint
+///
+/// Metrics must be registered before being updated, updates of unknown metrics are ignored.
+/// Clone it to update the metrics from other threads, all the clones share the same values.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    metrics: Arc<Mutex<BTreeMap<&'static str, Metric>>>,
+}
+
+#[derive(Debug)]
+struct Metric {
+    help: &'static str,
+    value: Value,
+}
+
+#[derive(Debug)]
+enum Value {
+    Counter(u64),
+    Gauge(f64),
+    Histogram {
+        /// Upper bounds of the buckets, the `+Inf` bucket is implicit
+        bounds: Vec<f64>,
+        /// Number of observations less or equal to each bound, not cumulative
+        counts: Vec<u64>,
+        sum: f64,
+        count: u64,
+    },
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_counter(&self, name: &'static str, help: &'static str) {
+        self.register(name, help, Value::Counter(0));
+    }
+
+    pub fn register_gauge(&self, name: &'static str, help: &'static str) {
+        self.register(name, help, Value::Gauge(0.0));
+    }
+
+    /// Register a histogram with buckets with the given upper bounds, in increasing order
+    pub fn register_histogram(&self, name: &'static str, help: &'static str, bounds: &[f64]) {
+        let value = Value::Histogram {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        };
+        self.register(name, help, value);
+    }
+
+    fn register(&self, name: &'static str, help: &'static str, value: Value) {
+        if let Ok(mut metrics) = self.metrics.lock() {
+            metrics.insert(name, Metric { help, value });
+        }
+    }
+
+    /// Increment the counter `name` by one
+    pub fn inc(&self, name: &str) {
+        self.update(name, |value| {
+            if let Value::Counter(c) = value {
+                *c += 1;
+            }
+        });
+    }
+
+    /// Set the gauge `name` to `val`
+    pub fn set(&self, name: &str, val: f64) {
+        self.update(name, |value| {
+            if let Value::Gauge(g) = value {
+                *g = val;
+            }
+        });
+    }
+
+    /// Add an observation to the histogram `name`
+    pub fn observe(&self, name: &str, val: f64) {
+        self.update(name, |value| {
+            if let Value::Histogram {
+                bounds,
+                counts,
+                sum,
+                count,
+            } = value
+            {
+                if let Some(i) = bounds.iter().position(|b| val <= *b) {
+                    counts[i] += 1;
+                }
+                *sum += val;
+                *count += 1;
+            }
+        });
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut Value)) {
+        match self.metrics.lock() {
+            Ok(mut metrics) => match metrics.get_mut(name) {
+                Some(metric) => f(&mut metric.value),
+                None => log::debug!("metric {} is not registered", name),
+            },
+            Err(_) => log::error!("metrics lock poisoned"),
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut s = String::new();
+        let Ok(metrics) = self.metrics.lock() else {
+            return s;
+        };
+        for (name, metric) in metrics.iter() {
+            let _ = writeln!(s, "# HELP {} {}", name, metric.help);
+            let _ = match &metric.value {
+                Value::Counter(c) => writeln!(s, "# TYPE {name} counter\n{name} {c}"),
+                Value::Gauge(g) => writeln!(s, "# TYPE {name} gauge\n{name} {g}"),
+                Value::Histogram {
+                    bounds,
+                    counts,
+                    sum,
+                    count,
+                } => {
+                    let _ = writeln!(s, "# TYPE {} histogram", name);
+                    let mut cumulative = 0;
+                    for (bound, c) in bounds.iter().zip(counts) {
+                        cumulative += c;
+                        let _ = writeln!(s, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+                    }
+                    writeln!(
+                        s,
+                        "{name}_bucket{{le=\"+Inf\"}} {count}\n{name}_sum {sum}\n{name}_count {count}"
+                    )
+                }
+            };
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+
+    #[test]
+    fn metrics() {
+        let metrics = Metrics::new();
+        metrics.register_counter("requests_total", "Requests received");
+        metrics.register_gauge("height", "Tip height");
+        metrics.register_histogram("duration_seconds", "Duration", &[0.5, 1.0]);
+
+        metrics.inc("requests_total");
+        metrics.inc("requests_total");
+        metrics.inc("unknown");
+        metrics.set("height", 42.0);
+        metrics.set("requests_total", 1.0); // wrong type, ignored
+        metrics.clone().observe("duration_seconds", 0.1);
+        metrics.observe("duration_seconds", 0.7);
+        metrics.observe("duration_seconds", 3.0);
+
+        let expected = "\
+# HELP duration_seconds Duration
+# TYPE duration_seconds histogram
+duration_seconds_bucket{le=\"0.5\"} 1
+duration_seconds_bucket{le=\"1\"} 2
+duration_seconds_bucket{le=\"+Inf\"} 3
+duration_seconds_sum 3.8
+duration_seconds_count 3
+# HELP height Tip height
+# TYPE height gauge
+height 42
+# HELP requests_total Requests received
+# TYPE requests_total counter
+requests_total 2
+";
+        assert_eq!(metrics.render(), expected);
+    }
+}
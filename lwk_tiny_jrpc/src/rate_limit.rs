@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Maximum rate of JSON-RPC requests accepted from a single client address
+///
+/// Clients can send bursts of up to `requests` requests, then they are limited to `requests`
+/// every `period`. The limit applies per IP address rather than per TCP connection, so that a
+/// client can't reset it by reconnecting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// Allow `requests` requests per second
+    pub fn per_second(requests: u32) -> Self {
+        Self {
+            requests,
+            period: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Token buckets of the clients, shared by the server threads
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Consume `cost` tokens of the client at `addr`, returns false if there are not enough
+    pub(crate) fn check(&self, addr: IpAddr, cost: usize) -> bool {
+        self.check_at(addr, cost, Instant::now())
+    }
+
+    fn check_at(&self, addr: IpAddr, cost: usize, now: Instant) -> bool {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return true;
+        };
+        let capacity = self.limit.requests as f64;
+        let rate = capacity / self.limit.period.as_secs_f64().max(f64::EPSILON);
+
+        // buckets refilled to capacity are equivalent to missing ones
+        buckets.retain(|_, b| {
+            b.tokens + now.saturating_duration_since(b.last).as_secs_f64() * rate < capacity
+        });
+
+        let bucket = buckets.entry(addr).or_insert(Bucket {
+            tokens: capacity,
+            last: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.last = now;
+        if bucket.tokens >= cost as f64 {
+            bucket.tokens -= cost as f64;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter() {
+        let limiter = RateLimiter::new(RateLimit::per_second(2));
+        let a: IpAddr = "127.0.0.1".parse().unwrap();
+        let b: IpAddr = "127.0.0.2".parse().unwrap();
+        let now = Instant::now();
+
+        assert!(limiter.check_at(a, 1, now));
+        assert!(limiter.check_at(a, 1, now));
+        assert!(!limiter.check_at(a, 1, now));
+        assert!(limiter.check_at(b, 2, now));
+        assert!(!limiter.check_at(b, 3, now));
+
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check_at(a, 1, later));
+        assert!(!limiter.check_at(a, 1, later));
+
+        let much_later = now + Duration::from_secs(10);
+        assert!(limiter.check_at(a, 2, much_later));
+        assert!(!limiter.check_at(a, 1, much_later));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::{Error, Script};
+use lwk_wollet::elements::hex::ToHex;
 use lwk_wollet::elements::{self, AddressParams};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
 /// Wrapper of [`elements::Address`]
@@ -108,6 +110,16 @@ impl From<lwk_wollet::AddressResult> for AddressResult {
     }
 }
 
+/// The details of an [`AddressResult`] as a plain object, to display them
+#[derive(Serialize)]
+struct AddressDetails {
+    address: String,
+    unconfidential: String,
+    script_pubkey: String,
+    blinding_pubkey: Option<String>,
+    index: u32,
+}
+
 #[wasm_bindgen]
 impl AddressResult {
     pub fn address(&self) -> Address {
@@ -117,6 +129,38 @@ impl AddressResult {
     pub fn index(&self) -> u32 {
         self.inner.index()
     }
+
+    /// The address without the blinding key
+    pub fn unconfidential(&self) -> Address {
+        self.inner.address().to_unconfidential().into()
+    }
+
+    #[wasm_bindgen(js_name = scriptPubkey)]
+    pub fn script_pubkey(&self) -> Script {
+        self.inner.address().script_pubkey().into()
+    }
+
+    /// The blinding public key of the address in hex, `undefined` if the address is not
+    /// confidential
+    #[wasm_bindgen(js_name = blindingPubkey)]
+    pub fn blinding_pubkey(&self) -> Option<String> {
+        self.inner.address().blinding_pubkey.map(|k| k.to_string())
+    }
+
+    /// Return an object with the address, the unconfidential address, the script pubkey, the
+    /// blinding public key and the index, used also by `JSON.stringify()`
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, Error> {
+        let address = self.inner.address();
+        let details = AddressDetails {
+            address: address.to_string(),
+            unconfidential: address.to_unconfidential().to_string(),
+            script_pubkey: address.script_pubkey().to_hex(),
+            blinding_pubkey: self.blinding_pubkey(),
+            index: self.inner.index(),
+        };
+        Ok(serde_wasm_bindgen::to_value(&details)?)
+    }
 }
 
 #[cfg(all(test, target_arch = "wasm32"))]
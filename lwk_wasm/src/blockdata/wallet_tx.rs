@@ -48,6 +48,14 @@ impl WalletTx {
         self.inner.timestamp
     }
 
+    pub fn confirmations(&self) -> u32 {
+        self.inner.confirmations
+    }
+
+    pub fn status(&self) -> String {
+        self.inner.status.to_string()
+    }
+
     pub fn inputs(&self) -> Vec<OptionWalletTxOut> {
         self.inner
             .inputs
@@ -118,6 +126,8 @@ mod tests {
             timestamp: Some(124),
             inputs: vec![Some(tx_out.clone())],
             outputs: vec![None, Some(tx_out.clone())],
+            confirmations: 2,
+            status: lwk_wollet::TxStatus::Final,
         };
 
         let wallet_tx: WalletTx = el.clone().into();
@@ -136,6 +146,10 @@ mod tests {
 
         assert_eq!(wallet_tx.timestamp(), Some(124));
 
+        assert_eq!(wallet_tx.confirmations(), 2);
+
+        assert_eq!(wallet_tx.status(), "final");
+
         assert_eq!(wallet_tx.inputs().len(), 1);
 
         assert_eq!(wallet_tx.outputs().len(), 2);
@@ -41,6 +41,7 @@ pub enum Error {
     #[error(transparent)]
     SignerNew(#[from] lwk_signer::NewError),
 
+    #[cfg(feature = "serial")]
     #[error(transparent)]
     Jade(#[from] lwk_jade::Error),
 
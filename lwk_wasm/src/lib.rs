@@ -1,12 +1,14 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "amp2")]
 mod amp2;
 mod bip;
 mod blockdata;
 mod contract;
 mod descriptor;
 mod error;
+#[cfg(feature = "esplora")]
 mod esplora;
 #[cfg(all(feature = "serial", target_arch = "wasm32"))]
 mod jade;
@@ -17,6 +19,7 @@ mod network;
 mod precision;
 mod pset;
 mod pset_details;
+#[cfg(feature = "esplora")]
 mod registry;
 #[cfg(all(feature = "serial", target_arch = "wasm32"))]
 mod serial;
@@ -26,6 +29,7 @@ mod update;
 mod wollet;
 mod xpub;
 
+#[cfg(feature = "amp2")]
 pub use amp2::{Amp2, Amp2Descriptor};
 pub use bip::Bip;
 pub use blockdata::address::{Address, AddressResult};
@@ -39,6 +43,7 @@ pub use blockdata::wallet_tx_out::{OptionWalletTxOut, WalletTxOut};
 pub use contract::Contract;
 pub use descriptor::WolletDescriptor;
 pub(crate) use error::Error;
+#[cfg(feature = "esplora")]
 pub use esplora::EsploraClient;
 #[cfg(all(feature = "serial", target_arch = "wasm32"))]
 pub use jade::{Jade, Singlesig};
@@ -47,6 +52,7 @@ pub use network::Network;
 pub use precision::Precision;
 pub use pset::Pset;
 pub use pset_details::{Issuance, PsetDetails};
+#[cfg(feature = "esplora")]
 pub use registry::{AssetMeta, Registry, RegistryPost};
 pub use signer::Signer;
 pub use tx_builder::TxBuilder;
@@ -1,7 +1,7 @@
 use lwk_wollet::elements;
 use wasm_bindgen::prelude::*;
 
-use crate::{AssetId, EsploraClient, TxBuilder};
+use crate::{AssetId, TxBuilder};
 
 /// Wrapper of [`lwk_wollet::ElementsNetwork`]
 #[wasm_bindgen]
@@ -74,8 +74,9 @@ impl Network {
         lwk_wollet::ElementsNetwork::ElementsRegtest { policy_asset }.into()
     }
 
+    #[cfg(feature = "esplora")]
     #[wasm_bindgen(js_name = defaultEsploraClient)]
-    pub fn default_esplora_client(&self) -> EsploraClient {
+    pub fn default_esplora_client(&self) -> crate::EsploraClient {
         let url = match &self.inner {
             lwk_wollet::ElementsNetwork::Liquid => "https://blockstream.info/liquid/api",
             lwk_wollet::ElementsNetwork::LiquidTestnet => {
@@ -84,7 +85,7 @@ impl Network {
             lwk_wollet::ElementsNetwork::ElementsRegtest { policy_asset: _ } => "127.0.0.1:3000",
         };
 
-        EsploraClient::new(self, url, false)
+        crate::EsploraClient::new(self, url, false)
     }
 
     #[wasm_bindgen(js_name = isMainnet)]
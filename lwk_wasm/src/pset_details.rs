@@ -181,6 +181,11 @@ impl Recipient {
     pub fn vout(&self) -> u32 {
         self.inner.vout
     }
+
+    #[wasm_bindgen(js_name = isConfidential)]
+    pub fn is_confidential(&self) -> bool {
+        self.inner.confidential
+    }
 }
 
 impl From<PsetDetails> for lwk_common::PsetDetails {
@@ -1,6 +1,6 @@
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::{AssetId, Contract, Error, EsploraClient, Network, Transaction};
+use crate::{AssetId, Contract, Error, EsploraClient, Network, Pset, Transaction};
 
 #[wasm_bindgen]
 pub struct Registry {
@@ -37,6 +37,25 @@ impl RegistryPost {
         lwk_wollet::registry::RegistryPost::new(contract.into(), asset_id.into()).into()
     }
 
+    /// Prepare the registration of the asset issued in `pset` with `contract`
+    #[wasm_bindgen(js_name = fromIssuance)]
+    pub fn from_issuance(pset: &Pset, contract: Contract) -> Result<RegistryPost, Error> {
+        let pset: lwk_wollet::elements::pset::PartiallySignedTransaction = pset.clone().into();
+        Ok(lwk_wollet::registry::RegistryPost::from_issuance(&pset, contract.into())?.into())
+    }
+
+    /// The URL where the issuer must publish `domainProof()` before posting
+    #[wasm_bindgen(js_name = domainProofUrl)]
+    pub fn domain_proof_url(&self) -> String {
+        self.inner.domain_proof().url()
+    }
+
+    /// The content proving the issuer controls the domain of the contract entity
+    #[wasm_bindgen(js_name = domainProof)]
+    pub fn domain_proof(&self) -> String {
+        self.inner.domain_proof().content()
+    }
+
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string_js(&self) -> String {
         format!("{}", self.inner)
@@ -1,12 +1,12 @@
 use crate::{
-    AddressResult, Error, Network, Pset, PsetDetails, Update, WalletTx, WalletTxOut,
+    AddressResult, Error, Network, OutPoint, Pset, PsetDetails, Update, WalletTx, WalletTxOut,
     WolletDescriptor,
 };
-use lwk_jade::derivation_path_to_vec;
 use lwk_wollet::elements::pset::PartiallySignedTransaction;
 use lwk_wollet::elements_miniscript::ForEachKey;
 use serde::Serialize;
 use serde_wasm_bindgen::Serializer;
+use std::str::FromStr;
 use wasm_bindgen::prelude::*;
 
 /// Wrapper of [`lwk_wollet::Wollet`]
@@ -56,7 +56,7 @@ impl Wollet {
         let mut full_path: Vec<u32> = vec![];
         definite_desc.for_each_key(|k| {
             if let Some(path) = k.full_derivation_path() {
-                full_path = derivation_path_to_vec(&path);
+                full_path = path.into_iter().map(|e| (*e).into()).collect();
             }
 
             true
@@ -70,6 +70,23 @@ impl Wollet {
         Ok(self.inner.apply_update(update.into())?)
     }
 
+    /// Try again to unblind the wallet outputs that cannot be unblinded, also with the given
+    /// blinding private keys in hex, returning the outputs unblinded
+    ///
+    /// wraps [lwk_wollet::Wollet::retry_unblind()]
+    #[wasm_bindgen(js_name = retryUnblind)]
+    pub fn retry_unblind(&mut self, blinding_keys: Vec<String>) -> Result<Vec<OutPoint>, Error> {
+        let blinding_keys = blinding_keys
+            .iter()
+            .map(|key| {
+                lwk_wollet::elements::secp256k1_zkp::SecretKey::from_str(key)
+                    .map_err(|e| Error::Generic(format!("Invalid blinding key: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let unblinded = self.inner.retry_unblind(&blinding_keys)?;
+        Ok(unblinded.into_iter().map(Into::into).collect())
+    }
+
     pub fn balance(&self) -> Result<JsValue, Error> {
         let balance = self.inner.balance()?;
         let serializer = Serializer::new().serialize_large_number_types_as_bigints(true);
@@ -186,10 +203,22 @@ mod tests {
         let descriptor = WolletDescriptor::new(DESCRIPTOR).unwrap();
         let network = Network::mainnet();
         let wollet = Wollet::new(&network, &descriptor).unwrap();
+        let address = wollet.address(Some(0)).unwrap();
         assert_eq!(
-            wollet.address(Some(0)).unwrap().address().to_string(),
+            address.address().to_string(),
             "VJLAQiChRTcVDXEBKrRnSBnGccJLxNg45zW8cuDwkhbxb8NVFkb4U2QMWAzot4idqhLMWjtZ7SXA4nrA"
         );
+        assert_eq!(address.index(), 0);
+        assert_eq!(
+            address.unconfidential().to_string(),
+            address.address().to_unconfidential().to_string()
+        );
+        assert_eq!(
+            address.script_pubkey().to_string(),
+            address.address().script_pubkey().to_string()
+        );
+        assert_eq!(address.blinding_pubkey().unwrap().len(), 66);
+        assert_eq!(wollet.address(None).unwrap().index(), 0);
         assert_eq!(wollet.descriptor().unwrap().to_string(), "ct(slip77(0371e66dde8ab9f3cb19d2c20c8fa2d7bd1ddc73454e6b7ef15f0c5f624d4a86),elsh(wpkh([75ea4a43/49'/1776'/0']xpub6D3Y5EKNsmegjE7azkF2foAYFivHrV5u7tcnN2TXELxv1djNtabCHtp3jMvxqEhTU737mYSUqHD1sA5MdZXQ8DWJLNft1gwtpzXZDsRnrZd/<0;1>/*)))#efvhq75f");
         assert_eq!(wollet.status(), 1421324647);
     }
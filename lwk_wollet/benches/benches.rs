@@ -1,11 +1,14 @@
 use std::str::FromStr;
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
 use elements::{pset::PartiallySignedTransaction, Address};
 use elements_miniscript::{ConfidentialDescriptor, DescriptorPublicKey};
-use lwk_wollet::{ElementsNetwork, NoPersist, Update, Wollet, WolletDescriptor};
+use lwk_wollet::blocking::{BlockchainBackend, MockBackend};
+use lwk_wollet::{
+    unblind_transaction, Chain, ElementsNetwork, NoPersist, Update, Wollet, WolletDescriptor,
+};
 
-criterion_group!(benches, wollet, address, pset);
+criterion_group!(benches, wollet, address, pset, sync);
 criterion_main!(benches);
 
 pub fn wollet(c: &mut Criterion) {
@@ -108,6 +111,95 @@ pub fn pset(c: &mut Criterion) {
         });
 }
 
+/// Hot paths of the sync, on a synthetic wallet with [`SYNTHETIC_WALLET_TXS`] transactions
+///
+/// The full scan includes script derivation, history download and unblinding, the other
+/// benchmarks measure them in isolation, so that the gains of parallel unblinding can be
+/// compared with the whole sync.
+pub fn sync(c: &mut Criterion) {
+    let network = ElementsNetwork::default_regtest();
+    let (client, synced) = synthetic_wallet(network);
+    let update = client.clone().full_scan(&empty_wollet(network)).unwrap();
+
+    c.benchmark_group("sync")
+        .sample_size(10)
+        .bench_function("derive 1000 scripts", |b: &mut criterion::Bencher<'_>| {
+            let desc = synced.wollet_descriptor();
+            b.iter(|| {
+                for index in 0..1000 {
+                    let script = desc.script_pubkey(Chain::External, index).unwrap();
+                    black_box(script);
+                }
+            });
+        })
+        .bench_function("full scan", |b: &mut criterion::Bencher<'_>| {
+            b.iter_batched(
+                || (client.clone(), empty_wollet(network)),
+                |(mut client, wollet)| black_box(client.full_scan(&wollet).unwrap()),
+                BatchSize::SmallInput,
+            );
+        })
+        .bench_function(
+            "full scan without changes",
+            |b: &mut criterion::Bencher<'_>| {
+                b.iter_batched(
+                    || client.clone(),
+                    |mut client| black_box(client.full_scan(&synced).unwrap()),
+                    BatchSize::SmallInput,
+                );
+            },
+        )
+        .bench_function("apply update", |b: &mut criterion::Bencher<'_>| {
+            b.iter_batched(
+                || (update.clone().unwrap(), empty_wollet(network)),
+                |(update, mut wollet)| {
+                    wollet.apply_update(update).unwrap();
+                    black_box(wollet)
+                },
+                BatchSize::SmallInput,
+            );
+        })
+        .bench_function("rewind rangeproofs", |b: &mut criterion::Bencher<'_>| {
+            let txs: Vec<_> = synced
+                .transactions()
+                .unwrap()
+                .into_iter()
+                .map(|t| t.tx)
+                .collect();
+            let key = &synced.descriptor().key;
+            b.iter(|| {
+                for tx in txs.iter() {
+                    black_box(unblind_transaction(tx, key).unwrap());
+                }
+            });
+        });
+}
+
+/// Number of confidential transactions received by the synthetic wallet
+const SYNTHETIC_WALLET_TXS: u32 = 200;
+
+fn empty_wollet(network: ElementsNetwork) -> Wollet {
+    let desc = include_str!("../../lwk_common/test_data/pset_details/descriptor");
+    Wollet::without_persist(network, desc.trim().parse().unwrap()).unwrap()
+}
+
+/// A wallet receiving [`SYNTHETIC_WALLET_TXS`] transactions on consecutive addresses, spread over
+/// several blocks, and the backend with its history
+fn synthetic_wallet(network: ElementsNetwork) -> (MockBackend, Wollet) {
+    let mut client = MockBackend::new(network);
+    let mut wollet = empty_wollet(network);
+    for index in 0..SYNTHETIC_WALLET_TXS {
+        let address = wollet.address(Some(index)).unwrap();
+        client.fund(address.address(), 1_000, None).unwrap();
+        if index % 10 == 9 {
+            client.mine(1);
+        }
+    }
+    let update = client.full_scan(&wollet).unwrap().unwrap();
+    wollet.apply_update(update).unwrap();
+    (client, wollet)
+}
+
 // duplicated from tests/test_wollet.rs
 pub fn test_wollet_with_many_transactions() -> Wollet {
     let update = lwk_test_util::update_test_vector_many_transactions();
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::elements::Txid;
+use crate::wollet::Wollet;
+use crate::{ElementsNetwork, Error, Persister, WolletDescriptor};
+use serde::{Deserialize, Serialize};
+
+/// A portable backup of a wallet
+///
+/// It contains the public information needed to restore the wallet on another machine, and
+/// the metadata that cannot be recovered from the blockchain. It does not contain any key that
+/// can be used to spend the wallet funds.
+///
+/// The backup is meant to be serialized in JSON, the `version` field allows to evolve the
+/// format without breaking the restore of older backups.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WolletBackup {
+    /// The version of the backup format
+    pub version: u32,
+
+    /// The network of the wallet
+    pub network: ElementsNetwork,
+
+    /// The CT descriptor of the wallet
+    pub descriptor: String,
+
+    /// The height of the first block containing a wallet transaction, if any
+    ///
+    /// Transactions before this height do not involve the wallet.
+    pub birthday: Option<u32>,
+
+    /// Memos of wallet transactions
+    #[serde(default)]
+    pub tx_memos: BTreeMap<Txid, String>,
+
+    /// Memos of wallet addresses
+    #[serde(default)]
+    pub addr_memos: BTreeMap<String, String>,
+}
+
+impl WolletBackup {
+    /// The latest version of the backup format
+    pub const VERSION: u32 = 1;
+
+    /// The descriptor of the wallet
+    pub fn descriptor(&self) -> Result<WolletDescriptor, Error> {
+        self.descriptor.parse()
+    }
+}
+
+impl std::str::FromStr for WolletBackup {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let backup: WolletBackup = serde_json::from_str(s)?;
+        if backup.version > Self::VERSION {
+            return Err(Error::UnsupportedBackupVersion(backup.version));
+        }
+        Ok(backup)
+    }
+}
+
+impl std::fmt::Display for WolletBackup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", s)
+    }
+}
+
+impl Wollet {
+    /// Export a backup of the wallet
+    ///
+    /// The wallet does not hold any memo, callers keeping memos can add them to the returned
+    /// backup before serializing it.
+    pub fn export_backup(&self) -> WolletBackup {
+        WolletBackup {
+            version: WolletBackup::VERSION,
+            network: self.network(),
+            descriptor: self.wollet_descriptor().to_string(),
+            birthday: self.birthday(),
+            tx_memos: BTreeMap::new(),
+            addr_memos: BTreeMap::new(),
+        }
+    }
+
+    /// Restore a wallet from a backup created with [`Wollet::export_backup()`]
+    ///
+    /// The returned wallet has the birthday of the backup and must be synced to get the wallet
+    /// transactions.
+    pub fn import_backup(
+        backup: &WolletBackup,
+        persister: Arc<dyn Persister + Send + Sync>,
+    ) -> Result<Self, Error> {
+        if backup.version > WolletBackup::VERSION {
+            return Err(Error::UnsupportedBackupVersion(backup.version));
+        }
+        let mut wollet = Self::new(backup.network, persister, backup.descriptor()?)?;
+        wollet.set_birthday(backup.birthday);
+        Ok(wollet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::test_wollet_with_many_transactions;
+    use crate::NoPersist;
+
+    #[test]
+    fn test_backup() {
+        let wollet = test_wollet_with_many_transactions();
+        let mut backup = wollet.export_backup();
+        assert_eq!(backup.version, WolletBackup::VERSION);
+        assert!(backup.birthday.is_some());
+        assert!(backup.tx_memos.is_empty());
+
+        let txid = wollet.transactions().unwrap()[0].txid;
+        backup.tx_memos.insert(txid, "memo".to_string());
+        let s = backup.to_string();
+        let parsed: WolletBackup = s.parse().unwrap();
+        assert_eq!(parsed, backup);
+
+        let restored = Wollet::import_backup(&parsed, Arc::new(NoPersist {})).unwrap();
+        assert_eq!(
+            restored.wollet_descriptor().to_string(),
+            wollet.wollet_descriptor().to_string()
+        );
+        assert_eq!(restored.network(), wollet.network());
+        assert!(restored.transactions().unwrap().is_empty());
+        assert_eq!(restored.birthday(), backup.birthday);
+        assert_eq!(restored.export_backup().birthday, backup.birthday);
+
+        let future = s.replace("\"version\":1", "\"version\":2");
+        assert!(matches!(
+            future.parse::<WolletBackup>(),
+            Err(Error::UnsupportedBackupVersion(2))
+        ));
+    }
+}
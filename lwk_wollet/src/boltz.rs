@@ -0,0 +1,1079 @@
+//! Swap between Lightning and Liquid with [Boltz](https://boltz.exchange)
+//!
+//! A submarine swap moves funds from Liquid to Lightning: the wallet sends L-BTC to a lockup
+//! address and Boltz claims them once it paid the invoice revealing the preimage. If Boltz does not
+//! pay the invoice, the wallet can refund the L-BTC after the swap timeout.
+//!
+//! A reverse submarine swap moves funds from Lightning to Liquid: the wallet pays an invoice held
+//! by Boltz, Boltz locks L-BTC in a lockup address and the wallet claims them revealing the
+//! preimage, which allows Boltz to settle the invoice.
+//!
+//! The lockup addresses are taproot addresses whose internal key is the MuSig2 aggregation of the
+//! Boltz and wallet keys, with a claim and a refund leaf. Always verify the swaps returned by the
+//! server with [`SubmarineSwap::swap_script()`] and [`ReverseSwap::swap_script()`] before locking
+//! any funds. Claims and refunds spend the leaves, the cooperative key path is not used.
+//!
+//! <div class="warning">
+//! Swaps are under development, expect breaking changes.
+//! </div>
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use elements::hashes::{ripemd160, sha256, Hash, HashEngine};
+use elements::hex::FromHex;
+use elements::opcodes::all::{
+    OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_CLTV, OP_EQUALVERIFY, OP_HASH160, OP_SIZE,
+};
+use elements::pset::{Input, Output, PartiallySignedTransaction};
+use elements::script::Builder;
+use elements::secp256k1_zkp::{Keypair, Message, PublicKey, Scalar, SecretKey, XOnlyPublicKey};
+use elements::sighash::{Prevouts, SighashCache};
+use elements::taproot::{LeafVersion, TapLeafHash, TaprootBuilder, TaprootSpendInfo};
+use elements::{
+    Address, BlockHash, LockTime, OutPoint, SchnorrSighashType, Script, Sequence, Transaction,
+    TxOut, TxOutSecrets, Txid,
+};
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::util::{serde_from_hex, serde_to_hex};
+use crate::{ElementsNetwork, Error, EC};
+
+pub const URL_MAINNET: &str = "https://api.boltz.exchange/v2";
+pub const URL_TESTNET: &str = "https://api.testnet.boltz.exchange/v2";
+
+/// Default fee rate of claim and refund transactions (sats/kvb)
+const DEFAULT_FEE_RATE: f32 = 100.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BoltzError {
+    #[error("Boltz API error: {0}")]
+    Api(String),
+
+    #[error("The swap tree does not match the expected scripts")]
+    InvalidSwapTree,
+
+    #[error("The lockup address does not match the swap tree")]
+    InvalidLockupAddress,
+
+    #[error("The swap amount {0} differs from the expected one {1}")]
+    InvalidAmount(u64, u64),
+
+    #[error("Transaction {0} does not lock funds to the swap address")]
+    MissingLockupOutput(Txid),
+
+    #[error("The preimage does not match the swap preimage hash")]
+    WrongPreimage,
+
+    #[error("The key does not match the swap key")]
+    WrongKey,
+
+    #[error("Swap amount {amount} is not enough to pay the fee {fee}")]
+    AmountTooLow { amount: u64, fee: u64 },
+
+    #[error("Cannot compute the sighash: {0}")]
+    Sighash(String),
+}
+
+/// The kind of a swap, which determines the scripts of its lockup address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapKind {
+    /// From Liquid to Lightning
+    Submarine,
+
+    /// From Lightning to Liquid
+    Reverse,
+}
+
+/// The secret revealed to claim a swap
+#[derive(Clone, PartialEq, Eq)]
+pub struct Preimage([u8; 32]);
+
+impl Preimage {
+    pub fn random() -> Self {
+        let mut bytes = [0u8; 32];
+        thread_rng().fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The payment hash of the invoice of the swap
+    pub fn sha256(&self) -> sha256::Hash {
+        sha256::Hash::hash(&self.0)
+    }
+}
+
+impl std::fmt::Debug for Preimage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // do not leak the secret in logs
+        write!(f, "Preimage(..)")
+    }
+}
+
+/// The limits and fees of swaps between L-BTC and BTC on Lightning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairInfo {
+    /// Identifies the current limits and fees, the server rejects swaps created with an old one
+    pub hash: String,
+    pub rate: f64,
+    pub limits: Limits,
+    pub fees: Fees,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Limits {
+    pub minimal: u64,
+    pub maximal: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fees {
+    /// Percentage of the swap amount
+    pub percentage: f64,
+
+    /// Onchain fees paid by Boltz, the format differs between submarine and reverse swaps
+    pub miner_fees: serde_json::Value,
+}
+
+/// A leaf of the taproot tree of a lockup address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leaf {
+    pub version: u8,
+    #[serde(deserialize_with = "serde_from_hex", serialize_with = "serde_to_hex")]
+    pub output: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapTree {
+    pub claim_leaf: Leaf,
+    pub refund_leaf: Leaf,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SubmarineRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    invoice: &'a str,
+    refund_public_key: String,
+}
+
+/// A submarine swap, from Liquid to Lightning, as returned by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmarineSwap {
+    pub id: String,
+
+    /// The lockup address where the wallet sends the L-BTC
+    pub address: String,
+    pub bip21: Option<String>,
+    pub expected_amount: u64,
+    pub accept_zero_conf: bool,
+    pub timeout_block_height: u32,
+    pub claim_public_key: String,
+    pub swap_tree: SwapTree,
+    pub blinding_key: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReverseRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    invoice_amount: u64,
+    preimage_hash: String,
+    claim_public_key: String,
+}
+
+/// A reverse submarine swap, from Lightning to Liquid, as returned by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseSwap {
+    pub id: String,
+
+    /// The invoice the wallet pays
+    pub invoice: String,
+    pub lockup_address: String,
+    pub onchain_amount: u64,
+    pub timeout_block_height: u32,
+    pub refund_public_key: String,
+    pub swap_tree: SwapTree,
+    pub blinding_key: String,
+}
+
+/// The status of a swap, for instance `transaction.mempool`, `transaction.claimed`,
+/// `invoice.settled` or `swap.expired`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapStatus {
+    pub status: String,
+    pub transaction: Option<StatusTransaction>,
+}
+
+/// The lockup transaction of a swap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusTransaction {
+    pub id: String,
+    pub hex: Option<String>,
+}
+
+impl StatusTransaction {
+    pub fn transaction(&self) -> Result<Option<Transaction>, Error> {
+        self.hex
+            .as_ref()
+            .map(|hex| Ok(elements::encode::deserialize(&Vec::<u8>::from_hex(hex)?)?))
+            .transpose()
+    }
+}
+
+#[derive(Serialize)]
+struct BroadcastRequest {
+    hex: String,
+}
+
+#[derive(Deserialize)]
+struct BroadcastResponse {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl SubmarineSwap {
+    /// Verify the swap and return its script
+    ///
+    /// `payment_hash` is the payment hash of the invoice and `refund_key` the key sent to the
+    /// server when creating the swap.
+    pub fn swap_script(
+        &self,
+        payment_hash: sha256::Hash,
+        refund_key: PublicKey,
+        network: ElementsNetwork,
+    ) -> Result<SwapScript, Error> {
+        let script = SwapScript {
+            kind: SwapKind::Submarine,
+            hash160: ripemd160::Hash::hash(payment_hash.as_byte_array()),
+            boltz_key: PublicKey::from_str(&self.claim_public_key)?,
+            user_key: refund_key,
+            timeout: self.timeout_block_height,
+            blinding_key: SecretKey::from_str(&self.blinding_key)?,
+            network,
+            genesis_hash: genesis_hash(network),
+        };
+        script.verify(&self.swap_tree, &self.address)?;
+        Ok(script)
+    }
+}
+
+impl ReverseSwap {
+    /// Verify the swap and return its script
+    ///
+    /// `claim_key` is the key sent to the server when creating the swap, `amount` the minimum
+    /// amount the wallet expects to receive onchain.
+    pub fn swap_script(
+        &self,
+        preimage: &Preimage,
+        claim_key: PublicKey,
+        amount: u64,
+        network: ElementsNetwork,
+    ) -> Result<SwapScript, Error> {
+        if self.onchain_amount < amount {
+            return Err(BoltzError::InvalidAmount(self.onchain_amount, amount).into());
+        }
+        let script = SwapScript {
+            kind: SwapKind::Reverse,
+            hash160: ripemd160::Hash::hash(preimage.sha256().as_byte_array()),
+            boltz_key: PublicKey::from_str(&self.refund_public_key)?,
+            user_key: claim_key,
+            timeout: self.timeout_block_height,
+            blinding_key: SecretKey::from_str(&self.blinding_key)?,
+            network,
+            genesis_hash: genesis_hash(network),
+        };
+        script.verify(&self.swap_tree, &self.lockup_address)?;
+        Ok(script)
+    }
+}
+
+fn genesis_hash(network: ElementsNetwork) -> BlockHash {
+    network.genesis_hash().unwrap_or_else(BlockHash::all_zeros)
+}
+
+/// The scripts of a verified swap, used to claim or refund it
+#[derive(Debug, Clone)]
+pub struct SwapScript {
+    kind: SwapKind,
+
+    /// `HASH160` of the preimage
+    hash160: ripemd160::Hash,
+    boltz_key: PublicKey,
+    user_key: PublicKey,
+    timeout: u32,
+    blinding_key: SecretKey,
+    network: ElementsNetwork,
+    genesis_hash: BlockHash,
+}
+
+impl SwapScript {
+    /// Set the genesis hash committed by the signatures, needed only for regtest since it
+    /// depends on the chain parameters
+    pub fn with_genesis_hash(mut self, genesis_hash: BlockHash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    pub fn kind(&self) -> SwapKind {
+        self.kind
+    }
+
+    /// The block height after which the swap can be refunded
+    pub fn timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    fn claim_key(&self) -> XOnlyPublicKey {
+        match self.kind {
+            SwapKind::Submarine => self.boltz_key.x_only_public_key().0,
+            SwapKind::Reverse => self.user_key.x_only_public_key().0,
+        }
+    }
+
+    fn refund_key(&self) -> XOnlyPublicKey {
+        match self.kind {
+            SwapKind::Submarine => self.user_key.x_only_public_key().0,
+            SwapKind::Reverse => self.boltz_key.x_only_public_key().0,
+        }
+    }
+
+    fn claim_leaf(&self) -> Script {
+        let builder = match self.kind {
+            // The preimage size check prevents Boltz from locking funds that can't be claimed
+            SwapKind::Reverse => Builder::new()
+                .push_opcode(OP_SIZE)
+                .push_int(32)
+                .push_opcode(OP_EQUALVERIFY),
+            SwapKind::Submarine => Builder::new(),
+        };
+        builder
+            .push_opcode(OP_HASH160)
+            .push_slice(self.hash160.as_byte_array())
+            .push_opcode(OP_EQUALVERIFY)
+            .push_slice(&self.claim_key().serialize())
+            .push_opcode(OP_CHECKSIG)
+            .into_script()
+    }
+
+    fn refund_leaf(&self) -> Script {
+        Builder::new()
+            .push_slice(&self.refund_key().serialize())
+            .push_opcode(OP_CHECKSIGVERIFY)
+            .push_int(self.timeout as i64)
+            .push_opcode(OP_CLTV)
+            .into_script()
+    }
+
+    fn spend_info(&self) -> Result<TaprootSpendInfo, Error> {
+        let internal_key = key_agg(&[self.boltz_key, self.user_key])?;
+        TaprootBuilder::new()
+            .add_leaf(1, self.claim_leaf())
+            .and_then(|b| b.add_leaf(1, self.refund_leaf()))
+            .and_then(|b| b.finalize(&EC, internal_key))
+            .map_err(|_| BoltzError::InvalidSwapTree.into())
+    }
+
+    /// The confidential lockup address of the swap
+    pub fn address(&self) -> Result<Address, Error> {
+        let output_key = self.spend_info()?.output_key();
+        Ok(Address::p2tr_tweaked(
+            output_key,
+            Some(self.blinding_key.public_key(&EC)),
+            self.network.address_params(),
+        ))
+    }
+
+    fn verify(&self, tree: &SwapTree, address: &str) -> Result<(), Error> {
+        let version = LeafVersion::default().as_u8();
+        if tree.claim_leaf.version != version
+            || tree.refund_leaf.version != version
+            || tree.claim_leaf.output != self.claim_leaf().as_bytes()
+            || tree.refund_leaf.output != self.refund_leaf().as_bytes()
+        {
+            return Err(BoltzError::InvalidSwapTree.into());
+        }
+        if Address::from_str(address)? != self.address()? {
+            return Err(BoltzError::InvalidLockupAddress.into());
+        }
+        Ok(())
+    }
+
+    /// Claim the funds of a reverse swap sending them to `address`
+    ///
+    /// `fee_rate` is in sats/kvb, if not specified a default value is used.
+    pub fn claim_tx(
+        &self,
+        lockup_tx: &Transaction,
+        preimage: &Preimage,
+        keypair: &Keypair,
+        address: &Address,
+        fee_rate: Option<f32>,
+    ) -> Result<Transaction, Error> {
+        if self.kind != SwapKind::Reverse {
+            return Err(Error::Generic("Only reverse swaps can be claimed".into()));
+        }
+        if ripemd160::Hash::hash(preimage.sha256().as_byte_array()) != self.hash160 {
+            return Err(BoltzError::WrongPreimage.into());
+        }
+        let witness = vec![preimage.as_bytes().to_vec()];
+        self.spend(
+            lockup_tx,
+            keypair,
+            address,
+            fee_rate,
+            witness,
+            LockTime::ZERO,
+        )
+    }
+
+    /// Refund the funds of a submarine swap sending them to `address`
+    ///
+    /// The transaction is valid only after the timeout block height. `fee_rate` is in sats/kvb,
+    /// if not specified a default value is used.
+    pub fn refund_tx(
+        &self,
+        lockup_tx: &Transaction,
+        keypair: &Keypair,
+        address: &Address,
+        fee_rate: Option<f32>,
+    ) -> Result<Transaction, Error> {
+        if self.kind != SwapKind::Submarine {
+            return Err(Error::Generic(
+                "Only submarine swaps can be refunded".into(),
+            ));
+        }
+        let lock_time = LockTime::from_height(self.timeout)?;
+        self.spend(lockup_tx, keypair, address, fee_rate, vec![], lock_time)
+    }
+
+    /// Spend the lockup output with the claim leaf if `lock_time` is zero, with the refund leaf
+    /// otherwise
+    fn spend(
+        &self,
+        lockup_tx: &Transaction,
+        keypair: &Keypair,
+        address: &Address,
+        fee_rate: Option<f32>,
+        witness: Vec<Vec<u8>>,
+        lock_time: LockTime,
+    ) -> Result<Transaction, Error> {
+        if keypair.public_key() != self.user_key {
+            return Err(BoltzError::WrongKey.into());
+        }
+        if address.blinding_pubkey.is_none() {
+            return Err(Error::NotConfidentialAddress);
+        }
+        let script_pubkey = self.address()?.script_pubkey();
+        let (vout, txout) = lockup_tx
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, o)| o.script_pubkey == script_pubkey)
+            .ok_or_else(|| BoltzError::MissingLockupOutput(lockup_tx.txid()))?;
+        let secrets = txout.unblind(&EC, self.blinding_key)?;
+        let outpoint = OutPoint::new(lockup_tx.txid(), vout as u32);
+
+        // Build the transaction with a placeholder fee to compute its weight, the signature
+        // has always the same size
+        let fee_rate = fee_rate.unwrap_or(DEFAULT_FEE_RATE);
+        let tx = self.sign(
+            outpoint, txout, secrets, keypair, address, 1, &witness, lock_time,
+        )?;
+        let fee = (tx.discount_vsize() as f32 * fee_rate / 1000.0).ceil() as u64;
+        self.sign(
+            outpoint, txout, secrets, keypair, address, fee, &witness, lock_time,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sign(
+        &self,
+        outpoint: OutPoint,
+        txout: &TxOut,
+        secrets: TxOutSecrets,
+        keypair: &Keypair,
+        address: &Address,
+        fee: u64,
+        witness: &[Vec<u8>],
+        lock_time: LockTime,
+    ) -> Result<Transaction, Error> {
+        let amount =
+            secrets
+                .value
+                .checked_sub(fee)
+                .filter(|a| *a > 0)
+                .ok_or(BoltzError::AmountTooLow {
+                    amount: secrets.value,
+                    fee,
+                })?;
+
+        let mut pset = PartiallySignedTransaction::new_v2();
+        let mut input = Input::from_prevout(outpoint);
+        input.witness_utxo = Some(txout.clone());
+        input.sequence = Some(Sequence::ENABLE_LOCKTIME_NO_RBF);
+        pset.add_input(input);
+
+        let mut output = Output::new_explicit(
+            address.script_pubkey(),
+            amount,
+            secrets.asset,
+            address
+                .blinding_pubkey
+                .map(elements::bitcoin::PublicKey::new),
+        );
+        output.blinder_index = Some(0);
+        pset.add_output(output);
+        pset.add_output(Output::new_explicit(
+            Script::new(),
+            fee,
+            secrets.asset,
+            None,
+        ));
+
+        let inp_txout_sec = HashMap::from([(0, secrets)]);
+        pset.blind_last(&mut thread_rng(), &EC, &inp_txout_sec)?;
+        let mut tx = pset.extract_tx()?;
+        tx.lock_time = lock_time;
+
+        let leaf = if lock_time == LockTime::ZERO {
+            self.claim_leaf()
+        } else {
+            self.refund_leaf()
+        };
+        let leaf_hash = TapLeafHash::from_script(&leaf, LeafVersion::default());
+        let sighash = SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[txout]),
+                leaf_hash,
+                SchnorrSighashType::Default,
+                self.genesis_hash,
+            )
+            .map_err(|e| BoltzError::Sighash(e.to_string()))?;
+        let msg = Message::from_digest(sighash.to_byte_array());
+        let signature = EC.sign_schnorr_no_aux_rand(&msg, keypair);
+
+        let control_block = self
+            .spend_info()?
+            .control_block(&(leaf.clone(), LeafVersion::default()))
+            .ok_or(BoltzError::InvalidSwapTree)?;
+        let mut script_witness = vec![signature.as_ref().to_vec()];
+        script_witness.extend(witness.iter().cloned());
+        script_witness.push(leaf.into_bytes());
+        script_witness.push(control_block.serialize());
+        tx.input[0].witness.script_witness = script_witness;
+        Ok(tx)
+    }
+}
+
+/// Aggregate the keys as in MuSig2 (BIP327), the order of the keys matters
+fn key_agg(keys: &[PublicKey]) -> Result<XOnlyPublicKey, Error> {
+    let mut engine = tagged_engine("KeyAgg list");
+    for key in keys {
+        engine.input(&key.serialize());
+    }
+    let list_hash = sha256::Hash::from_engine(engine);
+
+    let second = keys.iter().find(|k| *k != &keys[0]);
+    let mut tweaked = vec![];
+    for key in keys {
+        if Some(key) == second {
+            tweaked.push(*key);
+        } else {
+            let mut engine = tagged_engine("KeyAgg coefficient");
+            engine.input(list_hash.as_byte_array());
+            engine.input(&key.serialize());
+            let coefficient =
+                Scalar::from_be_bytes(sha256::Hash::from_engine(engine).to_byte_array())
+                    .map_err(|_| Error::Generic("Invalid key aggregation coefficient".into()))?;
+            tweaked.push(key.mul_tweak(&EC, &coefficient)?);
+        }
+    }
+    let keys: Vec<_> = tweaked.iter().collect();
+    Ok(PublicKey::combine_keys(&keys)?.x_only_public_key().0)
+}
+
+fn tagged_engine(tag: &str) -> sha256::HashEngine {
+    let tag = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_byte_array());
+    engine.input(tag.as_byte_array());
+    engine
+}
+
+/// Context for actions interacting with the Boltz API
+pub struct BoltzClient {
+    url: String,
+}
+
+impl BoltzClient {
+    /// A client of the Boltz API at `url`, for instance a local instance for regtest
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn new_mainnet() -> Self {
+        Self::new(URL_MAINNET)
+    }
+
+    pub fn new_testnet() -> Self {
+        Self::new(URL_TESTNET)
+    }
+
+    /// Limits and fees of submarine swaps
+    pub async fn submarine_pair(&self) -> Result<PairInfo, Error> {
+        let value = self.get("swap/submarine").await?;
+        pair(value, "L-BTC", "BTC")
+    }
+
+    /// Limits and fees of reverse swaps
+    pub async fn reverse_pair(&self) -> Result<PairInfo, Error> {
+        let value = self.get("swap/reverse").await?;
+        pair(value, "BTC", "L-BTC")
+    }
+
+    /// Create a submarine swap paying `invoice`
+    pub async fn create_submarine_swap(
+        &self,
+        invoice: &str,
+        refund_key: PublicKey,
+    ) -> Result<SubmarineSwap, Error> {
+        let body = submarine_request(invoice, refund_key);
+        self.post("swap/submarine", &body).await
+    }
+
+    /// Create a reverse swap with an invoice of `amount` satoshi
+    pub async fn create_reverse_swap(
+        &self,
+        amount: u64,
+        preimage: &Preimage,
+        claim_key: PublicKey,
+    ) -> Result<ReverseSwap, Error> {
+        let body = reverse_request(amount, preimage, claim_key);
+        self.post("swap/reverse", &body).await
+    }
+
+    pub async fn swap_status(&self, id: &str) -> Result<SwapStatus, Error> {
+        let value = self.get(&format!("swap/{id}")).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Broadcast a claim or refund transaction through the Boltz API
+    pub async fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        let body = broadcast_request(tx);
+        let r: BroadcastResponse = self.post("chain/L-BTC/transaction", &body).await?;
+        Ok(Txid::from_str(&r.id)?)
+    }
+
+    async fn get(&self, path: &str) -> Result<serde_json::Value, Error> {
+        let response = reqwest::Client::new()
+            .get(format!("{}/{path}", self.url))
+            .send()
+            .await?;
+        let status = response.status();
+        parse_response(status.is_success(), response.json().await?)
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, Error> {
+        let response = reqwest::Client::new()
+            .post(format!("{}/{path}", self.url))
+            .json(body)
+            .send()
+            .await?;
+        let status = response.status();
+        let value = parse_response(status.is_success(), response.json().await?)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Limits and fees of submarine swaps
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn blocking_submarine_pair(&self) -> Result<PairInfo, Error> {
+        let value = self.blocking_get("swap/submarine")?;
+        pair(value, "L-BTC", "BTC")
+    }
+
+    /// Limits and fees of reverse swaps
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn blocking_reverse_pair(&self) -> Result<PairInfo, Error> {
+        let value = self.blocking_get("swap/reverse")?;
+        pair(value, "BTC", "L-BTC")
+    }
+
+    /// Create a submarine swap paying `invoice`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn blocking_create_submarine_swap(
+        &self,
+        invoice: &str,
+        refund_key: PublicKey,
+    ) -> Result<SubmarineSwap, Error> {
+        let body = submarine_request(invoice, refund_key);
+        self.blocking_post("swap/submarine", &body)
+    }
+
+    /// Create a reverse swap with an invoice of `amount` satoshi
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn blocking_create_reverse_swap(
+        &self,
+        amount: u64,
+        preimage: &Preimage,
+        claim_key: PublicKey,
+    ) -> Result<ReverseSwap, Error> {
+        let body = reverse_request(amount, preimage, claim_key);
+        self.blocking_post("swap/reverse", &body)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn blocking_swap_status(&self, id: &str) -> Result<SwapStatus, Error> {
+        let value = self.blocking_get(&format!("swap/{id}"))?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Broadcast a claim or refund transaction through the Boltz API
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn blocking_broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        let body = broadcast_request(tx);
+        let r: BroadcastResponse = self.blocking_post("chain/L-BTC/transaction", &body)?;
+        Ok(Txid::from_str(&r.id)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn blocking_get(&self, path: &str) -> Result<serde_json::Value, Error> {
+        let response = reqwest::blocking::Client::new()
+            .get(format!("{}/{path}", self.url))
+            .send()?;
+        let status = response.status();
+        parse_response(status.is_success(), response.json()?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn blocking_post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, Error> {
+        let response = reqwest::blocking::Client::new()
+            .post(format!("{}/{path}", self.url))
+            .json(body)
+            .send()?;
+        let status = response.status();
+        let value = parse_response(status.is_success(), response.json()?)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+fn parse_response(success: bool, value: serde_json::Value) -> Result<serde_json::Value, Error> {
+    if success {
+        Ok(value)
+    } else {
+        let message = match serde_json::from_value::<ErrorResponse>(value.clone()) {
+            Ok(e) => e.error,
+            Err(_) => value.to_string(),
+        };
+        Err(BoltzError::Api(message).into())
+    }
+}
+
+fn pair(value: serde_json::Value, from: &str, to: &str) -> Result<PairInfo, Error> {
+    let pair = value
+        .get(from)
+        .and_then(|v| v.get(to))
+        .ok_or_else(|| BoltzError::Api(format!("Missing pair {from}/{to}")))?;
+    Ok(serde_json::from_value(pair.clone())?)
+}
+
+fn submarine_request(invoice: &str, refund_key: PublicKey) -> SubmarineRequest<'_> {
+    SubmarineRequest {
+        from: "L-BTC",
+        to: "BTC",
+        invoice,
+        refund_public_key: refund_key.to_string(),
+    }
+}
+
+fn reverse_request(
+    amount: u64,
+    preimage: &Preimage,
+    claim_key: PublicKey,
+) -> ReverseRequest<'static> {
+    ReverseRequest {
+        from: "BTC",
+        to: "L-BTC",
+        invoice_amount: amount,
+        preimage_hash: preimage.sha256().to_string(),
+        claim_public_key: claim_key.to_string(),
+    }
+}
+
+fn broadcast_request(tx: &Transaction) -> BroadcastRequest {
+    BroadcastRequest {
+        hex: elements::encode::serialize_hex(tx),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(s: &str) -> PublicKey {
+        PublicKey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn boltz_key_agg() {
+        // test vectors from BIP327
+        let x1 = key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9");
+        let x2 = key("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659");
+        let x3 = key("023590A94E768F8E1815C2F24B4D80A8E3149316C3518CE7B7AD338368D038CA66");
+        assert_eq!(
+            key_agg(&[x1, x2, x3]).unwrap().to_string(),
+            "90539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c"
+        );
+        assert_eq!(
+            key_agg(&[x3, x2, x1]).unwrap().to_string(),
+            "6204de8b083426dc6eaf9502d27024d53fc826bf7d2012148a0575435df54b2b"
+        );
+        assert_eq!(
+            key_agg(&[x1, x1, x1]).unwrap().to_string(),
+            "b436e3bad62b8cd409969a224731c193d051162d8c5ae8b109306127da3aa935"
+        );
+        assert_eq!(
+            key_agg(&[x1, x1, x2, x2]).unwrap().to_string(),
+            "69bc22bfa5d106306e48a20679de1d7389386124d07571d0d872686028c26a3e"
+        );
+    }
+
+    #[test]
+    fn boltz_reverse_claim() {
+        let network = ElementsNetwork::LiquidTestnet;
+        let user = Keypair::from_seckey_str(
+            &EC,
+            "0101010101010101010101010101010101010101010101010101010101010101",
+        )
+        .unwrap();
+        let boltz = Keypair::from_seckey_str(
+            &EC,
+            "0202020202020202020202020202020202020202020202020202020202020202",
+        )
+        .unwrap();
+        let blinding_key = SecretKey::from_slice(&[3; 32]).unwrap();
+        let preimage = Preimage::from_bytes([4; 32]);
+
+        // Build the swap as the server would
+        let expected = SwapScript {
+            kind: SwapKind::Reverse,
+            hash160: ripemd160::Hash::hash(preimage.sha256().as_byte_array()),
+            boltz_key: boltz.public_key(),
+            user_key: user.public_key(),
+            timeout: 1_000_000,
+            blinding_key,
+            network,
+            genesis_hash: genesis_hash(network),
+        };
+        let address = expected.address().unwrap();
+        let leaf = |script: Script| Leaf {
+            version: 0xc4,
+            output: script.into_bytes(),
+        };
+        let mut swap = ReverseSwap {
+            id: "id".into(),
+            invoice: "lntb1".into(),
+            lockup_address: address.to_string(),
+            onchain_amount: 10_000,
+            timeout_block_height: 1_000_000,
+            refund_public_key: boltz.public_key().to_string(),
+            swap_tree: SwapTree {
+                claim_leaf: leaf(expected.claim_leaf()),
+                refund_leaf: leaf(expected.refund_leaf()),
+            },
+            blinding_key: blinding_key.display_secret().to_string(),
+        };
+        let script = swap
+            .swap_script(&preimage, user.public_key(), 10_000, network)
+            .unwrap();
+        assert_eq!(script.address().unwrap(), address);
+
+        let err = swap
+            .swap_script(&preimage, user.public_key(), 10_001, network)
+            .unwrap_err();
+        assert!(matches!(err, Error::Boltz(BoltzError::InvalidAmount(..))));
+        let err = swap
+            .swap_script(
+                &Preimage::from_bytes([5; 32]),
+                user.public_key(),
+                0,
+                network,
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::Boltz(BoltzError::InvalidSwapTree)));
+        let err = swap
+            .swap_script(&preimage, boltz.public_key(), 0, network)
+            .unwrap_err();
+        assert!(matches!(err, Error::Boltz(BoltzError::InvalidSwapTree)));
+        swap.lockup_address = Address::p2tr_tweaked(
+            script.spend_info().unwrap().output_key(),
+            None,
+            network.address_params(),
+        )
+        .to_string();
+        let err = swap
+            .swap_script(&preimage, user.public_key(), 0, network)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Boltz(BoltzError::InvalidLockupAddress)
+        ));
+
+        // Lock funds to the swap address
+        let asset = network.policy_asset();
+        let mut lockup = PartiallySignedTransaction::new_v2();
+        let mut input = Input::from_prevout(OutPoint::default());
+        let secrets = TxOutSecrets::new(
+            asset,
+            elements::confidential::AssetBlindingFactor::zero(),
+            20_000,
+            elements::confidential::ValueBlindingFactor::zero(),
+        );
+        input.witness_utxo = Some(TxOut::new_fee(20_000, asset));
+        lockup.add_input(input);
+        let mut output = Output::new_explicit(
+            address.script_pubkey(),
+            10_000,
+            asset,
+            address
+                .blinding_pubkey
+                .map(elements::bitcoin::PublicKey::new),
+        );
+        output.blinder_index = Some(0);
+        lockup.add_output(output);
+        lockup.add_output(Output::new_explicit(Script::new(), 10_000, asset, None));
+        lockup
+            .blind_last(&mut thread_rng(), &EC, &HashMap::from([(0, secrets)]))
+            .unwrap();
+        let lockup_tx = lockup.extract_tx().unwrap();
+
+        let destination = Address::p2wpkh(
+            &elements::bitcoin::PublicKey::new(user.public_key()),
+            Some(boltz.public_key()),
+            network.address_params(),
+        );
+        let err = script
+            .claim_tx(
+                &lockup_tx,
+                &Preimage::from_bytes([5; 32]),
+                &user,
+                &destination,
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::Boltz(BoltzError::WrongPreimage)));
+        let err = script
+            .claim_tx(&lockup_tx, &preimage, &boltz, &destination, None)
+            .unwrap_err();
+        assert!(matches!(err, Error::Boltz(BoltzError::WrongKey)));
+
+        let tx = script
+            .claim_tx(&lockup_tx, &preimage, &user, &destination, None)
+            .unwrap();
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output.txid, lockup_tx.txid());
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(tx.output[0].script_pubkey, destination.script_pubkey());
+        assert!(tx.output[0].value.is_confidential());
+        let fee = tx.fee_in(asset);
+        assert_eq!(fee, (tx.discount_vsize() as f32 * 0.1).ceil() as u64);
+
+        let witness = &tx.input[0].witness.script_witness;
+        assert_eq!(witness.len(), 4);
+        assert_eq!(witness[1], preimage.as_bytes());
+        assert_eq!(witness[2], script.claim_leaf().into_bytes());
+
+        // Verify the signature
+        let leaf_hash = TapLeafHash::from_script(&script.claim_leaf(), LeafVersion::default());
+        let sighash = SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[&lockup_tx.output[0]]),
+                leaf_hash,
+                SchnorrSighashType::Default,
+                genesis_hash(network),
+            )
+            .unwrap();
+        let msg = Message::from_digest(sighash.to_byte_array());
+        let sig = elements::secp256k1_zkp::schnorr::Signature::from_slice(&witness[0]).unwrap();
+        EC.verify_schnorr(&sig, &msg, &user.x_only_public_key().0)
+            .unwrap();
+
+        // A reverse swap can't be refunded by the wallet
+        assert!(script
+            .refund_tx(&lockup_tx, &user, &destination, None)
+            .is_err());
+    }
+
+    #[test]
+    fn boltz_submarine_leaves() {
+        let user = key("02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9");
+        let boltz = key("03DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659");
+        let network = ElementsNetwork::Liquid;
+        let script = SwapScript {
+            kind: SwapKind::Submarine,
+            hash160: ripemd160::Hash::hash(&[0; 32]),
+            boltz_key: boltz,
+            user_key: user,
+            timeout: 3_000_000,
+            blinding_key: SecretKey::from_slice(&[3; 32]).unwrap(),
+            network,
+            genesis_hash: genesis_hash(network),
+        };
+        assert_eq!(
+            script.claim_leaf().to_string(),
+            format!(
+                "Script(OP_HASH160 OP_PUSHBYTES_20 {} OP_EQUALVERIFY OP_PUSHBYTES_32 {} OP_CHECKSIG)",
+                ripemd160::Hash::hash(&[0; 32]),
+                boltz.x_only_public_key().0
+            )
+        );
+        assert_eq!(
+            script.refund_leaf().to_string(),
+            format!(
+                "Script(OP_PUSHBYTES_32 {} OP_CHECKSIGVERIFY OP_PUSHBYTES_3 c0c62d OP_CLTV)",
+                user.x_only_public_key().0
+            )
+        );
+        assert!(script.address().unwrap().is_blinded());
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn boltz_network_calls() {
+        let client = BoltzClient::new_testnet();
+        let pair = client.submarine_pair().await.unwrap();
+        assert!(pair.limits.minimal > 0);
+        let pair = client.reverse_pair().await.unwrap();
+        assert!(pair.limits.maximal > pair.limits.minimal);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::elements::confidential::{Value, ValueBlindingFactor};
+use crate::elements::pset::PartiallySignedTransaction;
+use crate::elements::secp256k1_zkp::{RangeProof, Scalar, SecretKey, SurjectionProof};
+use crate::elements::{OutPoint, RangeProofMessage, Script, Transaction, Txid};
+use crate::util::EC;
+use crate::wollet::Wollet;
+use crate::Error;
+use elements::{BlindAssetProofs, BlindValueProofs};
+use rand::thread_rng;
+
+impl Wollet {
+    pub(crate) fn unconfirmed_tx(&self, txid: &Txid) -> Result<Transaction, Error> {
+        match self.store.cache.heights.get(txid) {
+            None => Err(Error::MissingTransaction),
+            Some(Some(_)) => Err(Error::ConfirmedTransaction(*txid)),
+            Some(None) => self.get_tx(txid),
+        }
+    }
+
+    /// Create a PSET replacing the unconfirmed wallet transaction `txid` with a higher fee
+    ///
+    /// The replacement spends the same inputs and keeps all the outputs, except the fee output,
+    /// which is increased to reach `fee_rate` (in sats/kvb), and the L-BTC output of the wallet
+    /// with the higher value, which is reduced by the same amount. Since the blinding factors of
+    /// the outputs not owned by the wallet are unknown, their commitments and proofs are kept as
+    /// they are and the value blinding factor of the reduced output is adjusted to keep the
+    /// transaction balanced.
+    ///
+    /// All the inputs must belong to the wallet. The returned PSET must be signed and broadcast
+    /// as usual, the network must accept the replacement of the original transaction.
+    ///
+    /// Only the outputs owned by the wallet have explicit values and blind proofs, thus
+    /// [`Wollet::get_details()`] fails on the returned PSET if there are outputs sent elsewhere.
+    pub fn bump_fee(
+        &self,
+        txid: &Txid,
+        fee_rate: f32,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        let tx = self.unconfirmed_tx(txid)?;
+        if tx.input.iter().any(|i| i.has_issuance() || i.is_pegin) {
+            return Err(Error::Generic(
+                "Cannot bump the fee of a transaction with issuances or pegins".into(),
+            ));
+        }
+        let policy_asset = self.policy_asset();
+
+        let mut pset = PartiallySignedTransaction::from_tx(tx.clone());
+        let mut inp_weight = 0;
+        for (input, txin) in pset.inputs_mut().iter_mut().zip(tx.input.iter()) {
+            let mut txout = self.get_txout(&txin.previous_output)?;
+            self.index(&txout.script_pubkey)?;
+            inp_weight += self.max_weight_to_satisfy();
+            input.in_utxo_rangeproof = txout.witness.rangeproof.take();
+            input.witness_utxo = Some(txout);
+            input.final_script_sig = None;
+            input.final_script_witness = None;
+        }
+        let fee_vout = tx
+            .output
+            .iter()
+            .position(|o| o.is_fee() && o.asset.explicit() == Some(policy_asset))
+            .ok_or_else(|| Error::Generic("Missing fee output".into()))?;
+        let old_fee = tx.output[fee_vout].value.explicit().unwrap_or(0);
+
+        let (change_vout, change) = tx
+            .output
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| o.value.is_confidential())
+            .filter_map(|(vout, _)| {
+                let outpoint = OutPoint::new(*txid, vout as u32);
+                let secrets = self.store.cache.unblinded.get(&outpoint)?;
+                (secrets.asset == policy_asset).then_some((vout, *secrets))
+            })
+            .max_by_key(|(_, secrets)| secrets.value)
+            .ok_or_else(|| Error::Generic("Missing wallet L-BTC output to reduce".into()))?;
+
+        let weight = inp_weight + pset.extract_tx()?.discount_weight();
+        let vsize = weight.div_ceil(4);
+        let new_fee = (vsize as f32 * fee_rate / 1000.0).ceil() as u64;
+        if new_fee <= old_fee {
+            return Err(Error::FeeBumpTooLow { old_fee, new_fee });
+        }
+        let delta = new_fee - old_fee;
+        if change.value <= delta {
+            return Err(Error::InsufficientFunds {
+                missing_sats: delta + 1 - change.value,
+                asset_id: policy_asset,
+                is_token: false,
+            });
+        }
+
+        // v * abf + vbf must not change, so vbf' = vbf + delta * abf
+        let mut delta_bytes = [0u8; 32];
+        delta_bytes[24..].copy_from_slice(&delta.to_be_bytes());
+        let delta = Scalar::from_be_bytes(delta_bytes).expect("u64 is a valid scalar");
+        let abf_times_delta =
+            SecretKey::from_slice(change.asset_bf.into_inner().as_ref())?.mul_tweak(&delta)?;
+        let mut vbf = change.value_bf;
+        vbf += ValueBlindingFactor::from_slice(&abf_times_delta.secret_bytes())?;
+
+        let blinding_pubkey = |script_pubkey: &Script| -> Result<_, Error> {
+            let (chain, index) = self.index(script_pubkey)?;
+            let (_, blinding_pubkey) = self
+                .store
+                .cache
+                .scripts
+                .get(&(chain, index.into()))
+                .ok_or(Error::ScriptNotMine)?;
+            Ok(crate::bitcoin::PublicKey {
+                inner: *blinding_pubkey,
+                compressed: true,
+            })
+        };
+        let script_pubkey = &tx.output[change_vout].script_pubkey;
+        let msg = RangeProofMessage {
+            asset: change.asset,
+            bf: change.asset_bf,
+        };
+        let (value, nonce, rangeproof) = Value::Explicit(change.value - new_fee + old_fee).blind(
+            &EC,
+            vbf,
+            blinding_pubkey(script_pubkey)?.inner,
+            SecretKey::new(&mut thread_rng()),
+            script_pubkey,
+            &msg,
+        )?;
+
+        let outputs = pset.outputs_mut();
+        let change_output = &mut outputs[change_vout];
+        change_output.amount_comm = value.commitment();
+        change_output.ecdh_pubkey = nonce.commitment().map(|pk| crate::bitcoin::PublicKey {
+            inner: pk,
+            compressed: true,
+        });
+        change_output.value_rangeproof = Some(Box::new(rangeproof));
+        outputs[fee_vout].amount = Some(new_fee);
+
+        // Add explicit values and blind proofs to the wallet outputs, so that signers can verify them
+        let mut rng = thread_rng();
+        for (vout, output) in outputs.iter_mut().enumerate() {
+            let outpoint = OutPoint::new(*txid, vout as u32);
+            let Some(secrets) = self.store.cache.unblinded.get(&outpoint) else {
+                continue;
+            };
+            let (value, value_bf) = if vout == change_vout {
+                (change.value - new_fee + old_fee, vbf)
+            } else {
+                (secrets.value, secrets.value_bf)
+            };
+            let (Some(value_comm), Some(asset_gen)) = (output.amount_comm, output.asset_comm)
+            else {
+                continue;
+            };
+            output.blinding_key = Some(blinding_pubkey(&output.script_pubkey)?);
+            output.asset = Some(secrets.asset);
+            output.blind_asset_proof = Some(Box::new(SurjectionProof::blind_asset_proof(
+                &mut rng,
+                &EC,
+                secrets.asset,
+                secrets.asset_bf,
+            )?));
+            output.amount = Some(value);
+            output.blind_value_proof = Some(Box::new(RangeProof::blind_value_proof(
+                &mut rng, &EC, value, value_comm, asset_gen, value_bf,
+            )?));
+        }
+
+        self.add_details(&mut pset)?;
+        Ok(pset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::test_wollet_with_many_transactions;
+
+    #[test]
+    fn test_bump_fee() {
+        let mut wollet = test_wollet_with_many_transactions();
+        let policy_asset = wollet.policy_asset();
+        let key = wollet.descriptor().key.clone();
+        let txids: Vec<_> = wollet.store.cache.heights.keys().cloned().collect();
+        assert!(matches!(
+            wollet.bump_fee(&txids[0], 1000.0),
+            Err(Error::ConfirmedTransaction(_))
+        ));
+
+        for txid in txids.iter() {
+            wollet.store.cache.heights.insert(*txid, None);
+        }
+        let mut bumped = 0;
+        for txid in txids.iter() {
+            if bumped == 3 {
+                break;
+            }
+            // transactions spending inputs not owned by the wallet cannot be bumped
+            let Ok(pset) = wollet.bump_fee(txid, 1000.0) else {
+                continue;
+            };
+            bumped += 1;
+            let original = wollet.get_tx(txid).unwrap();
+            let replacement = pset.extract_tx().unwrap();
+            assert_eq!(original.input.len(), replacement.input.len());
+            assert_eq!(original.output.len(), replacement.output.len());
+            let delta = replacement.fee_in(policy_asset) - original.fee_in(policy_asset);
+            assert!(delta > 0);
+
+            let spent: Vec<_> = pset
+                .inputs()
+                .iter()
+                .map(|i| i.witness_utxo.clone().unwrap())
+                .collect();
+            replacement.verify_tx_amt_proofs(&EC, &spent).unwrap();
+
+            // the wallet outputs can still be unblinded, only one has a lower value
+            let before = crate::unblind_transaction(&original, &key).unwrap();
+            let after = crate::unblind_transaction(&replacement, &key).unwrap();
+            assert_eq!(before.len(), after.len());
+            let changed: Vec<_> = before
+                .iter()
+                .filter(|(vout, secrets)| after[vout].value != secrets.value)
+                .collect();
+            assert_eq!(changed.len(), 1);
+            let (vout, secrets) = changed[0];
+            assert_eq!(secrets.asset, policy_asset);
+            assert_eq!(after[vout].value, secrets.value - delta);
+
+            assert!(matches!(
+                wollet.bump_fee(txid, 0.1),
+                Err(Error::FeeBumpTooLow { .. })
+            ));
+        }
+        assert!(bumped > 0);
+    }
+}
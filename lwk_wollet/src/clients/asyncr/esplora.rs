@@ -1,8 +1,8 @@
 //! NOTE This module is temporary, as soon we make the other clients async this will be merged in
 //! the standard esplora client of which contain a lot of duplicated code.
 
+use crate::clients::{blocks_to_verify, try_unblind, Capability, History, RetryPolicy};
 use crate::clients::{check_witnesses_non_empty, LastUnused};
-use crate::clients::{try_unblind, Capability, History};
 use crate::BlindingPublicKey;
 use crate::{
     clients::Data,
@@ -39,6 +39,8 @@ pub struct EsploraClient {
     waterfalls: bool,
     waterfalls_server_recipient: Option<Recipient>,
 
+    retry: RetryPolicy,
+
     /// Avoid encrypting the descriptor field
     pub(crate) waterfalls_avoid_encryption: bool,
 
@@ -54,7 +56,7 @@ impl EsploraClient {
     }
 
     pub(crate) async fn last_block_hash(&mut self) -> Result<elements::BlockHash, crate::Error> {
-        let response = get_with_retry(&self.client, &self.tip_hash_url).await?;
+        let response = get_with_retry(&self.client, &self.retry, &self.tip_hash_url).await?;
         Ok(BlockHash::from_str(&response.text().await?)?)
     }
 
@@ -67,7 +69,7 @@ impl EsploraClient {
 
     async fn header(&mut self, last_block_hash: BlockHash) -> Result<elements::BlockHeader, Error> {
         let header_url = format!("{}/block/{}/header", self.base_url, last_block_hash);
-        let response = get_with_retry(&self.client, &header_url).await?;
+        let response = get_with_retry(&self.client, &self.retry, &header_url).await?;
         let header_bytes = Vec::<u8>::from_hex(&response.text().await?)?;
 
         let header = elements::BlockHeader::consensus_decode(&header_bytes[..])?;
@@ -94,7 +96,7 @@ impl EsploraClient {
 
     pub(crate) async fn get_transaction(&self, txid: Txid) -> Result<elements::Transaction, Error> {
         let tx_url = format!("{}/tx/{}/raw", self.base_url, txid);
-        let response = get_with_retry(&self.client, &tx_url).await?;
+        let response = get_with_retry(&self.client, &self.retry, &tx_url).await?;
         let tx = elements::Transaction::consensus_decode(&response.bytes().await?[..])?;
 
         Ok(tx)
@@ -122,13 +124,13 @@ impl EsploraClient {
                 Some(block_hash) => *block_hash,
                 None => {
                     let block_height = format!("{}/block-height/{}", self.base_url, height);
-                    let response = get_with_retry(&self.client, &block_height).await?;
+                    let response = get_with_retry(&self.client, &self.retry, &block_height).await?;
                     BlockHash::from_str(&response.text().await?)?
                 }
             };
 
             let block_header = format!("{}/block/{}/header", self.base_url, block_hash);
-            let response = get_with_retry(&self.client, &block_header).await?;
+            let response = get_with_retry(&self.client, &self.retry, &block_header).await?;
             let header_bytes = Vec::<u8>::from_hex(&response.text().await?)?;
 
             let header = elements::BlockHeader::consensus_decode(&header_bytes[..])?;
@@ -152,14 +154,14 @@ impl EsploraClient {
             )?;
             let url = format!("{}/address/{}/txs", self.base_url, address);
             // TODO must handle paging -> https://github.com/blockstream/esplora/blob/master/API.md#addresses
-            let response = get_with_retry(&self.client, &url).await?;
+            let response = get_with_retry(&self.client, &self.retry, &url).await?;
 
             // TODO going through string and then json is not as efficient as it could be but we prioritize debugging for now
             let text = response.text().await?;
             let json: Vec<EsploraTx> = match serde_json::from_str(&text) {
                 Ok(e) => e,
                 Err(e) => {
-                    log::warn!("error {e:?} in converting following text:\n{text}");
+                    tracing::warn!("error {e:?} in converting following text:\n{text}");
                     return Err(e.into());
                 }
             };
@@ -190,6 +192,7 @@ impl EsploraClient {
             height_blockhash,
             height_timestamp,
             tip,
+            script_status: _,
         } = if self.waterfalls {
             if index != 0 {
                 return Err(Error::UsingWaterfallsWithNonZeroIndex);
@@ -212,24 +215,35 @@ impl EsploraClient {
         } else {
             self.tip().await?
         };
+        let reorged = self.reorged_blocks(wollet, &tip).await?;
+        let reorg_height = reorged.keys().min().cloned();
 
         let history_txs_id: HashSet<Txid> = txid_height.keys().cloned().collect();
         let new_txs = self
             .download_txs(&history_txs_id, &scripts, store, &descriptor)
             .await?;
-        let history_txs_heights_plus_tip: HashSet<Height> = txid_height
-            .values()
-            .filter_map(|e| *e)
+        let history_txs_heights: HashSet<Height> =
+            txid_height.values().filter_map(|e| *e).collect();
+        let history_txs_heights_plus_tip: HashSet<Height> = history_txs_heights
+            .iter()
+            .cloned()
             .chain(std::iter::once(tip.height))
             .collect();
-        let timestamps = self
+        let (timestamps, downloaded_hashes) = self
             .download_headers(
                 &history_txs_heights_plus_tip,
                 &height_blockhash,
                 &height_timestamp,
                 store,
+                reorg_height,
             )
             .await?;
+        let mut block_hashes = reorged;
+        block_hashes.extend(
+            downloaded_hashes
+                .into_iter()
+                .filter(|(h, _)| history_txs_heights.contains(h)),
+        );
 
         let store_last_unused_external = store
             .cache
@@ -247,14 +261,17 @@ impl EsploraClient {
             || last_unused_changed
             || !scripts.is_empty()
             || !timestamps.is_empty()
+            || reorg_height.is_some()
             || store.cache.tip != (tip.height, tip.block_hash());
 
         if changed {
-            log::debug!("something changed: !new_txs.txs.is_empty():{} last_unused_changed:{} !scripts.is_empty():{} !timestamps.is_empty():{}", !new_txs.txs.is_empty(), last_unused_changed, !scripts.is_empty(), !timestamps.is_empty() );
+            tracing::debug!("something changed: !new_txs.txs.is_empty():{} last_unused_changed:{} !scripts.is_empty():{} !timestamps.is_empty():{}", !new_txs.txs.is_empty(), last_unused_changed, !scripts.is_empty(), !timestamps.is_empty() );
 
             let txid_height_new: Vec<_> = txid_height
                 .iter()
                 .filter(|(k, v)| match store.cache.heights.get(*k) {
+                    // confirmed in a replaced block, re-verified even if at the same height
+                    Some(Some(h)) if reorg_height.is_some_and(|r| *h >= r) => true,
                     Some(e) => e != *v,
                     None => true,
                 })
@@ -277,7 +294,7 @@ impl EsploraClient {
                 .collect();
 
             let update = Update {
-                version: 2,
+                version: 3,
                 wollet_status,
                 new_txs,
                 txid_height_new,
@@ -285,6 +302,8 @@ impl EsploraClient {
                 timestamps,
                 scripts_with_blinding_pubkey,
                 tip,
+                block_hashes: block_hashes.into_iter().collect(),
+                script_status: vec![],
             };
             Ok(Some(update))
         } else {
@@ -463,6 +482,15 @@ impl EsploraClient {
         Ok(data)
     }
 
+    /// Change how failed requests are retried, for instance only for the next calls
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
     pub fn avoid_encryption(&mut self) {
         self.waterfalls_avoid_encryption = true;
     }
@@ -499,7 +527,7 @@ impl EsploraClient {
 
                     match try_unblind(output.clone(), descriptor) {
                             Ok(unblinded) => unblinds.push((outpoint, unblinded)),
-                            Err(_) => log::info!("{} cannot unblind, ignoring (could be sender messed up with the blinding process)", outpoint),
+                            Err(_) => tracing::info!("{} cannot unblind, see the unblindable outputs (could be sender messed up with the blinding process)", outpoint),
                         }
                 }
             }
@@ -510,15 +538,26 @@ impl EsploraClient {
         Ok(DownloadTxResult { txs, unblinds })
     }
 
+    /// The timestamps and the hashes of the blocks at the given heights not in the store
+    ///
+    /// Heights from `reorg_height` are downloaded again, since their blocks have been replaced.
     async fn download_headers(
         &self,
         history_txs_heights_plus_tip: &HashSet<Height>,
         height_blockhash: &HashMap<Height, BlockHash>,
         height_timestamp: &HashMap<Height, Timestamp>,
         store: &Store,
-    ) -> Result<Vec<(Height, Timestamp)>, Error> {
+        reorg_height: Option<Height>,
+    ) -> Result<(Vec<(Height, Timestamp)>, Vec<(Height, BlockHash)>), Error> {
         let mut result = vec![];
-        let heights_in_db: HashSet<Height> = store.cache.timestamps.keys().cloned().collect();
+        let mut hashes = vec![];
+        let heights_in_db: HashSet<Height> = store
+            .cache
+            .timestamps
+            .keys()
+            .filter(|h| reorg_height.map_or(true, |r| **h < r))
+            .cloned()
+            .collect();
         let heights_in_response: HashSet<Height> = height_timestamp.keys().cloned().collect();
         let heights_in_both: HashSet<Height> =
             heights_in_db.union(&heights_in_response).cloned().collect();
@@ -532,10 +571,11 @@ impl EsploraClient {
                 .get_headers(&heights_to_download, height_blockhash)
                 .await?
             {
-                result.push((h.height, h.time))
+                result.push((h.height, h.time));
+                hashes.push((h.height, h.block_hash()));
             }
 
-            log::debug!("{} headers_downloaded", heights_to_download.len());
+            tracing::debug!("{} headers_downloaded", heights_to_download.len());
         }
 
         let heights_to_insert = height_timestamp
@@ -543,8 +583,36 @@ impl EsploraClient {
             .filter(|e| !heights_in_db.contains(e.0))
             .map(|(h, t)| (*h, *t));
         result.extend(heights_to_insert);
+        hashes.extend(
+            height_blockhash
+                .iter()
+                .filter(|e| !heights_in_db.contains(e.0))
+                .map(|(h, b)| (*h, *b)),
+        );
 
-        Ok(result)
+        Ok((result, hashes))
+    }
+
+    /// Async version of [`crate::blocking::BlockchainBackend::reorged_blocks()`]
+    async fn reorged_blocks(
+        &self,
+        wollet: &Wollet,
+        tip: &elements::BlockHeader,
+    ) -> Result<HashMap<Height, BlockHash>, Error> {
+        let mut reorged = HashMap::new();
+        for (height, block_hash) in blocks_to_verify(wollet, tip) {
+            let Some(header) = self.get_headers(&[height], &HashMap::new()).await?.pop() else {
+                break;
+            };
+            if header.block_hash() == block_hash {
+                break;
+            }
+            reorged.insert(height, header.block_hash());
+        }
+        if let Some(height) = reorged.keys().min() {
+            tracing::info!("blocks replaced from height {height}");
+        }
+        Ok(reorged)
     }
 
     pub(crate) fn capabilities(&self) -> HashSet<Capability> {
@@ -562,6 +630,7 @@ pub struct EsploraClientBuilder {
     waterfalls: bool,
     network: ElementsNetwork,
     timeout: Option<u8>,
+    retry: RetryPolicy,
 }
 
 impl EsploraClientBuilder {
@@ -572,6 +641,7 @@ impl EsploraClientBuilder {
             waterfalls: false,
             network,
             timeout: None,
+            retry: RetryPolicy::patient(),
         }
     }
 
@@ -592,6 +662,13 @@ impl EsploraClientBuilder {
         self
     }
 
+    /// Set how requests failing with 429 (too many requests), 503 (service unavailable) or a
+    /// timeout are retried, by default [`RetryPolicy::patient()`]
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     /// Consume the builder and build a new [`EsploraClient`]
     pub fn build(self) -> EsploraClient {
         let client = match self.timeout {
@@ -605,6 +682,7 @@ impl EsploraClientBuilder {
             broadcast_url: format!("{}/tx", self.base_url),
             waterfalls: self.waterfalls,
             waterfalls_server_recipient: None,
+            retry: self.retry,
             waterfalls_avoid_encryption: false,
             network: self.network,
         }
@@ -625,36 +703,44 @@ fn client_with_timeout(timeout: u8) -> reqwest::Client {
     reqwest::Client::new()
 }
 
-async fn get_with_retry(client: &reqwest::Client, url: &str) -> Result<Response, Error> {
+async fn get_with_retry(
+    client: &reqwest::Client,
+    retry: &RetryPolicy,
+    url: &str,
+) -> Result<Response, Error> {
     let mut attempt = 0;
     loop {
-        let response = client.get(url).send().await?;
+        let response = match client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) if e.is_timeout() && attempt < retry.max_retries => {
+                let delay = retry.delay(attempt);
+                tracing::info!("{url} timed out, retrying in {delay:?}");
+                async_sleep(delay.as_millis() as i32).await;
+                attempt += 1;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        let level = if response.status() == 200 {
-            log::Level::Trace
+        let (status, bytes) = (response.status(), response.content_length());
+        if status == 200 {
+            tracing::trace!("{} status_code:{} - body bytes:{:?}", &url, status, bytes);
         } else {
-            log::Level::Info
-        };
-        log::log!(
-            level,
-            "{} status_code:{} - body bytes:{:?}",
-            &url,
-            response.status(),
-            response.content_length(),
-        );
+            tracing::info!("{} status_code:{} - body bytes:{:?}", &url, status, bytes);
+        }
 
         // 429 Too many requests
         // 503 Service Temporarily Unavailable
         if response.status() == 429 || response.status() == 503 {
-            if attempt > 6 {
-                log::warn!("{url} tried 6 times, failing");
+            if attempt >= retry.max_retries {
+                tracing::warn!("{url} retried {attempt} times, failing");
                 return Err(Error::Generic("Too many retry".to_string()));
             }
-            let secs = 1 << attempt;
+            let delay = retry.delay(attempt);
 
-            log::debug!("{url} waiting {secs}");
+            tracing::debug!("{url} waiting {delay:?}");
 
-            async_sleep(secs * 1000).await;
+            async_sleep(delay.as_millis() as i32).await;
             attempt += 1;
         } else {
             return Ok(response);
@@ -716,7 +802,7 @@ struct WaterfallsResult {
 /// Encrypt a plaintext using a recipient key
 ///
 /// This can be used to encrypt a descriptor to share with a "waterfalls" server
-fn encrypt(plaintext: &str, recipient: Recipient) -> Result<String, Error> {
+pub(crate) fn encrypt(plaintext: &str, recipient: Recipient) -> Result<String, Error> {
     let recipients = [recipient];
     let encryptor =
         age::Encryptor::with_recipients(recipients.iter().map(|e| e as &dyn age::Recipient))
@@ -736,7 +822,7 @@ fn encrypt(plaintext: &str, recipient: Recipient) -> Result<String, Error> {
 mod tests {
     use std::{collections::HashMap, str::FromStr};
 
-    use crate::{clients::asyncr::async_sleep, ElementsNetwork};
+    use crate::{clients::asyncr::async_sleep, clients::RetryPolicy, ElementsNetwork};
 
     use super::EsploraClient;
     use elements::{encode::Decodable, BlockHash};
@@ -744,7 +830,9 @@ mod tests {
     async fn get_block(base_url: &str, hash: BlockHash) -> elements::Block {
         let url = format!("{}/block/{}/raw", base_url, hash);
         let client = reqwest::Client::new();
-        let response = super::get_with_retry(&client, &url).await.unwrap();
+        let response = super::get_with_retry(&client, &RetryPolicy::patient(), &url)
+            .await
+            .unwrap();
         elements::Block::consensus_decode(&response.bytes().await.unwrap()[..]).unwrap()
     }
 
@@ -1,7 +1,9 @@
 //! Asyncronous clients to fetch data from the Blockchain. Suitable to be used in WASM environments like in the browser.
 
 mod esplora;
+mod stream;
 
 pub use esplora::async_sleep;
+pub(crate) use esplora::encrypt;
 pub use esplora::EsploraClient;
 pub use esplora::EsploraClientBuilder;
@@ -0,0 +1,75 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use elements::Txid;
+use futures::stream::{self, Stream};
+
+use super::{async_sleep, EsploraClient};
+use crate::{Error, WalletTx, Wollet};
+
+struct TxStream<'a> {
+    wollet: &'a mut Wollet,
+    client: &'a mut EsploraClient,
+    interval: Duration,
+
+    /// The height of the transactions already returned, `None` if unconfirmed
+    seen: HashMap<Txid, Option<u32>>,
+
+    /// The transactions to return, oldest first
+    pending: VecDeque<WalletTx>,
+
+    first: bool,
+}
+
+impl TxStream<'_> {
+    async fn scan(&mut self) -> Result<(), Error> {
+        if let Some(update) = self.client.full_scan(self.wollet).await? {
+            self.wollet.apply_update(update)?;
+        }
+        for tx in self.wollet.transactions()?.into_iter().rev() {
+            if self.seen.get(&tx.txid) != Some(&tx.height) {
+                self.seen.insert(tx.txid, tx.height);
+                self.pending.push_back(tx);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Wollet {
+    /// A stream of the wallet transactions that are new or changed height, for instance because
+    /// they confirmed
+    ///
+    /// The wallet is scanned with `client` every `interval` and the updates are applied to the
+    /// wallet. Transactions already in the wallet when the stream is created are not returned.
+    /// A failed scan is returned as an error and the stream continues with the next scan.
+    pub fn tx_stream<'a>(
+        &'a mut self,
+        client: &'a mut EsploraClient,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<WalletTx, Error>> + 'a {
+        let seen = self.store.cache.heights.clone();
+        let state = TxStream {
+            wollet: self,
+            client,
+            interval,
+            seen,
+            pending: VecDeque::new(),
+            first: true,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(tx) = state.pending.pop_front() {
+                    return Some((Ok(tx), state));
+                }
+                if !state.first {
+                    async_sleep(state.interval.as_millis() as i32).await;
+                }
+                state.first = false;
+                if let Err(e) = state.scan().await {
+                    return Some((Err(e), state));
+                }
+            }
+        })
+    }
+}
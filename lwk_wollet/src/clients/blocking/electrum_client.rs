@@ -1,28 +1,49 @@
 use crate::clients::check_witnesses_non_empty;
+use crate::clients::{CancellationToken, Capability, RetryPolicy};
+use crate::spv::MerkleProof;
 use crate::store::Height;
+use crate::ElementsNetwork;
 use crate::Error;
 use crate::History;
 
 use electrum_client::ScriptStatus;
-use electrum_client::{Client, ConfigBuilder, ElectrumApi, GetHistoryRes};
+use electrum_client::{
+    Batch, Client, ConfigBuilder, ElectrumApi, GetHistoryRes, Param, ToElectrumScriptHash,
+};
 use elements::encode::deserialize as elements_deserialize;
 use elements::encode::serialize as elements_serialize;
+use elements::hashes::Hash;
+use elements::hex::ToHex;
 use elements::Address;
-use elements::{bitcoin, BlockHash, BlockHeader, Script, Transaction, Txid};
-use std::collections::HashMap;
+use elements::{bitcoin, BlockHash, BlockHeader, Script, Transaction, TxMerkleNode, Txid};
+#[cfg(feature = "electrum_stream")]
+use futures::stream::{self, Stream};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::net::IpAddr;
 use std::str::FromStr;
+#[cfg(feature = "electrum_stream")]
+use std::time::Duration;
 
 use super::BlockchainBackend;
 
+/// The version of the electrum protocol negotiated with the servers
+pub const PROTOCOL_VERSION: &str = "1.4";
+
 /// A client to issue TCP requests to an electrum server.
 pub struct ElectrumClient {
     client: Client,
 
+    /// The software version of the server, as returned by `server.version`
+    server_software: String,
+
     tip: BlockHeader,
 
     script_status: HashMap<Script, ScriptStatus>,
+
+    retry: RetryPolicy,
+
+    cancel: CancellationToken,
 }
 
 /// An electrum url parsable from string in the following form: `tcp://example.com:50001` or `ssl://example.com:50002`
@@ -118,6 +139,37 @@ impl Debug for ElectrumClient {
 #[derive(Default)]
 pub struct ElectrumOptions {
     pub timeout: Option<u8>,
+
+    /// How connecting and requests failing because of network errors are retried
+    pub retry: RetryPolicy,
+
+    /// Stops waiting to retry connecting and requests once triggered
+    pub cancel: CancellationToken,
+
+    /// If set, the connection fails if the server is on another network, for instance a
+    /// bitcoin electrum server
+    ///
+    /// Regtest networks are not verified since their genesis block depends on the chain
+    /// parameters.
+    pub network: Option<ElementsNetwork>,
+}
+
+/// The features of an electrum server, as returned by `server.features`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerFeatures {
+    pub server_version: String,
+
+    /// The hash of the genesis block of the server chain
+    pub genesis_hash: BlockHash,
+
+    pub protocol_min: String,
+
+    pub protocol_max: String,
+
+    pub hash_function: Option<String>,
+
+    /// The height below which the server pruned the blocks, if any
+    pub pruning: Option<i64>,
 }
 
 impl ElectrumClient {
@@ -128,15 +180,64 @@ impl ElectrumClient {
 
     /// Creates an Electrum client specifying non default options like timeout
     pub fn with_options(url: &ElectrumUrl, options: ElectrumOptions) -> Result<Self, Error> {
-        let client = url.build_client(&options)?;
-        let header = client.block_headers_subscribe_raw()?;
+        let (client, server_software, header) = options.retry.retry_cancellable(
+            || {
+                let client = url.build_client(&options)?;
+                let server_software = negotiate_version(&client)?;
+                let header = client.block_headers_subscribe_raw()?;
+                Ok((client, server_software, header))
+            },
+            is_retryable,
+            &options.cancel,
+        )?;
         let tip: BlockHeader = elements_deserialize(&header.header)?;
 
-        Ok(Self {
+        let client = Self {
             client,
+            server_software,
             tip,
             script_status: HashMap::new(),
-        })
+            retry: options.retry,
+            cancel: options.cancel,
+        };
+        if let Some(network) = options.network {
+            client.verify_network(network)?;
+        }
+        Ok(client)
+    }
+
+    /// The software version of the server, for instance `electrs/0.4.1`
+    pub fn server_software(&self) -> &str {
+        &self.server_software
+    }
+
+    /// Check that the server is on `network`, comparing its genesis block
+    ///
+    /// Regtest networks are not verified since their genesis block depends on the chain
+    /// parameters.
+    pub fn verify_network(&self, network: ElementsNetwork) -> Result<(), Error> {
+        let Some(expected) = network.genesis_hash() else {
+            return Ok(());
+        };
+        let found = self.server_features()?.genesis_hash;
+        if found != expected {
+            return Err(Error::WrongGenesisHash { expected, found });
+        }
+        Ok(())
+    }
+
+    /// Change how requests failing because of network errors are retried, for instance only
+    /// for the next calls
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    /// Call `f` retrying requests according to `retry`, then restore the previous policy
+    pub fn with_retry_policy<T>(&mut self, retry: RetryPolicy, f: impl FnOnce(&Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.retry, retry);
+        let result = f(self);
+        self.retry = previous;
+        result
     }
 
     /// Return the status of an address as defined by the electrum protocol
@@ -167,9 +268,87 @@ impl ElectrumClient {
     pub fn ping(&self) -> Result<(), Error> {
         Ok(self.client.ping()?)
     }
+
+    /// The features of the server, such as the supported protocol versions and its chain
+    pub fn server_features(&self) -> Result<ServerFeatures, Error> {
+        let features = self.client.server_features()?;
+        // the hash is in the displayed (reversed) order
+        let mut genesis_hash = features.genesis_hash;
+        genesis_hash.reverse();
+        Ok(ServerFeatures {
+            server_version: features.server_version,
+            genesis_hash: BlockHash::from_byte_array(genesis_hash),
+            protocol_min: features.protocol_min,
+            protocol_max: features.protocol_max,
+            hash_function: features.hash_function,
+            pruning: features.pruning,
+        })
+    }
+
+    /// Get the merkle proof of inclusion of the transaction `txid` in the block at `height`
+    pub fn get_merkle_proof(&self, txid: &Txid, height: Height) -> Result<MerkleProof, Error> {
+        let txid = bitcoin::Txid::from_raw_hash(txid.to_raw_hash());
+        let res = self.client.transaction_get_merkle(&txid, height as usize)?;
+        Ok(MerkleProof {
+            block_height: res.block_height as u32,
+            pos: res.pos,
+            merkle: res
+                .merkle
+                .into_iter()
+                .map(|mut bytes| {
+                    // electrum returns hashes in the displayed (reversed) order
+                    bytes.reverse();
+                    TxMerkleNode::from_byte_array(bytes)
+                })
+                .collect(),
+        })
+    }
+
+    /// A stream of the new blocks, starting with the current tip
+    ///
+    /// New blocks are notified by the server through the headers subscription, which is checked
+    /// every `interval`. If several blocks are found at once, only the last one is returned.
+    ///
+    /// The calls to the server are blocking and are run with [`tokio::task::spawn_blocking`],
+    /// thus the stream must be polled within a tokio runtime.
+    #[cfg(feature = "electrum_stream")]
+    pub fn block_stream(
+        self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<BlockHeader, Error>> {
+        stream::unfold(
+            (self, None::<BlockHash>, true),
+            move |(client, mut last, mut first)| async move {
+                let mut client = client;
+                loop {
+                    if !first {
+                        tokio::time::sleep(interval).await;
+                    }
+                    first = false;
+                    let (c, tip) = tokio::task::spawn_blocking(move || {
+                        let tip = client.tip();
+                        (client, tip)
+                    })
+                    .await
+                    .ok()?;
+                    client = c;
+                    match tip {
+                        Ok(tip) if last == Some(tip.block_hash()) => continue,
+                        Ok(tip) => {
+                            last = Some(tip.block_hash());
+                            return Some((Ok(tip), (client, last, first)));
+                        }
+                        Err(e) => return Some((Err(e), (client, last, first))),
+                    }
+                }
+            },
+        )
+    }
 }
 impl BlockchainBackend for ElectrumClient {
     fn tip(&mut self) -> Result<BlockHeader, Error> {
+        let _span =
+            tracing::debug_span!("electrum_request", method = "headers.subscribe").entered();
         let mut popped_header = None;
         while let Some(header) = self.client.block_headers_pop_raw()? {
             popped_header = Some(header)
@@ -197,6 +376,8 @@ impl BlockchainBackend for ElectrumClient {
 
     fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
         check_witnesses_non_empty(tx)?; // We don't support legacy outputs, thus we always have the witness (or forget to sign/finalize)
+        let _span =
+            tracing::debug_span!("electrum_request", method = "transaction.broadcast").entered();
 
         let txid = self
             .client
@@ -205,13 +386,24 @@ impl BlockchainBackend for ElectrumClient {
     }
 
     fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>, Error> {
+        let _span = tracing::debug_span!(
+            "electrum_request",
+            method = "transaction.get",
+            batch = txids.len()
+        )
+        .entered();
         let txids: Vec<bitcoin::Txid> = txids
             .iter()
             .map(|t| bitcoin::Txid::from_raw_hash(t.to_raw_hash()))
             .collect();
 
         let mut result = vec![];
-        for tx in self.client.batch_transaction_get_raw(&txids)? {
+        let txs = self.retry.retry_cancellable(
+            || Ok(self.client.batch_transaction_get_raw(&txids)?),
+            is_retryable,
+            &self.cancel,
+        )?;
+        for tx in txs {
             let tx: Transaction = elements::encode::deserialize(&tx)?;
             result.push(tx);
         }
@@ -223,29 +415,149 @@ impl BlockchainBackend for ElectrumClient {
         heights: &[Height],
         _: &HashMap<Height, BlockHash>,
     ) -> Result<Vec<BlockHeader>, Error> {
+        let _span = tracing::debug_span!(
+            "electrum_request",
+            method = "block.header",
+            batch = heights.len()
+        )
+        .entered();
         let mut result = vec![];
-        for header in self.client.batch_block_header_raw(heights)? {
+        let headers = self.retry.retry_cancellable(
+            || Ok(self.client.batch_block_header_raw(heights)?),
+            is_retryable,
+            &self.cancel,
+        )?;
+        for header in headers {
             let header: BlockHeader = elements::encode::deserialize(&header)?;
             result.push(header);
         }
         Ok(result)
     }
 
+    fn capabilities(&self) -> HashSet<Capability> {
+        [Capability::ScriptStatus].into_iter().collect()
+    }
+
+    fn set_cancellation_token(&mut self, cancel: &CancellationToken) {
+        self.cancel = cancel.clone();
+    }
+
+    fn get_scripts_status(&mut self, scripts: &[&Script]) -> Result<Vec<Option<[u8; 32]>>, Error> {
+        let _span = tracing::debug_span!(
+            "electrum_request",
+            method = "scripthash.subscribe",
+            batch = scripts.len()
+        )
+        .entered();
+        let scripts: Vec<&bitcoin::Script> = scripts
+            .iter()
+            .map(|t| bitcoin::Script::from_bytes(t.as_bytes()))
+            .collect();
+
+        // Scripts already subscribed on this connection are subscribed again to get their
+        // current status, discarding the queued notifications
+        let mut subscribed = vec![];
+        let mut new = vec![];
+        for (i, script) in scripts.iter().enumerate() {
+            match self.client.script_pop(script) {
+                Ok(_) => {
+                    while self.client.script_pop(script)?.is_some() {}
+                    subscribed.push(i);
+                }
+                Err(electrum_client::Error::NotSubscribed(_)) => new.push(i),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut result = vec![None; scripts.len()];
+        if !new.is_empty() {
+            let new_scripts: Vec<&bitcoin::Script> = new.iter().map(|i| scripts[*i]).collect();
+            let statuses = self.client.batch_script_subscribe(&new_scripts)?;
+            for (i, status) in new.into_iter().zip(statuses) {
+                result[i] = status.map(|s| *s);
+            }
+        }
+        if !subscribed.is_empty() {
+            let mut batch = Batch::default();
+            for i in subscribed.iter() {
+                let script_hash = scripts[*i].to_electrum_scripthash();
+                batch.raw(
+                    "blockchain.scripthash.subscribe".to_string(),
+                    vec![Param::String(script_hash[..].to_hex())],
+                );
+            }
+            let values = self.client.batch_call(&batch)?;
+            for (i, value) in subscribed.into_iter().zip(values) {
+                let status: Option<ScriptStatus> = serde_json::from_value(value)?;
+                result[i] = status.map(|s| *s);
+            }
+        }
+        Ok(result)
+    }
+
     fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
+        let _span = tracing::debug_span!(
+            "electrum_request",
+            method = "scripthash.get_history",
+            batch = scripts.len()
+        )
+        .entered();
         let scripts: Vec<&bitcoin::Script> = scripts
             .iter()
             .map(|t| bitcoin::Script::from_bytes(t.as_bytes()))
             .collect();
 
         Ok(self
-            .client
-            .batch_script_get_history(&scripts)?
+            .retry
+            .retry_cancellable(
+                || Ok(self.client.batch_script_get_history(&scripts)?),
+                is_retryable,
+                &self.cancel,
+            )?
             .into_iter()
             .map(|e| e.into_iter().map(Into::into).collect())
             .collect())
     }
 }
 
+/// Negotiate the protocol version with `server.version`, returning the server software version
+fn negotiate_version(client: &Client) -> Result<String, Error> {
+    let client_name = format!("lwk/{}", env!("CARGO_PKG_VERSION"));
+    let res = client.raw_call(
+        "server.version",
+        [
+            Param::String(client_name),
+            Param::String(PROTOCOL_VERSION.to_string()),
+        ],
+    )?;
+    parse_server_version(&res)
+}
+
+fn parse_server_version(res: &serde_json::Value) -> Result<String, Error> {
+    match res.as_array().map(|a| &a[..]) {
+        Some([software, version]) => {
+            let version = version.as_str().unwrap_or_default();
+            if version != PROTOCOL_VERSION {
+                return Err(Error::UnsupportedElectrumProtocol(version.to_string()));
+            }
+            Ok(software.as_str().unwrap_or_default().to_string())
+        }
+        _ => Err(Error::UnsupportedElectrumProtocol(res.to_string())),
+    }
+}
+
+/// Network errors, that might not happen again
+fn is_retryable(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::ClientError(
+            electrum_client::Error::IOError(_)
+                | electrum_client::Error::SharedIOError(_)
+                | electrum_client::Error::AllAttemptsErrored(_)
+        )
+    )
+}
+
 impl From<GetHistoryRes> for History {
     fn from(value: GetHistoryRes) -> Self {
         History {
@@ -284,7 +596,8 @@ pub enum UrlError {
 
 #[cfg(test)]
 mod tests {
-    use super::{ElectrumUrl, UrlError};
+    use super::{parse_server_version, ElectrumUrl, UrlError};
+    use crate::Error;
 
     fn check_url(url: &str, url_no_scheme: &str, tls: bool, validate_domain: bool) {
         let electrum_url: ElectrumUrl = url.parse().unwrap();
@@ -346,6 +659,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_server_version() {
+        let res = serde_json::json!(["electrs/0.4.1", "1.4"]);
+        assert_eq!(parse_server_version(&res).unwrap(), "electrs/0.4.1");
+
+        let res = serde_json::json!(["ElectrumX 1.16.0", "1.2"]);
+        let err = parse_server_version(&res).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedElectrumProtocol(v) if v == "1.2"));
+
+        assert!(parse_server_version(&serde_json::json!("1.4")).is_err());
+    }
+
     #[test]
     fn test_electrum_url_new() {
         let err = ElectrumUrl::new("example.com", false, true)
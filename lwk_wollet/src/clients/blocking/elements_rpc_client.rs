@@ -4,7 +4,9 @@ use std::collections::HashMap;
 
 use bitcoincore_rpc::{Auth, Client, RpcApi};
 use elements::{
-    encode::deserialize, hex::FromHex, Address, OutPoint, Script, Transaction, TxOut, Txid,
+    encode::{deserialize, serialize},
+    hex::{FromHex, ToHex},
+    Address, OutPoint, Script, Transaction, TxOut, Txid,
 };
 
 /// A client to issue RPCs to a Elements node
@@ -74,6 +76,26 @@ impl ElementsRpcClient {
         Ok(txout)
     }
 
+    /// Check that the node would accept `tx` in its mempool, without broadcasting it
+    ///
+    /// Uses `testmempoolaccept`, an error with the reason given by the node is returned if the
+    /// transaction is rejected.
+    pub fn test_mempool_accept(&self, tx: &Transaction) -> Result<(), Error> {
+        let method = "testmempoolaccept";
+        let hex = serialize(tx).to_hex();
+        let r: Vec<MempoolAccept> = self.inner.call(method, &[[hex].into()])?;
+        let r = r
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::ElementsRpcUnexpectedReturn(method.into()))?;
+        if r.allowed {
+            Ok(())
+        } else {
+            let reason = r.reject_reason.unwrap_or_else(|| "unknown reason".into());
+            Err(Error::MempoolRejected(reason))
+        }
+    }
+
     /// Get the confirmed utxos for a descriptor
     pub fn confirmed_utxos(
         &self,
@@ -149,6 +171,13 @@ struct Unspent {
     script_pubkey: Script,
 }
 
+#[derive(serde::Deserialize)]
+struct MempoolAccept {
+    allowed: bool,
+    #[serde(rename = "reject-reason")]
+    reject_reason: Option<String>,
+}
+
 #[derive(serde::Deserialize)]
 struct ScanResult {
     unspents: Vec<Unspent>,
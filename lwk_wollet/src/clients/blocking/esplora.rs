@@ -4,7 +4,7 @@ use std::collections::{HashMap, HashSet};
 use tokio::runtime::Runtime;
 
 use crate::{
-    clients::{asyncr, Capability, Data, History},
+    clients::{asyncr, Capability, Data, History, RetryPolicy},
     store::Height,
     wollet::WolletState,
     ElementsNetwork, Error, WolletDescriptor,
@@ -27,6 +27,20 @@ impl EsploraClient {
             client: asyncr::EsploraClient::new(network, url),
         })
     }
+
+    /// Change how failed requests are retried, for instance only for the next calls
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.client.set_retry_policy(retry);
+    }
+
+    /// Call `f` retrying requests according to `retry`, then restore the previous policy
+    pub fn with_retry_policy<T>(&mut self, retry: RetryPolicy, f: impl FnOnce(&Self) -> T) -> T {
+        let previous = self.client.retry_policy().clone();
+        self.client.set_retry_policy(retry);
+        let result = f(self);
+        self.client.set_retry_policy(previous);
+        result
+    }
 }
 
 /// "Waterfalls" methods
@@ -0,0 +1,537 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::{Mutex, MutexGuard};
+
+use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
+use elements::hashes::{sha256, Hash};
+use elements::{
+    Address, AssetId, BlockExtData, BlockHash, BlockHeader, OutPoint, Script, Transaction, TxIn,
+    TxMerkleNode, TxOut, TxOutSecrets, Txid,
+};
+use rand::thread_rng;
+
+use super::BlockchainBackend;
+use crate::clients::{Capability, History};
+use crate::store::Height;
+use crate::{ElementsNetwork, Error, Wollet, EC};
+
+/// A [`BlockchainBackend`] over an in-memory chain of synthetic blocks and transactions
+///
+/// Meant for unit tests: addresses and scripts can be funded with [`MockBackend::fund()`]
+/// and blocks are created with [`MockBackend::mine()`], without running any node or server.
+///
+/// Transactions are not validated: the funding transactions spend made-up outpoints, and
+/// broadcast transactions are accepted as they are.
+#[derive(Debug)]
+pub struct MockBackend {
+    policy_asset: AssetId,
+
+    // Behind a mutex since [`BlockchainBackend::broadcast()`] takes `&self`
+    chain: Mutex<MockChain>,
+
+    /// Whether [`Capability::ScriptStatus`] is supported
+    pub(super) script_status: bool,
+}
+
+#[derive(Debug, Clone)]
+struct MockChain {
+    /// The blocks, the index is the height
+    blocks: Vec<(BlockHeader, Vec<Txid>)>,
+
+    mempool: Vec<Txid>,
+
+    txs: HashMap<Txid, Transaction>,
+
+    /// Used to create distinct funding transactions and blocks
+    counter: u32,
+
+    /// The number of script histories returned
+    histories_fetched: usize,
+}
+
+impl MockBackend {
+    /// Create a chain for `network` with only the genesis block
+    pub fn new(network: ElementsNetwork) -> Self {
+        let genesis = header(0, BlockHash::all_zeros());
+        Self {
+            policy_asset: network.policy_asset(),
+            chain: Mutex::new(MockChain {
+                blocks: vec![(genesis, vec![])],
+                mempool: vec![],
+                txs: HashMap::new(),
+                counter: 0,
+                histories_fetched: 0,
+            }),
+            script_status: false,
+        }
+    }
+
+    /// Support [`Capability::ScriptStatus`], like electrum servers
+    pub fn with_script_status(mut self) -> Self {
+        self.script_status = true;
+        self
+    }
+
+    /// The number of script histories returned so far
+    pub fn histories_fetched(&self) -> usize {
+        self.chain().histories_fetched
+    }
+
+    fn chain(&self) -> MutexGuard<'_, MockChain> {
+        self.chain.lock().expect("poisoned")
+    }
+
+    /// The height of the last block
+    pub fn height(&self) -> Height {
+        (self.chain().blocks.len() - 1) as Height
+    }
+
+    /// The transactions not confirmed yet
+    pub fn mempool(&self) -> Vec<Txid> {
+        self.chain().mempool.clone()
+    }
+
+    /// Create `blocks` blocks, the first one confirms the transactions in the mempool
+    pub fn mine(&mut self, blocks: u32) {
+        let mut chain = self.chain();
+        for _ in 0..blocks {
+            let (prev, _) = chain.blocks.last().expect("at least genesis");
+            let header = header(prev.height + 1, prev.block_hash());
+            let txids = std::mem::take(&mut chain.mempool);
+            chain.blocks.push((header, txids));
+        }
+    }
+
+    /// Replace the last `depth` blocks with as many new blocks, the first one confirming the
+    /// transactions of the replaced blocks
+    pub fn reorg(&mut self, depth: u32) {
+        let mut chain = self.chain();
+        chain.counter += 1;
+        let fork = chain.blocks.len().saturating_sub(depth as usize).max(1);
+        let replaced = chain.blocks.split_off(fork);
+        let count = replaced.len();
+        let mut txids: Vec<Txid> = replaced.into_iter().flat_map(|(_, t)| t).collect();
+        for _ in 0..count {
+            let (prev, _) = chain.blocks.last().expect("at least genesis");
+            let mut header = header(prev.height + 1, prev.block_hash());
+            header.time += chain.counter; // distinct from the replaced block
+            chain.blocks.push((header, std::mem::take(&mut txids)));
+        }
+    }
+
+    /// Send `satoshi` of `asset` (the policy asset if `None`) to `address`, the transaction is
+    /// added to the mempool
+    ///
+    /// The output is blinded if the address is confidential.
+    pub fn fund(
+        &mut self,
+        address: &Address,
+        satoshi: u64,
+        asset: Option<AssetId>,
+    ) -> Result<Txid, Error> {
+        let asset = asset.unwrap_or(self.policy_asset);
+        let output = if address.is_blinded() {
+            let input = TxOutSecrets::new(
+                asset,
+                AssetBlindingFactor::zero(),
+                satoshi,
+                ValueBlindingFactor::zero(),
+            );
+            let (output, _, _, _) = TxOut::new_not_last_confidential(
+                &mut thread_rng(),
+                &EC,
+                satoshi,
+                address.clone(),
+                asset,
+                &[input],
+            )?;
+            output
+        } else {
+            explicit_output(address.script_pubkey(), satoshi, asset)
+        };
+        Ok(self.fund_output(output))
+    }
+
+    /// Fund the last unused address of `wollet` with [`MockBackend::fund()`] and sync it
+    pub fn fund_wollet(
+        &mut self,
+        wollet: &mut Wollet,
+        satoshi: u64,
+        asset: Option<AssetId>,
+    ) -> Result<Txid, Error> {
+        let address = wollet.address(None)?;
+        let txid = self.fund(address.address(), satoshi, asset)?;
+        self.sync(wollet)?;
+        Ok(txid)
+    }
+
+    /// Scan `wollet` and apply the update, if any
+    pub fn sync(&mut self, wollet: &mut Wollet) -> Result<(), Error> {
+        if let Some(update) = self.full_scan(wollet)? {
+            wollet.apply_update(update)?;
+        }
+        Ok(())
+    }
+
+    /// Send an explicit output of `satoshi` of `asset` to `script_pubkey`, the transaction is
+    /// added to the mempool
+    pub fn fund_script(&mut self, script_pubkey: &Script, satoshi: u64, asset: AssetId) -> Txid {
+        self.fund_output(explicit_output(script_pubkey.clone(), satoshi, asset))
+    }
+
+    fn fund_output(&mut self, output: TxOut) -> Txid {
+        let mut chain = self.chain();
+        chain.counter += 1;
+        let mut prevout = [0u8; 32];
+        prevout[..4].copy_from_slice(&chain.counter.to_le_bytes());
+        let tx = Transaction {
+            version: 2,
+            lock_time: elements::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::from_byte_array(prevout), 0),
+                ..Default::default()
+            }],
+            output: vec![output, TxOut::new_fee(100, self.policy_asset)],
+        };
+        chain.add_tx(tx)
+    }
+}
+
+impl Clone for MockBackend {
+    fn clone(&self) -> Self {
+        Self {
+            policy_asset: self.policy_asset,
+            chain: Mutex::new(self.chain().clone()),
+            script_status: self.script_status,
+        }
+    }
+}
+
+impl MockChain {
+    fn add_tx(&mut self, tx: Transaction) -> Txid {
+        let txid = tx.txid();
+        if self.txs.insert(txid, tx).is_none() {
+            self.mempool.push(txid);
+        }
+        txid
+    }
+
+    fn history(&self, script: &Script) -> Vec<History> {
+        self.txs
+            .iter()
+            .filter(|(_, tx)| self.involves(tx, script))
+            .filter_map(|(txid, _)| {
+                let height = self.tx_height(txid)?;
+                let block_hash = (height > 0).then(|| self.blocks[height as usize].0.block_hash());
+                Some(History {
+                    txid: *txid,
+                    height: height as i32,
+                    block_hash,
+                    block_timestamp: None,
+                })
+            })
+            .collect()
+    }
+
+    /// The height of the block confirming `txid`, `Some(0)` if in mempool
+    fn tx_height(&self, txid: &Txid) -> Option<Height> {
+        if self.mempool.contains(txid) {
+            return Some(0);
+        }
+        self.blocks
+            .iter()
+            .position(|(_, txids)| txids.contains(txid))
+            .map(|h| h as Height)
+    }
+
+    fn involves(&self, tx: &Transaction, script: &Script) -> bool {
+        tx.output.iter().any(|o| &o.script_pubkey == script)
+            || tx.input.iter().any(|i| {
+                self.txs
+                    .get(&i.previous_output.txid)
+                    .and_then(|prev| prev.output.get(i.previous_output.vout as usize))
+                    .map_or(false, |o| &o.script_pubkey == script)
+            })
+    }
+}
+
+fn header(height: Height, prev_blockhash: BlockHash) -> BlockHeader {
+    BlockHeader {
+        version: 0x20000000,
+        prev_blockhash,
+        merkle_root: TxMerkleNode::all_zeros(),
+        time: 1_700_000_000 + height * 60,
+        height,
+        ext: BlockExtData::Proof {
+            challenge: Script::new(),
+            solution: Script::new(),
+        },
+    }
+}
+
+fn explicit_output(script_pubkey: Script, satoshi: u64, asset: AssetId) -> TxOut {
+    TxOut {
+        asset: elements::confidential::Asset::Explicit(asset),
+        value: elements::confidential::Value::Explicit(satoshi),
+        nonce: elements::confidential::Nonce::Null,
+        script_pubkey,
+        witness: Default::default(),
+    }
+}
+
+impl BlockchainBackend for MockBackend {
+    fn tip(&mut self) -> Result<BlockHeader, Error> {
+        Ok(self
+            .chain()
+            .blocks
+            .last()
+            .expect("at least genesis")
+            .0
+            .clone())
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        Ok(self.chain().add_tx(tx.clone()))
+    }
+
+    fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>, Error> {
+        let chain = self.chain();
+        txids
+            .iter()
+            .map(|txid| {
+                chain
+                    .txs
+                    .get(txid)
+                    .cloned()
+                    .ok_or_else(|| Error::Generic(format!("Missing transaction {txid}")))
+            })
+            .collect()
+    }
+
+    fn get_headers(
+        &self,
+        heights: &[Height],
+        _height_blockhash: &HashMap<Height, BlockHash>,
+    ) -> Result<Vec<BlockHeader>, Error> {
+        let chain = self.chain();
+        heights
+            .iter()
+            .map(|h| {
+                chain
+                    .blocks
+                    .get(*h as usize)
+                    .map(|(header, _)| header.clone())
+                    .ok_or_else(|| Error::Generic(format!("Missing block at height {h}")))
+            })
+            .collect()
+    }
+
+    fn capabilities(&self) -> HashSet<Capability> {
+        let mut capabilities = HashSet::new();
+        if self.script_status {
+            capabilities.insert(Capability::ScriptStatus);
+        }
+        capabilities
+    }
+
+    fn get_scripts_status(&mut self, scripts: &[&Script]) -> Result<Vec<Option<[u8; 32]>>, Error> {
+        let chain = self.chain();
+        Ok(scripts
+            .iter()
+            .map(|script| {
+                let mut history = chain.history(script);
+                if history.is_empty() {
+                    return None;
+                }
+                // as defined by the electrum protocol, confirmed transactions first
+                history.sort_by_key(|h| (h.height <= 0, h.height, h.txid));
+                let mut status = String::new();
+                for h in history {
+                    let _ = write!(status, "{}:{}:", h.txid, h.height);
+                }
+                Some(sha256::Hash::hash(status.as_bytes()).to_byte_array())
+            })
+            .collect())
+    }
+
+    fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
+        let mut chain = self.chain();
+        chain.histories_fetched += scripts.len();
+        Ok(scripts.iter().map(|script| chain.history(script)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{Wollet, WolletDescriptor};
+
+    #[test]
+    fn test_mock_backend() {
+        let network = ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let mut wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        assert_eq!(client.height(), 0);
+
+        let address = wollet.address(None).unwrap().address().clone();
+        let txid = client.fund(&address, 10_000, None).unwrap();
+        assert_eq!(client.mempool(), vec![txid]);
+
+        client.sync(&mut wollet).unwrap();
+        assert_eq!(wollet.balance().unwrap()[&network.policy_asset()], 10_000);
+        assert_eq!(wollet.transactions().unwrap()[0].height, None);
+
+        client.mine(2);
+        assert_eq!(client.height(), 2);
+        assert!(client.mempool().is_empty());
+
+        let asset = AssetId::from_slice(&[1; 32]).unwrap();
+        client.fund(&address, 5, Some(asset)).unwrap();
+        client.sync(&mut wollet).unwrap();
+        assert_eq!(wollet.tip().height(), 2);
+        assert_eq!(wollet.balance().unwrap()[&asset], 5);
+        let txs = wollet.transactions().unwrap();
+        assert_eq!(txs.len(), 2);
+        assert!(txs.iter().any(|tx| tx.txid == txid && tx.height == Some(1)));
+
+        // Scripts not in the wallet can be funded as well
+        let script = Script::from(vec![0x51]);
+        let txid = client.fund_script(&script, 1_000, network.policy_asset());
+        let history = client.get_scripts_history(&[&script]).unwrap();
+        assert_eq!(history[0][0].txid, txid);
+        assert_eq!(history[0][0].height, 0);
+    }
+
+    #[test]
+    fn test_balance_at() {
+        let network = ElementsNetwork::default_regtest();
+        let policy_asset = network.policy_asset();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let mut wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap();
+
+        client.fund(address.address(), 1_000, None).unwrap(); // height 1
+        client.mine(1);
+        client.fund(address.address(), 2_000, None).unwrap(); // height 2
+        client.mine(2);
+        client.fund(address.address(), 4_000, None).unwrap(); // unconfirmed
+        client.sync(&mut wollet).unwrap();
+
+        assert_eq!(wollet.balance().unwrap()[&policy_asset], 7_000);
+        assert_eq!(wollet.balance_at(0).unwrap()[&policy_asset], 0);
+        assert_eq!(wollet.balance_at(1).unwrap()[&policy_asset], 1_000);
+        assert_eq!(wollet.balance_at(2).unwrap()[&policy_asset], 3_000);
+        assert_eq!(wollet.balance_at(3).unwrap()[&policy_asset], 3_000);
+        assert_eq!(wollet.balance_at(100).unwrap()[&policy_asset], 3_000);
+        assert_eq!(wollet.utxos_at(1).unwrap().len(), 1);
+        assert_eq!(wollet.utxos_at(3).unwrap()[0].unblinded.value, 2_000);
+
+        // Spend everything at height 5, the mock doesn't need signatures
+        let external = Address::from_str("el1qq2xvpcvfup5j8zscjq05u2wxxjcyewk7979f3mmz5l7uw5pqmx6xf5xy50hsn6vhkm5euwt72x878eq6zxx2z0z676mna6kdq").unwrap();
+        let pset = wollet
+            .tx_builder()
+            .drain_lbtc_wallet()
+            .drain_lbtc_to(external)
+            .finish()
+            .unwrap();
+        client.mine(1); // height 4 confirms the 4_000
+        client.broadcast(&pset.extract_tx().unwrap()).unwrap();
+        client.mine(1);
+        client.sync(&mut wollet).unwrap();
+        assert_eq!(wollet.balance().unwrap()[&policy_asset], 0);
+        assert_eq!(wollet.balance_at(3).unwrap()[&policy_asset], 3_000);
+        assert_eq!(wollet.balance_at(4).unwrap()[&policy_asset], 7_000);
+        assert_eq!(wollet.balance_at(5).unwrap()[&policy_asset], 0);
+    }
+
+    #[test]
+    fn test_unconfidential_recipient() {
+        let network = ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let mut wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap();
+        client.fund(address.address(), 10_000, None).unwrap();
+        client.sync(&mut wollet).unwrap();
+
+        let mut address = wollet.address(Some(1)).unwrap().address().clone();
+        address.blinding_pubkey = None;
+        let err = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .finish()
+            .unwrap_err();
+        assert_eq!(err.to_string(), Error::NotConfidentialAddress.to_string());
+
+        // the policy is checked at finish, so it can be set after adding recipients
+        let pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .allow_unconfidential_addresses()
+            .finish()
+            .unwrap();
+        let tx = pset.extract_tx().unwrap();
+        assert!(tx.output.iter().any(
+            |o| o.script_pubkey == address.script_pubkey() && o.value.explicit() == Some(1_000)
+        ));
+    }
+
+    #[test]
+    fn test_full_scan_progress() {
+        let network = ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        for i in 0..3 {
+            let address = wollet.address(Some(i)).unwrap();
+            client.fund(address.address(), 1_000, None).unwrap();
+        }
+
+        let reports = std::cell::RefCell::new(vec![]);
+        client
+            .full_scan_with_progress(&wollet, 0, |p| reports.borrow_mut().push(p))
+            .unwrap()
+            .unwrap();
+        let reports = reports.into_inner();
+        let scanned: Vec<_> = reports.iter().map(|p| p.scripts_scanned).collect();
+        assert!(scanned.windows(2).all(|w| w[0] <= w[1]));
+        let last = reports.last().unwrap();
+        assert!(last.scripts_scanned >= 40);
+        assert_eq!(last.txs_to_download, 3);
+        assert_eq!(last.txs_downloaded, 3);
+        assert_eq!(last.unblinded, 3);
+    }
+
+    #[test]
+    fn test_full_scan_cancel() {
+        let network = ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let mut wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap();
+        client.fund(address.address(), 1_000, None).unwrap();
+
+        // Cancel from the progress callback, once the first batch of scripts is scanned
+        let cancel = crate::CancellationToken::new();
+        let err = client
+            .full_scan_cancellable(&wollet, 0, |_| cancel.cancel(), &cancel)
+            .unwrap_err();
+        assert!(matches!(err, Error::Cancelled));
+        assert!(wollet.transactions().unwrap().is_empty());
+
+        // The scan can be restarted with a new token
+        let cancel = crate::CancellationToken::new();
+        let update = client
+            .full_scan_cancellable(&wollet, 0, |_| {}, &cancel)
+            .unwrap()
+            .unwrap();
+        wollet.apply_update(update).unwrap();
+        assert_eq!(wollet.transactions().unwrap().len(), 1);
+    }
+}
@@ -2,7 +2,7 @@
 
 use crate::{
     clients::try_unblind,
-    store::{Height, Timestamp, BATCH_SIZE},
+    store::{Height, ScriptHistoryStatus, BATCH_SIZE},
     update::{DownloadTxResult, Update},
     wollet::WolletState,
     BlindingPublicKey, Chain, Error, WolletDescriptor,
@@ -20,7 +20,16 @@ pub use esplora::EsploraClient;
 #[cfg(feature = "elements_rpc")]
 pub use elements_rpc_client::ElementsRpcClient;
 
-use super::{Capability, Data, History, LastUnused};
+use super::{
+    blocks_to_verify, CancellationToken, Capability, Data, History, LastUnused, ScanControl,
+    SyncProgress,
+};
+
+mod mock;
+mod server_health;
+
+pub use mock::MockBackend;
+pub use server_health::{ServerHealth, ServerHealthCache};
 
 #[cfg(feature = "electrum")]
 pub(crate) mod electrum_client;
@@ -28,6 +37,9 @@ pub(crate) mod electrum_client;
 #[cfg(feature = "elements_rpc")]
 pub(crate) mod elements_rpc_client;
 
+/// Number of transactions requested at once by [`BlockchainBackend::full_scan_with_progress()`]
+const DOWNLOAD_CHUNK_SIZE: usize = 100;
+
 /// Trait implemented by types that can fetch data from a blockchain data source.
 pub trait BlockchainBackend {
     /// Get the blockchain latest block header
@@ -56,12 +68,78 @@ pub trait BlockchainBackend {
         HashSet::new()
     }
 
+    /// Stop waiting to retry failed requests once `cancel` is triggered
+    ///
+    /// Called by [`BlockchainBackend::full_scan_cancellable()`], so that cancelling a scan does
+    /// not wait for the pending retries. The token is kept until another one is set.
+    fn set_cancellation_token(&mut self, _cancel: &CancellationToken) {}
+
+    /// Get the electrum status of a list of scripts, `None` for scripts without history
+    ///
+    /// Implemented by backends with [`Capability::ScriptStatus`].
+    fn get_scripts_status(&mut self, _scripts: &[&Script]) -> Result<Vec<Option<[u8; 32]>>, Error> {
+        Err(Error::ScriptStatusUnimplemented)
+    }
+
+    /// Get the history of a list of scripts like [`BlockchainBackend::get_scripts_history()`],
+    /// fetching only the ones whose status changed since the last scan
+    ///
+    /// The histories of the other scripts are the transactions stored in `state`, the changed
+    /// statuses are added to `changed`.
+    fn get_scripts_history_by_status<S: WolletState>(
+        &mut self,
+        scripts: &[&Script],
+        state: &S,
+        changed: &mut Vec<(Script, Option<ScriptHistoryStatus>)>,
+    ) -> Result<Vec<Vec<History>>, Error> {
+        let statuses = self.get_scripts_status(scripts)?;
+        let heights = state.heights();
+        let mut result = vec![vec![]; scripts.len()];
+        let mut to_fetch = vec![];
+        for (i, (script, status)) in scripts.iter().zip(statuses.iter()).enumerate() {
+            match (status, state.script_status().get(*script)) {
+                (None, None) => {}
+                (Some(status), Some(stored))
+                    if stored.status == *status
+                        && stored.txids.iter().all(|txid| heights.contains_key(txid)) =>
+                {
+                    result[i] = stored
+                        .txids
+                        .iter()
+                        .map(|txid| History {
+                            txid: *txid,
+                            height: heights[txid].map(|h| h as i32).unwrap_or(0),
+                            block_hash: None,
+                            block_timestamp: None,
+                        })
+                        .collect();
+                }
+                _ => to_fetch.push(i),
+            }
+        }
+        if to_fetch.is_empty() {
+            return Ok(result);
+        }
+        let fetch: Vec<&Script> = to_fetch.iter().map(|i| scripts[*i]).collect();
+        let histories = self.get_scripts_history(&fetch)?;
+        for (i, history) in to_fetch.into_iter().zip(histories) {
+            let status = statuses[i].map(|status| ScriptHistoryStatus {
+                status,
+                txids: history.iter().map(|h| h.txid).collect(),
+            });
+            changed.push((scripts[i].clone(), status));
+            result[i] = history;
+        }
+        Ok(result)
+    }
+
     fn get_history<S: WolletState>(
         &mut self,
         descriptor: &WolletDescriptor,
         state: &S,
         index: u32,
         last_unused: LastUnused,
+        control: &mut ScanControl<'_>,
     ) -> Result<Data, Error> {
         let mut data = Data::default();
 
@@ -70,10 +148,18 @@ pub trait BlockchainBackend {
             let chain: Chain = (&descriptor).try_into().unwrap_or(Chain::External);
             let index = index.max(last_unused[chain]);
             loop {
+                control.check()?;
                 let batch = state.get_script_batch(batch_count, &descriptor)?;
 
                 let s: Vec<_> = batch.value.iter().map(|e| &e.0).collect();
-                let result: Vec<Vec<History>> = self.get_scripts_history(&s)?;
+                let result: Vec<Vec<History>> =
+                    if self.capabilities().contains(&Capability::ScriptStatus) {
+                        self.get_scripts_history_by_status(&s, state, &mut data.script_status)?
+                    } else {
+                        self.get_scripts_history(&s)?
+                    };
+                control.progress_mut().scripts_scanned += s.len();
+                control.report();
                 if !batch.cached {
                     data.scripts.extend(batch.value);
                 }
@@ -161,7 +247,36 @@ pub trait BlockchainBackend {
         state: &S,
         index: u32,
     ) -> Result<Option<Update>, Error> {
+        self.full_scan_with_progress(state, index, |_| {})
+    }
+
+    /// Like [`BlockchainBackend::full_scan_to_index()`], calling `progress` while scripts are
+    /// scanned and transactions are downloaded, for instance to render a progress bar
+    fn full_scan_with_progress<S: WolletState, F: Fn(SyncProgress)>(
+        &mut self,
+        state: &S,
+        index: u32,
+        progress: F,
+    ) -> Result<Option<Update>, Error> {
+        self.full_scan_cancellable(state, index, progress, &CancellationToken::new())
+    }
+
+    /// Like [`BlockchainBackend::full_scan_with_progress()`], returning [`Error::Cancelled`] as
+    /// soon as possible once `cancel` is triggered
+    ///
+    /// The scan produces an update only at the end, a cancelled scan leaves the wallet as it
+    /// was and can simply be restarted.
+    fn full_scan_cancellable<S: WolletState, F: Fn(SyncProgress)>(
+        &mut self,
+        state: &S,
+        index: u32,
+        progress: F,
+        cancel: &CancellationToken,
+    ) -> Result<Option<Update>, Error> {
+        let _span = tracing::info_span!("full_scan", index).entered();
+        self.set_cancellation_token(cancel);
         let descriptor = state.descriptor();
+        let mut control = ScanControl::new(&progress, cancel);
 
         let Data {
             txid_height,
@@ -170,6 +285,7 @@ pub trait BlockchainBackend {
             height_blockhash,
             height_timestamp: _height_timestamp,
             tip: _,
+            script_status,
         } = if self.capabilities().contains(&Capability::Waterfalls) {
             if index != 0 {
                 return Err(Error::UsingWaterfallsWithNonZeroIndex);
@@ -177,25 +293,44 @@ pub trait BlockchainBackend {
             match self.get_history_waterfalls(&descriptor, state) {
                 Ok(d) => d,
                 Err(Error::UsingWaterfallsWithElip151) => {
-                    self.get_history(&descriptor, state, index, state.last_unused())?
+                    self.get_history(&descriptor, state, index, state.last_unused(), &mut control)?
                 }
                 Err(e) => return Err(e),
             }
         } else {
-            self.get_history(&descriptor, state, index, state.last_unused())?
+            self.get_history(&descriptor, state, index, state.last_unused(), &mut control)?
         };
 
+        control.check()?;
         let tip = self.tip()?;
+        let reorged = self.reorged_blocks(state, &tip)?;
+        let reorg_height = reorged.keys().min().cloned();
 
         let history_txs_id: HashSet<Txid> = txid_height.keys().cloned().collect();
-        let new_txs = self.download_txs(&history_txs_id, &scripts, state, &descriptor)?;
-        let history_txs_heights_plus_tip: HashSet<Height> = txid_height
-            .values()
-            .filter_map(|e| *e)
+        let new_txs =
+            self.download_txs(&history_txs_id, &scripts, state, &descriptor, &mut control)?;
+        let history_txs_heights: HashSet<Height> =
+            txid_height.values().filter_map(|e| *e).collect();
+        let history_txs_heights_plus_tip: HashSet<Height> = history_txs_heights
+            .iter()
+            .cloned()
             .chain(std::iter::once(tip.height))
             .collect();
-        let timestamps =
-            self.download_headers(&history_txs_heights_plus_tip, &height_blockhash, state)?;
+        control.check()?;
+        let headers = self.download_headers(
+            &history_txs_heights_plus_tip,
+            &height_blockhash,
+            state,
+            reorg_height,
+        )?;
+        let timestamps: Vec<_> = headers.iter().map(|h| (h.height, h.time)).collect();
+        let mut block_hashes = reorged;
+        block_hashes.extend(
+            headers
+                .iter()
+                .filter(|h| history_txs_heights.contains(&h.height))
+                .map(|h| (h.height, h.block_hash())),
+        );
 
         let store_last_unused_external = state.last_unused()[Chain::External];
         let store_last_unused_internal = state.last_unused()[Chain::Internal];
@@ -207,14 +342,18 @@ pub trait BlockchainBackend {
             || last_unused_changed
             || !scripts.is_empty()
             || !timestamps.is_empty()
+            || reorg_height.is_some()
+            || !script_status.is_empty()
             || state.tip() != (tip.height, tip.block_hash());
 
         if changed {
-            log::debug!("something changed: !new_txs.txs.is_empty():{} last_unused_changed:{} !scripts.is_empty():{} !timestamps.is_empty():{}", !new_txs.txs.is_empty(), last_unused_changed, !scripts.is_empty(), !timestamps.is_empty() );
+            tracing::debug!("something changed: !new_txs.txs.is_empty():{} last_unused_changed:{} !scripts.is_empty():{} !timestamps.is_empty():{}", !new_txs.txs.is_empty(), last_unused_changed, !scripts.is_empty(), !timestamps.is_empty() );
 
             let txid_height_new: Vec<_> = txid_height
                 .iter()
                 .filter(|(k, v)| match state.heights().get(*k) {
+                    // confirmed in a replaced block, re-verified even if at the same height
+                    Some(Some(h)) if reorg_height.is_some_and(|r| *h >= r) => true,
                     Some(e) => e != *v,
                     None => true,
                 })
@@ -236,7 +375,7 @@ pub trait BlockchainBackend {
                 .collect();
 
             let update = Update {
-                version: 2,
+                version: 4,
                 wollet_status,
                 new_txs,
                 txid_height_new,
@@ -244,6 +383,8 @@ pub trait BlockchainBackend {
                 timestamps,
                 scripts_with_blinding_pubkey,
                 tip,
+                block_hashes: block_hashes.into_iter().collect(),
+                script_status,
             };
             Ok(Some(update))
         } else {
@@ -258,14 +399,24 @@ pub trait BlockchainBackend {
         scripts: &HashMap<Script, (Chain, ChildNumber, BlindingPublicKey)>,
         state: &S,
         descriptor: &WolletDescriptor,
+        control: &mut ScanControl<'_>,
     ) -> Result<DownloadTxResult, Error> {
         let mut txs = vec![];
         let mut unblinds = vec![];
 
         let mut txs_in_db = state.txs().clone();
         let txs_to_download: Vec<Txid> = history_txs_id.difference(&txs_in_db).cloned().collect();
-
-        let txs_downloaded = self.get_transactions(&txs_to_download)?;
+        control.progress_mut().txs_to_download = txs_to_download.len();
+        control.report();
+
+        // Download in chunks to report progress
+        let mut txs_downloaded = Vec::with_capacity(txs_to_download.len());
+        for chunk in txs_to_download.chunks(DOWNLOAD_CHUNK_SIZE) {
+            control.check()?;
+            txs_downloaded.extend(self.get_transactions(chunk)?);
+            control.progress_mut().txs_downloaded = txs_downloaded.len();
+            control.report();
+        }
 
         for tx in txs_downloaded.into_iter() {
             let txid = tx.txid();
@@ -283,40 +434,129 @@ pub trait BlockchainBackend {
                     };
 
                     match try_unblind(output.clone(), descriptor) {
-                            Ok(unblinded) => unblinds.push((outpoint, unblinded)),
-                            Err(_) => log::info!("{} cannot unblind, ignoring (could be sender messed up with the blinding process)", outpoint),
+                            Ok(unblinded) => {
+                                unblinds.push((outpoint, unblinded));
+                                control.progress_mut().unblinded += 1;
+                            }
+                            Err(_) => tracing::info!("{} cannot unblind, see the unblindable outputs (could be sender messed up with the blinding process)", outpoint),
                         }
                 }
             }
 
             txs.push((txid, tx));
         }
+        if !unblinds.is_empty() {
+            control.report();
+        }
 
         Ok(DownloadTxResult { txs, unblinds })
     }
 
     /// Download the headers if not available in the store
+    /// Download the headers at the given heights not in the state
+    ///
+    /// Heights from `reorg_height` are downloaded again, since their blocks have been replaced.
     fn download_headers<S: WolletState>(
         &self,
         history_txs_heights_plus_tip: &HashSet<Height>,
         height_blockhash: &HashMap<Height, BlockHash>,
         state: &S,
-    ) -> Result<Vec<(Height, Timestamp)>, Error> {
-        let mut result = vec![];
-        let heights_in_db: HashSet<Height> =
-            state.heights().iter().filter_map(|(_, h)| *h).collect();
+        reorg_height: Option<Height>,
+    ) -> Result<Vec<BlockHeader>, Error> {
+        let heights_in_db: HashSet<Height> = state
+            .heights()
+            .iter()
+            .filter_map(|(_, h)| *h)
+            .filter(|h| reorg_height.map_or(true, |r| *h < r))
+            .collect();
         let heights_to_download: Vec<Height> = history_txs_heights_plus_tip
             .difference(&heights_in_db)
             .cloned()
             .collect();
-        if !heights_to_download.is_empty() {
-            for h in self.get_headers(&heights_to_download, height_blockhash)? {
-                result.push((h.height, h.time))
-            }
+        if heights_to_download.is_empty() {
+            return Ok(vec![]);
+        }
+        let headers = self.get_headers(&heights_to_download, height_blockhash)?;
+        tracing::debug!("{} headers_downloaded", heights_to_download.len());
+
+        Ok(headers)
+    }
 
-            log::debug!("{} headers_downloaded", heights_to_download.len());
+    /// The stored blocks not in the chain ending with `tip` anymore, with the hashes of the
+    /// blocks replacing them
+    fn reorged_blocks<S: WolletState>(
+        &self,
+        state: &S,
+        tip: &BlockHeader,
+    ) -> Result<HashMap<Height, BlockHash>, Error> {
+        let mut reorged = HashMap::new();
+        for (height, block_hash) in blocks_to_verify(state, tip) {
+            let Some(header) = self.get_headers(&[height], &HashMap::new())?.pop() else {
+                break;
+            };
+            if header.block_hash() == block_hash {
+                break;
+            }
+            reorged.insert(height, header.block_hash());
         }
+        if let Some(height) = reorged.keys().min() {
+            tracing::info!("blocks replaced from height {height}");
+        }
+        Ok(reorged)
+    }
+}
 
-        Ok(result)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::mock_wollet;
+    use crate::Wollet;
+
+    #[test]
+    fn test_script_status() {
+        let (mut wollet, client) = mock_wollet();
+        let mut plain =
+            Wollet::without_persist(wollet.network(), wollet.wollet_descriptor()).unwrap();
+        let mut client = client.with_script_status();
+
+        let sync = |client: &mut MockBackend, wollet: &mut Wollet, plain: &mut Wollet| {
+            let fetched = client.histories_fetched();
+            client.sync(wollet).unwrap();
+            let fetched = client.histories_fetched() - fetched;
+            let mut client = client.clone();
+            client.script_status = false;
+            if let Some(update) = client.full_scan(plain).unwrap() {
+                plain.apply_update(update).unwrap();
+            }
+            assert_eq!(wollet.balance().unwrap(), plain.balance().unwrap());
+            assert_eq!(
+                wollet.transactions().unwrap(),
+                plain.transactions().unwrap()
+            );
+            fetched
+        };
+
+        // Scripts without history are not fetched
+        assert_eq!(sync(&mut client, &mut wollet, &mut plain), 0);
+        assert!(wollet.store.cache.script_status.is_empty());
+
+        // Only the funded script is fetched
+        let address = wollet.address(Some(0)).unwrap();
+        let txid = client.fund(address.address(), 10_000, None).unwrap();
+        assert_eq!(sync(&mut client, &mut wollet, &mut plain), 1);
+        let script = address.address().script_pubkey();
+        let status = wollet.store.cache.script_status[&script].clone();
+        assert_eq!(status.txids, vec![txid]);
+        assert_eq!(wollet.store.cache.script_status.len(), 1);
+
+        // Nothing changed
+        assert_eq!(sync(&mut client, &mut wollet, &mut plain), 0);
+
+        // The confirmation changes the status
+        client.mine(1);
+        assert_eq!(sync(&mut client, &mut wollet, &mut plain), 1);
+        assert_ne!(wollet.store.cache.script_status[&script], status);
+        assert_eq!(wollet.transaction(&txid).unwrap().unwrap().height, Some(1));
+        assert_eq!(sync(&mut client, &mut wollet, &mut plain), 0);
     }
 }
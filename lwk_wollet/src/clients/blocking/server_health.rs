@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// The measured capabilities and health of a server
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerHealth {
+    /// Response time in milliseconds, smoothed over the last measurements
+    pub latency_ms: Option<u64>,
+
+    /// The highest protocol version supported by the server
+    pub protocol_version: Option<String>,
+
+    /// The height below which the server pruned the blocks, if any
+    pub pruning: Option<i64>,
+
+    pub successes: u32,
+
+    pub failures: u32,
+
+    /// Failures since the last success, the server is considered healthy if 0
+    pub consecutive_failures: u32,
+
+    /// Unix timestamp in seconds of the last failure
+    pub last_failure: Option<u64>,
+}
+
+impl ServerHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures == 0
+    }
+}
+
+/// The [`ServerHealth`] of several servers, identified by their url, persisted in a file
+///
+/// Clients using several servers can use [`ServerHealthCache::rank()`] to prefer the healthy
+/// ones, also across restarts.
+#[derive(Debug, Clone, Default)]
+pub struct ServerHealthCache {
+    path: Option<PathBuf>,
+    servers: BTreeMap<String, ServerHealth>,
+}
+
+impl ServerHealthCache {
+    /// A cache not persisted
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the cache from `path`, empty if the file does not exist
+    ///
+    /// The cache is written back to `path` with [`ServerHealthCache::save()`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let servers = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            servers,
+        })
+    }
+
+    /// Write the cache to the file it was loaded from, if any
+    pub fn save(&self) -> Result<(), Error> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(&self.servers)?)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Record a successful request to `url` that took `latency`
+    pub fn record_success(&mut self, url: &str, latency: Duration) {
+        let server = self.servers.entry(url.to_string()).or_default();
+        let latency = latency.as_millis() as u64;
+        server.latency_ms = Some(match server.latency_ms {
+            Some(prev) => (prev * 7 + latency * 3) / 10,
+            None => latency,
+        });
+        server.successes += 1;
+        server.consecutive_failures = 0;
+    }
+
+    /// Record a failed request or connection to `url`
+    pub fn record_failure(&mut self, url: &str) {
+        let server = self.servers.entry(url.to_string()).or_default();
+        server.failures += 1;
+        server.consecutive_failures += 1;
+        server.last_failure = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+    }
+
+    /// Record the capabilities advertised by the server at `url`
+    pub fn record_features(&mut self, url: &str, protocol_version: &str, pruning: Option<i64>) {
+        let server = self.servers.entry(url.to_string()).or_default();
+        server.protocol_version = Some(protocol_version.to_string());
+        server.pruning = pruning;
+    }
+
+    pub fn get(&self, url: &str) -> Option<&ServerHealth> {
+        self.servers.get(url)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ServerHealth)> {
+        self.servers.iter().map(|(url, h)| (url.as_str(), h))
+    }
+
+    /// Sort `urls` from the most to the least preferable
+    ///
+    /// Servers with fewer consecutive failures come first, then the faster ones. Servers never
+    /// measured come after the healthy ones with a known latency.
+    pub fn rank<'a>(&self, urls: &[&'a str]) -> Vec<&'a str> {
+        let mut urls = urls.to_vec();
+        urls.sort_by_key(|url| match self.servers.get(*url) {
+            Some(s) => (s.consecutive_failures, s.latency_ms.unwrap_or(u64::MAX)),
+            None => (0, u64::MAX),
+        });
+        urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_health_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server_health.json");
+        let mut cache = ServerHealthCache::load(&path).unwrap();
+        assert_eq!(cache.iter().count(), 0);
+
+        let (a, b, c) = ("tcp://a:50001", "tcp://b:50001", "tcp://c:50001");
+        cache.record_success(a, Duration::from_millis(100));
+        cache.record_success(a, Duration::from_millis(200));
+        cache.record_features(a, "1.4", Some(1_000));
+        cache.record_success(b, Duration::from_millis(50));
+        cache.record_failure(b);
+        let health = cache.get(a).unwrap();
+        assert_eq!(health.latency_ms, Some(130));
+        assert_eq!(health.protocol_version.as_deref(), Some("1.4"));
+        assert!(health.is_healthy());
+        let health = cache.get(b).unwrap();
+        assert!(!health.is_healthy());
+        assert!(health.last_failure.is_some());
+        assert!(cache.get(c).is_none());
+        assert_eq!(cache.rank(&[b, c, a]), vec![a, c, b]);
+
+        cache.save().unwrap();
+        let loaded = ServerHealthCache::load(&path).unwrap();
+        assert_eq!(loaded.get(a), cache.get(a));
+        assert_eq!(loaded.get(b), cache.get(b));
+        assert_eq!(loaded.iter().count(), 2);
+        assert_eq!(loaded.rank(&[b, c, a]), vec![a, c, b]);
+
+        let mut cache = loaded;
+        cache.record_success(b, Duration::from_millis(50));
+        let health = cache.get(b).unwrap();
+        assert!(health.is_healthy());
+        assert_eq!((health.successes, health.failures), (2, 1));
+        assert_eq!(cache.rank(&[b, c, a]), vec![b, a, c]);
+    }
+}
@@ -1,25 +1,32 @@
 //! Clients to fetch data from the Blockchain.
 
 use crate::{
-    store::{Height, Timestamp},
-    BlindingPublicKey, Chain, Error, WolletDescriptor, EC,
+    store::{Height, ScriptHistoryStatus, Timestamp},
+    unblind_output,
+    wollet::WolletState,
+    BlindingPublicKey, Chain, Error, WolletDescriptor,
 };
 use elements::{
     bitcoin::bip32::ChildNumber,
     confidential::{Asset, Nonce, Value},
     Script, TxOut, TxOutSecrets,
 };
-use elements::{BlockHash, Txid};
+use elements::{BlockHash, BlockHeader, Txid};
 use lwk_common::derive_blinding_key;
+use rand::{thread_rng, Rng};
 use serde::Deserialize;
 use std::{
     collections::HashMap,
     ops::{Index, IndexMut},
+    sync::atomic::{AtomicBool, Ordering},
+    sync::Arc,
+    time::Duration,
 };
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod blocking;
 
+#[cfg(feature = "esplora")]
 pub mod asyncr;
 
 /// Last unused derivation index for each chain.
@@ -50,6 +57,217 @@ impl IndexMut<Chain> for LastUnused {
     }
 }
 
+/// Progress of a scan, reported by [`blocking::BlockchainBackend::full_scan_with_progress()`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SyncProgress {
+    /// Number of scripts whose history has been fetched
+    pub scripts_scanned: usize,
+
+    /// Number of transactions to download, known once all the scripts have been scanned
+    pub txs_to_download: usize,
+
+    /// Number of transactions downloaded so far
+    pub txs_downloaded: usize,
+
+    /// Number of wallet outputs unblinded so far
+    pub unblinded: usize,
+}
+
+/// A flag to abort a running scan from another thread
+///
+/// Clones share the same flag. A cancelled scan returns [`Error::Cancelled`] without producing
+/// an update, so the wallet is left as it was before the scan.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the cancellation, scans using this token stop at their next checkpoint
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Return [`Error::Cancelled`] if the cancellation has been requested
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sleep for `duration` or until the cancellation is requested, returning false in the
+    /// latter case
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn sleep(&self, duration: Duration) -> bool {
+        const STEP: Duration = Duration::from_millis(50);
+        let mut remaining = duration;
+        while !remaining.is_zero() {
+            if self.is_cancelled() {
+                return false;
+            }
+            let step = remaining.min(STEP);
+            std::thread::sleep(step);
+            remaining -= step;
+        }
+        !self.is_cancelled()
+    }
+}
+
+/// How network clients retry failed requests
+///
+/// The delay before the n-th retry is `base_delay * 2^n`, capped to `max_delay`; with `jitter`
+/// the actual delay is a random value between half and all of it, so that many clients failing
+/// at the same time don't retry all together.
+///
+/// The default policy retries twice waiting at most a couple of seconds, since callers are often
+/// blocked waiting for the request. Esplora clients default to [`RetryPolicy::patient()`], since
+/// public servers rate limit their clients. Long running tasks like background scans can use
+/// [`RetryPolicy::until_cancelled()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+
+    pub base_delay: Duration,
+
+    pub max_delay: Duration,
+
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(2),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy failing at the first error
+    pub fn no_retry() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// A policy for servers rate limiting their clients, retrying 7 times waiting 1, 2, 4, up to
+    /// 64 seconds, about two minutes overall
+    pub fn patient() -> Self {
+        Self {
+            max_retries: 7,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(64),
+            jitter: true,
+        }
+    }
+
+    /// A policy retrying until the [`CancellationToken`] given to
+    /// [`RetryPolicy::retry_cancellable()`] is triggered, waiting at most a minute between retries
+    pub fn until_cancelled() -> Self {
+        Self {
+            max_retries: u32::MAX,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+        }
+    }
+
+    /// The time to wait before retry number `retry` (starting from 0)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        if self.jitter && !delay.is_zero() {
+            let half = delay / 2;
+            half + half.mul_f64(thread_rng().gen::<f64>())
+        } else {
+            delay
+        }
+    }
+
+    /// Call `f` until it succeeds or the retries are exhausted, retrying only on errors for
+    /// which `retryable` is true
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn retry<T, E: std::fmt::Display>(
+        &self,
+        f: impl FnMut() -> Result<T, E>,
+        retryable: impl Fn(&E) -> bool,
+    ) -> Result<T, E> {
+        self.retry_cancellable(f, retryable, &CancellationToken::new())
+    }
+
+    /// Like [`RetryPolicy::retry()`], but stops waiting and returns the last error as soon as
+    /// `cancel` is triggered
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn retry_cancellable<T, E: std::fmt::Display>(
+        &self,
+        mut f: impl FnMut() -> Result<T, E>,
+        retryable: impl Fn(&E) -> bool,
+        cancel: &CancellationToken,
+    ) -> Result<T, E> {
+        let mut retry = 0;
+        loop {
+            match f() {
+                Err(e) if retry < self.max_retries && retryable(&e) => {
+                    let delay = self.delay(retry);
+                    tracing::info!("{e}, retrying in {delay:?}");
+                    if !cancel.sleep(delay) {
+                        return Err(e);
+                    }
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Progress reporting and cancellation of a running scan
+pub struct ScanControl<'a> {
+    progress: SyncProgress,
+    callback: &'a dyn Fn(SyncProgress),
+    cancel: &'a CancellationToken,
+}
+
+impl<'a> ScanControl<'a> {
+    pub fn new(callback: &'a dyn Fn(SyncProgress), cancel: &'a CancellationToken) -> Self {
+        Self {
+            progress: SyncProgress::default(),
+            callback,
+            cancel,
+        }
+    }
+
+    /// The progress to update before calling [`ScanControl::report()`]
+    pub fn progress_mut(&mut self) -> &mut SyncProgress {
+        &mut self.progress
+    }
+
+    /// Call the progress callback with the current progress
+    pub fn report(&self) {
+        (self.callback)(self.progress.clone())
+    }
+
+    /// Return [`Error::Cancelled`] if the scan has been cancelled
+    pub fn check(&self) -> Result<(), Error> {
+        self.cancel.check()
+    }
+}
+
 /// Data processed after a "get history" call
 #[derive(Debug, PartialEq, Eq, Default)]
 pub struct Data {
@@ -59,6 +277,9 @@ pub struct Data {
     pub height_blockhash: HashMap<Height, BlockHash>,
     pub height_timestamp: HashMap<Height, Timestamp>,
     pub tip: Option<BlockHash>,
+
+    /// The scripts whose electrum status changed, with the new status
+    pub script_status: Vec<(Script, Option<ScriptHistoryStatus>)>,
 }
 
 /// Capabilities that can be supported by a [`blocking::BlockchainBackend`]
@@ -66,6 +287,10 @@ pub struct Data {
 pub enum Capability {
     /// Can interfact with a Waterfalls data source
     Waterfalls,
+
+    /// Can return the electrum status of scripts, so that only the histories changed since the
+    /// last scan are fetched
+    ScriptStatus,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -92,9 +317,7 @@ pub fn try_unblind(output: TxOut, descriptor: &WolletDescriptor) -> Result<TxOut
         (Asset::Confidential(_), Value::Confidential(_), Nonce::Confidential(_)) => {
             let receiver_sk = derive_blinding_key(descriptor.as_ref(), &output.script_pubkey)
                 .ok_or_else(|| Error::MissingPrivateBlindingKey)?;
-            let txout_secrets = output.unblind(&EC, receiver_sk)?;
-
-            Ok(txout_secrets)
+            unblind_output(&output, &receiver_sk)
         }
         _ => Err(Error::Generic(
             "received unconfidential or null asset/value/nonce".into(),
@@ -102,6 +325,31 @@ pub fn try_unblind(output: TxOut, descriptor: &WolletDescriptor) -> Result<TxOut
     }
 }
 
+/// The stored blocks to look for in the chain ending with `tip`, from the highest
+///
+/// Empty if the stored tip is `tip`, since nothing changed. Once a stored block is found in the
+/// chain, the ones below it are in the chain as well and don't need to be checked.
+pub(crate) fn blocks_to_verify<S: WolletState>(
+    state: &S,
+    tip: &BlockHeader,
+) -> Vec<(Height, BlockHash)> {
+    let stored_tip = state.tip();
+    if stored_tip.0 == 0 || stored_tip == (tip.height, tip.block_hash()) {
+        return vec![];
+    }
+    let mut blocks: Vec<_> = state
+        .block_hashes()
+        .iter()
+        .map(|(height, hash)| (*height, *hash))
+        .chain(std::iter::once(stored_tip))
+        .filter(|(height, _)| *height <= tip.height)
+        .collect();
+    blocks.sort_by(|a, b| b.0.cmp(&a.0));
+    blocks.dedup_by_key(|(height, _)| *height);
+    blocks
+}
+
+#[cfg(any(feature = "esplora", feature = "electrum"))]
 pub(crate) fn check_witnesses_non_empty(tx: &elements::Transaction) -> Result<(), Error> {
     if tx.input.iter().any(|e| e.witness.is_empty()) {
         return Err(Error::EmptyWitness);
@@ -111,6 +359,91 @@ pub(crate) fn check_witnesses_non_empty(tx: &elements::Transaction) -> Result<()
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    use super::{CancellationToken, RetryPolicy};
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(3),
+            jitter: false,
+        };
+        let delays: Vec<_> = (0..4).map(|i| policy.delay(i)).collect();
+        assert_eq!(delays, [1, 2, 3, 3].map(Duration::from_millis));
+        assert_eq!(policy.delay(100), Duration::from_millis(3));
+
+        let jitter = RetryPolicy {
+            jitter: true,
+            ..policy.clone()
+        };
+        let d = jitter.delay(1);
+        assert!(d >= Duration::from_millis(1) && d <= Duration::from_millis(2));
+
+        let calls = Cell::new(0);
+        let fail = || -> Result<(), String> {
+            calls.set(calls.get() + 1);
+            Err("fail".to_string())
+        };
+        assert!(policy.retry(fail, |_| true).is_err());
+        assert_eq!(calls.get(), 4);
+
+        calls.set(0);
+        assert!(policy.retry(fail, |_| false).is_err());
+        assert_eq!(calls.get(), 1);
+
+        calls.set(0);
+        let succeed_third = || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Err("fail".to_string())
+            } else {
+                Ok(calls.get())
+            }
+        };
+        assert_eq!(policy.retry(succeed_third, |_| true), Ok(3));
+        assert_eq!(
+            RetryPolicy::no_retry().retry(fail, |_| true),
+            Err("fail".to_string())
+        );
+
+        let patient = RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::patient()
+        };
+        let total: Duration = (0..patient.max_retries).map(|i| patient.delay(i)).sum();
+        assert_eq!(total, Duration::from_secs(127));
+        let until_cancelled = RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::until_cancelled()
+        };
+        assert_eq!(until_cancelled.delay(u32::MAX - 1), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_retry_cancellable() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_secs(60),
+            max_delay: Duration::from_secs(60),
+            jitter: false,
+        };
+        let cancel = CancellationToken::new();
+        let calls = Cell::new(0);
+        let fail = || -> Result<(), String> {
+            calls.set(calls.get() + 1);
+            cancel.cancel();
+            Err("fail".to_string())
+        };
+        let start = Instant::now();
+        assert!(policy.retry_cancellable(fail, |_| true, &cancel).is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(calls.get(), 1);
+    }
+
     /*
     use std::time::Instant;
 
@@ -158,7 +491,7 @@ mod tests {
             }
             prec = Some(balance);
 
-            log::info!(
+            tracing::info!(
                 "first run: {}: {:.2}s",
                 urls[i],
                 start.elapsed().as_secs_f64()
@@ -175,7 +508,7 @@ mod tests {
                 assert!(update.txid_height_delete.is_empty());
                 assert_ne!(update.tip, first_update.tip);
             }
-            log::info!(
+            tracing::info!(
                 "second run: {}: {:.2}s",
                 urls[i],
                 start.elapsed().as_secs_f64()
@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+
+use crate::elements::pset::PartiallySignedTransaction;
+use crate::elements::{Script, Transaction, Txid};
+use crate::pset_session::pset_base64;
+use crate::{ElementsNetwork, Error, Wollet};
+use lwk_common::PsetDetails;
+
+/// A PSET exchanged between the online watch-only wallet and the offline signer of a cold wallet
+///
+/// The watch-only wallet, with the network features, builds the PSET and exports it with
+/// [`ColdPset::export()`], keeping a copy. The offline instance, which only needs the same
+/// descriptor and no network access, reviews the details returned by [`ColdPset::verify()`],
+/// signs a copy of [`ColdPset::pset()`] and adds the signatures with [`ColdPset::add_signed()`].
+/// Back online, the watch-only wallet gets the transaction to broadcast with
+/// [`ColdPset::finalize()`], which checks it against the copy it kept.
+///
+/// Every step checks that the PSET is still for the same wallet and the same unsigned
+/// transaction, so that a PSET tampered with is refused before signing and before broadcasting.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ColdPset {
+    version: u32,
+
+    network: ElementsNetwork,
+
+    /// The descriptor wallet identifier of the wallet spending
+    wallet: String,
+
+    /// The id of the transaction without script sigs, which signatures do not change
+    unsigned_txid: Txid,
+
+    #[serde(with = "pset_base64")]
+    pset: PartiallySignedTransaction,
+}
+
+impl ColdPset {
+    /// The latest version of the format
+    pub const VERSION: u32 = 1;
+
+    /// Export `pset`, spending from `wollet`, to be signed offline
+    pub fn export(wollet: &Wollet, pset: PartiallySignedTransaction) -> Result<Self, Error> {
+        let details = wollet.get_details(&pset)?;
+        if !details.balance.balances.values().any(|v| *v < 0) {
+            return Err(Error::InvalidColdPset(
+                "the PSET does not spend from the wallet".into(),
+            ));
+        }
+        wollet.verify_signatures(&pset)?;
+        Ok(Self {
+            version: Self::VERSION,
+            network: wollet.network(),
+            wallet: wallet_id(wollet)?,
+            unsigned_txid: unsigned_txid(&pset.extract_tx()?),
+            pset,
+        })
+    }
+
+    pub fn pset(&self) -> &PartiallySignedTransaction {
+        &self.pset
+    }
+
+    pub fn network(&self) -> ElementsNetwork {
+        self.network
+    }
+
+    /// The id of the transaction without script sigs
+    ///
+    /// It is the id of the finalized transaction only if the wallet spends native segwit outputs.
+    pub fn unsigned_txid(&self) -> Txid {
+        self.unsigned_txid
+    }
+
+    /// Check that the PSET spends from `wollet` as exported and that its signatures are valid,
+    /// returning its details to be reviewed before signing
+    ///
+    /// The details are computed from the PSET, to be reviewed by the user, since anything else
+    /// in the exported data could have been changed together with the PSET. `wollet` does not need
+    /// to be synced, the offline instance can create it from the descriptor only.
+    pub fn verify(&self, wollet: &Wollet) -> Result<PsetDetails, Error> {
+        self.verify_pset(wollet, &self.pset)
+    }
+
+    /// Add the signatures of `signed`, a signed copy of [`ColdPset::pset()`]
+    ///
+    /// Fails if `signed` is not the exported PSET or if any signature is invalid.
+    pub fn add_signed(
+        &mut self,
+        wollet: &Wollet,
+        signed: PartiallySignedTransaction,
+    ) -> Result<(), Error> {
+        self.verify_pset(wollet, &signed)?;
+        let mut pset = self.pset.clone();
+        pset.merge(signed)?;
+        self.verify_pset(wollet, &pset)?;
+        self.pset = pset;
+        Ok(())
+    }
+
+    /// Verify the PSET and finalize it, returning the transaction to broadcast
+    ///
+    /// `exported` is the copy kept by the watch-only wallet when exporting, the transaction must
+    /// be the same apart from the script sigs and the witnesses.
+    pub fn finalize(mut self, wollet: &Wollet, exported: &ColdPset) -> Result<Transaction, Error> {
+        exported.verify(wollet)?;
+        if self.unsigned_txid != exported.unsigned_txid {
+            return Err(Error::InvalidColdPset(format!(
+                "transaction {} is not the exported one {}",
+                self.unsigned_txid, exported.unsigned_txid
+            )));
+        }
+        self.verify(wollet)?;
+        let tx = wollet.finalize(&mut self.pset)?;
+        let txid = unsigned_txid(&tx);
+        if txid != exported.unsigned_txid {
+            return Err(Error::InvalidColdPset(format!(
+                "finalized transaction {txid} is not the exported one {}",
+                exported.unsigned_txid
+            )));
+        }
+        Ok(tx)
+    }
+
+    fn verify_pset(
+        &self,
+        wollet: &Wollet,
+        pset: &PartiallySignedTransaction,
+    ) -> Result<PsetDetails, Error> {
+        if wollet.network() != self.network {
+            return Err(Error::InvalidColdPset(format!(
+                "exported for {} but the wallet is on {}",
+                self.network.as_str(),
+                wollet.network().as_str()
+            )));
+        }
+        if wallet_id(wollet)? != self.wallet {
+            return Err(Error::InvalidColdPset(format!(
+                "exported for wallet {}",
+                self.wallet
+            )));
+        }
+        let txid = unsigned_txid(&pset.extract_tx()?);
+        if txid != self.unsigned_txid {
+            return Err(Error::InvalidColdPset(format!(
+                "transaction {txid} is not the exported one {}",
+                self.unsigned_txid
+            )));
+        }
+        let details = wollet.get_details(pset)?;
+        if !details.balance.balances.values().any(|v| *v < 0) {
+            return Err(Error::InvalidColdPset(
+                "the PSET does not spend from the wallet".into(),
+            ));
+        }
+        wollet.verify_signatures(pset)?;
+        Ok(details)
+    }
+}
+
+/// The id of `tx` without script sigs, committing to the fields signatures do not change
+///
+/// The witnesses are not part of the id.
+fn unsigned_txid(tx: &Transaction) -> Txid {
+    let mut tx = tx.clone();
+    for input in tx.input.iter_mut() {
+        input.script_sig = Script::new();
+    }
+    tx.txid()
+}
+
+pub(crate) fn wallet_id(wollet: &Wollet) -> Result<String, Error> {
+    let network = match wollet.network() {
+        ElementsNetwork::Liquid => lwk_common::Network::Liquid,
+        ElementsNetwork::LiquidTestnet => lwk_common::Network::TestnetLiquid,
+        ElementsNetwork::ElementsRegtest { .. } => lwk_common::Network::LocaltestLiquid,
+    };
+    wollet.wollet_descriptor().dwid(network)
+}
+
+impl std::str::FromStr for ColdPset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cold: ColdPset = serde_json::from_str(s)?;
+        if cold.version > Self::VERSION {
+            return Err(Error::UnsupportedColdPsetVersion(cold.version));
+        }
+        Ok(cold)
+    }
+}
+
+impl std::fmt::Display for ColdPset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use lwk_common::{singlesig_desc, DescriptorBlindingKey, Signer, Singlesig};
+
+    use super::*;
+    use crate::blocking::MockBackend;
+    use crate::WolletDescriptor;
+
+    #[test]
+    fn test_cold_pset() {
+        // The txid of sh(wpkh) transactions changes once finalized
+        for variant in [Singlesig::Wpkh, Singlesig::ShWpkh] {
+            cold_pset(variant);
+        }
+    }
+
+    fn cold_pset(variant: Singlesig) {
+        let network = ElementsNetwork::default_regtest();
+        let signer = lwk_signer::SwSigner::new(lwk_test_util::TEST_MNEMONIC, false).unwrap();
+        let desc = singlesig_desc(&signer, variant, DescriptorBlindingKey::Slip77).unwrap();
+        let desc = WolletDescriptor::from_str(&desc).unwrap();
+        let mut watch = Wollet::without_persist(network, desc.clone()).unwrap();
+        let mut client = MockBackend::new(network);
+        client.fund_wollet(&mut watch, 100_000, None).unwrap();
+        client.sync(&mut watch).unwrap();
+
+        // The watch-only wallet builds and exports the PSET, keeping a copy
+        let other_desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let other = Wollet::without_persist(network, other_desc).unwrap();
+        let recipient = other.address(None).unwrap().address().clone();
+        let pset = watch
+            .tx_builder()
+            .add_lbtc_recipient(&recipient, 10_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let kept = ColdPset::export(&watch, pset).unwrap();
+        let exported = kept.to_string();
+
+        // The offline instance has only the descriptor, and reviews the details
+        let offline = Wollet::without_persist(network, desc).unwrap();
+        let mut cold = ColdPset::from_str(&exported).unwrap();
+        let details = cold.verify(&offline).unwrap();
+        assert_eq!(details.balance.recipients.len(), 1);
+        assert_eq!(
+            details.balance.balances[&network.policy_asset()],
+            -10_000 - details.balance.fee as i64
+        );
+
+        let mut signed = cold.pset().clone();
+        assert_eq!(signer.sign(&mut signed).unwrap(), 1);
+        cold.add_signed(&offline, signed).unwrap();
+        let signed = cold.to_string();
+
+        // The watch-only wallet finalizes, checking against the copy it kept
+        let cold = ColdPset::from_str(&signed).unwrap();
+        let tx = cold.clone().finalize(&watch, &kept).unwrap();
+        assert_eq!(unsigned_txid(&tx), kept.unsigned_txid());
+        if matches!(variant, Singlesig::Wpkh) {
+            assert_eq!(tx.txid(), kept.unsigned_txid());
+        } else {
+            assert_ne!(tx.txid(), kept.unsigned_txid());
+        }
+
+        // PSETs replaced while offline are refused, even with consistent exported data
+        let other_pset = watch
+            .tx_builder()
+            .add_lbtc_recipient(&recipient, 20_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut replaced = ColdPset::export(&watch, other_pset.clone()).unwrap();
+        replaced.verify(&offline).unwrap();
+        let mut signed = replaced.pset().clone();
+        signer.sign(&mut signed).unwrap();
+        replaced.add_signed(&offline, signed).unwrap();
+        let err = replaced.finalize(&watch, &kept).unwrap_err();
+        assert!(err.to_string().contains("is not the exported one"), "{err}");
+
+        let mut cold = ColdPset::from_str(&exported).unwrap();
+        let err = cold.add_signed(&offline, other_pset).unwrap_err();
+        assert!(err.to_string().contains("is not the exported one"), "{err}");
+
+        assert!(cold.verify(&other).is_err());
+
+        let unsupported = exported.replace("\"version\":1", "\"version\":2");
+        let err = ColdPset::from_str(&unsupported).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedColdPsetVersion(2)));
+    }
+}
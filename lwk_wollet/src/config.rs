@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-use crate::elements::{AddressParams, AssetId};
+use crate::elements::{AddressParams, AssetId, BlockHash};
 use crate::error::Error;
+use crate::explorer::Explorer;
 use std::str::FromStr;
 
 const LIQUID_POLICY_ASSET_STR: &str =
@@ -9,6 +10,11 @@ const LIQUID_POLICY_ASSET_STR: &str =
 const LIQUID_TESTNET_POLICY_ASSET_STR: &str =
     "144c654344aa716d6f3abcc1ca90e5641e4e2a7f633bc09fe3baf64585819a49";
 
+const LIQUID_GENESIS_HASH: &str =
+    "1466275836220db2944ca059a3a10ef6fd2ea684b0688d2c379296888a206003";
+const LIQUID_TESTNET_GENESIS_HASH: &str =
+    "a771da8e52ee6ad581ed1e9a99825e5b3b7992225534eaa2ae23244fe26ab1c1";
+
 /// The network of the elements blockchain.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Copy, Hash)]
 pub enum ElementsNetwork {
@@ -38,6 +44,17 @@ impl ElementsNetwork {
         }
     }
 
+    /// Return the hash of the genesis block, `None` for regtest since it depends on the chain
+    /// parameters
+    pub fn genesis_hash(&self) -> Option<BlockHash> {
+        let hash = match self {
+            ElementsNetwork::Liquid => LIQUID_GENESIS_HASH,
+            ElementsNetwork::LiquidTestnet => LIQUID_TESTNET_GENESIS_HASH,
+            ElementsNetwork::ElementsRegtest { .. } => return None,
+        };
+        Some(BlockHash::from_str(hash).expect("can't fail on const"))
+    }
+
     pub fn address_params(&self) -> &'static AddressParams {
         match self {
             ElementsNetwork::Liquid => &AddressParams::LIQUID,
@@ -76,6 +93,11 @@ impl ElementsNetwork {
         }
     }
 
+    /// Return the default block explorer of this network, `None` for regtest
+    pub fn explorer(&self) -> Option<Explorer> {
+        Explorer::blockstream(*self)
+    }
+
     #[cfg(feature = "bindings")]
     pub fn tx_builder(&self) -> crate::TxBuilder {
         crate::TxBuilder::new(*self)
@@ -0,0 +1,82 @@
+use crate::elements::pset::PartiallySignedTransaction;
+use crate::elements::Txid;
+use crate::wollet::Wollet;
+use crate::Error;
+
+impl Wollet {
+    /// Create a PSET spending an output of the unconfirmed transaction `txid` (child pays for
+    /// parent)
+    ///
+    /// The child spends the wallet L-BTC output of the parent with the higher value and sends it
+    /// back to the wallet, paying a fee such that the package composed by the parent and the
+    /// child reaches `fee_rate` (in sats/kvb). If the parent already pays `fee_rate`, the child
+    /// pays `fee_rate` as well. Unconfirmed wallet transactions the parent spends from, see
+    /// [`Wollet::tx_ancestors()`], are part of the package too.
+    ///
+    /// Note that the child transaction can be used to speed up the confirmation of transactions
+    /// received by the wallet, while [`Wollet::bump_fee()`] only works with transactions sent.
+    pub fn cpfp(&self, txid: &Txid, fee_rate: f32) -> Result<PartiallySignedTransaction, Error> {
+        let parent = self.unconfirmed_tx(txid)?;
+        let policy_asset = self.policy_asset();
+        let utxo = self
+            .utxos()?
+            .into_iter()
+            .filter(|u| u.outpoint.txid == *txid && u.unblinded.asset == policy_asset)
+            .max_by_key(|u| u.unblinded.value)
+            .ok_or_else(|| Error::Generic(format!("Transaction {txid} has no L-BTC utxo")))?;
+
+        let ancestors = self.tx_ancestors(txid)?;
+        let parent_vsize = parent.discount_weight().div_ceil(4) + ancestors.vsize();
+        let parent_fee = parent.fee_in(policy_asset) + ancestors.fee();
+
+        let builder = self
+            .tx_builder()
+            .set_wallet_utxos(vec![utxo.outpoint])
+            .fee_rate(Some(fee_rate));
+        let child_vsize = builder.estimated_weight()?.div_ceil(4);
+
+        let package_fee = ((parent_vsize + child_vsize) as f32 * fee_rate / 1000.0).ceil() as u64;
+        let child_fee = package_fee.saturating_sub(parent_fee);
+        let child_fee_rate = (child_fee as f32 * 1000.0 / child_vsize as f32).max(fee_rate);
+
+        builder.fee_rate(Some(child_fee_rate)).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::test_wollet_with_many_transactions;
+
+    #[test]
+    fn test_cpfp() {
+        let mut wollet = test_wollet_with_many_transactions();
+        let policy_asset = wollet.policy_asset();
+        let utxo = wollet
+            .utxos()
+            .unwrap()
+            .into_iter()
+            .find(|u| u.unblinded.asset == policy_asset)
+            .unwrap();
+        let txid = utxo.outpoint.txid;
+        assert!(matches!(
+            wollet.cpfp(&txid, 1000.0),
+            Err(Error::ConfirmedTransaction(_))
+        ));
+        wollet.store.cache.heights.insert(txid, None);
+
+        let fee_rate = 1000.0;
+        let pset = wollet.cpfp(&txid, fee_rate).unwrap();
+        assert_eq!(pset.inputs().len(), 1);
+        assert_eq!(pset.inputs()[0].previous_txid, txid);
+
+        let parent = wollet.get_tx(&txid).unwrap();
+        let child = pset.extract_tx().unwrap();
+        let vsize =
+            (parent.discount_weight() + child.discount_weight() + wollet.max_weight_to_satisfy())
+                .div_ceil(4);
+        let fee = parent.fee_in(policy_asset) + child.fee_in(policy_asset);
+        assert!(fee as f32 * 1000.0 / vsize as f32 >= fee_rate);
+        assert!(child.fee_in(policy_asset) > parent.fee_in(policy_asset));
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt::Display, str::FromStr};
+use std::{collections::HashSet, fmt::Display, str::FromStr};
 
 use aes_gcm_siv::aead::generic_array::GenericArray;
 use aes_gcm_siv::Aes256GcmSiv;
@@ -10,7 +10,7 @@ use elements_miniscript::BtcDescriptor;
 use elements_miniscript::DefiniteDescriptorKey;
 use elements_miniscript::{
     confidential::Key,
-    descriptor::{DescriptorSecretKey, Wildcard},
+    descriptor::{DescriptorSecretKey, SinglePubKey, Wildcard},
     ConfidentialDescriptor, Descriptor, DescriptorPublicKey, ForEachKey,
 };
 use serde::{Deserialize, Serialize};
@@ -146,6 +146,75 @@ impl TryFrom<&ConfidentialDescriptor<DescriptorPublicKey>> for Chain {
     }
 }
 
+/// A potential problem of a descriptor found by [`WolletDescriptor::validate_strict()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorWarning {
+    /// The extended key has no key origin, signers may not recognize it as theirs
+    MissingKeyOrigin(String),
+
+    /// Keys have different wildcards, some may derive the same key for every address
+    InconsistentWildcards,
+
+    /// The key appears more than once in the descriptor
+    DuplicateKey(String),
+
+    /// The SLIP77 master blinding key is all zeros
+    WeakBlindingKey,
+
+    /// The blinding key is one of the keys of the descriptor, sharing the descriptor for
+    /// watch-only reveals it
+    BlindingKeyReused,
+}
+
+impl DescriptorWarning {
+    /// A short identifier of the kind of warning
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DescriptorWarning::MissingKeyOrigin(_) => "missing_key_origin",
+            DescriptorWarning::InconsistentWildcards => "inconsistent_wildcards",
+            DescriptorWarning::DuplicateKey(_) => "duplicate_key",
+            DescriptorWarning::WeakBlindingKey => "weak_blinding_key",
+            DescriptorWarning::BlindingKeyReused => "blinding_key_reused",
+        }
+    }
+
+    /// The key the warning refers to, if any
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            DescriptorWarning::MissingKeyOrigin(k) | DescriptorWarning::DuplicateKey(k) => Some(k),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DescriptorWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DescriptorWarning::MissingKeyOrigin(k) => write!(f, "key {k} has no key origin"),
+            DescriptorWarning::InconsistentWildcards => {
+                write!(f, "descriptor keys have different wildcards")
+            }
+            DescriptorWarning::DuplicateKey(k) => write!(f, "key {k} is used more than once"),
+            DescriptorWarning::WeakBlindingKey => write!(f, "blinding key is all zeros"),
+            DescriptorWarning::BlindingKeyReused => {
+                write!(f, "blinding key is also a key of the descriptor")
+            }
+        }
+    }
+}
+
+/// The key without origin, derivation path and wildcard, to compare keys
+fn key_id(key: &DescriptorPublicKey) -> String {
+    match key {
+        DescriptorPublicKey::Single(k) => match k.key {
+            SinglePubKey::FullKey(pk) => pk.to_string(),
+            SinglePubKey::XOnly(pk) => pk.to_string(),
+        },
+        DescriptorPublicKey::XPub(k) => k.xkey.to_string(),
+        DescriptorPublicKey::MultiXPub(k) => k.xkey.to_string(),
+    }
+}
+
 impl WolletDescriptor {
     pub fn descriptor(&self) -> &Descriptor<DescriptorPublicKey> {
         &self.0.descriptor
@@ -214,6 +283,38 @@ impl WolletDescriptor {
         result
     }
 
+    /// Return a descriptor to share with a scanning service, which can find the wallet
+    /// transactions but cannot unblind them
+    ///
+    /// The returned descriptor has the same scripts of this one, without key origins, and the
+    /// [Elip151](https://github.com/ElementsProject/ELIPs/blob/main/elip-0151.mediawiki)
+    /// blinding key in place of the wallet one. The service can use it to scan with a [`crate::Wollet`]
+    /// and return the [`crate::Update`], which must be unblinded locally with
+    /// [`crate::Wollet::unblind_scanned_update()`] before being applied.
+    ///
+    /// Fails if this descriptor uses the Elip151 blinding key, because it would be shared too.
+    pub fn scanning_descriptor(&self) -> Result<WolletDescriptor, crate::error::Error> {
+        if self.is_elip151() {
+            return Err(crate::error::Error::ScanningDescriptorWithElip151);
+        }
+        let desc = format!(
+            "ct(elip151,{})",
+            self.bitcoin_descriptor_without_key_origin()
+        );
+        WolletDescriptor::from_str(&desc)
+    }
+
+    /// Like [`WolletDescriptor::scanning_descriptor()`], but encrypted for the scanning service
+    /// `recipient` and encoded in base64
+    #[cfg(feature = "esplora")]
+    pub fn encrypted_scanning_descriptor(
+        &self,
+        recipient: age::x25519::Recipient,
+    ) -> Result<String, crate::error::Error> {
+        let desc = self.scanning_descriptor()?.to_string();
+        crate::clients::asyncr::encrypt(&desc, recipient)
+    }
+
     /// return the single descriptor if not multipath, if multipath returns the internal or the
     /// external descriptor accordint to `int_or_ext`
     fn inner_descriptor_if_available(&self, ext_int: Chain) -> WolletDescriptor {
@@ -326,6 +427,86 @@ impl WolletDescriptor {
         }
     }
 
+    /// Check the descriptor more strictly than parsing does, returning the potential problems
+    ///
+    /// Parsing accepts every descriptor the wallet can use, this also checks that every
+    /// extended key has its key origin, that keys have the same wildcard, that keys are not
+    /// repeated and that the blinding key is sound.
+    pub fn validate_strict(&self) -> Vec<DescriptorWarning> {
+        let mut warnings = vec![];
+        let mut keys = vec![];
+        self.descriptor().for_each_key(|k| {
+            keys.push(k.clone());
+            true
+        });
+
+        for key in keys.iter() {
+            let has_origin = match key {
+                DescriptorPublicKey::Single(_) => true,
+                DescriptorPublicKey::XPub(k) => k.origin.is_some(),
+                DescriptorPublicKey::MultiXPub(k) => k.origin.is_some(),
+            };
+            if !has_origin {
+                warnings.push(DescriptorWarning::MissingKeyOrigin(key_id(key)));
+            }
+        }
+
+        let wildcards: HashSet<_> = keys
+            .iter()
+            .map(|k| match k {
+                DescriptorPublicKey::Single(_) => Wildcard::None,
+                DescriptorPublicKey::XPub(k) => k.wildcard,
+                DescriptorPublicKey::MultiXPub(k) => k.wildcard,
+            })
+            .collect();
+        if wildcards.len() > 1 {
+            warnings.push(DescriptorWarning::InconsistentWildcards);
+        }
+
+        let mut seen = HashSet::new();
+        let mut duplicates = HashSet::new();
+        for id in keys.iter().map(key_id) {
+            if !seen.insert(id.clone()) && duplicates.insert(id.clone()) {
+                warnings.push(DescriptorWarning::DuplicateKey(id));
+            }
+        }
+
+        match &self.0.key {
+            Key::Slip77(k) if k.as_bytes().iter().all(|b| *b == 0) => {
+                warnings.push(DescriptorWarning::WeakBlindingKey)
+            }
+            Key::View(k) => {
+                if let Ok(public) = k.to_public(&EC) {
+                    if seen.contains(&key_id(&public)) {
+                        warnings.push(DescriptorWarning::BlindingKeyReused);
+                    }
+                }
+            }
+            _ => {}
+        }
+        warnings
+    }
+
+    /// Check that the extended keys of the descriptor are for `network`
+    ///
+    /// Liquid uses mainnet keys (xpub), Liquid Testnet and regtest use testnet keys (tpub), as
+    /// in [`WolletDescriptor::is_mainnet()`]. Descriptors with single keys only are valid on any
+    /// network.
+    pub fn check_network(&self, network: crate::ElementsNetwork) -> Result<(), crate::Error> {
+        let mainnet = matches!(network, crate::ElementsNetwork::Liquid);
+        let single_keys = self
+            .descriptor()
+            .for_each_key(|k| matches!(k, DescriptorPublicKey::Single(_)));
+        if single_keys || self.is_mainnet() == mainnet {
+            Ok(())
+        } else {
+            Err(crate::Error::DescriptorNetworkMismatch {
+                mainnet_keys: !mainnet,
+                network,
+            })
+        }
+    }
+
     /// Returns true if all the xpubs in the descriptors are for mainnet
     pub fn is_mainnet(&self) -> bool {
         self.descriptor().for_each_key(|k| match k {
@@ -547,6 +728,7 @@ mod test {
         let tpub = "tpubDC2Q4xK4XH72GM7MowNuajyWVbigRLBWKswyP5T88hpPwu5nGqJWnda8zhJEFt71av73Hm8mUMMFSz9acNVzz8b1UbdSHCDXKTbSv5eEytu";
         let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
         let view_key = "1111111111111111111111111111111111111111111111111111111111111111";
+        let pk = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
 
         // An xpub might contain the tpub string and viceversa
         let xpub_t = "xpub661MyMwAqRbcH4oCG7tpubMCYWM3pHRZbhBQgi7uVZGcu1EuuomWqwB5gGHXk4VykarKGVA2jKtT4esCXspWW45mzwAzZEsi3U5j94gCKXc";
@@ -560,8 +742,15 @@ mod test {
             format!("ct({view_key},elwsh(multi(2,{tpub}/<0;1>/*,{tpub}/<0;1>/*)))"),
             format!("ct({view_key},elwsh(multi(2,{tpub}/*,{xpub}/*)))"),
             format!("ct({view_key},elwpkh({tpub_x}/*))"),
+            // single keys are valid on any network
+            format!("ct({view_key},elwsh(multi(2,{pk},{tpub}/*)))"),
         ] {
-            assert!(!WolletDescriptor::from_str(&d).unwrap().is_mainnet());
+            let desc = WolletDescriptor::from_str(&d).unwrap();
+            assert!(!desc.is_mainnet());
+            desc.check_network(crate::ElementsNetwork::LiquidTestnet)
+                .unwrap();
+            desc.check_network(crate::ElementsNetwork::Liquid)
+                .unwrap_err();
         }
 
         // mainnet
@@ -571,8 +760,13 @@ mod test {
             format!("ct({view_key},elwsh(multi(2,{xpub}/*,{xpub}/*)))"),
             format!("ct({view_key},elwsh(multi(2,{xpub}/<0;1>/*,{xpub}/<0;1>/*)))"),
             format!("ct({view_key},elwpkh({xpub_t}/*))"),
+            format!("ct({view_key},elwsh(multi(2,{pk},{xpub}/*)))"),
         ] {
-            assert!(WolletDescriptor::from_str(&d).unwrap().is_mainnet());
+            let desc = WolletDescriptor::from_str(&d).unwrap();
+            assert!(desc.is_mainnet());
+            desc.check_network(crate::ElementsNetwork::Liquid).unwrap();
+            desc.check_network(crate::ElementsNetwork::default_regtest())
+                .unwrap_err();
         }
     }
 
@@ -604,6 +798,34 @@ mod test {
         assert_eq!(a, s);
     }
 
+    #[test]
+    fn test_sortedmulti() {
+        let xpub1 = "[11111111/87h/1h/0h]tpubDC2Q4xK4XH72GM7MowNuajyWVbigRLBWKswyP5T88hpPwu5nGqJWnda8zhJEFt71av73Hm8mUMMFSz9acNVzz8b1UbdSHCDXKTbSv5eEytu/<0;1>/*";
+        let xpub2 = "[22222222/87h/1h/0h]tpubDCTUqRMrF2GHZ6pi5FcamdyGZ3oDJtQMH4y5Hyh8Uu7CQ3Zymbh1hpM84aXyJhgBhuh6WcUpKteMeYdyYfVUDRrsz8FUeRdoaaSRKkyMx6Y/<0;1>/*";
+        let desc = |kind: &str, a: &str, b: &str| -> WolletDescriptor {
+            format!("ct(elip151,elwsh({kind}(1,{a},{b})))")
+                .parse()
+                .unwrap()
+        };
+        let spks = |d: &WolletDescriptor| -> Vec<_> {
+            (0..5)
+                .map(|i| d.script_pubkey(Chain::External, i).unwrap())
+                .collect()
+        };
+
+        // With sortedmulti the key order in the descriptor does not matter
+        let sorted = spks(&desc("sortedmulti", xpub1, xpub2));
+        assert_eq!(sorted, spks(&desc("sortedmulti", xpub2, xpub1)));
+        let multi = spks(&desc("multi", xpub1, xpub2));
+        let multi_swapped = spks(&desc("multi", xpub2, xpub1));
+        assert_ne!(multi, multi_swapped);
+
+        // and it matches multi with the keys sorted at each derivation
+        for i in 0..5 {
+            assert!(sorted[i] == multi[i] || sorted[i] == multi_swapped[i]);
+        }
+    }
+
     #[test]
     fn get_pegin_address() {
         let d: BtcDescriptor<bitcoin::PublicKey> =
@@ -625,4 +847,59 @@ mod test {
         let pegin_address_api = desc.pegin_address(0, bitcoin::Network::Testnet, d).unwrap();
         assert_eq!(pegin_address_api.to_string(), expected);
     }
+
+    #[test]
+    fn test_validate_strict() {
+        use crate::DescriptorWarning;
+        use elements::bitcoin::bip32::{Xpriv, Xpub};
+        use elements::bitcoin::NetworkKind;
+
+        let slip77 = "slip77(ab5824f4477b4ebb00a132adfd8eb0b7935cf24f6ac151add5d1913db374ce92)";
+        let xpub = "tpubDCRMaF33e44pcJj534LXVhFbHibPbJ5vuLhSSPFAw57kYURv4tzXFL6LSnd78bkjqdmE3USedkbpXJUPA1tdzKfuYSL7PianceqAhwL2UkA";
+        let xpub2 = "tpubDDmvBugC5YMK3UDKjcym7ED8Vfv8aLiX83Tcbecc783VFPEDqBigmzF52uFMyh89bXaf7jAporM1LcoaMcLdKeV4m7ixNAchpMQCL569Ldv";
+        let validate = |desc: &str| {
+            let desc: WolletDescriptor = desc.parse().unwrap();
+            desc.validate_strict()
+        };
+
+        let desc = format!("ct({slip77},elwpkh([759db348/84'/1'/0']{xpub}/<0;1>/*))");
+        assert!(validate(&desc).is_empty());
+
+        let desc = format!("ct({slip77},elwpkh({xpub}/<0;1>/*))");
+        let warnings = validate(&desc);
+        assert_eq!(
+            warnings,
+            vec![DescriptorWarning::MissingKeyOrigin(xpub.to_string())]
+        );
+        assert_eq!(warnings[0].kind(), "missing_key_origin");
+        assert_eq!(warnings[0].key(), Some(xpub));
+
+        let desc = format!(
+            "ct({slip77},elwsh(multi(2,[759db348/84'/1'/0']{xpub}/0/*,[e6b7814d/87'/1'/0']{xpub2}/0)))"
+        );
+        assert_eq!(
+            validate(&desc),
+            vec![DescriptorWarning::InconsistentWildcards]
+        );
+
+        let desc = format!(
+            "ct({slip77},elwsh(multi(2,[759db348/84'/1'/0']{xpub}/<0;1>/*,[759db348/84'/1'/0']{xpub}/<0;1>/*)))"
+        );
+        assert_eq!(
+            validate(&desc),
+            vec![DescriptorWarning::DuplicateKey(xpub.to_string())]
+        );
+
+        let zeros = "slip77(0000000000000000000000000000000000000000000000000000000000000000)";
+        let desc = format!("ct({zeros},elwpkh([759db348/84'/1'/0']{xpub}/<0;1>/*))");
+        assert_eq!(validate(&desc), vec![DescriptorWarning::WeakBlindingKey]);
+
+        let xprv = Xpriv::new_master(NetworkKind::Test, &[1; 32]).unwrap();
+        let xpub = Xpub::from_priv(&EC, &xprv);
+        let desc = format!(
+            "ct({xprv},elwpkh([{}]{xpub}/<0;1>/*))",
+            xprv.fingerprint(&EC)
+        );
+        assert_eq!(validate(&desc), vec![DescriptorWarning::BlindingKeyReused]);
+    }
 }
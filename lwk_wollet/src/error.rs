@@ -1,4 +1,4 @@
-use elements::OutPoint;
+use elements::{BlockHash, OutPoint};
 
 /// Error type for the whole crate.
 #[derive(thiserror::Error, Debug)]
@@ -39,6 +39,10 @@ pub enum Error {
     #[error("Elements RPC returned an unexpected value for call {0}")]
     ElementsRpcUnexpectedReturn(String),
 
+    #[cfg(feature = "elements_rpc")]
+    #[error("The node would not accept the transaction in its mempool: {0}")]
+    MempoolRejected(String),
+
     #[error(transparent)]
     ElementsEncode(#[from] crate::elements::encode::Error),
 
@@ -87,9 +91,15 @@ pub enum Error {
     #[error(transparent)]
     PsetDetailsError(#[from] lwk_common::Error),
 
+    #[error(transparent)]
+    PsetCheck(#[from] lwk_common::pset::Error),
+
     #[error(transparent)]
     InvalidKeyOriginXpubError(#[from] lwk_common::InvalidKeyOriginXpub),
 
+    #[error(transparent)]
+    Precision(#[from] lwk_common::precision::Error),
+
     #[error(transparent)]
     UtxoUpdateError(#[from] elements_miniscript::psbt::UtxoUpdateError),
 
@@ -99,7 +109,7 @@ pub enum Error {
     #[error(transparent)]
     ParseInt(#[from] std::num::ParseIntError),
 
-    #[cfg(feature = "esplora")]
+    #[cfg(any(feature = "esplora", feature = "amp2", feature = "boltz"))]
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
@@ -120,9 +130,57 @@ pub enum Error {
     #[error("Missing issuance")]
     MissingIssuance,
 
+    #[error("{0} is not the reissuance token of an asset issued by the wallet")]
+    NotReissuanceToken(crate::elements::AssetId),
+
+    #[error("Asset {0} has been issued without reissuance tokens, it cannot be reissued")]
+    NotReissuable(crate::elements::AssetId),
+
     #[error("Missing transaction")]
     MissingTransaction,
 
+    #[error("Transaction {0} is unconfirmed")]
+    UnconfirmedTransaction(crate::elements::Txid),
+
+    #[error("Invalid SPV proof")]
+    InvalidSpvProof,
+
+    #[error("Invalid proof of reserves: {0}")]
+    InvalidProofOfReserves(String),
+
+    #[error("Invalid explicit proof: {0}")]
+    InvalidExplicitProof(String),
+
+    #[error("Output {0} is not unblinded by the wallet")]
+    MissingUnblindedOutput(crate::elements::OutPoint),
+
+    #[error("Transaction {0} is already confirmed")]
+    ConfirmedTransaction(crate::elements::Txid),
+
+    #[error("The new fee {new_fee} must be higher than the current fee {old_fee}")]
+    FeeBumpTooLow { old_fee: u64, new_fee: u64 },
+
+    #[error("Unsupported PSET session version {0}")]
+    UnsupportedPsetSessionVersion(u32),
+
+    #[error("Invalid PSET session threshold {threshold} with {participants} participants")]
+    InvalidPsetSessionThreshold {
+        threshold: usize,
+        participants: usize,
+    },
+
+    #[error("Unsupported backup version {0}")]
+    UnsupportedBackupVersion(u32),
+
+    #[error("Unsupported cold PSET version {0}")]
+    UnsupportedColdPsetVersion(u32),
+
+    #[error("Invalid cold PSET: {0}")]
+    InvalidColdPset(String),
+
+    #[error(transparent)]
+    ConfidentialTxOut(#[from] crate::elements::ConfidentialTxOutError),
+
     #[error("Missing vin")]
     MissingVin,
 
@@ -141,6 +199,9 @@ pub enum Error {
     #[error("Invalid domain")]
     InvalidDomain,
 
+    #[error("{url} must contain '{content}'")]
+    InvalidDomainProof { url: String, content: String },
+
     #[error("Invalid version")]
     InvalidVersion,
 
@@ -191,18 +252,36 @@ pub enum Error {
         update_status: u64,
     },
 
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Wallet lock poisoned")]
+    LockPoisoned,
+
     #[error("An issuance has already being set on this tx builder")]
     IssuanceAlreadySet,
 
     #[error("Blockchain backend have not implemented waterfalls method")]
     WaterfallsUnimplemented,
 
+    #[error("Blockchain backend have not implemented script status method")]
+    ScriptStatusUnimplemented,
+
     #[error("Cannot use waterfalls scan with elip151 because it would reveal the blinding key to the server")]
     UsingWaterfallsWithElip151,
 
+    #[error("Cannot create a scanning descriptor from an elip151 descriptor because it would reveal the blinding key")]
+    ScanningDescriptorWithElip151,
+
+    #[error("The scanned update is for a different descriptor")]
+    ScannedUpdateDescriptorMismatch,
+
     #[error("At the moment waterfalls does not support scanning up to index (index should be 0)")]
     UsingWaterfallsWithNonZeroIndex,
 
+    #[error("The wallet has never been synced")]
+    NotSynced,
+
     #[error("Cannot encrypt")]
     CannotEncrypt,
 
@@ -213,17 +292,70 @@ pub enum Error {
     #[error(transparent)]
     Url(#[from] crate::clients::blocking::electrum_client::UrlError),
 
+    #[cfg(feature = "electrum")]
+    #[error(
+        "The electrum server negotiated protocol version {0} instead of {}",
+        crate::clients::blocking::electrum_client::PROTOCOL_VERSION
+    )]
+    UnsupportedElectrumProtocol(String),
+
+    #[error(
+        "Descriptor is for the wrong network, it has {} keys while the network is {}",
+        if *mainnet_keys { "mainnet (xpub)" } else { "testnet (tpub)" },
+        network.as_str()
+    )]
+    DescriptorNetworkMismatch {
+        mainnet_keys: bool,
+        network: crate::ElementsNetwork,
+    },
+
+    #[error(
+        "The server is on another network, its genesis block is {found} instead of {expected}"
+    )]
+    WrongGenesisHash {
+        expected: BlockHash,
+        found: BlockHash,
+    },
+
     #[error("Manual coin selection is not allowed when assets are involved (this limitation will be removed in the future)")]
     ManualCoinSelectionOnlyLbtc,
 
     #[error("Missing wallet UTXO {0}")]
     MissingWalletUtxo(OutPoint),
 
+    #[error("Recovery is not allowed when assets are involved")]
+    RecoverOnlyLbtc,
+
+    #[error("There are no L-BTC UTXOs spendable with a timelocked path")]
+    NoRecoverableUtxos,
+
+    #[error("Splitting is allowed only for L-BTC recipients, without issuances and LiquiDEX")]
+    SplitOnlyLbtc,
+
+    #[error("Cannot split the transaction in transactions with weight at most {0}")]
+    CannotSplit(usize),
+
+    #[error("Cannot split a transaction spending {0}, its txid is known only once signed")]
+    SplitNotSegwit(OutPoint),
+
+    #[error("There are no funds controlled by the private key")]
+    NoFundsToSweep,
+
+    #[error("Cannot set the sequence for {0}, it is not spent by the transaction")]
+    SequenceForMissingInput(OutPoint),
+
+    #[error(transparent)]
+    Locktime(#[from] elements::locktime::Error),
+
     #[error("Transaction has empty witness, did you forget to sign and finalize?")]
     EmptyWitness,
 
     #[error(transparent)]
     LiquidexError(#[from] crate::liquidex::LiquidexError),
+
+    #[cfg(feature = "boltz")]
+    #[error(transparent)]
+    Boltz(#[from] crate::boltz::BoltzError),
 }
 
 // cannot derive automatically with this error because of trait bound
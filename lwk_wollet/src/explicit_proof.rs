@@ -0,0 +1,227 @@
+use crate::elements::confidential::{Asset, Value};
+use crate::elements::pset::PartiallySignedTransaction;
+use crate::elements::secp256k1_zkp::{RangeProof, SurjectionProof};
+use crate::elements::{AssetId, OutPoint, Transaction, TxOut, TxOutSecrets};
+use crate::util::EC;
+use crate::wollet::Wollet;
+use crate::Error;
+use elements::{BlindAssetProofs, BlindValueProofs};
+use serde::{Deserialize, Serialize};
+
+/// A proof of the asset and value of a confidential transaction output
+///
+/// It allows a third party to verify the asset and value of the output without knowing the
+/// blinding factors, for instance a payer can prove how much was paid to a recipient.
+///
+/// The proofs are the same explicit blind asset and value proofs used in PSET inputs and outputs,
+/// they prove the asset and value behind the commitments without revealing the blinding factors.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExplicitProof {
+    /// The output proven
+    pub outpoint: OutPoint,
+
+    /// The unblinded asset of the output
+    pub asset: AssetId,
+
+    /// The unblinded value of the output
+    pub value: u64,
+
+    /// Proof that the asset commitment is a blinding of `asset`
+    pub blind_asset_proof: SurjectionProof,
+
+    /// Proof that the value commitment is a blinding of `value`
+    pub blind_value_proof: RangeProof,
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidExplicitProof(reason.to_string())
+}
+
+impl ExplicitProof {
+    /// Create an explicit proof for the confidential output `txout` at `outpoint`, given its
+    /// blinding factors
+    pub fn new(outpoint: OutPoint, txout: &TxOut, secrets: &TxOutSecrets) -> Result<Self, Error> {
+        let (asset_comm, value_comm) = match (txout.asset, txout.value) {
+            (Asset::Confidential(a), Value::Confidential(v)) => (a, v),
+            _ => return Err(invalid("output is not confidential")),
+        };
+        let mut rng = rand::thread_rng();
+        let blind_asset_proof =
+            SurjectionProof::blind_asset_proof(&mut rng, &EC, secrets.asset, secrets.asset_bf)
+                .map_err(|e| Error::InvalidExplicitProof(e.to_string()))?;
+        let blind_value_proof = RangeProof::blind_value_proof(
+            &mut rng,
+            &EC,
+            secrets.value,
+            value_comm,
+            asset_comm,
+            secrets.value_bf,
+        )
+        .map_err(|e| Error::InvalidExplicitProof(e.to_string()))?;
+        let proof = Self {
+            outpoint,
+            asset: secrets.asset,
+            value: secrets.value,
+            blind_asset_proof,
+            blind_value_proof,
+        };
+        proof.verify_txout(txout)?;
+        Ok(proof)
+    }
+
+    /// Create an explicit proof for the output `vout` of a blinded PSET
+    ///
+    /// The PSET must contain the asset and value of the output and their blind proofs, as added
+    /// by the blinder. This allows the payer to prove a payment to an output it does not own.
+    pub fn from_pset(pset: &PartiallySignedTransaction, vout: u32) -> Result<Self, Error> {
+        let tx = pset.extract_tx()?;
+        let txout = tx.output.get(vout as usize).ok_or(Error::MissingVout)?;
+        let output = &pset.outputs()[vout as usize];
+        let proof = Self {
+            outpoint: OutPoint::new(tx.txid(), vout),
+            asset: output.asset.ok_or_else(|| invalid("missing asset"))?,
+            value: output.amount.ok_or_else(|| invalid("missing value"))?,
+            blind_asset_proof: *output
+                .blind_asset_proof
+                .clone()
+                .ok_or_else(|| invalid("missing blind asset proof"))?,
+            blind_value_proof: *output
+                .blind_value_proof
+                .clone()
+                .ok_or_else(|| invalid("missing blind value proof"))?,
+        };
+        proof.verify_txout(txout)?;
+        Ok(proof)
+    }
+
+    /// Verify the proof against the transaction containing the output
+    pub fn verify(&self, tx: &Transaction) -> Result<(), Error> {
+        if tx.txid() != self.outpoint.txid {
+            return Err(invalid("transaction does not match the outpoint"));
+        }
+        let txout = tx
+            .output
+            .get(self.outpoint.vout as usize)
+            .ok_or(Error::MissingVout)?;
+        self.verify_txout(txout)
+    }
+
+    fn verify_txout(&self, txout: &TxOut) -> Result<(), Error> {
+        let (asset_comm, value_comm) = match (txout.asset, txout.value) {
+            (Asset::Confidential(a), Value::Confidential(v)) => (a, v),
+            _ => return Err(invalid("output is not confidential")),
+        };
+        if !self
+            .blind_asset_proof
+            .blind_asset_proof_verify(&EC, self.asset, asset_comm)
+        {
+            return Err(invalid("asset does not match the commitment"));
+        }
+        if !self
+            .blind_value_proof
+            .blind_value_proof_verify(&EC, self.value, asset_comm, value_comm)
+        {
+            return Err(invalid("value does not match the commitment"));
+        }
+        Ok(())
+    }
+}
+
+impl Wollet {
+    /// Get the unblinded asset and value and the blinding factors of a wallet output
+    ///
+    /// The output can be spent or unspent.
+    pub fn output_blinders(&self, outpoint: &OutPoint) -> Result<TxOutSecrets, Error> {
+        self.store
+            .cache
+            .unblinded
+            .get(outpoint)
+            .cloned()
+            .ok_or(Error::MissingUnblindedOutput(*outpoint))
+    }
+
+    /// Create an [`ExplicitProof`] of the asset and value of a wallet output
+    pub fn explicit_proof(&self, outpoint: &OutPoint) -> Result<ExplicitProof, Error> {
+        let secrets = self.output_blinders(outpoint)?;
+        let txout = self.get_txout(outpoint)?;
+        ExplicitProof::new(*outpoint, &txout, &secrets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::test_wollet_with_many_transactions;
+
+    #[test]
+    fn test_explicit_proof() {
+        let wollet = test_wollet_with_many_transactions();
+        let txos = wollet.txos().unwrap();
+        let txo = &txos[0];
+        assert_eq!(
+            wollet.output_blinders(&txo.outpoint).unwrap(),
+            txo.unblinded
+        );
+
+        let tx = wollet.transaction(&txo.outpoint.txid).unwrap().unwrap().tx;
+        let proof = wollet.explicit_proof(&txo.outpoint).unwrap();
+        assert_eq!(proof.asset, txo.unblinded.asset);
+        assert_eq!(proof.value, txo.unblinded.value);
+        proof.verify(&tx).unwrap();
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let back: ExplicitProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(proof, back);
+
+        let mut wrong_value = proof.clone();
+        wrong_value.value += 1;
+        assert!(wrong_value.verify(&tx).is_err());
+
+        let mut wrong_asset = proof.clone();
+        wrong_asset.asset = AssetId::default();
+        assert!(wrong_asset.verify(&tx).is_err());
+
+        let other = txos
+            .iter()
+            .find(|t| t.outpoint.txid != txo.outpoint.txid)
+            .unwrap();
+        let other_tx = wollet
+            .transaction(&other.outpoint.txid)
+            .unwrap()
+            .unwrap()
+            .tx;
+        assert!(proof.verify(&other_tx).is_err());
+
+        // wrong blinders
+        let txout = &tx.output[txo.outpoint.vout as usize];
+        assert!(ExplicitProof::new(txo.outpoint, txout, &other.unblinded).is_err());
+
+        let not_mine = OutPoint::new(other.outpoint.txid, 1000);
+        assert!(wollet.output_blinders(&not_mine).is_err());
+    }
+
+    #[test]
+    fn test_explicit_proof_from_pset() {
+        let wollet = test_wollet_with_many_transactions();
+        let address = wollet.address(None).unwrap().address().clone();
+        let pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let tx = pset.extract_tx().unwrap();
+        let vout = pset
+            .outputs()
+            .iter()
+            .position(|o| o.amount == Some(1_000))
+            .unwrap() as u32;
+        let proof = ExplicitProof::from_pset(&pset, vout).unwrap();
+        assert_eq!(proof.value, 1_000);
+        proof.verify(&tx).unwrap();
+
+        // fee output is not confidential
+        let fee_vout = pset.outputs().len() as u32 - 1;
+        assert!(ExplicitProof::from_pset(&pset, fee_vout).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use elements::{Address, AssetId, BlockHash, Txid};
+
+use crate::{ElementsNetwork, WalletTx};
+
+/// Build links to the pages of a Liquid block explorer
+///
+/// The base url is the one of the web pages, not of the API, and always ends with `/`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Explorer {
+    base_url: String,
+}
+
+impl Explorer {
+    /// An explorer at a custom url, for instance a self hosted esplora
+    pub fn custom(base_url: &str) -> Self {
+        let mut base_url = base_url.to_string();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        Self { base_url }
+    }
+
+    /// The explorer at blockstream.info, `None` for regtest
+    pub fn blockstream(network: ElementsNetwork) -> Option<Self> {
+        match network {
+            ElementsNetwork::Liquid => Some(Self::custom("https://blockstream.info/liquid/")),
+            ElementsNetwork::LiquidTestnet => {
+                Some(Self::custom("https://blockstream.info/liquidtestnet/"))
+            }
+            ElementsNetwork::ElementsRegtest { .. } => None,
+        }
+    }
+
+    /// The explorer at liquid.network, `None` for regtest
+    pub fn liquid_network(network: ElementsNetwork) -> Option<Self> {
+        match network {
+            ElementsNetwork::Liquid => Some(Self::custom("https://liquid.network/")),
+            ElementsNetwork::LiquidTestnet => Some(Self::custom("https://liquid.network/testnet/")),
+            ElementsNetwork::ElementsRegtest { .. } => None,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn tx_url(&self, txid: &Txid) -> String {
+        format!("{}tx/{txid}", self.base_url)
+    }
+
+    /// The transaction page with the blinding data of the wallet inputs and outputs in the
+    /// fragment, so that the explorer shows the unblinded amounts and assets
+    pub fn unblinded_tx_url(&self, tx: &WalletTx) -> String {
+        tx.unblinded_url(&self.base_url)
+    }
+
+    pub fn address_url(&self, address: &Address) -> String {
+        format!("{}address/{address}", self.base_url)
+    }
+
+    pub fn asset_url(&self, asset_id: &AssetId) -> String {
+        format!("{}asset/{asset_id}", self.base_url)
+    }
+
+    pub fn block_url(&self, block_hash: &BlockHash) -> String {
+        format!("{}block/{block_hash}", self.base_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_explorer() {
+        let txid =
+            Txid::from_str("c6e3187f028942973ad27224ca79baa8382e90ad686e927fc29896e8a2edf3f3")
+                .unwrap();
+        let asset = ElementsNetwork::Liquid.policy_asset();
+        let address = Address::from_str("el1qq2xvpcvfup5j8zscjq05u2wxxjcyewk7979f3mmz5l7uw5pqmx6xf5xy50hsn6vhkm5euwt72x878eq6zxx2z0z676mna6kdq").unwrap();
+
+        let explorer = ElementsNetwork::Liquid.explorer().unwrap();
+        assert_eq!(explorer.base_url(), "https://blockstream.info/liquid/");
+        assert_eq!(
+            explorer.tx_url(&txid),
+            format!("https://blockstream.info/liquid/tx/{txid}")
+        );
+        assert_eq!(
+            explorer.asset_url(&asset),
+            format!("https://blockstream.info/liquid/asset/{asset}")
+        );
+
+        let explorer = Explorer::liquid_network(ElementsNetwork::LiquidTestnet).unwrap();
+        assert_eq!(
+            explorer.address_url(&address),
+            format!("https://liquid.network/testnet/address/{address}")
+        );
+
+        let regtest = ElementsNetwork::default_regtest();
+        assert!(regtest.explorer().is_none());
+        assert!(Explorer::liquid_network(regtest).is_none());
+
+        let custom = Explorer::custom("http://127.0.0.1:3000");
+        assert_eq!(custom, Explorer::custom("http://127.0.0.1:3000/"));
+        assert_eq!(
+            custom.tx_url(&txid),
+            format!("http://127.0.0.1:3000/tx/{txid}")
+        );
+
+        let json_str = include_str!("../tests/data/wallet_tx.json");
+        let wallet_tx: WalletTx = serde_json::from_str(json_str).unwrap();
+        let explorer = ElementsNetwork::LiquidTestnet.explorer().unwrap();
+        assert!(explorer
+            .unblinded_tx_url(&wallet_tx)
+            .starts_with(&format!("{}#blinded=", explorer.tx_url(&wallet_tx.txid))));
+    }
+}
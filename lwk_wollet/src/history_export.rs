@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use elements::{AssetId, Txid};
+use lwk_common::precision::Precision;
+use serde::Serialize;
+
+use crate::{Error, WalletTx};
+
+/// The precision of the policy asset, used for the fee column
+const POLICY_ASSET_PRECISION: u8 = 8;
+
+/// The formats supported by [`export_history()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for HistoryFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(HistoryFormat::Csv),
+            "json" => Ok(HistoryFormat::Json),
+            _ => Err(Error::Generic(format!(
+                "Unknown history format '{s}', expected 'csv' or 'json'"
+            ))),
+        }
+    }
+}
+
+impl Display for HistoryFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryFormat::Csv => write!(f, "csv"),
+            HistoryFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// A row of the exported history: the effect of a transaction on the balance of an asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HistoryEntry {
+    /// UTC date of the block including the transaction, empty if unconfirmed
+    pub date: String,
+
+    pub txid: Txid,
+
+    /// The ticker of the asset if known, otherwise the asset id
+    pub asset: String,
+
+    /// The net amount received (positive) or sent (negative), with the asset precision
+    pub amount: String,
+
+    /// The fee paid by the wallet, in the policy asset, only on the row of the policy asset
+    pub fee: String,
+
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    pub memo: String,
+}
+
+/// Convert wallet transactions to the rows of the history to export, one row per asset moved
+///
+/// `asset_info` returns the ticker and precision of an asset, unknown assets are reported by
+/// their id with precision 0.
+pub fn history_entries(
+    txs: &[WalletTx],
+    policy_asset: AssetId,
+    asset_info: impl Fn(&AssetId) -> Option<(String, u8)>,
+    memos: &HashMap<Txid, String>,
+) -> Result<Vec<HistoryEntry>, Error> {
+    let fee_precision = Precision::new(POLICY_ASSET_PRECISION)?;
+    let mut entries = vec![];
+    for tx in txs {
+        let date = tx.timestamp.map(format_date).unwrap_or_default();
+        let memo = memos.get(&tx.txid).cloned().unwrap_or_default();
+        for (asset_id, amount) in tx.balance.iter() {
+            let (asset, precision) =
+                asset_info(asset_id).unwrap_or_else(|| (asset_id.to_string(), 0));
+            let fee = if *asset_id == policy_asset && *amount < 0 {
+                tx.fee as i64
+            } else {
+                0
+            };
+            entries.push(HistoryEntry {
+                date: date.clone(),
+                txid: tx.txid,
+                asset,
+                amount: Precision::new(precision)?.sats_to_string(*amount),
+                fee: fee_precision.sats_to_string(fee),
+                type_: tx.type_.clone(),
+                memo: memo.clone(),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Serialize the history rows in the given format
+///
+/// The CSV has a header line with the columns `date,txid,asset,amount,fee,type,memo`.
+pub fn export_history(entries: &[HistoryEntry], format: HistoryFormat) -> Result<String, Error> {
+    match format {
+        HistoryFormat::Json => Ok(serde_json::to_string_pretty(entries)?),
+        HistoryFormat::Csv => {
+            let mut csv = "date,txid,asset,amount,fee,type,memo\n".to_string();
+            for e in entries {
+                let fields = [
+                    e.date.clone(),
+                    e.txid.to_string(),
+                    e.asset.clone(),
+                    e.amount.clone(),
+                    e.fee.clone(),
+                    e.type_.clone(),
+                    e.memo.clone(),
+                ];
+                let fields: Vec<_> = fields.iter().map(|f| csv_field(f)).collect();
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+            Ok(csv)
+        }
+    }
+}
+
+/// Quote the field if it contains characters with a special meaning in CSV
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Format a unix timestamp as `YYYY-MM-DD HH:MM:SS` in UTC
+fn format_date(timestamp: u32) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+
+    // Civil from days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01 00:00:00");
+        assert_eq!(format_date(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14 22:13:20");
+        assert_eq!(format_date(1_709_251_199), "2024-02-29 23:59:59");
+    }
+
+    #[test]
+    fn test_export_history() {
+        let wollet = crate::wollet::tests::test_wollet_with_many_transactions();
+        let txs = wollet.transactions().unwrap();
+        let policy_asset = wollet.policy_asset();
+        let memos: HashMap<_, _> = [(txs[0].txid, "rent, \"march\"".to_string())].into();
+        let info = |a: &AssetId| (*a == policy_asset).then(|| ("L-BTC".to_string(), 8));
+        let entries = history_entries(&txs, policy_asset, info, &memos).unwrap();
+        let rows: usize = txs.iter().map(|tx| tx.balance.len()).sum();
+        assert_eq!(entries.len(), rows);
+
+        let first = &entries[0];
+        assert_eq!(first.memo, "rent, \"march\"");
+        let lbtc: Vec<_> = entries.iter().filter(|e| e.asset == "L-BTC").collect();
+        assert!(!lbtc.is_empty());
+        assert!(lbtc.iter().all(|e| e.amount.contains('.')));
+        assert!(entries
+            .iter()
+            .filter(|e| e.asset != "L-BTC")
+            .all(|e| e.fee == "0.00000000"));
+
+        let csv = export_history(&entries, HistoryFormat::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "date,txid,asset,amount,fee,type,memo"
+        );
+        assert!(lines.next().unwrap().ends_with(",\"rent, \"\"march\"\"\""));
+        assert_eq!(csv.lines().count(), rows + 1);
+
+        let json = export_history(&entries, HistoryFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), rows);
+        assert_eq!(value[0]["type"], first.type_);
+
+        assert_eq!("csv".parse::<HistoryFormat>().unwrap(), HistoryFormat::Csv);
+        assert!("xls".parse::<HistoryFormat>().is_err());
+    }
+}
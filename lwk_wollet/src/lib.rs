@@ -15,6 +15,13 @@
 //! * Create transactions, inclunding issuances, reissuances and burn via the [`TxBuilder`].
 //! * Analyze a partially signed transaction with respect to the wallet via [`Wollet::get_details()`].
 //!
+//! ## Features
+//!
+//! The blockchain clients are behind the `electrum`, `esplora` and `elements_rpc` features, enabled
+//! by default. Without default features the crate only has the wallet logic, which works offline:
+//! descriptors, addresses, PSET creation and analysis, unblinding and persistence. This is enough
+//! for instance for the offline signer of a cold wallet, see [`ColdPset`].
+//!
 //! ## Examples
 //!
 //! ### Generate an address
@@ -90,50 +97,88 @@
 
 #[cfg(feature = "amp2")]
 pub mod amp2;
+mod backup;
+#[cfg(feature = "boltz")]
+pub mod boltz;
+mod bump_fee;
 pub mod clients;
+mod cold;
 mod config;
+mod cpfp;
 mod descriptor;
 mod domain;
 mod error;
+mod explicit_proof;
+mod explorer;
+mod history_export;
 mod liquidex;
 mod model;
 pub mod pegin;
 mod persister;
+mod portfolio;
+mod proof_of_reserves;
 mod pset_create;
+mod pset_session;
 pub mod registry;
+mod shared;
+mod spending_path;
+mod spv;
 mod store;
+#[cfg(not(target_arch = "wasm32"))]
+mod sweep;
 mod tx_builder;
+mod tx_chain;
+mod unblind;
 mod update;
 mod util;
 mod wollet;
 
-pub use crate::clients::{Capability, History};
+pub use crate::backup::WolletBackup;
+pub use crate::clients::{CancellationToken, Capability, History, RetryPolicy, SyncProgress};
+pub use crate::cold::ColdPset;
 pub use crate::config::ElementsNetwork;
-pub use crate::descriptor::{Chain, WolletDescriptor};
+pub use crate::descriptor::{Chain, DescriptorWarning, WolletDescriptor};
 pub use crate::error::Error;
+pub use crate::explicit_proof::ExplicitProof;
+pub use crate::explorer::Explorer;
+pub use crate::history_export::{export_history, history_entries, HistoryEntry, HistoryFormat};
 pub use crate::liquidex::LiquidexProposal;
 pub use crate::model::{
-    AddressResult, ExternalUtxo, IssuanceDetails, Recipient, UnvalidatedRecipient, WalletTx,
-    WalletTxOut,
+    AddressInfo, AddressResult, ExternalUtxo, IssuanceDetails, PaymentMismatch,
+    PaymentVerification, Recipient, TxChain, TxStatus, UnblindableOutput, UnvalidatedRecipient,
+    WalletTx, WalletTxOut,
 };
 pub use crate::pegin::fed_peg_script;
 pub use crate::persister::{FsPersister, NoPersist, PersistError, Persister};
-pub use crate::registry::{asset_ids, issuance_ids, Contract, Entity};
+pub use crate::portfolio::{Portfolio, PortfolioTx};
+pub use crate::proof_of_reserves::{proof_of_reserves_challenge, verify_proof_of_reserves};
+pub use crate::pset_session::{PsetSession, PsetSessionState};
+pub use crate::registry::{
+    asset_ids, issuance_ids, Contract, DomainProof, Entity, KnownAsset, KnownAssets,
+};
+pub use crate::shared::SharedWollet;
+pub use crate::spending_path::{InputSignatureStatus, SpendingPath};
+pub use crate::spv::{MerkleProof, SpvProof, SPV_PROOF_MAX_HEADERS};
+pub use crate::store::ScriptHistoryStatus;
 pub use crate::tx_builder::{TxBuilder, WolletTxBuilder};
+pub use crate::unblind::{unblind_output, unblind_transaction};
 pub use crate::update::{DownloadTxResult, Update};
 pub use crate::util::EC;
-pub use crate::wollet::{Tip, Wollet};
+pub use crate::wollet::{finalize_pset, Tip, Wollet, DEFAULT_FINALITY_THRESHOLD};
 
 #[cfg(feature = "electrum")]
 pub use crate::wollet::full_scan_to_index_with_electrum_client;
 #[cfg(feature = "electrum")]
 pub use crate::wollet::full_scan_with_electrum_client;
 #[cfg(feature = "electrum")]
-pub use clients::blocking::electrum_client::{ElectrumClient, ElectrumOptions, ElectrumUrl};
+pub use clients::blocking::electrum_client::{
+    ElectrumClient, ElectrumOptions, ElectrumUrl, ServerFeatures,
+};
 
 #[cfg(feature = "esplora")]
 pub use age;
 
+#[cfg(feature = "esplora")]
 pub use crate::clients::asyncr;
 
 #[cfg(feature = "electrum")]
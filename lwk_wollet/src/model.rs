@@ -1,6 +1,8 @@
 use crate::descriptor::Chain;
-use crate::elements::{Address, AssetId, OutPoint, Script, Transaction, TxOutSecrets, Txid};
-use crate::pset_create::validate_address;
+use crate::elements::{
+    Address, AssetId, BlockHash, OutPoint, Script, Transaction, TxOut, TxOutSecrets, Txid,
+};
+use crate::pset_create::{parse_address, validate_address};
 use crate::secp256k1::PublicKey;
 use crate::store::Timestamp;
 use crate::{ElementsNetwork, Error};
@@ -25,6 +27,20 @@ pub struct WalletTxOut {
     pub address: Address,
 }
 
+/// An output on a wallet script that the wallet cannot unblind
+///
+/// The sender blinded it with a key different from the wallet one, or messed up with the blinding
+/// process. See [`crate::Wollet::unblindable_outputs()`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UnblindableOutput {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    pub height: Option<u32>,
+    pub wildcard_index: u32,
+    pub ext_int: Chain,
+    pub is_spent: bool,
+}
+
 /// A UTXO owned by another wallet
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ExternalUtxo {
@@ -39,6 +55,14 @@ pub struct ExternalUtxo {
 
     /// Max weight to satisfy
     pub max_weight_to_satisfy: usize,
+
+    /// The redeem script, needed to sign P2SH outputs
+    #[serde(default)]
+    pub redeem_script: Option<elements::Script>,
+
+    /// The witness script, needed to sign P2WSH outputs
+    #[serde(default)]
+    pub witness_script: Option<elements::Script>,
 }
 
 /// Value returned by [`crate::Wollet::transactions()`] containing details about a transaction
@@ -55,6 +79,50 @@ pub struct WalletTx {
     pub timestamp: Option<Timestamp>,
     pub inputs: Vec<Option<WalletTxOut>>,
     pub outputs: Vec<Option<WalletTxOut>>,
+
+    /// Number of blocks from the one including the transaction to the wallet tip, both included
+    #[serde(default)]
+    pub confirmations: u32,
+
+    /// Confirmation status according to [`crate::Wollet::finality_threshold()`]
+    #[serde(default)]
+    pub status: TxStatus,
+}
+
+/// Confirmation status of a [`WalletTx`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    /// Not included in a block
+    #[default]
+    Unconfirmed,
+
+    /// Included in a block, with less confirmations than the finality threshold
+    Confirming(u32),
+
+    /// Confirmed by at least the finality threshold blocks
+    Final,
+}
+
+impl TxStatus {
+    /// The status of a transaction with `confirmations`, final from `finality_threshold`
+    pub fn new(confirmations: u32, finality_threshold: u32) -> Self {
+        match confirmations {
+            0 => TxStatus::Unconfirmed,
+            n if n < finality_threshold => TxStatus::Confirming(n),
+            _ => TxStatus::Final,
+        }
+    }
+}
+
+impl std::fmt::Display for TxStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TxStatus::Unconfirmed => write!(f, "unconfirmed"),
+            TxStatus::Confirming(_) => write!(f, "confirming"),
+            TxStatus::Final => write!(f, "final"),
+        }
+    }
 }
 
 /// A recipient of a transaction.
@@ -156,6 +224,17 @@ impl UnvalidatedRecipient {
     }
 
     pub fn validate(&self, network: ElementsNetwork) -> Result<Recipient, Error> {
+        self.validate_inner(network, false)
+    }
+
+    /// Validate the recipient, accepting unconfidential addresses if `allow_unconfidential`
+    ///
+    /// Sending to an unconfidential address reveals the asset and amount sent.
+    pub(crate) fn validate_inner(
+        &self,
+        network: ElementsNetwork,
+        allow_unconfidential: bool,
+    ) -> Result<Recipient, Error> {
         let satoshi = self.validate_satoshi()?;
         let asset = self.validate_asset(network)?;
         if self.address == "burn" {
@@ -167,7 +246,11 @@ impl UnvalidatedRecipient {
                 asset,
             })
         } else {
-            let address = validate_address(&self.address, network)?;
+            let address = if allow_unconfidential {
+                parse_address(&self.address, network)?
+            } else {
+                validate_address(&self.address, network)?
+            };
             Ok(Recipient::from_address(self.satoshi, &address, asset))
         }
     }
@@ -190,11 +273,209 @@ impl AddressResult {
         &self.address
     }
 
+    /// The address without the blinding key, for systems not supporting confidential addresses
+    ///
+    /// Funds sent to it are not blinded.
+    pub fn unconfidential(&self) -> Address {
+        self.address.to_unconfidential()
+    }
+
     pub fn index(&self) -> u32 {
         self.index
     }
 }
 
+/// Value returned from [`crate::Wollet::is_mine()`], where a script of the wallet is derived
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AddressInfo {
+    address: Address,
+    chain: Chain,
+    index: u32,
+}
+
+impl AddressInfo {
+    pub fn new(address: Address, chain: Chain, index: u32) -> Self {
+        Self {
+            address,
+            chain,
+            index,
+        }
+    }
+
+    /// The confidential address of the wallet with the script
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// The address without the blinding key, as shown by explorers for the script
+    pub fn unconfidential(&self) -> Address {
+        self.address.to_unconfidential()
+    }
+
+    /// Whether the script is a receiving or a change one
+    pub fn chain(&self) -> Chain {
+        self.chain
+    }
+
+    /// The derivation index of the script
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// The verification of a payment received by the wallet, returned from
+/// [`crate::Wollet::verify_payment()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentVerification {
+    /// The descriptor wallet id of the wallet, see [`crate::WolletDescriptor::dwid()`]
+    pub wallet_id: String,
+
+    pub txid: Txid,
+    pub vout: u32,
+    pub address: Address,
+    pub asset: AssetId,
+    pub satoshi: u64,
+
+    /// Confirmations required for the payment to be verified
+    pub min_confirmations: u32,
+
+    /// Height of the block including the transaction, `None` if unconfirmed
+    pub height: Option<u32>,
+
+    /// Confirmations of the transaction when verified, 0 if unconfirmed
+    pub confirmations: u32,
+
+    /// Height of the wallet tip when verified
+    pub tip_height: u32,
+
+    /// Hash of the wallet tip when verified
+    pub tip_hash: BlockHash,
+
+    /// Time of the wallet tip when verified, `None` if unknown
+    pub tip_timestamp: Option<Timestamp>,
+
+    /// Why the claimed payment does not match the transaction, `None` if verified
+    pub mismatch: Option<PaymentMismatch>,
+}
+
+impl PaymentVerification {
+    /// Whether the output pays the claimed amount of the asset to the address, with at least
+    /// the required confirmations
+    pub fn is_verified(&self) -> bool {
+        self.mismatch.is_none()
+    }
+
+    /// The claimed payment and the result of its verification, as text to be signed
+    ///
+    /// The wallet and its tip are included, so that the result can be bound to the state of the
+    /// blockchain seen by the wallet.
+    pub fn message(&self) -> String {
+        let height = match self.height {
+            Some(height) => height.to_string(),
+            None => "unconfirmed".to_string(),
+        };
+        let tip_time = match self.tip_timestamp {
+            Some(timestamp) => timestamp.to_string(),
+            None => "unknown".to_string(),
+        };
+        let result = match &self.mismatch {
+            None => "verified".to_string(),
+            Some(mismatch) => format!("not verified, {mismatch}"),
+        };
+        format!(
+            "Liquid payment verification\nwallet: {}\ntxid: {}\nvout: {}\naddress: {}\nasset: {}\nsatoshi: {}\nmin confirmations: {}\nheight: {height}\nconfirmations: {}\ntip height: {}\ntip hash: {}\ntip time: {tip_time}\nresult: {result}",
+            self.wallet_id,
+            self.txid,
+            self.vout,
+            self.address,
+            self.asset,
+            self.satoshi,
+            self.min_confirmations,
+            self.confirmations,
+            self.tip_height,
+            self.tip_hash,
+        )
+    }
+}
+
+/// Why a payment is not verified, see [`PaymentVerification`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentMismatch {
+    /// The transaction is not in the wallet, not synced yet or double spent
+    UnknownTransaction,
+
+    /// The transaction has no such output
+    UnknownOutput,
+
+    /// The output does not pay the address or the address is not of the wallet
+    Address,
+
+    /// The output cannot be unblinded with the wallet keys
+    NotUnblinded,
+
+    /// The output pays another asset
+    Asset(AssetId),
+
+    /// The output pays another amount
+    Amount(u64),
+
+    /// The transaction has fewer confirmations than required
+    Confirmations(u32),
+}
+
+impl std::fmt::Display for PaymentMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PaymentMismatch::UnknownTransaction => write!(f, "unknown transaction"),
+            PaymentMismatch::UnknownOutput => write!(f, "unknown output"),
+            PaymentMismatch::Address => write!(f, "the output does not pay the address"),
+            PaymentMismatch::NotUnblinded => write!(f, "the output cannot be unblinded"),
+            PaymentMismatch::Asset(asset) => write!(f, "the output pays asset {asset}"),
+            PaymentMismatch::Amount(satoshi) => write!(f, "the output pays {satoshi} satoshi"),
+            PaymentMismatch::Confirmations(confirmations) => {
+                write!(f, "the transaction has {confirmations} confirmations")
+            }
+        }
+    }
+}
+
+/// Unconfirmed wallet transactions linked to a transaction, returned from
+/// [`crate::Wollet::tx_ancestors()`] and [`crate::Wollet::tx_descendants()`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxChain {
+    txids: Vec<Txid>,
+    fee: u64,
+    vsize: usize,
+}
+
+impl TxChain {
+    pub(crate) fn push(&mut self, tx: &Transaction, policy_asset: AssetId) {
+        self.txids.push(tx.txid());
+        self.fee += tx.fee_in(policy_asset);
+        self.vsize += tx.discount_vsize();
+    }
+
+    /// The transactions of the chain, closest to the starting transaction first
+    pub fn txids(&self) -> &[Txid] {
+        &self.txids
+    }
+
+    /// The sum of the fees of the transactions of the chain
+    pub fn fee(&self) -> u64 {
+        self.fee
+    }
+
+    /// The sum of the (discounted) virtual sizes of the transactions of the chain
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    /// Whether the chain has no transactions
+    pub fn is_empty(&self) -> bool {
+        self.txids.is_empty()
+    }
+}
+
 /// Value returned from [`crate::Wollet::pegin_address()`], containing the bitcoin address
 /// and the derivation index used to derive the elements script pubkey used to create the commit for the pegin address
 #[derive(Debug, Clone)]
@@ -235,6 +516,16 @@ pub struct IssuanceDetails {
     // token_blinder
 }
 
+impl IssuanceDetails {
+    /// Whether the asset can be reissued
+    ///
+    /// An asset issued without reissuance tokens can never be reissued, for issuances with
+    /// blinded amounts this is not known and `true` is returned.
+    pub fn is_reissuable(&self) -> bool {
+        self.is_reissuance || self.token_amount != Some(0)
+    }
+}
+
 pub(crate) struct DisplayTxOutSecrets<'a>(&'a TxOutSecrets);
 impl std::fmt::Display for DisplayTxOutSecrets<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
@@ -307,6 +598,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tx_status() {
+        assert_eq!(TxStatus::new(0, 2), TxStatus::Unconfirmed);
+        assert_eq!(TxStatus::new(1, 2), TxStatus::Confirming(1));
+        assert_eq!(TxStatus::new(2, 2), TxStatus::Final);
+        assert_eq!(TxStatus::new(1, 1), TxStatus::Final);
+        assert_eq!(TxStatus::new(0, 0), TxStatus::Unconfirmed);
+        assert_eq!(TxStatus::Confirming(1).to_string(), "confirming");
+    }
+
     #[test]
     fn test_recipient() {
         let address = "el1qq2xvpcvfup5j8zscjq05u2wxxjcyewk7979f3mmz5l7uw5pqmx6xf5xy50hsn6vhkm5euwt72x878eq6zxx2z0z676mna6kdq";
@@ -1,6 +1,7 @@
 use std::{
     fmt::Display,
     fs,
+    io::Write,
     ops::Add,
     path::{Path, PathBuf},
     str::FromStr,
@@ -22,6 +23,19 @@ pub enum PersistError {
 
     #[error("{0}")]
     Other(String),
+
+    /// The persisted update at `index` cannot be read back, the updates from `index` on are
+    /// discarded when creating the wallet and must be recovered with a new scan
+    #[error("Persisted update {index} is corrupted: {reason}")]
+    Corrupted { index: usize, reason: String },
+
+    /// The persisted update at `index` is intact but cannot be decoded, for instance because it
+    /// was written by a newer version, the updates are kept and the wallet cannot be created
+    #[error("Persisted update {index} cannot be decoded: {source}")]
+    Undecodable {
+        index: usize,
+        source: elements::encode::Error,
+    },
 }
 
 /// Trait for persisting updates.
@@ -37,6 +51,16 @@ pub trait Persister {
     ///
     /// Implementors are encouraged to coalesce consequent updates with `update.only_tip() == true`
     fn push(&self, update: Update) -> Result<(), PersistError>;
+
+    /// Make sure the pushed updates are on durable storage
+    fn flush(&self) -> Result<(), PersistError> {
+        Ok(())
+    }
+
+    /// Discard the updates from position `len` on, called when an update cannot be read back
+    fn truncate(&self, _len: usize) -> Result<(), PersistError> {
+        Ok(())
+    }
 }
 
 sha256t_hash_newtype! {
@@ -79,11 +103,32 @@ struct FsPersisterInner {
 }
 
 /// A file system persister that writes encrypted incremental updates
+///
+/// Every update is written in a temporary file which is renamed once synced, so that a crash
+/// never leaves a partially written update. Updates are encrypted with an authenticated cipher,
+/// thus a corrupted file is detected when loading it and reported as [`PersistError::Corrupted`].
+/// An authentic update that cannot be decoded is reported as [`PersistError::Undecodable`].
 pub struct FsPersister {
     inner: Mutex<FsPersisterInner>,
 }
 
 impl FsPersister {
+    /// The directory where the updates of the wallet with descriptor `desc` are written
+    ///
+    /// Its name is derived from the descriptor with a one-way hash, this allows to find the cache
+    /// of a wallet, for instance to delete it once the wallet is not used anymore.
+    pub fn cache_dir<P: AsRef<Path>>(
+        path: P,
+        network: ElementsNetwork,
+        desc: &WolletDescriptor,
+    ) -> PathBuf {
+        let mut path = path.as_ref().to_path_buf();
+        path.push(network.as_str());
+        path.push("enc_cache");
+        path.push(DirectoryIdHash::hash(desc.to_string().as_bytes()).to_string());
+        path
+    }
+
     /// Creates a persister of updates. While being written they are encrypted using a key derived
     /// from the given descriptor.
     /// From the given path create a network subdirectory with
@@ -93,10 +138,7 @@ impl FsPersister {
         network: ElementsNetwork,
         desc: &WolletDescriptor,
     ) -> Result<Arc<Self>, Error> {
-        let mut path = path.as_ref().to_path_buf();
-        path.push(network.as_str());
-        path.push("enc_cache");
-        path.push(DirectoryIdHash::hash(desc.to_string().as_bytes()).to_string());
+        let path = Self::cache_dir(path, network, desc);
         if path.is_file() {
             return Err(Error::Generic("given path is a file".to_string()));
         }
@@ -109,6 +151,11 @@ impl FsPersister {
             if entry.path().is_file() {
                 let file_name = entry.file_name();
                 let name = file_name.to_str();
+                if name.map_or(false, |n| n.ends_with(TMP_EXTENSION)) {
+                    // Leftover of an interrupted write
+                    fs::remove_file(entry.path())?;
+                    continue;
+                }
                 if let Some(name) = name {
                     let counter: Counter = name.parse()?;
                     next = next.max(counter + 1);
@@ -146,10 +193,17 @@ impl FsPersisterInner {
             let path = self.path(&Counter::from(index));
             let bytes = fs::read(path)?;
 
-            Ok(Some(
-                Update::deserialize_decrypted(&bytes, &self.desc)
-                    .map_err(|e| PersistError::Other(e.to_string()))?,
-            ))
+            // Only a file that cannot be authenticated is corrupted, a file that is authentic but
+            // cannot be decoded must not be discarded
+            let plaintext = crate::update::decrypt(&bytes, &self.desc).map_err(|e| {
+                PersistError::Corrupted {
+                    index,
+                    reason: e.to_string(),
+                }
+            })?;
+            let update = Update::deserialize(&plaintext)
+                .map_err(|source| PersistError::Undecodable { index, source })?;
+            Ok(Some(update))
         } else {
             Ok(None)
         }
@@ -184,12 +238,38 @@ impl Persister for FsPersister {
             .serialize_encrypted(&inner.desc)
             .map_err(|e| PersistError::Other(e.to_string()))?;
 
-        fs::write(path, ciphertext)?;
+        let tmp_path = path.with_extension(TMP_EXTENSION);
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&ciphertext)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, path)?;
         inner.next = inner.next.clone() + 1;
         Ok(())
     }
+
+    fn flush(&self) -> Result<(), PersistError> {
+        let _inner = self.inner.lock().map_err(to_other)?;
+        // Files are synced when written, sync the directory so that the renames are durable
+        #[cfg(unix)]
+        fs::File::open(&_inner.path)?.sync_all()?;
+        Ok(())
+    }
+
+    fn truncate(&self, len: usize) -> Result<(), PersistError> {
+        let mut inner = self.inner.lock().map_err(to_other)?;
+        for index in len..inner.next.0 {
+            let path = inner.path(&Counter::from(index));
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        inner.next = inner.next.clone().min(Counter::from(len));
+        Ok(())
+    }
 }
 
+const TMP_EXTENSION: &str = "tmp";
+
 const PERSISTED_FILE_NAME_LENGTH: usize = 12;
 
 /// Encapsulate an usize so that its to/from string representation are coherent
@@ -242,9 +322,11 @@ mod test {
         sync::{Arc, Mutex},
     };
 
-    use crate::{ElementsNetwork, FsPersister, PersistError, Update, WolletDescriptor};
+    use crate::blocking::MockBackend;
+    use crate::update::encrypt;
+    use crate::{ElementsNetwork, FsPersister, PersistError, Update, Wollet, WolletDescriptor};
 
-    use super::{Counter, NoPersist, Persister};
+    use super::{Counter, NoPersist, Persister, TMP_EXTENSION};
 
     struct MemoryPersister(Mutex<Vec<Update>>);
     impl MemoryPersister {
@@ -315,10 +397,91 @@ mod test {
         let n = ElementsNetwork::LiquidTestnet;
         let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
         inner_test_persister(persister, true);
+        let cache_dir = FsPersister::cache_dir(&tempdir, n, &desc);
+        assert!(cache_dir.starts_with(tempdir.path().join("liquid-testnet/enc_cache")));
+        assert_eq!(cache_dir.read_dir().unwrap().count(), 2);
         let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
         inner_test_persister(persister, false);
     }
 
+    #[test]
+    fn test_corrupted_fs_persister() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let network = ElementsNetwork::default_regtest();
+        let mut client = MockBackend::new(network);
+
+        let mut wollet = Wollet::with_fs_persist(network, desc.clone(), &tempdir).unwrap();
+        let address = wollet.address(None).unwrap();
+        for _ in 0..2 {
+            client.fund(address.address(), 1_000, None).unwrap();
+            client.sync(&mut wollet).unwrap();
+        }
+        wollet.flush().unwrap();
+        assert_eq!(wollet.updates().unwrap().len(), 2);
+        let balance = wollet.balance().unwrap()[&network.policy_asset()];
+        assert_eq!(balance, 2_000);
+        drop(wollet);
+
+        // Corrupt the last update and leave an interrupted write
+        let persister = FsPersister::new(&tempdir, network, &desc).unwrap();
+        let path = persister.inner.lock().unwrap().path(&Counter::from(1));
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        std::fs::write(path.with_extension(TMP_EXTENSION), b"partial").unwrap();
+        assert!(matches!(
+            persister.get(1).unwrap_err(),
+            PersistError::Corrupted { index: 1, .. }
+        ));
+        drop(persister);
+
+        // The wallet loads what is valid and recovers the rest with a scan
+        let mut wollet = Wollet::with_fs_persist(network, desc, &tempdir).unwrap();
+        assert_eq!(wollet.updates().unwrap().len(), 1);
+        assert_eq!(wollet.balance().unwrap()[&network.policy_asset()], 1_000);
+        client.sync(&mut wollet).unwrap();
+        assert_eq!(wollet.balance().unwrap()[&network.policy_asset()], 2_000);
+        assert_eq!(wollet.updates().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_undecodable_fs_persister() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let network = ElementsNetwork::default_regtest();
+        let mut client = MockBackend::new(network);
+
+        let mut wollet = Wollet::with_fs_persist(network, desc.clone(), &tempdir).unwrap();
+        let address = wollet.address(None).unwrap();
+        for _ in 0..2 {
+            client.fund(address.address(), 1_000, None).unwrap();
+            client.sync(&mut wollet).unwrap();
+        }
+        wollet.flush().unwrap();
+        drop(wollet);
+
+        // The last update is authentic but of a newer version
+        let persister = FsPersister::new(&tempdir, network, &desc).unwrap();
+        let mut plaintext = persister.get(1).unwrap().unwrap().serialize().unwrap();
+        plaintext[4] = u8::MAX;
+        let path = persister.inner.lock().unwrap().path(&Counter::from(1));
+        std::fs::write(&path, encrypt(plaintext, &desc).unwrap()).unwrap();
+        assert!(matches!(
+            persister.get(1).unwrap_err(),
+            PersistError::Undecodable { index: 1, .. }
+        ));
+        drop(persister);
+
+        // The wallet is not created and the updates are kept
+        let err = Wollet::with_fs_persist(network, desc.clone(), &tempdir).unwrap_err();
+        assert!(err.to_string().contains("cannot be decoded"), "{err}");
+        assert!(path.exists());
+        let persister = FsPersister::new(&tempdir, network, &desc).unwrap();
+        assert!(persister.get(0).unwrap().is_some());
+    }
+
     #[test]
     fn test_counter() {
         let c = Counter::default();
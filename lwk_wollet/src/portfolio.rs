@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::thread;
+
+use elements::AssetId;
+
+use crate::clients::blocking::BlockchainBackend;
+use crate::{ElementsNetwork, Error, SharedWollet, WalletTx};
+
+/// A set of named wallets, possibly of different networks, watched together
+///
+/// For instance the cold and hot wallets of a treasury. Wallets are held as [`SharedWollet`],
+/// so they can be synced concurrently with [`Portfolio::full_scan()`] while the aggregated
+/// balance and transactions are read.
+#[derive(Clone, Default)]
+pub struct Portfolio {
+    wollets: BTreeMap<String, SharedWollet>,
+}
+
+/// A transaction of a wallet in a [`Portfolio`]
+#[derive(Debug, Clone)]
+pub struct PortfolioTx {
+    /// The name of the wallet in the portfolio
+    pub name: String,
+
+    /// The transaction from the point of view of the wallet
+    pub tx: WalletTx,
+}
+
+impl Portfolio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a wallet with the given `name`, returning the wallet previously having that name
+    pub fn insert(&mut self, name: &str, wollet: impl Into<SharedWollet>) -> Option<SharedWollet> {
+        self.wollets.insert(name.to_string(), wollet.into())
+    }
+
+    /// Remove the wallet with the given `name`
+    pub fn remove(&mut self, name: &str) -> Option<SharedWollet> {
+        self.wollets.remove(name)
+    }
+
+    /// Get the wallet with the given `name`
+    pub fn get(&self, name: &str) -> Option<&SharedWollet> {
+        self.wollets.get(name)
+    }
+
+    /// The names of the wallets, sorted
+    pub fn names(&self) -> Vec<&str> {
+        self.wollets.keys().map(|n| n.as_str()).collect()
+    }
+
+    /// The sum of the wallets balances per asset
+    ///
+    /// Asset ids differ across networks, so the balances of wallets of different networks are
+    /// never merged together.
+    pub fn balance(&self) -> Result<BTreeMap<AssetId, u64>, Error> {
+        let mut total = BTreeMap::new();
+        for wollet in self.wollets.values() {
+            for (asset, satoshi) in wollet.read()?.balance()? {
+                *total.entry(asset).or_default() += satoshi;
+            }
+        }
+        Ok(total)
+    }
+
+    /// The transactions of all the wallets, unconfirmed first, then the most recent first
+    ///
+    /// Transactions are ordered by block timestamp, since heights of different networks are not
+    /// comparable. A transaction involving several wallets of the portfolio, for instance a
+    /// transfer from the hot to the cold wallet, appears once per wallet, each with its own
+    /// balance.
+    pub fn transactions(&self) -> Result<Vec<PortfolioTx>, Error> {
+        let mut txs = vec![];
+        for (name, wollet) in self.wollets.iter() {
+            for tx in wollet.read()?.transactions()? {
+                txs.push(PortfolioTx {
+                    name: name.clone(),
+                    tx,
+                });
+            }
+        }
+        txs.sort_by_key(|t| {
+            let unconfirmed = t.tx.height.is_none();
+            std::cmp::Reverse((unconfirmed, t.tx.timestamp, t.tx.height))
+        });
+        Ok(txs)
+    }
+
+    /// Scan all the wallets concurrently, one thread per wallet
+    ///
+    /// Each thread creates its own client with `new_client`, given the network of the wallet.
+    /// Returns for each wallet whether it changed or the error encountered, the failure of a
+    /// wallet does not prevent the others from being synced.
+    pub fn full_scan<B, F>(&self, new_client: F) -> BTreeMap<String, Result<bool, Error>>
+    where
+        B: BlockchainBackend,
+        F: Fn(ElementsNetwork) -> Result<B, Error> + Sync,
+    {
+        let new_client = &new_client;
+        thread::scope(|s| {
+            let handles: Vec<_> = self
+                .wollets
+                .iter()
+                .map(|(name, wollet)| {
+                    let handle = s.spawn(move || {
+                        let network = wollet.read()?.network();
+                        wollet.full_scan(&mut new_client(network)?)
+                    });
+                    (name.clone(), handle)
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|(name, handle)| {
+                    let result = handle
+                        .join()
+                        .unwrap_or_else(|_| Err(Error::Generic("scan thread panicked".into())));
+                    (name, result)
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::blocking::MockBackend;
+    use crate::{Wollet, WolletDescriptor};
+
+    #[test]
+    fn test_portfolio() {
+        let network = ElementsNetwork::default_regtest();
+        let policy_asset = network.policy_asset();
+        let mut client = MockBackend::new(network);
+        let mut portfolio = Portfolio::new();
+        let descs = [
+            lwk_test_util::TEST_DESCRIPTOR,
+            "ct(slip77(9c8e4f05c7711a98c838be228bcb84924d4570ca53f35fa1c793e58841d47023),elwpkh([73c5da0a/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/<0;1>/*))",
+        ];
+        for (name, desc) in ["hot", "cold"].iter().zip(descs) {
+            let desc = WolletDescriptor::from_str(desc).unwrap();
+            let wollet = Wollet::without_persist(network, desc).unwrap();
+            let address = wollet.address(None).unwrap();
+            client.fund(address.address(), 1_000, None).unwrap();
+            client.mine(1);
+            assert!(portfolio.insert(name, wollet).is_none());
+        }
+        assert_eq!(portfolio.names(), vec!["cold", "hot"]);
+
+        let results = portfolio.full_scan(|_| Ok(client.clone()));
+        assert_eq!(results.len(), 2);
+        assert!(results.values().all(|r| matches!(r, Ok(true))));
+        assert_eq!(portfolio.balance().unwrap()[&policy_asset], 2_000);
+
+        let hot = portfolio.get("hot").unwrap().read().unwrap();
+        client
+            .fund(hot.address(Some(1)).unwrap().address(), 500, None)
+            .unwrap();
+        drop(hot);
+        let results = portfolio.full_scan(|_| Ok(client.clone()));
+        assert!(matches!(results["hot"], Ok(true)));
+        assert_eq!(portfolio.balance().unwrap()[&policy_asset], 2_500);
+
+        let txs = portfolio.transactions().unwrap();
+        let names: Vec<_> = txs.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["hot", "cold", "hot"]);
+        assert!(txs[0].tx.height.is_none());
+
+        let results = portfolio
+            .full_scan(|_| -> Result<MockBackend, _> { Err(Error::Generic("offline".into())) });
+        assert!(results.values().all(|r| r.is_err()));
+
+        assert!(portfolio.remove("cold").is_some());
+        assert_eq!(portfolio.balance().unwrap()[&policy_asset], 1_500);
+    }
+}
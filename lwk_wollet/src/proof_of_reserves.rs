@@ -0,0 +1,246 @@
+use crate::elements::confidential::{Asset, Nonce, Value};
+use crate::elements::opcodes::all::OP_PUSHNUM_1;
+use crate::elements::pset::{Input, Output, PartiallySignedTransaction};
+use crate::elements::script::Builder;
+use crate::elements::{AssetId, EcdsaSighashType, OutPoint, Script, TxOut, Txid};
+use crate::hashes::{sha256d, Hash};
+use crate::util::EC;
+use crate::wollet::Wollet;
+use crate::Error;
+use elements::{BlindAssetProofs, BlindValueProofs};
+use elements_miniscript::interpreter::{KeySigPair, SatisfiedConstraint};
+use elements_miniscript::{Interpreter, TxEnv};
+use lwk_common::burn_script;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const CHALLENGE_TAG: &str = "Liquid Proof of Reserves: ";
+
+/// The non-existent outpoint spent by the first input of a proof of reserves, committing to the
+/// `message`
+pub fn proof_of_reserves_challenge(message: &str) -> OutPoint {
+    let hash = sha256d::Hash::hash(format!("{CHALLENGE_TAG}{message}").as_bytes());
+    OutPoint::new(Txid::from_raw_hash(hash), 0)
+}
+
+fn challenge_txout() -> TxOut {
+    TxOut {
+        asset: Asset::Null,
+        value: Value::Explicit(0),
+        nonce: Nonce::Null,
+        script_pubkey: Builder::new().push_opcode(OP_PUSHNUM_1).into_script(),
+        witness: Default::default(),
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidProofOfReserves(reason.to_string())
+}
+
+impl Wollet {
+    /// Create a proof of reserves over all the wallet UTXOs committing to `message`
+    ///
+    /// The returned PSET spends a non-existent challenge input (see
+    /// [`proof_of_reserves_challenge()`]) and all the wallet UTXOs, with the unblinded values
+    /// and blind proofs of every input, so that amounts can be verified without the blinding key.
+    /// Outputs are unspendable and explicit, one for every asset.
+    ///
+    /// Since the challenge input does not exist, the transaction can never be broadcast.
+    ///
+    /// The PSET must be signed and then finalized with [`Wollet::finalize()`], the challenge
+    /// input will be left unfinalized. The result can be verified with
+    /// [`verify_proof_of_reserves()`].
+    pub fn create_proof_of_reserves(
+        &self,
+        message: &str,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        let mut pset = PartiallySignedTransaction::new_v2();
+        let mut input = Input::from_prevout(proof_of_reserves_challenge(message));
+        input.witness_utxo = Some(challenge_txout());
+        pset.add_input(input);
+
+        let mut inp_txout_sec = HashMap::new();
+        let mut inp_weight = 0;
+        let mut balances: BTreeMap<AssetId, u64> = BTreeMap::new();
+        for utxo in self.utxos()? {
+            self.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+            *balances.entry(utxo.unblinded.asset).or_default() += utxo.unblinded.value;
+        }
+
+        for (asset, satoshi) in balances {
+            pset.add_output(Output {
+                script_pubkey: burn_script(),
+                amount: Some(satoshi),
+                asset: Some(asset),
+                ..Default::default()
+            });
+        }
+
+        self.add_details(&mut pset)?;
+        Ok(pset)
+    }
+}
+
+/// Verify a proof of reserves created with [`Wollet::create_proof_of_reserves()`] for `message`
+///
+/// Returns the amount proven for every asset.
+///
+/// Checks that the first input is the challenge committing to `message`, that every other input
+/// has valid signatures committing to the whole transaction, and that its unblinded asset and
+/// value match the commitments.
+///
+/// Note: this does not check that the inputs are unspent, the verifier should check this against
+/// the blockchain.
+pub fn verify_proof_of_reserves(
+    pset: &PartiallySignedTransaction,
+    message: &str,
+) -> Result<BTreeMap<AssetId, u64>, Error> {
+    let inputs = pset.inputs();
+    let challenge = inputs.first().ok_or_else(|| invalid("missing inputs"))?;
+    let challenge_outpoint =
+        OutPoint::new(challenge.previous_txid, challenge.previous_output_index);
+    if challenge_outpoint != proof_of_reserves_challenge(message) {
+        return Err(invalid("first input is not the challenge for the message"));
+    }
+
+    let tx = pset.extract_tx()?;
+    let prevouts = inputs
+        .iter()
+        .map(|i| i.witness_utxo.clone())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| invalid("missing witness utxo"))?;
+
+    let mut outpoints = HashSet::new();
+    let mut balances: BTreeMap<AssetId, u64> = BTreeMap::new();
+    for (idx, input) in inputs.iter().enumerate().skip(1) {
+        if !outpoints.insert(OutPoint::new(
+            input.previous_txid,
+            input.previous_output_index,
+        )) {
+            return Err(invalid("duplicated input"));
+        }
+        let txout = &prevouts[idx];
+        let (asset, value) = match (input.asset, input.amount) {
+            (Some(asset), Some(value)) => (asset, value),
+            _ => return Err(invalid("missing unblinded asset or value")),
+        };
+        match (txout.asset, txout.value) {
+            (Asset::Explicit(a), Value::Explicit(v)) if a == asset && v == value => {}
+            (Asset::Confidential(asset_comm), Value::Confidential(value_comm)) => {
+                let asset_proof = input
+                    .blind_asset_proof
+                    .as_ref()
+                    .ok_or_else(|| invalid("missing blind asset proof"))?;
+                let value_proof = input
+                    .blind_value_proof
+                    .as_ref()
+                    .ok_or_else(|| invalid("missing blind value proof"))?;
+                if !asset_proof.blind_asset_proof_verify(&EC, asset, asset_comm)
+                    || !value_proof.blind_value_proof_verify(&EC, value, asset_comm, value_comm)
+                {
+                    return Err(invalid("invalid blind proofs"));
+                }
+            }
+            _ => return Err(invalid("unblinded values do not match the commitments")),
+        }
+
+        let empty_script_sig = Script::new();
+        let script_sig = input.final_script_sig.as_ref().unwrap_or(&empty_script_sig);
+        let witness = input
+            .final_script_witness
+            .as_ref()
+            .ok_or_else(|| invalid("input not finalized"))?;
+        let interpreter = Interpreter::from_txdata(
+            &txout.script_pubkey,
+            script_sig,
+            witness,
+            tx.input[idx].sequence,
+            tx.lock_time,
+        )
+        .map_err(|e| Error::InvalidProofOfReserves(e.to_string()))?;
+        let env = TxEnv::new(&tx, &prevouts, idx).ok_or_else(|| invalid("missing utxo"))?;
+        // genesis_hash is only used for BIP341 (taproot) sighash computation
+        let mut signatures = 0;
+        for constraint in interpreter.iter(&EC, &env, elements::BlockHash::all_zeros()) {
+            let key_sig =
+                match constraint.map_err(|e| Error::InvalidProofOfReserves(e.to_string()))? {
+                    SatisfiedConstraint::PublicKey { key_sig } => key_sig,
+                    SatisfiedConstraint::PublicKeyHash { key_sig, .. } => key_sig,
+                    _ => continue,
+                };
+            match key_sig {
+                KeySigPair::Ecdsa(_, (_, EcdsaSighashType::All)) => signatures += 1,
+                _ => return Err(invalid("signatures must use SIGHASH_ALL")),
+            }
+        }
+        if signatures == 0 {
+            return Err(invalid("input without signatures"));
+        }
+
+        *balances.entry(asset).or_default() += value;
+    }
+
+    let mut outputs: BTreeMap<AssetId, u64> = BTreeMap::new();
+    for output in pset.outputs() {
+        if !output.script_pubkey.is_provably_unspendable() {
+            return Err(invalid("spendable output"));
+        }
+        match (output.asset, output.amount) {
+            (Some(asset), Some(value)) => *outputs.entry(asset).or_default() += value,
+            _ => return Err(invalid("output without explicit asset or value")),
+        }
+    }
+    if outputs != balances {
+        return Err(invalid("outputs do not match inputs"));
+    }
+
+    Ok(balances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::test_wollet_with_many_transactions;
+
+    #[test]
+    fn test_proof_of_reserves_unsigned() {
+        let wollet = test_wollet_with_many_transactions();
+        let message = "proof of reserves";
+        let pset = wollet.create_proof_of_reserves(message).unwrap();
+
+        let utxos = wollet.utxos().unwrap();
+        assert_eq!(pset.inputs().len(), utxos.len() + 1);
+        let balance = wollet.balance().unwrap();
+        assert_eq!(pset.outputs().len(), balance.len());
+        for output in pset.outputs() {
+            let asset = output.asset.unwrap();
+            assert_eq!(output.amount.unwrap(), balance[&asset]);
+        }
+
+        // signers must be able to compute the sighash of every input, including the challenge
+        // the signer is not part of the wallet, so it's refused unless some input claims its key
+        let signer = lwk_signer::SwSigner::new(lwk_test_util::TEST_MNEMONIC, false).unwrap();
+        let mut signed = pset.clone();
+        assert!(lwk_common::Signer::sign(&signer, &mut signed).is_err());
+        let (pk, (_, path)) = signed.inputs()[1]
+            .bip32_derivation
+            .iter()
+            .next()
+            .map(|(pk, origin)| (*pk, origin.clone()))
+            .unwrap();
+        signed.inputs_mut()[1]
+            .bip32_derivation
+            .insert(pk, (signer.fingerprint(), path));
+        assert_eq!(lwk_common::Signer::sign(&signer, &mut signed).unwrap(), 0);
+
+        let err = verify_proof_of_reserves(&pset, "another message").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid proof of reserves: first input is not the challenge for the message"
+        );
+        let err = verify_proof_of_reserves(&pset, message).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid proof of reserves: input not finalized"
+        );
+    }
+}
@@ -13,7 +13,7 @@ use elements::pset::elip100::AssetMetadata;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 // We make issuance and reissuance are mutually exclusive for simplicity
 pub enum IssuanceRequest {
     None,
@@ -22,15 +22,7 @@ pub enum IssuanceRequest {
 }
 
 impl Wollet {
-    pub(crate) fn asset_utxos(&self, asset: &AssetId) -> Result<Vec<WalletTxOut>, Error> {
-        Ok(self
-            .utxos()?
-            .into_iter()
-            .filter(|utxo| &utxo.unblinded.asset == asset)
-            .collect())
-    }
-
-    fn get_tx(&self, txid: &Txid) -> Result<Transaction, Error> {
+    pub(crate) fn get_tx(&self, txid: &Txid) -> Result<Transaction, Error> {
         Ok(self
             .store
             .cache
@@ -40,7 +32,7 @@ impl Wollet {
             .clone())
     }
 
-    fn get_txout(&self, outpoint: &OutPoint) -> Result<TxOut, Error> {
+    pub(crate) fn get_txout(&self, outpoint: &OutPoint) -> Result<TxOut, Error> {
         Ok(self
             .get_tx(&outpoint.txid)?
             .output
@@ -207,17 +199,23 @@ fn convert_pubkey(pk: crate::elements::secp256k1_zkp::PublicKey) -> BitcoinPubli
 }
 
 pub(crate) fn validate_address(address: &str, network: ElementsNetwork) -> Result<Address, Error> {
-    let params = network.address_params();
-    let address = Address::parse_with_params(address, params)?;
+    let address = parse_address(address, network)?;
     if address.blinding_pubkey.is_none() {
         return Err(Error::NotConfidentialAddress);
     };
     Ok(address)
 }
 
+/// Parse an address for the given network, without checking it's confidential
+pub(crate) fn parse_address(address: &str, network: ElementsNetwork) -> Result<Address, Error> {
+    let params = network.address_params();
+    Ok(Address::parse_with_params(address, params)?)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{pset_create::validate_address, ElementsNetwork};
+    use crate::pset_create::{parse_address, validate_address};
+    use crate::ElementsNetwork;
 
     #[test]
     fn test_validate() {
@@ -229,4 +227,21 @@ mod test {
         let network = ElementsNetwork::Liquid;
         assert!(validate_address(testnet_address, network).is_err())
     }
+
+    #[test]
+    fn test_validate_unconfidential() {
+        let network = ElementsNetwork::LiquidTestnet;
+        let testnet_address = "tlq1qq2xvpcvfup5j8zscjq05u2wxxjcyewk7979f3mmz5l7uw5pqmx6xf5xy50hsn6vhkm5euwt72x878eq6zxx2z58hd7zrsg9qn";
+        let mut addr = validate_address(testnet_address, network).unwrap();
+        addr.blinding_pubkey = None;
+        let unconfidential = addr.to_string();
+
+        let err = validate_address(&unconfidential, network).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            crate::Error::NotConfidentialAddress.to_string()
+        );
+        let addr = parse_address(&unconfidential, network).unwrap();
+        assert!(addr.blinding_pubkey.is_none());
+    }
 }
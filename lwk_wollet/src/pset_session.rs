@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::bitcoin::bip32::Fingerprint;
+use crate::elements::pset::PartiallySignedTransaction;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// The state of a [`PsetSession`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PsetSessionState {
+    /// Some inputs have less signatures than the threshold
+    WaitingSignatures,
+
+    /// All inputs have enough signatures, the PSET can be finalized
+    ReadyToFinalize,
+
+    /// All inputs are finalized
+    Finalized,
+}
+
+impl std::fmt::Display for PsetSessionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            PsetSessionState::WaitingSignatures => "waiting_signatures",
+            PsetSessionState::ReadyToFinalize => "ready_to_finalize",
+            PsetSessionState::Finalized => "finalized",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A PSET being signed by the cosigners of a multisig wallet
+///
+/// The session is meant to be serialized in JSON and exchanged as a single file between the
+/// cosigners: each one adds its signatures with [`PsetSession::add_pset()`], and sessions
+/// signed in parallel can be joined with [`PsetSession::merge()`].
+///
+/// The signatures collected and the state are derived from the PSET, they are serialized for
+/// convenience but recomputed when the session is parsed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PsetSession {
+    version: u32,
+
+    #[serde(with = "pset_base64")]
+    pset: PartiallySignedTransaction,
+
+    participants: BTreeSet<Fingerprint>,
+
+    threshold: usize,
+
+    #[serde(default)]
+    signatures: BTreeMap<Fingerprint, BTreeSet<usize>>,
+
+    state: PsetSessionState,
+}
+
+impl PsetSession {
+    /// The latest version of the session format
+    pub const VERSION: u32 = 1;
+
+    /// Create a session for `pset` which needs `threshold` signatures per input from the
+    /// `participants`
+    pub fn new(
+        pset: PartiallySignedTransaction,
+        participants: BTreeSet<Fingerprint>,
+        threshold: usize,
+    ) -> Result<Self, Error> {
+        if threshold == 0 || threshold > participants.len() {
+            return Err(Error::InvalidPsetSessionThreshold {
+                threshold,
+                participants: participants.len(),
+            });
+        }
+        let mut session = PsetSession {
+            version: Self::VERSION,
+            pset,
+            participants,
+            threshold,
+            signatures: BTreeMap::new(),
+            state: PsetSessionState::WaitingSignatures,
+        };
+        session.refresh();
+        Ok(session)
+    }
+
+    /// The PSET with all the signatures collected so far
+    pub fn pset(&self) -> &PartiallySignedTransaction {
+        &self.pset
+    }
+
+    /// The fingerprints of the cosigners
+    pub fn participants(&self) -> &BTreeSet<Fingerprint> {
+        &self.participants
+    }
+
+    /// The number of signatures needed for each input
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// For each cosigner that signed, the indexes of the inputs it signed
+    pub fn signatures(&self) -> &BTreeMap<Fingerprint, BTreeSet<usize>> {
+        &self.signatures
+    }
+
+    /// The cosigners that have not signed yet
+    pub fn missing(&self) -> BTreeSet<Fingerprint> {
+        self.participants
+            .iter()
+            .filter(|f| !self.signatures.contains_key(*f))
+            .cloned()
+            .collect()
+    }
+
+    /// The state of the session
+    pub fn state(&self) -> PsetSessionState {
+        self.state
+    }
+
+    /// Add the signatures (and any other data) contained in `pset`
+    ///
+    /// Fails if `pset` is not a version of the PSET of this session.
+    pub fn add_pset(&mut self, pset: PartiallySignedTransaction) -> Result<(), Error> {
+        self.pset.merge(pset)?;
+        self.refresh();
+        Ok(())
+    }
+
+    /// Join another session for the same PSET
+    pub fn merge(&mut self, other: PsetSession) -> Result<(), Error> {
+        if self.threshold != other.threshold {
+            return Err(Error::Generic(format!(
+                "Cannot merge sessions with different thresholds: {} and {}",
+                self.threshold, other.threshold
+            )));
+        }
+        self.pset.merge(other.pset)?;
+        self.participants.extend(other.participants);
+        self.refresh();
+        Ok(())
+    }
+
+    fn refresh(&mut self) {
+        let mut signatures: BTreeMap<Fingerprint, BTreeSet<usize>> = BTreeMap::new();
+        let mut ready = true;
+        let mut finalized = true;
+        for (idx, input) in self.pset.inputs().iter().enumerate() {
+            let mut count = 0;
+            for (pk, (fingerprint, _)) in input.bip32_derivation.iter() {
+                if input.partial_sigs.contains_key(pk) {
+                    signatures.entry(*fingerprint).or_default().insert(idx);
+                    if self.participants.contains(fingerprint) {
+                        count += 1;
+                    }
+                }
+            }
+            let is_finalized = input.final_script_witness.is_some();
+            finalized &= is_finalized;
+            ready &= is_finalized || count >= self.threshold;
+        }
+        self.signatures = signatures;
+        self.state = if finalized {
+            PsetSessionState::Finalized
+        } else if ready {
+            PsetSessionState::ReadyToFinalize
+        } else {
+            PsetSessionState::WaitingSignatures
+        };
+    }
+}
+
+impl std::str::FromStr for PsetSession {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut session: PsetSession = serde_json::from_str(s)?;
+        if session.version > Self::VERSION {
+            return Err(Error::UnsupportedPsetSessionVersion(session.version));
+        }
+        session.refresh();
+        Ok(session)
+    }
+}
+
+impl std::fmt::Display for PsetSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", s)
+    }
+}
+
+pub(crate) mod pset_base64 {
+    use std::str::FromStr;
+
+    use crate::elements::pset::PartiallySignedTransaction;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        pset: &PartiallySignedTransaction,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&pset.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PartiallySignedTransaction, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        PartiallySignedTransaction::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::bip32::DerivationPath;
+    use crate::bitcoin::secp256k1::SecretKey;
+    use crate::bitcoin::PublicKey;
+    use crate::elements::{pset::Input, OutPoint, Txid};
+    use crate::hashes::Hash;
+    use crate::EC;
+
+    fn fingerprint(s: &str) -> Fingerprint {
+        s.parse().unwrap()
+    }
+
+    fn public_key(i: u8) -> PublicKey {
+        let sk = SecretKey::from_slice(&[i; 32]).unwrap();
+        PublicKey::new(sk.public_key(&EC))
+    }
+
+    /// A PSET with 2 inputs, each one with keys from 2 cosigners
+    fn multisig_pset(fingerprints: &[Fingerprint]) -> PartiallySignedTransaction {
+        let mut pset = PartiallySignedTransaction::new_v2();
+        for vout in 0..2 {
+            let outpoint = OutPoint::new(Txid::all_zeros(), vout);
+            let mut input = Input::from_prevout(outpoint);
+            for (i, fingerprint) in fingerprints.iter().enumerate() {
+                let path: DerivationPath = format!("m/0/{vout}").parse().unwrap();
+                let pk = public_key(vout as u8 * 10 + i as u8 + 1);
+                input.bip32_derivation.insert(pk, (*fingerprint, path));
+            }
+            pset.add_input(input);
+        }
+        pset
+    }
+
+    fn sign(pset: &mut PartiallySignedTransaction, fingerprint: Fingerprint) {
+        for input in pset.inputs_mut() {
+            let pk = input
+                .bip32_derivation
+                .iter()
+                .find(|(_, (f, _))| *f == fingerprint)
+                .map(|(pk, _)| *pk)
+                .unwrap();
+            input.partial_sigs.insert(pk, vec![1u8; 71]);
+        }
+    }
+
+    #[test]
+    fn test_pset_session() {
+        let (a, b) = (fingerprint("aaaaaaaa"), fingerprint("bbbbbbbb"));
+        let pset = multisig_pset(&[a, b]);
+        let participants: BTreeSet<_> = [a, b].into();
+
+        let err = PsetSession::new(pset.clone(), participants.clone(), 3).unwrap_err();
+        assert!(matches!(err, Error::InvalidPsetSessionThreshold { .. }));
+
+        let session = PsetSession::new(pset.clone(), participants.clone(), 2).unwrap();
+        assert_eq!(session.state(), PsetSessionState::WaitingSignatures);
+        assert_eq!(session.missing(), participants);
+
+        // Cosigners sign in parallel
+        let mut session_a = session.clone();
+        let mut pset_a = pset.clone();
+        sign(&mut pset_a, a);
+        session_a.add_pset(pset_a).unwrap();
+        assert_eq!(session_a.state(), PsetSessionState::WaitingSignatures);
+        assert_eq!(session_a.missing(), [b].into());
+
+        let mut session_b: PsetSession = session.to_string().parse().unwrap();
+        let mut pset_b = pset.clone();
+        sign(&mut pset_b, b);
+        session_b.add_pset(pset_b).unwrap();
+
+        // and the sessions are merged
+        session_a.merge(session_b).unwrap();
+        assert_eq!(session_a.state(), PsetSessionState::ReadyToFinalize);
+        assert!(session_a.missing().is_empty());
+        assert_eq!(session_a.signatures()[&a], [0, 1].into());
+        assert_eq!(session_a.signatures()[&b], [0, 1].into());
+
+        let roundtrip: PsetSession = session_a.to_string().parse().unwrap();
+        assert_eq!(roundtrip, session_a);
+
+        // Sessions for different PSETs or thresholds cannot be merged
+        let other = PsetSession::new(pset.clone(), [a].into(), 1).unwrap();
+        assert!(session_a.clone().merge(other).is_err());
+        let mut other_pset = pset;
+        other_pset.add_input(Input::from_prevout(OutPoint::new(Txid::all_zeros(), 5)));
+        let c = fingerprint("cccccccc");
+        let other = PsetSession::new(other_pset, [a, b, c].into(), 2).unwrap();
+        let before = session_a.clone();
+        assert!(session_a.merge(other).is_err());
+        // a failed merge leaves the session unchanged
+        assert_eq!(session_a, before);
+    }
+}
@@ -1,13 +1,16 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
 
 use crate::domain::verify_domain_name;
 use crate::elements::hashes::{sha256, Hash};
+use crate::elements::pset::PartiallySignedTransaction;
 use crate::elements::{AssetId, ContractHash, OutPoint};
 use crate::error::Error;
 use crate::util::{serde_from_hex, serde_to_hex, verify_pubkey};
 use crate::ElementsNetwork;
-use elements::{Transaction, Txid};
+use elements::Txid;
+use lwk_common::precision::PrecisionAmount;
 use once_cell::sync::Lazy;
 use regex_lite::Regex;
 use serde::{Deserialize, Serialize};
@@ -86,6 +89,14 @@ impl Contract {
         let value = serde_json::to_value(self)?;
         contract_json_hash(&value)
     }
+
+    /// The proof linking the contract domain to `asset_id`, see [`DomainProof`]
+    pub fn domain_proof(&self, asset_id: AssetId) -> DomainProof {
+        DomainProof {
+            domain: self.entity.domain().to_string(),
+            asset_id,
+        }
+    }
 }
 
 impl FromStr for Contract {
@@ -99,12 +110,14 @@ impl FromStr for Contract {
     }
 }
 
+#[cfg(feature = "esplora")]
 pub struct Registry {
     client: reqwest::Client,
     base_url: String,
+    known_assets: KnownAssets,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, Debug)]
 pub struct RegistryPost {
     contract: Contract,
     asset_id: AssetId,
@@ -124,19 +137,128 @@ impl RegistryPost {
     pub fn new(contract: Contract, asset_id: AssetId) -> Self {
         Self { contract, asset_id }
     }
+
+    /// Prepare the registration of the asset issued in `pset` with `contract`
+    ///
+    /// The PSET is the one returned by [`crate::TxBuilder::issue_asset()`], it must contain the
+    /// issuance committing to `contract`.
+    pub fn from_issuance(
+        pset: &PartiallySignedTransaction,
+        contract: Contract,
+    ) -> Result<Self, Error> {
+        contract.validate()?;
+        let contract_hash = contract.contract_hash()?.to_byte_array();
+        let input = pset
+            .inputs()
+            .iter()
+            .find(|i| {
+                i.has_issuance()
+                    && i.issuance_blinding_nonce.is_none()
+                    && i.issuance_asset_entropy == Some(contract_hash)
+            })
+            .ok_or(Error::MissingIssuance)?;
+        let (asset_id, _) = input.issuance_ids();
+        Ok(Self::new(contract, asset_id))
+    }
+
+    pub fn contract(&self) -> &Contract {
+        &self.contract
+    }
+
+    pub fn asset_id(&self) -> AssetId {
+        self.asset_id
+    }
+
+    /// The proof the issuer must publish on the contract domain before posting
+    pub fn domain_proof(&self) -> DomainProof {
+        self.contract.domain_proof(self.asset_id)
+    }
+}
+
+/// The file linking the domain of a [`Contract`] entity to an asset, required by the registry
+///
+/// The issuer publishes a file named [`DomainProof::file_name()`] with content
+/// [`DomainProof::content()`] in the `.well-known` directory of the domain, so that it's reachable
+/// at [`DomainProof::url()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainProof {
+    domain: String,
+    asset_id: AssetId,
+}
+
+impl DomainProof {
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    pub fn asset_id(&self) -> AssetId {
+        self.asset_id
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("liquid-asset-proof-{}", self.asset_id)
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://{}/.well-known/{}", self.domain, self.file_name())
+    }
+
+    pub fn content(&self) -> String {
+        format!(
+            "Authorize linking the domain name {} to the Liquid asset {}",
+            self.domain, self.asset_id
+        )
+    }
+
+    /// Check the content fetched from [`DomainProof::url()`]
+    pub fn check(&self, fetched: &str) -> Result<(), Error> {
+        if fetched.trim() == self.content() {
+            Ok(())
+        } else {
+            Err(Error::InvalidDomainProof {
+                url: self.url(),
+                content: self.content(),
+            })
+        }
+    }
 }
 
+#[cfg(feature = "esplora")]
 impl Registry {
     pub fn new(base_url: &str) -> Self {
         Self {
             client: reqwest::Client::new(),
             base_url: base_url.to_string(),
+            known_assets: KnownAssets::default(),
         }
     }
 
     #[allow(dead_code)]
     pub fn default_for_network(network: ElementsNetwork) -> Result<Self, Error> {
-        Ok(Self::new(network_default_url(network)?))
+        let mut registry = Self::new(network_default_url(network)?);
+        registry.known_assets = KnownAssets::bundled(network);
+        Ok(registry)
+    }
+
+    /// The assets used as fallback by [`Registry::asset_metadata()`]
+    pub fn known_assets(&self) -> &KnownAssets {
+        &self.known_assets
+    }
+
+    /// Add an asset to the ones used as fallback by [`Registry::asset_metadata()`]
+    pub fn add_known_asset(&mut self, asset: KnownAsset) {
+        self.known_assets.insert(asset);
+    }
+
+    /// Get name, ticker and precision of an asset
+    ///
+    /// The registry is queried first, if it cannot be reached or it does not have the asset,
+    /// the known assets are used instead.
+    pub async fn asset_metadata(&self, asset_id: AssetId) -> Result<KnownAsset, Error> {
+        match self.fetch(asset_id).await {
+            Ok(data) => Ok(KnownAsset::from_contract(asset_id, &data.contract)),
+            Err(e) => self.known_assets.get(&asset_id).cloned().ok_or(e),
+        }
     }
 
     pub async fn fetch(&self, asset_id: AssetId) -> Result<RegistryData, Error> {
@@ -150,13 +272,30 @@ impl Registry {
         &self,
         asset_id: AssetId,
         client: &crate::asyncr::EsploraClient,
-    ) -> Result<(Contract, Transaction), Error> {
+    ) -> Result<(Contract, elements::Transaction), Error> {
         let data = self.fetch(asset_id).await?;
         let tx = client.get_transaction(data.issuance_txin.txid).await?;
         Ok((data.contract, tx))
     }
 
+    /// Fetch the domain proof of `data` and check it's the expected one
+    pub async fn verify_domain_proof(&self, data: &RegistryPost) -> Result<(), Error> {
+        let proof = data.domain_proof();
+        let response = self.client.get(proof.url()).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::InvalidDomainProof {
+                url: proof.url(),
+                content: proof.content(),
+            });
+        }
+        proof.check(&response.text().await?)
+    }
+
+    /// Register the asset in the registry
+    ///
+    /// The domain proof is verified with [`Registry::verify_domain_proof()`] before posting.
     pub async fn post(&self, data: &RegistryPost) -> Result<(), Error> {
+        self.verify_domain_proof(data).await?;
         let response = self.client.post(&self.base_url).json(&data).send().await?;
         let status = response.status();
         if status.is_success() {
@@ -171,6 +310,121 @@ impl Registry {
     }
 }
 
+/// Name, ticker and precision of an asset
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct KnownAsset {
+    pub asset_id: AssetId,
+    pub name: String,
+    pub ticker: String,
+    pub precision: u8,
+}
+
+impl KnownAsset {
+    pub fn new(asset_id: AssetId, name: &str, ticker: &str, precision: u8) -> Self {
+        Self {
+            asset_id,
+            name: name.to_string(),
+            ticker: ticker.to_string(),
+            precision,
+        }
+    }
+
+    pub fn from_contract(asset_id: AssetId, contract: &Contract) -> Self {
+        Self::new(
+            asset_id,
+            &contract.name,
+            &contract.ticker,
+            contract.precision,
+        )
+    }
+
+    /// The given `sats` of this asset, displayed with its precision and ticker
+    pub fn amount(&self, sats: i64) -> Result<PrecisionAmount, Error> {
+        Ok(PrecisionAmount::new(sats, self.precision, &self.ticker)?)
+    }
+}
+
+/// Snapshot of well-known Liquid assets: asset id, name, ticker and precision
+const LIQUID_ASSETS: &[(&str, &str, &str, u8)] = &[
+    (
+        "ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2",
+        "Tether USD",
+        "USDt",
+        8,
+    ),
+    (
+        "0e99c1a6da379d1f4151fb9df90449d40d0608f6cb33a5bcbfc8c265f42bab0a",
+        "Liquid CAD",
+        "LCAD",
+        8,
+    ),
+    (
+        "18729918ab4bca843656f08d4dd877bed6641fbd596a0a963abbf199cfeb3cec",
+        "PEGx EUR",
+        "EURx",
+        8,
+    ),
+];
+
+/// A set of assets whose metadata is available without querying the registry
+///
+/// Can be created with the snapshot of well-known assets bundled in this crate
+/// ([`KnownAssets::bundled()`]) and extended with [`KnownAssets::insert()`].
+#[derive(Debug, Clone, Default)]
+pub struct KnownAssets(HashMap<AssetId, KnownAsset>);
+
+impl KnownAssets {
+    /// The well-known assets of the given network
+    ///
+    /// Only Liquid mainnet has bundled assets.
+    pub fn bundled(network: ElementsNetwork) -> Self {
+        let mut assets = Self::default();
+        if network == ElementsNetwork::Liquid {
+            for (asset_id, name, ticker, precision) in LIQUID_ASSETS {
+                let asset_id = AssetId::from_str(asset_id).expect("static");
+                assets.insert(KnownAsset::new(asset_id, name, ticker, *precision));
+            }
+        }
+        assets
+    }
+
+    /// Add an asset, replacing any previous entry with the same asset id
+    pub fn insert(&mut self, asset: KnownAsset) {
+        self.0.insert(asset.asset_id, asset);
+    }
+
+    pub fn get(&self, asset_id: &AssetId) -> Option<&KnownAsset> {
+        self.0.get(asset_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &KnownAsset> {
+        self.0.values()
+    }
+
+    /// Format `sats` of `asset_id` with the asset precision and ticker, e.g. `12.34 USDt`
+    ///
+    /// Unknown assets are displayed in satoshi units followed by the asset id.
+    pub fn sats_to_display(&self, asset_id: &AssetId, sats: i64) -> String {
+        match self.get(asset_id).and_then(|a| a.amount(sats).ok()) {
+            Some(amount) => amount.to_string(),
+            None => format!("{} {}", sats, asset_id),
+        }
+    }
+
+    /// Parse an amount of `asset_id` formatted with [`KnownAssets::sats_to_display()`] or
+    /// without the ticker, returning the satoshi units
+    ///
+    /// Digits beyond the asset precision are rounded.
+    pub fn display_to_sats(&self, asset_id: &AssetId, s: &str) -> Result<i64, Error> {
+        let amount = match self.get(asset_id) {
+            Some(a) => PrecisionAmount::parse(s, a.precision, &a.ticker)?,
+            None => PrecisionAmount::parse(s, 0, &asset_id.to_string())?,
+        };
+        Ok(amount.sats())
+    }
+}
+
+#[cfg(feature = "esplora")]
 fn network_default_url(network: ElementsNetwork) -> Result<&'static str, Error> {
     Ok(match network {
         ElementsNetwork::Liquid => "https://assets.blockstream.info",
@@ -179,14 +433,14 @@ fn network_default_url(network: ElementsNetwork) -> Result<&'static str, Error>
     })
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "esplora", not(target_arch = "wasm32")))]
 pub mod blocking {
     use elements::{AssetId, Transaction};
     use tokio::runtime::Runtime;
 
     use crate::{ElementsNetwork, Error};
 
-    use super::RegistryPost;
+    use super::{KnownAsset, KnownAssets, RegistryPost};
 
     pub struct Registry {
         inner: super::Registry,
@@ -203,11 +457,23 @@ pub mod blocking {
 
         pub fn default_for_network(network: ElementsNetwork) -> Result<Self, Error> {
             Ok(Self {
-                inner: super::Registry::new(super::network_default_url(network)?),
+                inner: super::Registry::default_for_network(network)?,
                 rt: Runtime::new()?,
             })
         }
 
+        pub fn known_assets(&self) -> &KnownAssets {
+            self.inner.known_assets()
+        }
+
+        pub fn add_known_asset(&mut self, asset: KnownAsset) {
+            self.inner.add_known_asset(asset)
+        }
+
+        pub fn asset_metadata(&self, asset_id: AssetId) -> Result<KnownAsset, Error> {
+            self.rt.block_on(self.inner.asset_metadata(asset_id))
+        }
+
         pub fn fetch(&self, asset_id: AssetId) -> Result<super::RegistryData, Error> {
             self.rt.block_on(self.inner.fetch(asset_id))
         }
@@ -220,6 +486,10 @@ pub mod blocking {
             self.rt.block_on(self.inner.fetch_with_tx(asset_id, client))
         }
 
+        pub fn verify_domain_proof(&self, data: &RegistryPost) -> Result<(), Error> {
+            self.rt.block_on(self.inner.verify_domain_proof(data))
+        }
+
         pub fn post(&self, data: &RegistryPost) -> Result<(), Error> {
             self.rt.block_on(self.inner.post(data))
         }
@@ -288,6 +558,7 @@ mod tests {
 
     #[ignore = "require internet connection"]
     #[test]
+    #[cfg(feature = "esplora")]
     fn test_registry_fetch_blocking() {
         let tether_asset_id =
             AssetId::from_str("ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2")
@@ -298,6 +569,77 @@ mod tests {
     }
 
     #[test]
+    fn test_domain_proof() {
+        let contract = Contract::from_str("{\"entity\":{\"domain\":\"tether.to\"},\"issuer_pubkey\":\"0337cceec0beea0232ebe14cba0197a9fbd45fcf2ec946749de920e71434c2b904\",\"name\":\"Tether USD\",\"precision\":8,\"ticker\":\"USDt\",\"version\":0}").unwrap();
+        let asset_id =
+            AssetId::from_str("ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2")
+                .unwrap();
+        let proof = contract.domain_proof(asset_id);
+        assert_eq!(proof.domain(), "tether.to");
+        assert_eq!(
+            proof.file_name(),
+            "liquid-asset-proof-ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2"
+        );
+        assert_eq!(proof.url(), "https://tether.to/.well-known/liquid-asset-proof-ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2");
+        assert_eq!(proof.content(), "Authorize linking the domain name tether.to to the Liquid asset ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2");
+        assert_eq!(RegistryPost::new(contract, asset_id).domain_proof(), proof);
+
+        proof.check(&format!("{}\n", proof.content())).unwrap();
+        let err = proof.check("<html>Not found</html>").unwrap_err();
+        assert!(matches!(err, Error::InvalidDomainProof { .. }));
+        assert!(err.to_string().starts_with(&proof.url()));
+    }
+
+    #[ignore = "require internet connection"]
+    #[test]
+    #[cfg(feature = "esplora")]
+    fn test_verify_domain_proof_blocking() {
+        let registry = blocking::Registry::default_for_network(ElementsNetwork::Liquid).unwrap();
+        let asset_id =
+            AssetId::from_str("ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2")
+                .unwrap();
+        let data = registry.fetch(asset_id).unwrap();
+        let post = RegistryPost::new(data.contract, asset_id);
+        registry.verify_domain_proof(&post).unwrap();
+    }
+
+    #[test]
+    fn test_known_assets() {
+        let usdt =
+            AssetId::from_str("ce091c998b83c78bb71a632313ba3760f1763d9cfcffae02258ffa9865a37bd2")
+                .unwrap();
+        let assets = KnownAssets::bundled(ElementsNetwork::Liquid);
+        assert_eq!(assets.iter().count(), LIQUID_ASSETS.len());
+        let asset = assets.get(&usdt).unwrap();
+        assert_eq!(asset.ticker, "USDt");
+        assert_eq!(asset.precision, 8);
+        assert_eq!(
+            assets.sats_to_display(&usdt, 1_234_000_000),
+            "12.34000000 USDt"
+        );
+        assert_eq!(
+            assets.display_to_sats(&usdt, "12.34 USDt").unwrap(),
+            1_234_000_000
+        );
+        assert_eq!(assets.display_to_sats(&usdt, "0.000000005").unwrap(), 1);
+        assert!(assets.display_to_sats(&usdt, "12.34 LCAD").is_err());
+        let other = AssetId::default();
+        assert_eq!(assets.sats_to_display(&other, 5), format!("5 {other}"));
+        assert_eq!(assets.display_to_sats(&other, "5").unwrap(), 5);
+        assert!(KnownAssets::bundled(ElementsNetwork::LiquidTestnet)
+            .get(&usdt)
+            .is_none());
+
+        // Unreachable registry, known assets are used
+        let mut registry = blocking::Registry::new("http://127.0.0.1:1").unwrap();
+        assert!(registry.asset_metadata(usdt).is_err());
+        let asset = KnownAsset::new(usdt, "Tether USD", "USDt", 8);
+        registry.add_known_asset(asset.clone());
+        assert_eq!(registry.asset_metadata(usdt).unwrap(), asset);
+    }
+
+    #[test]
+    #[cfg(feature = "esplora")]
     fn test_registry() {
         let contract_string = "{\"entity\":{\"domain\":\"tether.to\"},\"issuer_pubkey\":\"0337cceec0beea0232ebe14cba0197a9fbd45fcf2ec946749de920e71434c2b904\",\"name\":\"Tether USD\",\"precision\":8,\"ticker\":\"USDt\",\"version\":0}";
         let contract_value = serde_json::Value::from_str(contract_string).unwrap();
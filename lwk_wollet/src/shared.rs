@@ -0,0 +1,126 @@
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::clients::blocking::BlockchainBackend;
+use crate::{Error, Wollet};
+
+/// How many times [`SharedWollet::full_scan()`] retries when the wallet changes during the scan
+const MAX_SCAN_ATTEMPTS: usize = 3;
+
+/// A cheaply cloneable handle to a [`Wollet`] that can be shared across threads
+///
+/// The wallet is behind a [`RwLock`], which is never held while talking to the blockchain
+/// backend, so that reads (balance, transactions, addresses) are not blocked by a slow sync.
+///
+/// Locking order: a scan takes the read lock only to snapshot the wallet state, releases it,
+/// downloads the data, then takes the write lock to apply the update. Never call a scanning
+/// method while holding a guard obtained with [`SharedWollet::read()`] or
+/// [`SharedWollet::write()`] on the same handle, it would deadlock.
+#[derive(Clone)]
+pub struct SharedWollet {
+    inner: Arc<RwLock<Wollet>>,
+}
+
+impl SharedWollet {
+    pub fn new(wollet: Wollet) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(wollet)),
+        }
+    }
+
+    /// Lock the wallet for reading, many readers can hold the lock at the same time
+    pub fn read(&self) -> Result<RwLockReadGuard<'_, Wollet>, Error> {
+        self.inner.read().map_err(|_| Error::LockPoisoned)
+    }
+
+    /// Lock the wallet for writing, for instance to apply an update
+    pub fn write(&self) -> Result<RwLockWriteGuard<'_, Wollet>, Error> {
+        self.inner.write().map_err(|_| Error::LockPoisoned)
+    }
+
+    /// Scan the blockchain with `client` and apply the resulting update, if any
+    ///
+    /// If the wallet is modified by another thread while downloading, the update would be
+    /// computed on a stale state, in this case the scan is repeated.
+    ///
+    /// Returns true if the wallet changed.
+    pub fn full_scan<B: BlockchainBackend>(&self, client: &mut B) -> Result<bool, Error> {
+        let mut attempt = 1;
+        loop {
+            let state = self.read()?.state();
+            let update = match client.full_scan(&state)? {
+                Some(update) => update,
+                None => return Ok(false),
+            };
+            match self.write()?.apply_update(update) {
+                Err(Error::UpdateOnDifferentStatus { .. }) if attempt < MAX_SCAN_ATTEMPTS => {
+                    tracing::debug!("wallet changed during scan, retrying");
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+                Ok(()) => return Ok(true),
+            }
+        }
+    }
+}
+
+impl From<Wollet> for SharedWollet {
+    fn from(wollet: Wollet) -> Self {
+        Self::new(wollet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::thread;
+
+    use super::*;
+    use crate::blocking::MockBackend;
+    use crate::{ElementsNetwork, WolletDescriptor};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_shared_wollet() {
+        assert_send_sync::<Wollet>();
+        assert_send_sync::<SharedWollet>();
+
+        let network = ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let wollet = SharedWollet::new(Wollet::without_persist(network, desc).unwrap());
+        let mut client = MockBackend::new(network);
+        for i in 0..4 {
+            let address = wollet.read().unwrap().address(Some(i)).unwrap();
+            client.fund(address.address(), 1_000, None).unwrap();
+        }
+        client.mine(1);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let wollet = wollet.clone();
+                let mut client = client.clone();
+                thread::spawn(move || {
+                    wollet.read().unwrap().balance().unwrap();
+                    wollet.full_scan(&mut client).unwrap()
+                })
+            })
+            .collect();
+        let changed = handles.into_iter().map(|h| h.join().unwrap());
+        assert!(changed.filter(|c| *c).count() >= 1);
+
+        let balance = wollet.read().unwrap().balance().unwrap();
+        assert_eq!(balance[&network.policy_asset()], 4_000);
+        assert!(!wollet.full_scan(&mut client).unwrap());
+
+        client
+            .fund(
+                wollet.read().unwrap().address(None).unwrap().address(),
+                1,
+                None,
+            )
+            .unwrap();
+        assert!(wollet.full_scan(&mut client).unwrap());
+        let balance = wollet.read().unwrap().balance().unwrap();
+        assert_eq!(balance[&network.policy_asset()], 4_001);
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::BTreeSet;
+
+use elements::bitcoin::bip32::Fingerprint;
+use elements::pset::{Input, PartiallySignedTransaction};
+use elements::{LockTime, Sequence};
+use elements_miniscript::policy::{Liftable, Semantic};
+use elements_miniscript::DescriptorPublicKey;
+
+use crate::descriptor::WolletDescriptor;
+use crate::model::WalletTxOut;
+use crate::wollet::Wollet;
+use crate::Error;
+
+/// A set of conditions which, once met, allows to spend the wallet outputs
+///
+/// Simple descriptors like `elwpkh` have a single spending path, miniscript descriptors like
+/// `elwsh(or_d(pk(A),and_v(v:pk(B),older(144))))` can have many, in this case two: A signs,
+/// or B signs and the spent output has 144 confirmations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendingPath {
+    /// The keys that must sign
+    pub keys: Vec<DescriptorPublicKey>,
+
+    /// The absolute locktime (`after`) that the spending transaction must have, if any
+    pub after: Option<LockTime>,
+
+    /// The relative locktime (`older`) that the spending input must have, if any
+    pub older: Option<Sequence>,
+
+    /// Whether some hash preimages must be revealed
+    pub requires_preimage: bool,
+}
+
+impl SpendingPath {
+    /// The fingerprints of the signers that must sign
+    pub fn signers(&self) -> Vec<Fingerprint> {
+        self.keys.iter().map(|k| k.master_fingerprint()).collect()
+    }
+
+    /// Whether this path has timelocks
+    pub fn is_timelocked(&self) -> bool {
+        self.after.is_some() || self.older.is_some()
+    }
+
+    /// Whether this path can be used in the next block to spend an output confirmed at
+    /// `utxo_height`, given the current `tip_height`
+    ///
+    /// Timelocks expressed in seconds are considered not satisfiable, since the wallet does not
+    /// track the median time past.
+    pub fn is_satisfiable(&self, tip_height: u32, utxo_height: Option<u32>) -> bool {
+        if self.requires_preimage {
+            return false;
+        }
+        if let Some(after) = self.after {
+            match after {
+                LockTime::Blocks(h) if h.to_consensus_u32() <= tip_height => (),
+                _ => return false,
+            }
+        }
+        if let Some(older) = self.older {
+            if !older.is_height_locked() {
+                return false;
+            }
+            let blocks = older.0 & 0xffff;
+            match utxo_height {
+                Some(h) if tip_height + 1 >= h + blocks => (),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Whether the timelocks of `input` of `pset` allow to spend with this path
+    fn is_enabled_by(&self, pset: &PartiallySignedTransaction, input: &Input) -> bool {
+        if self.requires_preimage {
+            return false;
+        }
+        if let Some(after) = self.after {
+            let locktime = pset.global.tx_data.fallback_locktime;
+            match (after, locktime) {
+                (LockTime::Blocks(a), Some(LockTime::Blocks(l))) if a <= l => (),
+                (LockTime::Seconds(a), Some(LockTime::Seconds(l))) if a <= l => (),
+                _ => return false,
+            }
+        }
+        if let Some(older) = self.older {
+            let enabled = input.sequence.is_some_and(|s| {
+                s.is_relative_lock_time()
+                    && older.is_height_locked() == s.is_height_locked()
+                    && s.0 & 0xffff >= older.0 & 0xffff
+            });
+            if !enabled {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn from_conditions(conditions: &[&Semantic<DescriptorPublicKey>]) -> Self {
+        let mut path = SpendingPath {
+            keys: vec![],
+            after: None,
+            older: None,
+            requires_preimage: false,
+        };
+        for condition in conditions {
+            match condition {
+                Semantic::Key(k) => {
+                    if !path.keys.contains(k) {
+                        path.keys.push(k.clone());
+                    }
+                }
+                Semantic::After(t) => {
+                    let t: LockTime = (*t).into();
+                    if path
+                        .after
+                        .map_or(true, |a| a.to_consensus_u32() < t.to_consensus_u32())
+                    {
+                        path.after = Some(t);
+                    }
+                }
+                Semantic::Older(s) => {
+                    if path.older.map_or(true, |o| o < *s) {
+                        path.older = Some(*s);
+                    }
+                }
+                Semantic::Sha256(_)
+                | Semantic::Hash256(_)
+                | Semantic::Ripemd160(_)
+                | Semantic::Hash160(_) => path.requires_preimage = true,
+                Semantic::Unsatisfiable | Semantic::Trivial | Semantic::Threshold(_, _) => (),
+            }
+        }
+        path
+    }
+}
+
+/// All the ways to pick `k` elements out of `n`
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    if n < k {
+        return vec![];
+    }
+    let mut result = combinations(n - 1, k);
+    for mut c in combinations(n - 1, k - 1) {
+        c.push(n - 1);
+        result.push(c);
+    }
+    result
+}
+
+/// Expand the policy in disjunctive normal form, every element is a set of conditions that must
+/// be all met
+fn conjunctions(
+    policy: &Semantic<DescriptorPublicKey>,
+) -> Vec<Vec<&Semantic<DescriptorPublicKey>>> {
+    match policy {
+        Semantic::Unsatisfiable => vec![],
+        Semantic::Trivial => vec![vec![]],
+        Semantic::Threshold(k, subs) => {
+            let subs: Vec<_> = subs.iter().map(conjunctions).collect();
+            let mut result = vec![];
+            for combination in combinations(subs.len(), *k) {
+                let mut partial = vec![vec![]];
+                for i in combination {
+                    partial = partial
+                        .iter()
+                        .flat_map(|p| {
+                            subs[i].iter().map(move |c| {
+                                let mut p: Vec<_> = p.clone();
+                                p.extend(c.iter());
+                                p
+                            })
+                        })
+                        .collect();
+                }
+                result.extend(partial);
+            }
+            result
+        }
+        leaf => vec![vec![leaf]],
+    }
+}
+
+impl WolletDescriptor {
+    /// Get the ways the outputs of this descriptor can be spent
+    pub fn spending_paths(&self) -> Result<Vec<SpendingPath>, Error> {
+        let policy = self.descriptor().lift()?.normalized();
+        let mut paths: Vec<SpendingPath> = vec![];
+        for conditions in conjunctions(&policy) {
+            let path = SpendingPath::from_conditions(&conditions);
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// Which cosigners signed an input of a PSET, see [`Wollet::signature_status()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSignatureStatus {
+    /// The signers that signed the input
+    pub signed: BTreeSet<Fingerprint>,
+
+    /// The signers expected to sign the input which did not sign yet
+    pub missing: BTreeSet<Fingerprint>,
+
+    /// The number of further signatures needed to satisfy the cheapest spending path, `None` if
+    /// the input is not of the wallet or no spending path is enabled by the PSET timelocks
+    pub needed: Option<usize>,
+}
+
+impl InputSignatureStatus {
+    /// Whether the input has enough signatures
+    pub fn is_complete(&self) -> bool {
+        self.needed == Some(0)
+    }
+}
+
+impl Wollet {
+    /// For each input of `pset`, which cosigners signed and how many more signatures are needed
+    ///
+    /// The needed signatures are computed according to the spending paths of the wallet, for
+    /// instance for a 2-of-3 multisig an input signed by a cosigner needs 1 more signature.
+    /// Timelocked paths are considered only if the PSET locktime or the input sequence enable
+    /// them. Signatures are not verified, see [`Wollet::verify_signatures()`].
+    pub fn signature_status(
+        &self,
+        pset: &PartiallySignedTransaction,
+    ) -> Result<Vec<InputSignatureStatus>, Error> {
+        let paths = self.spending_paths()?;
+        let mut result = vec![];
+        for input in pset.inputs() {
+            let mut signed = BTreeSet::new();
+            let mut missing = BTreeSet::new();
+            for (pk, (fingerprint, _)) in input.bip32_derivation.iter() {
+                if input.partial_sigs.contains_key(pk) {
+                    signed.insert(*fingerprint);
+                } else {
+                    missing.insert(*fingerprint);
+                }
+            }
+            missing.retain(|f| !signed.contains(f));
+
+            let is_mine = input
+                .witness_utxo
+                .as_ref()
+                .is_some_and(|txout| self.index(&txout.script_pubkey).is_ok());
+            let needed = if is_mine {
+                paths
+                    .iter()
+                    .filter(|p| p.is_enabled_by(pset, input))
+                    .map(|p| {
+                        p.signers()
+                            .into_iter()
+                            .filter(|f| !signed.contains(f))
+                            .count()
+                    })
+                    .min()
+            } else {
+                None
+            };
+            result.push(InputSignatureStatus {
+                signed,
+                missing,
+                needed,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Get the spending paths of the wallet descriptor
+    pub fn spending_paths(&self) -> Result<Vec<SpendingPath>, Error> {
+        self.wollet_descriptor().spending_paths()
+    }
+
+    /// Get the spending paths that can be used now to spend `utxo`, given the wallet tip
+    pub fn satisfiable_spending_paths(
+        &self,
+        utxo: &WalletTxOut,
+    ) -> Result<Vec<SpendingPath>, Error> {
+        let tip_height = self.tip().height();
+        Ok(self
+            .spending_paths()?
+            .into_iter()
+            .filter(|p| p.is_satisfiable(tip_height, utxo.height))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chain;
+
+    const XPUB1: &str = "[11111111/87h/1h/0h]tpubDC2Q4xK4XH72GM7MowNuajyWVbigRLBWKswyP5T88hpPwu5nGqJWnda8zhJEFt71av73Hm8mUMMFSz9acNVzz8b1UbdSHCDXKTbSv5eEytu/<0;1>/*";
+    const XPUB2: &str = "[22222222/87h/1h/0h]tpubDCTUqRMrF2GHZ6pi5FcamdyGZ3oDJtQMH4y5Hyh8Uu7CQ3Zymbh1hpM84aXyJhgBhuh6WcUpKteMeYdyYfVUDRrsz8FUeRdoaaSRKkyMx6Y/<0;1>/*";
+
+    fn fingerprint(s: &str) -> Fingerprint {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_combinations() {
+        assert_eq!(combinations(3, 0), vec![Vec::<usize>::new()]);
+        assert_eq!(combinations(2, 3), Vec::<Vec<usize>>::new());
+        assert_eq!(combinations(3, 2), vec![vec![0, 1], vec![0, 2], vec![1, 2]]);
+    }
+
+    #[test]
+    fn test_spending_paths() {
+        let desc: WolletDescriptor = format!("ct(elip151,elwpkh({XPUB1}))").parse().unwrap();
+        let paths = desc.spending_paths().unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].signers(), vec![fingerprint("11111111")]);
+        assert!(!paths[0].is_timelocked());
+        assert!(paths[0].is_satisfiable(0, None));
+
+        let desc: WolletDescriptor = format!("ct(elip151,elwsh(multi(1,{XPUB1},{XPUB2})))")
+            .parse()
+            .unwrap();
+        let paths = desc.spending_paths().unwrap();
+        assert_eq!(paths.len(), 2);
+
+        // Key A can spend now, key B after the output has 144 confirmations
+        let desc: WolletDescriptor =
+            format!("ct(elip151,elwsh(or_d(pk({XPUB1}),and_v(v:pk({XPUB2}),older(144)))))")
+                .parse()
+                .unwrap();
+        desc.script_pubkey(Chain::External, 0).unwrap();
+        let paths = desc.spending_paths().unwrap();
+        assert_eq!(paths.len(), 2);
+        let now = paths.iter().find(|p| !p.is_timelocked()).unwrap();
+        assert_eq!(now.signers(), vec![fingerprint("11111111")]);
+        assert!(now.is_satisfiable(100, None));
+        let later = paths.iter().find(|p| p.is_timelocked()).unwrap();
+        assert_eq!(later.signers(), vec![fingerprint("22222222")]);
+        assert_eq!(later.older, Some(Sequence(144)));
+        assert!(!later.is_satisfiable(1000, None));
+        assert!(!later.is_satisfiable(1000, Some(858)));
+        assert!(later.is_satisfiable(1000, Some(857)));
+
+        // Both keys, or key A after block 500
+        let desc: WolletDescriptor = format!(
+            "ct(elip151,elwsh(or_d(multi(2,{XPUB1},{XPUB2}),and_v(v:pk({XPUB1}),after(500)))))"
+        )
+        .parse()
+        .unwrap();
+        let paths = desc.spending_paths().unwrap();
+        assert_eq!(paths.len(), 2);
+        let later = paths.iter().find(|p| p.is_timelocked()).unwrap();
+        assert_eq!(later.signers(), vec![fingerprint("11111111")]);
+        assert!(!later.is_satisfiable(499, None));
+        assert!(later.is_satisfiable(500, None));
+    }
+
+    #[test]
+    fn test_signature_status() {
+        use crate::blocking::MockBackend;
+        use crate::ElementsNetwork;
+
+        let network = ElementsNetwork::default_regtest();
+        let desc: WolletDescriptor = format!(
+            "ct(elip151,elwsh(or_d(multi(2,{XPUB1},{XPUB2}),and_v(v:pk({XPUB1}),after(500)))))"
+        )
+        .parse()
+        .unwrap();
+        let mut wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap();
+        client.fund(address.address(), 100_000, None).unwrap();
+        client.sync(&mut wollet).unwrap();
+
+        let mut pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(address.address(), 1_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let (a, b) = (fingerprint("11111111"), fingerprint("22222222"));
+        let status = wollet.signature_status(&pset).unwrap();
+        assert_eq!(status.len(), 1);
+        assert!(status[0].signed.is_empty());
+        assert_eq!(status[0].missing, BTreeSet::from([a, b]));
+        assert_eq!(status[0].needed, Some(2));
+
+        // A signs
+        let input = &mut pset.inputs_mut()[0];
+        let (pk, _) = input
+            .bip32_derivation
+            .iter()
+            .find(|(_, (f, _))| *f == a)
+            .unwrap();
+        input.partial_sigs.insert(*pk, vec![0x30]);
+        let status = wollet.signature_status(&pset).unwrap();
+        assert_eq!(status[0].signed, BTreeSet::from([a]));
+        assert_eq!(status[0].missing, BTreeSet::from([b]));
+        assert_eq!(status[0].needed, Some(1));
+        assert!(!status[0].is_complete());
+
+        // A alone is enough once the locktime enables the timelocked path
+        pset.global.tx_data.fallback_locktime = Some(LockTime::from_height(500).unwrap());
+        let status = wollet.signature_status(&pset).unwrap();
+        assert!(status[0].is_complete());
+    }
+
+    #[test]
+    fn test_recover_sequence() {
+        use crate::blocking::MockBackend;
+        use crate::ElementsNetwork;
+
+        let network = ElementsNetwork::default_regtest();
+        let desc: WolletDescriptor = format!(
+            "ct(elip151,elwsh(or_d(multi(2,{XPUB1},{XPUB2}),and_v(v:pk({XPUB1}),after(500)))))"
+        )
+        .parse()
+        .unwrap();
+        let mut wollet = Wollet::without_persist(network, desc).unwrap();
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap();
+        client.fund(address.address(), 100_000, None).unwrap();
+        client.mine(500);
+        client.sync(&mut wollet).unwrap();
+
+        // The timelocked path enables the locktime and keeps the transaction replaceable
+        let pset = wollet
+            .tx_builder()
+            .recover()
+            .drain_lbtc_wallet()
+            .finish()
+            .unwrap();
+        let tx = pset.extract_tx().unwrap();
+        assert!(tx.lock_time.to_consensus_u32() >= 500);
+        assert!(tx.input.iter().all(|i| i.sequence.is_rbf()));
+        assert!(tx
+            .input
+            .iter()
+            .all(|i| i.sequence.enables_absolute_lock_time()));
+    }
+}
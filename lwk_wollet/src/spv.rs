@@ -0,0 +1,166 @@
+use crate::elements::{BlockHeader, Transaction, TxMerkleNode, Txid};
+use crate::hashes::{sha256d, Hash, HashEngine};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of headers included in a [`SpvProof`], starting from the block containing the
+/// transaction.
+pub const SPV_PROOF_MAX_HEADERS: u32 = 100;
+
+/// A merkle inclusion proof of a transaction in a block, as returned by
+/// [`crate::ElectrumClient::get_merkle_proof()`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// Height of the block containing the transaction
+    pub block_height: u32,
+
+    /// Position of the transaction in the block
+    pub pos: usize,
+
+    /// The merkle branch, from the leaf to the root
+    pub merkle: Vec<TxMerkleNode>,
+}
+
+impl MerkleProof {
+    /// Compute the merkle root committing to `txid` using this branch
+    pub fn merkle_root(&self, txid: &Txid) -> TxMerkleNode {
+        let mut index = self.pos;
+        let mut cur = txid.to_raw_hash();
+        for node in self.merkle.iter() {
+            let mut engine = sha256d::Hash::engine();
+            if index % 2 == 0 {
+                engine.input(cur.as_ref());
+                engine.input(node.as_ref());
+            } else {
+                engine.input(node.as_ref());
+                engine.input(cur.as_ref());
+            }
+            cur = sha256d::Hash::from_engine(engine);
+            index /= 2;
+        }
+        TxMerkleNode::from_raw_hash(cur)
+    }
+
+    /// Returns true if `txid` is included in the block with the given `header`
+    pub fn verify(&self, txid: &Txid, header: &BlockHeader) -> bool {
+        header.height == self.block_height && self.merkle_root(txid) == header.merkle_root
+    }
+}
+
+/// A self contained proof that a transaction has been included in the blockchain, returned by
+/// [`crate::Wollet::export_spv_proof()`]
+///
+/// It can be serialized and verified offline by third parties with [`SpvProof::verify()`].
+///
+/// Note: the block signatures are not verified, the verifier should check that the headers are
+/// part of the chain it considers valid (eg. comparing the block hashes with a trusted node).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SpvProof {
+    /// The transaction
+    pub tx: Transaction,
+
+    /// The merkle branch proving the transaction is in the first header
+    pub merkle_proof: MerkleProof,
+
+    /// A chain of consecutive headers, the first one contains the transaction
+    pub headers: Vec<BlockHeader>,
+}
+
+impl SpvProof {
+    /// Verify the transaction is committed in the first header and that headers are connected
+    pub fn verify(&self) -> Result<(), Error> {
+        let first = self.headers.first().ok_or(Error::InvalidSpvProof)?;
+        if !self.merkle_proof.verify(&self.tx.txid(), first) {
+            return Err(Error::InvalidSpvProof);
+        }
+        for w in self.headers.windows(2) {
+            if w[1].prev_blockhash != w[0].block_hash() || w[1].height != w[0].height + 1 {
+                return Err(Error::InvalidSpvProof);
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of confirmations proven by the header chain
+    pub fn confirmations(&self) -> u32 {
+        self.headers.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elements::encode::deserialize;
+    use crate::elements::hex::FromHex;
+
+    #[test]
+    fn test_merkle_proof() {
+        let a = Txid::from_byte_array([1u8; 32]);
+        let b = Txid::from_byte_array([2u8; 32]);
+        let mut engine = sha256d::Hash::engine();
+        engine.input(a.as_ref());
+        engine.input(b.as_ref());
+        let root = TxMerkleNode::from_raw_hash(sha256d::Hash::from_engine(engine));
+
+        let proof_a = MerkleProof {
+            block_height: 1,
+            pos: 0,
+            merkle: vec![TxMerkleNode::from_raw_hash(b.to_raw_hash())],
+        };
+        assert_eq!(proof_a.merkle_root(&a), root);
+        assert_ne!(proof_a.merkle_root(&b), root);
+
+        let proof_b = MerkleProof {
+            block_height: 1,
+            pos: 1,
+            merkle: vec![TxMerkleNode::from_raw_hash(a.to_raw_hash())],
+        };
+        assert_eq!(proof_b.merkle_root(&b), root);
+
+        // a block with a single transaction has the txid as merkle root
+        let single = MerkleProof {
+            block_height: 1,
+            pos: 0,
+            merkle: vec![],
+        };
+        assert_eq!(single.merkle_root(&a).to_raw_hash(), a.to_raw_hash());
+    }
+
+    #[test]
+    fn test_spv_proof() {
+        let tx_hex = include_str!("../tests/data/usdt-issuance-tx.hex");
+        let tx: Transaction = deserialize(&Vec::<u8>::from_hex(tx_hex).unwrap()).unwrap();
+
+        // a block containing only the transaction
+        let mut first = lwk_test_util::liquid_block_header_2_963_520();
+        first.merkle_root = TxMerkleNode::from_raw_hash(tx.txid().to_raw_hash());
+        let mut second = first.clone();
+        second.height += 1;
+        second.prev_blockhash = first.block_hash();
+
+        let merkle_proof = MerkleProof {
+            block_height: first.height,
+            pos: 0,
+            merkle: vec![],
+        };
+        let mut proof = SpvProof {
+            tx,
+            merkle_proof,
+            headers: vec![first, second],
+        };
+        proof.verify().unwrap();
+        assert_eq!(proof.confirmations(), 2);
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let back: SpvProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(proof, back);
+
+        // headers not connected
+        proof.headers[1].prev_blockhash = proof.headers[1].block_hash();
+        assert!(proof.verify().is_err());
+
+        // no headers
+        proof.headers.clear();
+        assert!(proof.verify().is_err());
+    }
+}
@@ -1,16 +1,35 @@
 use crate::descriptor::Chain;
-use crate::elements::{BlockHash, OutPoint, Script, Transaction, TxOutSecrets, Txid};
+use crate::elements::{
+    AssetId, BlockHash, BlockHeader, OutPoint, Script, Transaction, TxOutSecrets, Txid,
+};
 use crate::hashes::Hash;
 use crate::{BlindingPublicKey, Error};
 use elements::bitcoin::bip32::ChildNumber;
 use elements_miniscript::{ConfidentialDescriptor, DescriptorPublicKey};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
 
 pub const BATCH_SIZE: u32 = 20;
+
+/// The maximum number of the most recent block headers kept, to check that new tips extend them
+pub const MAX_HEADERS: usize = 100;
 pub type Height = u32;
 pub type Timestamp = u32;
 
+/// The electrum status of a script, with the transactions of its history when it was fetched
+///
+/// The status changes only when the history changes, so the history of a script with the same
+/// status is not fetched again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptHistoryStatus {
+    /// The hash of the script history, as defined by the electrum protocol
+    pub status: [u8; 32],
+
+    /// The transactions of the script history
+    pub txids: Vec<Txid>,
+}
+
 /// `RawCache` is a cache of wallet data, like wallet transactions.
 /// It is fully reconstructable from the CT Descriptor and the blockchain.
 pub struct RawCache {
@@ -20,21 +39,43 @@ pub struct RawCache {
     /// contains all my script up to an empty batch of BATCHSIZE
     pub paths: HashMap<Script, (Chain, ChildNumber)>,
 
+    /// scripts derived by lookups missing `paths`, added to it with the next applied update
+    pub derived: Mutex<HashMap<Script, (Chain, ChildNumber)>>,
+
     /// inverse of `paths`, with the blinding public key for each script
     pub scripts: HashMap<(Chain, ChildNumber), (Script, BlindingPublicKey)>,
 
     /// contains only my wallet txs with the relative heights (None if unconfirmed)
     pub heights: HashMap<Txid, Option<Height>>,
 
+    /// unconfirmed txs removed from `heights` because double spent, with the conflicting tx
+    pub conflicted: HashMap<Txid, Txid>,
+
     /// unblinded values
     pub unblinded: HashMap<OutPoint, TxOutSecrets>,
 
+    /// index of `unblinded` by asset
+    pub unblinded_by_asset: HashMap<AssetId, HashSet<OutPoint>>,
+
     /// height and hash of tip of the blockchain
     pub tip: (Height, BlockHash),
 
+    /// the last [`MAX_HEADERS`] headers seen as tips of the blockchain, the last one is the tip
+    ///
+    /// Rebuilt from the tips of the persisted updates, the headers are consecutive and each one
+    /// is the parent of the following one: a tip not extending them starts a new chain.
+    pub headers: BTreeMap<Height, BlockHeader>,
+
     /// Contains the time of blocks at the given height. There are only heights containinig wallet txs
     pub timestamps: HashMap<Height, Timestamp>,
 
+    /// Contains the hash of blocks at the given height, to detect when they are reorged
+    pub block_hashes: HashMap<Height, BlockHash>,
+
+    /// the electrum status of the scripts with a history, from the scans of backends supporting
+    /// [`crate::clients::Capability::ScriptStatus`]
+    pub script_status: HashMap<Script, ScriptHistoryStatus>,
+
     /// last unused index for external addresses for current descriptor
     pub last_unused_external: AtomicU32,
 
@@ -47,17 +88,30 @@ impl Default for RawCache {
         Self {
             all_txs: HashMap::default(),
             paths: HashMap::default(),
+            derived: Mutex::default(),
             scripts: HashMap::default(),
             heights: HashMap::default(),
+            conflicted: HashMap::default(),
             unblinded: HashMap::default(),
+            unblinded_by_asset: HashMap::default(),
             tip: (0, BlockHash::all_zeros()),
+            headers: BTreeMap::default(),
             last_unused_internal: 0.into(),
             last_unused_external: 0.into(),
             timestamps: HashMap::default(),
+            block_hashes: HashMap::default(),
+            script_status: HashMap::default(),
         }
     }
 }
 
+impl RawCache {
+    /// The header of the tip of the blockchain, None if the wallet has never been synced
+    pub fn tip_header(&self) -> Option<&BlockHeader> {
+        self.headers.values().next_back()
+    }
+}
+
 impl std::hash::Hash for RawCache {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         let mut vec: Vec<_> = self.all_txs.keys().collect();
@@ -157,8 +211,9 @@ impl Store {
         Ok(self
             .cache
             .all_txs
-            .values()
-            .flat_map(|tx| tx.input.iter())
+            .iter()
+            .filter(|(txid, _)| !self.cache.conflicted.contains_key(*txid))
+            .flat_map(|(_, tx)| tx.input.iter())
             .map(|i| i.previous_output)
             .collect())
     }
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+
+use elements::bitcoin::{PrivateKey, PublicKey};
+use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
+use elements::hashes::Hash;
+use elements::pset::PartiallySignedTransaction;
+use elements::secp256k1_zkp::SecretKey;
+use elements::sighash::SighashCache;
+use elements::{Address, BlockHash, EcdsaSighashType, OutPoint, Script, TxOutSecrets};
+use elements_miniscript::psbt::PsbtExt;
+use elements_miniscript::{elementssig_to_rawsig, Descriptor};
+
+use crate::clients::blocking::BlockchainBackend;
+use crate::{Error, ExternalUtxo, Wollet, EC};
+
+impl Wollet {
+    /// Create a PSET sending all the funds controlled by a private key to the wallet
+    ///
+    /// The outputs of the P2WPKH and P2SH-P2WPKH scripts of the `wif` private key are fetched with
+    /// `client`, confidential outputs are unblinded with `blinding_key` and skipped if they
+    /// can't be. L-BTC, minus the fee, is sent to the next wallet address, other assets to
+    /// wallet change addresses.
+    ///
+    /// The returned PSET is already signed with the private key, it only needs to be finalized
+    /// with [`Wollet::finalize()`].
+    pub fn sweep_private_key<B: BlockchainBackend>(
+        &self,
+        client: &B,
+        wif: &str,
+        blinding_key: Option<SecretKey>,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        let key =
+            PrivateKey::from_wif(wif).map_err(|e| Error::Generic(format!("Invalid WIF: {e}")))?;
+        if !key.compressed {
+            return Err(Error::Generic(
+                "Uncompressed keys do not control segwit outputs".into(),
+            ));
+        }
+        let public_key = key.public_key(&EC);
+        let params = self.network().address_params();
+
+        // (script pubkey, redeem script, max weight to satisfy) of the key outputs
+        let wpkh = Address::p2wpkh(&public_key, None, params).script_pubkey();
+        let sh_wpkh = Address::p2shwpkh(&public_key, None, params).script_pubkey();
+        let scripts = [
+            (
+                wpkh.clone(),
+                None,
+                Descriptor::<PublicKey>::new_wpkh(public_key)?.max_weight_to_satisfy()?,
+            ),
+            (
+                sh_wpkh,
+                Some(wpkh),
+                Descriptor::<PublicKey>::new_sh_wpkh(public_key)?.max_weight_to_satisfy()?,
+            ),
+        ];
+
+        let utxos = key_utxos(client, &scripts, blinding_key)?;
+        if utxos.is_empty() {
+            return Err(Error::NoFundsToSweep);
+        }
+        let swept: HashSet<_> = utxos.iter().map(|u| u.outpoint).collect();
+
+        let address = self.address(None)?.address().clone();
+        let mut pset = self
+            .tx_builder()
+            .set_wallet_utxos(vec![])
+            .add_external_utxos(utxos)?
+            .drain_lbtc_to(address)
+            .finish()?;
+
+        let tx = pset.extract_tx()?;
+        let mut cache = SighashCache::new(&tx);
+        let mut sigs = vec![];
+        for (idx, input) in pset.inputs().iter().enumerate() {
+            let outpoint = OutPoint::new(input.previous_txid, input.previous_output_index);
+            if swept.contains(&outpoint) {
+                // genesis hash is used only for taproot sighashes
+                let msg = pset
+                    .sighash_msg(idx, &mut cache, None, BlockHash::all_zeros())
+                    .map_err(|e| Error::Generic(format!("Cannot compute sighash: {e:?}")))?
+                    .to_secp_msg();
+                let sig = EC.sign_ecdsa_low_r(&msg, &key.inner);
+                sigs.push((idx, elementssig_to_rawsig(&(sig, EcdsaSighashType::All))));
+            }
+        }
+        for (idx, sig) in sigs {
+            pset.inputs_mut()[idx].partial_sigs.insert(public_key, sig);
+        }
+        Ok(pset)
+    }
+}
+
+/// The unspent outputs of `scripts` that can be unblinded
+fn key_utxos<B: BlockchainBackend>(
+    client: &B,
+    scripts: &[(Script, Option<Script>, usize)],
+    blinding_key: Option<SecretKey>,
+) -> Result<Vec<ExternalUtxo>, Error> {
+    let script_pubkeys: Vec<_> = scripts.iter().map(|(s, _, _)| s).collect();
+    let txids: HashSet<_> = client
+        .get_scripts_history(&script_pubkeys)?
+        .into_iter()
+        .flatten()
+        .map(|h| h.txid)
+        .collect();
+    let txids: Vec<_> = txids.into_iter().collect();
+    let txs = client.get_transactions(&txids)?;
+
+    let spent: HashSet<_> = txs
+        .iter()
+        .flat_map(|tx| tx.input.iter().map(|i| i.previous_output))
+        .collect();
+    let mut utxos = vec![];
+    for tx in txs.iter() {
+        for (vout, txout) in tx.output.iter().enumerate() {
+            let outpoint = OutPoint::new(tx.txid(), vout as u32);
+            let Some((_, redeem_script, max_weight_to_satisfy)) =
+                scripts.iter().find(|(s, _, _)| s == &txout.script_pubkey)
+            else {
+                continue;
+            };
+            if spent.contains(&outpoint) {
+                continue;
+            }
+            let unblinded = match (txout.asset.explicit(), txout.value.explicit()) {
+                (Some(asset), Some(value)) => TxOutSecrets::new(
+                    asset,
+                    AssetBlindingFactor::zero(),
+                    value,
+                    ValueBlindingFactor::zero(),
+                ),
+                _ => match blinding_key.map(|k| txout.unblind(&EC, k)) {
+                    Some(Ok(unblinded)) => unblinded,
+                    _ => {
+                        tracing::warn!("cannot unblind {outpoint}, it is not swept");
+                        continue;
+                    }
+                },
+            };
+            utxos.push(ExternalUtxo {
+                outpoint,
+                txout: txout.clone(),
+                unblinded,
+                max_weight_to_satisfy: *max_weight_to_satisfy,
+                redeem_script: redeem_script.clone(),
+                witness_script: None,
+            });
+        }
+    }
+    Ok(utxos)
+}
+
+#[cfg(test)]
+mod tests {
+    use elements::bitcoin::NetworkKind;
+    use elements::AssetId;
+
+    use crate::clients::blocking::MockBackend;
+    use crate::ElementsNetwork;
+
+    use super::*;
+
+    #[test]
+    fn test_sweep_private_key() {
+        let network = ElementsNetwork::LiquidTestnet;
+        let wollet = crate::wollet::tests::test_wollet_with_many_transactions();
+        let policy_asset = network.policy_asset();
+        let asset = AssetId::from_slice(&[7; 32]).unwrap();
+
+        let key = PrivateKey::new(SecretKey::from_slice(&[1; 32]).unwrap(), NetworkKind::Test);
+        let public_key = PublicKey::from_private_key(&EC, &key);
+        let blinding_key = SecretKey::from_slice(&[2; 32]).unwrap();
+        let params = network.address_params();
+        let wpkh = Address::p2wpkh(&public_key, Some(blinding_key.public_key(&EC)), params);
+        let sh_wpkh = Address::p2shwpkh(&public_key, None, params);
+
+        let mut client = MockBackend::new(network);
+        let err = wollet
+            .sweep_private_key(&client, &key.to_wif(), None)
+            .unwrap_err();
+        assert!(matches!(err, Error::NoFundsToSweep));
+
+        client.fund(&wpkh, 10_000, None).unwrap();
+        client.fund(&sh_wpkh, 20_000, None).unwrap();
+        client.fund(&sh_wpkh, 500, Some(asset)).unwrap();
+        client.mine(1);
+
+        // confidential output without blinding key is not swept
+        let pset = wollet
+            .sweep_private_key(&client, &key.to_wif(), None)
+            .unwrap();
+        assert_eq!(pset.inputs().len(), 2);
+
+        let pset = wollet
+            .sweep_private_key(&client, &key.to_wif(), Some(blinding_key))
+            .unwrap();
+        assert_eq!(pset.inputs().len(), 3);
+        assert_eq!(lwk_common::pset::verify_signatures(&pset).unwrap(), 3);
+
+        let details = wollet.get_details(&pset).unwrap();
+        let fee = details.balance.fee;
+        assert_eq!(details.balance.balances[&policy_asset], 30_000 - fee as i64);
+        assert_eq!(details.balance.balances[&asset], 500);
+
+        let mut pset = pset;
+        let tx = wollet.finalize(&mut pset).unwrap();
+        assert!(tx
+            .input
+            .iter()
+            .all(|i| !i.witness.script_witness.is_empty()));
+    }
+}
@@ -3,17 +3,21 @@ use std::collections::{HashMap, HashSet};
 use elements::{
     confidential::{AssetBlindingFactor, Nonce, Value, ValueBlindingFactor},
     issuance::ContractHash,
-    pset::{Output, PartiallySignedTransaction, PsbtSighashType},
+    pset::{elip100::AssetMetadata, Output, PartiallySignedTransaction, PsbtSighashType},
     secp256k1_zkp::{self, ZERO_TWEAK},
-    Address, AssetId, EcdsaSighashType, OutPoint, Script, Transaction,
+    Address, AssetId, EcdsaSighashType, LockTime, OutPoint, Script, Sequence, Transaction,
 };
+use lwk_common::burn_script;
 use rand::thread_rng;
 
 use crate::{
+    clients::try_unblind,
     hashes::Hash,
     liquidex::{self, LiquidexError},
     model::{ExternalUtxo, IssuanceDetails, Recipient},
     pset_create::{validate_address, IssuanceRequest},
+    registry::issuance_ids,
+    spending_path::SpendingPath,
     Contract, ElementsNetwork, Error, LiquidexProposal, UnvalidatedRecipient, Wollet, EC,
 };
 
@@ -33,6 +37,8 @@ pub fn extract_issuances(tx: &Transaction) -> Vec<IssuanceDetails> {
             };
             let token_amount = match txin.asset_issuance.inflation_keys {
                 Value::Explicit(a) => Some(a),
+                // issued without reissuance tokens
+                Value::Null if !is_reissuance => Some(0),
                 _ => None,
             };
             // FIXME: comment if the issuance is blinded
@@ -68,6 +74,8 @@ fn add_external_input(
     // relying on its presence.
     input.in_utxo_rangeproof = txout.witness.rangeproof.take();
     input.witness_utxo = Some(txout);
+    input.redeem_script.clone_from(&utxo.redeem_script);
+    input.witness_script.clone_from(&utxo.witness_script);
 
     pset.add_input(input);
     let idx = pset.inputs().len() - 1;
@@ -75,6 +83,46 @@ fn add_external_input(
     *inp_weight += utxo.max_weight_to_satisfy;
 }
 
+/// The txid of the transaction of `pset` once finalized
+///
+/// The txid commits to the script sigs, which are known in advance only for segwit inputs: empty
+/// for native segwit and the push of the redeem script for P2SH-wrapped segwit.
+fn final_txid(pset: &PartiallySignedTransaction) -> Result<elements::Txid, Error> {
+    let mut tx = pset.extract_tx()?;
+    for (txin, input) in tx.input.iter_mut().zip(pset.inputs()) {
+        let script_pubkey = input.witness_utxo.as_ref().map(|u| &u.script_pubkey);
+        txin.script_sig = match (&input.redeem_script, script_pubkey) {
+            (Some(redeem_script), _) if redeem_script.is_witness_program() => {
+                elements::script::Builder::new()
+                    .push_slice(redeem_script.as_bytes())
+                    .into_script()
+            }
+            (None, Some(script_pubkey)) if script_pubkey.is_witness_program() => Script::new(),
+            _ => return Err(Error::SplitNotSegwit(txin.previous_output)),
+        };
+    }
+    Ok(tx.txid())
+}
+
+/// Set the sequence of the input and the locktime of the transaction to satisfy `path`
+fn set_timelocks(pset: &mut PartiallySignedTransaction, idx: usize, path: &SpendingPath) {
+    let input = &mut pset.inputs_mut()[idx];
+    if let Some(older) = path.older {
+        input.sequence = Some(older);
+    } else if path.after.is_some() {
+        input.sequence = Some(Sequence::ENABLE_RBF_NO_LOCKTIME);
+    }
+    if let Some(after) = path.after {
+        let locktime = &mut pset.global.tx_data.fallback_locktime;
+        if locktime.map_or(true, |l| l.to_consensus_u32() < after.to_consensus_u32()) {
+            *locktime = Some(after);
+        }
+    }
+}
+
+/// The maximum weight of a standard transaction, larger ones are not relayed
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+
 /// A transaction builder
 ///
 /// See [`WolletTxBuilder`] for usage from rust.
@@ -85,18 +133,26 @@ fn add_external_input(
 ///   of the wallet in the finish methods because this it more friendly for bindings implementation.
 ///   Moreover, we could have an alternative finish which don't use a wallet at all.
 /// * We are consuming and returning self to build the tx with method chaining
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TxBuilder {
     network: ElementsNetwork,
     recipients: Vec<Recipient>,
     fee_rate: f32,
     ct_discount: bool,
+    allow_unconfidential: bool,
     issuance_request: IssuanceRequest,
     drain_lbtc: bool,
     drain_to: Option<Address>,
     external_utxos: Vec<ExternalUtxo>,
 
     selected_utxos: Option<Vec<OutPoint>>,
+    recover: bool,
+    locktime: Option<u32>,
+    sequences: HashMap<OutPoint, Sequence>,
+    asset_metadata: HashMap<AssetId, (Contract, OutPoint)>,
+    token_split: Option<(AssetId, Vec<Address>)>,
+    /// The index of the first change address, instead of the last unused, set when splitting
+    first_change_index: Option<u32>,
 
     // LiquiDEX fields
     is_liquidex_make: bool,
@@ -111,11 +167,18 @@ impl TxBuilder {
             recipients: vec![],
             fee_rate: 100.0,
             ct_discount: true,
+            allow_unconfidential: false,
             issuance_request: IssuanceRequest::None,
             drain_lbtc: false,
             drain_to: None,
             external_utxos: vec![],
             selected_utxos: None,
+            recover: false,
+            locktime: None,
+            sequences: HashMap::new(),
+            asset_metadata: HashMap::new(),
+            token_split: None,
+            first_change_index: None,
             is_liquidex_make: false,
             liquidex_proposals: vec![],
         }
@@ -145,7 +208,7 @@ impl TxBuilder {
         mut self,
         recipient: &UnvalidatedRecipient,
     ) -> Result<Self, Error> {
-        let addr: Recipient = recipient.validate(self.network())?;
+        let addr: Recipient = recipient.validate_inner(self.network(), true)?;
         self.recipients.push(addr);
         Ok(self)
     }
@@ -204,6 +267,24 @@ impl TxBuilder {
         self
     }
 
+    /// Allow sending to unconfidential addresses
+    ///
+    /// By default recipients with unconfidential addresses are rejected with
+    /// [`Error::NotConfidentialAddress`], since the asset and the value sent to them are not
+    /// blinded and are visible to everyone.
+    ///
+    /// Note: this policy is checked when the transaction is built, so it can be set at any time.
+    pub fn allow_unconfidential_addresses(mut self) -> Self {
+        self.allow_unconfidential = true;
+        self
+    }
+
+    /// Reject unconfidential addresses when building the transaction (default)
+    pub fn reject_unconfidential_addresses(mut self) -> Self {
+        self.allow_unconfidential = false;
+        self
+    }
+
     /// Issue an asset
     ///
     /// There will be `asset_sats` units of this asset that will be received by
@@ -212,8 +293,12 @@ impl TxBuilder {
     /// There will be `token_sats` reissuance tokens that allow token holder to reissue the created
     /// asset. Reissuance token will be received by `token_receiver` if it's some, or to an
     /// address of the wallet generating the issuance if none.
+    /// If `token_sats` is 0 no reissuance token is created and the asset can never be reissued,
+    /// the issuance amounts are not blinded so anyone can verify the asset supply is fixed.
     ///
     /// If a `contract` is provided, it's metadata will be committed in the generated asset id.
+    /// Once the transaction is broadcast, the asset can be registered with
+    /// [`crate::registry::RegistryPost::from_issuance()`].
     ///
     /// Can't be used if `reissue_asset` has been called
     pub fn issue_asset(
@@ -264,7 +349,7 @@ impl TxBuilder {
         asset_receiver: Option<Address>,
         issuance_tx: Option<Transaction>,
     ) -> Result<Self, Error> {
-        if !matches!(self.issuance_request, IssuanceRequest::None) {
+        if !matches!(self.issuance_request, IssuanceRequest::None) || self.token_split.is_some() {
             return Err(Error::IssuanceAlreadySet);
         }
         if let Some(addr) = asset_receiver.as_ref() {
@@ -282,6 +367,38 @@ impl TxBuilder {
         Ok(self)
     }
 
+    /// Split the reissuance token `token` among `receivers`
+    ///
+    /// All the wallet UTXOs of `token` are spent and their total amount is divided as evenly as
+    /// possible among `receivers`, so that the right to reissue the asset can be distributed
+    /// across several keys, for instance of different custodians. Token amounts are blinded as
+    /// any other output.
+    ///
+    /// Once the transaction is confirmed, each receiver can reissue the asset with
+    /// [`TxBuilder::reissue_asset()`] from its own wallet.
+    ///
+    /// `token` must be the reissuance token of an asset issued by the wallet, otherwise
+    /// [`Error::NotReissuanceToken`] is returned when finishing.
+    ///
+    /// Can't be used if `reissue_asset` has been called
+    pub fn split_reissuance_token(
+        mut self,
+        token: AssetId,
+        receivers: Vec<Address>,
+    ) -> Result<Self, Error> {
+        if matches!(self.issuance_request, IssuanceRequest::Reissuance(..)) {
+            return Err(Error::IssuanceAlreadySet);
+        }
+        if receivers.is_empty() {
+            return Err(Error::InvalidAmount);
+        }
+        for addr in receivers.iter() {
+            validate_address(&addr.to_string(), self.network())?;
+        }
+        self.token_split = Some((token, receivers));
+        Ok(self)
+    }
+
     /// Select all available L-BTC inputs
     pub fn drain_lbtc_wallet(mut self) -> Self {
         self.drain_lbtc = true;
@@ -296,17 +413,27 @@ impl TxBuilder {
 
     /// Adds external UTXOs
     ///
+    /// External UTXOs are not tracked by the wallet descriptor, for instance the lockup output of
+    /// a swap or the outputs of a paper wallet being swept. They are always spent, together with
+    /// the wallet UTXOs needed to fund the recipients, and their amounts in excess go to the
+    /// wallet as change. The signers of the external UTXOs need their redeem or witness script,
+    /// if any.
+    ///
     /// Note: unblinded UTXOs with the same scriptpubkeys as the wallet, are considered external.
     pub fn add_external_utxos(mut self, utxos: Vec<ExternalUtxo>) -> Result<Self, Error> {
-        // TODO: allow for non L-BTC utxos
-        let policy_asset = self.network().policy_asset();
         for utxo in &utxos {
-            if utxo.unblinded.asset != policy_asset {
-                return Err(Error::Generic("External utxos must be L-BTC".to_string()));
+            let duplicate = self
+                .external_utxos
+                .iter()
+                .any(|u| u.outpoint == utxo.outpoint);
+            if duplicate {
+                return Err(Error::Generic(format!(
+                    "External utxo {} added twice",
+                    utxo.outpoint
+                )));
             }
+            self.external_utxos.push(utxo.clone());
         }
-
-        self.external_utxos.extend(utxos);
         Ok(self)
     }
 
@@ -322,6 +449,59 @@ impl TxBuilder {
         self
     }
 
+    /// Set the locktime of the transaction to the block `height`
+    ///
+    /// By default the locktime is set to the height of the wallet tip, to discourage fee sniping.
+    /// If a timelocked spending path requires a higher locktime, the higher one is used.
+    pub fn set_locktime(mut self, height: u32) -> Self {
+        self.locktime = Some(height);
+        self
+    }
+
+    /// Set the sequence of the input spending `outpoint`
+    ///
+    /// By default the inputs have sequence `0xfffffffd`, which enables the locktime and signals
+    /// replaceability (BIP125).
+    /// A relative timelock can be set with [`Sequence::from_height`].
+    ///
+    /// This method never fails, if the outpoint is not spent by the transaction an error is
+    /// raised in [`TxBuilder::finish`].
+    pub fn set_sequence(mut self, outpoint: OutPoint, sequence: Sequence) -> Self {
+        self.sequences.insert(outpoint, sequence);
+        self
+    }
+
+    /// Spend the L-BTC UTXOs using the timelocked spending paths of the wallet descriptor
+    ///
+    /// Only the UTXOs for which a timelocked path is satisfiable at the current wallet tip are
+    /// spent, and the sequence and locktime of the transaction are set accordingly, so that the
+    /// signers of the timelocked path can spend them. For instance with a descriptor created
+    /// with [`lwk_common::inheritance_desc`], the heir can spend the outputs that the owner has
+    /// not moved for the given number of blocks.
+    ///
+    /// If [`TxBuilder::set_wallet_utxos`] is also used, only the given UTXOs are considered.
+    pub fn recover(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Add the metadata of an asset as defined in ELIP-100
+    ///
+    /// If the asset defined by `contract` and `issuance_prevout` is sent or spent by the
+    /// transaction, its contract is added to the PSET global fields, so that signers such as Jade
+    /// can show the asset ticker and precision instead of the asset id. The contract commits to
+    /// the asset id, thus signers can verify the metadata.
+    pub fn add_asset_metadata(
+        mut self,
+        contract: Contract,
+        issuance_prevout: OutPoint,
+    ) -> Result<Self, Error> {
+        let (asset_id, _) = issuance_ids(&contract, issuance_prevout, false)?;
+        self.asset_metadata
+            .insert(asset_id, (contract, issuance_prevout));
+        Ok(self)
+    }
+
     /// Set data to create a PSET from which you
     /// can create a LiquiDEX proposal
     pub fn liquidex_make(
@@ -546,7 +726,7 @@ impl TxBuilder {
         if maker_output_asset != wollet.policy_asset() {
             let satoshi_out = maker_output_satoshi;
             let mut satoshi_in = 0;
-            for utxo in wollet.asset_utxos(&maker_output_asset)? {
+            for utxo in wollet.utxos_for_asset(&maker_output_asset)? {
                 wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
                 let surj_input = elements::SurjectionInput::from_txout_secrets(utxo.unblinded);
                 input_domain.push(surj_input.surjection_target(&EC).expect("from secrets"));
@@ -586,7 +766,7 @@ impl TxBuilder {
         }
 
         // FIXME: For implementation simplicity now we always add all L-BTC inputs
-        for utxo in wollet.asset_utxos(&wollet.policy_asset())? {
+        for utxo in wollet.utxos_for_asset(&wollet.policy_asset())? {
             wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
             let surj_input = elements::SurjectionInput::from_txout_secrets(utxo.unblinded);
             input_domain.push(surj_input.surjection_target(&EC).expect("from secrets"));
@@ -678,8 +858,167 @@ impl TxBuilder {
         Ok(pset)
     }
 
+    /// Estimate the weight of the transaction once signed
+    ///
+    /// The transaction is built (without consuming the builder) and the weight of the blinded
+    /// transaction is added to the maximum weight needed to satisfy every input that is not
+    /// already finalized, according to the wallet descriptor (or the weight specified in the
+    /// [`ExternalUtxo`]). If the CT discount is enabled the discounted weight is returned.
+    ///
+    /// This allows to show the fee before asking for signatures, for instance to a hardware
+    /// signer.
+    pub fn estimated_weight(&self, wollet: &Wollet) -> Result<usize, Error> {
+        let pset = self.clone().finish(wollet)?;
+        self.signed_weight(&pset, wollet, self.ct_discount)
+    }
+
+    /// The weight of `pset` once signed, see [`TxBuilder::estimated_weight()`]
+    fn signed_weight(
+        &self,
+        pset: &PartiallySignedTransaction,
+        wollet: &Wollet,
+        ct_discount: bool,
+    ) -> Result<usize, Error> {
+        let inp_weight: usize = pset
+            .inputs()
+            .iter()
+            .map(|input| {
+                if input.final_script_witness.is_some() {
+                    // Already included in the extracted transaction
+                    return 0;
+                }
+                let outpoint = OutPoint::new(input.previous_txid, input.previous_output_index);
+                self.external_utxos
+                    .iter()
+                    .find(|u| u.outpoint == outpoint)
+                    .map(|u| u.max_weight_to_satisfy)
+                    .unwrap_or_else(|| wollet.max_weight_to_satisfy())
+            })
+            .sum();
+        let tx = pset.extract_tx()?;
+        let tx_weight = if ct_discount {
+            tx.discount_weight()
+        } else {
+            tx.weight()
+        };
+        Ok(inp_weight + tx_weight)
+    }
+
+    /// Estimate the fee (in satoshi of the policy asset) that the transaction will pay
+    ///
+    /// The transaction is built without consuming the builder, the returned value is the fee
+    /// that will be in the transaction returned by [`TxBuilder::finish()`] with the same
+    /// parameters and wallet state.
+    pub fn estimated_fee(&self, wollet: &Wollet) -> Result<u64, Error> {
+        let pset = self.clone().finish(wollet)?;
+        let policy_asset = self.network().policy_asset();
+        Ok(pset
+            .outputs()
+            .iter()
+            .filter(|o| o.script_pubkey.is_empty() && o.asset == Some(policy_asset))
+            .filter_map(|o| o.amount)
+            .sum())
+    }
+
+    /// Finish building the transaction, splitting it if it would not be standard
+    ///
+    /// If the transaction weight once signed exceeds [`MAX_STANDARD_TX_WEIGHT`], for instance
+    /// because of hundreds of recipients, the recipients are split among several transactions.
+    /// Every transaction spends the L-BTC change of the previous one, so they must be broadcast
+    /// in the returned order. Each transaction pays its fee at the given fee rate and, if
+    /// draining, only the last one sends the remaining L-BTC to the drain address. Every
+    /// transaction sends its change to a different change address.
+    ///
+    /// Splitting is supported only for L-BTC recipients, without issuances and LiquiDEX, and only
+    /// spending segwit outputs, native or P2SH-wrapped, so that the txids of the transactions are
+    /// known before they are signed.
+    pub fn finish_package(self, wollet: &Wollet) -> Result<Vec<PartiallySignedTransaction>, Error> {
+        let pset = self.clone().finish(wollet)?;
+        if self.signed_weight(&pset, wollet, false)? <= MAX_STANDARD_TX_WEIGHT {
+            return Ok(vec![pset]);
+        }
+        let policy_asset = self.network().policy_asset();
+        if self.recipients.iter().any(|r| r.asset != policy_asset)
+            || !matches!(self.issuance_request, IssuanceRequest::None)
+            || self.is_liquidex_make
+            || !self.liquidex_proposals.is_empty()
+            || self.recover
+        {
+            return Err(Error::SplitOnlyLbtc);
+        }
+
+        // start from the number of transactions needed if the weight was split evenly, so that
+        // usually only a couple of attempts are blinded
+        let weight = self.signed_weight(&pset, wollet, false)?;
+        let min_chunks = weight.div_ceil(MAX_STANDARD_TX_WEIGHT).max(2);
+        for chunks in min_chunks..=self.recipients.len() {
+            if let Some(package) = self.split(wollet, chunks)? {
+                return Ok(package);
+            }
+        }
+        Err(Error::CannotSplit(MAX_STANDARD_TX_WEIGHT))
+    }
+
+    /// Build a transaction for each of the `chunks` groups of recipients, `None` if any of them
+    /// is not standard
+    fn split(
+        &self,
+        wollet: &Wollet,
+        chunks: usize,
+    ) -> Result<Option<Vec<PartiallySignedTransaction>>, Error> {
+        let chunk_size = self.recipients.len().div_ceil(chunks);
+        let groups: Vec<_> = self.recipients.chunks(chunk_size).collect();
+        let descriptor = wollet.wollet_descriptor();
+        let first_change_index = wollet.change(None)?.index();
+        let mut package = vec![];
+        let mut change: Option<ExternalUtxo> = None;
+        for (i, recipients) in groups.iter().enumerate() {
+            let mut builder = self.clone();
+            builder.recipients = recipients.to_vec();
+            // every transaction has its own change address
+            builder.first_change_index = Some(first_change_index + i as u32);
+            if i + 1 < groups.len() {
+                builder.drain_to = None;
+            }
+            if let Some(change) = change.take() {
+                // Only the change of the previous transaction
+                builder.external_utxos = vec![change];
+                builder.selected_utxos = Some(vec![]);
+                builder.sequences.clear();
+            }
+            let pset = builder.clone().finish(wollet)?;
+            if builder.signed_weight(&pset, wollet, false)? > MAX_STANDARD_TX_WEIGHT {
+                return Ok(None);
+            }
+
+            // L-BTC change and fee are always the last outputs
+            let tx = pset.extract_tx()?;
+            let vout = tx.output.len() - 2;
+            let txout = tx.output[vout].clone();
+            let output = &pset.outputs()[vout];
+            change = Some(ExternalUtxo {
+                outpoint: OutPoint::new(final_txid(&pset)?, vout as u32),
+                unblinded: try_unblind(txout.clone(), &descriptor)?,
+                txout,
+                max_weight_to_satisfy: wollet.max_weight_to_satisfy(),
+                redeem_script: output.redeem_script.clone(),
+                witness_script: output.witness_script.clone(),
+            });
+            package.push(pset);
+        }
+        Ok(Some(package))
+    }
+
     /// Finish building the transaction
     pub fn finish(self, wollet: &Wollet) -> Result<PartiallySignedTransaction, Error> {
+        if !self.allow_unconfidential
+            && self
+                .recipients
+                .iter()
+                .any(|r| r.blinding_pubkey.is_none() && r.script_pubkey != burn_script())
+        {
+            return Err(Error::NotConfidentialAddress);
+        }
         if self.is_liquidex_make {
             return self.finish_liquidex_make(wollet);
         } else if !self.liquidex_proposals.is_empty() {
@@ -688,14 +1027,20 @@ impl TxBuilder {
         // Init PSET
         let mut pset = PartiallySignedTransaction::new_v2();
         let mut inp_txout_sec = HashMap::new();
-        let mut last_unused_internal = wollet.change(None)?.index();
+        let mut last_unused_internal = match self.first_change_index {
+            Some(index) => index,
+            None => wollet.change(None)?.index(),
+        };
         let mut last_unused_external = wollet.address(None)?.index();
 
         let mut inp_weight = 0;
 
         let policy_asset = self.network().policy_asset();
-        let (addressees_lbtc, addressees_asset): (Vec<_>, Vec<_>) = self
-            .recipients
+        let mut recipients = self.recipients;
+        if let Some((token, receivers)) = self.token_split.as_ref() {
+            recipients.extend(token_split_recipients(wollet, token, receivers)?);
+        }
+        let (addressees_lbtc, addressees_asset): (Vec<_>, Vec<_>) = recipients
             .into_iter()
             .partition(|a| a.asset == policy_asset);
 
@@ -704,6 +1049,15 @@ impl TxBuilder {
         if !assets.is_empty() && self.selected_utxos.is_some() {
             return Err(Error::ManualCoinSelectionOnlyLbtc);
         }
+        if !assets.is_empty() && self.recover {
+            return Err(Error::RecoverOnlyLbtc);
+        }
+        let external_assets = self
+            .external_utxos
+            .iter()
+            .map(|u| u.unblinded.asset)
+            .filter(|a| *a != policy_asset);
+        let assets: HashSet<_> = assets.into_iter().chain(external_assets).collect();
         for asset in assets {
             let mut satoshi_out = 0;
             let mut satoshi_in = 0;
@@ -711,22 +1065,29 @@ impl TxBuilder {
                 wollet.add_output(&mut pset, addressee)?;
                 satoshi_out += addressee.satoshi;
             }
-            for utxo in wollet.asset_utxos(&asset)? {
-                wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+            for utxo in self
+                .external_utxos
+                .iter()
+                .filter(|u| u.unblinded.asset == asset)
+            {
+                add_external_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, utxo);
                 satoshi_in += utxo.unblinded.value;
-                if satoshi_in >= satoshi_out {
-                    if satoshi_in > satoshi_out {
-                        let satoshi_change = satoshi_in - satoshi_out;
-                        let addressee = wollet.addressee_change(
-                            satoshi_change,
-                            asset,
-                            &mut last_unused_internal,
-                        )?;
-                        wollet.add_output(&mut pset, &addressee)?;
+            }
+            if satoshi_in < satoshi_out {
+                for utxo in wollet.utxos_for_asset(&asset)? {
+                    wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+                    satoshi_in += utxo.unblinded.value;
+                    if satoshi_in >= satoshi_out {
+                        break;
                     }
-                    break;
                 }
             }
+            if satoshi_in > satoshi_out {
+                let satoshi_change = satoshi_in - satoshi_out;
+                let addressee =
+                    wollet.addressee_change(satoshi_change, asset, &mut last_unused_internal)?;
+                wollet.add_output(&mut pset, &addressee)?;
+            }
             if satoshi_in < satoshi_out {
                 return Err(Error::InsufficientFunds {
                     missing_sats: satoshi_out - satoshi_in,
@@ -755,6 +1116,30 @@ impl TxBuilder {
         }
 
         match self.selected_utxos {
+            _ if self.recover => {
+                let tip_height = wollet.tip().height();
+                for utxo in wollet.utxos_for_asset(&policy_asset)? {
+                    if let Some(coins) = self.selected_utxos.as_ref() {
+                        if !coins.contains(&utxo.outpoint) {
+                            continue;
+                        }
+                    }
+                    let Some(path) = wollet
+                        .spending_paths()?
+                        .into_iter()
+                        .find(|p| p.is_timelocked() && p.is_satisfiable(tip_height, utxo.height))
+                    else {
+                        continue;
+                    };
+                    let idx =
+                        wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+                    set_timelocks(&mut pset, idx, &path);
+                    satoshi_in += utxo.unblinded.value;
+                }
+                if satoshi_in == 0 {
+                    return Err(Error::NoRecoverableUtxos);
+                }
+            }
             Some(coins) => {
                 let utxos = wollet.utxos_map()?;
 
@@ -769,7 +1154,7 @@ impl TxBuilder {
             }
             None => {
                 // FIXME: For implementation simplicity now we always add all L-BTC inputs
-                for utxo in wollet.asset_utxos(&wollet.policy_asset())? {
+                for utxo in wollet.utxos_for_asset(&wollet.policy_asset())? {
                     wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
                     satoshi_in += utxo.unblinded.value;
                 }
@@ -825,6 +1210,9 @@ impl TxBuilder {
                 } else {
                     wollet.issuance(&asset)?
                 };
+                if !issuance.is_reissuable() {
+                    return Err(Error::NotReissuable(asset));
+                }
                 let token = issuance.token;
                 // Find or add input for the token
                 let (idx, token_asset_bf) =
@@ -832,7 +1220,7 @@ impl TxBuilder {
                         Some((idx, u)) => (*idx, u.asset_bf),
                         None => {
                             // Add an input sending the token,
-                            let utxos_token = wollet.asset_utxos(&token)?;
+                            let utxos_token = wollet.utxos_for_asset(&token)?;
                             let utxo_token =
                                 utxos_token
                                     .first()
@@ -942,6 +1330,29 @@ impl TxBuilder {
 
         // TODO inputs/outputs(except fee) randomization, not trivial because of blinder_index on inputs
 
+        // Set sequences and locktime
+        for (outpoint, sequence) in self.sequences.iter() {
+            let input = pset
+                .inputs_mut()
+                .iter_mut()
+                .find(|i| {
+                    i.previous_txid == outpoint.txid && i.previous_output_index == outpoint.vout
+                })
+                .ok_or(Error::SequenceForMissingInput(*outpoint))?;
+            input.sequence = Some(*sequence);
+        }
+        for input in pset.inputs_mut() {
+            if input.sequence.is_none() {
+                input.sequence = Some(Sequence::ENABLE_RBF_NO_LOCKTIME);
+            }
+        }
+        let height = self.locktime.unwrap_or_else(|| wollet.tip().height());
+        let locktime = LockTime::from_height(height)?;
+        let fallback_locktime = &mut pset.global.tx_data.fallback_locktime;
+        if fallback_locktime.map_or(true, |l| l.to_consensus_u32() < height) {
+            *fallback_locktime = Some(locktime);
+        }
+
         // Blind the transaction
         let mut rng = thread_rng();
         pset.blind_last(&mut rng, &EC, &inp_txout_sec)?;
@@ -949,10 +1360,71 @@ impl TxBuilder {
         // Add details to the pset from our descriptor, like bip32derivation and keyorigin
         wollet.add_details(&mut pset)?;
 
+        add_asset_metadata(&mut pset, &self.asset_metadata)?;
+
         Ok(pset)
     }
 }
 
+/// Check that `token` is the reissuance token of an asset issued by `wollet`
+fn check_reissuance_token(wollet: &Wollet, token: &AssetId) -> Result<(), Error> {
+    let issuances = wollet.issuances()?;
+    if issuances
+        .iter()
+        .any(|i| &i.token == token && !i.is_reissuance)
+    {
+        Ok(())
+    } else {
+        Err(Error::NotReissuanceToken(*token))
+    }
+}
+
+/// The recipients receiving the whole wallet balance of `token`, split as evenly as possible
+fn token_split_recipients(
+    wollet: &Wollet,
+    token: &AssetId,
+    receivers: &[Address],
+) -> Result<Vec<Recipient>, Error> {
+    check_reissuance_token(wollet, token)?;
+    let balance: u64 = wollet
+        .utxos_for_asset(token)?
+        .iter()
+        .map(|u| u.unblinded.value)
+        .sum();
+    let n = receivers.len() as u64;
+    if balance < n {
+        // Each receiver gets at least one token
+        return Err(Error::InsufficientFunds {
+            missing_sats: n - balance,
+            asset_id: *token,
+            is_token: true,
+        });
+    }
+    Ok(receivers
+        .iter()
+        .enumerate()
+        .map(|(i, address)| {
+            let satoshi = balance / n + u64::from((i as u64) < balance % n);
+            Recipient::from_address(satoshi, address, *token)
+        })
+        .collect())
+}
+
+/// Add the metadata of the assets in the outputs of `pset`
+fn add_asset_metadata(
+    pset: &mut PartiallySignedTransaction,
+    asset_metadata: &HashMap<AssetId, (Contract, OutPoint)>,
+) -> Result<(), Error> {
+    let assets: HashSet<_> = pset.outputs().iter().filter_map(|o| o.asset).collect();
+    for (asset_id, (contract, issuance_prevout)) in asset_metadata.iter() {
+        if assets.contains(asset_id) {
+            let contract = serde_json::to_string(contract)?;
+            pset.add_asset_metadata(*asset_id, &AssetMetadata::new(contract, *issuance_prevout));
+        }
+    }
+    Ok(())
+}
+
 /// A transaction builder.
 #[derive(Debug)]
 pub struct WolletTxBuilder<'a> {
@@ -974,6 +1446,11 @@ impl<'a> WolletTxBuilder<'a> {
         self.inner.finish(self.wollet)
     }
 
+    /// Wrapper of [`TxBuilder::finish_package()`]
+    pub fn finish_package(self) -> Result<Vec<PartiallySignedTransaction>, Error> {
+        self.inner.finish_package(self.wollet)
+    }
+
     /// Wrapper of [`TxBuilder::add_recipient()`]
     pub fn add_recipient(
         self,
@@ -1057,6 +1534,22 @@ impl<'a> WolletTxBuilder<'a> {
         }
     }
 
+    /// Wrapper of [`TxBuilder::allow_unconfidential_addresses()`]
+    pub fn allow_unconfidential_addresses(self) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.allow_unconfidential_addresses(),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::reject_unconfidential_addresses()`]
+    pub fn reject_unconfidential_addresses(self) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.reject_unconfidential_addresses(),
+        }
+    }
+
     /// Wrapper of [`TxBuilder::issue_asset()`]
     pub fn issue_asset(
         self,
@@ -1128,6 +1621,30 @@ impl<'a> WolletTxBuilder<'a> {
         }
     }
 
+    /// Wrapper of [`TxBuilder::set_locktime()`]
+    pub fn set_locktime(self, height: u32) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.set_locktime(height),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::set_sequence()`]
+    pub fn set_sequence(self, outpoint: OutPoint, sequence: Sequence) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.set_sequence(outpoint, sequence),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::recover()`]
+    pub fn recover(self) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.recover(),
+        }
+    }
+
     /// Wrapper of [`TxBuilder::liquidex_make()`]
     pub fn liquidex_make(
         self,
@@ -1142,6 +1659,41 @@ impl<'a> WolletTxBuilder<'a> {
         })
     }
 
+    /// Wrapper of [`TxBuilder::estimated_weight()`]
+    pub fn estimated_weight(&self) -> Result<usize, Error> {
+        self.inner.estimated_weight(self.wollet)
+    }
+
+    /// Wrapper of [`TxBuilder::estimated_fee()`]
+    pub fn estimated_fee(&self) -> Result<u64, Error> {
+        self.inner.estimated_fee(self.wollet)
+    }
+
+    /// Wrapper of [`TxBuilder::split_reissuance_token()`]
+    pub fn split_reissuance_token(
+        self,
+        token: AssetId,
+        receivers: Vec<Address>,
+    ) -> Result<Self, Error> {
+        check_reissuance_token(self.wollet, &token)?;
+        Ok(Self {
+            wollet: self.wollet,
+            inner: self.inner.split_reissuance_token(token, receivers)?,
+        })
+    }
+
+    /// Wrapper of [`TxBuilder::add_asset_metadata()`]
+    pub fn add_asset_metadata(
+        self,
+        contract: Contract,
+        issuance_prevout: OutPoint,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            wollet: self.wollet,
+            inner: self.inner.add_asset_metadata(contract, issuance_prevout)?,
+        })
+    }
+
     /// Wrapper of [`TxBuilder::liquidex_take()`]
     pub fn liquidex_take(self, proposals: Vec<LiquidexProposal>) -> Result<Self, Error> {
         Ok(Self {
@@ -1153,9 +1705,70 @@ impl<'a> WolletTxBuilder<'a> {
 
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
+    use elements::confidential::{Asset, AssetBlindingFactor, Nonce, Value, ValueBlindingFactor};
     use elements::encode::Decodable;
+    use elements::hashes::Hash;
+    use elements::opcodes::OP_TRUE;
+    use elements::{TxOut, Txid};
 
     use super::*;
+    use crate::clients::blocking::BlockchainBackend;
+    use crate::wollet::tests::{mock_wollet, mock_wollet_from};
+
+    #[test]
+    fn test_external_utxo_asset() {
+        let wollet = crate::wollet::tests::test_wollet_with_many_transactions();
+        let asset = AssetId::from_slice(&[7; 32]).unwrap();
+        let witness_script = elements::script::Builder::new()
+            .push_opcode(OP_TRUE)
+            .into_script();
+        let utxo = ExternalUtxo {
+            outpoint: OutPoint::new(Txid::all_zeros(), 0),
+            txout: TxOut {
+                asset: Asset::Explicit(asset),
+                value: Value::Explicit(5_000),
+                nonce: Nonce::Null,
+                script_pubkey: witness_script.to_v0_p2wsh(),
+                witness: Default::default(),
+            },
+            unblinded: elements::TxOutSecrets::new(
+                asset,
+                AssetBlindingFactor::zero(),
+                5_000,
+                ValueBlindingFactor::zero(),
+            ),
+            max_weight_to_satisfy: 10,
+            redeem_script: None,
+            witness_script: Some(witness_script.clone()),
+        };
+
+        let address = wollet.address(None).unwrap().address().clone();
+        let pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .add_external_utxos(vec![utxo.clone()])
+            .unwrap()
+            .finish()
+            .unwrap();
+        let input = pset
+            .inputs()
+            .iter()
+            .find(|i| i.previous_txid == Txid::all_zeros())
+            .unwrap();
+        assert_eq!(input.witness_script, Some(witness_script));
+
+        // The external asset goes to the wallet as change
+        let details = wollet.get_details(&pset).unwrap();
+        assert_eq!(details.balance.balances.get(&asset), Some(&5_000));
+
+        assert!(wollet
+            .tx_builder()
+            .add_external_utxos(vec![utxo.clone(), utxo])
+            .is_err());
+    }
 
     #[test]
     fn test_extract_issuances() {
@@ -1183,4 +1796,265 @@ mod tests {
         assert_eq!(issuance.asset_amount, Some(1000000000));
         assert_eq!(issuance.token_amount, Some(1));
     }
+
+    #[test]
+    fn test_finish_package() {
+        let (mut wollet, mut client) = mock_wollet();
+        client.fund_wollet(&mut wollet, 10_000_000, None).unwrap();
+
+        let mut builder = wollet.tx_builder();
+        for i in 1..=150 {
+            let address = wollet.address(Some(i)).unwrap();
+            builder = builder
+                .add_lbtc_recipient(address.address(), 1_000)
+                .unwrap();
+        }
+        let package = builder.finish_package().unwrap();
+        assert!(package.len() > 1);
+        let mut n_recipients = 0;
+        for (i, pset) in package.iter().enumerate() {
+            let tx = pset.extract_tx().unwrap();
+            assert!(tx.weight() <= MAX_STANDARD_TX_WEIGHT);
+            n_recipients += tx.output.len() - 2;
+            if i > 0 {
+                // spends the change of the previous transaction
+                let prev = package[i - 1].extract_tx().unwrap();
+                let change = OutPoint::new(prev.txid(), prev.output.len() as u32 - 2);
+                assert_eq!(pset.inputs().len(), 1);
+                assert_eq!(tx.input[0].previous_output, change);
+            }
+        }
+        assert_eq!(n_recipients, 150);
+
+        // a small send is not split
+        let address = wollet.address(Some(1)).unwrap();
+        let package = wollet
+            .tx_builder()
+            .add_lbtc_recipient(address.address(), 1_000)
+            .unwrap()
+            .finish_package()
+            .unwrap();
+        assert_eq!(package.len(), 1);
+    }
+
+    #[test]
+    fn test_finish_package_sign_broadcast() {
+        use lwk_common::Signer;
+
+        // the txids of P2SH-wrapped transactions change once finalized
+        let signer = lwk_signer::SwSigner::new(lwk_test_util::TEST_MNEMONIC, false).unwrap();
+        let desc = lwk_common::singlesig_desc(
+            &signer,
+            lwk_common::Singlesig::ShWpkh,
+            lwk_common::DescriptorBlindingKey::Slip77,
+        )
+        .unwrap();
+        let (mut wollet, mut client) = mock_wollet_from(&desc);
+        let network = wollet.network();
+        client.fund_wollet(&mut wollet, 10_000_000, None).unwrap();
+
+        let (other, _) = lwk_signer::SwSigner::random(false).unwrap();
+        let key = other.xpub().to_pub();
+        let recipient = Address::p2wpkh(&key.into(), Some(key.0), network.address_params());
+        let mut builder = wollet.tx_builder();
+        for _ in 0..150 {
+            builder = builder.add_lbtc_recipient(&recipient, 1_000).unwrap();
+        }
+        let package = builder.finish_package().unwrap();
+        assert!(package.len() > 1);
+
+        let mut prev: Option<Txid> = None;
+        let mut fees = 0;
+        let mut changes = HashSet::new();
+        for mut pset in package {
+            let vout = pset.outputs().len() - 2;
+            let change = &pset.outputs()[vout];
+            assert!(changes.insert(change.script_pubkey.clone()));
+            assert!(change.redeem_script.is_some());
+            fees += pset.outputs()[vout + 1].amount.unwrap();
+
+            assert_eq!(signer.sign(&mut pset).unwrap(), pset.inputs().len() as u32);
+            assert_eq!(
+                wollet.verify_signatures(&pset).unwrap(),
+                pset.inputs().len()
+            );
+            let tx = wollet.finalize(&mut pset).unwrap();
+            if let Some(prev) = prev {
+                // spends the change of the previous transaction, once finalized
+                assert_eq!(
+                    tx.input[0].previous_output,
+                    OutPoint::new(prev, vout as u32)
+                );
+            }
+            prev = Some(client.broadcast(&tx).unwrap());
+        }
+        client.sync(&mut wollet).unwrap();
+        let balance = wollet.balance().unwrap()[&network.policy_asset()];
+        assert_eq!(balance, 10_000_000 - 150 * 1_000 - fees);
+    }
+
+    #[test]
+    fn test_add_asset_metadata() {
+        let (mut wollet, mut client) = mock_wollet();
+        let contract = "{\"entity\":{\"domain\":\"tether.to\"},\"issuer_pubkey\":\"0337cceec0beea0232ebe14cba0197a9fbd45fcf2ec946749de920e71434c2b904\",\"name\":\"Tether USD\",\"precision\":8,\"ticker\":\"USDt\",\"version\":0}";
+        let contract = Contract::from_str(contract).unwrap();
+        let issuance_prevout = OutPoint::new(Txid::all_zeros(), 0);
+        let (asset, _) = issuance_ids(&contract, issuance_prevout, false).unwrap();
+        let address = wollet.address(None).unwrap();
+        client.fund(address.address(), 100_000, None).unwrap();
+        client.fund(address.address(), 1_000, Some(asset)).unwrap();
+        client.sync(&mut wollet).unwrap();
+
+        let address = wollet.address(Some(1)).unwrap();
+        let pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(address.address(), 1_000)
+            .unwrap()
+            .add_asset_metadata(contract.clone(), issuance_prevout)
+            .unwrap()
+            .finish()
+            .unwrap();
+        // the asset is not involved
+        assert!(pset.get_asset_metadata(asset).is_none());
+
+        let pset = wollet
+            .tx_builder()
+            .add_recipient(address.address(), 100, asset)
+            .unwrap()
+            .add_asset_metadata(contract.clone(), issuance_prevout)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let metadata = pset.get_asset_metadata(asset).unwrap().unwrap();
+        assert_eq!(metadata.issuance_prevout(), issuance_prevout);
+        assert_eq!(Contract::from_str(metadata.contract()).unwrap(), contract);
+    }
+
+    #[test]
+    fn test_issue_without_token() {
+        let (mut wollet, mut client) = mock_wollet();
+        client.fund_wollet(&mut wollet, 100_000, None).unwrap();
+
+        let contract = "{\"entity\":{\"domain\":\"tether.to\"},\"issuer_pubkey\":\"0337cceec0beea0232ebe14cba0197a9fbd45fcf2ec946749de920e71434c2b904\",\"name\":\"Tether USD\",\"precision\":8,\"ticker\":\"USDt\",\"version\":0}";
+        let contract = Contract::from_str(contract).unwrap();
+        let pset = wollet
+            .tx_builder()
+            .issue_asset(1_000, None, 0, None, Some(contract.clone()))
+            .unwrap()
+            .finish()
+            .unwrap();
+        let (asset, token) = pset.inputs()[0].issuance_ids();
+        assert!(pset.outputs().iter().all(|o| o.asset != Some(token)));
+
+        let tx = pset.extract_tx().unwrap();
+        let issuance = &extract_issuances(&tx)[0];
+        assert_eq!(issuance.asset, asset);
+        assert_eq!(issuance.token_amount, Some(0));
+        assert!(!issuance.is_reissuable());
+        let err = wollet
+            .tx_builder()
+            .reissue_asset(asset, 1_000, None, Some(tx))
+            .unwrap()
+            .finish()
+            .unwrap_err();
+        assert!(matches!(err, Error::NotReissuable(a) if a == asset));
+
+        let post = crate::registry::RegistryPost::from_issuance(&pset, contract.clone()).unwrap();
+        assert_eq!(post.asset_id(), asset);
+        assert_eq!(post.domain_proof(), contract.domain_proof(asset));
+
+        // the issuance does not commit to another contract
+        let mut other = post.contract().clone();
+        other.ticker = "OTHER".into();
+        let err = crate::registry::RegistryPost::from_issuance(&pset, other).unwrap_err();
+        assert!(matches!(err, Error::MissingIssuance));
+    }
+
+    #[test]
+    fn test_split_reissuance_token() {
+        let (mut wollet, mut client) = mock_wollet();
+        client.fund_wollet(&mut wollet, 100_000, None).unwrap();
+
+        // only the tokens of the assets issued by the wallet can be split
+        let other = AssetId::from_slice(&[7; 32]).unwrap();
+        client.fund_wollet(&mut wollet, 10, Some(other)).unwrap();
+        let receivers = vec![wollet.address(Some(1)).unwrap().address().clone()];
+        let err = wollet
+            .tx_builder()
+            .split_reissuance_token(other, receivers.clone())
+            .unwrap_err();
+        assert!(matches!(err, Error::NotReissuanceToken(a) if a == other));
+
+        let pset = wollet
+            .tx_builder()
+            .issue_asset(1_000, None, 4, None, None)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let (asset, token) = pset.inputs()[0].issuance_ids();
+        // the mock doesn't validate transactions, there is no need to sign it
+        client.broadcast(&pset.extract_tx().unwrap()).unwrap();
+        client.fund_wollet(&mut wollet, 6, Some(token)).unwrap();
+        let err = wollet
+            .tx_builder()
+            .split_reissuance_token(asset, receivers)
+            .unwrap_err();
+        assert!(matches!(err, Error::NotReissuanceToken(a) if a == asset));
+
+        let receivers: Vec<_> = (1..=3)
+            .map(|i| wollet.address(Some(i)).unwrap().address().clone())
+            .collect();
+        let pset = wollet
+            .tx_builder()
+            .split_reissuance_token(token, receivers.clone())
+            .unwrap()
+            .finish()
+            .unwrap();
+        let mut amounts: Vec<_> = pset
+            .outputs()
+            .iter()
+            .filter(|o| o.asset == Some(token))
+            .map(|o| o.amount.unwrap())
+            .collect();
+        amounts.sort();
+        assert_eq!(amounts, vec![3, 3, 4]);
+        let tokens_in: u64 = wollet
+            .get_details(&pset)
+            .unwrap()
+            .balance
+            .balances
+            .get(&token)
+            .copied()
+            .unwrap_or_default() as u64;
+        assert_eq!(tokens_in, 0);
+        assert_eq!(
+            pset.inputs()
+                .iter()
+                .filter(|i| i.asset == Some(token))
+                .count(),
+            2
+        );
+
+        let receivers: Vec<_> = (1..=11)
+            .map(|i| wollet.address(Some(i)).unwrap().address().clone())
+            .collect();
+        let err = wollet
+            .tx_builder()
+            .split_reissuance_token(token, receivers)
+            .unwrap()
+            .finish()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InsufficientFunds {
+                missing_sats: 1,
+                is_token: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            wollet.tx_builder().split_reissuance_token(token, vec![]),
+            Err(Error::InvalidAmount)
+        ));
+    }
 }
@@ -0,0 +1,121 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::elements::{Transaction, Txid};
+use crate::model::TxChain;
+use crate::wollet::Wollet;
+use crate::Error;
+
+impl Wollet {
+    /// The unconfirmed wallet transactions that `txid` spends from, directly or not
+    ///
+    /// These transactions must confirm before or together with `txid`, so their fee and size
+    /// count when estimating the fee rate at which `txid` is mined. Only wallet transactions are
+    /// known, unconfirmed ancestors not involving the wallet are not included.
+    pub fn tx_ancestors(&self, txid: &Txid) -> Result<TxChain, Error> {
+        let tx = self.get_tx(txid)?;
+        let policy_asset = self.policy_asset();
+        let mut chain = TxChain::default();
+        let mut visited = HashSet::from([*txid]);
+        let mut queue = VecDeque::from([tx]);
+        while let Some(tx) = queue.pop_front() {
+            for input in tx.input.iter() {
+                let parent = input.previous_output.txid;
+                if self.is_unconfirmed(&parent) && visited.insert(parent) {
+                    let parent = self.get_tx(&parent)?;
+                    chain.push(&parent, policy_asset);
+                    queue.push_back(parent);
+                }
+            }
+        }
+        Ok(chain)
+    }
+
+    /// The unconfirmed wallet transactions spending from `txid`, directly or not
+    ///
+    /// These transactions are dropped if `txid` is replaced, for instance with
+    /// [`Wollet::bump_fee()`].
+    pub fn tx_descendants(&self, txid: &Txid) -> Result<TxChain, Error> {
+        self.get_tx(txid)?;
+        let policy_asset = self.policy_asset();
+        let unconfirmed: Vec<&Transaction> = self
+            .store
+            .cache
+            .heights
+            .iter()
+            .filter(|(_, height)| height.is_none())
+            .filter_map(|(txid, _)| self.store.cache.all_txs.get(txid))
+            .collect();
+
+        let mut chain = TxChain::default();
+        let mut visited = HashSet::from([*txid]);
+        let mut queue = VecDeque::from([*txid]);
+        while let Some(parent) = queue.pop_front() {
+            for tx in unconfirmed.iter() {
+                let spends_parent = tx.input.iter().any(|i| i.previous_output.txid == parent);
+                let child = tx.txid();
+                if spends_parent && visited.insert(child) {
+                    chain.push(tx, policy_asset);
+                    queue.push_back(child);
+                }
+            }
+        }
+        Ok(chain)
+    }
+
+    fn is_unconfirmed(&self, txid: &Txid) -> bool {
+        matches!(self.store.cache.heights.get(txid), Some(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::test_wollet_with_many_transactions;
+
+    #[test]
+    fn test_tx_chain() {
+        let mut wollet = test_wollet_with_many_transactions();
+        let cache = &wollet.store.cache;
+        let (parent, child) = cache
+            .heights
+            .keys()
+            .filter_map(|txid| {
+                let tx = cache.all_txs.get(txid)?;
+                tx.input
+                    .iter()
+                    .map(|i| i.previous_output.txid)
+                    .find(|p| cache.heights.contains_key(p))
+                    .map(|p| (p, *txid))
+            })
+            .next()
+            .unwrap();
+        let policy_asset = wollet.policy_asset();
+
+        // confirmed transactions have no chains
+        assert!(wollet.tx_ancestors(&child).unwrap().is_empty());
+        assert!(wollet.tx_descendants(&parent).unwrap().is_empty());
+        let missing = Txid::from_raw_hash(elements::hashes::Hash::all_zeros());
+        assert!(matches!(
+            wollet.tx_ancestors(&missing),
+            Err(Error::MissingTransaction)
+        ));
+
+        wollet.store.cache.heights.insert(parent, None);
+        wollet.store.cache.heights.insert(child, None);
+        let parent_tx = wollet.get_tx(&parent).unwrap();
+        let child_tx = wollet.get_tx(&child).unwrap();
+
+        let ancestors = wollet.tx_ancestors(&child).unwrap();
+        assert_eq!(ancestors.txids(), &[parent]);
+        assert_eq!(ancestors.fee(), parent_tx.fee_in(policy_asset));
+        assert_eq!(ancestors.vsize(), parent_tx.discount_vsize());
+
+        let descendants = wollet.tx_descendants(&parent).unwrap();
+        assert_eq!(descendants.txids(), &[child]);
+        assert_eq!(descendants.fee(), child_tx.fee_in(policy_asset));
+        assert_eq!(descendants.vsize(), child_tx.discount_vsize());
+
+        assert!(wollet.tx_ancestors(&parent).unwrap().is_empty());
+        assert!(wollet.tx_descendants(&child).unwrap().is_empty());
+    }
+}
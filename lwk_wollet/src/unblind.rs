@@ -0,0 +1,78 @@
+use crate::elements::confidential::{Asset, Nonce, Value};
+use crate::elements::secp256k1_zkp::SecretKey;
+use crate::elements::{Transaction, TxOut, TxOutSecrets};
+use crate::util::EC;
+use crate::Error;
+use elements_miniscript::confidential::Key;
+use lwk_common::derive_blinding_key_from_key;
+use std::collections::BTreeMap;
+
+/// Unblind a transaction output with the private blinding key of the address receiving it
+///
+/// Fails if the output is not confidential or if the key is not the right one.
+pub fn unblind_output(txout: &TxOut, blinding_privkey: &SecretKey) -> Result<TxOutSecrets, Error> {
+    match (txout.asset, txout.value, txout.nonce) {
+        (Asset::Confidential(_), Value::Confidential(_), Nonce::Confidential(_)) => {
+            Ok(txout.unblind(&EC, *blinding_privkey)?)
+        }
+        _ => Err(Error::Generic(
+            "received unconfidential or null asset/value/nonce".into(),
+        )),
+    }
+}
+
+/// Unblind the outputs of a transaction that can be unblinded with a descriptor blinding key
+///
+/// The private blinding key of every output is derived from the `descriptor_blinding_key` and
+/// the output script pubkey, so the outputs can be unblinded even if the wallet has not
+/// synced them, or if they are sent to addresses not derived from the wallet descriptor.
+///
+/// Returns the secrets of the outputs that could be unblinded, by output index.
+pub fn unblind_transaction(
+    tx: &Transaction,
+    descriptor_blinding_key: &Key,
+) -> Result<BTreeMap<u32, TxOutSecrets>, Error> {
+    let mut result = BTreeMap::new();
+    for (vout, txout) in tx.output.iter().enumerate() {
+        if txout.is_fee() || txout.script_pubkey.is_empty() {
+            continue;
+        }
+        let blinding_privkey =
+            derive_blinding_key_from_key(descriptor_blinding_key, &txout.script_pubkey)
+                .ok_or_else(|| Error::MissingPrivateBlindingKey)?;
+        if let Ok(secrets) = unblind_output(txout, &blinding_privkey) {
+            result.insert(vout as u32, secrets);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wollet::tests::test_wollet_with_many_transactions;
+    use lwk_common::derive_blinding_key;
+
+    #[test]
+    fn test_unblind() {
+        let wollet = test_wollet_with_many_transactions();
+        let key = &wollet.descriptor().key;
+        let txos = wollet.txos().unwrap();
+        assert!(!txos.is_empty());
+        for txo in txos.into_iter().take(10) {
+            let tx = wollet.transaction(&txo.outpoint.txid).unwrap().unwrap().tx;
+            let secrets = unblind_transaction(&tx, key).unwrap();
+            assert_eq!(secrets.get(&txo.outpoint.vout), Some(&txo.unblinded));
+
+            let txout = &tx.output[txo.outpoint.vout as usize];
+            let blinding_privkey =
+                derive_blinding_key(wollet.descriptor(), &txout.script_pubkey).unwrap();
+            assert_eq!(
+                unblind_output(txout, &blinding_privkey).unwrap(),
+                txo.unblinded
+            );
+            let other_privkey = SecretKey::from_slice(&[1u8; 32]).unwrap();
+            assert!(unblind_output(txout, &other_privkey).is_err());
+        }
+    }
+}
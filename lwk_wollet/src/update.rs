@@ -1,7 +1,9 @@
+use crate::clients::try_unblind;
 use crate::descriptor::Chain;
 use crate::elements::{OutPoint, Script, Transaction, TxOutSecrets, Txid};
 use crate::error::Error;
-use crate::store::{Height, Timestamp};
+use crate::store::{Height, RawCache, ScriptHistoryStatus, Timestamp, MAX_HEADERS};
+use crate::unblind::unblind_output;
 use crate::wollet::WolletState;
 use crate::EC;
 use crate::{BlindingPublicKey, Wollet, WolletDescriptor};
@@ -11,9 +13,10 @@ use base64::prelude::*;
 use elements::bitcoin::bip32::ChildNumber;
 use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
 use elements::encode::{Decodable, Encodable};
-use elements::{BlockHeader, TxInWitness, TxOutWitness};
+use elements::secp256k1_zkp::SecretKey;
+use elements::{BlockHash, BlockHeader, TxInWitness, TxOutWitness};
 use rand::{thread_rng, Rng};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::atomic;
 
 /// Transactions downloaded and unblinded
@@ -69,6 +72,17 @@ pub struct Update {
     /// The blinding pubkey is optional for backward compatibility reasons
     pub scripts_with_blinding_pubkey: Vec<(Chain, ChildNumber, Script, Option<BlindingPublicKey>)>,
     pub tip: BlockHeader,
+
+    /// The hashes of the blocks at the heights of the wallet transactions
+    ///
+    /// It also contains the blocks replacing the ones of the wallet in case of a reorg.
+    /// Only serialized from version 3.
+    pub block_hashes: Vec<(Height, BlockHash)>,
+
+    /// The electrum status of the scripts whose history changed, `None` if it's now empty
+    ///
+    /// Only serialized from version 4.
+    pub script_status: Vec<(Script, Option<ScriptHistoryStatus>)>,
 }
 
 impl Update {
@@ -77,7 +91,19 @@ impl Update {
             && self.txid_height_new.is_empty()
             && self.txid_height_delete.is_empty()
             && self.scripts_with_blinding_pubkey.is_empty()
+            && self.script_status.is_empty()
+    }
+    /// The number of blocks of `wollet` replaced by this update, if the chain has been reorganized
+    ///
+    /// Wallet transactions confirmed in the replaced blocks are re-verified by the scan producing
+    /// the update, so their confirmations are rolled back when it's applied. The depth is the
+    /// one observed by the wallet, which only knows the blocks containing its transactions and
+    /// the tip.
+    pub fn reorg_depth(&self, wollet: &Wollet) -> Option<u32> {
+        let height = reorg_height(&wollet.store.cache, self)?;
+        Some(wollet.store.cache.tip.0.saturating_sub(height) + 1)
     }
+
     pub fn prune(&mut self, wallet: &Wollet) {
         self.new_txs.prune(&wallet.store.cache.paths);
     }
@@ -91,20 +117,7 @@ impl Update {
     }
 
     pub fn serialize_encrypted(&self, desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
-        let mut plaintext = self.serialize()?;
-
-        let mut nonce_bytes = [0u8; 12];
-        thread_rng().fill(&mut nonce_bytes);
-        let nonce = GenericArray::from_slice(&nonce_bytes);
-
-        desc.cipher().encrypt_in_place(nonce, b"", &mut plaintext)?;
-        let ciphertext = plaintext;
-
-        let mut result = Vec::with_capacity(ciphertext.len() + 12);
-        result.extend(nonce.as_slice());
-        result.extend(&ciphertext);
-
-        Ok(result)
+        encrypt(self.serialize()?, desc)
     }
 
     pub fn serialize_encrypted_base64(&self, desc: &WolletDescriptor) -> Result<String, Error> {
@@ -113,16 +126,7 @@ impl Update {
     }
 
     pub fn deserialize_decrypted(bytes: &[u8], desc: &WolletDescriptor) -> Result<Update, Error> {
-        let nonce_bytes = &bytes[..12];
-        let mut ciphertext = bytes[12..].to_vec();
-
-        let nonce = GenericArray::from_slice(nonce_bytes);
-
-        desc.cipher()
-            .decrypt_in_place(nonce, b"", &mut ciphertext)?;
-        let plaintext = ciphertext;
-
-        Ok(Update::deserialize(&plaintext)?)
+        Ok(Update::deserialize(&decrypt(bytes, desc)?)?)
     }
 
     pub fn deserialize_decrypted_base64(
@@ -136,6 +140,40 @@ impl Update {
     }
 }
 
+/// Encrypt `plaintext` with the key derived from the descriptor, prefixed by the random nonce
+pub(crate) fn encrypt(mut plaintext: Vec<u8>, desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
+    let mut nonce_bytes = [0u8; 12];
+    thread_rng().fill(&mut nonce_bytes);
+    let nonce = GenericArray::from_slice(&nonce_bytes);
+
+    desc.cipher().encrypt_in_place(nonce, b"", &mut plaintext)?;
+    let ciphertext = plaintext;
+
+    let mut result = Vec::with_capacity(ciphertext.len() + 12);
+    result.extend(nonce.as_slice());
+    result.extend(&ciphertext);
+
+    Ok(result)
+}
+
+/// Decrypt and authenticate the output of [`encrypt()`]
+///
+/// Fails if `bytes` are too short or were not encrypted with the key derived from the descriptor
+/// or were modified.
+pub(crate) fn decrypt(bytes: &[u8], desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
+    if bytes.len() < 12 {
+        return Err(Error::Generic("Encrypted update too short".to_string()));
+    }
+    let nonce_bytes = &bytes[..12];
+    let mut ciphertext = bytes[12..].to_vec();
+
+    let nonce = GenericArray::from_slice(nonce_bytes);
+
+    desc.cipher()
+        .decrypt_in_place(nonce, b"", &mut ciphertext)?;
+    Ok(ciphertext)
+}
+
 impl Wollet {
     pub fn apply_update(&mut self, update: Update) -> Result<(), Error> {
         self.apply_update_inner(update, true)
@@ -145,7 +183,150 @@ impl Wollet {
         self.apply_update_inner(update, false)
     }
 
-    fn apply_update_inner(&mut self, update: Update, do_persist: bool) -> Result<(), Error> {
+    /// Derive the wallet scripts up to `index` (excluded) and persist them with their chain and
+    /// derivation index
+    ///
+    /// Scans cache the scripts up to the gap limit, this extends the cache beyond it, for both
+    /// receiving and change scripts if the descriptor is multipath. Cached scripts are found by
+    /// [`Wollet::is_mine()`] with a lookup and are not derived again by the following scans.
+    ///
+    /// The scripts are persisted as an update, thus the wallet must have been synced at least
+    /// once.
+    pub fn cache_scripts(&mut self, index: u32) -> Result<(), Error> {
+        let tip = self
+            .store
+            .cache
+            .tip_header()
+            .cloned()
+            .ok_or(Error::NotSynced)?;
+        let mut scripts_with_blinding_pubkey = vec![];
+        for descriptor in self.wollet_descriptor().as_single_descriptors()? {
+            let chain: Chain = (&descriptor).try_into().unwrap_or(Chain::External);
+            for j in 0..index {
+                let child = ChildNumber::from_normal_idx(j)?;
+                let (script, blinding_pubkey, cached) =
+                    self.store.get_or_derive(chain, child, &descriptor)?;
+                if !cached {
+                    scripts_with_blinding_pubkey.push((
+                        chain,
+                        child,
+                        script,
+                        Some(blinding_pubkey),
+                    ));
+                }
+            }
+        }
+        if scripts_with_blinding_pubkey.is_empty() {
+            return Ok(());
+        }
+        let update = Update {
+            version: 4,
+            wollet_status: self.status(),
+            new_txs: DownloadTxResult::default(),
+            txid_height_new: vec![],
+            txid_height_delete: vec![],
+            timestamps: vec![],
+            scripts_with_blinding_pubkey,
+            tip,
+            block_hashes: vec![],
+            script_status: vec![],
+        };
+        self.apply_update(update)
+    }
+
+    /// Try again to unblind the [`Wollet::unblindable_outputs()`]
+    ///
+    /// Each output is unblinded with the wallet blinding key, which might not have been available
+    /// when the output was scanned, or with one of `blinding_keys`, for outputs blinded by the
+    /// sender with a different key. The unblinded outputs are persisted as an update and become
+    /// part of the wallet balance and UTXOs, they are returned.
+    pub fn retry_unblind(&mut self, blinding_keys: &[SecretKey]) -> Result<Vec<OutPoint>, Error> {
+        let descriptor = self.wollet_descriptor();
+        let mut unblinds = vec![];
+        for output in self.unblindable_outputs()? {
+            let unblinded = try_unblind(output.txout.clone(), &descriptor).or_else(|e| {
+                blinding_keys
+                    .iter()
+                    .find_map(|key| unblind_output(&output.txout, key).ok())
+                    .ok_or(e)
+            });
+            match unblinded {
+                Ok(unblinded) => unblinds.push((output.outpoint, unblinded)),
+                Err(_) => tracing::info!("{} still cannot unblind", output.outpoint),
+            }
+        }
+        if unblinds.is_empty() {
+            return Ok(vec![]);
+        }
+        let tip = self
+            .store
+            .cache
+            .tip_header()
+            .cloned()
+            .ok_or(Error::NotSynced)?;
+        let outpoints = unblinds.iter().map(|(outpoint, _)| *outpoint).collect();
+        let update = Update {
+            version: 4,
+            wollet_status: self.status(),
+            new_txs: DownloadTxResult {
+                txs: vec![],
+                unblinds,
+            },
+            txid_height_new: vec![],
+            txid_height_delete: vec![],
+            timestamps: vec![],
+            scripts_with_blinding_pubkey: vec![],
+            tip,
+            block_hashes: vec![],
+            script_status: vec![],
+        };
+        self.apply_update(update)?;
+        Ok(outpoints)
+    }
+
+    /// Unblind an [`Update`] created by a scanning service using the
+    /// [`WolletDescriptor::scanning_descriptor()`] of this wallet
+    ///
+    /// The update scripts are checked to be the wallet ones, the outputs of the update
+    /// transactions are unblinded with the wallet blinding key and the blinding public keys are
+    /// replaced. The returned update can be applied with [`Wollet::apply_update()`].
+    ///
+    /// The update status is set to the wallet one: the service scans with a wallet mirroring this
+    /// one, so its updates must be unblinded and applied in the order they are created.
+    pub fn unblind_scanned_update(&self, mut update: Update) -> Result<Update, Error> {
+        let descriptor = self.wollet_descriptor();
+        let mut scripts: HashSet<Script> = self.store.cache.paths.keys().cloned().collect();
+        for (chain, child, script, blinding_pubkey) in
+            update.scripts_with_blinding_pubkey.iter_mut()
+        {
+            if &descriptor.script_pubkey(*chain, (*child).into())? != script {
+                return Err(Error::ScannedUpdateDescriptorMismatch);
+            }
+            *blinding_pubkey = None;
+            scripts.insert(script.clone());
+        }
+
+        let mut unblinds = vec![];
+        for (txid, tx) in update.new_txs.txs.iter() {
+            for (vout, output) in tx.output.iter().enumerate() {
+                if !scripts.contains(&output.script_pubkey) {
+                    continue;
+                }
+                let outpoint = OutPoint::new(*txid, vout as u32);
+                match try_unblind(output.clone(), &descriptor) {
+                    Ok(unblinded) => unblinds.push((outpoint, unblinded)),
+                    Err(_) => {
+                        tracing::info!("{} cannot unblind, see the unblindable outputs", outpoint)
+                    }
+                }
+            }
+        }
+        update.new_txs.unblinds = unblinds;
+        update.wollet_status = self.status();
+        Ok(update)
+    }
+
+    fn apply_update_inner(&mut self, mut update: Update, do_persist: bool) -> Result<(), Error> {
         // TODO should accept &Update
 
         if update.wollet_status != 0 {
@@ -158,6 +339,33 @@ impl Wollet {
             }
         }
         let descriptor = self.wollet_descriptor();
+        if do_persist {
+            // persist the scripts derived by lookups along with the update
+            let derived: Vec<_> = {
+                let derived = self
+                    .store
+                    .cache
+                    .derived
+                    .lock()
+                    .map_err(|_| Error::LockPoisoned)?;
+                derived
+                    .iter()
+                    .filter(|(script, _)| {
+                        !update
+                            .scripts_with_blinding_pubkey
+                            .iter()
+                            .any(|(_, _, s, _)| s == *script)
+                    })
+                    .map(|(script, (chain, child))| (*chain, *child, script.clone(), None))
+                    .collect()
+            };
+            let derived = compute_blinding_pubkey_if_missing(derived, descriptor.clone())?;
+            update.scripts_with_blinding_pubkey.extend(
+                derived
+                    .into_iter()
+                    .map(|(chain, child, script, pubkey)| (chain, child, script, Some(pubkey))),
+            );
+        }
         let store = &mut self.store;
         let Update {
             version: _,
@@ -168,22 +376,55 @@ impl Wollet {
             timestamps,
             scripts_with_blinding_pubkey,
             tip,
+            block_hashes,
+            script_status,
         } = update.clone();
 
         let scripts_with_blinding_pubkey =
             compute_blinding_pubkey_if_missing(scripts_with_blinding_pubkey, descriptor)?;
 
-        if tip.height + 1 < store.cache.tip.0 {
-            // Checking we are not applying an old update while giving enough space for a single block reorg
+        let reorg_height = reorg_height(&store.cache, &update);
+        // a lower tip on the stored chain comes from an old update, on another chain it's followed
+        let ancestor = store.cache.headers.get(&tip.height) == Some(&tip);
+        if tip.height < store.cache.tip.0
+            && reorg_height.is_none()
+            && (ancestor || tip.height + 1 < store.cache.tip.0)
+        {
             return Err(Error::UpdateHeightTooOld {
                 update_tip_height: tip.height,
                 store_tip_height: store.cache.tip.0,
             });
         }
 
+        if let Some(reorg_height) = reorg_height {
+            // the replaced blocks are downloaded again by the scan, when still containing wallet txs
+            store.cache.block_hashes.retain(|h, _| *h < reorg_height);
+            store.cache.timestamps.retain(|h, _| *h < reorg_height);
+            store.cache.headers.retain(|h, _| *h < reorg_height);
+        }
+        store.cache.block_hashes.extend(block_hashes);
+        for (script, status) in script_status {
+            match status {
+                Some(status) => store.cache.script_status.insert(script, status),
+                None => store.cache.script_status.remove(&script),
+            };
+        }
         store.cache.tip = (tip.height, tip.block_hash());
+        store.cache.headers.retain(|h, _| *h < tip.height);
+        store.cache.headers.insert(tip.height, tip);
+        retain_connected(&mut store.cache.headers);
+        for (outpoint, secrets) in new_txs.unblinds.iter() {
+            store
+                .cache
+                .unblinded_by_asset
+                .entry(secrets.asset)
+                .or_default()
+                .insert(*outpoint);
+        }
         store.cache.unblinded.extend(new_txs.unblinds);
         store.cache.all_txs.extend(new_txs.txs);
+        // before deleting, since the conflicted transactions are usually deleted as well
+        mark_conflicted(&mut store.cache, &txid_height_new);
         store
             .cache
             .heights
@@ -249,12 +490,111 @@ impl Wollet {
 
         if do_persist {
             self.persister.push(update)?;
+            let paths = &self.store.cache.paths;
+            self.store
+                .cache
+                .derived
+                .lock()
+                .map_err(|_| Error::LockPoisoned)?
+                .retain(|script, _| !paths.contains_key(script));
         }
 
         Ok(())
     }
 }
 
+/// The lowest height at which `update` has a block different from the one stored in `cache`
+///
+/// The blocks of the update are the ones containing wallet transactions, its tip and the parent
+/// of its tip, which must be the stored header at the previous height, if any.
+fn reorg_height(cache: &RawCache, update: &Update) -> Option<Height> {
+    let (tip_height, tip_hash) = cache.tip;
+    if tip_height == 0 {
+        return None;
+    }
+    let parent = update
+        .tip
+        .height
+        .checked_sub(1)
+        .map(|height| (height, update.tip.prev_blockhash));
+    update
+        .block_hashes
+        .iter()
+        .cloned()
+        .chain(std::iter::once((
+            update.tip.height,
+            update.tip.block_hash(),
+        )))
+        .chain(parent)
+        .filter(|(height, hash)| {
+            let stored = if *height == tip_height {
+                Some(tip_hash)
+            } else if let Some(header) = cache.headers.get(height) {
+                Some(header.block_hash())
+            } else {
+                cache.block_hashes.get(height).cloned()
+            };
+            stored.is_some_and(|stored| stored != *hash)
+        })
+        .map(|(height, _)| height)
+        .min()
+}
+
+/// Keep the last [`MAX_HEADERS`] headers connected to the tip, without gaps
+///
+/// The headers below a missing height or not matching the parent hash of the following header
+/// are removed.
+fn retain_connected(headers: &mut BTreeMap<Height, BlockHeader>) {
+    let mut first = None;
+    let mut next: Option<&BlockHeader> = None;
+    for (height, header) in headers.iter().rev().take(MAX_HEADERS) {
+        if let Some(next) = next {
+            if next.height != height + 1 || next.prev_blockhash != header.block_hash() {
+                break;
+            }
+        }
+        first = Some(*height);
+        next = Some(header);
+    }
+    if let Some(first) = first {
+        headers.retain(|h, _| *h >= first);
+    }
+}
+
+/// Remove the stored unconfirmed transactions double spent by the transactions of the update
+///
+/// A transaction of the update is either confirmed or it has replaced the stored one in the
+/// mempool, so the stored one can't confirm anymore.
+fn mark_conflicted(cache: &mut RawCache, txid_height_new: &[(Txid, Option<Height>)]) {
+    for (txid, _) in txid_height_new {
+        cache.conflicted.remove(txid);
+    }
+    let new_txids: HashSet<_> = txid_height_new.iter().map(|(txid, _)| *txid).collect();
+    let stored_unconfirmed: Vec<Txid> = cache
+        .heights
+        .iter()
+        .filter(|(txid, height)| height.is_none() && !new_txids.contains(*txid))
+        .map(|(txid, _)| *txid)
+        .collect();
+    for stored in stored_unconfirmed {
+        let Some(stored_tx) = cache.all_txs.get(&stored) else {
+            continue;
+        };
+        let spent: HashSet<_> = stored_tx.input.iter().map(|i| i.previous_output).collect();
+        let conflicting = new_txids.iter().find(|txid| {
+            cache
+                .all_txs
+                .get(*txid)
+                .is_some_and(|tx| tx.input.iter().any(|i| spent.contains(&i.previous_output)))
+        });
+        if let Some(conflicting) = conflicting {
+            tracing::info!("{stored} is conflicted by {conflicting}");
+            cache.conflicted.insert(stored, *conflicting);
+            cache.heights.remove(&stored);
+        }
+    }
+}
+
 fn compute_blinding_pubkey_if_missing(
     scripts_with_blinding_pubkey: Vec<(
         Chain,
@@ -454,6 +794,35 @@ impl Encodable for Update {
         }
         bytes_written += self.tip.consensus_encode(&mut w)?;
 
+        if self.version >= 3 {
+            bytes_written += elements::encode::VarInt(self.block_hashes.len() as u64)
+                .consensus_encode(&mut w)?;
+            for (height, block_hash) in self.block_hashes.iter() {
+                bytes_written += height.consensus_encode(&mut w)?;
+                bytes_written += block_hash.consensus_encode(&mut w)?;
+            }
+        }
+
+        if self.version >= 4 {
+            bytes_written += elements::encode::VarInt(self.script_status.len() as u64)
+                .consensus_encode(&mut w)?;
+            for (script, status) in self.script_status.iter() {
+                bytes_written += script.consensus_encode(&mut w)?;
+                match status {
+                    Some(status) => {
+                        bytes_written += 1u8.consensus_encode(&mut w)?;
+                        bytes_written += status.status.consensus_encode(&mut w)?;
+                        bytes_written += elements::encode::VarInt(status.txids.len() as u64)
+                            .consensus_encode(&mut w)?;
+                        for txid in status.txids.iter() {
+                            bytes_written += txid.consensus_encode(&mut w)?;
+                        }
+                    }
+                    None => bytes_written += 0u8.consensus_encode(&mut w)?,
+                }
+            }
+        }
+
         Ok(bytes_written)
     }
 }
@@ -466,7 +835,7 @@ impl Decodable for Update {
         }
 
         let version = u8::consensus_decode(&mut d)?;
-        if version > 2 {
+        if version > 4 {
             return Err(elements::encode::Error::ParseFailed("Unsupported version"));
         }
         let wollet_status = if version >= 1 {
@@ -522,7 +891,7 @@ impl Decodable for Update {
                     _ => return Err(elements::encode::Error::ParseFailed("Invalid chain")),
                 };
                 let child_number: ChildNumber = u32::consensus_decode(&mut d)?.into();
-                let blinding_pubkey = if version == 2 {
+                let blinding_pubkey = if version >= 2 {
                     Some(BlindingPublicKey::consensus_decode(&mut d)?)
                 } else {
                     None
@@ -534,6 +903,48 @@ impl Decodable for Update {
 
         let tip = BlockHeader::consensus_decode(&mut d)?;
 
+        let block_hashes = if version >= 3 {
+            let len = elements::encode::VarInt::consensus_decode(&mut d)?.0;
+            let mut vec = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                let h = u32::consensus_decode(&mut d)?;
+                let b = BlockHash::consensus_decode(&mut d)?;
+                vec.push((h, b));
+            }
+            vec
+        } else {
+            vec![]
+        };
+
+        let script_status = if version >= 4 {
+            let len = elements::encode::VarInt::consensus_decode(&mut d)?.0;
+            let mut vec = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                let script = Script::consensus_decode(&mut d)?;
+                let status = match u8::consensus_decode(&mut d)? {
+                    0 => None,
+                    1 => {
+                        let status = <[u8; 32]>::consensus_decode(&mut d)?;
+                        let len = elements::encode::VarInt::consensus_decode(&mut d)?.0;
+                        let mut txids = Vec::with_capacity(len as usize);
+                        for _ in 0..len {
+                            txids.push(Txid::consensus_decode(&mut d)?);
+                        }
+                        Some(ScriptHistoryStatus { status, txids })
+                    }
+                    _ => {
+                        return Err(elements::encode::Error::ParseFailed(
+                            "Invalid script status",
+                        ))
+                    }
+                };
+                vec.push((script, status));
+            }
+            vec
+        } else {
+            vec![]
+        };
+
         Ok(Self {
             version,
             wollet_status,
@@ -543,6 +954,8 @@ impl Decodable for Update {
             timestamps,
             scripts_with_blinding_pubkey,
             tip,
+            block_hashes,
+            script_status,
         })
     }
 }
@@ -557,7 +970,10 @@ mod test {
 
     use crate::{update::DownloadTxResult, Chain, Update, Wollet, WolletDescriptor};
 
-    use super::EncodableTxOutSecrets;
+    use super::{EncodableTxOutSecrets, ScriptHistoryStatus, SecretKey, EC};
+    use crate::clients::blocking::BlockchainBackend;
+    use crate::store::BATCH_SIZE;
+    use crate::wollet::tests::mock_wollet;
 
     pub fn download_tx_result_test_vector() -> DownloadTxResult {
         // there are issue in moving this in test_util
@@ -583,6 +999,8 @@ mod test {
             timestamps: Default::default(),
             scripts_with_blinding_pubkey: Default::default(),
             tip,
+            block_hashes: vec![],
+            script_status: vec![],
             wollet_status: 1,
         };
         assert!(update.only_tip());
@@ -592,6 +1010,50 @@ mod test {
         assert!(!update.only_tip());
     }
 
+    #[test]
+    fn test_conflicted() {
+        let mut wollet = crate::wollet::tests::test_wollet_with_many_transactions();
+        let txs = wollet.transactions().unwrap();
+        let stored = txs.iter().find(|tx| !tx.outputs.is_empty()).unwrap();
+        let stored_txid = stored.txid;
+        wollet.store.cache.heights.insert(stored_txid, None);
+        assert!(wollet
+            .txos()
+            .unwrap()
+            .iter()
+            .any(|txo| txo.outpoint.txid == stored_txid));
+
+        let mut conflicting = stored.tx.clone();
+        conflicting.lock_time = elements::LockTime::from_height(1).unwrap();
+        let conflicting_txid = conflicting.txid();
+        let mut tip = lwk_test_util::liquid_block_1().header;
+        tip.height = wollet.tip().height();
+        let update = Update {
+            version: 1,
+            new_txs: DownloadTxResult {
+                txs: vec![(conflicting_txid, conflicting)],
+                unblinds: vec![],
+            },
+            txid_height_new: vec![(conflicting_txid, None)],
+            txid_height_delete: vec![stored_txid],
+            timestamps: Default::default(),
+            scripts_with_blinding_pubkey: Default::default(),
+            tip,
+            block_hashes: vec![],
+            script_status: vec![],
+            wollet_status: 0,
+        };
+        wollet.apply_update_no_persist(update).unwrap();
+
+        assert_eq!(wollet.conflicted_by(&stored_txid), Some(conflicting_txid));
+        assert!(wollet.transaction(&stored_txid).unwrap().is_none());
+        assert!(wollet
+            .txos()
+            .unwrap()
+            .iter()
+            .all(|txo| txo.outpoint.txid != stored_txid));
+    }
+
     #[test]
     fn test_tx_out_secrets_roundtrip() {
         let secret = EncodableTxOutSecrets {
@@ -637,6 +1099,8 @@ mod test {
             timestamps: vec![(12, 44), (12, 44)],
             scripts_with_blinding_pubkey,
             tip,
+            block_hashes: vec![],
+            script_status: vec![],
             wollet_status: 1,
         };
 
@@ -669,6 +1133,45 @@ mod test {
         assert_eq!(upd_from_v0, upd_from_v1);
     }
 
+    #[test]
+    fn test_update_v3() {
+        let mut update =
+            Update::deserialize(&lwk_test_util::update_test_vector_v1_bytes()).unwrap();
+        update.scripts_with_blinding_pubkey.clear(); // the blinding pubkeys are required from v2
+        update.version = 2;
+        update.block_hashes = vec![(12, update.tip.block_hash())];
+        // Block hashes are serialized only from version 3
+        let back = Update::deserialize(&update.serialize().unwrap()).unwrap();
+        assert!(back.block_hashes.is_empty());
+
+        update.version = 3;
+        let back = Update::deserialize(&update.serialize().unwrap()).unwrap();
+        assert_eq!(update, back);
+    }
+
+    #[test]
+    fn test_update_v4() {
+        let mut update =
+            Update::deserialize(&lwk_test_util::update_test_vector_v1_bytes()).unwrap();
+        update.scripts_with_blinding_pubkey.clear();
+        update.version = 3;
+        let status = ScriptHistoryStatus {
+            status: [7; 32],
+            txids: vec![lwk_test_util::txid_test_vector()],
+        };
+        update.script_status = vec![
+            (Script::from(vec![0x51]), Some(status)),
+            (Script::from(vec![0x52]), None),
+        ];
+        // Script statuses are serialized only from version 4
+        let back = Update::deserialize(&update.serialize().unwrap()).unwrap();
+        assert!(back.script_status.is_empty());
+
+        update.version = 4;
+        let back = Update::deserialize(&update.serialize().unwrap()).unwrap();
+        assert_eq!(update, back);
+    }
+
     #[test]
     fn test_update_decription() {
         let update = Update::deserialize(&lwk_test_util::update_test_vector_bytes()).unwrap();
@@ -712,4 +1215,286 @@ mod test {
         assert_eq!(update.new_txs.txs.len(), update_pruned.new_txs.txs.len());
         assert_eq!(update.new_txs.unblinds, update_pruned.new_txs.unblinds);
     }
+
+    #[test]
+    fn test_scanning_descriptor() {
+        use crate::clients::blocking::{BlockchainBackend, MockBackend};
+        use base64::Engine;
+        use std::io::Read;
+        use std::str::FromStr;
+
+        let network = crate::ElementsNetwork::default_regtest();
+        let policy_asset = network.policy_asset();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let mut wollet = Wollet::without_persist(network, desc.clone()).unwrap();
+
+        let scanning_desc = desc.scanning_descriptor().unwrap();
+        assert!(scanning_desc.is_elip151());
+        assert!(!scanning_desc.to_string().contains("slip77"));
+        let err = scanning_desc.scanning_descriptor().unwrap_err();
+        assert!(matches!(err, crate::Error::ScanningDescriptorWithElip151));
+
+        let identity = age::x25519::Identity::generate();
+        let encrypted = desc
+            .encrypted_scanning_descriptor(identity.to_public())
+            .unwrap();
+        let encrypted = base64::prelude::BASE64_STANDARD_NO_PAD
+            .decode(encrypted)
+            .unwrap();
+        let decryptor = age::Decryptor::new(&encrypted[..]).unwrap();
+        let mut decrypted = String::new();
+        decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .unwrap()
+            .read_to_string(&mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, scanning_desc.to_string());
+
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap().address().clone();
+        client.fund(&address, 10_000, None).unwrap();
+        client.mine(1);
+
+        // The scanning service finds the transaction but cannot unblind it
+        let mut scanner = Wollet::without_persist(network, scanning_desc).unwrap();
+        let update = client.full_scan(&scanner).unwrap().unwrap();
+        assert_eq!(update.new_txs.txs.len(), 1);
+        assert!(update.new_txs.unblinds.is_empty());
+        scanner.apply_update(update.clone()).unwrap();
+        assert!(scanner.utxos().unwrap().is_empty());
+
+        // Another wallet cannot use the update
+        let other: WolletDescriptor = lwk_test_util::wollet_descriptor_many_transactions()
+            .parse()
+            .unwrap();
+        let other = Wollet::without_persist(network, other).unwrap();
+        let err = other.unblind_scanned_update(update.clone()).unwrap_err();
+        assert!(matches!(err, crate::Error::ScannedUpdateDescriptorMismatch));
+
+        let update = wollet.unblind_scanned_update(update).unwrap();
+        wollet.apply_update(update).unwrap();
+        assert_eq!(wollet.balance().unwrap()[&policy_asset], 10_000);
+        assert_eq!(wollet.address(None).unwrap().index(), 1);
+    }
+
+    #[test]
+    fn test_cache_scripts() {
+        use crate::clients::blocking::{BlockchainBackend, MockBackend};
+        use std::str::FromStr;
+
+        let network = crate::ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let mut wollet = Wollet::with_fs_persist(network, desc.clone(), &tempdir).unwrap();
+        let err = wollet.cache_scripts(100).unwrap_err();
+        assert!(matches!(err, crate::Error::NotSynced));
+
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap().address().clone();
+        client.fund(&address, 10_000, None).unwrap();
+        client.sync(&mut wollet).unwrap();
+
+        // beyond the gap limit
+        let external = wollet.address(Some(99)).unwrap().address().script_pubkey();
+        let internal = wollet.change(Some(99)).unwrap().address().script_pubkey();
+        assert!(wollet.is_mine(&external).unwrap().is_none());
+        assert!(wollet.is_mine(&internal).unwrap().is_none());
+
+        wollet.cache_scripts(100).unwrap();
+        let info = wollet.is_mine(&external).unwrap().unwrap();
+        assert_eq!((info.chain(), info.index()), (Chain::External, 99));
+        let info = wollet.is_mine(&internal).unwrap().unwrap();
+        assert_eq!((info.chain(), info.index()), (Chain::Internal, 99));
+        let status = wollet.status();
+
+        // already cached, nothing to persist
+        wollet.cache_scripts(50).unwrap();
+        assert_eq!(wollet.status(), status);
+
+        // the scripts are persisted and not derived again by the scans
+        let mut wollet = Wollet::with_fs_persist(network, desc, &tempdir).unwrap();
+        assert_eq!(wollet.status(), status);
+        let info = wollet.is_mine(&internal).unwrap().unwrap();
+        assert_eq!((info.chain(), info.index()), (Chain::Internal, 99));
+        client.fund(&address, 10_000, None).unwrap();
+        let update = client.full_scan(&wollet).unwrap().unwrap();
+        assert!(update.scripts_with_blinding_pubkey.is_empty());
+        wollet.apply_update(update).unwrap();
+        assert_eq!(wollet.balance().unwrap()[&network.policy_asset()], 20_000);
+    }
+
+    #[test]
+    fn test_is_mine_caches_derived_scripts() {
+        use crate::clients::blocking::MockBackend;
+        use std::str::FromStr;
+
+        let network = crate::ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let mut wollet = Wollet::with_fs_persist(network, desc.clone(), &tempdir).unwrap();
+
+        // not scanned yet, derived and cached by the lookup
+        let script = wollet.change(Some(5)).unwrap().address().script_pubkey();
+        let info = wollet.is_mine(&script).unwrap().unwrap();
+        assert_eq!((info.chain(), info.index()), (Chain::Internal, 5));
+        assert!(!wollet.store.cache.paths.contains_key(&script));
+        assert_eq!(
+            wollet.store.cache.derived.lock().unwrap().len(),
+            BATCH_SIZE as usize + 6
+        );
+        assert_eq!(wollet.index(&script).unwrap(), (Chain::Internal, 5));
+        let other = wollet.change(Some(3)).unwrap().address().script_pubkey();
+        assert!(wollet.is_mine(&other).unwrap().is_some());
+        assert_eq!(
+            wollet.store.cache.derived.lock().unwrap().len(),
+            BATCH_SIZE as usize + 6
+        );
+
+        // moved to the persisted scripts by the next update
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap().address().clone();
+        client.fund(&address, 10_000, None).unwrap();
+        client.sync(&mut wollet).unwrap();
+        assert!(wollet.store.cache.paths.contains_key(&script));
+        assert!(wollet.store.cache.derived.lock().unwrap().is_empty());
+        let wollet = Wollet::with_fs_persist(network, desc, &tempdir).unwrap();
+        assert!(wollet.store.cache.paths.contains_key(&script));
+        assert_eq!(wollet.balance().unwrap()[&network.policy_asset()], 10_000);
+    }
+
+    #[test]
+    fn test_retry_unblind() {
+        use crate::clients::blocking::MockBackend;
+        use std::str::FromStr;
+
+        let network = crate::ElementsNetwork::default_regtest();
+        let policy_asset = network.policy_asset();
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let tempdir = tempfile::TempDir::new().unwrap();
+        let mut wollet = Wollet::with_fs_persist(network, desc.clone(), &tempdir).unwrap();
+        let mut client = MockBackend::new(network);
+        let address = wollet.address(None).unwrap().address().clone();
+        client.fund(&address, 10_000, None).unwrap();
+
+        // The sender blinds to the wallet script with another key
+        let other_key = SecretKey::from_slice(&[7; 32]).unwrap();
+        let other_pubkey = elements::secp256k1_zkp::PublicKey::from_secret_key(&EC, &other_key);
+        let script_pubkey = wollet.address(Some(1)).unwrap().address().script_pubkey();
+        let params = network.address_params();
+        let external = elements::Address::from_script(&script_pubkey, Some(other_pubkey), params);
+        let txid = client.fund(&external.unwrap(), 5_000, None).unwrap();
+        client.sync(&mut wollet).unwrap();
+        assert_eq!(wollet.balance().unwrap()[&policy_asset], 10_000);
+        let unblindable = wollet.unblindable_outputs().unwrap();
+        assert_eq!(unblindable.len(), 1);
+        assert_eq!(unblindable[0].outpoint.txid, txid);
+        assert_eq!(unblindable[0].txout.script_pubkey, script_pubkey);
+        assert_eq!(
+            (unblindable[0].ext_int, unblindable[0].wildcard_index),
+            (Chain::External, 1)
+        );
+        assert!(!unblindable[0].is_spent);
+
+        // Nothing to persist if the outputs still cannot be unblinded
+        let status = wollet.status();
+        let wrong_key = SecretKey::from_slice(&[8; 32]).unwrap();
+        assert!(wollet.retry_unblind(&[wrong_key]).unwrap().is_empty());
+        assert_eq!(wollet.status(), status);
+
+        let unblinded = wollet.retry_unblind(&[wrong_key, other_key]).unwrap();
+        assert_eq!(unblinded, vec![unblindable[0].outpoint]);
+        assert!(wollet.unblindable_outputs().unwrap().is_empty());
+        assert_eq!(wollet.balance().unwrap()[&policy_asset], 15_000);
+
+        // The unblinded output is persisted
+        let wollet = Wollet::with_fs_persist(network, desc, &tempdir).unwrap();
+        assert!(wollet.unblindable_outputs().unwrap().is_empty());
+        assert_eq!(wollet.balance().unwrap()[&policy_asset], 15_000);
+    }
+
+    #[test]
+    fn test_reorg() {
+        let (mut wollet, mut client) = mock_wollet();
+        let address = wollet.address(None).unwrap();
+        let txid = client.fund(address.address(), 1_000, None).unwrap();
+        client.mine(3);
+        let update = client.full_scan(&wollet).unwrap().unwrap();
+        assert_eq!(update.reorg_depth(&wollet), None);
+        wollet.apply_update(update).unwrap();
+        let before = wollet.transactions().unwrap()[0].timestamp;
+
+        // The tx stays at height 1 but in a different block
+        client.reorg(3);
+        let update = client.full_scan(&wollet).unwrap().unwrap();
+        assert!(update.txid_height_new.contains(&(txid, Some(1))));
+        assert_eq!(update.reorg_depth(&wollet), Some(3));
+        wollet.apply_update(update).unwrap();
+        let tx = &wollet.transactions().unwrap()[0];
+        assert_eq!(tx.height, Some(1));
+        assert_ne!(tx.timestamp, before);
+        assert_eq!(
+            tx.timestamp,
+            client
+                .get_headers(&[1], &std::collections::HashMap::new())
+                .unwrap()[0]
+                .time
+                .into()
+        );
+        let update = client.full_scan(&wollet).unwrap().unwrap();
+        assert_eq!(update.reorg_depth(&wollet), None);
+
+        // Blocks above the wallet tx are replaced
+        client.reorg(2);
+        let update = client.full_scan(&wollet).unwrap().unwrap();
+        assert_eq!(update.reorg_depth(&wollet), Some(1));
+        wollet.apply_update(update).unwrap();
+        assert_eq!(wollet.tip().hash(), client.tip().unwrap().block_hash());
+        let update = client.full_scan(&wollet).unwrap().unwrap();
+        assert_eq!(update.reorg_depth(&wollet), None);
+    }
+
+    #[test]
+    fn test_tip_headers() {
+        let (mut wollet, mut client) = mock_wollet();
+        for _ in 0..3 {
+            client.mine(1);
+            client.sync(&mut wollet).unwrap();
+        }
+        let headers: Vec<_> = wollet.store.cache.headers.values().cloned().collect();
+        assert_eq!(headers.len(), 3);
+        for pair in headers.windows(2) {
+            assert_eq!(pair[1].prev_blockhash, pair[0].block_hash());
+        }
+        assert_eq!(wollet.store.cache.tip_header(), headers.last());
+
+        // An old update on the stored chain is refused
+        let mut update = client.full_scan(&wollet).unwrap().unwrap();
+        update.tip = headers[1].clone();
+        assert_eq!(update.reorg_depth(&wollet), None);
+        let err = wollet.apply_update(update).unwrap_err();
+        assert!(matches!(err, crate::Error::UpdateHeightTooOld { .. }));
+
+        // A tip on a shorter fork is followed
+        let mut update = client.full_scan(&wollet).unwrap().unwrap();
+        update.tip = headers[1].clone();
+        update.tip.time += 1;
+        assert_eq!(update.reorg_depth(&wollet), Some(2));
+        wollet.apply_update(update.clone()).unwrap();
+        let heights: Vec<_> = wollet.store.cache.headers.keys().cloned().collect();
+        assert_eq!(heights, vec![1, 2]);
+        assert_eq!(wollet.tip().hash(), update.tip.block_hash());
+
+        // A tip not extending the stored headers starts a new chain, without gaps
+        client.mine(2);
+        let update = client.full_scan(&wollet).unwrap().unwrap();
+        assert_eq!(update.reorg_depth(&wollet), Some(1));
+        wollet.apply_update(update).unwrap();
+        let heights: Vec<_> = wollet.store.cache.headers.keys().cloned().collect();
+        assert_eq!(heights, vec![5]);
+        assert_eq!(wollet.tip().hash(), client.tip().unwrap().block_hash());
+        client.mine(1);
+        client.sync(&mut wollet).unwrap();
+        let heights: Vec<_> = wollet.store.cache.headers.keys().cloned().collect();
+        assert_eq!(heights, vec![5, 6]);
+    }
 }
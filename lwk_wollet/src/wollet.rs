@@ -5,14 +5,17 @@ use crate::descriptor::Chain;
 use crate::elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
 use crate::elements::pset::PartiallySignedTransaction;
 use crate::elements::secp256k1_zkp::ZERO_TWEAK;
-use crate::elements::{AssetId, BlockHash, OutPoint, Script, Transaction, TxOutSecrets, Txid};
+use crate::elements::{
+    AssetId, BlockHash, OutPoint, Script, Transaction, TxOut, TxOutSecrets, Txid,
+};
 use crate::error::Error;
 use crate::hashes::Hash;
 use crate::model::{
-    AddressResult, BitcoinAddressResult, ExternalUtxo, IssuanceDetails, WalletTx, WalletTxOut,
+    AddressInfo, AddressResult, BitcoinAddressResult, ExternalUtxo, IssuanceDetails,
+    PaymentMismatch, PaymentVerification, TxStatus, UnblindableOutput, WalletTx, WalletTxOut,
 };
 use crate::persister::PersistError;
-use crate::store::{Height, ScriptBatch, Store, Timestamp, BATCH_SIZE};
+use crate::store::{Height, ScriptBatch, ScriptHistoryStatus, Store, Timestamp, BATCH_SIZE};
 use crate::tx_builder::{extract_issuances, WolletTxBuilder};
 use crate::util::EC;
 use crate::{BlindingPublicKey, FsPersister, NoPersist, Persister, Update, WolletDescriptor};
@@ -39,8 +42,16 @@ pub struct Wollet {
     descriptor: WolletDescriptor,
     // cached value
     max_weight_to_satisfy: usize,
+    finality_threshold: u32,
+    birthday: Option<Height>,
 }
 
+/// Confirmations after which a transaction is final, see [`Wollet::set_finality_threshold()`]
+///
+/// Liquid has two blocks finality: the federation doesn't sign blocks competing with a block
+/// that has already been built upon.
+pub const DEFAULT_FINALITY_THRESHOLD: u32 = 2;
+
 /// A coincise state of the wallet, in particular having only transactions ids instead of full
 /// transactions and missing other things not strictly needed for a scan.
 /// By using this instead of a borrow of the wallet we can release locks
@@ -51,6 +62,8 @@ pub struct WolletConciseState {
     paths: HashMap<Script, (Chain, ChildNumber)>,
     scripts: HashMap<(Chain, ChildNumber), (Script, BlindingPublicKey)>,
     heights: HashMap<Txid, Option<Height>>,
+    block_hashes: HashMap<Height, BlockHash>,
+    script_status: HashMap<Script, ScriptHistoryStatus>,
     tip: (Height, BlockHash),
     last_unused: LastUnused,
 }
@@ -71,6 +84,8 @@ pub trait WolletState {
     fn paths(&self) -> &HashMap<Script, (Chain, ChildNumber)>;
     fn txs(&self) -> HashSet<Txid>;
     fn tip(&self) -> (Height, BlockHash);
+    fn block_hashes(&self) -> &HashMap<Height, BlockHash>;
+    fn script_status(&self) -> &HashMap<Script, ScriptHistoryStatus>;
     fn last_unused(&self) -> LastUnused; // TODO change to &LastUnused when possible
     fn descriptor(&self) -> WolletDescriptor;
     fn wollet_status(&self) -> u64;
@@ -137,6 +152,14 @@ impl WolletState for WolletConciseState {
         self.tip
     }
 
+    fn block_hashes(&self) -> &HashMap<Height, BlockHash> {
+        &self.block_hashes
+    }
+
+    fn script_status(&self) -> &HashMap<Script, ScriptHistoryStatus> {
+        &self.script_status
+    }
+
     fn last_unused(&self) -> LastUnused {
         self.last_unused.clone()
     }
@@ -190,6 +213,14 @@ impl WolletState for Wollet {
         self.store.cache.tip
     }
 
+    fn block_hashes(&self) -> &HashMap<Height, BlockHash> {
+        &self.store.cache.block_hashes
+    }
+
+    fn script_status(&self) -> &HashMap<Script, ScriptHistoryStatus> {
+        &self.store.cache.script_status
+    }
+
     fn last_unused(&self) -> LastUnused {
         // TODO use LastUnused internally in Wollet
         LastUnused {
@@ -230,6 +261,7 @@ impl Wollet {
         persister: Arc<dyn Persister + Send + Sync>,
         descriptor: WolletDescriptor,
     ) -> Result<Self, Error> {
+        descriptor.check_network(network)?;
         let config = Config::new(network)?;
 
         let store = Store::default();
@@ -242,12 +274,22 @@ impl Wollet {
             descriptor,
             persister,
             max_weight_to_satisfy,
+            finality_threshold: DEFAULT_FINALITY_THRESHOLD,
+            birthday: None,
         };
 
         for i in 0.. {
-            match wollet.persister.get(i)? {
-                Some(update) => wollet.apply_update_no_persist(update)?,
-                None => break,
+            match wollet.persister.get(i) {
+                Ok(Some(update)) => wollet.apply_update_no_persist(update)?,
+                Ok(None) => break,
+                Err(e @ PersistError::Corrupted { .. }) => {
+                    // The wallet is consistent up to the previous update, the rest is
+                    // recovered with the next scan
+                    tracing::warn!("{e}, discarding it and the following updates");
+                    wollet.persister.truncate(i)?;
+                    break;
+                }
+                Err(e) => return Err(e.into()),
             }
         }
 
@@ -259,6 +301,43 @@ impl Wollet {
         self.max_weight_to_satisfy
     }
 
+    /// Confirmations after which [`WalletTx::status`] is [`TxStatus::Final`]
+    pub fn finality_threshold(&self) -> u32 {
+        self.finality_threshold
+    }
+
+    /// Set the confirmations after which [`WalletTx::status`] is [`TxStatus::Final`], by default
+    /// [`DEFAULT_FINALITY_THRESHOLD`]
+    pub fn set_finality_threshold(&mut self, confirmations: u32) {
+        self.finality_threshold = confirmations;
+    }
+
+    /// The height of the first block containing a wallet transaction, if known
+    ///
+    /// It's the lowest between the height set with [`Wollet::set_birthday()`] and the height of
+    /// the first confirmed wallet transaction.
+    pub fn birthday(&self) -> Option<Height> {
+        let first_tx = self.store.cache.heights.values().flatten().min().cloned();
+        match (self.birthday, first_tx) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Set the birthday of the wallet, for instance when it's restored before being synced
+    pub fn set_birthday(&mut self, birthday: Option<Height>) {
+        self.birthday = birthday;
+    }
+
+    /// The confirmations and the status of a transaction confirmed at `height`
+    fn tx_status(&self, height: Option<Height>) -> (u32, TxStatus) {
+        let confirmations = height.map_or(0, |h| self.store.cache.tip.0.saturating_sub(h) + 1);
+        (
+            confirmations,
+            TxStatus::new(confirmations, self.finality_threshold),
+        )
+    }
+
     pub fn state(&self) -> WolletConciseState {
         let cache = &self.store.cache;
         WolletConciseState {
@@ -268,6 +347,8 @@ impl Wollet {
             paths: cache.paths.clone(),
             scripts: cache.scripts.clone(),
             heights: cache.heights.clone(),
+            block_hashes: cache.block_hashes.clone(),
+            script_status: cache.script_status.clone(),
             tip: cache.tip,
             last_unused: LastUnused {
                 internal: cache.last_unused_internal.load(atomic::Ordering::Relaxed),
@@ -297,6 +378,22 @@ impl Wollet {
         Self::new(network, Arc::new(NoPersist {}), descriptor)
     }
 
+    /// Create a wallet for planning on an offline machine, such as an air-gapped coordinator
+    ///
+    /// The network is inferred from the descriptor keys (Liquid or Liquid Testnet) and nothing
+    /// is persisted. The wallet can derive addresses, analyse PSETs with
+    /// [`Wollet::get_details()`] and verify their signatures with
+    /// [`Wollet::verify_signatures()`], without any data directory or blockchain client. For
+    /// regtest use [`Wollet::without_persist()`].
+    pub fn planning(descriptor: WolletDescriptor) -> Result<Self, Error> {
+        let network = if descriptor.is_mainnet() {
+            ElementsNetwork::Liquid
+        } else {
+            ElementsNetwork::LiquidTestnet
+        };
+        Self::without_persist(network, descriptor)
+    }
+
     /// Get the network policy asset
     pub fn policy_asset(&self) -> AssetId {
         self.config.policy_asset()
@@ -312,6 +409,30 @@ impl Wollet {
         self.config.network()
     }
 
+    /// Check that `backend` is on the wallet network, to refuse operating on the wrong chain
+    ///
+    /// The genesis block of `backend` is compared with the one of the wallet network, regtest
+    /// networks are not compared since their genesis block depends on the chain parameters. The
+    /// descriptor keys are checked against the wallet network when the wallet is created.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn verify_backend<B: crate::clients::blocking::BlockchainBackend>(
+        &self,
+        backend: &B,
+    ) -> Result<(), Error> {
+        let Some(expected) = self.network().genesis_hash() else {
+            return Ok(());
+        };
+        let genesis = backend.get_headers(&[0], &HashMap::new())?;
+        let found = genesis
+            .first()
+            .ok_or_else(|| Error::Generic("Backend returned no genesis header".into()))?
+            .block_hash();
+        if found != expected {
+            return Err(Error::WrongGenesisHash { expected, found });
+        }
+        Ok(())
+    }
+
     /// Get a reference of the wallet descriptor
     pub fn descriptor(&self) -> &ConfidentialDescriptor<DescriptorPublicKey> {
         self.descriptor.as_ref()
@@ -322,6 +443,13 @@ impl Wollet {
         self.descriptor.clone()
     }
 
+    /// The wallet transaction double spending the unconfirmed transaction `txid`, if any
+    ///
+    /// Conflicted transactions are removed from the wallet transactions and balance.
+    pub fn conflicted_by(&self, txid: &Txid) -> Option<Txid> {
+        self.store.cache.conflicted.get(txid).cloned()
+    }
+
     /// Get the blockchain tip
     pub fn tip(&self) -> Tip {
         let (height, hash) = self.store.cache.tip;
@@ -405,6 +533,64 @@ impl Wollet {
         Ok(AddressResult::new(address, index))
     }
 
+    /// Whether the script pubkey belongs to the wallet, and where it's derived if it does
+    ///
+    /// The scripts cached while scanning or with [`Wollet::cache_scripts()`] are looked up first,
+    /// then the scripts are derived up to the gap limit after the last used index, for both
+    /// receiving and change addresses. The derived scripts are cached as well, and persisted with
+    /// the next applied update.
+    pub fn is_mine(&self, script_pubkey: &Script) -> Result<Option<AddressInfo>, Error> {
+        let found = match self.index(script_pubkey) {
+            Ok(found) => Some(found),
+            Err(Error::ScriptNotMine) => self.find_within_gap_limit(script_pubkey)?,
+            Err(e) => return Err(e),
+        };
+        found
+            .map(|(chain, index)| {
+                let address =
+                    self.descriptor
+                        .inner_address(index, self.config.address_params(), chain)?;
+                Ok(AddressInfo::new(address, chain, index))
+            })
+            .transpose()
+    }
+
+    fn find_within_gap_limit(&self, script_pubkey: &Script) -> Result<Option<(Chain, u32)>, Error> {
+        let last_unused = self.last_unused();
+        let mut chains = vec![(Chain::External, last_unused.external)];
+        if self.descriptor().descriptor.is_multipath() {
+            chains.push((Chain::Internal, last_unused.internal));
+        }
+        let mut derived = self
+            .store
+            .cache
+            .derived
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+        for (chain, last_unused) in chains {
+            // scripts are cached in contiguous batches, only the following ones are derived
+            let start = self
+                .store
+                .cache
+                .paths
+                .values()
+                .chain(derived.values())
+                .filter(|(c, _)| *c == chain)
+                .map(|(_, child)| u32::from(*child) + 1)
+                .max()
+                .unwrap_or(0);
+            for index in start..last_unused.saturating_add(BATCH_SIZE) {
+                let script = self.descriptor.script_pubkey(chain, index)?;
+                let found = &script == script_pubkey;
+                derived.insert(script, (chain, ChildNumber::from_normal_idx(index)?));
+                if found {
+                    return Ok(Some((chain, index)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
     fn utxos_inner(&self) -> Result<Vec<WalletTxOut>, Error> {
         Ok(self
             .txos_inner()?
@@ -435,31 +621,7 @@ impl Wollet {
                     (out_point, output, spent.contains(&out_point))
                 })
                 .filter_map(|(outpoint, output, is_spent)| {
-                    if let Some(unblinded) = self.store.cache.unblinded.get(&outpoint) {
-                        let index = self.index(&output.script_pubkey).ok()?;
-                        let blinding_pubkey = self
-                            .store
-                            .cache
-                            .scripts
-                            .get(&(index.0, index.1.into()))
-                            .map(|(_, blinding_pubkey)| *blinding_pubkey);
-                        let address = Address::from_script(
-                            &output.script_pubkey,
-                            blinding_pubkey,
-                            self.network().address_params(),
-                        )?;
-                        return Some(WalletTxOut {
-                            outpoint,
-                            script_pubkey: output.script_pubkey.clone(),
-                            height: *height,
-                            unblinded: *unblinded,
-                            wildcard_index: index.1,
-                            ext_int: index.0,
-                            is_spent,
-                            address,
-                        });
-                    }
-                    None
+                    self.txo(outpoint, output, *height, is_spent)
                 });
             txos.extend(tx_txos);
         }
@@ -467,6 +629,39 @@ impl Wollet {
         Ok(txos)
     }
 
+    /// The wallet output `output` at `outpoint`, None if it cannot be unblinded by the wallet
+    fn txo(
+        &self,
+        outpoint: OutPoint,
+        output: &TxOut,
+        height: Option<Height>,
+        is_spent: bool,
+    ) -> Option<WalletTxOut> {
+        let unblinded = self.store.cache.unblinded.get(&outpoint)?;
+        let index = self.index(&output.script_pubkey).ok()?;
+        let blinding_pubkey = self
+            .store
+            .cache
+            .scripts
+            .get(&(index.0, index.1.into()))
+            .map(|(_, blinding_pubkey)| *blinding_pubkey);
+        let address = Address::from_script(
+            &output.script_pubkey,
+            blinding_pubkey,
+            self.network().address_params(),
+        )?;
+        Some(WalletTxOut {
+            outpoint,
+            script_pubkey: output.script_pubkey.clone(),
+            height,
+            unblinded: *unblinded,
+            wildcard_index: index.1,
+            ext_int: index.0,
+            is_spent,
+            address,
+        })
+    }
+
     /// Get the wallet UTXOs
     pub fn utxos(&self) -> Result<Vec<WalletTxOut>, Error> {
         let mut utxos = self.utxos_inner()?;
@@ -520,6 +715,8 @@ impl Wollet {
                         txout: o.clone(),
                         unblinded,
                         max_weight_to_satisfy: self.max_weight_to_satisfy,
+                        redeem_script: None,
+                        witness_script: None,
                     });
                 }
             }
@@ -527,6 +724,48 @@ impl Wollet {
         Ok(utxos)
     }
 
+    /// Get the outputs on wallet scripts that the wallet cannot unblind
+    ///
+    /// Funds sent to the wallet blinded with a different key, for instance by a sender messing up
+    /// with the blinding process, are not part of the balance nor of the UTXOs. They are kept
+    /// here until [`Wollet::retry_unblind()`] unblinds them. Explicit outputs are not included,
+    /// see [`Wollet::explicit_utxos()`].
+    pub fn unblindable_outputs(&self) -> Result<Vec<UnblindableOutput>, Error> {
+        let spent = self.store.spent()?;
+        let mut outputs = vec![];
+        for (txid, height) in self.store.cache.heights.iter() {
+            let tx = self
+                .store
+                .cache
+                .all_txs
+                .get(txid)
+                .ok_or_else(|| Error::Generic(format!("unblindable outputs no tx {}", txid)))?;
+            for (vout, o) in tx.output.iter().enumerate() {
+                let outpoint = OutPoint::new(*txid, vout as u32);
+                if o.script_pubkey.is_empty()
+                    || (o.asset.is_explicit() && o.value.is_explicit())
+                    || self.store.cache.unblinded.contains_key(&outpoint)
+                {
+                    continue;
+                }
+                if let Some((ext_int, ChildNumber::Normal { index })) =
+                    self.store.cache.paths.get(&o.script_pubkey)
+                {
+                    outputs.push(UnblindableOutput {
+                        outpoint,
+                        txout: o.clone(),
+                        height: *height,
+                        wildcard_index: *index,
+                        ext_int: *ext_int,
+                        is_spent: spent.contains(&outpoint),
+                    });
+                }
+            }
+        }
+        outputs.sort_by_key(|o| o.outpoint);
+        Ok(outputs)
+    }
+
     pub(crate) fn balance_from_utxos(
         &self,
         utxos: &[WalletTxOut],
@@ -545,6 +784,70 @@ impl Wollet {
         self.balance_from_utxos(&utxos)
     }
 
+    /// Get the wallet UTXOs as of the block at `height`
+    ///
+    /// Only transactions confirmed at or below `height` are considered, an output spent by a
+    /// later or unconfirmed transaction is thus returned as unspent.
+    pub fn utxos_at(&self, height: Height) -> Result<Vec<WalletTxOut>, Error> {
+        let confirmed = |txid: &Txid| matches!(self.store.cache.heights.get(txid), Some(Some(h)) if *h <= height);
+        let spent: HashSet<OutPoint> = self
+            .store
+            .cache
+            .all_txs
+            .iter()
+            .filter(|(txid, _)| confirmed(txid))
+            .flat_map(|(_, tx)| tx.input.iter().map(|i| i.previous_output))
+            .collect();
+        let mut utxos: Vec<_> = self
+            .txos_inner()?
+            .into_iter()
+            .filter(|txo| confirmed(&txo.outpoint.txid) && !spent.contains(&txo.outpoint))
+            .map(|txo| WalletTxOut {
+                is_spent: false,
+                ..txo
+            })
+            .collect();
+        utxos.sort_by(|a, b| b.unblinded.value.cmp(&a.unblinded.value));
+        Ok(utxos)
+    }
+
+    /// Get the wallet UTXOs of `asset`, sorted like [`Wollet::utxos()`]
+    ///
+    /// Only the outputs of `asset` are considered, the wallet keeps them indexed by asset.
+    pub fn utxos_for_asset(&self, asset: &AssetId) -> Result<Vec<WalletTxOut>, Error> {
+        let outpoints = match self.store.cache.unblinded_by_asset.get(asset) {
+            Some(outpoints) => outpoints,
+            None => return Ok(vec![]),
+        };
+        let spent = self.store.spent()?;
+        let mut utxos = vec![];
+        for outpoint in outpoints.iter().filter(|o| !spent.contains(o)) {
+            // outputs of transactions no longer in the wallet, for instance conflicted
+            let Some(height) = self.store.cache.heights.get(&outpoint.txid) else {
+                continue;
+            };
+            let output = self.get_txout(outpoint)?;
+            utxos.extend(self.txo(*outpoint, &output, *height, false));
+        }
+        utxos.sort_by(|a, b| b.unblinded.value.cmp(&a.unblinded.value));
+        Ok(utxos)
+    }
+
+    /// Get the wallet balance of `asset`
+    pub fn balance_of(&self, asset: &AssetId) -> Result<u64, Error> {
+        Ok(self
+            .utxos_for_asset(asset)?
+            .iter()
+            .map(|u| u.unblinded.value)
+            .sum())
+    }
+
+    /// Get the wallet balance as of the block at `height`, see [`Wollet::utxos_at()`]
+    pub fn balance_at(&self, height: Height) -> Result<BTreeMap<AssetId, u64>, Error> {
+        let utxos = self.utxos_at(height)?;
+        self.balance_from_utxos(&utxos)
+    }
+
     /// Get the wallet transactions
     pub fn transactions(&self) -> Result<Vec<WalletTx>, Error> {
         let mut txs = vec![];
@@ -578,6 +881,7 @@ impl Wollet {
             let timestamp = height.and_then(|h| self.store.cache.timestamps.get(&h).cloned());
             let inputs = tx_inputs(tx, &txos);
             let outputs = tx_outputs(**txid, tx, &txos);
+            let (confirmations, status) = self.tx_status(**height);
             txs.push(WalletTx {
                 tx: tx.clone(),
                 txid: **txid,
@@ -588,6 +892,8 @@ impl Wollet {
                 timestamp,
                 inputs,
                 outputs,
+                confirmations,
+                status,
             });
         }
 
@@ -608,6 +914,7 @@ impl Wollet {
             let timestamp = height.and_then(|h| self.store.cache.timestamps.get(&h).cloned());
             let inputs = tx_inputs(tx, &txos);
             let outputs = tx_outputs(*txid, tx, &txos);
+            let (confirmations, status) = self.tx_status(*height);
 
             Ok(Some(WalletTx {
                 tx: tx.clone(),
@@ -619,12 +926,104 @@ impl Wollet {
                 timestamp,
                 inputs,
                 outputs,
+                confirmations,
+                status,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// Get the fee of a wallet transaction, from its explicit fee output in the policy asset
+    ///
+    /// Returns `None` if the transaction is not in the wallet cache.
+    pub fn tx_fee(&self, txid: &Txid) -> Option<u64> {
+        let tx = self.store.cache.all_txs.get(txid)?;
+        Some(tx.fee_in(self.policy_asset()))
+    }
+
+    /// Verify that output `vout` of transaction `txid` pays `satoshi` of `asset` to `address`
+    ///
+    /// The transaction must be in the wallet, thus synced from the blockchain, and have at least
+    /// `min_confirmations`. The output is unblinded with the wallet keys. If `address` is
+    /// confidential its blinding key must be the one of the wallet. A payment not verified is not
+    /// an error, the result tells why.
+    pub fn verify_payment(
+        &self,
+        txid: &Txid,
+        vout: u32,
+        address: &Address,
+        asset: AssetId,
+        satoshi: u64,
+        min_confirmations: u32,
+    ) -> Result<PaymentVerification, Error> {
+        let tip = self.tip();
+        let mut mismatch = self.payment_mismatch(txid, vout, address, asset, satoshi)?;
+        let height = match mismatch {
+            Some(PaymentMismatch::UnknownTransaction) => None,
+            _ => self.store.cache.heights.get(txid).copied().flatten(),
+        };
+        let confirmations = match height {
+            Some(height) => tip.height().saturating_sub(height) + 1,
+            None => 0,
+        };
+        if mismatch.is_none() && confirmations < min_confirmations {
+            mismatch = Some(PaymentMismatch::Confirmations(confirmations));
+        }
+        Ok(PaymentVerification {
+            wallet_id: crate::cold::wallet_id(self)?,
+            txid: *txid,
+            vout,
+            address: address.clone(),
+            asset,
+            satoshi,
+            min_confirmations,
+            height,
+            confirmations,
+            tip_height: tip.height(),
+            tip_hash: tip.hash(),
+            tip_timestamp: tip.timestamp(),
+            mismatch,
+        })
+    }
+
+    fn payment_mismatch(
+        &self,
+        txid: &Txid,
+        vout: u32,
+        address: &Address,
+        asset: AssetId,
+        satoshi: u64,
+    ) -> Result<Option<PaymentMismatch>, Error> {
+        let cache = &self.store.cache;
+        let tx = match cache.all_txs.get(txid) {
+            Some(tx) if cache.heights.contains_key(txid) => tx,
+            _ => return Ok(Some(PaymentMismatch::UnknownTransaction)),
+        };
+        let output = match tx.output.get(vout as usize) {
+            Some(output) => output,
+            None => return Ok(Some(PaymentMismatch::UnknownOutput)),
+        };
+        if output.script_pubkey != address.script_pubkey() {
+            return Ok(Some(PaymentMismatch::Address));
+        }
+        match self.is_mine(&output.script_pubkey)? {
+            Some(info) if address.blinding_pubkey.is_none() || info.address() == address => (),
+            _ => return Ok(Some(PaymentMismatch::Address)),
+        }
+        let secrets = match cache.unblinded.get(&OutPoint::new(*txid, vout)) {
+            Some(secrets) => secrets,
+            None => return Ok(Some(PaymentMismatch::NotUnblinded)),
+        };
+        if secrets.asset != asset {
+            return Ok(Some(PaymentMismatch::Asset(secrets.asset)));
+        }
+        if secrets.value != satoshi {
+            return Ok(Some(PaymentMismatch::Amount(secrets.value)));
+        }
+        Ok(None)
+    }
+
     /// Get the wallet (re)issuances
     pub fn issuances(&self) -> Result<Vec<IssuanceDetails>, Error> {
         let mut r = vec![];
@@ -654,20 +1053,32 @@ impl Wollet {
         })
     }
 
+    /// Verify the signatures in the PSET, returning how many they are
+    ///
+    /// Fails if any signature is invalid, see [`lwk_common::pset::verify_signatures()`].
+    pub fn verify_signatures(&self, pset: &PartiallySignedTransaction) -> Result<usize, Error> {
+        Ok(lwk_common::pset::verify_signatures(pset)?)
+    }
+
     pub(crate) fn index(&self, script_pubkey: &Script) -> Result<(Chain, u32), Error> {
-        let (ext_int, index) = self
-            .store
-            .cache
-            .paths
-            .get(script_pubkey)
-            .ok_or_else(|| Error::ScriptNotMine)?;
+        let (ext_int, index) = match self.store.cache.paths.get(script_pubkey) {
+            Some(path) => *path,
+            None => *self
+                .store
+                .cache
+                .derived
+                .lock()
+                .map_err(|_| Error::LockPoisoned)?
+                .get(script_pubkey)
+                .ok_or_else(|| Error::ScriptNotMine)?,
+        };
         let index = match index {
             ChildNumber::Normal { index } => index,
             ChildNumber::Hardened { index: _ } => {
                 return Err(Error::Generic("unexpected hardened derivation".into()));
             }
         };
-        Ok((*ext_int, *index))
+        Ok((ext_int, index))
     }
 
     // TODO: move to WolletDescriptor::definite_descriptor(index)
@@ -680,17 +1091,39 @@ impl Wollet {
     }
 
     /// Add the PSET details with respect to the wallet
+    ///
+    /// For every input and output belonging to the wallet, set the key origins
+    /// (`bip32_derivation`), the witness and redeem scripts, so that signers can sign the inputs
+    /// and verify the change outputs. Inputs spending wallet outputs without the witness utxo, for
+    /// instance because the PSET was created by someone else, get their witness utxo set.
+    /// The wallet xpubs are added to the global xpubs with their key origin.
     pub fn add_details(&self, pset: &mut PartiallySignedTransaction) -> Result<(), Error> {
         let pset_clone = pset.clone();
         for (idx, input) in pset_clone.inputs().iter().enumerate() {
-            if let Some(txout) = input.witness_utxo.as_ref() {
-                match self.definite_descriptor(&txout.script_pubkey) {
-                    Ok(desc) => {
-                        pset.update_input_with_descriptor(idx, &desc)?;
+            let txout = match input.witness_utxo.as_ref() {
+                Some(txout) => txout.clone(),
+                None => {
+                    let outpoint = OutPoint::new(input.previous_txid, input.previous_output_index);
+                    match self.get_txout(&outpoint) {
+                        Ok(txout) if self.index(&txout.script_pubkey).is_ok() => {
+                            let input = &mut pset.inputs_mut()[idx];
+                            let mut witness_utxo = txout.clone();
+                            if input.in_utxo_rangeproof.is_none() {
+                                input.in_utxo_rangeproof = witness_utxo.witness.rangeproof.take();
+                            }
+                            input.witness_utxo = Some(witness_utxo);
+                            txout
+                        }
+                        _ => continue,
                     }
-                    Err(Error::ScriptNotMine) => (),
-                    Err(e) => return Err(e),
                 }
+            };
+            match self.definite_descriptor(&txout.script_pubkey) {
+                Ok(desc) => {
+                    pset.update_input_with_descriptor(idx, &desc)?;
+                }
+                Err(Error::ScriptNotMine) => (),
+                Err(e) => return Err(e),
             }
         }
 
@@ -752,18 +1185,12 @@ impl Wollet {
     }
 
     pub fn finalize(&self, pset: &mut PartiallySignedTransaction) -> Result<Transaction, Error> {
-        // genesis_hash is only used for BIP341 (taproot) sighash computation
-        let result = pset.finalize_mut(&EC, BlockHash::all_zeros());
-        if let Err(errors) = result {
-            if !errors.is_empty() && errors.len() == pset.inputs().len() {
-                // Failed to finalize all inputs
-                // TODO: do not use Generic
-                return Err(Error::Generic(format!("{:?}", errors)));
-            }
-            // If some inputs have been finalized ignore the other errors
-        }
+        finalize_pset(pset)
+    }
 
-        Ok(pset.extract_tx()?)
+    /// Make sure the updates applied so far are on durable storage
+    pub fn flush(&self) -> Result<(), PersistError> {
+        self.persister.flush()
     }
 
     pub fn updates(&self) -> Result<Vec<Update>, PersistError> {
@@ -793,6 +1220,49 @@ impl Wollet {
     pub fn never_scanned(&self) -> bool {
         self.store.cache.tip == (0, BlockHash::all_zeros())
     }
+
+    /// Export a proof that the wallet transaction `txid` has been included in the blockchain
+    ///
+    /// The proof contains the transaction, the merkle branch and the headers from the block
+    /// containing the transaction up to the wallet tip (at most [`crate::SPV_PROOF_MAX_HEADERS`]),
+    /// and can be verified offline with [`crate::SpvProof::verify()`].
+    #[cfg(feature = "electrum")]
+    pub fn export_spv_proof(
+        &self,
+        txid: &Txid,
+        electrum_client: &crate::ElectrumClient,
+    ) -> Result<crate::SpvProof, Error> {
+        use crate::clients::blocking::BlockchainBackend;
+
+        let height = self
+            .store
+            .cache
+            .heights
+            .get(txid)
+            .ok_or(Error::MissingTransaction)?
+            .ok_or(Error::UnconfirmedTransaction(*txid))?;
+        let tx = self
+            .store
+            .cache
+            .all_txs
+            .get(txid)
+            .ok_or(Error::MissingTransaction)?
+            .clone();
+        let merkle_proof = electrum_client.get_merkle_proof(txid, height)?;
+
+        let tip_height = self.store.cache.tip.0.max(height);
+        let last = tip_height.min(height + crate::SPV_PROOF_MAX_HEADERS - 1);
+        let heights: Vec<Height> = (height..=last).collect();
+        let headers = electrum_client.get_headers(&heights, &HashMap::new())?;
+
+        let proof = crate::SpvProof {
+            tx,
+            merkle_proof,
+            headers,
+        };
+        proof.verify()?;
+        Ok(proof)
+    }
 }
 
 fn tx_balance(
@@ -816,6 +1286,25 @@ fn tx_balance(
     balance
 }
 
+/// Finalize the inputs of `pset` and extract the transaction
+///
+/// The inputs are finalized with the scripts and the signatures in the PSET, thus it doesn't need
+/// the wallet, see [`Wollet::finalize()`].
+pub fn finalize_pset(pset: &mut PartiallySignedTransaction) -> Result<Transaction, Error> {
+    // genesis_hash is only used for BIP341 (taproot) sighash computation
+    let result = pset.finalize_mut(&EC, BlockHash::all_zeros());
+    if let Err(errors) = result {
+        if !errors.is_empty() && errors.len() == pset.inputs().len() {
+            // Failed to finalize all inputs
+            // TODO: do not use Generic
+            return Err(Error::Generic(format!("{:?}", errors)));
+        }
+        // If some inputs have been finalized ignore the other errors
+    }
+
+    Ok(pset.extract_tx()?)
+}
+
 /// Performs a full blockchain scan using an Electrum client and applies any updates to the wallet.
 ///
 /// For details about the scan see ['BlockchainBackend::full_scan']
@@ -947,12 +1436,13 @@ pub fn derive_script_and_blinding_key(
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
     use std::str::FromStr;
 
     use super::*;
+    use crate::blocking::MockBackend;
     use crate::elements::bitcoin::bip32::{Xpriv, Xpub};
     use crate::elements::bitcoin::network::Network;
     use crate::elements::AddressParams;
@@ -1153,6 +1643,154 @@ mod tests {
         assert_eq!(addr.address().to_string(), lwk_test_util::PEGIN_TEST_ADDR);
     }
 
+    #[test]
+    fn test_is_mine() {
+        let wollet = test_wollet_with_many_transactions();
+        let last_unused = wollet.last_unused();
+
+        let change = wollet.change(Some(3)).unwrap();
+        let info = wollet
+            .is_mine(&change.address().script_pubkey())
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.chain(), Chain::Internal);
+        assert_eq!(info.index(), 3);
+        assert_eq!(info.address(), change.address());
+
+        // not cached, but within the gap limit
+        let descriptor = lwk_test_util::wollet_descriptor_many_transactions();
+        let never_scanned = Wollet::new(
+            ElementsNetwork::LiquidTestnet,
+            NoPersist::new(),
+            descriptor.parse().unwrap(),
+        )
+        .unwrap();
+        let address = never_scanned.change(Some(BATCH_SIZE - 1)).unwrap();
+        let info = never_scanned
+            .is_mine(&address.address().script_pubkey())
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.chain(), Chain::Internal);
+        assert_eq!(info.index(), BATCH_SIZE - 1);
+        let address = never_scanned.change(Some(BATCH_SIZE)).unwrap();
+        let script_pubkey = address.address().script_pubkey();
+        assert!(never_scanned.is_mine(&script_pubkey).unwrap().is_none());
+
+        let index = last_unused.external + BATCH_SIZE - 1;
+
+        // beyond the gap limit
+        let address = wollet.address(Some(index + 1000)).unwrap();
+        let script_pubkey = address.address().script_pubkey();
+        assert!(wollet.is_mine(&script_pubkey).unwrap().is_none());
+        assert!(wollet.is_mine(&burn_script()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tx_fee() {
+        let wollet = test_wollet_with_many_transactions();
+        let txs = wollet.transactions().unwrap();
+        assert!(txs.iter().any(|tx| tx.fee > 0));
+        for tx in txs {
+            assert_eq!(wollet.tx_fee(&tx.txid), Some(tx.fee));
+        }
+        let txid = Txid::from_str(&"00".repeat(32)).unwrap();
+        assert_eq!(wollet.tx_fee(&txid), None);
+    }
+
+    #[test]
+    fn test_verify_payment() {
+        let wollet = test_wollet_with_many_transactions();
+        let utxo = &wollet.utxos().unwrap()[0];
+        let OutPoint { txid, vout } = utxo.outpoint;
+        let (asset, value) = (utxo.unblinded.asset, utxo.unblinded.value);
+        let verify = |address: &Address, asset, satoshi| {
+            let v = wollet.verify_payment(&txid, vout, address, asset, satoshi, 1);
+            v.unwrap().mismatch
+        };
+
+        let v = wollet
+            .verify_payment(&txid, vout, &utxo.address, asset, value, 1)
+            .unwrap();
+        assert!(v.is_verified());
+        assert_eq!(v.height, utxo.height);
+        assert!(v.confirmations > 0);
+        assert_eq!(v.tip_hash, wollet.tip().hash());
+        let message = v.message();
+        assert!(message.contains(&format!("\nwallet: {}\n", v.wallet_id)));
+        assert!(message.contains(&format!("\ntip hash: {}\n", v.tip_hash)));
+        assert!(message.ends_with("result: verified"));
+
+        let required = v.confirmations + 1;
+        let v = wollet
+            .verify_payment(&txid, vout, &utxo.address, asset, value, required)
+            .unwrap();
+        let confirmations = v.confirmations;
+        assert_eq!(
+            v.mismatch,
+            Some(PaymentMismatch::Confirmations(confirmations))
+        );
+        let unconfidential = utxo.address.to_unconfidential();
+        assert_eq!(verify(&unconfidential, asset, value), None);
+
+        assert_eq!(
+            verify(&utxo.address, asset, value + 1),
+            Some(PaymentMismatch::Amount(value))
+        );
+        let other = AssetId::from_slice(&[1; 32]).unwrap();
+        assert_eq!(
+            verify(&utxo.address, other, value),
+            Some(PaymentMismatch::Asset(asset))
+        );
+        let mut address = utxo.address.clone();
+        address.blinding_pubkey = wollet
+            .address(Some(1000))
+            .unwrap()
+            .address()
+            .blinding_pubkey;
+        assert_eq!(
+            verify(&address, asset, value),
+            Some(PaymentMismatch::Address)
+        );
+        let v = wollet.verify_payment(&txid, 1000, &utxo.address, asset, value, 1);
+        assert_eq!(v.unwrap().mismatch, Some(PaymentMismatch::UnknownOutput));
+        let unknown = Txid::from_str(&"00".repeat(32)).unwrap();
+        let v = wollet
+            .verify_payment(&unknown, vout, &utxo.address, asset, value, 1)
+            .unwrap();
+        assert_eq!(v.mismatch, Some(PaymentMismatch::UnknownTransaction));
+        assert_eq!((v.height, v.confirmations), (None, 0));
+        assert!(v
+            .message()
+            .ends_with("result: not verified, unknown transaction"));
+    }
+
+    #[test]
+    fn test_utxos_for_asset() {
+        let wollet = test_wollet_with_many_transactions();
+        let utxos = wollet.utxos().unwrap();
+        let balance = wollet.balance().unwrap();
+        assert!(balance.len() > 1);
+        for (asset, satoshi) in balance {
+            let mut expected: Vec<_> = utxos
+                .iter()
+                .filter(|u| u.unblinded.asset == asset)
+                .cloned()
+                .collect();
+            let mut asset_utxos = wollet.utxos_for_asset(&asset).unwrap();
+            assert!(asset_utxos
+                .windows(2)
+                .all(|w| w[0].unblinded.value >= w[1].unblinded.value));
+            // outputs with the same value are in no particular order
+            expected.sort_by_key(|u| u.outpoint);
+            asset_utxos.sort_by_key(|u| u.outpoint);
+            assert_eq!(asset_utxos, expected);
+            assert_eq!(wollet.balance_of(&asset).unwrap(), satoshi);
+        }
+        let unknown = AssetId::from_slice(&[1; 32]).unwrap();
+        assert!(wollet.utxos_for_asset(&unknown).unwrap().is_empty());
+        assert_eq!(wollet.balance_of(&unknown).unwrap(), 0);
+    }
+
     #[test]
     fn test_txos_inner() {
         let wollet = test_wollet_with_many_transactions();
@@ -1190,6 +1828,80 @@ mod tests {
         assert!(duration < MAX_DURATION);
     }
 
+    #[test]
+    fn test_add_details_key_origin() {
+        let wollet = test_wollet_with_many_transactions();
+        let fingerprint = wollet.signers()[0];
+        let address = wollet.address(None).unwrap().address().clone();
+        let policy_asset = wollet.policy_asset();
+        let pset = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        let has_origin = |derivation: &BTreeMap<_, (Fingerprint, _)>| {
+            !derivation.is_empty() && derivation.values().all(|(f, _)| *f == fingerprint)
+        };
+        for input in pset.inputs() {
+            assert!(has_origin(&input.bip32_derivation));
+        }
+        // the recipient and the change are ours, the fee is not
+        let n_ours = pset
+            .outputs()
+            .iter()
+            .filter(|o| has_origin(&o.bip32_derivation))
+            .count();
+        assert_eq!(n_ours, pset.outputs().len() - 1);
+        assert!(!pset.global.xpub.is_empty());
+
+        // a PSET spending wallet outputs created elsewhere
+        let mut stripped = pset.clone();
+        for input in stripped.inputs_mut() {
+            input.witness_utxo = None;
+            input.in_utxo_rangeproof = None;
+            input.bip32_derivation.clear();
+        }
+        for output in stripped.outputs_mut() {
+            output.bip32_derivation.clear();
+        }
+        stripped.global.xpub.clear();
+        wollet.add_details(&mut stripped).unwrap();
+        assert_eq!(stripped, pset);
+        let details = wollet.get_details(&stripped).unwrap();
+        assert!(details.balance.balances[&policy_asset] < 0);
+    }
+
+    #[test]
+    fn test_planning() {
+        let descriptor = lwk_test_util::wollet_descriptor_many_transactions();
+        let descriptor: WolletDescriptor = descriptor.parse().unwrap();
+        let wollet = Wollet::planning(descriptor.clone()).unwrap();
+        assert_eq!(wollet.network(), ElementsNetwork::LiquidTestnet);
+        assert!(wollet.never_scanned());
+
+        let synced = test_wollet_with_many_transactions();
+        for i in 0..3 {
+            assert_eq!(
+                wollet.address(Some(i)).unwrap().address(),
+                synced.address(Some(i)).unwrap().address()
+            );
+        }
+
+        let pset = synced
+            .tx_builder()
+            .add_lbtc_recipient(&wollet.address(Some(0)).unwrap().address().clone(), 1_000)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let details = wollet.get_details(&pset).unwrap();
+        let synced_details = synced.get_details(&pset).unwrap();
+        assert_eq!(details.balance.fee, synced_details.balance.fee);
+        assert_eq!(details.balance.balances, synced_details.balance.balances);
+        assert_eq!(wollet.verify_signatures(&pset).unwrap(), 0);
+    }
+
     // duplicated from tests/test_wollet.rs
     pub fn test_wollet_with_many_transactions() -> Wollet {
         let update = lwk_test_util::update_test_vector_many_transactions();
@@ -1205,4 +1917,152 @@ mod tests {
         wollet.apply_update(update).unwrap();
         wollet
     }
+
+    /// A regtest wallet of [`lwk_test_util::TEST_DESCRIPTOR`], with a mock backend for its chain
+    pub fn mock_wollet() -> (Wollet, MockBackend) {
+        mock_wollet_from(lwk_test_util::TEST_DESCRIPTOR)
+    }
+
+    /// A regtest wallet of `desc`, with a mock backend for its chain
+    pub fn mock_wollet_from(desc: &str) -> (Wollet, MockBackend) {
+        let network = ElementsNetwork::default_regtest();
+        let desc = WolletDescriptor::from_str(desc).unwrap();
+        let wollet = Wollet::without_persist(network, desc).unwrap();
+        (wollet, MockBackend::new(network))
+    }
+
+    #[test]
+    fn test_estimated_weight_and_fee() {
+        let wollet = test_wollet_with_many_transactions();
+        let address = wollet.address(None).unwrap().address().clone();
+        let builder = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap();
+        let weight = builder.estimated_weight().unwrap();
+        let fee = builder.estimated_fee().unwrap();
+
+        // default fee rate is 100 sat/kvB
+        assert_eq!(fee, (weight.div_ceil(4) as f32 * 0.1).ceil() as u64);
+
+        let pset = builder.finish().unwrap();
+        let fee_in_pset: u64 = pset
+            .outputs()
+            .iter()
+            .filter(|o| o.script_pubkey.is_empty())
+            .filter_map(|o| o.amount)
+            .sum();
+        assert_eq!(fee, fee_in_pset);
+        let unsigned_weight = pset.extract_tx().unwrap().discount_weight();
+        assert!(weight > unsigned_weight);
+    }
+
+    #[test]
+    fn test_locktime_and_sequence() {
+        let wollet = test_wollet_with_many_transactions();
+        let address = wollet.address(None).unwrap().address().clone();
+        let tip_height = wollet.tip().height();
+
+        // Anti fee sniping and replaceable by default
+        let tx = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .extract_tx()
+            .unwrap();
+        assert_eq!(tx.lock_time.to_consensus_u32(), tip_height);
+        assert!(tx
+            .input
+            .iter()
+            .all(|i| i.sequence == elements::Sequence::ENABLE_RBF_NO_LOCKTIME));
+        assert!(tx.input.iter().all(|i| i.sequence.is_rbf()));
+
+        let outpoint = tx.input[0].previous_output;
+        let sequence = elements::Sequence::from_height(10);
+        let tx = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .set_locktime(tip_height - 10)
+            .set_sequence(outpoint, sequence)
+            .finish()
+            .unwrap()
+            .extract_tx()
+            .unwrap();
+        assert_eq!(tx.lock_time.to_consensus_u32(), tip_height - 10);
+        let input = tx.input.iter().find(|i| i.previous_output == outpoint);
+        assert_eq!(input.unwrap().sequence, sequence);
+
+        let err = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .set_locktime(500_000_000)
+            .finish()
+            .unwrap_err();
+        assert!(matches!(err, Error::Locktime(_)));
+
+        let missing = OutPoint::new(outpoint.txid, 1_000);
+        let err = wollet
+            .tx_builder()
+            .add_lbtc_recipient(&address, 1_000)
+            .unwrap()
+            .set_sequence(missing, sequence)
+            .finish()
+            .unwrap_err();
+        assert!(matches!(err, Error::SequenceForMissingInput(o) if o == missing));
+    }
+
+    #[test]
+    fn test_network_mismatch() {
+        let desc = WolletDescriptor::from_str(lwk_test_util::TEST_DESCRIPTOR).unwrap();
+        let err = Wollet::without_persist(ElementsNetwork::Liquid, desc.clone()).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DescriptorNetworkMismatch {
+                mainnet_keys: false,
+                network: ElementsNetwork::Liquid
+            }
+        ));
+        assert!(err.to_string().contains("testnet (tpub)"), "{err}");
+
+        // The mock chain has a made-up genesis block, verified only on public networks
+        let wollet = Wollet::without_persist(ElementsNetwork::LiquidTestnet, desc.clone()).unwrap();
+        let client = MockBackend::new(ElementsNetwork::LiquidTestnet);
+        let err = wollet.verify_backend(&client).unwrap_err();
+        assert!(matches!(err, Error::WrongGenesisHash { .. }));
+
+        let (wollet, client) = mock_wollet();
+        wollet.verify_backend(&client).unwrap();
+    }
+
+    #[test]
+    fn test_wallet_tx_confirmations() {
+        let (mut wollet, mut client) = mock_wollet();
+        let address = wollet.address(None).unwrap();
+        client.fund(address.address(), 1_000, None).unwrap();
+        let status = |client: &mut MockBackend, wollet: &mut Wollet| {
+            client.sync(wollet).unwrap();
+            let tx = &wollet.transactions().unwrap()[0];
+            (tx.confirmations, tx.status)
+        };
+
+        assert_eq!(status(&mut client, &mut wollet), (0, TxStatus::Unconfirmed));
+        client.mine(1);
+        assert_eq!(
+            status(&mut client, &mut wollet),
+            (1, TxStatus::Confirming(1))
+        );
+        client.mine(1);
+        assert_eq!(status(&mut client, &mut wollet), (2, TxStatus::Final));
+
+        wollet.set_finality_threshold(6);
+        client.mine(1);
+        assert_eq!(
+            status(&mut client, &mut wollet),
+            (3, TxStatus::Confirming(3))
+        );
+    }
 }
@@ -105,6 +105,58 @@ fn liquid_issue(signers: &[&AnySigner]) {
     TestWollet::check_persistence(wallet);
 }
 
+#[test]
+fn spv_proof() {
+    let server = setup();
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let client = test_client_electrum(&server.electrs.electrum_url);
+    let mut wallet = TestWollet::new(client, &desc);
+
+    wallet.fund_btc(&server);
+    let txid = wallet.wollet.transactions().unwrap()[0].txid;
+    assert!(matches!(
+        wallet.wollet.export_spv_proof(&txid, &wallet.client),
+        Err(Error::UnconfirmedTransaction(_))
+    ));
+
+    server.elementsd_generate(3);
+    let height = server.elementsd_height() as u32;
+    wallet.wait_height(height);
+
+    let proof = wallet
+        .wollet
+        .export_spv_proof(&txid, &wallet.client)
+        .unwrap();
+    assert_eq!(proof.tx.txid(), txid);
+    assert_eq!(proof.confirmations(), 3);
+    proof.verify().unwrap();
+}
+
+#[test]
+fn proof_of_reserves() {
+    let server = setup();
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let client = test_client_electrum(&server.electrs.electrum_url);
+    let mut wallet = TestWollet::new(client, &desc);
+
+    wallet.fund_btc(&server);
+    let asset = wallet.fund_asset(&server);
+
+    let message = "proof of reserves";
+    let mut pset = wallet.wollet.create_proof_of_reserves(message).unwrap();
+    wallet.sign(&signer, &mut pset);
+    wallet.wollet.finalize(&mut pset).unwrap();
+
+    let balances = verify_proof_of_reserves(&pset, message).unwrap();
+    assert_eq!(balances.get(&wallet.policy_asset()), Some(&1_000_000));
+    assert_eq!(balances.get(&asset), Some(&10_000));
+    assert!(verify_proof_of_reserves(&pset, "another message").is_err());
+}
+
 #[test]
 fn view() {
     let server = setup();
@@ -186,6 +238,19 @@ fn roundtrip() {
     let signer7 = generate_signer();
     let desc7 = format!("ct(elip151,elwpkh({}/*))", signer7.xpub());
 
+    let signer81 = generate_signer();
+    let signer82 = generate_signer();
+    let xpub81: bitcoin::bip32::Xpub = signer81.xpub();
+    let xpub82: bitcoin::bip32::Xpub = signer82.xpub();
+    let desc8 = format!("ct(elip151,elwsh(sortedmulti(2,{xpub81}/*,{xpub82}/*)))");
+
+    let signer91 = generate_signer();
+    let signer92 = generate_signer();
+    let xpub91: bitcoin::bip32::Xpub = signer91.xpub();
+    let xpub92: bitcoin::bip32::Xpub = signer92.xpub();
+    let desc9 =
+        format!("ct(elip151,elwsh(or_d(pk({xpub91}/*),and_v(v:pk({xpub92}/*),older(144)))))");
+
     let signers1 = [&AnySigner::Software(signer1)];
     let signers2 = [&AnySigner::Software(signer2)];
     let signers3 = [&AnySigner::Software(signer3)];
@@ -196,6 +261,11 @@ fn roundtrip() {
     ];
     let signers6 = [&AnySigner::Software(signer6)];
     let signers7 = [&AnySigner::Software(signer7)];
+    let signers8 = [
+        &AnySigner::Software(signer81),
+        &AnySigner::Software(signer82),
+    ];
+    let signers9 = [&AnySigner::Software(signer91)];
 
     std::thread::scope(|s| {
         for (signers, desc) in [
@@ -206,6 +276,8 @@ fn roundtrip() {
             (&signers5[..], desc5),
             (&signers6[..], desc6),
             (&signers7[..], desc7),
+            (&signers8[..], desc8),
+            (&signers9[..], desc9),
         ] {
             let server = &server;
             let client = test_client_electrum(&server.electrs.electrum_url);
@@ -637,9 +709,39 @@ fn create_pset_error() {
     let err = wallet
         .tx_builder()
         .set_unvalidated_recipients(&addressees)
+        .unwrap()
+        .finish()
         .unwrap_err();
     assert_eq!(err.to_string(), Error::NotConfidentialAddress.to_string());
 
+    // Policy is checked at finish, so it can be set after adding recipients
+    wallet
+        .tx_builder()
+        .set_unvalidated_recipients(&addressees)
+        .unwrap()
+        .allow_unconfidential_addresses()
+        .finish()
+        .unwrap();
+
+    // Not confidential address explicitly allowed
+    let mut address = server.elementsd_getnewaddress();
+    address.blinding_pubkey = None;
+    let addressees = vec![UnvalidatedRecipient {
+        satoshi: 1_000,
+        address: address.to_string(),
+        asset: "".to_string(),
+    }];
+    let pset = wallet
+        .tx_builder()
+        .allow_unconfidential_addresses()
+        .set_unvalidated_recipients(&addressees)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let details = wallet.wollet.get_details(&pset).unwrap();
+    assert_eq!(details.balance.recipients.len(), 1);
+    assert!(!details.balance.recipients[0].confidential);
+
     let address = wallet.address().to_string();
     // Invalid amount
     let addressees = vec![UnvalidatedRecipient {
@@ -975,6 +1077,50 @@ async fn test_esplora_wasm_client() {
     assert!(wollet.tip().timestamp().is_some());
 }
 
+#[cfg(feature = "esplora")]
+#[tokio::test]
+async fn test_tx_stream() {
+    use futures::StreamExt;
+    let server = setup_with_esplora();
+    let url = format!("http://{}", server.electrs.esplora_url.as_ref().unwrap());
+    let network = ElementsNetwork::default_regtest();
+    let mut client = clients::asyncr::EsploraClient::new(network, &url);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let descriptor = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let descriptor: WolletDescriptor = descriptor.parse().unwrap();
+    let mut wollet = Wollet::new(network, NoPersist::new(), descriptor).unwrap();
+    let address = wollet.address(None).unwrap();
+    let txid = server.elementsd_sendtoaddress(address.address(), 10000, None);
+
+    let stream = wollet.tx_stream(&mut client, std::time::Duration::from_millis(200));
+    let mut stream = std::pin::pin!(stream);
+    let tx = stream.next().await.unwrap().unwrap();
+    assert_eq!(tx.txid, txid);
+    assert!(tx.height.is_none());
+
+    server.elementsd_generate(1);
+    let tx = stream.next().await.unwrap().unwrap();
+    assert_eq!(tx.txid, txid);
+    assert!(tx.height.is_some());
+}
+
+#[cfg(feature = "electrum_stream")]
+#[tokio::test]
+async fn test_block_stream() {
+    use futures::StreamExt;
+    let server = setup();
+    let client = test_client_electrum(&server.electrs.electrum_url);
+    let stream = client.block_stream(std::time::Duration::from_millis(200));
+    let mut stream = std::pin::pin!(stream);
+    let tip = stream.next().await.unwrap().unwrap();
+
+    server.elementsd_generate(1);
+    let new_tip = stream.next().await.unwrap().unwrap();
+    assert_eq!(new_tip.height, tip.height + 1);
+    assert_eq!(new_tip.prev_blockhash, tip.block_hash());
+}
+
 #[cfg(feature = "esplora")]
 async fn wait_update_with_txs(
     client: &mut clients::asyncr::EsploraClient,
@@ -1638,6 +1784,26 @@ fn test_elements_rpc() {
     server.elementsd_generate(1);
     let utxos = elements_rpc_client.confirmed_utxos(&wd, 20).unwrap();
     assert_eq!(utxos.len(), 1);
+
+    // Check mempool acceptance without broadcasting
+    let address = wallet.address();
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&address, 1_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let unsigned_tx = pset.extract_tx().unwrap();
+    let err = elements_rpc_client
+        .test_mempool_accept(&unsigned_tx)
+        .unwrap_err();
+    assert!(
+        matches!(err, lwk_wollet::Error::MempoolRejected(_)),
+        "{err}"
+    );
+    wallet.sign(&signer, &mut pset);
+    let tx = wallet.wollet.finalize(&mut pset).unwrap();
+    elements_rpc_client.test_mempool_accept(&tx).unwrap();
 }
 
 #[cfg(feature = "esplora")]
@@ -2167,3 +2333,64 @@ fn test_liquidex() {
 
     // TODO: check fees
 }
+
+#[test]
+fn test_inheritance() {
+    let server = setup();
+
+    let owner = generate_signer();
+    let heir = generate_signer();
+    let path = DerivationPath::from_str("m/84h/1h/0h").unwrap();
+    let key = |s: &SwSigner| {
+        let xpub = Signer::derive_xpub(s, &path).unwrap();
+        (Some((s.fingerprint(), path.clone())), xpub)
+    };
+    let blocks = 3;
+    let desc = lwk_common::inheritance_desc(
+        key(&owner),
+        key(&heir),
+        blocks,
+        lwk_common::DescriptorBlindingKey::Elip151,
+    )
+    .unwrap();
+    let client = test_client_electrum(&server.electrs.electrum_url);
+    let mut w = TestWollet::new(client, &desc);
+    let node_address = server.elementsd_getnewaddress();
+
+    w.fund_btc(&server);
+    server.elementsd_generate(1);
+    w.sync();
+    assert_eq!(w.wollet.spending_paths().unwrap().len(), 2);
+
+    // The owner can spend immediately
+    w.send_btc(&[&AnySigner::Software(owner)], None, None);
+    server.elementsd_generate(1);
+    w.sync();
+
+    // The heir must wait for the timelock to expire
+    let err = w.tx_builder().recover().finish().unwrap_err();
+    assert!(matches!(err, Error::NoRecoverableUtxos));
+    let utxo = w.wollet.utxos().unwrap()[0].clone();
+    let paths = w.wollet.satisfiable_spending_paths(&utxo).unwrap();
+    assert_eq!(paths.len(), 1);
+    assert!(!paths[0].is_timelocked());
+
+    let height = w.tip().height();
+    server.elementsd_generate(blocks as u32);
+    w.wait_height(height + blocks as u32);
+    let paths = w.wollet.satisfiable_spending_paths(&utxo).unwrap();
+    assert_eq!(paths.len(), 2);
+
+    let mut pset = w
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .recover()
+        .finish()
+        .unwrap();
+    for input in pset.inputs() {
+        assert_eq!(input.sequence, Some(elements::Sequence(blocks as u32)));
+    }
+    w.sign(&heir, &mut pset);
+    w.send(&mut pset);
+}
@@ -0,0 +1,100 @@
+//! Property tests of the parsers of untrusted input: descriptors, PSETs and transactions to
+//! unblind, which come from RPC calls and files.
+//!
+//! Inputs are either arbitrary or valid data with random mutations, the parsers must return an
+//! error instead of panicking. The fuzz targets in `fuzz/` explore the same functions further.
+
+use std::str::FromStr;
+
+use elements::encode::{deserialize, serialize};
+use elements::hex::FromHex;
+use elements::pset::PartiallySignedTransaction;
+use elements::secp256k1_zkp::SecretKey;
+use elements::Transaction;
+use lwk_wollet::*;
+use proptest::prelude::*;
+
+const PSET_DESCRIPTOR: &str = include_str!("../../lwk_common/test_data/pset_details/descriptor");
+const PSET: &str = include_str!("../../lwk_common/test_data/pset_details/pset.base64");
+const MAINNET_TX: &str = include_str!("data/usdt-issuance-tx.hex");
+
+/// Replace the bytes at the given positions, modulo the length
+fn mutate(mut data: Vec<u8>, mutations: &[(usize, u8)]) -> Vec<u8> {
+    if !data.is_empty() {
+        let len = data.len();
+        for (idx, byte) in mutations {
+            data[idx % len] = *byte;
+        }
+    }
+    data
+}
+
+fn pset_wollet() -> Wollet {
+    let desc = WolletDescriptor::from_str(PSET_DESCRIPTOR.trim()).unwrap();
+    Wollet::without_persist(ElementsNetwork::LiquidTestnet, desc).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn descriptor_arbitrary(s in "\\PC*") {
+        let _ = WolletDescriptor::from_str(&s);
+    }
+
+    #[test]
+    fn descriptor_mutated(
+        mutations in prop::collection::vec((any::<usize>(), 0x20u8..0x7f), 1..4),
+        strip_checksum in any::<bool>(),
+    ) {
+        let mut desc = PSET_DESCRIPTOR.trim();
+        if strip_checksum {
+            desc = desc.split('#').next().unwrap();
+        }
+        let mutated = mutate(desc.as_bytes().to_vec(), &mutations);
+        let mutated = String::from_utf8(mutated).unwrap();
+        if let Ok(desc) = WolletDescriptor::from_str(&mutated) {
+            // what is accepted is printed in a canonical form
+            let s = desc.to_string();
+            let roundtrip = WolletDescriptor::from_str(&s).unwrap();
+            prop_assert_eq!(roundtrip.to_string(), s);
+            let _ = desc.address(0, &elements::AddressParams::LIQUID_TESTNET);
+        }
+    }
+
+    #[test]
+    fn pset_arbitrary(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        let _ = deserialize::<PartiallySignedTransaction>(&bytes);
+        let _ = PartiallySignedTransaction::from_str(&String::from_utf8_lossy(&bytes));
+    }
+}
+
+proptest! {
+    // unblinding verifies rangeproofs, which is slow in debug builds
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn pset_mutated(mutations in prop::collection::vec((any::<usize>(), any::<u8>()), 1..4)) {
+        let pset = PartiallySignedTransaction::from_str(PSET.trim()).unwrap();
+        let mutated = mutate(serialize(&pset), &mutations);
+        if let Ok(pset) = deserialize::<PartiallySignedTransaction>(&mutated) {
+            let _ = pset_wollet().get_details(&pset);
+        }
+    }
+
+    #[test]
+    fn unblind_mutated(
+        mutations in prop::collection::vec((any::<usize>(), any::<u8>()), 0..4),
+        key in any::<[u8; 32]>(),
+    ) {
+        let bytes = Vec::<u8>::from_hex(MAINNET_TX.trim()).unwrap();
+        let mutated = mutate(bytes, &mutations);
+        if let Ok(tx) = deserialize::<Transaction>(&mutated) {
+            if let Ok(key) = SecretKey::from_slice(&key) {
+                for txout in tx.output.iter() {
+                    prop_assert!(unblind_output(txout, &key).is_err());
+                }
+            }
+            let desc = WolletDescriptor::from_str(PSET_DESCRIPTOR.trim()).unwrap();
+            let _ = unblind_transaction(&tx, &desc.as_ref().key);
+        }
+    }
+}
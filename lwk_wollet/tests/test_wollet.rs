@@ -650,6 +650,8 @@ impl<C: BlockchainBackend> TestWollet<C> {
             txout,
             unblinded: utxo.unblinded,
             max_weight_to_satisfy: self.wollet.max_weight_to_satisfy(),
+            redeem_script: None,
+            witness_script: None,
         }
     }
 